@@ -193,6 +193,7 @@ pub fn get_config<E: EthSpec>(
             freezer_db_path: None,
             update_period_secs,
             monitoring_endpoint: monitoring_endpoint.to_string(),
+            ..<_>::default()
         });
     }
 
@@ -79,6 +79,14 @@ pub fn write_file_via_temporary(
         create_with_600_perms(temp_path, bytes)?;
     }
 
+    // Flush the temporary file to disk before the rename, so that a crash cannot leave an empty or
+    // partially written file in place of the original.
+    fs::OpenOptions::new()
+        .write(true)
+        .open(temp_path)
+        .and_then(|file| file.sync_all())
+        .map_err(FsError::UnableToWriteFile)?;
+
     // With the temporary file created, perform an atomic rename.
     fs::rename(temp_path, file_path).map_err(FsError::UnableToRenameFile)?;
 
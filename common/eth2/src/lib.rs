@@ -12,9 +12,11 @@ pub mod lighthouse;
 #[cfg(feature = "lighthouse")]
 pub mod lighthouse_vc;
 pub mod mixin;
+pub mod optional_fields;
 pub mod types;
 
 use self::mixin::{RequestAccept, ResponseOptional};
+use self::optional_fields::{fill_optional_fields, OptionalFields};
 use self::types::{Error as ResponseError, *};
 use futures::Stream;
 use futures_util::StreamExt;
@@ -29,7 +31,8 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::iter::Iterator;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use store::fork_versioned_response::ExecutionOptimisticFinalizedForkVersionedResponse;
 
 pub const V1: EndpointVersion = EndpointVersion(1);
@@ -135,6 +138,9 @@ impl Timeouts {
     }
 }
 
+/// Called with the JSON pointer of each optional response field for which a default is substituted.
+pub type OnDefaultSubstituted = Arc<dyn Fn(&'static str) + Send + Sync>;
+
 /// A wrapper around `reqwest::Client` which provides convenience methods for interfacing with a
 /// Lighthouse Beacon Node HTTP server (`http_api`).
 #[derive(Clone)]
@@ -142,6 +148,40 @@ pub struct BeaconNodeHttpClient {
     client: reqwest::Client,
     server: SensitiveUrl,
     timeouts: Timeouts,
+    /// If set, responses which omit optional fields are decoded with defaults.
+    on_default_substituted: Option<OnDefaultSubstituted>,
+    /// If set, replaces `client` once it reaches a maximum age.
+    expiring_client: Option<Arc<ExpiringClient>>,
+}
+
+/// Builds a new `reqwest::Client` to replace an expired one.
+pub type ClientFactory = Arc<dyn Fn() -> Result<reqwest::Client, reqwest::Error> + Send + Sync>;
+
+/// A `reqwest::Client` which is rebuilt once it is older than `max_age`.
+///
+/// The connections pooled by the old client are closed once their in-flight requests complete,
+/// so that connections are never reused for longer than `max_age` and new connections resolve the
+/// server's address again.
+struct ExpiringClient {
+    factory: ClientFactory,
+    max_age: Duration,
+    current: Mutex<(reqwest::Client, Instant)>,
+}
+
+impl ExpiringClient {
+    fn client(&self) -> reqwest::Client {
+        let mut current = self
+            .current
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if current.1.elapsed() >= self.max_age {
+            // If a new client can't be built, keep using the current one until the next request.
+            if let Ok(client) = (self.factory)() {
+                *current = (client, Instant::now());
+            }
+        }
+        current.0.clone()
+    }
 }
 
 impl fmt::Display for BeaconNodeHttpClient {
@@ -162,6 +202,8 @@ impl BeaconNodeHttpClient {
             client: reqwest::Client::new(),
             server,
             timeouts,
+            on_default_substituted: None,
+            expiring_client: None,
         }
     }
 
@@ -174,6 +216,44 @@ impl BeaconNodeHttpClient {
             client,
             server,
             timeouts,
+            on_default_substituted: None,
+            expiring_client: None,
+        }
+    }
+
+    /// Returns the URL of the server, including any secrets it contains.
+    pub fn server(&self) -> &SensitiveUrl {
+        &self.server
+    }
+
+    /// Decode responses which omit optional fields with defaults, rather than failing.
+    ///
+    /// `on_default_substituted` is called for each field for which a default is substituted. Only
+    /// the fields listed in `optional_fields` are ever substituted.
+    pub fn with_lenient_responses(
+        mut self,
+        on_default_substituted: impl Fn(&'static str) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_default_substituted = Some(Arc::new(on_default_substituted));
+        self
+    }
+
+    /// Replace the underlying client with one built by `factory` whenever it is older than
+    /// `max_age`, bounding how long connections to the server are reused.
+    pub fn with_max_client_age(mut self, max_age: Duration, factory: ClientFactory) -> Self {
+        self.expiring_client = Some(Arc::new(ExpiringClient {
+            factory,
+            max_age,
+            current: Mutex::new((self.client.clone(), Instant::now())),
+        }));
+        self
+    }
+
+    /// Returns the client to send the next request with.
+    fn client(&self) -> reqwest::Client {
+        match &self.expiring_client {
+            Some(expiring_client) => expiring_client.client(),
+            None => self.client.clone(),
         }
     }
 
@@ -195,13 +275,33 @@ impl BeaconNodeHttpClient {
         Ok(response.json().await?)
     }
 
+    /// Perform a HTTP GET request, substituting defaults for omitted optional fields of the
+    /// response data if lenient responses are enabled.
+    async fn get_lenient<T, U>(&self, url: U) -> Result<GenericResponse<T>, Error>
+    where
+        T: OptionalFields + Serialize + DeserializeOwned,
+        U: IntoUrl,
+    {
+        let on_default_substituted = match &self.on_default_substituted {
+            Some(on_default_substituted) => on_default_substituted,
+            None => return self.get(url).await,
+        };
+        let mut response: serde_json::Value = self.get(url).await?;
+        if let Some(data) = response.get_mut("data") {
+            for field in fill_optional_fields(data, T::OPTIONAL_FIELDS) {
+                on_default_substituted(field);
+            }
+        }
+        serde_json::from_value(response).map_err(Error::InvalidJson)
+    }
+
     /// Perform an HTTP GET request, returning the `Response` for processing.
     pub async fn get_response<U: IntoUrl>(
         &self,
         url: U,
         builder: impl FnOnce(RequestBuilder) -> RequestBuilder,
     ) -> Result<Response, Error> {
-        let response = builder(self.client.get(url)).send().await?;
+        let response = builder(self.client().get(url)).send().await?;
         ok_or_error(response).await
     }
 
@@ -314,7 +414,7 @@ impl BeaconNodeHttpClient {
         body: &T,
         timeout: Option<Duration>,
     ) -> Result<Response, Error> {
-        let mut builder = self.client.post(url);
+        let mut builder = self.client().post(url);
         if let Some(timeout) = timeout {
             builder = builder.timeout(timeout);
         }
@@ -330,7 +430,7 @@ impl BeaconNodeHttpClient {
         timeout: Option<Duration>,
         fork: ForkName,
     ) -> Result<Response, Error> {
-        let mut builder = self.client.post(url);
+        let mut builder = self.client().post(url);
         if let Some(timeout) = timeout {
             builder = builder.timeout(timeout);
         }
@@ -1256,7 +1356,7 @@ impl BeaconNodeHttpClient {
             .push("node")
             .push("identity");
 
-        self.get(path).await
+        self.get_lenient(path).await
     }
 
     /// `GET node/syncing`
@@ -1280,7 +1380,7 @@ impl BeaconNodeHttpClient {
             .push("node")
             .push("health");
 
-        let status = self.client.get(path).send().await?.status();
+        let status = self.client().get(path).send().await?.status();
         if status == StatusCode::OK || status == StatusCode::PARTIAL_CONTENT {
             Ok(status)
         } else {
@@ -1466,6 +1566,25 @@ impl BeaconNodeHttpClient {
         randao_reveal: &SignatureBytes,
         graffiti: Option<&Graffiti>,
         skip_randao_verification: SkipRandaoVerification,
+    ) -> Result<ForkVersionedResponse<BeaconBlock<T, Payload>>, Error> {
+        self.get_validator_blocks_with_limits(
+            slot,
+            randao_reveal,
+            graffiti,
+            skip_randao_verification,
+            &BlockProductionLimits::default(),
+        )
+        .await
+    }
+
+    /// `GET v2/validator/blocks/{slot}`, limiting the contents of the block to `limits`.
+    pub async fn get_validator_blocks_with_limits<T: EthSpec, Payload: AbstractExecPayload<T>>(
+        &self,
+        slot: Slot,
+        randao_reveal: &SignatureBytes,
+        graffiti: Option<&Graffiti>,
+        skip_randao_verification: SkipRandaoVerification,
+        limits: &BlockProductionLimits,
     ) -> Result<ForkVersionedResponse<BeaconBlock<T, Payload>>, Error> {
         let mut path = self.eth_path(V2)?;
 
@@ -1488,6 +1607,11 @@ impl BeaconNodeHttpClient {
                 .append_pair("skip_randao_verification", "");
         }
 
+        if let Some(max_transactions) = limits.max_transactions {
+            path.query_pairs_mut()
+                .append_pair("max_transactions", &max_transactions.to_string());
+        }
+
         self.get(path).await
     }
 
@@ -11,7 +11,7 @@ use crate::{
     ok_or_error,
     types::{
         BeaconState, ChainSpec, DepositTreeSnapshot, Epoch, EthSpec, FinalizedExecutionBlock,
-        GenericResponse, ValidatorId,
+        GenericResponse, PublicKeyBytes, ValidatorId,
     },
     BeaconNodeHttpClient, DepositData, Error, Eth1Data, Hash256, Slot, StateId, StatusCode,
 };
@@ -353,6 +353,14 @@ impl From<Eth1Block> for FinalizedExecutionBlock {
     }
 }
 
+/// An opaque tag which a beacon node, or a proxy in front of it, may use to route the
+/// validator's registration to a set of relays.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorRelayTag {
+    pub pubkey: PublicKeyBytes,
+    pub relay_tag: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DatabaseInfo {
     pub schema_version: u64,
@@ -364,7 +372,7 @@ pub struct DatabaseInfo {
 impl BeaconNodeHttpClient {
     /// Perform a HTTP GET request, returning `None` on a 404 error.
     async fn get_bytes_opt<U: IntoUrl>(&self, url: U) -> Result<Option<Vec<u8>>, Error> {
-        let response = self.client.get(url).send().await.map_err(Error::from)?;
+        let response = self.client().get(url).send().await.map_err(Error::from)?;
         match ok_or_error(response).await {
             Ok(resp) => Ok(Some(
                 resp.bytes()
@@ -567,6 +575,24 @@ impl BeaconNodeHttpClient {
         self.post_with_response(path, &()).await
     }
 
+    /// `POST lighthouse/validator/relay_tags`
+    ///
+    /// Beacon nodes which do not support relay tags are expected to return a 404.
+    pub async fn post_lighthouse_validator_relay_tags(
+        &self,
+        relay_tags: &[ValidatorRelayTag],
+    ) -> Result<(), Error> {
+        let mut path = self.server.full.clone();
+
+        path.path_segments_mut()
+            .map_err(|()| Error::InvalidUrl(self.server.clone()))?
+            .push("lighthouse")
+            .push("validator")
+            .push("relay_tags");
+
+        self.post(path, &relay_tags).await
+    }
+
     ///
     /// Analysis endpoints.
     ///
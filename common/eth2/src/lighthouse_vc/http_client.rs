@@ -398,6 +398,74 @@ impl ValidatorClientHttpClient {
         self.get_opt(path).await
     }
 
+    /// `GET lighthouse/validators/{validator_pubkey}/settings`
+    pub async fn get_lighthouse_validators_pubkey_settings(
+        &self,
+        validator_pubkey: &PublicKeyBytes,
+    ) -> Result<Option<GenericResponse<ValidatorSettings>>, Error> {
+        let mut path = self.server.full.clone();
+
+        path.path_segments_mut()
+            .map_err(|()| Error::InvalidUrl(self.server.clone()))?
+            .push("lighthouse")
+            .push("validators")
+            .push(&validator_pubkey.to_string())
+            .push("settings");
+
+        self.get_opt(path).await
+    }
+
+    /// `GET lighthouse/validators/{validator_pubkey}/slashing-protection/summary`
+    pub async fn get_lighthouse_validators_pubkey_slashing_protection_summary(
+        &self,
+        validator_pubkey: &PublicKeyBytes,
+    ) -> Result<Option<GenericResponse<SlashingProtectionSummary>>, Error> {
+        let mut path = self.server.full.clone();
+
+        path.path_segments_mut()
+            .map_err(|()| Error::InvalidUrl(self.server.clone()))?
+            .push("lighthouse")
+            .push("validators")
+            .push(&validator_pubkey.to_string())
+            .push("slashing-protection")
+            .push("summary");
+
+        self.get_opt(path).await
+    }
+
+    /// `GET lighthouse/validators/definitions`
+    pub async fn get_lighthouse_validators_definitions(
+        &self,
+    ) -> Result<GenericResponse<Vec<ValidatorDefinition>>, Error> {
+        let mut path = self.server.full.clone();
+
+        path.path_segments_mut()
+            .map_err(|()| Error::InvalidUrl(self.server.clone()))?
+            .push("lighthouse")
+            .push("validators")
+            .push("definitions");
+
+        self.get(path).await
+    }
+
+    /// `GET lighthouse/duties?epoch`
+    pub async fn get_lighthouse_duties(
+        &self,
+        epoch: Epoch,
+    ) -> Result<GenericResponse<EpochDuties>, Error> {
+        let mut path = self.server.full.clone();
+
+        path.path_segments_mut()
+            .map_err(|()| Error::InvalidUrl(self.server.clone()))?
+            .push("lighthouse")
+            .push("duties");
+
+        path.query_pairs_mut()
+            .append_pair("epoch", &epoch.to_string());
+
+        self.get(path).await
+    }
+
     /// `POST lighthouse/validators`
     pub async fn post_lighthouse_validators(
         &self,
@@ -413,6 +481,22 @@ impl ValidatorClientHttpClient {
         self.post(path, &validators).await
     }
 
+    /// `POST lighthouse/validators/register`
+    pub async fn post_lighthouse_validators_register(
+        &self,
+        request: &RegisterValidatorsRequest,
+    ) -> Result<GenericResponse<Vec<RegisterValidatorResponse>>, Error> {
+        let mut path = self.server.full.clone();
+
+        path.path_segments_mut()
+            .map_err(|()| Error::InvalidUrl(self.server.clone()))?
+            .push("lighthouse")
+            .push("validators")
+            .push("register");
+
+        self.post(path, &request).await
+    }
+
     /// `POST lighthouse/validators/mnemonic`
     pub async fn post_lighthouse_validators_mnemonic(
         &self,
@@ -544,6 +628,24 @@ impl ValidatorClientHttpClient {
         self.get_unsigned(url).await
     }
 
+    /// `POST lighthouse/auth/rotate-token`
+    ///
+    /// The previous API token is invalid once this request has succeeded, so subsequent requests
+    /// must use a client constructed with the returned token.
+    pub async fn post_lighthouse_auth_rotate_token(
+        &self,
+    ) -> Result<GenericResponse<RotateTokenResponse>, Error> {
+        let mut path = self.server.full.clone();
+
+        path.path_segments_mut()
+            .map_err(|()| Error::InvalidUrl(self.server.clone()))?
+            .push("lighthouse")
+            .push("auth")
+            .push("rotate-token");
+
+        self.post(path, &()).await
+    }
+
     /// `GET eth/v1/keystores`
     pub async fn get_keystores(&self) -> Result<ListKeystoresResponse, Error> {
         let url = self.make_keystores_url()?;
@@ -568,6 +670,21 @@ impl ValidatorClientHttpClient {
         self.delete_with_unsigned_response(url, req).await
     }
 
+    /// `POST eth/v1/validator/slashing-protection`
+    pub async fn post_slashing_protection(
+        &self,
+        req: &ImportSlashingProtectionRequest,
+    ) -> Result<ImportSlashingProtectionResponse, Error> {
+        let mut url = self.server.full.clone();
+        url.path_segments_mut()
+            .map_err(|()| Error::InvalidUrl(self.server.clone()))?
+            .push("eth")
+            .push("v1")
+            .push("validator")
+            .push("slashing-protection");
+        self.post_with_unsigned_response(url, req).await
+    }
+
     /// `GET eth/v1/remotekeys`
     pub async fn get_remotekeys(&self) -> Result<ListRemotekeysResponse, Error> {
         let url = self.make_remotekeys_url()?;
@@ -116,6 +116,31 @@ pub enum DeleteKeystoreStatus {
     Error,
 }
 
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImportSlashingProtectionRequest {
+    pub slashing_protection: InterchangeJsonStr,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImportSlashingProtectionResponse {
+    pub data: Vec<SingleImportSlashingProtectionResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SingleImportSlashingProtectionResponse {
+    pub pubkey: PublicKeyBytes,
+    #[serde(flatten)]
+    pub status: Status<ImportSlashingProtectionStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSlashingProtectionStatus {
+    Imported,
+    Error,
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct ListRemotekeysResponse {
     pub data: Vec<SingleListRemotekeysResponse>,
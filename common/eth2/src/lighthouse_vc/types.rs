@@ -1,3 +1,4 @@
+pub use account_utils::validator_definitions::ValidatorDefinition;
 use account_utils::ZeroizeString;
 use eth2_keystore::Keystore;
 use graffiti::GraffitiString;
@@ -6,7 +7,7 @@ use std::path::PathBuf;
 
 pub use crate::lighthouse::Health;
 pub use crate::lighthouse_vc::std_types::*;
-pub use crate::types::{GenericResponse, VersionData};
+pub use crate::types::{AttesterData, GenericResponse, ProposerData, VersionData};
 pub use types::*;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -16,6 +17,54 @@ pub struct ValidatorData {
     pub voting_pubkey: PublicKeyBytes,
 }
 
+/// The origin of a resolved per-validator setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingSource {
+    /// Set for this validator in `validator_definitions.yml`, either by hand or via the
+    /// keymanager/lighthouse APIs.
+    Keymanager,
+    /// Set for this validator in a file, such as the `--graffiti-file` or the
+    /// `--builder-proposals-enabled-file`.
+    File,
+    /// Set for all validators by a process-level flag.
+    Global,
+    /// Not configured anywhere, the client default applies.
+    Default,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedSetting<T> {
+    pub value: T,
+    pub source: SettingSource,
+}
+
+impl<T> ResolvedSetting<T> {
+    pub fn new(value: T, source: SettingSource) -> Self {
+        Self { value, source }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorSettings {
+    pub voting_pubkey: PublicKeyBytes,
+    pub fee_recipient: ResolvedSetting<Option<Address>>,
+    pub gas_limit: ResolvedSetting<u64>,
+    pub graffiti: ResolvedSetting<Option<String>>,
+    pub builder_proposals: ResolvedSetting<bool>,
+}
+
+/// The latest messages signed by a validator, according to its slashing protection data.
+///
+/// Each field is `None` if no message of that kind has been signed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlashingProtectionSummary {
+    pub voting_pubkey: PublicKeyBytes,
+    pub max_block_slot: Option<Slot>,
+    pub max_attestation_source_epoch: Option<Epoch>,
+    pub max_attestation_target_epoch: Option<Epoch>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ValidatorRequest {
     pub enable: bool,
@@ -152,3 +201,51 @@ pub struct UpdateGasLimitRequest {
 pub struct VoluntaryExitQuery {
     pub epoch: Option<Epoch>,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DutiesQuery {
+    pub epoch: Epoch,
+}
+
+/// The duties of local validators in a single epoch, as cached by the validator client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpochDuties {
+    pub epoch: Epoch,
+    pub attesters: Vec<AttesterData>,
+    pub proposers: Vec<ProposerData>,
+    /// The local members of the sync committee for the period containing `epoch`.
+    pub sync_committee: Vec<SyncDuty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RotateTokenResponse {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegisterValidatorsRequest {
+    /// The validators to register, or all eligible validators if `None`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pubkeys: Option<Vec<PublicKeyBytes>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterValidatorStatus {
+    /// The registration was published to the builder network.
+    Registered,
+    /// The validator is not eligible for registration, so nothing was published.
+    NotRegistered,
+    /// The registration could not be signed or published.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegisterValidatorResponse {
+    pub pubkey: PublicKeyBytes,
+    pub status: RegisterValidatorStatus,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
@@ -0,0 +1,159 @@
+//! Lenient decoding of beacon node responses which omit optional fields.
+//!
+//! Some beacon node implementations omit response fields which are not essential to the validator
+//! client, e.g. the `syncnets` of a node's metadata. A client created with
+//! `BeaconNodeHttpClient::with_lenient_responses` substitutes a default for each such field which
+//! is missing or `null`, rather than failing to decode the whole response. Fields which are not
+//! listed here are never substituted, so genuinely missing data is still reported as an error.
+
+use crate::types::IdentityData;
+use serde_json::Value;
+
+/// A field which may be omitted from a response, and the value substituted in its place.
+#[derive(Debug, Clone, Copy)]
+pub struct OptionalField {
+    /// The JSON pointer of the field, relative to the response data.
+    pub pointer: &'static str,
+    /// The JSON encoding of the value substituted if the field is omitted.
+    pub default: &'static str,
+}
+
+/// A response type with fields that may be omitted by some beacon nodes.
+pub trait OptionalFields {
+    const OPTIONAL_FIELDS: &'static [OptionalField];
+}
+
+impl OptionalFields for IdentityData {
+    const OPTIONAL_FIELDS: &'static [OptionalField] = &[
+        OptionalField {
+            pointer: "/p2p_addresses",
+            default: "[]",
+        },
+        OptionalField {
+            pointer: "/discovery_addresses",
+            default: "[]",
+        },
+        OptionalField {
+            pointer: "/metadata/syncnets",
+            default: "\"0x00\"",
+        },
+    ];
+}
+
+/// Substitutes the defaults of any of `fields` which are missing or `null` in `data`, returning the
+/// pointers of the substituted fields.
+///
+/// A field is only substituted if the object containing it is present, so that an omitted required
+/// object is still reported when decoding.
+pub fn fill_optional_fields(data: &mut Value, fields: &[OptionalField]) -> Vec<&'static str> {
+    let mut substituted = vec![];
+    for field in fields {
+        let (parent, key) = match field.pointer.rsplit_once('/') {
+            Some(split) => split,
+            None => continue,
+        };
+        let object = match data.pointer_mut(parent).and_then(Value::as_object_mut) {
+            Some(object) => object,
+            None => continue,
+        };
+        if object.get(key).map_or(true, Value::is_null) {
+            if let Ok(default) = serde_json::from_str(field.default) {
+                object.insert(key.to_string(), default);
+                substituted.push(field.pointer);
+            }
+        }
+    }
+    substituted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ENR: &str = "enr:-IS4QHCYrYZbAKWCBRlAy5zzaDZXJBGkcnh4MHcBFZntXNFrdvJjX04jRzjzCBOonrkTfj\
+                       499SZuOh8R33Ls8RRcy5wBgmlkgnY0gmlwhH8AAAGJc2VjcDI1NmsxoQPKY0yuDUmstAHYpMa2\
+                       _oxVtw0RW_QAdpzBQA8yWM0xOIN1ZHCCdl8";
+
+    fn decode_identity(mut data: Value) -> (Result<IdentityData, String>, Vec<&'static str>) {
+        let substituted = fill_optional_fields(&mut data, IdentityData::OPTIONAL_FIELDS);
+        (
+            serde_json::from_value(data).map_err(|e| e.to_string()),
+            substituted,
+        )
+    }
+
+    #[test]
+    fn optional_field_defaults_are_valid() {
+        for field in IdentityData::OPTIONAL_FIELDS {
+            assert!(serde_json::from_str::<Value>(field.default).is_ok());
+        }
+    }
+
+    #[test]
+    fn minimal_identity() {
+        let (identity, substituted) = decode_identity(json!({
+            "peer_id": "16Uiu2HAmLbdHpSWT3ZZUKkEakmM5Q4XvanpyfC2RBhPyD2xADtm8",
+            "enr": ENR,
+            "p2p_addresses": null,
+            "metadata": {
+                "seq_number": "4",
+                "attnets": "0x0000000000000000",
+            },
+        }));
+        let identity = identity.unwrap();
+        assert_eq!(
+            substituted,
+            vec![
+                "/p2p_addresses",
+                "/discovery_addresses",
+                "/metadata/syncnets"
+            ]
+        );
+        assert!(identity.p2p_addresses.is_empty());
+        assert!(identity.discovery_addresses.is_empty());
+        assert_eq!(identity.metadata.seq_number, 4);
+        assert_eq!(identity.metadata.syncnets, "0x00");
+    }
+
+    #[test]
+    fn complete_identity_is_unchanged() {
+        let data = json!({
+            "peer_id": "16Uiu2HAmLbdHpSWT3ZZUKkEakmM5Q4XvanpyfC2RBhPyD2xADtm8",
+            "enr": ENR,
+            "p2p_addresses": ["/ip4/127.0.0.1/tcp/9000"],
+            "discovery_addresses": ["/ip4/127.0.0.1/udp/9000"],
+            "metadata": {
+                "seq_number": "4",
+                "attnets": "0x0000000000000000",
+                "syncnets": "0x0f",
+            },
+        });
+        let (identity, substituted) = decode_identity(data);
+        assert!(substituted.is_empty());
+        let identity = identity.unwrap();
+        assert_eq!(identity.p2p_addresses.len(), 1);
+        assert_eq!(identity.metadata.syncnets, "0x0f");
+    }
+
+    #[test]
+    fn required_fields_are_not_substituted() {
+        // A missing required field still fails to decode.
+        let (identity, _) = decode_identity(json!({
+            "enr": ENR,
+            "metadata": {
+                "seq_number": "4",
+                "attnets": "0x0000000000000000",
+            },
+        }));
+        assert!(identity.is_err());
+
+        // Optional fields within an omitted required object are not substituted.
+        let (identity, substituted) = decode_identity(json!({
+            "peer_id": "16Uiu2HAmLbdHpSWT3ZZUKkEakmM5Q4XvanpyfC2RBhPyD2xADtm8",
+            "enr": ENR,
+        }));
+        assert!(identity.is_err());
+        assert_eq!(substituted, vec!["/p2p_addresses", "/discovery_addresses"]);
+    }
+}
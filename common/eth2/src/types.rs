@@ -689,6 +689,15 @@ pub struct ValidatorBlocksQuery {
     pub skip_randao_verification: SkipRandaoVerification,
 }
 
+/// Limits on the contents of a locally built block, passed to beacon nodes which support them.
+///
+/// Beacon nodes which do not support a limit ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockProductionLimits {
+    /// The maximum number of transactions in the execution payload.
+    pub max_transactions: Option<u64>,
+}
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
 #[serde(try_from = "Option<String>")]
 pub enum SkipRandaoVerification {
@@ -0,0 +1,161 @@
+//! Filtering of log records by a level which depends on the category of the logger.
+//!
+//! A logger is assigned a category by adding a `LOG_CATEGORY_KEY` value to it, e.g.
+//! `log.new(o!(LOG_CATEGORY_KEY => "signing"))`.
+
+use slog::{Drain, Key, Level, OwnedKVList, Record, Serializer, KV};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The key holding the category of a logger.
+pub const LOG_CATEGORY_KEY: &str = "category";
+
+/// Parses a log level, using the names accepted by `--debug-level`.
+pub fn parse_log_level(level: &str) -> Result<Level, String> {
+    match level {
+        "info" => Ok(Level::Info),
+        "debug" => Ok(Level::Debug),
+        "trace" => Ok(Level::Trace),
+        "warn" => Ok(Level::Warning),
+        "error" => Ok(Level::Error),
+        "crit" => Ok(Level::Critical),
+        unknown => Err(format!("Unknown log level: {}", unknown)),
+    }
+}
+
+/// Parses a comma-separated list of `CATEGORY=LEVEL` pairs, e.g. `signing=debug,network=warn`.
+pub fn parse_log_levels(log_levels: &str) -> Result<HashMap<String, Level>, String> {
+    log_levels
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (category, level) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected CATEGORY=LEVEL, got {}", entry))?;
+            let category = category.trim();
+            if category.is_empty() {
+                return Err(format!("Missing category in {}", entry));
+            }
+            Ok((category.to_string(), parse_log_level(level.trim())?))
+        })
+        .collect()
+}
+
+/// A drain which passes on records at or above `default_level`, or at or above the level of the
+/// category of the record's logger if one is configured.
+pub struct CategoryFilter<D: Drain> {
+    drain: D,
+    default_level: Level,
+    levels: HashMap<String, Level>,
+}
+
+impl<D: Drain> CategoryFilter<D> {
+    pub fn new(drain: D, default_level: Level, levels: HashMap<String, Level>) -> Self {
+        Self {
+            drain,
+            default_level,
+            levels,
+        }
+    }
+
+    /// Returns the minimum level of records with the given logger values.
+    fn level(&self, record: &Record, values: &OwnedKVList) -> Level {
+        if self.levels.is_empty() {
+            return self.default_level;
+        }
+        let mut visitor = CategoryVisitor(None);
+        // Record values (`"k" => v` in a log statement) take precedence over logger values.
+        let _ = record.kv().serialize(record, &mut visitor);
+        if visitor.0.is_none() {
+            let _ = values.serialize(record, &mut visitor);
+        }
+        visitor
+            .0
+            .and_then(|category| self.levels.get(&category).copied())
+            .unwrap_or(self.default_level)
+    }
+}
+
+impl<D: Drain> Drain for CategoryFilter<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        if record.level().is_at_least(self.level(record, values)) {
+            self.drain.log(record, values).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Finds the first `LOG_CATEGORY_KEY` value.
+struct CategoryVisitor(Option<String>);
+
+impl Serializer for CategoryVisitor {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+        if self.0.is_none() && key == LOG_CATEGORY_KEY {
+            self.0 = Some(val.to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::{debug, info, o, Logger};
+    use std::sync::{Arc, Mutex};
+
+    /// A drain which records the messages passed to it.
+    #[derive(Clone, Default)]
+    struct Messages(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Messages {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, record: &Record, _: &OwnedKVList) -> Result<(), slog::Never> {
+            self.0.lock().unwrap().push(record.msg().to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parse_levels() {
+        let levels = parse_log_levels("signing=debug, network = warn,").unwrap();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels["signing"], Level::Debug);
+        assert_eq!(levels["network"], Level::Warning);
+
+        assert!(parse_log_levels("").unwrap().is_empty());
+        assert!(parse_log_levels("signing").is_err());
+        assert!(parse_log_levels("signing=loud").is_err());
+        assert!(parse_log_levels("=info").is_err());
+    }
+
+    #[test]
+    fn filter_by_category() {
+        let messages = Messages::default();
+        let levels = parse_log_levels("signing=debug,quiet=warn").unwrap();
+        let log = Logger::root(
+            CategoryFilter::new(messages.clone(), Level::Info, levels).fuse(),
+            o!(),
+        );
+
+        debug!(log, "general debug");
+        info!(log, "general info");
+        let signing = log.new(o!(LOG_CATEGORY_KEY => "signing"));
+        debug!(signing, "signing debug");
+        let quiet = log.new(o!(LOG_CATEGORY_KEY => "quiet"));
+        info!(quiet, "quiet info");
+        let unknown = log.new(o!(LOG_CATEGORY_KEY => "unknown"));
+        info!(unknown, "unknown info");
+
+        assert_eq!(
+            *messages.0.lock().unwrap(),
+            vec!["general info", "signing debug", "unknown info"]
+        );
+    }
+}
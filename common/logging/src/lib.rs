@@ -12,7 +12,9 @@ use std::time::{Duration, Instant};
 pub const MAX_MESSAGE_WIDTH: usize = 40;
 
 pub mod async_record;
+mod category_filter;
 mod sse_logging_components;
+pub use category_filter::{parse_log_level, parse_log_levels, CategoryFilter, LOG_CATEGORY_KEY};
 pub use sse_logging_components::SSELoggingComponents;
 
 /// The minimum interval between log messages indicating that a queue is full.
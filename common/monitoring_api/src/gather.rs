@@ -1,4 +1,4 @@
-use super::types::{BeaconProcessMetrics, ValidatorProcessMetrics};
+use super::types::{BeaconProcessMetrics, ProcessMetrics, ValidatorProcessMetrics};
 use lazy_static::lazy_static;
 use lighthouse_metrics::{MetricFamily, MetricType};
 use serde_json::json;
@@ -178,17 +178,27 @@ pub fn gather_metrics(metrics_map: &HashMap<String, JsonMetric>) -> Option<serde
     Some(serde_json::Value::Object(res))
 }
 
+/// Returns the metrics of the current process, or `None` if `include_process` is `false`.
+fn gather_process_metrics(include_process: bool) -> Result<Option<ProcessMetrics>, String> {
+    if include_process {
+        Ok(Some(eth2::lighthouse::ProcessHealth::observe()?.into()))
+    } else {
+        Ok(None)
+    }
+}
+
 /// Gathers and returns the lighthouse beacon metrics.
 pub fn gather_beacon_metrics(
     db_path: &Path,
     freezer_db_path: &Path,
+    include_process: bool,
 ) -> Result<BeaconProcessMetrics, String> {
     // Update db size metrics
     store::metrics::scrape_for_metrics(db_path, freezer_db_path);
 
     let beacon_metrics = gather_metrics(&BEACON_METRICS_MAP)
         .ok_or_else(|| "Failed to gather beacon metrics".to_string())?;
-    let process = eth2::lighthouse::ProcessHealth::observe()?.into();
+    let process = gather_process_metrics(include_process)?;
 
     Ok(BeaconProcessMetrics {
         beacon: beacon_metrics,
@@ -197,11 +207,11 @@ pub fn gather_beacon_metrics(
 }
 
 /// Gathers and returns the lighthouse validator metrics.
-pub fn gather_validator_metrics() -> Result<ValidatorProcessMetrics, String> {
+pub fn gather_validator_metrics(include_process: bool) -> Result<ValidatorProcessMetrics, String> {
     let validator_metrics = gather_metrics(&VALIDATOR_METRICS_MAP)
         .ok_or_else(|| "Failed to gather validator metrics".to_string())?;
 
-    let process = eth2::lighthouse::ProcessHealth::observe()?.into();
+    let process = gather_process_metrics(include_process)?;
     Ok(ValidatorProcessMetrics {
         validator: validator_metrics,
         common: process,
@@ -19,6 +19,15 @@ pub use types::ProcessType;
 pub const DEFAULT_UPDATE_DURATION: u64 = 60;
 /// Timeout for HTTP requests.
 pub const TIMEOUT_DURATION: u64 = 5;
+/// Update periods shorter than this are likely to be rate limited by the remote endpoint.
+pub const MIN_RECOMMENDED_UPDATE_DURATION: u64 = 30;
+/// The JSON key of the number of enabled validators in the validator metrics.
+const VALIDATOR_ACTIVE_KEY: &str = "validator_active";
+/// The JSON keys of the validator metrics which are omitted when `redact_validator_ids` is set.
+///
+/// Validator counts can be matched against the deposits on chain to identify an operator's
+/// validators.
+const VALIDATOR_IDENTIFYING_KEYS: &[&str] = &[VALIDATOR_ACTIVE_KEY, "validator_total"];
 
 #[derive(Debug)]
 pub enum Error {
@@ -45,7 +54,7 @@ impl std::fmt::Display for Error {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     /// Endpoint
     pub monitoring_endpoint: String,
@@ -57,6 +66,32 @@ pub struct Config {
     pub freezer_db_path: Option<PathBuf>,
     /// User-defined update period in seconds.
     pub update_period_secs: Option<u64>,
+    /// Whether to send the CPU and memory usage of the Lighthouse process.
+    pub process_metrics: bool,
+    /// Whether to send metrics of the host system.
+    pub system_metrics: bool,
+    /// Whether to send validator metrics, i.e. validator counts and beacon node connectivity.
+    pub validator_metrics: bool,
+    /// Validator metrics are only sent while at least this many validators are enabled.
+    pub validator_metrics_min_validators: u64,
+    /// Omit validator metrics which could be used to identify the validators of this client.
+    pub redact_validator_ids: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            monitoring_endpoint: String::new(),
+            db_path: None,
+            freezer_db_path: None,
+            update_period_secs: None,
+            process_metrics: true,
+            system_metrics: true,
+            validator_metrics: true,
+            validator_metrics_min_validators: 0,
+            redact_validator_ids: false,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -68,6 +103,11 @@ pub struct MonitoringHttpClient {
     freezer_db_path: Option<PathBuf>,
     update_period: Duration,
     monitoring_endpoint: SensitiveUrl,
+    process_metrics: bool,
+    system_metrics: bool,
+    validator_metrics: bool,
+    validator_metrics_min_validators: u64,
+    redact_validator_ids: bool,
     log: slog::Logger,
 }
 
@@ -82,6 +122,11 @@ impl MonitoringHttpClient {
             ),
             monitoring_endpoint: SensitiveUrl::parse(&config.monitoring_endpoint)
                 .map_err(|e| format!("Invalid monitoring endpoint: {:?}", e))?,
+            process_metrics: config.process_metrics,
+            system_metrics: config.system_metrics,
+            validator_metrics: config.validator_metrics,
+            validator_metrics_min_validators: config.validator_metrics_min_validators,
+            redact_validator_ids: config.redact_validator_ids,
             log,
         })
     }
@@ -102,7 +147,15 @@ impl MonitoringHttpClient {
 
     /// Creates a task which periodically sends the provided process metrics
     /// to the configured remote endpoint.
-    pub fn auto_update(self, executor: TaskExecutor, processes: Vec<ProcessType>) {
+    ///
+    /// Processes which are excluded by the configuration are not sent.
+    pub fn auto_update(self, executor: TaskExecutor, mut processes: Vec<ProcessType>) {
+        processes.retain(|process| match process {
+            ProcessType::BeaconNode => true,
+            ProcessType::Validator => self.validator_metrics,
+            ProcessType::System => self.system_metrics,
+        });
+
         let mut interval = interval_at(
             // Have some initial delay for the metrics to get initialized
             Instant::now() + Duration::from_secs(25),
@@ -114,6 +167,9 @@ impl MonitoringHttpClient {
             "Starting monitoring API";
             "endpoint" => %self.monitoring_endpoint,
             "update_period" => format!("{}s", self.update_period.as_secs()),
+            "processes" => ?processes,
+            "process_metrics" => self.process_metrics,
+            "redact_validator_ids" => self.redact_validator_ids,
         );
 
         let update_future = async move {
@@ -142,8 +198,8 @@ impl MonitoringHttpClient {
         let freezer_db_path = self.freezer_db_path.as_ref().ok_or_else(|| {
             Error::BeaconMetricsFailed("Beacon metrics require freezer db path".to_string())
         })?;
-        let metrics =
-            gather_beacon_metrics(db_path, freezer_db_path).map_err(Error::BeaconMetricsFailed)?;
+        let metrics = gather_beacon_metrics(db_path, freezer_db_path, self.process_metrics)
+            .map_err(Error::BeaconMetricsFailed)?;
         Ok(MonitoringMetrics {
             metadata: Metadata::new(ProcessType::BeaconNode),
             process_metrics: Process::Beacon(metrics),
@@ -152,7 +208,8 @@ impl MonitoringHttpClient {
 
     /// Gets validator process metrics by querying the validator metrics endpoint
     pub fn get_validator_metrics(&self) -> Result<MonitoringMetrics, Error> {
-        let metrics = gather_validator_metrics().map_err(Error::BeaconMetricsFailed)?;
+        let metrics =
+            gather_validator_metrics(self.process_metrics).map_err(Error::BeaconMetricsFailed)?;
         Ok(MonitoringMetrics {
             metadata: Metadata::new(ProcessType::Validator),
             process_metrics: Process::Validator(metrics),
@@ -180,6 +237,22 @@ impl MonitoringHttpClient {
         }
     }
 
+    /// Returns `false` if `metric` contains validator metrics and fewer validators than
+    /// `validator_metrics_min_validators` are enabled.
+    fn validator_threshold_reached(&self, metric: &MonitoringMetrics) -> bool {
+        match &metric.process_metrics {
+            Process::Validator(metrics) => {
+                let active = metrics
+                    .validator
+                    .get(VALIDATOR_ACTIVE_KEY)
+                    .and_then(|value| value.as_u64())
+                    .unwrap_or(0);
+                active >= self.validator_metrics_min_validators
+            }
+            _ => true,
+        }
+    }
+
     /// Send metrics to the remote endpoint
     pub async fn send_metrics(&self, processes: &[ProcessType]) -> Result<(), Error> {
         let mut metrics = Vec::new();
@@ -191,9 +264,26 @@ impl MonitoringHttpClient {
                     "process_type" => ?process,
                     "error" => %e
                 ),
-                Ok(metric) => metrics.push(metric),
+                Ok(mut metric) => {
+                    if !self.validator_threshold_reached(&metric) {
+                        debug!(
+                            self.log,
+                            "Not sending validator metrics";
+                            "reason" => "too few validators",
+                            "min_validators" => self.validator_metrics_min_validators,
+                        );
+                        continue;
+                    }
+                    if self.redact_validator_ids {
+                        redact_validator_ids(&mut metric);
+                    }
+                    metrics.push(metric)
+                }
             }
         }
+        if metrics.is_empty() {
+            return Ok(());
+        }
         info!(
             self.log,
             "Sending metrics to remote endpoint";
@@ -204,6 +294,17 @@ impl MonitoringHttpClient {
     }
 }
 
+/// Removes the fields of `metric` which could identify the validators of this client.
+fn redact_validator_ids(metric: &mut MonitoringMetrics) {
+    if let Process::Validator(metrics) = &mut metric.process_metrics {
+        if let Some(fields) = metrics.validator.as_object_mut() {
+            for key in VALIDATOR_IDENTIFYING_KEYS {
+                fields.remove(*key);
+            }
+        }
+    }
+}
+
 /// Returns `Ok(response)` if the response is a `200 OK` response. Otherwise, creates an
 /// appropriate error message.
 async fn ok_or_error(response: Response) -> Result<Response, Error> {
@@ -217,3 +318,33 @@ async fn ok_or_error(response: Response) -> Result<Response, Error> {
         Err(Error::StatusCode(status))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redact_validator_metrics() {
+        let mut metric = MonitoringMetrics {
+            metadata: Metadata::new(ProcessType::Validator),
+            process_metrics: Process::Validator(ValidatorProcessMetrics {
+                common: None,
+                validator: json!({
+                    "validator_active": 3,
+                    "validator_total": 4,
+                    "sync_eth2_fallback_configured": true,
+                }),
+            }),
+        };
+        redact_validator_ids(&mut metric);
+
+        match metric.process_metrics {
+            Process::Validator(metrics) => assert_eq!(
+                metrics.validator,
+                json!({ "sync_eth2_fallback_configured": true })
+            ),
+            _ => panic!("process type changed"),
+        }
+    }
+}
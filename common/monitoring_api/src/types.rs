@@ -148,8 +148,9 @@ impl From<SystemHealth> for SystemMetrics {
 /// All beacon process metrics.
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BeaconProcessMetrics {
+    /// `None` if process metrics were excluded by the configuration.
     #[serde(flatten)]
-    pub common: ProcessMetrics,
+    pub common: Option<ProcessMetrics>,
     #[serde(flatten)]
     pub beacon: serde_json::Value,
 }
@@ -157,8 +158,9 @@ pub struct BeaconProcessMetrics {
 /// All validator process metrics
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ValidatorProcessMetrics {
+    /// `None` if process metrics were excluded by the configuration.
     #[serde(flatten)]
-    pub common: ProcessMetrics,
+    pub common: Option<ProcessMetrics>,
     #[serde(flatten)]
     pub validator: serde_json::Value,
 }
@@ -115,6 +115,16 @@ impl TaskExecutor {
         }
     }
 
+    /// Clones the task executor adding a log category, see `logging::LOG_CATEGORY_KEY`.
+    pub fn clone_with_log_category(&self, category: &'static str) -> Self {
+        TaskExecutor {
+            handle_provider: self.handle_provider.clone(),
+            exit: self.exit.clone(),
+            signal_tx: self.signal_tx.clone(),
+            log: self.log.new(o!(logging::LOG_CATEGORY_KEY => category)),
+        }
+    }
+
     /// A convenience wrapper for `Self::spawn` which ignores a `Result` as long as both `Ok`/`Err`
     /// are of type `()`.
     ///
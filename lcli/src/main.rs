@@ -873,6 +873,7 @@ fn run<T: EthSpec>(
             path: None,
             debug_level: String::from("trace"),
             logfile_debug_level: String::from("trace"),
+            log_levels: None,
             log_format: None,
             logfile_format: None,
             log_color: false,
@@ -14,7 +14,7 @@ use futures::{future, StreamExt};
 
 use logging::SSELoggingComponents;
 use serde_derive::{Deserialize, Serialize};
-use slog::{error, info, o, warn, Drain, Duplicate, Level, Logger};
+use slog::{error, info, o, warn, Drain, Duplicate, Logger};
 use sloggers::{file::FileLoggerBuilder, types::Format, types::Severity, Build};
 use std::fs::create_dir_all;
 use std::io::{Result as IOResult, Write};
@@ -51,6 +51,8 @@ pub struct LoggerConfig {
     pub path: Option<PathBuf>,
     pub debug_level: String,
     pub logfile_debug_level: String,
+    /// Per-category overrides of `debug_level`, as a comma-separated list of `CATEGORY=LEVEL`.
+    pub log_levels: Option<String>,
     pub log_format: Option<String>,
     pub logfile_format: Option<String>,
     pub log_color: bool,
@@ -67,6 +69,7 @@ impl Default for LoggerConfig {
             path: None,
             debug_level: String::from("info"),
             logfile_debug_level: String::from("debug"),
+            log_levels: None,
             log_format: None,
             logfile_format: None,
             log_color: false,
@@ -107,6 +110,20 @@ impl<E: EthSpec> RuntimeContext<E> {
         }
     }
 
+    /// Returns a sub-context of this context.
+    ///
+    /// The generated service will have the log `category` in all it's logs, which allows its
+    /// verbosity to be set separately with `LoggerConfig::log_levels`.
+    pub fn log_category_context(&self, category: &'static str) -> Self {
+        Self {
+            executor: self.executor.clone_with_log_category(category),
+            eth_spec_instance: self.eth_spec_instance.clone(),
+            eth2_config: self.eth2_config.clone(),
+            eth2_network_config: self.eth2_network_config.clone(),
+            sse_logging_components: self.sse_logging_components.clone(),
+        }
+    }
+
     /// Returns the `eth2_config` for this service.
     pub fn eth2_config(&self) -> &Eth2Config {
         &self.eth2_config
@@ -235,15 +252,16 @@ impl<E: EthSpec> EnvironmentBuilder<E> {
                 .build()
         };
 
-        let stdout_drain = match config.debug_level.as_str() {
-            "info" => stdout_drain.filter_level(Level::Info),
-            "debug" => stdout_drain.filter_level(Level::Debug),
-            "trace" => stdout_drain.filter_level(Level::Trace),
-            "warn" => stdout_drain.filter_level(Level::Warning),
-            "error" => stdout_drain.filter_level(Level::Error),
-            "crit" => stdout_drain.filter_level(Level::Critical),
-            unknown => return Err(format!("Unknown debug-level: {}", unknown)),
-        };
+        let debug_level = logging::parse_log_level(&config.debug_level)
+            .map_err(|_| format!("Unknown debug-level: {}", config.debug_level))?;
+        let log_levels = config
+            .log_levels
+            .as_deref()
+            .map(logging::parse_log_levels)
+            .transpose()
+            .map_err(|e| format!("Invalid log-levels: {}", e))?
+            .unwrap_or_default();
+        let stdout_drain = logging::CategoryFilter::new(stdout_drain, debug_level, log_levels);
 
         let stdout_logger = Logger::root(stdout_drain.fuse(), o!());
 
@@ -201,6 +201,17 @@ fn main() {
                 .global(true)
                 .default_value("info"),
         )
+        .arg(
+            Arg::with_name("log-levels")
+                .long("log-levels")
+                .value_name("CATEGORY=LEVEL,...")
+                .help("Overrides --debug-level for logs of the given categories, e.g. \
+                    `signing=debug`. The validator client logs attestation, block and sync \
+                    committee signing and publication, and slashing protection, under the \
+                    `signing` category. Only applies to terminal output.")
+                .takes_value(true)
+                .global(true),
+        )
         .arg(
             Arg::with_name("datadir")
                 .long("datadir")
@@ -423,6 +434,8 @@ fn run<E: EthSpec>(
         .value_of("debug-level")
         .ok_or("Expected --debug-level flag")?;
 
+    let log_levels = matches.value_of("log-levels");
+
     let log_format = matches.value_of("log-format");
 
     let log_color = matches.is_present("log-color");
@@ -497,6 +510,7 @@ fn run<E: EthSpec>(
         path: log_path,
         debug_level: String::from(debug_level),
         logfile_debug_level: String::from(logfile_debug_level),
+        log_levels: log_levels.map(String::from),
         log_format: log_format.map(String::from),
         logfile_format: logfile_format.map(String::from),
         log_color,
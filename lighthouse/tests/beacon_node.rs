@@ -2136,6 +2136,24 @@ fn logfile_no_restricted_perms_flag() {
         });
 }
 #[test]
+fn log_levels_default() {
+    CommandLineTest::new()
+        .run_with_zero_port()
+        .with_config(|config| assert_eq!(config.logger_config.log_levels, None));
+}
+#[test]
+fn log_levels_flag() {
+    CommandLineTest::new()
+        .flag("log-levels", Some("signing=debug"))
+        .run_with_zero_port()
+        .with_config(|config| {
+            assert_eq!(
+                config.logger_config.log_levels,
+                Some("signing=debug".to_string())
+            )
+        });
+}
+#[test]
 fn logfile_format_default() {
     CommandLineTest::new()
         .run_with_zero_port()
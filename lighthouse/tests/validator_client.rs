@@ -1,4 +1,9 @@
-use validator_client::Config;
+use validator_client::{
+    fork_schedule::{ForkOverride, OnForkVersionMismatch},
+    initialized_validators::OnKeystoreError,
+    BeaconNodeSelection, BuilderUnavailableAction, Config, MetricsValidatorBucketing,
+    OnExistingSlashingProtection, OnGasLimitOutOfRange, OnGraffitiOverflow, OnMissingFeeRecipient,
+};
 
 use crate::exec::CommandLineTestExec;
 use bls::{Keypair, PublicKeyBytes};
@@ -9,8 +14,9 @@ use std::path::PathBuf;
 use std::process::Command;
 use std::str::FromStr;
 use std::string::ToString;
+use std::time::Duration;
 use tempfile::TempDir;
-use types::Address;
+use types::{Address, ChainSpec, Epoch, ForkName, Hash256};
 
 /// Returns the `lighthouse validator_client` command.
 fn base_cmd() -> Command {
@@ -66,6 +72,43 @@ fn validators_and_secrets_dir_flags() {
         });
 }
 
+#[test]
+fn network_dir_name_does_not_override_explicit_dirs() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    CommandLineTest::new()
+        .flag("network-dir-name", Some("my-network"))
+        .flag("validators-dir", dir.path().join("validators").to_str())
+        .flag("secrets-dir", dir.path().join("secrets").to_str())
+        .run_with_no_datadir()
+        .with_config(|config| {
+            assert_eq!(config.validator_dir, dir.path().join("validators"));
+            assert_eq!(config.secrets_dir, dir.path().join("secrets"));
+        });
+}
+
+#[test]
+fn network_name_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.network_name, "mainnet"));
+}
+
+#[test]
+fn network_dir_name_does_not_override_network_name() {
+    CommandLineTest::new()
+        .flag("network-dir-name", Some("my-network"))
+        .run()
+        .with_config(|config| assert_eq!(config.network_name, "mainnet"));
+}
+
+#[test]
+#[should_panic]
+fn network_dir_name_invalid() {
+    CommandLineTest::new()
+        .flag("network-dir-name", Some("../mainnet"))
+        .run();
+}
+
 #[test]
 fn validators_dir_alias_flags() {
     let dir = TempDir::new().expect("Unable to create temporary directory");
@@ -101,6 +144,77 @@ fn beacon_nodes_flag() {
         });
 }
 
+#[test]
+fn log_deprecations_flag() {
+    CommandLineTest::new()
+        .flag("log-deprecations", None)
+        .flag("server", Some("http://localhost:1001"))
+        .flag("delete-lockfiles", None)
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.beacon_nodes.len(), 1);
+            assert_eq!(
+                config.beacon_nodes[0].full.to_string(),
+                "http://localhost:1001/"
+            );
+        });
+}
+
+#[test]
+fn register_via_proposer_nodes_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.register_via_proposer_nodes));
+}
+#[test]
+fn register_via_proposer_nodes_flag() {
+    CommandLineTest::new()
+        .flag("register-via-proposer-nodes", None)
+        .run()
+        .with_config(|config| assert!(config.register_via_proposer_nodes));
+}
+#[test]
+fn attestation_fallback_nodes_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.attestation_fallback_nodes.is_empty()));
+}
+#[test]
+fn attestation_fallback_nodes_flag() {
+    CommandLineTest::new()
+        .flag(
+            "attestation-fallback-nodes",
+            Some("http://localhost:1001,http://localhost:1002"),
+        )
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.attestation_fallback_nodes.len(), 2);
+            assert_eq!(
+                config.attestation_fallback_nodes[0].full.to_string(),
+                "http://localhost:1001/"
+            );
+            assert_eq!(
+                config.attestation_fallback_nodes[1].full.to_string(),
+                "http://localhost:1002/"
+            );
+        });
+}
+#[test]
+#[should_panic]
+fn attestation_fallback_nodes_invalid_url() {
+    CommandLineTest::new()
+        .flag("attestation-fallback-nodes", Some("not-a-url"))
+        .run();
+}
+#[test]
+#[should_panic]
+fn attestation_fallback_nodes_same_as_beacon_node() {
+    CommandLineTest::new()
+        .flag("beacon-nodes", Some("http://localhost:1001"))
+        .flag("attestation-fallback-nodes", Some("http://localhost:1001"))
+        .run();
+}
+
 #[test]
 fn allow_unsynced_flag() {
     // No-op, but doesn't crash.
@@ -115,6 +229,19 @@ fn disable_auto_discover_flag() {
         .with_config(|config| assert!(config.disable_auto_discover));
 }
 
+#[test]
+fn rebuild_index_cache_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.rebuild_index_cache));
+}
+#[test]
+fn rebuild_index_cache_flag() {
+    CommandLineTest::new()
+        .flag("rebuild-index-cache", None)
+        .run()
+        .with_config(|config| assert!(config.rebuild_index_cache));
+}
 #[test]
 fn init_slashing_protections_flag() {
     CommandLineTest::new()
@@ -124,399 +251,2451 @@ fn init_slashing_protections_flag() {
 }
 
 #[test]
-fn use_long_timeouts_flag() {
+fn on_existing_slashing_protection_default() {
     CommandLineTest::new()
-        .flag("use-long-timeouts", None)
+        .flag("init-slashing-protection", None)
         .run()
-        .with_config(|config| assert!(config.use_long_timeouts));
+        .with_config(|config| {
+            assert_eq!(
+                config.on_existing_slashing_protection,
+                OnExistingSlashingProtection::Keep
+            )
+        });
 }
-
 #[test]
-fn beacon_nodes_tls_certs_flag() {
-    let dir = TempDir::new().expect("Unable to create temporary directory");
+fn on_existing_slashing_protection_strict() {
     CommandLineTest::new()
-        .flag(
-            "beacon-nodes-tls-certs",
-            Some(
-                vec![
-                    dir.path().join("certificate.crt").to_str().unwrap(),
-                    dir.path().join("certificate2.crt").to_str().unwrap(),
-                ]
-                .join(",")
-                .as_str(),
-            ),
-        )
+        .flag("init-slashing-protection", None)
+        .flag("on-existing-slashing-protection", Some("strict"))
         .run()
         .with_config(|config| {
             assert_eq!(
-                config.beacon_nodes_tls_certs,
-                Some(vec![
-                    dir.path().join("certificate.crt"),
-                    dir.path().join("certificate2.crt")
-                ])
+                config.on_existing_slashing_protection,
+                OnExistingSlashingProtection::Strict
             )
         });
 }
+#[test]
+#[should_panic]
+fn on_existing_slashing_protection_without_init() {
+    CommandLineTest::new()
+        .flag("on-existing-slashing-protection", Some("strict"))
+        .run();
+}
 
-// Tests for Graffiti flags.
 #[test]
-fn graffiti_flag() {
+fn on_keystore_error_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(
+            config.initialized_validators.on_keystore_error,
+            OnKeystoreError::Abort
+        )
+    });
+}
+#[test]
+fn on_keystore_error_flag() {
     CommandLineTest::new()
-        .flag("graffiti", Some("nice-graffiti"))
+        .flag("on-keystore-error", Some("skip"))
         .run()
         .with_config(|config| {
             assert_eq!(
-                config.graffiti.unwrap().to_string(),
-                "0x6e6963652d677261666669746900000000000000000000000000000000000000"
+                config.initialized_validators.on_keystore_error,
+                OnKeystoreError::Skip
+            )
+        });
+    CommandLineTest::new()
+        .flag("on-keystore-error", Some("abort"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.initialized_validators.on_keystore_error,
+                OnKeystoreError::Abort
             )
         });
 }
 #[test]
-fn graffiti_file_flag() {
-    let dir = TempDir::new().expect("Unable to create temporary directory");
-    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
-    let new_key = Keypair::random();
-    let pubkeybytes = PublicKeyBytes::from(new_key.pk);
-    let contents = "default:nice-graffiti";
-    file.write_all(contents.as_bytes())
-        .expect("Unable to write to file");
+#[should_panic]
+fn on_keystore_error_invalid_value() {
     CommandLineTest::new()
-        .flag(
-            "graffiti-file",
-            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        .flag("on-keystore-error", Some("ignore"))
+        .run();
+}
+#[test]
+fn secrets_manifest_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.initialized_validators.secrets_manifest, None));
+}
+#[test]
+fn secrets_manifest_flag() {
+    CommandLineTest::new()
+        .flag("secrets-manifest", Some("./secrets.json"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.initialized_validators.secrets_manifest,
+                Some(PathBuf::from("./secrets.json"))
+            )
+        });
+}
+#[test]
+fn validators_count_limit_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.initialized_validators.max_validators, None));
+}
+#[test]
+fn validators_count_limit_flag() {
+    CommandLineTest::new()
+        .flag("validators-count-limit", Some("100"))
+        .run()
+        .with_config(|config| assert_eq!(config.initialized_validators.max_validators, Some(100)));
+}
+#[test]
+#[should_panic]
+fn validators_count_limit_zero() {
+    CommandLineTest::new()
+        .flag("validators-count-limit", Some("0"))
+        .run();
+}
+#[test]
+fn pkcs11_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.initialized_validators.pkcs11, None));
+}
+#[test]
+fn pkcs11_flags() {
+    CommandLineTest::new()
+        .flag("pkcs11-module", Some("/usr/lib/libvendor-pkcs11.so"))
+        .flag("pkcs11-slot", Some("1"))
+        .flag("pkcs11-pin-file", Some("./pin.txt"))
+        .flag("pkcs11-mechanism", Some("0x80000101"))
+        .run()
+        .with_config(|config| {
+            let pkcs11 = config.initialized_validators.pkcs11.as_ref().unwrap();
+            assert_eq!(
+                pkcs11.module_path,
+                PathBuf::from("/usr/lib/libvendor-pkcs11.so")
+            );
+            assert_eq!(pkcs11.slot, 1);
+            assert_eq!(pkcs11.pin_path, PathBuf::from("./pin.txt"));
+            assert_eq!(pkcs11.mechanism, 0x8000_0101);
+        });
+}
+#[test]
+#[should_panic]
+fn pkcs11_module_without_slot() {
+    CommandLineTest::new()
+        .flag("pkcs11-module", Some("/usr/lib/libvendor-pkcs11.so"))
+        .flag("pkcs11-pin-file", Some("./pin.txt"))
+        .flag("pkcs11-mechanism", Some("0x80000101"))
+        .run();
+}
+#[test]
+fn keystore_decrypt_concurrency_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(
+            config.initialized_validators.keystore_decrypt_concurrency,
+            None
         )
+    });
+}
+#[test]
+fn keystore_decrypt_concurrency_flag() {
+    CommandLineTest::new()
+        .flag("keystore-decrypt-concurrency", Some("2"))
         .run()
         .with_config(|config| {
-            // Public key not present so load default.
             assert_eq!(
-                config
-                    .graffiti_file
-                    .clone()
-                    .unwrap()
-                    .load_graffiti(&pubkeybytes)
-                    .unwrap()
-                    .unwrap()
-                    .to_string(),
-                "0x6e6963652d677261666669746900000000000000000000000000000000000000"
+                config.initialized_validators.keystore_decrypt_concurrency,
+                Some(2)
             )
         });
 }
 #[test]
-fn graffiti_file_with_pk_flag() {
+fn validator_load_priority_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.validator_load_priority, None);
+        assert_eq!(config.initialized_validators.load_priority, None);
+    });
+}
+#[test]
+fn validator_load_priority_flag() {
     let dir = TempDir::new().expect("Unable to create temporary directory");
-    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
-    let new_key = Keypair::random();
-    let pubkeybytes = PublicKeyBytes::from(new_key.pk);
-    let contents = format!("{}:nice-graffiti", pubkeybytes.to_string());
+    let path = dir.path().join("load_priority.txt");
+    let mut file = File::create(&path).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("# high value validators\n{}\n\n", pubkeybytes);
     file.write_all(contents.as_bytes())
         .expect("Unable to write to file");
     CommandLineTest::new()
-        .flag(
-            "graffiti-file",
-            dir.path().join("graffiti.txt").as_os_str().to_str(),
-        )
+        .flag("validator-load-priority", path.as_os_str().to_str())
         .run()
         .with_config(|config| {
-            assert_eq!(
-                config
-                    .graffiti_file
-                    .clone()
-                    .unwrap()
-                    .load_graffiti(&pubkeybytes)
-                    .unwrap()
-                    .unwrap()
-                    .to_string(),
-                "0x6e6963652d677261666669746900000000000000000000000000000000000000"
-            )
+            assert_eq!(config.validator_load_priority, Some(path.clone()));
+            let load_priority = config
+                .initialized_validators
+                .load_priority
+                .as_ref()
+                .unwrap();
+            assert_eq!(load_priority.len(), 1);
+            assert!(load_priority.contains(&pubkeybytes));
         });
 }
-
-// Tests for suggested-fee-recipient flags.
 #[test]
-fn fee_recipient_flag() {
+#[should_panic]
+fn validator_load_priority_invalid_pubkey() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let path = dir.path().join("load_priority.txt");
+    let mut file = File::create(&path).expect("Unable to create file");
+    file.write_all(b"0xdeadbeef\n")
+        .expect("Unable to write to file");
     CommandLineTest::new()
-        .flag(
-            "suggested-fee-recipient",
-            Some("0x00000000219ab540356cbb839cbe05303d7705fa"),
-        )
+        .flag("validator-load-priority", path.as_os_str().to_str())
+        .run();
+}
+#[test]
+fn validator_index_range_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.validator_index_range, None);
+        assert_eq!(config.initialized_validators.index_range, None);
+    });
+}
+#[test]
+fn validator_index_range_flag() {
+    CommandLineTest::new()
+        .flag("validator-index-range", Some("1000..2000"))
         .run()
         .with_config(|config| {
+            assert_eq!(config.validator_index_range, Some((1000, 2000)));
             assert_eq!(
-                config.fee_recipient,
-                Some(Address::from_str("0x00000000219ab540356cbb839cbe05303d7705fa").unwrap())
-            )
+                config.initialized_validators.index_range,
+                Some((1000, 2000))
+            );
+        });
+}
+#[test]
+#[should_panic]
+fn validator_index_range_empty() {
+    CommandLineTest::new()
+        .flag("validator-index-range", Some("2000..1000"))
+        .run();
+}
+#[test]
+#[should_panic]
+fn keystore_decrypt_concurrency_zero() {
+    CommandLineTest::new()
+        .flag("keystore-decrypt-concurrency", Some("0"))
+        .run();
+}
+#[test]
+fn on_fork_version_mismatch_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.on_fork_version_mismatch, OnForkVersionMismatch::Warn)
+    });
+}
+#[test]
+fn on_fork_version_mismatch_flag() {
+    CommandLineTest::new()
+        .flag("on-fork-version-mismatch", Some("refuse"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.on_fork_version_mismatch,
+                OnForkVersionMismatch::Refuse
+            )
+        });
+}
+#[test]
+#[should_panic]
+fn on_fork_version_mismatch_invalid_value() {
+    CommandLineTest::new()
+        .flag("on-fork-version-mismatch", Some("ignore"))
+        .run();
+}
+#[test]
+fn fork_transition_caution_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(config.fork_transition_caution);
+        assert_eq!(config.fork_transition_window, 2);
+    });
+}
+#[test]
+fn disable_fork_transition_caution_flag() {
+    CommandLineTest::new()
+        .flag("disable-fork-transition-caution", None)
+        .run()
+        .with_config(|config| assert!(!config.fork_transition_caution));
+}
+#[test]
+fn fork_transition_window_flag() {
+    CommandLineTest::new()
+        .flag("fork-transition-window", Some("5"))
+        .run()
+        .with_config(|config| assert_eq!(config.fork_transition_window, 5));
+}
+#[test]
+fn use_long_timeouts_flag() {
+    CommandLineTest::new()
+        .flag("use-long-timeouts", None)
+        .run()
+        .with_config(|config| assert!(config.use_long_timeouts));
+}
+
+#[test]
+fn beacon_nodes_min_tls_version_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.beacon_nodes_min_tls_version, None);
+    });
+}
+#[test]
+fn beacon_nodes_min_tls_version_flag() {
+    CommandLineTest::new()
+        .flag("beacon-nodes-min-tls-version", Some("1.3"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.beacon_nodes_min_tls_version, Some("1.3".to_string()));
+        });
+}
+#[test]
+#[should_panic]
+fn beacon_nodes_min_tls_version_invalid() {
+    CommandLineTest::new()
+        .flag("beacon-nodes-min-tls-version", Some("1.4"))
+        .run();
+}
+#[test]
+fn beacon_nodes_tls_certs_directory() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let cert = include_str!("../../testing/web3signer_tests/tls/lighthouse/cert.pem");
+    for name in ["b.pem", "a.CRT", "README.txt"] {
+        std::fs::write(dir.path().join(name), cert).expect("Unable to write certificate");
+    }
+    let single = dir.path().join("single.crt");
+    CommandLineTest::new()
+        .flag(
+            "beacon-nodes-tls-certs",
+            Some(format!("{},{}", single.display(), dir.path().display()).as_str()),
+        )
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.beacon_nodes_tls_certs,
+                Some(vec![
+                    single.clone(),
+                    dir.path().join("a.CRT"),
+                    dir.path().join("b.pem"),
+                ])
+            )
+        });
+}
+#[test]
+#[should_panic]
+fn beacon_nodes_tls_certs_empty_directory() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    std::fs::write(dir.path().join("README.txt"), "not a certificate").unwrap();
+    CommandLineTest::new()
+        .flag("beacon-nodes-tls-certs", dir.path().to_str())
+        .run();
+}
+#[test]
+#[should_panic]
+fn beacon_nodes_tls_certs_invalid_directory() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    std::fs::write(dir.path().join("ca.pem"), "not a certificate").unwrap();
+    CommandLineTest::new()
+        .flag("beacon-nodes-tls-certs", dir.path().to_str())
+        .run();
+}
+#[test]
+fn beacon_nodes_tls_certs_flag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    CommandLineTest::new()
+        .flag(
+            "beacon-nodes-tls-certs",
+            Some(
+                vec![
+                    dir.path().join("certificate.crt").to_str().unwrap(),
+                    dir.path().join("certificate2.crt").to_str().unwrap(),
+                ]
+                .join(",")
+                .as_str(),
+            ),
+        )
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.beacon_nodes_tls_certs,
+                Some(vec![
+                    dir.path().join("certificate.crt"),
+                    dir.path().join("certificate2.crt")
+                ])
+            )
+        });
+}
+
+#[test]
+fn beacon_node_dns_ttl_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.beacon_node_dns_ttl, None));
+}
+#[test]
+fn beacon_node_dns_ttl_flag() {
+    CommandLineTest::new()
+        .flag("beacon-node-dns-ttl", Some("30"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.beacon_node_dns_ttl, Some(Duration::from_secs(30)))
+        });
+}
+#[test]
+#[should_panic]
+fn beacon_node_dns_ttl_invalid() {
+    CommandLineTest::new()
+        .flag("beacon-node-dns-ttl", Some("two"))
+        .run();
+}
+#[test]
+fn beacon_nodes_tls_cert_expiry_days_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(
+            config.beacon_nodes_tls_cert_expiry_window,
+            Duration::from_secs(14 * 24 * 60 * 60)
+        )
+    });
+}
+#[test]
+fn beacon_nodes_tls_cert_expiry_days_flag() {
+    CommandLineTest::new()
+        .flag("beacon-nodes-tls-cert-expiry-days", Some("30"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.beacon_nodes_tls_cert_expiry_window,
+                Duration::from_secs(30 * 24 * 60 * 60)
+            )
+        });
+}
+#[test]
+fn beacon_nodes_tls_cert_expiry_days_zero() {
+    CommandLineTest::new()
+        .flag("beacon-nodes-tls-cert-expiry-days", Some("0"))
+        .run()
+        .with_config(|config| assert!(config.beacon_nodes_tls_cert_expiry_window.is_zero()));
+}
+
+// Tests for Graffiti flags.
+#[test]
+fn graffiti_flag() {
+    CommandLineTest::new()
+        .flag("graffiti", Some("nice-graffiti"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.graffiti.unwrap().to_string(),
+                "0x6e6963652d677261666669746900000000000000000000000000000000000000"
+            )
+        });
+}
+#[test]
+#[should_panic]
+fn graffiti_too_long() {
+    CommandLineTest::new()
+        .flag("graffiti", Some(&"a".repeat(33)))
+        .run();
+}
+#[test]
+fn graffiti_on_overflow_truncate() {
+    CommandLineTest::new()
+        .flag("graffiti", Some(&format!("{}b", "a".repeat(32))))
+        .flag("graffiti-on-overflow", Some("truncate"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.graffiti_on_overflow, OnGraffitiOverflow::Truncate);
+            assert_eq!(config.graffiti.unwrap().as_utf8_lossy(), "a".repeat(32));
+        });
+}
+#[test]
+fn graffiti_force_default() {
+    CommandLineTest::new()
+        .flag("graffiti", Some("nice-graffiti"))
+        .run()
+        .with_config(|config| assert!(!config.graffiti_force));
+}
+#[test]
+fn graffiti_force_with_graffiti_file() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
+    file.write_all(b"default:file-graffiti")
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag("graffiti", Some("nice-graffiti"))
+        .flag("graffiti-force", None)
+        .flag(
+            "graffiti-file",
+            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        )
+        .run()
+        .with_config(|config| {
+            assert!(config.graffiti_force);
+            assert!(config.graffiti_file.is_some());
+            assert_eq!(config.graffiti.unwrap().as_utf8_lossy(), "nice-graffiti");
+        });
+}
+#[test]
+#[should_panic]
+fn graffiti_file_with_graffiti_without_force() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
+    file.write_all(b"default:file-graffiti")
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag("graffiti", Some("nice-graffiti"))
+        .flag(
+            "graffiti-file",
+            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        )
+        .run();
+}
+#[test]
+#[should_panic]
+fn graffiti_force_without_graffiti() {
+    CommandLineTest::new().flag("graffiti-force", None).run();
+}
+#[test]
+fn graffiti_file_flag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
+    let new_key = Keypair::random();
+    let pubkeybytes = PublicKeyBytes::from(new_key.pk);
+    let contents = "default:nice-graffiti";
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "graffiti-file",
+            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        )
+        .run()
+        .with_config(|config| {
+            // Public key not present so load default.
+            assert_eq!(
+                config
+                    .graffiti_file
+                    .clone()
+                    .unwrap()
+                    .load_graffiti(&pubkeybytes, Epoch::new(0), &ChainSpec::mainnet())
+                    .unwrap()
+                    .unwrap()
+                    .to_string(),
+                "0x6e6963652d677261666669746900000000000000000000000000000000000000"
+            )
+        });
+}
+#[test]
+fn graffiti_file_with_pk_flag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
+    let new_key = Keypair::random();
+    let pubkeybytes = PublicKeyBytes::from(new_key.pk);
+    let contents = format!("{}:nice-graffiti", pubkeybytes.to_string());
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "graffiti-file",
+            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        )
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config
+                    .graffiti_file
+                    .clone()
+                    .unwrap()
+                    .load_graffiti(&pubkeybytes, Epoch::new(0), &ChainSpec::mainnet())
+                    .unwrap()
+                    .unwrap()
+                    .to_string(),
+                "0x6e6963652d677261666669746900000000000000000000000000000000000000"
+            )
+        });
+}
+#[test]
+fn graffiti_file_duplicate_last_wins() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("{0}:first\n{0}:second\n", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "graffiti-file",
+            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        )
+        .run()
+        .with_config(|config| {
+            let graffiti_file = config.graffiti_file.as_ref().unwrap();
+            assert_eq!(graffiti_file.duplicates(), &[pubkeybytes]);
+            assert_eq!(
+                graffiti_file
+                    .clone()
+                    .load_graffiti(&pubkeybytes, Epoch::new(0), &ChainSpec::mainnet())
+                    .unwrap()
+                    .unwrap()
+                    .as_utf8_lossy(),
+                "second"
+            );
+        });
+}
+#[test]
+fn graffiti_file_duplicate_last_wins_flag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("{0}:first\n{0}:second\n", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "graffiti-file",
+            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        )
+        .flag("graffiti-file-on-duplicate", Some("last_wins"))
+        .run()
+        .with_config(|config| {
+            let graffiti_file = config.graffiti_file.as_ref().unwrap();
+            assert_eq!(graffiti_file.duplicates(), &[pubkeybytes]);
+        });
+}
+#[test]
+#[should_panic]
+fn graffiti_file_duplicate_kebab_case_rejected() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
+    CommandLineTest::new()
+        .flag(
+            "graffiti-file",
+            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        )
+        .flag("graffiti-file-on-duplicate", Some("last-wins"))
+        .run();
+}
+#[test]
+#[should_panic]
+fn graffiti_file_duplicate_error() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("graffiti.txt")).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("{0}:first\n{0}:second\n", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "graffiti-file",
+            dir.path().join("graffiti.txt").as_os_str().to_str(),
+        )
+        .flag("graffiti-file-on-duplicate", Some("error"))
+        .run();
+}
+
+// Tests for suggested-fee-recipient flags.
+#[test]
+fn fee_recipient_flag() {
+    CommandLineTest::new()
+        .flag(
+            "suggested-fee-recipient",
+            Some("0x00000000219ab540356cbb839cbe05303d7705fa"),
+        )
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.fee_recipient,
+                Some(Address::from_str("0x00000000219ab540356cbb839cbe05303d7705fa").unwrap())
+            )
+        });
+}
+#[test]
+fn on_missing_fee_recipient_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.on_missing_fee_recipient, OnMissingFeeRecipient::Zero)
+    });
+}
+#[test]
+fn on_missing_fee_recipient_flag() {
+    for (value, expected) in [
+        ("zero", OnMissingFeeRecipient::Zero),
+        ("skip_proposal", OnMissingFeeRecipient::SkipProposal),
+        ("error_startup", OnMissingFeeRecipient::ErrorStartup),
+    ] {
+        CommandLineTest::new()
+            .flag("on-missing-fee-recipient", Some(value))
+            .run()
+            .with_config(|config| assert_eq!(config.on_missing_fee_recipient, expected));
+    }
+}
+#[test]
+#[should_panic]
+fn on_missing_fee_recipient_invalid_value() {
+    CommandLineTest::new()
+        .flag("on-missing-fee-recipient", Some("skip"))
+        .run();
+}
+
+// Tests for HTTP flags.
+#[test]
+fn http_flag() {
+    CommandLineTest::new()
+        .flag("http", None)
+        .run()
+        .with_config(|config| assert!(config.http_api.enabled));
+}
+#[test]
+fn http_address_flag() {
+    let addr = "127.0.0.99".parse::<IpAddr>().unwrap();
+    CommandLineTest::new()
+        .flag("http-address", Some("127.0.0.99"))
+        .flag("unencrypted-http-transport", None)
+        .run()
+        .with_config(|config| assert_eq!(config.http_api.listen_addr, addr));
+}
+#[test]
+fn http_address_ipv6_flag() {
+    let addr = "::1".parse::<IpAddr>().unwrap();
+    CommandLineTest::new()
+        .flag("http-address", Some("::1"))
+        .flag("unencrypted-http-transport", None)
+        .run()
+        .with_config(|config| assert_eq!(config.http_api.listen_addr, addr));
+}
+#[test]
+#[should_panic]
+fn missing_unencrypted_http_transport_flag() {
+    let addr = "127.0.0.99".parse::<IpAddr>().unwrap();
+    CommandLineTest::new()
+        .flag("http-address", Some("127.0.0.99"))
+        .run()
+        .with_config(|config| assert_eq!(config.http_api.listen_addr, addr));
+}
+#[test]
+fn http_port_flag() {
+    CommandLineTest::new()
+        .flag("http-port", Some("9090"))
+        .run()
+        .with_config(|config| assert_eq!(config.http_api.listen_port, 9090));
+}
+#[test]
+fn http_allow_origin_flag() {
+    CommandLineTest::new()
+        .flag("http-allow-origin", Some("http://localhost:9009"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.http_api.allow_origin,
+                Some("http://localhost:9009".to_string())
+            );
+        });
+}
+#[test]
+fn http_allow_origin_all_flag() {
+    CommandLineTest::new()
+        .flag("http-allow-origin", Some("*"))
+        .run()
+        .with_config(|config| assert_eq!(config.http_api.allow_origin, Some("*".to_string())));
+}
+
+// Tests for Metrics flags.
+#[test]
+fn metrics_flag() {
+    CommandLineTest::new()
+        .flag("metrics", None)
+        .run()
+        .with_config(|config| assert!(config.http_metrics.enabled));
+}
+#[test]
+fn index_resolution_interval_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.index_resolution_interval, Duration::from_secs(60))
+    });
+}
+#[test]
+fn index_resolution_interval_flag() {
+    CommandLineTest::new()
+        .flag("index-resolution-interval", Some("384"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.index_resolution_interval, Duration::from_secs(384))
+        });
+}
+#[test]
+#[should_panic]
+fn index_resolution_interval_zero() {
+    CommandLineTest::new()
+        .flag("index-resolution-interval", Some("0"))
+        .run();
+}
+#[test]
+fn activation_poll_epochs_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.activation_poll_epochs, 1));
+}
+#[test]
+fn activation_poll_epochs_flag() {
+    CommandLineTest::new()
+        .flag("activation-poll-epochs", Some("4"))
+        .run()
+        .with_config(|config| assert_eq!(config.activation_poll_epochs, 4));
+}
+#[test]
+fn duties_batch_size_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.duties_batch_size, 4096));
+}
+#[test]
+fn duties_batch_size_flag() {
+    CommandLineTest::new()
+        .flag("duties-batch-size", Some("512"))
+        .run()
+        .with_config(|config| assert_eq!(config.duties_batch_size, 512));
+}
+#[test]
+#[should_panic]
+fn duties_batch_size_zero() {
+    CommandLineTest::new()
+        .flag("duties-batch-size", Some("0"))
+        .run();
+}
+#[test]
+fn metrics_validator_bucketing_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(
+            config.metrics_validator_bucketing,
+            MetricsValidatorBucketing::Full
+        )
+    });
+}
+#[test]
+fn metrics_validator_bucketing_flag() {
+    CommandLineTest::new()
+        .flag("metrics-validator-bucketing", Some("bucketed"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.metrics_validator_bucketing,
+                MetricsValidatorBucketing::Bucketed
+            )
+        });
+}
+#[test]
+#[should_panic]
+fn metrics_validator_bucketing_invalid() {
+    CommandLineTest::new()
+        .flag("metrics-validator-bucketing", Some("status"))
+        .run();
+}
+#[test]
+fn min_balance_alert_gwei_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.min_balance_alert_gwei, None));
+}
+#[test]
+fn min_balance_alert_gwei_flag() {
+    CommandLineTest::new()
+        .flag("min-balance-alert-gwei", Some("31000000000"))
+        .run()
+        .with_config(|config| assert_eq!(config.min_balance_alert_gwei, Some(31_000_000_000)));
+}
+#[test]
+#[should_panic]
+fn min_balance_alert_gwei_invalid_value() {
+    CommandLineTest::new()
+        .flag("min-balance-alert-gwei", Some("two"))
+        .run();
+}
+#[test]
+fn auto_disable_inactive_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.auto_disable_inactive));
+}
+#[test]
+fn auto_disable_inactive_flag() {
+    CommandLineTest::new()
+        .flag("auto-disable-inactive", None)
+        .run()
+        .with_config(|config| assert!(config.auto_disable_inactive));
+}
+#[test]
+fn metrics_address_flag() {
+    let addr = "127.0.0.99".parse::<IpAddr>().unwrap();
+    CommandLineTest::new()
+        .flag("metrics-address", Some("127.0.0.99"))
+        .run()
+        .with_config(|config| assert_eq!(config.http_metrics.listen_addr, addr));
+}
+#[test]
+fn metrics_address_ipv6_flag() {
+    let addr = "::1".parse::<IpAddr>().unwrap();
+    CommandLineTest::new()
+        .flag("metrics-address", Some("::1"))
+        .run()
+        .with_config(|config| assert_eq!(config.http_metrics.listen_addr, addr));
+}
+#[test]
+fn metrics_port_flag() {
+    CommandLineTest::new()
+        .flag("metrics-port", Some("9090"))
+        .run()
+        .with_config(|config| assert_eq!(config.http_metrics.listen_port, 9090));
+}
+#[test]
+fn metrics_allow_origin_flag() {
+    CommandLineTest::new()
+        .flag("metrics-allow-origin", Some("http://localhost:9009"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.http_metrics.allow_origin,
+                Some("http://localhost:9009".to_string())
+            );
+        });
+}
+#[test]
+fn metrics_allow_origin_all_flag() {
+    CommandLineTest::new()
+        .flag("metrics-allow-origin", Some("*"))
+        .run()
+        .with_config(|config| assert_eq!(config.http_metrics.allow_origin, Some("*".to_string())));
+}
+#[test]
+pub fn malloc_tuning_flag() {
+    CommandLineTest::new()
+        .flag("disable-malloc-tuning", None)
+        .run()
+        .with_config(|config| assert_eq!(config.http_metrics.allocator_metrics_enabled, false));
+}
+#[test]
+pub fn malloc_tuning_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.http_metrics.allocator_metrics_enabled, true));
+}
+#[test]
+fn doppelganger_protection_flag() {
+    CommandLineTest::new()
+        .flag("enable-doppelganger-protection", None)
+        .run()
+        .with_config(|config| assert!(config.enable_doppelganger_protection));
+}
+#[test]
+fn no_doppelganger_protection_flag() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.enable_doppelganger_protection));
+}
+#[test]
+fn continuous_doppelganger_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(!config.continuous_doppelganger);
+        assert!(!config.continuous_doppelganger_pause);
+    });
+}
+#[test]
+fn continuous_doppelganger_flags() {
+    CommandLineTest::new()
+        .flag("enable-doppelganger-protection", None)
+        .flag("continuous-doppelganger", None)
+        .flag("continuous-doppelganger-pause", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.continuous_doppelganger);
+            assert!(config.continuous_doppelganger_pause);
+        });
+}
+#[test]
+fn doppelganger_disable_detected_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.doppelganger_disable_detected));
+}
+#[test]
+fn doppelganger_disable_detected_flag() {
+    CommandLineTest::new()
+        .flag("enable-doppelganger-protection", None)
+        .flag("doppelganger-disable-detected", None)
+        .run()
+        .with_config(|config| assert!(config.doppelganger_disable_detected));
+}
+#[test]
+#[should_panic]
+fn doppelganger_disable_detected_without_doppelganger_protection() {
+    CommandLineTest::new()
+        .flag("doppelganger-disable-detected", None)
+        .run();
+}
+#[test]
+#[should_panic]
+fn continuous_doppelganger_without_doppelganger_protection() {
+    CommandLineTest::new()
+        .flag("continuous-doppelganger", None)
+        .run();
+}
+#[test]
+fn attestation_deadline_fraction_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.attestation_deadline_fraction, 1.0 / 3.0));
+}
+#[test]
+fn attestation_deadline_fraction_flag() {
+    CommandLineTest::new()
+        .flag("attestation-deadline-fraction", Some("0.4"))
+        .run()
+        .with_config(|config| assert_eq!(config.attestation_deadline_fraction, 0.4));
+}
+#[test]
+#[should_panic]
+fn attestation_deadline_fraction_too_large() {
+    CommandLineTest::new()
+        .flag("attestation-deadline-fraction", Some("1.0"))
+        .run();
+}
+#[test]
+fn max_attestation_slot_lag_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.max_attestation_slot_lag, 2));
+}
+#[test]
+fn max_attestation_slot_lag_flag() {
+    CommandLineTest::new()
+        .flag("max-attestation-slot-lag", Some("8"))
+        .run()
+        .with_config(|config| assert_eq!(config.max_attestation_slot_lag, 8));
+}
+#[test]
+#[should_panic]
+fn max_attestation_slot_lag_invalid() {
+    CommandLineTest::new()
+        .flag("max-attestation-slot-lag", Some("two"))
+        .run();
+}
+#[test]
+fn max_pending_aggregations_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.max_pending_aggregations, 1_024));
+}
+#[test]
+fn max_pending_aggregations_flag() {
+    CommandLineTest::new()
+        .flag("max-pending-aggregations", Some("64"))
+        .run()
+        .with_config(|config| assert_eq!(config.max_pending_aggregations, 64));
+}
+#[test]
+#[should_panic]
+fn max_pending_aggregations_zero() {
+    CommandLineTest::new()
+        .flag("max-pending-aggregations", Some("0"))
+        .run();
+}
+#[test]
+fn attestation_publish_retry_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.attestation_publish_retry_nodes));
+}
+#[test]
+fn disable_attestation_publish_retry_flag() {
+    CommandLineTest::new()
+        .flag("disable-attestation-publish-retry", None)
+        .run()
+        .with_config(|config| assert!(!config.attestation_publish_retry_nodes));
+}
+#[test]
+fn verify_block_before_signing_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.verify_block_before_signing));
+}
+#[test]
+fn disable_block_verification_flag() {
+    CommandLineTest::new()
+        .flag("disable-block-verification", None)
+        .run()
+        .with_config(|config| assert!(!config.verify_block_before_signing));
+}
+#[test]
+fn verify_fee_recipient_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.verify_fee_recipient));
+}
+#[test]
+fn disable_fee_recipient_verification_flag() {
+    CommandLineTest::new()
+        .flag("disable-fee-recipient-verification", None)
+        .run()
+        .with_config(|config| assert!(!config.verify_fee_recipient));
+}
+#[test]
+fn optimistic_head_policy_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(config.refuse_proposal_on_optimistic);
+        assert!(!config.refuse_attestation_on_optimistic);
+    });
+}
+#[test]
+fn allow_proposal_on_optimistic_head_flag() {
+    CommandLineTest::new()
+        .flag("allow-proposal-on-optimistic-head", None)
+        .run()
+        .with_config(|config| assert!(!config.refuse_proposal_on_optimistic));
+}
+#[test]
+fn first_proposal_caution_epochs_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.first_proposal_caution_epochs, None));
+}
+#[test]
+fn first_proposal_caution_epochs_flag() {
+    CommandLineTest::new()
+        .flag("first-proposal-caution-epochs", Some("4"))
+        .run()
+        .with_config(|config| assert_eq!(config.first_proposal_caution_epochs, Some(4)));
+}
+#[test]
+#[should_panic]
+fn first_proposal_caution_epochs_zero() {
+    CommandLineTest::new()
+        .flag("first-proposal-caution-epochs", Some("0"))
+        .run();
+}
+#[test]
+fn refuse_attestation_on_optimistic_head_flag() {
+    CommandLineTest::new()
+        .flag("refuse-attestation-on-optimistic-head", None)
+        .run()
+        .with_config(|config| assert!(config.refuse_attestation_on_optimistic));
+}
+#[test]
+fn block_delay_ms() {
+    CommandLineTest::new()
+        .flag("block-delay-ms", Some("2000"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.block_delay,
+                Some(std::time::Duration::from_millis(2000))
+            )
+        });
+}
+#[test]
+fn no_block_delay_ms() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.block_delay, None));
+}
+#[test]
+#[should_panic]
+fn block_delay_ms_too_large() {
+    CommandLineTest::new()
+        .flag("block-delay-ms", Some("4001"))
+        .flag("seconds-per-slot-override", Some("12"))
+        .run();
+}
+#[test]
+fn block_max_transactions_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.block_max_transactions, None));
+}
+#[test]
+fn block_max_transactions_flag() {
+    CommandLineTest::new()
+        .flag("block-max-transactions", Some("10"))
+        .run()
+        .with_config(|config| assert_eq!(config.block_max_transactions, Some(10)));
+}
+#[test]
+#[should_panic]
+fn block_max_transactions_invalid() {
+    CommandLineTest::new()
+        .flag("block-max-transactions", Some("ten"))
+        .run();
+}
+#[test]
+fn block_prefetch_lead_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.block_prefetch_lead, None));
+}
+#[test]
+fn block_prefetch_lead_ms() {
+    CommandLineTest::new()
+        .flag("block-prefetch-lead-ms", Some("500"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.block_prefetch_lead, Some(Duration::from_millis(500)))
+        });
+}
+#[test]
+#[should_panic]
+fn block_prefetch_lead_ms_zero() {
+    CommandLineTest::new()
+        .flag("block-prefetch-lead-ms", Some("0"))
+        .run();
+}
+#[test]
+#[should_panic]
+fn block_prefetch_lead_ms_too_large() {
+    CommandLineTest::new()
+        .flag("block-prefetch-lead-ms", Some("4001"))
+        .flag("seconds-per-slot-override", Some("12"))
+        .run();
+}
+#[test]
+fn allow_large_block_delay_flag() {
+    CommandLineTest::new()
+        .flag("block-delay-ms", Some("4001"))
+        .flag("seconds-per-slot-override", Some("12"))
+        .flag("allow-large-block-delay", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.allow_large_block_delay);
+            assert_eq!(config.block_delay, Some(Duration::from_millis(4001)));
+        });
+}
+#[test]
+#[should_panic]
+fn allow_large_block_delay_without_block_delay() {
+    CommandLineTest::new()
+        .flag("allow-large-block-delay", None)
+        .run();
+}
+#[test]
+fn block_delay_slot_fraction_flag() {
+    CommandLineTest::new()
+        .flag("block-delay-slot-fraction", Some("0.25"))
+        .flag("seconds-per-slot-override", Some("12"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.block_delay_slot_fraction, Some(0.25));
+            assert_eq!(
+                config.effective_block_delay(12),
+                Ok(Some(Duration::from_secs(3)))
+            );
+        });
+}
+#[test]
+#[should_panic]
+fn block_delay_slot_fraction_too_large() {
+    CommandLineTest::new()
+        .flag("block-delay-slot-fraction", Some("1"))
+        .flag("allow-large-block-delay", None)
+        .run();
+}
+#[test]
+#[should_panic]
+fn block_delay_slot_fraction_conflicts_with_block_delay_ms() {
+    CommandLineTest::new()
+        .flag("block-delay-slot-fraction", Some("0.25"))
+        .flag("block-delay-ms", Some("2000"))
+        .run();
+}
+#[test]
+fn block_delay_attestation_deadline_offset_flag() {
+    CommandLineTest::new()
+        .flag("block-delay-attestation-deadline-offset-ms", Some("-1000"))
+        .flag("seconds-per-slot-override", Some("12"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.block_delay_attestation_deadline_offset_ms,
+                Some(-1000)
+            );
+            assert_eq!(
+                config.effective_block_delay(12),
+                Ok(Some(Duration::from_secs(3)))
+            );
+        });
+}
+#[test]
+#[should_panic]
+fn block_delay_attestation_deadline_offset_beyond_slot() {
+    CommandLineTest::new()
+        .flag("block-delay-attestation-deadline-offset-ms", Some("8000"))
+        .flag("seconds-per-slot-override", Some("12"))
+        .flag("allow-large-block-delay", None)
+        .run();
+}
+#[test]
+fn no_gas_limit_flag() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.gas_limit.is_none()));
+}
+#[test]
+fn gas_limit_flag() {
+    CommandLineTest::new()
+        .flag("gas-limit", Some("600"))
+        .flag("builder-proposals", None)
+        .run()
+        .with_config(|config| assert_eq!(config.gas_limit, Some(600)));
+}
+#[test]
+fn gas_limit_bounds_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.gas_limit_min, 10_000_000);
+        assert_eq!(config.gas_limit_max, 100_000_000);
+        assert_eq!(
+            config.on_gas_limit_out_of_range,
+            OnGasLimitOutOfRange::Clamp
+        );
+    });
+}
+#[test]
+fn gas_limit_bounds_flags() {
+    CommandLineTest::new()
+        .flag("gas-limit-min", Some("20000000"))
+        .flag("gas-limit-max", Some("40000000"))
+        .flag("on-gas-limit-out-of-range", Some("reject"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.gas_limit_min, 20_000_000);
+            assert_eq!(config.gas_limit_max, 40_000_000);
+            assert_eq!(
+                config.on_gas_limit_out_of_range,
+                OnGasLimitOutOfRange::Reject
+            );
+        });
+}
+#[test]
+#[should_panic]
+fn gas_limit_min_greater_than_max() {
+    CommandLineTest::new()
+        .flag("gas-limit-min", Some("40000000"))
+        .flag("gas-limit-max", Some("20000000"))
+        .run();
+}
+#[test]
+fn gas_limit_out_of_range_clamp() {
+    CommandLineTest::new()
+        .flag("gas-limit", Some("600"))
+        .flag("builder-proposals", None)
+        .flag("on-gas-limit-out-of-range", Some("clamp"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.gas_limit, Some(600));
+            assert_eq!(config.gas_limit_bounds().apply(600), Some(10_000_000));
+        });
+}
+#[test]
+#[should_panic]
+fn gas_limit_out_of_range_reject() {
+    CommandLineTest::new()
+        .flag("gas-limit", Some("600"))
+        .flag("builder-proposals", None)
+        .flag("on-gas-limit-out-of-range", Some("reject"))
+        .run();
+}
+#[test]
+fn no_builder_proposals_flag() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.builder_proposals));
+}
+#[test]
+fn builder_proposals_flag() {
+    CommandLineTest::new()
+        .flag("builder-proposals", None)
+        .run()
+        .with_config(|config| assert!(config.builder_proposals));
+}
+#[test]
+fn builder_unavailable_action_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(
+            config.builder_unavailable_action,
+            BuilderUnavailableAction::Local
+        )
+    });
+}
+#[test]
+fn builder_unavailable_action_flag() {
+    for (value, expected) in [
+        ("local", BuilderUnavailableAction::Local),
+        ("skip", BuilderUnavailableAction::Skip),
+    ] {
+        CommandLineTest::new()
+            .flag("builder-unavailable-action", Some(value))
+            .run()
+            .with_config(|config| assert_eq!(config.builder_unavailable_action, expected));
+    }
+}
+#[test]
+#[should_panic]
+fn builder_unavailable_action_invalid_value() {
+    CommandLineTest::new()
+        .flag("builder-unavailable-action", Some("full"))
+        .run();
+}
+#[test]
+fn no_builder_registration_timestamp_override_flag() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.builder_registration_timestamp_override.is_none()));
+}
+#[test]
+fn builder_registration_timestamp_override_flag() {
+    CommandLineTest::new()
+        .flag("builder-registration-timestamp-override", Some("100"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.builder_registration_timestamp_override, Some(100))
+        });
+}
+#[test]
+fn builder_registration_timestamp_override_file_flag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("timestamps.txt")).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("{}: 100", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "builder-registration-timestamp-override-file",
+            dir.path().join("timestamps.txt").as_os_str().to_str(),
+        )
+        .run()
+        .with_config(|config| {
+            let timestamp_file = config
+                .builder_registration_timestamp_override_file
+                .as_ref()
+                .unwrap();
+            assert_eq!(timestamp_file.get(&pubkeybytes), Some(100));
+            assert_eq!(
+                timestamp_file.get(&PublicKeyBytes::from(Keypair::random().pk)),
+                None
+            );
+        });
+}
+#[test]
+#[should_panic]
+fn builder_registration_timestamp_override_file_invalid_timestamp() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("timestamps.txt")).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("{}: not-a-timestamp", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "builder-registration-timestamp-override-file",
+            dir.path().join("timestamps.txt").as_os_str().to_str(),
+        )
+        .run();
+}
+#[test]
+fn relay_tag_file_flag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("relay_tags.txt")).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("{}: regulated", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "relay-tag-file",
+            dir.path().join("relay_tags.txt").as_os_str().to_str(),
+        )
+        .run()
+        .with_config(|config| {
+            let relay_tag_file = config.relay_tag_file.as_ref().unwrap();
+            assert_eq!(relay_tag_file.get(&pubkeybytes), Some("regulated"));
+            assert_eq!(
+                relay_tag_file.get(&PublicKeyBytes::from(Keypair::random().pk)),
+                None
+            );
+        });
+}
+#[test]
+fn sync_committee_opt_out_file_flag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let path = dir.path().join("sync_committee_opt_out.txt");
+    let mut file = File::create(&path).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("# bandwidth constrained\n{}\n", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag("sync-committee-opt-out-file", path.as_os_str().to_str())
+        .run()
+        .with_config(|config| {
+            let opt_out_file = config.sync_committee_opt_out_file.as_ref().unwrap();
+            assert_eq!(opt_out_file.len(), 1);
+            assert!(opt_out_file.contains(&pubkeybytes));
+        });
+}
+#[test]
+fn no_sync_committee_opt_out_file_flag() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.sync_committee_opt_out_file.is_none()));
+}
+#[test]
+#[should_panic]
+fn sync_committee_opt_out_file_invalid_pubkey() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let path = dir.path().join("sync_committee_opt_out.txt");
+    let mut file = File::create(&path).expect("Unable to create file");
+    file.write_all(b"0xdeadbeef\n")
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag("sync-committee-opt-out-file", path.as_os_str().to_str())
+        .run();
+}
+#[test]
+fn sync_committee_broadcast_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.sync_committee_broadcast));
+}
+#[test]
+fn sync_committee_broadcast_flag() {
+    CommandLineTest::new()
+        .flag("sync-committee-broadcast", None)
+        .run()
+        .with_config(|config| assert!(config.sync_committee_broadcast));
+}
+#[test]
+fn builder_proposals_enabled_file_flag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let path = dir.path().join("builder_proposals.txt");
+    let mut file = File::create(&path).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("# mev-boost pilot\n{}\n", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag("builder-proposals-enabled-file", path.as_os_str().to_str())
+        .run()
+        .with_config(|config| {
+            let enabled_file = config.builder_proposals_enabled_pubkeys.as_ref().unwrap();
+            assert_eq!(enabled_file.len(), 1);
+            assert!(enabled_file.contains(&pubkeybytes));
+        });
+}
+#[test]
+fn no_builder_proposals_enabled_file_flag() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.builder_proposals_enabled_pubkeys.is_none()));
+}
+#[test]
+#[should_panic]
+fn builder_proposals_enabled_file_invalid_pubkey() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let path = dir.path().join("builder_proposals.txt");
+    let mut file = File::create(&path).expect("Unable to create file");
+    file.write_all(b"0xdeadbeef\n")
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag("builder-proposals-enabled-file", path.as_os_str().to_str())
+        .run();
+}
+#[test]
+#[should_panic]
+fn builder_proposals_enabled_file_conflicts_with_builder_proposals() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let path = dir.path().join("builder_proposals.txt");
+    File::create(&path).expect("Unable to create file");
+    CommandLineTest::new()
+        .flag("builder-proposals", None)
+        .flag("builder-proposals-enabled-file", path.as_os_str().to_str())
+        .run();
+}
+#[test]
+fn no_relay_tag_file_flag() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.relay_tag_file.is_none()));
+}
+#[test]
+#[should_panic]
+fn relay_tag_file_invalid_tag() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let mut file = File::create(dir.path().join("relay_tags.txt")).expect("Unable to create file");
+    let pubkeybytes = PublicKeyBytes::from(Keypair::random().pk);
+    let contents = format!("{}: not/a/tag", pubkeybytes);
+    file.write_all(contents.as_bytes())
+        .expect("Unable to write to file");
+    CommandLineTest::new()
+        .flag(
+            "relay-tag-file",
+            dir.path().join("relay_tags.txt").as_os_str().to_str(),
+        )
+        .run();
+}
+#[test]
+fn monitoring_endpoint() {
+    CommandLineTest::new()
+        .flag("monitoring-endpoint", Some("http://example:8000"))
+        .flag("monitoring-endpoint-period", Some("30"))
+        .run()
+        .with_config(|config| {
+            let api_conf = config.monitoring_api.as_ref().unwrap();
+            assert_eq!(api_conf.monitoring_endpoint.as_str(), "http://example:8000");
+            assert_eq!(api_conf.update_period_secs, Some(30));
+        });
+}
+#[test]
+fn monitoring_endpoint_metrics_default() {
+    CommandLineTest::new()
+        .flag("monitoring-endpoint", Some("http://example:8000"))
+        .run()
+        .with_config(|config| {
+            let api_conf = config.monitoring_api.as_ref().unwrap();
+            assert!(api_conf.process_metrics);
+            assert!(api_conf.system_metrics);
+            assert!(api_conf.validator_metrics);
+            assert_eq!(api_conf.validator_metrics_min_validators, 0);
+        });
+}
+#[test]
+fn monitoring_endpoint_exclude_metrics() {
+    CommandLineTest::new()
+        .flag("monitoring-endpoint", Some("http://example:8000"))
+        .flag("monitoring-endpoint-exclude", Some("process,system"))
+        .run()
+        .with_config(|config| {
+            let api_conf = config.monitoring_api.as_ref().unwrap();
+            assert!(!api_conf.process_metrics);
+            assert!(!api_conf.system_metrics);
+            assert!(api_conf.validator_metrics);
+        });
+}
+#[test]
+fn monitoring_endpoint_min_validators() {
+    CommandLineTest::new()
+        .flag("monitoring-endpoint", Some("http://example:8000"))
+        .flag("monitoring-endpoint-min-validators", Some("10"))
+        .run()
+        .with_config(|config| {
+            let api_conf = config.monitoring_api.as_ref().unwrap();
+            assert_eq!(api_conf.validator_metrics_min_validators, 10);
+        });
+}
+#[test]
+fn monitoring_endpoint_redact_validator_ids_default() {
+    CommandLineTest::new()
+        .flag("monitoring-endpoint", Some("http://example:8000"))
+        .run()
+        .with_config(|config| {
+            assert!(!config.monitoring_api.as_ref().unwrap().redact_validator_ids);
+        });
+}
+#[test]
+fn monitoring_endpoint_redact_validator_ids() {
+    CommandLineTest::new()
+        .flag("monitoring-endpoint", Some("http://example:8000"))
+        .flag("monitoring-endpoint-redact-validator-ids", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.monitoring_api.as_ref().unwrap().redact_validator_ids);
+        });
+}
+#[test]
+#[should_panic]
+fn monitoring_endpoint_unknown_category() {
+    CommandLineTest::new()
+        .flag("monitoring-endpoint", Some("http://example:8000"))
+        .flag("monitoring-endpoint-exclude", Some("beacon"))
+        .run();
+}
+#[test]
+#[should_panic]
+fn monitoring_endpoint_period_zero() {
+    CommandLineTest::new()
+        .flag("monitoring-endpoint", Some("http://example:8000"))
+        .flag("monitoring-endpoint-period", Some("0"))
+        .run();
+}
+#[test]
+fn disable_run_on_all_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(!config.disable_run_on_all);
+    });
+}
+
+#[test]
+fn disable_run_on_all() {
+    CommandLineTest::new()
+        .flag("disable-run-on-all", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.disable_run_on_all);
+        });
+}
+
+#[test]
+fn lenient_bn_responses_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(config.lenient_bn_responses));
+}
+
+#[test]
+fn disable_lenient_bn_responses_flag() {
+    CommandLineTest::new()
+        .flag("disable-lenient-bn-responses", None)
+        .run()
+        .with_config(|config| assert!(!config.lenient_bn_responses));
+}
+
+#[test]
+fn require_matching_genesis_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(config.require_matching_genesis);
+    });
+}
+
+#[test]
+fn allow_mismatched_genesis_flag() {
+    CommandLineTest::new()
+        .flag("allow-mismatched-genesis", None)
+        .run()
+        .with_config(|config| {
+            assert!(!config.require_matching_genesis);
+        });
+}
+
+#[test]
+fn beacon_node_consensus_check_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(!config.beacon_node_consensus_check);
+    });
+}
+
+#[test]
+fn beacon_node_consensus_check() {
+    CommandLineTest::new()
+        .flag("beacon-node-consensus-check", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.beacon_node_consensus_check);
+        });
+}
+
+#[test]
+fn beacon_node_selection_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.beacon_node_selection, BeaconNodeSelection::Priority);
+    });
+}
+
+#[test]
+fn beacon_node_selection_flag() {
+    for (value, selection) in [
+        ("priority", BeaconNodeSelection::Priority),
+        ("round_robin", BeaconNodeSelection::RoundRobin),
+        ("sticky", BeaconNodeSelection::Sticky),
+    ] {
+        CommandLineTest::new()
+            .flag("beacon-node-selection", Some(value))
+            .run()
+            .with_config(|config| {
+                assert_eq!(config.beacon_node_selection, selection);
+            });
+    }
+}
+
+#[test]
+#[should_panic]
+fn beacon_node_selection_invalid() {
+    CommandLineTest::new()
+        .flag("beacon-node-selection", Some("random"))
+        .run();
+}
+
+#[test]
+fn beacon_node_startup_timeout_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.beacon_node_startup_timeout, None);
+    });
+}
+
+#[test]
+fn beacon_node_startup_timeout_flag() {
+    CommandLineTest::new()
+        .flag("beacon-node-startup-timeout", Some("120"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(
+                config.beacon_node_startup_timeout,
+                Some(Duration::from_secs(120))
+            );
+        });
+}
+
+#[test]
+#[should_panic]
+fn beacon_node_startup_timeout_invalid() {
+    CommandLineTest::new()
+        .flag("beacon-node-startup-timeout", Some("2m"))
+        .run();
+}
+
+#[test]
+fn detect_config_changes_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(!config.detect_config_changes);
+    });
+}
+
+#[test]
+fn detect_config_changes_flag() {
+    CommandLineTest::new()
+        .flag("detect-config-changes", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.detect_config_changes);
+        });
+}
+
+#[test]
+fn require_homogeneous_beacon_nodes_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(!config.require_homogeneous_beacon_nodes);
+    });
+}
+
+#[test]
+fn require_homogeneous_beacon_nodes_flag() {
+    CommandLineTest::new()
+        .flag("require-homogeneous-beacon-nodes", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.require_homogeneous_beacon_nodes);
+        });
+}
+
+#[test]
+fn persist_beacon_node_health_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(!config.persist_beacon_node_health);
+    });
+}
+
+#[test]
+fn persist_beacon_node_health_flag() {
+    CommandLineTest::new()
+        .flag("persist-beacon-node-health", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.persist_beacon_node_health);
+        });
+}
+
+#[test]
+fn latency_measurement_service() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(config.enable_latency_measurement_service);
+    });
+    CommandLineTest::new()
+        .flag("latency-measurement-service", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.enable_latency_measurement_service);
+        });
+    CommandLineTest::new()
+        .flag("latency-measurement-service", Some("true"))
+        .run()
+        .with_config(|config| {
+            assert!(config.enable_latency_measurement_service);
+        });
+    CommandLineTest::new()
+        .flag("latency-measurement-service", Some("false"))
+        .run()
+        .with_config(|config| {
+            assert!(!config.enable_latency_measurement_service);
+        });
+}
+#[test]
+fn latency_sample_interval_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.latency_sample_interval, Duration::from_secs(12));
+        assert_eq!(config.latency_sample_window, 32);
+    });
+}
+#[test]
+fn latency_sample_interval_flag() {
+    CommandLineTest::new()
+        .flag("latency-sample-interval", Some("60"))
+        .flag("latency-sample-window", Some("8"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.latency_sample_interval, Duration::from_secs(60));
+            assert_eq!(config.latency_sample_window, 8);
+        });
+}
+#[test]
+#[should_panic]
+fn latency_sample_interval_zero() {
+    CommandLineTest::new()
+        .flag("latency-sample-interval", Some("0"))
+        .run();
+}
+#[test]
+#[should_panic]
+fn latency_sample_window_zero() {
+    CommandLineTest::new()
+        .flag("latency-sample-window", Some("0"))
+        .run();
+}
+
+#[test]
+fn min_free_disk_bytes_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert_eq!(config.min_free_disk_bytes, 536_870_912));
+}
+#[test]
+fn min_free_disk_bytes_flag() {
+    CommandLineTest::new()
+        .flag("min-free-disk-bytes", Some("1073741824"))
+        .run()
+        .with_config(|config| assert_eq!(config.min_free_disk_bytes, 1_073_741_824));
+}
+#[test]
+fn min_free_disk_bytes_zero() {
+    CommandLineTest::new()
+        .flag("min-free-disk-bytes", Some("0"))
+        .run()
+        .with_config(|config| assert_eq!(config.min_free_disk_bytes, 0));
+}
+#[test]
+fn slashing_protection_pruning_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.slashing_protection_prune_interval, None);
+        assert_eq!(config.slashing_protection_retention_epochs, 512);
+    });
+}
+#[test]
+fn slashing_protection_pruning_flags() {
+    CommandLineTest::new()
+        .flag("slashing-protection-prune-interval", Some("32"))
+        .flag("slashing-protection-retention-epochs", Some("4096"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.slashing_protection_prune_interval, Some(32));
+            assert_eq!(config.slashing_protection_retention_epochs, 4096);
+        });
+}
+#[test]
+#[should_panic]
+fn slashing_protection_prune_interval_zero() {
+    CommandLineTest::new()
+        .flag("slashing-protection-prune-interval", Some("0"))
+        .run();
+}
+#[test]
+#[should_panic]
+fn slashing_protection_retention_epochs_too_low() {
+    CommandLineTest::new()
+        .flag("slashing-protection-retention-epochs", Some("511"))
+        .run();
+}
+#[test]
+fn strict_dir_permissions_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.strict_dir_permissions));
+}
+#[test]
+fn strict_dir_permissions_flag() {
+    CommandLineTest::new()
+        .flag("strict-dir-permissions", None)
+        .run()
+        .with_config(|config| assert!(config.strict_dir_permissions));
+}
+#[test]
+fn low_memory_mode_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(!config.low_memory_mode);
+        assert_eq!(config.max_pending_aggregations, 1_024);
+        assert_eq!(config.validator_registration_batch_size, 500);
+    });
+}
+#[test]
+fn low_memory_mode_flag() {
+    CommandLineTest::new()
+        .flag("low-memory-mode", None)
+        .run()
+        .with_config(|config| {
+            assert!(config.low_memory_mode);
+            assert_eq!(config.slashing_protection_pool_size, Some(1));
+            assert_eq!(config.max_concurrent_signings, Some(4));
+            assert_eq!(config.max_pending_aggregations, 64);
+            assert_eq!(config.latency_sample_window, 8);
+            assert_eq!(config.validator_registration_batch_size, 100);
+        });
+}
+#[test]
+fn low_memory_mode_individual_overrides() {
+    CommandLineTest::new()
+        .flag("low-memory-mode", None)
+        .flag("max-pending-aggregations", Some("256"))
+        .flag("validator-registration-batch-size", Some("500"))
+        .run()
+        .with_config(|config| {
+            assert_eq!(config.max_pending_aggregations, 256);
+            assert_eq!(config.validator_registration_batch_size, 500);
+            assert_eq!(config.latency_sample_window, 8);
+        });
+}
+#[test]
+fn require_synced_clock_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.require_synced_clock));
+}
+#[test]
+fn require_synced_clock_flag() {
+    CommandLineTest::new()
+        .flag("require-synced-clock", None)
+        .run()
+        .with_config(|config| assert!(config.require_synced_clock));
+}
+#[test]
+fn strict_default_dirs_default() {
+    CommandLineTest::new()
+        .run()
+        .with_config(|config| assert!(!config.strict_default_dirs));
+}
+#[test]
+fn strict_default_dirs_flag_with_datadir() {
+    CommandLineTest::new()
+        .flag("network", Some("mainnet"))
+        .flag("strict-default-dirs", None)
+        .run()
+        .with_config(|config| assert!(config.strict_default_dirs));
+}
+#[test]
+fn strict_default_dirs_flag_with_validators_dir() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    CommandLineTest::new()
+        .flag("network", Some("mainnet"))
+        .flag("strict-default-dirs", None)
+        .flag("validators-dir", dir.path().join("validators").to_str())
+        .flag("secrets-dir", dir.path().join("secrets").to_str())
+        .run_with_no_datadir()
+        .with_config(|config| {
+            assert!(config.strict_default_dirs);
+            assert_eq!(config.validator_dir, dir.path().join("validators"));
         });
 }
-
-// Tests for HTTP flags.
 #[test]
-fn http_flag() {
+#[should_panic]
+fn strict_default_dirs_flag_with_default_dirs() {
     CommandLineTest::new()
-        .flag("http", None)
-        .run()
-        .with_config(|config| assert!(config.http_api.enabled));
+        .flag("network", Some("mainnet"))
+        .flag("strict-default-dirs", None)
+        .run_with_no_datadir();
 }
+
 #[test]
-fn http_address_flag() {
-    let addr = "127.0.0.99".parse::<IpAddr>().unwrap();
-    CommandLineTest::new()
-        .flag("http-address", Some("127.0.0.99"))
-        .flag("unencrypted-http-transport", None)
-        .run()
-        .with_config(|config| assert_eq!(config.http_api.listen_addr, addr));
+fn dir_access_timeout_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.dir_access_timeout, Duration::from_secs(5));
+    });
 }
 #[test]
-fn http_address_ipv6_flag() {
-    let addr = "::1".parse::<IpAddr>().unwrap();
+fn dir_access_timeout_flag() {
     CommandLineTest::new()
-        .flag("http-address", Some("::1"))
-        .flag("unencrypted-http-transport", None)
+        .flag("dir-access-timeout", Some("30"))
         .run()
-        .with_config(|config| assert_eq!(config.http_api.listen_addr, addr));
+        .with_config(|config| {
+            assert_eq!(config.dir_access_timeout, Duration::from_secs(30));
+        });
 }
 #[test]
 #[should_panic]
-fn missing_unencrypted_http_transport_flag() {
-    let addr = "127.0.0.99".parse::<IpAddr>().unwrap();
+fn dir_access_timeout_zero() {
     CommandLineTest::new()
-        .flag("http-address", Some("127.0.0.99"))
-        .run()
-        .with_config(|config| assert_eq!(config.http_api.listen_addr, addr));
+        .flag("dir-access-timeout", Some("0"))
+        .run();
 }
 #[test]
-fn http_port_flag() {
+#[should_panic]
+fn secrets_dir_not_a_directory() {
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let secrets_file = dir.path().join("secrets");
+    File::create(&secrets_file).expect("Unable to create file");
     CommandLineTest::new()
-        .flag("http-port", Some("9090"))
-        .run()
-        .with_config(|config| assert_eq!(config.http_api.listen_port, 9090));
+        .flag("validators-dir", dir.path().join("validators").to_str())
+        .flag("secrets-dir", secrets_file.to_str())
+        .run_with_no_datadir();
 }
+
 #[test]
-fn http_allow_origin_flag() {
+fn validator_registration_batch_size() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.validator_registration_batch_size, 500);
+    });
     CommandLineTest::new()
-        .flag("http-allow-origin", Some("http://localhost:9009"))
+        .flag("validator-registration-batch-size", Some("100"))
         .run()
         .with_config(|config| {
-            assert_eq!(
-                config.http_api.allow_origin,
-                Some("http://localhost:9009".to_string())
-            );
+            assert_eq!(config.validator_registration_batch_size, 100);
         });
 }
+
 #[test]
-fn http_allow_origin_all_flag() {
+#[should_panic]
+fn validator_registration_batch_size_zero_value() {
     CommandLineTest::new()
-        .flag("http-allow-origin", Some("*"))
-        .run()
-        .with_config(|config| assert_eq!(config.http_api.allow_origin, Some("*".to_string())));
+        .flag("validator-registration-batch-size", Some("0"))
+        .run();
 }
 
-// Tests for Metrics flags.
 #[test]
-fn metrics_flag() {
+fn print_duties_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(!config.print_duties);
+        assert!(!config.exit_after_print_duties);
+    });
+}
+#[test]
+fn print_duties_flag() {
     CommandLineTest::new()
-        .flag("metrics", None)
+        .flag("print-duties", None)
         .run()
-        .with_config(|config| assert!(config.http_metrics.enabled));
+        .with_config(|config| {
+            assert!(config.print_duties);
+            assert!(!config.exit_after_print_duties);
+        });
 }
 #[test]
-fn metrics_address_flag() {
-    let addr = "127.0.0.99".parse::<IpAddr>().unwrap();
+fn exit_after_print_duties_flag() {
     CommandLineTest::new()
-        .flag("metrics-address", Some("127.0.0.99"))
+        .flag("print-duties", None)
+        .flag("exit-after-print-duties", None)
         .run()
-        .with_config(|config| assert_eq!(config.http_metrics.listen_addr, addr));
+        .with_config(|config| {
+            assert!(config.print_duties);
+            assert!(config.exit_after_print_duties);
+        });
 }
 #[test]
-fn metrics_address_ipv6_flag() {
-    let addr = "::1".parse::<IpAddr>().unwrap();
+#[should_panic]
+fn exit_after_print_duties_without_print_duties() {
     CommandLineTest::new()
-        .flag("metrics-address", Some("::1"))
-        .run()
-        .with_config(|config| assert_eq!(config.http_metrics.listen_addr, addr));
+        .flag("exit-after-print-duties", None)
+        .run();
 }
+
 #[test]
-fn metrics_port_flag() {
+fn validator_registration_max_body_bytes() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.validator_registration_max_body_bytes, None);
+    });
     CommandLineTest::new()
-        .flag("metrics-port", Some("9090"))
+        .flag("validator-registration-max-body-bytes", Some("1048576"))
         .run()
-        .with_config(|config| assert_eq!(config.http_metrics.listen_port, 9090));
+        .with_config(|config| {
+            assert_eq!(
+                config.validator_registration_max_body_bytes,
+                Some(1_048_576)
+            );
+        });
 }
+
 #[test]
-fn metrics_allow_origin_flag() {
+#[should_panic]
+fn validator_registration_max_body_bytes_zero_value() {
     CommandLineTest::new()
-        .flag("metrics-allow-origin", Some("http://localhost:9009"))
+        .flag("validator-registration-max-body-bytes", Some("0"))
+        .run();
+}
+
+#[test]
+fn validator_registration_refresh_interval() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.validator_registration_refresh_interval, None);
+    });
+    CommandLineTest::new()
+        .flag("validator-registration-refresh-interval", Some("3840"))
         .run()
         .with_config(|config| {
             assert_eq!(
-                config.http_metrics.allow_origin,
-                Some("http://localhost:9009".to_string())
+                config.validator_registration_refresh_interval,
+                Some(Duration::from_secs(3840))
             );
         });
 }
+
 #[test]
-fn metrics_allow_origin_all_flag() {
+#[should_panic]
+fn validator_registration_refresh_interval_below_minimum() {
     CommandLineTest::new()
-        .flag("metrics-allow-origin", Some("*"))
-        .run()
-        .with_config(|config| assert_eq!(config.http_metrics.allow_origin, Some("*".to_string())));
+        .flag("validator-registration-refresh-interval", Some("59"))
+        .run();
 }
+
 #[test]
-pub fn malloc_tuning_flag() {
+fn reregister_on_bn_restart_default() {
     CommandLineTest::new()
-        .flag("disable-malloc-tuning", None)
         .run()
-        .with_config(|config| assert_eq!(config.http_metrics.allocator_metrics_enabled, false));
+        .with_config(|config| assert!(!config.reregister_on_bn_restart));
 }
+
 #[test]
-pub fn malloc_tuning_default() {
+fn reregister_on_bn_restart_flag() {
     CommandLineTest::new()
+        .flag("reregister-on-bn-restart", None)
         .run()
-        .with_config(|config| assert_eq!(config.http_metrics.allocator_metrics_enabled, true));
+        .with_config(|config| assert!(config.reregister_on_bn_restart));
 }
+
 #[test]
-fn doppelganger_protection_flag() {
+fn canary_validator_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert!(config.canary_validator.is_none());
+    });
+}
+
+#[test]
+fn canary_validator_flag() {
+    let pk = Keypair::random().pk;
     CommandLineTest::new()
-        .flag("enable-doppelganger-protection", None)
+        .flag("canary-validator", Some(&pk.as_hex_string()))
         .run()
-        .with_config(|config| assert!(config.enable_doppelganger_protection));
+        .with_config(|config| {
+            assert_eq!(config.canary_validator, Some(pk.clone()));
+        });
 }
+
 #[test]
-fn no_doppelganger_protection_flag() {
+#[should_panic]
+fn canary_validator_invalid_pubkey() {
+    CommandLineTest::new()
+        .flag("canary-validator", Some("0x1234"))
+        .run();
+}
+
+#[test]
+fn startup_warmup_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.startup_warmup, None);
+    });
+}
+
+#[test]
+fn startup_warmup_flag() {
     CommandLineTest::new()
+        .flag("startup-warmup", Some("30"))
         .run()
-        .with_config(|config| assert!(!config.enable_doppelganger_protection));
+        .with_config(|config| {
+            assert_eq!(config.startup_warmup, Some(Duration::from_secs(30)));
+        });
 }
+
 #[test]
-fn block_delay_ms() {
+fn slashing_protection_retries_default() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.slashing_protection_retries, 3);
+        assert_eq!(
+            config.slashing_protection_retry_delay,
+            Duration::from_millis(50)
+        );
+    });
+}
+
+#[test]
+fn slashing_protection_retries_flags() {
     CommandLineTest::new()
-        .flag("block-delay-ms", Some("2000"))
+        .flag("slashing-protection-retries", Some("0"))
+        .flag("slashing-protection-retry-delay-ms", Some("200"))
         .run()
         .with_config(|config| {
+            assert_eq!(config.slashing_protection_retries, 0);
             assert_eq!(
-                config.block_delay,
-                Some(std::time::Duration::from_millis(2000))
-            )
+                config.slashing_protection_retry_delay,
+                Duration::from_millis(200)
+            );
         });
 }
+
 #[test]
-fn no_block_delay_ms() {
+fn slashing_protection_pool_size_default() {
     CommandLineTest::new()
         .run()
-        .with_config(|config| assert_eq!(config.block_delay, None));
+        .with_config(|config| assert_eq!(config.slashing_protection_pool_size, None));
 }
+
 #[test]
-fn no_gas_limit_flag() {
+fn slashing_protection_pool_size_flag() {
     CommandLineTest::new()
+        .flag("slashing-protection-pool-size", Some("2"))
         .run()
-        .with_config(|config| assert!(config.gas_limit.is_none()));
+        .with_config(|config| assert_eq!(config.slashing_protection_pool_size, Some(2)));
 }
+
 #[test]
-fn gas_limit_flag() {
+#[should_panic]
+fn slashing_protection_pool_size_zero() {
     CommandLineTest::new()
-        .flag("gas-limit", Some("600"))
-        .flag("builder-proposals", None)
-        .run()
-        .with_config(|config| assert_eq!(config.gas_limit, Some(600)));
+        .flag("slashing-protection-pool-size", Some("0"))
+        .run();
 }
+
 #[test]
-fn no_builder_proposals_flag() {
+fn max_concurrent_signings_default() {
     CommandLineTest::new()
         .run()
-        .with_config(|config| assert!(!config.builder_proposals));
+        .with_config(|config| assert_eq!(config.max_concurrent_signings, None));
 }
+
 #[test]
-fn builder_proposals_flag() {
+fn max_concurrent_signings_flag() {
     CommandLineTest::new()
-        .flag("builder-proposals", None)
+        .flag("max-concurrent-signings", Some("16"))
         .run()
-        .with_config(|config| assert!(config.builder_proposals));
+        .with_config(|config| assert_eq!(config.max_concurrent_signings, Some(16)));
 }
+
 #[test]
-fn no_builder_registration_timestamp_override_flag() {
+#[should_panic]
+fn max_concurrent_signings_zero() {
     CommandLineTest::new()
-        .run()
-        .with_config(|config| assert!(config.builder_registration_timestamp_override.is_none()));
+        .flag("max-concurrent-signings", Some("0"))
+        .run();
 }
+
 #[test]
-fn builder_registration_timestamp_override_flag() {
+fn slow_sign_threshold_default() {
     CommandLineTest::new()
-        .flag("builder-registration-timestamp-override", Some("100"))
         .run()
-        .with_config(|config| {
-            assert_eq!(config.builder_registration_timestamp_override, Some(100))
-        });
+        .with_config(|config| assert_eq!(config.slow_sign_threshold, Duration::from_secs(1)));
 }
+
 #[test]
-fn monitoring_endpoint() {
+fn slow_sign_threshold_flag() {
     CommandLineTest::new()
-        .flag("monitoring-endpoint", Some("http://example:8000"))
-        .flag("monitoring-endpoint-period", Some("30"))
+        .flag("slow-sign-threshold-ms", Some("250"))
         .run()
-        .with_config(|config| {
-            let api_conf = config.monitoring_api.as_ref().unwrap();
-            assert_eq!(api_conf.monitoring_endpoint.as_str(), "http://example:8000");
-            assert_eq!(api_conf.update_period_secs, Some(30));
-        });
+        .with_config(|config| assert_eq!(config.slow_sign_threshold, Duration::from_millis(250)));
 }
+
 #[test]
-fn disable_run_on_all_default() {
-    CommandLineTest::new().run().with_config(|config| {
-        assert!(!config.disable_run_on_all);
-    });
+#[should_panic]
+fn slow_sign_threshold_zero() {
+    CommandLineTest::new()
+        .flag("slow-sign-threshold-ms", Some("0"))
+        .run();
 }
 
 #[test]
-fn disable_run_on_all() {
+fn genesis_time_and_seconds_per_slot_override() {
+    CommandLineTest::new().run().with_config(|config| {
+        assert_eq!(config.genesis_time_override, None);
+        assert_eq!(config.seconds_per_slot_override, None);
+    });
     CommandLineTest::new()
-        .flag("disable-run-on-all", None)
+        .flag("genesis-time-override", Some("1606824023"))
+        .flag("seconds-per-slot-override", Some("6"))
         .run()
         .with_config(|config| {
-            assert!(config.disable_run_on_all);
+            assert_eq!(config.genesis_time_override, Some(1606824023));
+            assert_eq!(config.seconds_per_slot_override, Some(6));
         });
 }
 
 #[test]
-fn latency_measurement_service() {
+#[should_panic]
+fn seconds_per_slot_override_zero_value() {
+    CommandLineTest::new()
+        .flag("seconds-per-slot-override", Some("0"))
+        .run();
+}
+
+#[test]
+fn genesis_validators_root_override() {
     CommandLineTest::new().run().with_config(|config| {
-        assert!(config.enable_latency_measurement_service);
+        assert_eq!(config.genesis_validators_root_override, None);
     });
+    let root = "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95";
     CommandLineTest::new()
-        .flag("latency-measurement-service", None)
-        .run()
-        .with_config(|config| {
-            assert!(config.enable_latency_measurement_service);
-        });
-    CommandLineTest::new()
-        .flag("latency-measurement-service", Some("true"))
+        .flag("genesis-validators-root-override", Some(root))
         .run()
         .with_config(|config| {
-            assert!(config.enable_latency_measurement_service);
+            assert_eq!(
+                config.genesis_validators_root_override,
+                Some(Hash256::from_str(root).unwrap())
+            );
         });
+}
+
+#[test]
+#[should_panic]
+fn genesis_validators_root_override_zero_value() {
     CommandLineTest::new()
-        .flag("latency-measurement-service", Some("false"))
-        .run()
-        .with_config(|config| {
-            assert!(!config.enable_latency_measurement_service);
-        });
+        .flag(
+            "genesis-validators-root-override",
+            Some("0x0000000000000000000000000000000000000000000000000000000000000000"),
+        )
+        .run();
 }
 
 #[test]
-fn validator_registration_batch_size() {
+fn fork_schedule_override() {
     CommandLineTest::new().run().with_config(|config| {
-        assert_eq!(config.validator_registration_batch_size, 500);
+        assert_eq!(config.fork_schedule_override, None);
     });
+    let dir = TempDir::new().expect("Unable to create temporary directory");
+    let path = dir.path().join("forks.yaml");
+    let mut file = File::create(&path).expect("Unable to create fork schedule");
+    file.write_all(
+        b"- fork: phase0\n  version: \"0x00000042\"\n\
+         - fork: altair\n  version: \"0x01000042\"\n  epoch: 10\n",
+    )
+    .expect("Unable to write fork schedule");
     CommandLineTest::new()
-        .flag("validator-registration-batch-size", Some("100"))
+        .flag("fork-schedule-override", path.to_str())
         .run()
         .with_config(|config| {
-            assert_eq!(config.validator_registration_batch_size, 100);
+            assert_eq!(
+                config.fork_schedule_override,
+                Some(vec![
+                    ForkOverride {
+                        fork: ForkName::Base,
+                        version: [0, 0, 0, 0x42],
+                        epoch: None,
+                    },
+                    ForkOverride {
+                        fork: ForkName::Altair,
+                        version: [1, 0, 0, 0x42],
+                        epoch: Some(Epoch::new(10)),
+                    },
+                ])
+            );
         });
 }
 
 #[test]
 #[should_panic]
-fn validator_registration_batch_size_zero_value() {
+fn fork_schedule_override_missing_file() {
     CommandLineTest::new()
-        .flag("validator-registration-batch-size", Some("0"))
+        .flag("fork-schedule-override", Some("/does/not/exist.yaml"))
         .run();
 }
@@ -64,6 +64,7 @@ pub fn run_eth1_sim(matches: &ArgMatches) -> Result<(), String> {
             path: None,
             debug_level: String::from("debug"),
             logfile_debug_level: String::from("debug"),
+            log_levels: None,
             log_format: None,
             logfile_format: None,
             log_color: false,
@@ -50,6 +50,7 @@ fn syncing_sim(
             path: None,
             debug_level: String::from(log_level),
             logfile_debug_level: String::from("debug"),
+            log_levels: None,
             log_format: log_format.map(String::from),
             logfile_format: None,
             log_color: false,
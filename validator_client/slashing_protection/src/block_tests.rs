@@ -2,6 +2,7 @@
 
 use super::*;
 use crate::test_utils::*;
+use tempfile::tempdir;
 use types::{BeaconBlockHeader, Hash256, Slot};
 
 pub fn block(slot: u64) -> BeaconBlockHeader {
@@ -122,3 +123,33 @@ fn invalid_unregistered_validator() {
     }
     .run()
 }
+
+#[test]
+fn prune_blocks_returns_count() {
+    let dir = tempdir().unwrap();
+    let slashing_db =
+        SlashingDatabase::create(&dir.path().join("slashing_protection.sqlite")).unwrap();
+    let pubkey = pubkey(DEFAULT_VALIDATOR_INDEX);
+    slashing_db.register_validator(pubkey).unwrap();
+
+    for slot in [1, 2, 3, 10] {
+        slashing_db
+            .check_and_insert_block_proposal(&pubkey, &block(slot), DEFAULT_DOMAIN)
+            .unwrap();
+    }
+
+    let pruned = slashing_db
+        .prune_all_signed_blocks(std::iter::once(&pubkey), Slot::new(3))
+        .unwrap();
+    assert_eq!(pruned, 2);
+
+    // The most recent block is never pruned.
+    let pruned = slashing_db
+        .prune_all_signed_blocks(std::iter::once(&pubkey), Slot::new(100))
+        .unwrap();
+    assert_eq!(pruned, 1);
+    let pruned = slashing_db
+        .prune_all_signed_blocks(std::iter::once(&pubkey), Slot::new(100))
+        .unwrap();
+    assert_eq!(pruned, 0);
+}
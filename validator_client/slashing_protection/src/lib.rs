@@ -13,10 +13,10 @@ pub mod test_utils;
 pub use crate::signed_attestation::{InvalidAttestation, SignedAttestation};
 pub use crate::signed_block::{InvalidBlock, SignedBlock};
 pub use crate::slashing_database::{
-    InterchangeError, InterchangeImportOutcome, SlashingDatabase,
-    SUPPORTED_INTERCHANGE_FORMAT_VERSION,
+    InterchangeError, InterchangeImportOutcome, RegistrationOutcome, SlashingDatabase,
+    ValidatorSummary, POOL_SIZE, SUPPORTED_INTERCHANGE_FORMAT_VERSION,
 };
-use rusqlite::Error as SQLError;
+use rusqlite::{Error as SQLError, ErrorCode};
 use std::io::{Error as IOError, ErrorKind};
 use std::string::ToString;
 use types::{Hash256, PublicKeyBytes};
@@ -31,15 +31,29 @@ pub const SLASHING_PROTECTION_FILENAME: &str = "slashing_protection.sqlite";
 pub enum NotSafe {
     UnregisteredValidator(PublicKeyBytes),
     DisabledValidator(PublicKeyBytes),
+    /// The validator was expected to be absent from the database, but it is already present.
+    ExistingValidator(PublicKeyBytes),
     InvalidBlock(InvalidBlock),
     InvalidAttestation(InvalidAttestation),
     PermissionsError,
     IOError(ErrorKind),
     SQLError(String),
     SQLPoolError(String),
+    /// The database is locked by another connection.
+    DatabaseLocked(String),
     ConsistencyError,
 }
 
+impl NotSafe {
+    /// Returns `true` if the operation failed because the database was in use by another
+    /// connection, in which case it may succeed if retried.
+    ///
+    /// Failed operations are rolled back, so retrying is always safe.
+    pub fn is_lock_error(&self) -> bool {
+        matches!(self, NotSafe::DatabaseLocked(_) | NotSafe::SQLPoolError(_))
+    }
+}
+
 /// The attestation or block is safe to sign, and will not cause the signer to be slashed.
 #[derive(PartialEq, Debug)]
 pub enum Safe {
@@ -111,7 +125,12 @@ impl From<IOError> for NotSafe {
 
 impl From<SQLError> for NotSafe {
     fn from(error: SQLError) -> NotSafe {
-        NotSafe::SQLError(error.to_string())
+        match error.sqlite_error_code() {
+            Some(ErrorCode::DatabaseBusy) | Some(ErrorCode::DatabaseLocked) => {
+                NotSafe::DatabaseLocked(error.to_string())
+            }
+            _ => NotSafe::SQLError(error.to_string()),
+        }
     }
 }
 
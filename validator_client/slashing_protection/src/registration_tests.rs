@@ -4,6 +4,7 @@ use crate::test_utils::*;
 use crate::*;
 use std::iter;
 use tempfile::tempdir;
+use types::{Epoch, Hash256, Slot};
 
 #[test]
 fn double_register_validators() {
@@ -72,3 +73,103 @@ fn reregister_validator() {
         })
         .unwrap();
 }
+
+#[test]
+fn register_validators_with_outcomes() {
+    let dir = tempdir().unwrap();
+    let slashing_db_file = dir.path().join("slashing_protection.sqlite");
+    let slashing_db = SlashingDatabase::create(&slashing_db_file).unwrap();
+
+    let pubkeys = (0..3).map(pubkey).collect::<Vec<_>>();
+
+    slashing_db.register_validator(pubkeys[0]).unwrap();
+    slashing_db.register_validator(pubkeys[1]).unwrap();
+    let disabled_id = slashing_db.get_validator_id(&pubkeys[1]).unwrap();
+    slashing_db
+        .with_transaction(|txn| slashing_db.update_validator_status(txn, disabled_id, false))
+        .unwrap();
+
+    // Strict registration fails without registering anything.
+    assert_eq!(
+        slashing_db
+            .register_validators_with_outcomes(pubkeys.iter(), false)
+            .unwrap_err(),
+        NotSafe::ExistingValidator(pubkeys[0])
+    );
+    assert_eq!(slashing_db.num_validator_rows().unwrap(), 2);
+    assert_eq!(
+        slashing_db.get_validator_id(&pubkeys[1]).unwrap_err(),
+        NotSafe::DisabledValidator(pubkeys[1])
+    );
+
+    let outcomes = slashing_db
+        .register_validators_with_outcomes(pubkeys.iter(), true)
+        .unwrap();
+    assert_eq!(
+        outcomes,
+        vec![
+            (pubkeys[0], RegistrationOutcome::AlreadyRegistered),
+            (pubkeys[1], RegistrationOutcome::Reenabled),
+            (pubkeys[2], RegistrationOutcome::Registered),
+        ]
+    );
+    assert_eq!(
+        slashing_db.get_validator_id(&pubkeys[1]).unwrap(),
+        disabled_id
+    );
+
+    // Strict registration of new validators succeeds.
+    let new_pubkey = pubkey(3);
+    assert_eq!(
+        slashing_db
+            .register_validators_with_outcomes(iter::once(&new_pubkey), false)
+            .unwrap(),
+        vec![(new_pubkey, RegistrationOutcome::Registered)]
+    );
+}
+
+#[test]
+fn signed_in_epoch() {
+    let dir = tempdir().unwrap();
+    let slashing_db_file = dir.path().join("slashing_protection.sqlite");
+    let slashing_db = SlashingDatabase::create(&slashing_db_file).unwrap();
+    let slots_per_epoch = 32;
+
+    let pk = pubkey(0);
+    slashing_db.register_validator(pk).unwrap();
+
+    let signed_in_epoch = |epoch: u64| {
+        slashing_db
+            .with_transaction(|txn| {
+                slashing_db.signed_in_epoch(&pk, Epoch::new(epoch), slots_per_epoch, txn)
+            })
+            .unwrap()
+    };
+
+    slashing_db
+        .check_and_insert_attestation_signing_root(
+            &pk,
+            Epoch::new(1),
+            Epoch::new(2),
+            Hash256::repeat_byte(1).into(),
+        )
+        .unwrap();
+    slashing_db
+        .check_and_insert_block_signing_root(&pk, Slot::new(4 * 32 + 7), Hash256::zero().into())
+        .unwrap();
+
+    assert!(!signed_in_epoch(1));
+    assert!(signed_in_epoch(2));
+    assert!(!signed_in_epoch(3));
+    assert!(signed_in_epoch(4));
+    assert!(!signed_in_epoch(5));
+
+    assert_eq!(
+        slashing_db
+            .with_transaction(|txn| {
+                slashing_db.signed_in_epoch(&pubkey(1), Epoch::new(2), slots_per_epoch, txn)
+            })
+            .unwrap_err(),
+        NotSafe::UnregisteredValidator(pubkey(1))
+    );
+}
@@ -15,10 +15,12 @@ use types::{AttestationData, BeaconBlockHeader, Epoch, Hash256, PublicKeyBytes,
 
 type Pool = r2d2::Pool<SqliteConnectionManager>;
 
-/// We set the pool size to 1 for compatibility with locking_mode=EXCLUSIVE.
+/// We set the default pool size to 1 for compatibility with locking_mode=EXCLUSIVE.
 ///
 /// This is perhaps overkill in the presence of exclusive transactions, but has
 /// the added bonus of preventing other processes from trying to use our slashing database.
+///
+/// Larger pools use locking_mode=NORMAL instead, relying on exclusive transactions alone.
 pub const POOL_SIZE: u32 = 1;
 #[cfg(not(test))]
 pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
@@ -39,10 +41,17 @@ pub struct SlashingDatabase {
 impl SlashingDatabase {
     /// Open an existing database at the given `path`, or create one if none exists.
     pub fn open_or_create(path: &Path) -> Result<Self, NotSafe> {
+        Self::open_or_create_with_pool_size(path, POOL_SIZE)
+    }
+
+    /// Like `open_or_create`, with a connection pool of `pool_size` connections.
+    ///
+    /// A `pool_size` greater than 1 does not lock the database against other processes.
+    pub fn open_or_create_with_pool_size(path: &Path, pool_size: u32) -> Result<Self, NotSafe> {
         if path.exists() {
-            Self::open(path)
+            Self::open_with_pool_size(path, pool_size)
         } else {
-            Self::create(path)
+            Self::create_with_pool_size(path, pool_size)
         }
     }
 
@@ -50,6 +59,12 @@ impl SlashingDatabase {
     ///
     /// Error if a database (or any file) already exists at `path`.
     pub fn create(path: &Path) -> Result<Self, NotSafe> {
+        Self::create_with_pool_size(path, POOL_SIZE)
+    }
+
+    /// Like `create`, with a connection pool of `pool_size` connections.
+    pub fn create_with_pool_size(path: &Path, pool_size: u32) -> Result<Self, NotSafe> {
+        Self::check_pool_size(pool_size)?;
         let _file = File::options()
             .write(true)
             .read(true)
@@ -57,7 +72,7 @@ impl SlashingDatabase {
             .open(path)?;
 
         restrict_file_permissions(path).map_err(|_| NotSafe::PermissionsError)?;
-        let conn_pool = Self::open_conn_pool(path)?;
+        let conn_pool = Self::open_conn_pool(path, pool_size)?;
         let mut conn = conn_pool.get()?;
 
         conn.execute(
@@ -104,7 +119,12 @@ impl SlashingDatabase {
     ///
     /// This will automatically check for and apply the latest schema migrations.
     pub fn open(path: &Path) -> Result<Self, NotSafe> {
-        let conn_pool = Self::open_conn_pool(path)?;
+        Self::open_with_pool_size(path, POOL_SIZE)
+    }
+
+    /// Like `open`, with a connection pool of `pool_size` connections.
+    pub fn open_with_pool_size(path: &Path, pool_size: u32) -> Result<Self, NotSafe> {
+        let conn_pool = Self::open_conn_pool(path, pool_size)?;
         let db = Self { conn_pool };
         db.with_transaction(Self::apply_schema_migrations)?;
         Ok(db)
@@ -143,27 +163,49 @@ impl SlashingDatabase {
     }
 
     /// Open a new connection pool with all of the necessary settings and tweaks.
-    fn open_conn_pool(path: &Path) -> Result<Pool, NotSafe> {
+    fn open_conn_pool(path: &Path, pool_size: u32) -> Result<Pool, NotSafe> {
+        Self::check_pool_size(pool_size)?;
+        // Exclusive locking would prevent all but one connection from accessing the database.
+        let exclusive = pool_size == 1;
         let manager = SqliteConnectionManager::file(path)
             .with_flags(rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE)
-            .with_init(Self::apply_pragmas);
+            .with_init(move |conn| Self::apply_pragmas(conn, exclusive));
         let conn_pool = Pool::builder()
-            .max_size(POOL_SIZE)
+            .max_size(pool_size)
             .connection_timeout(CONNECTION_TIMEOUT)
             .build(manager)
             .map_err(|e| NotSafe::SQLError(format!("Unable to open database: {:?}", e)))?;
         Ok(conn_pool)
     }
 
+    fn check_pool_size(pool_size: u32) -> Result<(), NotSafe> {
+        if pool_size == 0 {
+            return Err(NotSafe::SQLError(
+                "Unable to open database: pool size must be nonzero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Apply the necessary settings to an SQLite connection.
     ///
     /// Most importantly, put the database into exclusive locking mode, so that threads are forced
     /// to serialise all DB access (to prevent slashable data being checked and signed in parallel).
     /// The exclusive locking mode also has the benefit of applying to other processes, so multiple
     /// Lighthouse processes trying to access the same database will also be blocked.
-    fn apply_pragmas(conn: &mut rusqlite::Connection) -> Result<(), rusqlite::Error> {
+    ///
+    /// If `exclusive` is `false` the normal locking mode is used, and checks are serialised by
+    /// their exclusive transactions only.
+    fn apply_pragmas(
+        conn: &mut rusqlite::Connection,
+        exclusive: bool,
+    ) -> Result<(), rusqlite::Error> {
         conn.pragma_update(None, "foreign_keys", true)?;
-        conn.pragma_update(None, "locking_mode", "EXCLUSIVE")?;
+        if exclusive {
+            conn.pragma_update(None, "locking_mode", "EXCLUSIVE")?;
+        } else {
+            conn.busy_timeout(CONNECTION_TIMEOUT)?;
+        }
         Ok(())
     }
 
@@ -203,6 +245,32 @@ impl SlashingDatabase {
         self.with_transaction(|txn| self.register_validators_in_txn(public_keys, txn))
     }
 
+    /// Register multiple validators, returning what happened to each of them.
+    ///
+    /// If `allow_existing` is `false` and any of the validators is already present in the
+    /// database (enabled or not), no validators are registered and
+    /// `NotSafe::ExistingValidator` is returned.
+    ///
+    /// The slashing protection records of existing validators are never modified.
+    pub fn register_validators_with_outcomes<'a>(
+        &self,
+        public_keys: impl Iterator<Item = &'a PublicKeyBytes>,
+        allow_existing: bool,
+    ) -> Result<Vec<(PublicKeyBytes, RegistrationOutcome)>, NotSafe> {
+        self.with_transaction(|txn| {
+            let outcomes = self.register_validators_in_txn_with_outcomes(public_keys, txn)?;
+            if !allow_existing {
+                if let Some((pubkey, _)) = outcomes
+                    .iter()
+                    .find(|(_, outcome)| *outcome != RegistrationOutcome::Registered)
+                {
+                    return Err(NotSafe::ExistingValidator(*pubkey));
+                }
+            }
+            Ok(outcomes)
+        })
+    }
+
     /// Register multiple validators inside the given transaction.
     ///
     /// The caller must commit the transaction for the changes to be persisted.
@@ -211,22 +279,36 @@ impl SlashingDatabase {
         public_keys: impl Iterator<Item = &'a PublicKeyBytes>,
         txn: &Transaction,
     ) -> Result<(), NotSafe> {
+        self.register_validators_in_txn_with_outcomes(public_keys, txn)
+            .map(|_| ())
+    }
+
+    fn register_validators_in_txn_with_outcomes<'a>(
+        &self,
+        public_keys: impl Iterator<Item = &'a PublicKeyBytes>,
+        txn: &Transaction,
+    ) -> Result<Vec<(PublicKeyBytes, RegistrationOutcome)>, NotSafe> {
         let mut stmt =
             txn.prepare("INSERT INTO validators (public_key, enabled) VALUES (?1, TRUE)")?;
+        let mut outcomes = vec![];
         for pubkey in public_keys {
-            match self.get_validator_id_with_status(txn, pubkey)? {
+            let outcome = match self.get_validator_id_with_status(txn, pubkey)? {
                 None => {
                     stmt.execute([pubkey.as_hex_string()])?;
+                    RegistrationOutcome::Registered
                 }
                 Some((validator_id, false)) => {
                     self.update_validator_status(txn, validator_id, true)?;
+                    RegistrationOutcome::Reenabled
                 }
                 Some((_, true)) => {
                     // Validator already registered and enabled.
+                    RegistrationOutcome::AlreadyRegistered
                 }
-            }
+            };
+            outcomes.push((*pubkey, outcome));
         }
-        Ok(())
+        Ok(outcomes)
     }
 
     pub fn update_validator_status(
@@ -901,10 +983,10 @@ impl SlashingDatabase {
         public_key: &PublicKeyBytes,
         new_min_slot: Slot,
         txn: &Transaction,
-    ) -> Result<(), NotSafe> {
+    ) -> Result<usize, NotSafe> {
         let validator_id = self.get_validator_id_in_txn(txn, public_key)?;
 
-        txn.execute(
+        let deleted = txn.execute(
             "DELETE FROM signed_blocks
              WHERE
                 validator_id = ?1 AND
@@ -915,20 +997,25 @@ impl SlashingDatabase {
             params![validator_id, new_min_slot],
         )?;
 
-        Ok(())
+        Ok(deleted)
     }
 
     /// Prune the signed blocks table for the given public keys.
+    ///
+    /// Returns the number of blocks removed.
     pub fn prune_all_signed_blocks<'a>(
         &self,
-        mut public_keys: impl Iterator<Item = &'a PublicKeyBytes>,
+        public_keys: impl Iterator<Item = &'a PublicKeyBytes>,
         new_min_slot: Slot,
-    ) -> Result<(), NotSafe> {
+    ) -> Result<usize, NotSafe> {
         let mut conn = self.conn_pool.get()?;
         let txn = conn.transaction()?;
-        public_keys.try_for_each(|pubkey| self.prune_signed_blocks(pubkey, new_min_slot, &txn))?;
+        let mut deleted = 0;
+        for pubkey in public_keys {
+            deleted += self.prune_signed_blocks(pubkey, new_min_slot, &txn)?;
+        }
         txn.commit()?;
-        Ok(())
+        Ok(deleted)
     }
 
     /// Remove all attestations for `public_key` with `target < new_min_target`.
@@ -941,14 +1028,14 @@ impl SlashingDatabase {
         public_key: &PublicKeyBytes,
         new_min_target: Epoch,
         txn: &Transaction,
-    ) -> Result<(), NotSafe> {
+    ) -> Result<usize, NotSafe> {
         let validator_id = self.get_validator_id_in_txn(txn, public_key)?;
 
         // The following holds, because we never store mutually slashable attestations:
         //   a.target < new_min_target --> a.source <= new_min_source
         //
         // The `MAX(target_epoch)` acts as a guard to prevent accidentally clearing the DB.
-        txn.execute(
+        let deleted = txn.execute(
             "DELETE FROM signed_attestations
              WHERE
                 validator_id = ?1 AND
@@ -959,7 +1046,7 @@ impl SlashingDatabase {
             params![validator_id, new_min_target],
         )?;
 
-        Ok(())
+        Ok(deleted)
     }
 
     /// Remove all attestations signed by a given `public_key`.
@@ -1001,15 +1088,17 @@ impl SlashingDatabase {
     /// Prune the signed attestations table for the given validator keys.
     pub fn prune_all_signed_attestations<'a>(
         &self,
-        mut public_keys: impl Iterator<Item = &'a PublicKeyBytes>,
+        public_keys: impl Iterator<Item = &'a PublicKeyBytes>,
         new_min_target: Epoch,
-    ) -> Result<(), NotSafe> {
+    ) -> Result<usize, NotSafe> {
         let mut conn = self.conn_pool.get()?;
         let txn = conn.transaction()?;
-        public_keys
-            .try_for_each(|pubkey| self.prune_signed_attestations(pubkey, new_min_target, &txn))?;
+        let mut deleted = 0;
+        for pubkey in public_keys {
+            deleted += self.prune_signed_attestations(pubkey, new_min_target, &txn)?;
+        }
         txn.commit()?;
-        Ok(())
+        Ok(deleted)
     }
 
     pub fn num_validator_rows(&self) -> Result<u32, NotSafe> {
@@ -1028,6 +1117,65 @@ impl SlashingDatabase {
         txn: &Transaction,
     ) -> Result<ValidatorSummary, NotSafe> {
         let validator_id = self.get_validator_id_in_txn(txn, public_key)?;
+        self.validator_summary_for_id(validator_id, txn)
+    }
+
+    /// As for `validator_summary`, but also for validators which are disabled.
+    pub fn validator_summary_ignoring_status(
+        &self,
+        public_key: &PublicKeyBytes,
+        txn: &Transaction,
+    ) -> Result<ValidatorSummary, NotSafe> {
+        let validator_id = self.get_validator_id_ignoring_status(txn, public_key)?;
+        self.validator_summary_for_id(validator_id, txn)
+    }
+
+    /// Returns `true` if the validator signed an attestation targeting `epoch` or a block in one of
+    /// its slots. Validators which are disabled are included.
+    pub fn signed_in_epoch(
+        &self,
+        public_key: &PublicKeyBytes,
+        epoch: Epoch,
+        slots_per_epoch: u64,
+        txn: &Transaction,
+    ) -> Result<bool, NotSafe> {
+        let validator_id = self.get_validator_id_ignoring_status(txn, public_key)?;
+
+        let signed_attestation = txn
+            .prepare(
+                "SELECT 1 FROM signed_attestations
+                 WHERE validator_id = ?1 AND target_epoch = ?2",
+            )?
+            .query_row(params![validator_id, epoch], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if signed_attestation {
+            return Ok(true);
+        }
+
+        let signed_block = txn
+            .prepare(
+                "SELECT 1 FROM signed_blocks
+                 WHERE validator_id = ?1 AND slot >= ?2 AND slot <= ?3",
+            )?
+            .query_row(
+                params![
+                    validator_id,
+                    epoch.start_slot(slots_per_epoch),
+                    epoch.end_slot(slots_per_epoch)
+                ],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        Ok(signed_block)
+    }
+
+    fn validator_summary_for_id(
+        &self,
+        validator_id: i64,
+        txn: &Transaction,
+    ) -> Result<ValidatorSummary, NotSafe> {
         let (min_block_slot, max_block_slot) = txn
             .prepare(
                 "SELECT MIN(slot), MAX(slot)
@@ -1118,6 +1266,17 @@ fn monotonic<T: PartialOrd>(new: Option<T>, prev: Option<T>) -> bool {
     })
 }
 
+/// What happened to a validator when it was registered with the slashing protection database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationOutcome {
+    /// The validator was not present and has been added.
+    Registered,
+    /// The validator was present but disabled, and has been enabled with its records kept.
+    Reenabled,
+    /// The validator was already registered and enabled, its records have been kept.
+    AlreadyRegistered,
+}
+
 /// The result of importing a single entry from an interchange file.
 #[derive(Debug)]
 pub enum InterchangeImportOutcome {
@@ -1200,6 +1359,27 @@ mod tests {
         SlashingDatabase::open(&file).unwrap_err();
     }
 
+    // An operation which can't acquire a connection because another operation is using it should
+    // report a lock error, so that it can be retried.
+    #[test]
+    fn connection_in_use_is_lock_error() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("db.sqlite");
+        let db = SlashingDatabase::create(&file).unwrap();
+        let pk = PublicKeyBytes::empty();
+        db.register_validator(pk).unwrap();
+
+        let conn = db.conn_pool.get().unwrap();
+        let err = db
+            .check_and_insert_block_signing_root(&pk, Slot::new(1), Hash256::repeat_byte(1).into())
+            .unwrap_err();
+        assert!(err.is_lock_error(), "{:?}", err);
+
+        drop(conn);
+        db.check_and_insert_block_signing_root(&pk, Slot::new(1), Hash256::repeat_byte(1).into())
+            .unwrap();
+    }
+
     // Attempting to create the same database twice should error.
     #[test]
     fn double_create_error() {
@@ -1238,6 +1418,32 @@ mod tests {
         check(&db2);
     }
 
+    // A larger pool allows concurrent connections, while checks remain serialised.
+    #[test]
+    fn larger_pool_size() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("db.sqlite");
+        let db = SlashingDatabase::create_with_pool_size(&file, 2).unwrap();
+        assert_eq!(db.conn_pool.max_size(), 2);
+        let pk = PublicKeyBytes::empty();
+        db.register_validator(pk).unwrap();
+
+        let conn = db.conn_pool.get().unwrap();
+        assert_eq!(
+            conn.pragma_query_value(None, "locking_mode", |row| { row.get::<_, String>(0) })
+                .unwrap()
+                .to_uppercase(),
+            "NORMAL"
+        );
+        db.check_and_insert_block_signing_root(&pk, Slot::new(1), Hash256::repeat_byte(1).into())
+            .unwrap();
+        drop(conn);
+
+        assert!(
+            SlashingDatabase::create_with_pool_size(&dir.path().join("db2.sqlite"), 0).is_err()
+        );
+    }
+
     #[test]
     fn test_transaction_failure() {
         let dir = tempdir().unwrap();
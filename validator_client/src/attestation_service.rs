@@ -1,17 +1,28 @@
-use crate::beacon_node_fallback::{BeaconNodeFallback, RequireSynced};
+use crate::beacon_node_fallback::{BeaconNodeFallback, Errors, RequireSynced};
 use crate::{
+    check_synced::is_optimistic,
+    config::{
+        DEFAULT_ATTESTATION_DEADLINE_FRACTION, DEFAULT_MAX_ATTESTATION_SLOT_LAG,
+        DEFAULT_MAX_PENDING_AGGREGATIONS,
+    },
     duties_service::{DutiesService, DutyAndProof},
     http_metrics::metrics,
+    skip_reason::{self, SkipReason},
     validator_store::ValidatorStore,
     OfflineOnFailure,
 };
 use environment::RuntimeContext;
+use eth2::BeaconNodeHttpClient;
 use futures::future::join_all;
-use slog::{crit, error, info, trace};
+use parking_lot::Mutex;
+use slog::{crit, debug, error, info, trace, warn};
 use slot_clock::SlotClock;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::future::Future;
 use std::ops::Deref;
 use std::sync::Arc;
+use tokio::sync::oneshot;
 use tokio::time::{sleep, sleep_until, Duration, Instant};
 use tree_hash::TreeHash;
 use types::{
@@ -25,6 +36,12 @@ pub struct AttestationServiceBuilder<T: SlotClock + 'static, E: EthSpec> {
     validator_store: Option<Arc<ValidatorStore<T, E>>>,
     slot_clock: Option<T>,
     beacon_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
+    attestation_fallback_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
+    attestation_deadline_fraction: f64,
+    attestation_publish_retry_nodes: bool,
+    max_attestation_slot_lag: u64,
+    max_pending_aggregations: usize,
+    refuse_attestation_on_optimistic: bool,
     context: Option<RuntimeContext<E>>,
 }
 
@@ -35,6 +52,12 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationServiceBuilder<T, E> {
             validator_store: None,
             slot_clock: None,
             beacon_nodes: None,
+            attestation_fallback_nodes: None,
+            attestation_deadline_fraction: DEFAULT_ATTESTATION_DEADLINE_FRACTION,
+            attestation_publish_retry_nodes: true,
+            max_attestation_slot_lag: DEFAULT_MAX_ATTESTATION_SLOT_LAG,
+            max_pending_aggregations: DEFAULT_MAX_PENDING_AGGREGATIONS,
+            refuse_attestation_on_optimistic: false,
             context: None,
         }
     }
@@ -59,6 +82,39 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationServiceBuilder<T, E> {
         self
     }
 
+    pub fn attestation_fallback_nodes(
+        mut self,
+        attestation_fallback_nodes: Arc<BeaconNodeFallback<T, E>>,
+    ) -> Self {
+        self.attestation_fallback_nodes = Some(attestation_fallback_nodes);
+        self
+    }
+
+    pub fn attestation_deadline_fraction(mut self, fraction: f64) -> Self {
+        self.attestation_deadline_fraction = fraction;
+        self
+    }
+
+    pub fn attestation_publish_retry_nodes(mut self, retry_nodes: bool) -> Self {
+        self.attestation_publish_retry_nodes = retry_nodes;
+        self
+    }
+
+    pub fn max_attestation_slot_lag(mut self, max_lag: u64) -> Self {
+        self.max_attestation_slot_lag = max_lag;
+        self
+    }
+
+    pub fn max_pending_aggregations(mut self, max_pending: usize) -> Self {
+        self.max_pending_aggregations = max_pending;
+        self
+    }
+
+    pub fn refuse_attestation_on_optimistic(mut self, refuse: bool) -> Self {
+        self.refuse_attestation_on_optimistic = refuse;
+        self
+    }
+
     pub fn runtime_context(mut self, context: RuntimeContext<E>) -> Self {
         self.context = Some(context);
         self
@@ -79,6 +135,12 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationServiceBuilder<T, E> {
                 beacon_nodes: self
                     .beacon_nodes
                     .ok_or("Cannot build AttestationService without beacon_nodes")?,
+                attestation_fallback_nodes: self.attestation_fallback_nodes,
+                attestation_deadline_fraction: self.attestation_deadline_fraction,
+                attestation_publish_retry_nodes: self.attestation_publish_retry_nodes,
+                max_attestation_slot_lag: self.max_attestation_slot_lag,
+                pending_aggregations: PendingAggregations::new(self.max_pending_aggregations),
+                refuse_attestation_on_optimistic: self.refuse_attestation_on_optimistic,
                 context: self
                     .context
                     .ok_or("Cannot build AttestationService without runtime_context")?,
@@ -87,16 +149,144 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationServiceBuilder<T, E> {
     }
 }
 
+/// Combines the standard `beacon_nodes` with `attestation_fallback_nodes` which are only used
+/// when none of the standard beacon nodes are available.
+pub struct AttestationFallback<T, E: EthSpec> {
+    beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
+    attestation_fallback_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
+}
+
+impl<T: SlotClock, E: EthSpec> AttestationFallback<T, E> {
+    /// Try `func` on `self.beacon_nodes`. If that doesn't work and all of them are unavailable,
+    /// try `self.attestation_fallback_nodes`.
+    pub async fn first_success<'a, F, O, Err, R>(
+        &'a self,
+        require_synced: RequireSynced,
+        offline_on_failure: OfflineOnFailure,
+        func: F,
+    ) -> Result<O, Errors<Err>>
+    where
+        F: Fn(&'a BeaconNodeHttpClient) -> R + Clone,
+        R: Future<Output = Result<O, Err>>,
+        Err: Debug,
+    {
+        let beacon_nodes_result = self
+            .beacon_nodes
+            .first_success(require_synced, offline_on_failure, func.clone())
+            .await;
+
+        match (beacon_nodes_result, &self.attestation_fallback_nodes) {
+            // The standard beacon node call succeeded, return the result.
+            (Ok(success), _) => Ok(success),
+            // The standard beacon node call failed and all of them are down, try the same call on
+            // the attestation fallback nodes.
+            (Err(e), Some(attestation_fallback_nodes)) => {
+                if self.beacon_nodes.num_available().await == 0 {
+                    attestation_fallback_nodes
+                        .first_success(require_synced, offline_on_failure, func)
+                        .await
+                } else {
+                    Err(e)
+                }
+            }
+            // The standard beacon node call failed, but we don't have any attestation fallback
+            // nodes. Return an error.
+            (Err(e), None) => Err(e),
+        }
+    }
+}
+
 /// Helper to minimise `Arc` usage.
 pub struct Inner<T, E: EthSpec> {
     duties_service: Arc<DutiesService<T, E>>,
     validator_store: Arc<ValidatorStore<T, E>>,
     slot_clock: T,
     beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
+    attestation_fallback_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
+    attestation_deadline_fraction: f64,
+    /// If true, attestations rejected by one beacon node are published to the next.
+    attestation_publish_retry_nodes: bool,
+    /// Attestations for slots more than this many slots before the current slot are not signed.
+    max_attestation_slot_lag: u64,
+    pending_aggregations: PendingAggregations,
+    /// If true, attestation data is not requested from a beacon node with an optimistic head.
+    refuse_attestation_on_optimistic: bool,
     context: RuntimeContext<E>,
 }
 
-/// Attempts to produce attestations for all known validators 1/3rd of the way through each slot.
+/// A committee waiting for its aggregate to be produced and published.
+struct PendingAggregation {
+    id: u64,
+    slot: Slot,
+    committee_index: CommitteeIndex,
+    /// Dropped to cancel the aggregation.
+    _cancel: oneshot::Sender<()>,
+}
+
+/// A bounded queue of pending aggregations, oldest first.
+///
+/// Without a bound, aggregations pile up whilst the beacon node is slow to produce aggregates.
+struct PendingAggregations {
+    max_pending: usize,
+    next_id: Mutex<u64>,
+    pending: Mutex<VecDeque<PendingAggregation>>,
+}
+
+impl PendingAggregations {
+    fn new(max_pending: usize) -> Self {
+        Self {
+            max_pending,
+            next_id: Mutex::new(0),
+            pending: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Adds an aggregation to the queue, returning its id, a receiver which resolves if the
+    /// aggregation is shed and the aggregations which were shed to make room for it.
+    fn push(
+        &self,
+        slot: Slot,
+        committee_index: CommitteeIndex,
+    ) -> (u64, oneshot::Receiver<()>, Vec<(Slot, CommitteeIndex)>) {
+        let id = {
+            let mut next_id = self.next_id.lock();
+            *next_id += 1;
+            *next_id
+        };
+        let (cancel, cancelled) = oneshot::channel();
+
+        let mut pending = self.pending.lock();
+        let mut shed = vec![];
+        while pending.len() >= self.max_pending {
+            match pending.pop_front() {
+                Some(oldest) => shed.push((oldest.slot, oldest.committee_index)),
+                None => break,
+            }
+        }
+        pending.push_back(PendingAggregation {
+            id,
+            slot,
+            committee_index,
+            _cancel: cancel,
+        });
+
+        (id, cancelled, shed)
+    }
+
+    /// Removes a completed aggregation from the queue.
+    fn remove(&self, id: u64) {
+        self.pending
+            .lock()
+            .retain(|aggregation| aggregation.id != id);
+    }
+
+    fn len(&self) -> usize {
+        self.pending.lock().len()
+    }
+}
+
+/// Attempts to produce attestations for all known validators `attestation_deadline_fraction` of
+/// the way (1/3rd by default) through each slot.
 ///
 /// If any validators are on the same committee, a single attestation will be downloaded and
 /// returned to the beacon node. This attestation will have a signature from each of the
@@ -122,11 +312,19 @@ impl<T, E: EthSpec> Deref for AttestationService<T, E> {
 }
 
 impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
+    fn attestation_fallback(&self) -> AttestationFallback<T, E> {
+        AttestationFallback {
+            beacon_nodes: self.beacon_nodes.clone(),
+            attestation_fallback_nodes: self.attestation_fallback_nodes.clone(),
+        }
+    }
+
     /// Starts the service which periodically produces attestations.
     pub fn start_update_service(self, spec: &ChainSpec) -> Result<(), String> {
         let log = self.context.log().clone();
 
         let slot_duration = Duration::from_secs(spec.seconds_per_slot);
+        let attestation_offset = slot_duration.mul_f64(self.attestation_deadline_fraction);
         let duration_to_next_slot = self
             .slot_clock
             .duration_to_next_slot()
@@ -143,7 +341,7 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
         let interval_fut = async move {
             loop {
                 if let Some(duration_to_next_slot) = self.slot_clock.duration_to_next_slot() {
-                    sleep(duration_to_next_slot + slot_duration / 3).await;
+                    sleep(duration_to_next_slot + attestation_offset).await;
                     let log = self.context.log();
 
                     if let Err(e) = self.spawn_attestation_tasks(slot_duration) {
@@ -175,6 +373,15 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
     /// attestation to the beacon node.
     fn spawn_attestation_tasks(&self, slot_duration: Duration) -> Result<(), String> {
         let slot = self.slot_clock.now().ok_or("Failed to read slot clock")?;
+
+        if !self.validator_store.startup_warmup_complete(slot) {
+            debug!(
+                self.context.log(),
+                "Not attesting during startup warm-up";
+                "slot" => slot,
+            );
+            return Ok(());
+        }
         let duration_to_next_slot = self
             .slot_clock
             .duration_to_next_slot()
@@ -275,24 +482,48 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
         //
         // If an attestation was produced, make an aggregate.
         if let Some(attestation_data) = attestation_opt {
-            // First, wait until the `aggregation_production_instant` (2/3rds
-            // of the way though the slot). As verified in the
-            // `delay_triggers_when_in_the_past` test, this code will still run
-            // even if the instant has already elapsed.
-            sleep_until(aggregate_production_instant).await;
-
-            // Start the metrics timer *after* we've done the delay.
-            let _aggregates_timer = metrics::start_timer_vec(
-                &metrics::ATTESTATION_SERVICE_TIMES,
-                &[metrics::AGGREGATES],
-            );
+            let (id, cancelled, shed) = self.pending_aggregations.push(slot, committee_index);
+            for (shed_slot, shed_committee_index) in shed {
+                metrics::inc_counter(&metrics::AGGREGATIONS_SHED);
+                warn!(
+                    log,
+                    "Dropping pending aggregation";
+                    "msg" => "too many aggregations are pending, the beacon node may be slow",
+                    "max_pending_aggregations" => self.pending_aggregations.max_pending,
+                    "pending" => self.pending_aggregations.len(),
+                    "committee_index" => shed_committee_index,
+                    "slot" => shed_slot.as_u64(),
+                );
+            }
 
-            // Then download, sign and publish a `SignedAggregateAndProof` for each
-            // validator that is elected to aggregate for this `slot` and
-            // `committee_index`.
-            self.produce_and_publish_aggregates(&attestation_data, &validator_duties)
-                .await
-                .map_err(move |e| {
+            let aggregate = async {
+                // First, wait until the `aggregation_production_instant` (2/3rds
+                // of the way though the slot). As verified in the
+                // `delay_triggers_when_in_the_past` test, this code will still run
+                // even if the instant has already elapsed.
+                sleep_until(aggregate_production_instant).await;
+
+                // Start the metrics timer *after* we've done the delay.
+                let _aggregates_timer = metrics::start_timer_vec(
+                    &metrics::ATTESTATION_SERVICE_TIMES,
+                    &[metrics::AGGREGATES],
+                );
+
+                // Then download, sign and publish a `SignedAggregateAndProof` for each
+                // validator that is elected to aggregate for this `slot` and
+                // `committee_index`.
+                self.produce_and_publish_aggregates(&attestation_data, &validator_duties)
+                    .await
+            };
+
+            let result = tokio::select! {
+                result = aggregate => Some(result),
+                _ = cancelled => None,
+            };
+            self.pending_aggregations.remove(id);
+
+            if let Some(result) = result {
+                result.map_err(move |e| {
                     crit!(
                         log,
                         "Error during attestation routine";
@@ -301,6 +532,7 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
                         "slot" => slot.as_u64(),
                     )
                 })?;
+            }
         }
 
         Ok(())
@@ -318,7 +550,7 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
     ///
     /// Only one `Attestation` is downloaded from the BN. It is then cloned and signed by each
     /// validator and the list of individually-signed `Attestation` objects is returned to the BN.
-    async fn produce_and_publish_attestations(
+    pub(crate) async fn produce_and_publish_attestations(
         &self,
         slot: Slot,
         committee_index: CommitteeIndex,
@@ -336,26 +568,94 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
             .ok_or("Unable to determine current slot from clock")?
             .epoch(E::slots_per_epoch());
 
-        let attestation_data = self
-            .beacon_nodes
+        let attestation_fallback = self.attestation_fallback();
+        let slot_clock = &self.slot_clock;
+        let canary = validator_duties
+            .iter()
+            .find(|duty_and_proof| self.validator_store.is_canary(&duty_and_proof.duty.pubkey))
+            .map(|duty_and_proof| duty_and_proof.duty.validator_index);
+        let refuse_attestation_on_optimistic = self.refuse_attestation_on_optimistic;
+
+        let attestation_data = attestation_fallback
             .first_success(
                 RequireSynced::No,
                 OfflineOnFailure::Yes,
                 |beacon_node| async move {
+                    if refuse_attestation_on_optimistic
+                        && is_optimistic(beacon_node).await.map_err(|e| {
+                            format!(
+                                "Unable to check whether the beacon node head is optimistic: {:?}",
+                                e
+                            )
+                        })?
+                    {
+                        error!(
+                            log,
+                            "Refusing to attest to an optimistic head";
+                            "msg" => "trying the next beacon node",
+                            "beacon_node" => %beacon_node,
+                            "committee_index" => committee_index,
+                            "slot" => slot.as_u64(),
+                        );
+                        return Err("Beacon node head is optimistic".to_string());
+                    }
+
                     let _timer = metrics::start_timer_vec(
                         &metrics::ATTESTATION_SERVICE_TIMES,
                         &[metrics::ATTESTATIONS_HTTP_GET],
                     );
-                    beacon_node
+                    let attestation_data = beacon_node
                         .get_validator_attestation_data(slot, committee_index)
                         .await
-                        .map_err(|e| format!("Failed to produce attestation data: {:?}", e))
-                        .map(|result| result.data)
+                        .map_err(|e| format!("Failed to produce attestation data: {:?}", e))?
+                        .data;
+                    if let Some(validator_index) = canary {
+                        let slot_delay_ms = slot_clock
+                            .millis_from_current_slot_start()
+                            .map(|delay| delay.as_millis());
+                        info!(
+                            log,
+                            "Canary validator diagnostics";
+                            "stage" => "received attestation data",
+                            "validator_index" => validator_index,
+                            "beacon_node" => %beacon_node,
+                            "slot_delay_ms" => ?slot_delay_ms,
+                            "head_block" => ?attestation_data.beacon_block_root,
+                            "slot" => slot.as_u64(),
+                        );
+                    }
+                    Ok::<_, String>(attestation_data)
                 },
             )
             .await
             .map_err(|e| e.to_string())?;
 
+        // Refuse to sign if the duties or the beacon node have stalled for so long that the
+        // attestation slot is well in the past.
+        let current_slot = self
+            .slot_clock
+            .now()
+            .ok_or("Unable to determine current slot from clock")?;
+        if exceeds_slot_lag(slot, current_slot, self.max_attestation_slot_lag) {
+            warn!(
+                log,
+                "Refusing to sign stale attestation";
+                "msg" => "attestation slot is too far behind the current slot",
+                "skip_reason" => SkipReason::Stale.as_str(),
+                "slot" => slot.as_u64(),
+                "current_slot" => current_slot.as_u64(),
+                "max_attestation_slot_lag" => self.max_attestation_slot_lag,
+                "committee_index" => committee_index,
+                "validators" => validator_duties.len(),
+            );
+            skip_reason::record_skipped_duties(
+                SkipReason::Stale,
+                metrics::ATTESTATIONS,
+                validator_duties.len(),
+            );
+            return Ok(None);
+        }
+
         // Create futures to produce signed `Attestation` objects.
         let attestation_data_ref = &attestation_data;
         let signing_futures = validator_duties.iter().map(|duty_and_proof| async move {
@@ -396,10 +696,12 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
             {
                 Ok(()) => Some((attestation, duty.validator_index)),
                 Err(e) => {
+                    let skip_reason = skip_reason::record_signing_error(&e, metrics::ATTESTATIONS);
                     crit!(
                         log,
                         "Failed to sign attestation";
                         "error" => ?e,
+                        "skip_reason" => skip_reason.map(SkipReason::as_str),
                         "validator" => ?duty.pubkey,
                         "committee_index" => committee_index,
                         "slot" => slot.as_u64(),
@@ -417,8 +719,11 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
             .unzip();
 
         // Post the attestations to the BN.
-        match self
-            .beacon_nodes
+        //
+        // The attestations have already been signed, so publishing them to another beacon node
+        // after a rejection does not consult slashing protection again.
+        let retry_nodes = self.attestation_publish_retry_nodes;
+        match attestation_fallback
             .first_success(
                 RequireSynced::No,
                 OfflineOnFailure::Yes,
@@ -427,14 +732,57 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
                         &metrics::ATTESTATION_SERVICE_TIMES,
                         &[metrics::ATTESTATIONS_HTTP_POST],
                     );
-                    beacon_node
+                    metrics::inc_counter_vec(
+                        &metrics::ATTESTATION_PUBLISH_REQUESTS,
+                        &[beacon_node.as_ref()],
+                    );
+                    if let Err(e) = beacon_node
                         .post_beacon_pool_attestations(attestations)
                         .await
+                    {
+                        if !is_rejection(&e) {
+                            return Err(e);
+                        }
+                        metrics::inc_counter_vec(
+                            &metrics::ATTESTATION_PUBLISH_REJECTIONS,
+                            &[beacon_node.as_ref()],
+                        );
+                        warn!(
+                            log,
+                            "Beacon node rejected attestations";
+                            "beacon_node" => %beacon_node,
+                            "error" => %e,
+                            "retry_other_nodes" => retry_nodes,
+                            "committee_index" => committee_index,
+                            "slot" => slot.as_u64(),
+                        );
+                        // Returning `Ok` stops the attempt at the first rejection.
+                        return if retry_nodes { Err(e) } else { Ok(Err(e)) };
+                    }
+                    if let Some(validator_index) = canary {
+                        let slot_delay_ms = slot_clock
+                            .millis_from_current_slot_start()
+                            .map(|delay| delay.as_millis());
+                        let request_bytes =
+                            serde_json::to_vec(attestations).map_or(0, |bytes| bytes.len());
+                        info!(
+                            log,
+                            "Canary validator diagnostics";
+                            "stage" => "published attestation",
+                            "validator_index" => validator_index,
+                            "beacon_node" => %beacon_node,
+                            "slot_delay_ms" => ?slot_delay_ms,
+                            "request_bytes" => request_bytes,
+                            "count" => attestations.len(),
+                            "slot" => slot.as_u64(),
+                        );
+                    }
+                    Ok::<_, eth2::Error>(Ok(()))
                 },
             )
             .await
         {
-            Ok(()) => info!(
+            Ok(Ok(())) => info!(
                 log,
                 "Successfully published attestations";
                 "count" => attestations.len(),
@@ -444,6 +792,14 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
                 "slot" => attestation_data.slot.as_u64(),
                 "type" => "unaggregated",
             ),
+            Ok(Err(e)) => error!(
+                log,
+                "Unable to publish attestations";
+                "error" => %e,
+                "committee_index" => attestation_data.index,
+                "slot" => slot.as_u64(),
+                "type" => "unaggregated",
+            ),
             Err(e) => error!(
                 log,
                 "Unable to publish attestations";
@@ -477,8 +833,15 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
     ) -> Result<(), String> {
         let log = self.context.log();
 
-        let aggregated_attestation = &self
-            .beacon_nodes
+        let attestation_fallback = self.attestation_fallback();
+        let slot_clock = &self.slot_clock;
+        let canary = validator_duties
+            .iter()
+            .filter(|duty_and_proof| duty_and_proof.selection_proof.is_some())
+            .find(|duty_and_proof| self.validator_store.is_canary(&duty_and_proof.duty.pubkey))
+            .map(|duty_and_proof| duty_and_proof.duty.validator_index);
+
+        let aggregated_attestation = &attestation_fallback
             .first_success(
                 RequireSynced::No,
                 OfflineOnFailure::Yes,
@@ -528,10 +891,12 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
             {
                 Ok(aggregate) => Some(aggregate),
                 Err(e) => {
+                    let skip_reason = skip_reason::record_signing_error(&e, metrics::AGGREGATES);
                     crit!(
                         log,
                         "Failed to sign attestation";
                         "error" => ?e,
+                        "skip_reason" => skip_reason.map(SkipReason::as_str),
                         "pubkey" => ?duty.pubkey,
                     );
                     None
@@ -548,8 +913,7 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
 
         if !signed_aggregate_and_proofs.is_empty() {
             let signed_aggregate_and_proofs_slice = signed_aggregate_and_proofs.as_slice();
-            match self
-                .beacon_nodes
+            match attestation_fallback
                 .first_success(
                     RequireSynced::No,
                     OfflineOnFailure::Yes,
@@ -560,7 +924,27 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
                         );
                         beacon_node
                             .post_validator_aggregate_and_proof(signed_aggregate_and_proofs_slice)
-                            .await
+                            .await?;
+                        if let Some(validator_index) = canary {
+                            let slot_delay_ms = slot_clock
+                                .millis_from_current_slot_start()
+                                .map(|delay| delay.as_millis());
+                            let request_bytes =
+                                serde_json::to_vec(signed_aggregate_and_proofs_slice)
+                                    .map_or(0, |bytes| bytes.len());
+                            info!(
+                                log,
+                                "Canary validator diagnostics";
+                                "stage" => "published aggregate",
+                                "validator_index" => validator_index,
+                                "beacon_node" => %beacon_node,
+                                "slot_delay_ms" => ?slot_delay_ms,
+                                "request_bytes" => request_bytes,
+                                "count" => signed_aggregate_and_proofs_slice.len(),
+                                "slot" => attestation_data.slot.as_u64(),
+                            );
+                        }
+                        Ok::<_, eth2::Error>(())
                     },
                 )
                 .await
@@ -627,6 +1011,19 @@ impl<T: SlotClock + 'static, E: EthSpec> AttestationService<T, E> {
     }
 }
 
+/// Returns `true` if `error` is the beacon node refusing the request, rather than failing to
+/// process it.
+fn is_rejection(error: &eth2::Error) -> bool {
+    error
+        .status()
+        .map_or(false, |status| status.is_client_error())
+}
+
+/// Returns `true` if `slot` is more than `max_lag` slots before `current_slot`.
+fn exceeds_slot_lag(slot: Slot, current_slot: Slot, max_lag: u64) -> bool {
+    current_slot.as_u64().saturating_sub(slot.as_u64()) > max_lag
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -650,4 +1047,57 @@ mod tests {
             "state should have been updated"
         );
     }
+
+    #[test]
+    fn client_errors_are_rejections() {
+        use eth2::StatusCode;
+
+        assert!(is_rejection(&eth2::Error::StatusCode(
+            StatusCode::BAD_REQUEST
+        )));
+        assert!(!is_rejection(&eth2::Error::StatusCode(
+            StatusCode::INTERNAL_SERVER_ERROR
+        )));
+        assert!(!is_rejection(&eth2::Error::InvalidSignatureHeader));
+    }
+
+    #[test]
+    fn pending_aggregations_shed_oldest() {
+        let pending = PendingAggregations::new(2);
+
+        let (first, mut first_cancelled, shed) = pending.push(Slot::new(1), 0);
+        assert!(shed.is_empty());
+        let (_, mut second_cancelled, shed) = pending.push(Slot::new(1), 1);
+        assert!(shed.is_empty());
+        assert_eq!(pending.len(), 2);
+
+        // The oldest aggregation is shed to make room.
+        let (_, _third_cancelled, shed) = pending.push(Slot::new(2), 0);
+        assert_eq!(shed, vec![(Slot::new(1), 0)]);
+        assert_eq!(pending.len(), 2);
+        assert_eq!(
+            first_cancelled.try_recv(),
+            Err(oneshot::error::TryRecvError::Closed)
+        );
+        assert_eq!(
+            second_cancelled.try_recv(),
+            Err(oneshot::error::TryRecvError::Empty)
+        );
+
+        // Removing a shed aggregation has no effect.
+        pending.remove(first);
+        assert_eq!(pending.len(), 2);
+    }
+
+    #[test]
+    fn slot_lag() {
+        let current_slot = Slot::new(100);
+
+        assert!(!exceeds_slot_lag(Slot::new(100), current_slot, 0));
+        assert!(exceeds_slot_lag(Slot::new(99), current_slot, 0));
+        assert!(!exceeds_slot_lag(Slot::new(98), current_slot, 2));
+        assert!(exceeds_slot_lag(Slot::new(97), current_slot, 2));
+        // Attestations for future slots are never stale.
+        assert!(!exceeds_slot_lag(Slot::new(101), current_slot, 0));
+    }
 }
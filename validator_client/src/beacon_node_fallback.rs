@@ -2,21 +2,31 @@
 //! "fallback" behaviour; it will try a request on all of the nodes until one or none of them
 //! succeed.
 
+use crate::beacon_node_health::{unix_time, BeaconNodeHealth, CandidateHealth};
 use crate::check_synced::check_synced;
-use crate::http_metrics::metrics::{inc_counter_vec, ENDPOINT_ERRORS, ENDPOINT_REQUESTS};
+use crate::http_metrics::metrics::{
+    inc_counter_vec, set_int_gauge, BEACON_NODES_AVAILABLE, BEACON_NODES_CONFIGURED,
+    BEACON_NODES_QUARANTINED, BEACON_NODES_SYNCED, BEACON_NODE_ACTIVE, BEACON_NODE_QUARANTINED,
+    BEACON_NODE_SYNCED, ENDPOINT_ERRORS, ENDPOINT_REQUESTS,
+};
 use environment::RuntimeContext;
-use eth2::BeaconNodeHttpClient;
+use eth2::{types::BlockId, BeaconNodeHttpClient};
 use futures::future;
+use parking_lot::Mutex;
+use serde_derive::{Deserialize, Serialize};
 use slog::{debug, error, info, warn, Logger};
 use slot_clock::SlotClock;
 use std::fmt;
 use std::fmt::Debug;
 use std::future::Future;
 use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use strum::{Display, EnumString, EnumVariantNames};
 use tokio::{sync::RwLock, time::sleep};
-use types::{ChainSpec, Config, EthSpec};
+use types::{ChainSpec, Config, EthSpec, Hash256, Slot};
 
 /// Message emitted when the VC detects the BN is using a different spec.
 const UPDATE_REQUIRED_LOG_HINT: &str = "this VC or the remote BN may need updating";
@@ -30,6 +40,68 @@ const UPDATE_REQUIRED_LOG_HINT: &str = "this VC or the remote BN may need updati
 /// having the correct nodes up and running prior to the start of the slot.
 const SLOT_LOOKAHEAD: Duration = Duration::from_secs(2);
 
+/// The number of consecutive consensus checks in which a beacon node must disagree with the
+/// majority head before it is quarantined.
+///
+/// Nodes regularly disagree for a single slot when a block arrives late, so a node is only
+/// quarantined once the disagreement has persisted for several slots.
+const CONSENSUS_CHECK_QUARANTINE_THRESHOLD: usize = 3;
+
+/// The order in which synced beacon nodes are tried for requests which only need a single
+/// response.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum BeaconNodeSelection {
+    /// Always try the nodes in the order they were configured.
+    #[default]
+    Priority,
+    /// Try a different node first for each request, cycling through the nodes.
+    RoundRobin,
+    /// Keep trying the node which served the previous request first, until it fails.
+    Sticky,
+}
+
+impl BeaconNodeSelection {
+    /// Reorders `ready`, the indices of the candidates which are ready to serve a request in the
+    /// order they were configured, into the order in which they should be tried.
+    ///
+    /// `last_used` is the index of the candidate which served the previous request, if any, and
+    /// `request` is the number of requests made so far.
+    fn order(self, ready: &mut [usize], last_used: Option<usize>, request: usize) {
+        if ready.is_empty() {
+            return;
+        }
+
+        match self {
+            BeaconNodeSelection::Priority => (),
+            BeaconNodeSelection::RoundRobin => ready.rotate_left(request % ready.len()),
+            BeaconNodeSelection::Sticky => {
+                if let Some(last_used) = last_used {
+                    // Continue from the last used candidate, or the next candidate after it if it
+                    // is no longer ready.
+                    let start = ready
+                        .iter()
+                        .position(|index| *index >= last_used)
+                        .unwrap_or(0);
+                    ready.rotate_left(start);
+                }
+            }
+        }
+    }
+}
+
 /// Indicates a measurement of latency between the VC and a BN.
 pub struct LatencyMeasurement {
     /// An identifier for the beacon node (e.g. the URL).
@@ -40,10 +112,13 @@ pub struct LatencyMeasurement {
 
 /// Starts a service that will routinely try and update the status of the provided `beacon_nodes`.
 ///
+/// The health metrics of `beacon_nodes` are published under the `kind` label after each update.
+///
 /// See `SLOT_LOOKAHEAD` for information about when this should run.
 pub fn start_fallback_updater_service<T: SlotClock + 'static, E: EthSpec>(
     context: RuntimeContext<E>,
     beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
+    kind: &'static str,
 ) -> Result<(), &'static str> {
     let executor = context.executor;
     if beacon_nodes.slot_clock.is_none() {
@@ -53,6 +128,8 @@ pub fn start_fallback_updater_service<T: SlotClock + 'static, E: EthSpec>(
     let future = async move {
         loop {
             beacon_nodes.update_all_candidates().await;
+            beacon_nodes.update_metrics(kind).await;
+            beacon_nodes.save_health().await;
 
             let sleep_time = beacon_nodes
                 .slot_clock
@@ -133,12 +210,55 @@ impl<E: Debug> fmt::Display for Errors<E> {
 }
 
 /// Reasons why a candidate might not be ready.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum CandidateError {
     Uninitialized,
     Offline,
     Incompatible,
     NotSynced,
+    Quarantined,
+}
+
+/// The result of comparing a beacon node's head with the heads of the other beacon nodes.
+#[derive(Debug, Default, Clone, Copy)]
+struct ConsensusStatus {
+    /// The number of consecutive checks in which the node's head differed from the majority.
+    disagreements: usize,
+    /// Set if the node has been excluded from use because of persistent disagreement.
+    quarantined: bool,
+}
+
+/// The identity of a beacon node, as last observed by the restart detection.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct RestartDetection {
+    /// The peer ID and metadata sequence number reported by the node.
+    identity: Option<(String, u64)>,
+    /// Set if the node was unreachable after its identity was observed.
+    unreachable: bool,
+}
+
+impl RestartDetection {
+    /// Records the `peer_id` and `seq_number` reported by the node, returning the reason for
+    /// which the node is considered to have restarted since the previous observation, if any.
+    fn observe(&mut self, peer_id: String, seq_number: u64) -> Option<&'static str> {
+        let reason = match &self.identity {
+            None => None,
+            Some((previous_peer_id, _)) if *previous_peer_id != peer_id => Some("peer_id_changed"),
+            Some((_, previous_seq_number)) if seq_number < *previous_seq_number => {
+                Some("metadata_reset")
+            }
+            Some(_) if self.unreachable => Some("reconnected"),
+            Some(_) => None,
+        };
+        self.identity = Some((peer_id, seq_number));
+        self.unreachable = false;
+        reason
+    }
+
+    /// Records that the node could not be reached.
+    fn observe_unreachable(&mut self) {
+        self.unreachable = self.identity.is_some();
+    }
 }
 
 /// Represents a `BeaconNodeHttpClient` inside a `BeaconNodeFallback` that may or may not be used
@@ -146,6 +266,8 @@ pub enum CandidateError {
 pub struct CandidateBeaconNode<E> {
     beacon_node: BeaconNodeHttpClient,
     status: RwLock<Result<(), CandidateError>>,
+    consensus: RwLock<ConsensusStatus>,
+    restart_detection: RwLock<RestartDetection>,
     _phantom: PhantomData<E>,
 }
 
@@ -155,6 +277,8 @@ impl<E: EthSpec> CandidateBeaconNode<E> {
         Self {
             beacon_node,
             status: RwLock::new(Err(CandidateError::Uninitialized)),
+            consensus: RwLock::new(ConsensusStatus::default()),
+            restart_detection: RwLock::new(RestartDetection::default()),
             _phantom: PhantomData,
         }
     }
@@ -162,13 +286,22 @@ impl<E: EthSpec> CandidateBeaconNode<E> {
     /// Returns the status of `self`.
     ///
     /// If `RequiredSynced::No`, any `NotSynced` node will be ignored and mapped to `Ok(())`.
+    /// Quarantined nodes are never available, regardless of `synced`.
     pub async fn status(&self, synced: RequireSynced) -> Result<(), CandidateError> {
+        if self.is_quarantined().await {
+            return Err(CandidateError::Quarantined);
+        }
         match *self.status.read().await {
             Err(CandidateError::NotSynced) if synced == false => Ok(()),
             other => other,
         }
     }
 
+    /// Returns `true` if `self` has been quarantined by the consensus check.
+    pub async fn is_quarantined(&self) -> bool {
+        self.consensus.read().await.quarantined
+    }
+
     /// Indicate that `self` is offline.
     pub async fn set_offline(&self) {
         *self.status.write().await = Err(CandidateError::Offline)
@@ -180,6 +313,7 @@ impl<E: EthSpec> CandidateBeaconNode<E> {
         &self,
         slot_clock: Option<&T>,
         spec: &ChainSpec,
+        genesis_validators_root: Option<Hash256>,
         log: &Logger,
     ) -> Result<(), CandidateError> {
         let previous_status = self.status(RequireSynced::Yes).await;
@@ -189,6 +323,11 @@ impl<E: EthSpec> CandidateBeaconNode<E> {
             Err(e)
         } else if let Err(e) = self.is_compatible(spec, log).await {
             Err(e)
+        } else if let Err(e) = self
+            .has_genesis_validators_root(genesis_validators_root, log)
+            .await
+        {
+            Err(e)
         } else if let Err(e) = self.is_synced(slot_clock, log).await {
             Err(e)
         } else {
@@ -204,6 +343,101 @@ impl<E: EthSpec> CandidateBeaconNode<E> {
         new_status
     }
 
+    /// Returns the slot and root of the head block of `self`, if it is online, compatible and
+    /// synced.
+    ///
+    /// Quarantined nodes are included so that they can be released once they agree with the
+    /// other nodes again.
+    async fn head(&self) -> Option<(Slot, Hash256)> {
+        if self.status.read().await.is_err() {
+            return None;
+        }
+        let header = self
+            .beacon_node
+            .get_beacon_headers_block_id(BlockId::Head)
+            .await
+            .ok()??
+            .data;
+        Some((header.header.message.slot, header.root))
+    }
+
+    /// Updates the quarantine status of `self` after comparing its `head` with the `majority`
+    /// head of all beacon nodes.
+    async fn update_consensus(
+        &self,
+        head: (Slot, Hash256),
+        majority: (Slot, Hash256),
+        log: &Logger,
+    ) {
+        let mut consensus = self.consensus.write().await;
+        let (head_slot, head_root) = head;
+        let (majority_slot, majority_root) = majority;
+
+        if head == majority {
+            if consensus.quarantined {
+                info!(
+                    log,
+                    "Releasing beacon node from quarantine";
+                    "msg" => "head agrees with the majority of beacon nodes",
+                    "endpoint" => %self.beacon_node,
+                    "head_slot" => head_slot,
+                    "head_root" => ?head_root,
+                );
+            }
+            *consensus = ConsensusStatus::default();
+            return;
+        }
+
+        consensus.disagreements += 1;
+        if consensus.quarantined {
+            return;
+        }
+        if consensus.disagreements >= CONSENSUS_CHECK_QUARANTINE_THRESHOLD {
+            consensus.quarantined = true;
+            warn!(
+                log,
+                "Quarantining beacon node";
+                "msg" => "head persistently disagrees with the majority of beacon nodes",
+                "endpoint" => %self.beacon_node,
+                "head_slot" => head_slot,
+                "head_root" => ?head_root,
+                "majority_slot" => majority_slot,
+                "majority_root" => ?majority_root,
+                "consecutive_disagreements" => consensus.disagreements,
+            );
+        } else {
+            debug!(
+                log,
+                "Beacon node head disagrees with majority";
+                "endpoint" => %self.beacon_node,
+                "head_slot" => head_slot,
+                "head_root" => ?head_root,
+                "majority_slot" => majority_slot,
+                "majority_root" => ?majority_root,
+                "consecutive_disagreements" => consensus.disagreements,
+            );
+        }
+    }
+
+    /// Queries the identity of the node, returning the reason for which it is considered to have
+    /// restarted since the previous query, if any.
+    ///
+    /// A node is considered to have restarted if its peer ID changes, if its metadata sequence
+    /// number decreases, or if it becomes reachable again after being unreachable.
+    async fn detect_restart(&self) -> Option<&'static str> {
+        let identity = self.beacon_node.get_node_identity().await;
+        let mut restart_detection = self.restart_detection.write().await;
+        match identity {
+            Ok(identity) => {
+                restart_detection.observe(identity.data.peer_id, identity.data.metadata.seq_number)
+            }
+            Err(_) => {
+                restart_detection.observe_unreachable();
+                None
+            }
+        }
+    }
+
     /// Checks if the node is reachable.
     async fn is_online(&self, was_offline: bool, log: &Logger) -> Result<(), CandidateError> {
         let result = self
@@ -301,6 +535,48 @@ impl<E: EthSpec> CandidateBeaconNode<E> {
         Ok(())
     }
 
+    /// Checks if the node reports `genesis_validators_root`, if any.
+    async fn has_genesis_validators_root(
+        &self,
+        genesis_validators_root: Option<Hash256>,
+        log: &Logger,
+    ) -> Result<(), CandidateError> {
+        let genesis_validators_root = match genesis_validators_root {
+            Some(genesis_validators_root) => genesis_validators_root,
+            None => return Ok(()),
+        };
+
+        let beacon_node_genesis_validators_root = self
+            .beacon_node
+            .get_beacon_genesis()
+            .await
+            .map_err(|e| {
+                error!(
+                    log,
+                    "Unable to read genesis from beacon node";
+                    "error" => %e,
+                    "endpoint" => %self.beacon_node,
+                );
+                CandidateError::Offline
+            })?
+            .data
+            .genesis_validators_root;
+
+        if beacon_node_genesis_validators_root != genesis_validators_root {
+            error!(
+                log,
+                "Beacon node has a different genesis validators root";
+                "msg" => "the beacon node is likely on a different network and will not be used",
+                "endpoint" => %self.beacon_node,
+                "bn_genesis_validators_root" => ?beacon_node_genesis_validators_root,
+                "our_genesis_validators_root" => ?genesis_validators_root,
+            );
+            return Err(CandidateError::Incompatible);
+        }
+
+        Ok(())
+    }
+
     /// Checks if the beacon node is synced.
     async fn is_synced<T: SlotClock>(
         &self,
@@ -323,6 +599,20 @@ pub struct BeaconNodeFallback<T, E> {
     candidates: Vec<CandidateBeaconNode<E>>,
     slot_clock: Option<T>,
     disable_run_on_all: bool,
+    consensus_check: bool,
+    selection: BeaconNodeSelection,
+    /// The number of requests made by `Self::first_success`, used for round-robin selection.
+    num_requests: AtomicUsize,
+    /// The index of the candidate which served the most recent successful request.
+    last_used: Mutex<Option<usize>>,
+    /// The file to which the health of the candidates is saved after each update, if any.
+    health_path: Option<PathBuf>,
+    /// The genesis validators root which all candidates must report, if any.
+    genesis_validators_root: Option<Hash256>,
+    restart_detection: bool,
+    /// The indices of the candidates which have restarted since the last call to
+    /// `Self::take_restarted_nodes`.
+    restarted: Mutex<Vec<usize>>,
     spec: ChainSpec,
     log: Logger,
 }
@@ -338,6 +628,14 @@ impl<T: SlotClock, E: EthSpec> BeaconNodeFallback<T, E> {
             candidates,
             slot_clock: None,
             disable_run_on_all,
+            consensus_check: false,
+            selection: BeaconNodeSelection::default(),
+            num_requests: AtomicUsize::new(0),
+            last_used: Mutex::new(None),
+            health_path: None,
+            genesis_validators_root: None,
+            restart_detection: false,
+            restarted: Mutex::new(vec![]),
             spec,
             log,
         }
@@ -352,6 +650,156 @@ impl<T: SlotClock, E: EthSpec> BeaconNodeFallback<T, E> {
         self.slot_clock = Some(slot_clock);
     }
 
+    /// Enables the comparison of candidate heads after each update, quarantining any candidate
+    /// which persistently disagrees with the majority.
+    pub fn enable_consensus_check(&mut self) {
+        self.consensus_check = true;
+    }
+
+    /// Requires all candidates to report `genesis_validators_root`, so that a candidate on a
+    /// different network is never used. Candidates are checked each time their status is
+    /// refreshed.
+    pub fn set_genesis_validators_root(&mut self, genesis_validators_root: Hash256) {
+        self.genesis_validators_root = Some(genesis_validators_root);
+    }
+
+    /// Enables the detection of candidate restarts after each update, see
+    /// `Self::take_restarted_nodes`.
+    pub fn enable_restart_detection(&mut self) {
+        self.restart_detection = true;
+    }
+
+    /// Sets the order in which synced candidates are tried by `Self::first_success`.
+    pub fn set_selection(&mut self, selection: BeaconNodeSelection) {
+        self.selection = selection;
+    }
+
+    /// Saves the health of the candidates to `path` after each update by the fallback updater
+    /// service, so that it can be restored with `Self::restore_health` after a restart.
+    pub fn set_health_path(&mut self, path: PathBuf) {
+        self.health_path = Some(path);
+    }
+
+    /// Restore the health of the candidates from the file given to `Self::set_health_path`.
+    ///
+    /// Nothing is restored if the file is missing, unreadable or older than
+    /// `MAX_HEALTH_STATE_AGE`. A candidate is only restored if its URL is unchanged, so that
+    /// reordering or replacing beacon nodes doesn't apply the health of one node to another.
+    pub async fn restore_health(&self) {
+        let Some(path) = self.health_path.as_ref() else {
+            return;
+        };
+        let health = match BeaconNodeHealth::load(path) {
+            Ok(Some(health)) => health,
+            Ok(None) => return,
+            Err(e) => {
+                warn!(
+                    self.log,
+                    "Unable to load beacon node health";
+                    "error" => ?e,
+                    "path" => %path.display(),
+                );
+                return;
+            }
+        };
+
+        let now = unix_time();
+        if health.is_stale(now) {
+            debug!(
+                self.log,
+                "Discarding stale beacon node health";
+                "age_secs" => health.age(now).as_secs(),
+                "path" => %path.display(),
+            );
+            return;
+        }
+
+        let mut num_restored = 0;
+        for (candidate, saved) in self.candidates.iter().zip(&health.candidates) {
+            if candidate.beacon_node.to_string() != saved.endpoint {
+                continue;
+            }
+            *candidate.status.write().await = saved.status;
+            *candidate.consensus.write().await = ConsensusStatus {
+                disagreements: saved.disagreements,
+                quarantined: saved.quarantined,
+            };
+            num_restored += 1;
+            if saved.quarantined {
+                warn!(
+                    self.log,
+                    "Beacon node remains quarantined";
+                    "msg" => "the node was quarantined before the last shutdown",
+                    "endpoint" => %candidate.beacon_node,
+                );
+            }
+        }
+
+        if let Some(last_used) = health.last_used {
+            let unchanged = self
+                .candidates
+                .get(last_used)
+                .zip(health.candidates.get(last_used))
+                .map_or(false, |(candidate, saved)| {
+                    candidate.beacon_node.to_string() == saved.endpoint
+                });
+            if unchanged {
+                *self.last_used.lock() = Some(last_used);
+            }
+        }
+
+        info!(
+            self.log,
+            "Restored beacon node health";
+            "restored" => num_restored,
+            "total" => self.candidates.len(),
+            "age_secs" => health.age(now).as_secs(),
+        );
+    }
+
+    /// Save the health of the candidates to the file given to `Self::set_health_path`, if any.
+    ///
+    /// Candidates are identified by their redacted URL to avoid writing secrets to disk.
+    pub async fn save_health(&self) {
+        let Some(path) = self.health_path.as_ref() else {
+            return;
+        };
+
+        let mut candidates = Vec::with_capacity(self.candidates.len());
+        for candidate in &self.candidates {
+            let status = *candidate.status.read().await;
+            let consensus = *candidate.consensus.read().await;
+            candidates.push(CandidateHealth {
+                endpoint: candidate.beacon_node.to_string(),
+                status,
+                disagreements: consensus.disagreements,
+                quarantined: consensus.quarantined,
+            });
+        }
+        let health = BeaconNodeHealth::new(*self.last_used.lock(), candidates);
+
+        if let Err(e) = health.save(path) {
+            warn!(
+                self.log,
+                "Unable to save beacon node health";
+                "error" => ?e,
+                "path" => %path.display(),
+            );
+        }
+    }
+
+    /// Record that the candidate at `index` served a request.
+    fn record_used(&self, index: usize) {
+        let previous = self.last_used.lock().replace(index);
+        if self.selection == BeaconNodeSelection::Sticky && previous.map_or(false, |i| i != index) {
+            info!(
+                self.log,
+                "Switched beacon node";
+                "node" => self.candidates[index].beacon_node.to_string(),
+            );
+        }
+    }
+
     /// The count of candidates, regardless of their state.
     pub fn num_total(&self) -> usize {
         self.candidates.len()
@@ -400,12 +848,123 @@ impl<T: SlotClock, E: EthSpec> BeaconNodeFallback<T, E> {
             .candidates
             .iter()
             .map(|candidate| {
-                candidate.refresh_status(self.slot_clock.as_ref(), &self.spec, &self.log)
+                candidate.refresh_status(
+                    self.slot_clock.as_ref(),
+                    &self.spec,
+                    self.genesis_validators_root,
+                    &self.log,
+                )
             })
             .collect::<Vec<_>>();
 
         // run all updates concurrently and ignore errors
         let _ = future::join_all(futures).await;
+
+        if self.consensus_check {
+            self.check_head_consensus().await;
+        }
+
+        if self.restart_detection {
+            self.detect_restarts().await;
+        }
+    }
+
+    /// Queries the identity of all candidates, recording those which have restarted since the
+    /// previous update.
+    async fn detect_restarts(&self) {
+        let reasons = future::join_all(
+            self.candidates
+                .iter()
+                .map(|candidate| candidate.detect_restart()),
+        )
+        .await;
+
+        let mut restarted = self.restarted.lock();
+        for (index, reason) in reasons.into_iter().enumerate() {
+            if let Some(reason) = reason {
+                info!(
+                    self.log,
+                    "Beacon node restart detected";
+                    "endpoint" => %self.candidates[index].beacon_node,
+                    "reason" => reason,
+                );
+                if !restarted.contains(&index) {
+                    restarted.push(index);
+                }
+            }
+        }
+    }
+
+    /// Returns the candidates which have restarted since the previous call, if restart detection
+    /// is enabled.
+    pub fn take_restarted_nodes(&self) -> Vec<BeaconNodeHttpClient> {
+        std::mem::take(&mut *self.restarted.lock())
+            .into_iter()
+            .map(|index| self.candidates[index].beacon_node.clone())
+            .collect()
+    }
+
+    /// Compare the heads of all online and synced candidates and update the quarantine status of
+    /// each.
+    ///
+    /// Nothing is changed unless a strict majority of candidates agree on a head, so at least
+    /// three candidates are required for a node to be quarantined.
+    async fn check_head_consensus(&self) {
+        let heads = future::join_all(self.candidates.iter().map(CandidateBeaconNode::head)).await;
+        let responses = heads.iter().flatten().copied().collect::<Vec<_>>();
+        let Some(majority) = majority_head(&responses) else {
+            return;
+        };
+
+        for (candidate, head) in self.candidates.iter().zip(heads) {
+            if let Some(head) = head {
+                candidate.update_consensus(head, majority, &self.log).await;
+            }
+        }
+    }
+
+    /// Publish the number of configured, available and synced candidates to the metrics server
+    /// under the `kind` label.
+    ///
+    /// Candidates are identified by their index rather than their URL to avoid leaking secrets.
+    pub async fn update_metrics(&self, kind: &str) {
+        let mut num_available = 0;
+        let mut num_synced = 0;
+        let mut num_quarantined = 0;
+        let last_used = *self.last_used.lock();
+        for (index, candidate) in self.candidates.iter().enumerate() {
+            let is_synced = candidate.status(RequireSynced::Yes).await.is_ok();
+            let is_quarantined = candidate.is_quarantined().await;
+            if is_quarantined {
+                num_quarantined += 1;
+            }
+            if candidate.status(RequireSynced::No).await.is_ok() {
+                num_available += 1;
+            }
+            if is_synced {
+                num_synced += 1;
+            }
+            set_int_gauge(
+                &BEACON_NODE_SYNCED,
+                &[kind, &index.to_string()],
+                is_synced as i64,
+            );
+            set_int_gauge(
+                &BEACON_NODE_QUARANTINED,
+                &[kind, &index.to_string()],
+                is_quarantined as i64,
+            );
+            set_int_gauge(
+                &BEACON_NODE_ACTIVE,
+                &[kind, &index.to_string()],
+                (last_used == Some(index)) as i64,
+            );
+        }
+
+        set_int_gauge(&BEACON_NODES_CONFIGURED, &[kind], self.num_total() as i64);
+        set_int_gauge(&BEACON_NODES_AVAILABLE, &[kind], num_available);
+        set_int_gauge(&BEACON_NODES_SYNCED, &[kind], num_synced);
+        set_int_gauge(&BEACON_NODES_QUARANTINED, &[kind], num_quarantined);
     }
 
     /// Concurrently send a request to all candidates (regardless of
@@ -449,12 +1008,42 @@ impl<T: SlotClock, E: EthSpec> BeaconNodeFallback<T, E> {
             .collect()
     }
 
+    /// Concurrently request the version string of all candidates, returning the endpoint of each
+    /// candidate with its version, or `None` if it did not respond.
+    pub async fn node_versions(&self) -> Vec<(String, Option<String>)> {
+        let futures = self.candidates.iter().map(|candidate| async {
+            let version = candidate
+                .beacon_node
+                .get_node_version()
+                .await
+                .ok()
+                .map(|body| body.data.version);
+            (candidate.beacon_node.to_string(), version)
+        });
+        future::join_all(futures).await
+    }
+
+    /// Concurrently request the genesis validators root of all candidates, returning the endpoint
+    /// of each candidate with its root, or `None` if it did not respond.
+    pub async fn genesis_validators_roots(&self) -> Vec<(String, Option<Hash256>)> {
+        let futures = self.candidates.iter().map(|candidate| async {
+            let genesis_validators_root = candidate
+                .beacon_node
+                .get_beacon_genesis()
+                .await
+                .ok()
+                .map(|body| body.data.genesis_validators_root);
+            (candidate.beacon_node.to_string(), genesis_validators_root)
+        });
+        future::join_all(futures).await
+    }
+
     /// Run `func` against each candidate in `self`, returning immediately if a result is found.
     /// Otherwise, return all the errors encountered along the way.
     ///
-    /// First this function will try all nodes with a suitable status. If no candidates are suitable
-    /// or all the requests fail, it will try updating the status of all unsuitable nodes and
-    /// re-running `func` again.
+    /// First this function will try all nodes with a suitable status, in the order given by
+    /// `self.selection`. If no candidates are suitable or all the requests fail, it will try
+    /// updating the status of all unsuitable nodes and re-running `func` again.
     pub async fn first_success<'a, F, O, Err, R>(
         &'a self,
         require_synced: RequireSynced,
@@ -476,13 +1065,16 @@ impl<T: SlotClock, E: EthSpec> BeaconNodeFallback<T, E> {
         // We use a macro instead of a closure here since it is not trivial to move `func` into a
         // closure.
         macro_rules! try_func {
-            ($candidate: ident) => {{
+            ($index: ident, $candidate: ident) => {{
                 inc_counter_vec(&ENDPOINT_REQUESTS, &[$candidate.beacon_node.as_ref()]);
 
                 // There exists a race condition where `func` may be called when the candidate is
                 // actually not ready. We deem this an acceptable inefficiency.
                 match func(&$candidate.beacon_node).await {
-                    Ok(val) => return Ok(val),
+                    Ok(val) => {
+                        self.record_used($index);
+                        return Ok(val);
+                    }
                     Err(e) => {
                         debug!(
                             log,
@@ -505,52 +1097,69 @@ impl<T: SlotClock, E: EthSpec> BeaconNodeFallback<T, E> {
             }};
         }
 
-        // First pass: try `func` on all synced and ready candidates.
+        // First pass: try `func` on all synced and ready candidates, in the order given by
+        // `self.selection`.
         //
         // This ensures that we always choose a synced node if it is available.
-        for candidate in &self.candidates {
+        let mut ready = vec![];
+        for (index, candidate) in self.candidates.iter().enumerate() {
             match candidate.status(RequireSynced::Yes).await {
                 Err(e @ CandidateError::NotSynced) if require_synced == false => {
                     // This client is unsynced we will try it after trying all synced clients
-                    retry_unsynced.push(candidate);
+                    retry_unsynced.push((index, candidate));
                     errors.push((candidate.beacon_node.to_string(), Error::Unavailable(e)));
                 }
                 Err(e) => {
                     // This client was not ready on the first pass, we might try it again later.
-                    to_retry.push(candidate);
+                    to_retry.push((index, candidate));
                     errors.push((candidate.beacon_node.to_string(), Error::Unavailable(e)));
                 }
-                _ => try_func!(candidate),
+                _ => ready.push(index),
             }
         }
 
+        let last_used = *self.last_used.lock();
+        let request = self.num_requests.fetch_add(1, Ordering::Relaxed);
+        self.selection.order(&mut ready, last_used, request);
+        for index in ready {
+            let candidate = &self.candidates[index];
+            try_func!(index, candidate);
+        }
+
         // Second pass: try `func` on ready unsynced candidates. This only runs if we permit
         // unsynced candidates.
         //
         // Due to async race-conditions, it is possible that we will send a request to a candidate
         // that has been set to an offline/unready status. This is acceptable.
         if require_synced == false {
-            for candidate in retry_unsynced {
-                try_func!(candidate);
+            for (index, candidate) in retry_unsynced {
+                try_func!(index, candidate);
             }
         }
 
         // Third pass: try again, attempting to make non-ready clients become ready.
-        for candidate in to_retry {
+        for (index, candidate) in to_retry {
             // If the candidate hasn't luckily transferred into the correct state in the meantime,
             // force an update of the state.
             let new_status = match candidate.status(require_synced).await {
                 Ok(()) => Ok(()),
                 Err(_) => {
                     candidate
-                        .refresh_status(self.slot_clock.as_ref(), &self.spec, &self.log)
+                        .refresh_status(
+                            self.slot_clock.as_ref(),
+                            &self.spec,
+                            self.genesis_validators_root,
+                            &self.log,
+                        )
                         .await
                 }
             };
 
             match new_status {
-                Ok(()) => try_func!(candidate),
-                Err(CandidateError::NotSynced) if require_synced == false => try_func!(candidate),
+                Ok(()) => try_func!(index, candidate),
+                Err(CandidateError::NotSynced) if require_synced == false => {
+                    try_func!(index, candidate)
+                }
                 Err(e) => {
                     errors.push((candidate.beacon_node.to_string(), Error::Unavailable(e)));
                 }
@@ -652,7 +1261,12 @@ impl<T: SlotClock, E: EthSpec> BeaconNodeFallback<T, E> {
                 Ok(()) => Ok(()),
                 Err(_) => {
                     candidate
-                        .refresh_status(self.slot_clock.as_ref(), &self.spec, &self.log)
+                        .refresh_status(
+                            self.slot_clock.as_ref(),
+                            &self.spec,
+                            self.genesis_validators_root,
+                            &self.log,
+                        )
                         .await
                 }
             };
@@ -701,3 +1315,99 @@ impl<T: SlotClock, E: EthSpec> BeaconNodeFallback<T, E> {
         }
     }
 }
+
+/// Returns the head reported by a strict majority of `heads`, if any.
+fn majority_head(heads: &[(Slot, Hash256)]) -> Option<(Slot, Hash256)> {
+    heads
+        .iter()
+        .find(|head| heads.iter().filter(|other| other == head).count() * 2 > heads.len())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restart_detection() {
+        let mut detection = RestartDetection::default();
+        let peer_id = || "peer".to_string();
+
+        assert_eq!(detection.observe(peer_id(), 5), None);
+        assert_eq!(detection.observe(peer_id(), 6), None);
+        assert_eq!(detection.observe(peer_id(), 2), Some("metadata_reset"));
+        assert_eq!(
+            detection.observe("other".to_string(), 2),
+            Some("peer_id_changed")
+        );
+
+        detection.observe_unreachable();
+        assert_eq!(
+            detection.observe("other".to_string(), 2),
+            Some("reconnected")
+        );
+        assert_eq!(detection.observe("other".to_string(), 2), None);
+
+        // A node which has never been reached is not considered to have restarted.
+        let mut detection = RestartDetection::default();
+        detection.observe_unreachable();
+        assert_eq!(detection.observe(peer_id(), 0), None);
+    }
+
+    #[test]
+    fn priority_selection() {
+        let mut ready = vec![0, 2, 3];
+        BeaconNodeSelection::Priority.order(&mut ready, Some(2), 1);
+        assert_eq!(ready, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn round_robin_selection() {
+        let order = |request| {
+            let mut ready = vec![0, 2, 3];
+            BeaconNodeSelection::RoundRobin.order(&mut ready, None, request);
+            ready
+        };
+        assert_eq!(order(0), vec![0, 2, 3]);
+        assert_eq!(order(1), vec![2, 3, 0]);
+        assert_eq!(order(2), vec![3, 0, 2]);
+        assert_eq!(order(3), vec![0, 2, 3]);
+
+        let mut ready = vec![];
+        BeaconNodeSelection::RoundRobin.order(&mut ready, None, 1);
+        assert!(ready.is_empty());
+    }
+
+    #[test]
+    fn sticky_selection() {
+        let order = |last_used| {
+            let mut ready = vec![0, 2, 3];
+            BeaconNodeSelection::Sticky.order(&mut ready, last_used, 1);
+            ready
+        };
+        // No node has served a request yet.
+        assert_eq!(order(None), vec![0, 2, 3]);
+        // The last used node is kept, even if an earlier node is ready.
+        assert_eq!(order(Some(2)), vec![2, 3, 0]);
+        assert_eq!(order(Some(3)), vec![3, 0, 2]);
+        // The last used node is not ready, so the next node is tried first.
+        assert_eq!(order(Some(1)), vec![2, 3, 0]);
+        assert_eq!(order(Some(4)), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn majority_head_requires_strict_majority() {
+        let a = (Slot::new(10), Hash256::repeat_byte(1));
+        let b = (Slot::new(9), Hash256::repeat_byte(2));
+        let c = (Slot::new(10), Hash256::repeat_byte(3));
+
+        assert_eq!(majority_head(&[]), None);
+        assert_eq!(majority_head(&[a]), Some(a));
+        assert_eq!(majority_head(&[a, a]), Some(a));
+        assert_eq!(majority_head(&[a, b]), None);
+        assert_eq!(majority_head(&[a, a, b]), Some(a));
+        assert_eq!(majority_head(&[a, b, c]), None);
+        assert_eq!(majority_head(&[b, a, c, a, a]), Some(a));
+        assert_eq!(majority_head(&[a, a, b, b]), None);
+    }
+}
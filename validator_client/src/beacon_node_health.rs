@@ -0,0 +1,157 @@
+//! Persistence of the health of the beacon nodes in a `BeaconNodeFallback` across restarts.
+//!
+//! Without it, a restarted validator client forgets which beacon nodes were quarantined by the
+//! consensus check and which node served the previous request, and may briefly route requests to
+//! a node that was known to be bad.
+
+use crate::beacon_node_fallback::CandidateError;
+use account_utils::write_file_via_temporary;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Health state saved longer than this ago is discarded rather than restored.
+pub const MAX_HEALTH_STATE_AGE: Duration = Duration::from_secs(600);
+
+#[derive(Debug)]
+pub enum Error {
+    /// The health file could not be opened.
+    UnableToOpenFile(io::Error),
+    /// The health file could not be parsed as JSON.
+    UnableToParseFile(serde_json::Error),
+    /// The health state could not be serialized as JSON.
+    UnableToEncodeFile(serde_json::Error),
+    /// The health file or its temporary could not be written to the filesystem.
+    UnableToWriteFile(filesystem::Error),
+}
+
+/// Returns the path of the health file for the beacon nodes published under the `kind` metrics
+/// label.
+pub fn health_path<P: AsRef<Path>>(validators_dir: P, kind: &str) -> PathBuf {
+    validators_dir
+        .as_ref()
+        .join(format!("beacon_node_health_{}.json", kind))
+}
+
+/// The saved health of a single candidate beacon node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateHealth {
+    /// The redacted URL of the node, used to detect changes to the list of beacon nodes.
+    pub endpoint: String,
+    pub status: Result<(), CandidateError>,
+    pub disagreements: usize,
+    pub quarantined: bool,
+}
+
+/// The saved health of all candidates of a `BeaconNodeFallback`, in the order they were given.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BeaconNodeHealth {
+    /// The number of seconds since the UNIX epoch at which the state was saved.
+    pub saved_at: u64,
+    /// The index of the candidate which served the most recent successful request.
+    pub last_used: Option<usize>,
+    pub candidates: Vec<CandidateHealth>,
+}
+
+impl BeaconNodeHealth {
+    /// Returns the health state with the current time as `saved_at`.
+    pub fn new(last_used: Option<usize>, candidates: Vec<CandidateHealth>) -> Self {
+        Self {
+            saved_at: unix_time().as_secs(),
+            last_used,
+            candidates,
+        }
+    }
+
+    /// Read the health file at `path`, returning `Ok(None)` if it does not exist.
+    pub fn load(path: &Path) -> Result<Option<Self>, Error> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(path).map_err(Error::UnableToOpenFile)?;
+        serde_json::from_reader(file)
+            .map(Some)
+            .map_err(Error::UnableToParseFile)
+    }
+
+    /// Write the health state to `path`, replacing any existing file.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+        let bytes = serde_json::to_vec(self).map_err(Error::UnableToEncodeFile)?;
+        write_file_via_temporary(path, &temp_path, &bytes).map_err(Error::UnableToWriteFile)
+    }
+
+    /// Returns the time elapsed between `self.saved_at` and `now`, a duration since the UNIX
+    /// epoch.
+    ///
+    /// A state saved in the future is considered to be saved at `now`.
+    pub fn age(&self, now: Duration) -> Duration {
+        now.saturating_sub(Duration::from_secs(self.saved_at))
+    }
+
+    /// Returns `true` if the state is too old to be restored at `now`.
+    pub fn is_stale(&self, now: Duration) -> bool {
+        self.age(now) > MAX_HEALTH_STATE_AGE
+    }
+}
+
+/// Returns the current duration since the UNIX epoch.
+pub fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn health() -> BeaconNodeHealth {
+        BeaconNodeHealth::new(
+            Some(1),
+            vec![
+                CandidateHealth {
+                    endpoint: "http://localhost:5052/".into(),
+                    status: Err(CandidateError::Offline),
+                    disagreements: 0,
+                    quarantined: false,
+                },
+                CandidateHealth {
+                    endpoint: "http://localhost:5053/".into(),
+                    status: Ok(()),
+                    disagreements: 4,
+                    quarantined: true,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = TempDir::new().unwrap();
+        let path = health_path(dir.path(), "beacon_nodes");
+        assert_eq!(BeaconNodeHealth::load(&path).unwrap(), None);
+
+        let health = health();
+        health.save(&path).unwrap();
+        assert_eq!(BeaconNodeHealth::load(&path).unwrap(), Some(health));
+    }
+
+    #[test]
+    fn stale_state() {
+        let health = health();
+        let saved_at = Duration::from_secs(health.saved_at);
+
+        assert!(!health.is_stale(saved_at));
+        assert!(!health.is_stale(saved_at - Duration::from_secs(1)));
+        assert!(!health.is_stale(saved_at + MAX_HEALTH_STATE_AGE));
+        assert!(health.is_stale(saved_at + MAX_HEALTH_STATE_AGE + Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,105 @@
+//! Detection of beacon nodes which run different clients or client versions.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The client and version of a beacon node, parsed from its `node/version` string.
+///
+/// Clients format the string differently, e.g. `Lighthouse/v4.2.0-1234abcd/x86_64-linux`,
+/// `teku/v23.6.0/linux-x86_64/-eclipseadoptium-openjdk64bitservervm-java-17` or
+/// `Nimbus/v23.5.1-4842c9-stateofus`, so only the client name and the numeric part of the version
+/// are compared.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NodeVersion {
+    /// The lowercase client name.
+    pub client: String,
+    /// The dot-separated numeric version, without any `v` prefix or build suffix.
+    pub version: Option<String>,
+}
+
+impl NodeVersion {
+    pub fn parse(version: &str) -> Self {
+        let mut parts = version.trim().split('/');
+        let client = parts.next().unwrap_or_default().trim().to_lowercase();
+        let version = parts.next().and_then(|version| {
+            let version = version.trim().trim_start_matches(['v', 'V']);
+            let numeric: String = version
+                .chars()
+                .take_while(|c| c.is_ascii_digit() || *c == '.')
+                .collect();
+            let numeric = numeric.trim_end_matches('.');
+            (!numeric.is_empty()).then(|| numeric.to_string())
+        });
+        Self { client, version }
+    }
+}
+
+impl fmt::Display for NodeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}/{}", self.client, version),
+            None => write!(f, "{}", self.client),
+        }
+    }
+}
+
+/// Returns the distinct client versions among `versions`.
+pub fn distinct_versions<'a>(versions: impl IntoIterator<Item = &'a str>) -> BTreeSet<NodeVersion> {
+    versions.into_iter().map(NodeVersion::parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(client: &str, version: Option<&str>) -> NodeVersion {
+        NodeVersion {
+            client: client.into(),
+            version: version.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn parse_client_versions() {
+        assert_eq!(
+            NodeVersion::parse("Lighthouse/v4.2.0-1234abcd/x86_64-linux"),
+            version("lighthouse", Some("4.2.0"))
+        );
+        assert_eq!(
+            NodeVersion::parse(
+                "teku/v23.6.0/linux-x86_64/-eclipseadoptium-openjdk64bitservervm-java-17"
+            ),
+            version("teku", Some("23.6.0"))
+        );
+        assert_eq!(
+            NodeVersion::parse("Nimbus/v23.5.1-4842c9-stateofus"),
+            version("nimbus", Some("23.5.1"))
+        );
+        assert_eq!(
+            NodeVersion::parse("Prysm/v4.0.5/3a0e6fd7ae8b5b7d2e9d3e5e8e4ef6c0e4fd7f3c"),
+            version("prysm", Some("4.0.5"))
+        );
+        assert_eq!(
+            NodeVersion::parse("Lodestar/1.8.0/c4a3b5e"),
+            version("lodestar", Some("1.8.0"))
+        );
+        assert_eq!(NodeVersion::parse("Unknown"), version("unknown", None));
+        assert_eq!(NodeVersion::parse("Custom/dev"), version("custom", None));
+    }
+
+    #[test]
+    fn distinct() {
+        let versions = distinct_versions([
+            "Lighthouse/v4.2.0-1234abcd/x86_64-linux",
+            "Lighthouse/v4.2.0-5678ef90/aarch64-linux",
+        ]);
+        assert_eq!(versions.len(), 1);
+
+        let versions = distinct_versions([
+            "Lighthouse/v4.2.0-1234abcd/x86_64-linux",
+            "Lighthouse/v4.1.0-1234abcd/x86_64-linux",
+            "teku/v23.6.0/linux-x86_64",
+        ]);
+        assert_eq!(versions.len(), 3);
+    }
+}
@@ -1,33 +1,88 @@
 use crate::beacon_node_fallback::{Error as FallbackError, Errors};
 use crate::{
     beacon_node_fallback::{BeaconNodeFallback, RequireSynced},
+    check_synced::is_optimistic,
     determine_graffiti,
+    duties_service::DutiesService,
     graffiti_file::GraffitiFile,
+    preparation_service::OnMissingFeeRecipient,
+    skip_reason::{self, SkipReason},
     OfflineOnFailure,
 };
 use crate::{http_metrics::metrics, validator_store::ValidatorStore};
 use environment::RuntimeContext;
+use eth2::types::{BlockId, BlockProductionLimits, SkipRandaoVerification, StateId, ValidatorId};
 use eth2::BeaconNodeHttpClient;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use parking_lot::Mutex;
+use serde_derive::{Deserialize, Serialize};
 use slog::{crit, debug, error, info, trace, warn};
 use slot_clock::SlotClock;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::future::Future;
 use std::ops::Deref;
 use std::sync::Arc;
 use std::time::Duration;
+use strum::{Display, EnumString, EnumVariantNames};
 use tokio::sync::mpsc;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
 use types::{
-    AbstractExecPayload, BlindedPayload, BlockType, EthSpec, FullPayload, Graffiti, PublicKeyBytes,
-    Slot,
+    AbstractExecPayload, Address, BeaconBlock, BlindedPayload, BlockType, Epoch, EthSpec,
+    ExecPayload, FullPayload, Graffiti, Hash256, PublicKeyBytes, Slot, GRAFFITI_BYTES_LEN,
 };
 
+/// A graffiti token which is replaced by the source of the execution payload of the block, i.e.
+/// `builder` for blinded blocks and `local` for full blocks.
+pub const BLOCK_SOURCE_GRAFFITI_TOKEN: &str = "{block_source}";
+
+/// A graffiti token which is replaced by the name of the network, e.g. `mainnet`.
+pub const NETWORK_GRAFFITI_TOKEN: &str = "{network}";
+
+/// The action to take when a builder proposal fails, e.g. because the beacon node is unable to
+/// reach any relay.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum BuilderUnavailableAction {
+    /// Propose a block with a payload from the local execution engine.
+    #[default]
+    Local,
+    /// Decline to propose the block.
+    Skip,
+}
+
 #[derive(Debug)]
 pub enum BlockError {
     Recoverable(String),
     Irrecoverable(String),
 }
 
+/// A mismatch between a block produced by a beacon node and the block that was requested.
+#[derive(Debug, PartialEq)]
+pub enum BlockVerificationError {
+    /// The block is for a different slot than the one requested.
+    WrongSlot { requested: Slot, block: Slot },
+    /// The block is proposed by a different validator, possibly due to a re-org.
+    WrongProposer { expected: Option<u64>, block: u64 },
+    /// The parent root of the block is zero, which is only valid for the genesis block.
+    ZeroParentRoot,
+    /// The execution payload of the block pays a different fee recipient than the one configured
+    /// for the validator.
+    WrongFeeRecipient { expected: Address, block: Address },
+}
+
 impl From<Errors<BlockError>> for BlockError {
     fn from(e: Errors<BlockError>) -> Self {
         if e.0.iter().any(|(_, error)| {
@@ -51,8 +106,17 @@ pub struct BlockServiceBuilder<T, E: EthSpec> {
     proposer_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
     context: Option<RuntimeContext<E>>,
     graffiti: Option<Graffiti>,
+    graffiti_force: bool,
     graffiti_file: Option<GraffitiFile>,
     block_delay: Option<Duration>,
+    builder_unavailable_action: BuilderUnavailableAction,
+    verify_block_before_signing: bool,
+    verify_fee_recipient: bool,
+    refuse_proposal_on_optimistic: bool,
+    network_name: String,
+    duties_service: Option<Arc<DutiesService<T, E>>>,
+    block_prefetch_lead: Option<Duration>,
+    block_production_limits: BlockProductionLimits,
 }
 
 impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
@@ -64,8 +128,17 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
             proposer_nodes: None,
             context: None,
             graffiti: None,
+            graffiti_force: false,
             graffiti_file: None,
             block_delay: None,
+            builder_unavailable_action: BuilderUnavailableAction::default(),
+            verify_block_before_signing: true,
+            verify_fee_recipient: true,
+            refuse_proposal_on_optimistic: true,
+            network_name: String::new(),
+            duties_service: None,
+            block_prefetch_lead: None,
+            block_production_limits: BlockProductionLimits::default(),
         }
     }
 
@@ -99,6 +172,11 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
         self
     }
 
+    pub fn graffiti_force(mut self, graffiti_force: bool) -> Self {
+        self.graffiti_force = graffiti_force;
+        self
+    }
+
     pub fn graffiti_file(mut self, graffiti_file: Option<GraffitiFile>) -> Self {
         self.graffiti_file = graffiti_file;
         self
@@ -109,7 +187,60 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
         self
     }
 
+    pub fn builder_unavailable_action(
+        mut self,
+        builder_unavailable_action: BuilderUnavailableAction,
+    ) -> Self {
+        self.builder_unavailable_action = builder_unavailable_action;
+        self
+    }
+
+    pub fn verify_block_before_signing(mut self, verify_block_before_signing: bool) -> Self {
+        self.verify_block_before_signing = verify_block_before_signing;
+        self
+    }
+
+    pub fn verify_fee_recipient(mut self, verify_fee_recipient: bool) -> Self {
+        self.verify_fee_recipient = verify_fee_recipient;
+        self
+    }
+
+    pub fn refuse_proposal_on_optimistic(mut self, refuse_proposal_on_optimistic: bool) -> Self {
+        self.refuse_proposal_on_optimistic = refuse_proposal_on_optimistic;
+        self
+    }
+
+    pub fn network_name(mut self, network_name: String) -> Self {
+        self.network_name = network_name;
+        self
+    }
+
+    pub fn duties_service(mut self, duties_service: Arc<DutiesService<T, E>>) -> Self {
+        self.duties_service = Some(duties_service);
+        self
+    }
+
+    pub fn block_prefetch_lead(mut self, block_prefetch_lead: Option<Duration>) -> Self {
+        self.block_prefetch_lead = block_prefetch_lead;
+        self
+    }
+
+    pub fn block_production_limits(
+        mut self,
+        block_production_limits: BlockProductionLimits,
+    ) -> Self {
+        self.block_production_limits = block_production_limits;
+        self
+    }
+
     pub fn build(self) -> Result<BlockService<T, E>, String> {
+        if self.block_prefetch_lead.is_some() && self.duties_service.is_none() {
+            return Err(
+                "Cannot build BlockService with block_prefetch_lead without duties_service"
+                    .to_string(),
+            );
+        }
+
         Ok(BlockService {
             inner: Arc::new(Inner {
                 validator_store: self
@@ -126,8 +257,18 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockServiceBuilder<T, E> {
                     .ok_or("Cannot build BlockService without runtime_context")?,
                 proposer_nodes: self.proposer_nodes,
                 graffiti: self.graffiti,
+                graffiti_force: self.graffiti_force,
                 graffiti_file: self.graffiti_file,
                 block_delay: self.block_delay,
+                builder_unavailable_action: self.builder_unavailable_action,
+                verify_block_before_signing: self.verify_block_before_signing,
+                verify_fee_recipient: self.verify_fee_recipient,
+                refuse_proposal_on_optimistic: self.refuse_proposal_on_optimistic,
+                network_name: self.network_name,
+                duties_service: self.duties_service,
+                block_prefetch_lead: self.block_prefetch_lead,
+                block_production_limits: self.block_production_limits,
+                prefetched_blocks: Mutex::new(PrefetchedBlocks::default()),
             }),
         })
     }
@@ -210,8 +351,77 @@ pub struct Inner<T, E: EthSpec> {
     proposer_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
     context: RuntimeContext<E>,
     graffiti: Option<Graffiti>,
+    graffiti_force: bool,
     graffiti_file: Option<GraffitiFile>,
     block_delay: Option<Duration>,
+    builder_unavailable_action: BuilderUnavailableAction,
+    verify_block_before_signing: bool,
+    verify_fee_recipient: bool,
+    refuse_proposal_on_optimistic: bool,
+    network_name: String,
+    duties_service: Option<Arc<DutiesService<T, E>>>,
+    block_prefetch_lead: Option<Duration>,
+    /// Limits on the contents of locally built blocks, which are not applied to blinded blocks.
+    block_production_limits: BlockProductionLimits,
+    /// Unsigned blocks requested before the start of their slot, at most one per proposer.
+    prefetched_blocks: Mutex<PrefetchedBlocks<E>>,
+}
+
+/// The root of the head block of the beacon nodes, shared by all of the prefetched blocks of a
+/// slot.
+type HeadCheck = Shared<BoxFuture<'static, Result<Hash256, String>>>;
+
+/// The blocks requested by `BlockService::prefetch_block`, keyed by slot and proposer.
+#[derive(Default)]
+pub struct PrefetchedBlocks<E: EthSpec> {
+    full: HashMap<(Slot, PublicKeyBytes), BeaconBlock<E, FullPayload<E>>>,
+    blinded: HashMap<(Slot, PublicKeyBytes), BeaconBlock<E, BlindedPayload<E>>>,
+    /// The head check started at the start of the slot of the prefetched blocks.
+    head_check: Option<(Slot, HeadCheck)>,
+}
+
+impl<E: EthSpec> PrefetchedBlocks<E> {
+    fn has_slot(&self, slot: Slot) -> bool {
+        self.full.keys().any(|(block_slot, _)| *block_slot == slot)
+            || self
+                .blinded
+                .keys()
+                .any(|(block_slot, _)| *block_slot == slot)
+    }
+
+    /// Discards the blocks and head check of the slots before `slot`, which were never proposed.
+    fn prune(&mut self, slot: Slot) {
+        self.full.retain(|(block_slot, _), _| *block_slot >= slot);
+        self.blinded
+            .retain(|(block_slot, _), _| *block_slot >= slot);
+        if matches!(self.head_check, Some((check_slot, _)) if check_slot < slot) {
+            self.head_check = None;
+        }
+    }
+}
+
+/// A payload for which blocks can be prefetched, selecting the map in `PrefetchedBlocks` which
+/// holds its blocks.
+pub trait PrefetchPayload<E: EthSpec>: AbstractExecPayload<E> {
+    fn blocks(
+        prefetched: &mut PrefetchedBlocks<E>,
+    ) -> &mut HashMap<(Slot, PublicKeyBytes), BeaconBlock<E, Self>>;
+}
+
+impl<E: EthSpec> PrefetchPayload<E> for FullPayload<E> {
+    fn blocks(
+        prefetched: &mut PrefetchedBlocks<E>,
+    ) -> &mut HashMap<(Slot, PublicKeyBytes), BeaconBlock<E, Self>> {
+        &mut prefetched.full
+    }
+}
+
+impl<E: EthSpec> PrefetchPayload<E> for BlindedPayload<E> {
+    fn blocks(
+        prefetched: &mut PrefetchedBlocks<E>,
+    ) -> &mut HashMap<(Slot, PublicKeyBytes), BeaconBlock<E, Self>> {
+        &mut prefetched.blinded
+    }
 }
 
 /// Attempts to produce attestations for any block producer(s) at the start of the epoch.
@@ -252,6 +462,15 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
 
         let executor = self.inner.context.executor.clone();
 
+        if let Some(lead) = self.block_prefetch_lead {
+            info!(
+                log,
+                "Block prefetching enabled";
+                "lead_ms" => lead.as_millis(),
+            );
+            self.clone().start_prefetch_service(lead);
+        }
+
         executor.spawn(
             async move {
                 while let Some(notif) = notification_rx.recv().await {
@@ -276,6 +495,231 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
         Ok(())
     }
 
+    /// Starts a service which requests the blocks of the proposers of each slot `lead` before the
+    /// slot starts.
+    ///
+    /// The blocks are not signed until they are proposed at the start of the slot by
+    /// `publish_block`, and are discarded if the head has changed by then.
+    fn start_prefetch_service(self, lead: Duration) {
+        let executor = self.context.executor.clone();
+
+        executor.spawn(
+            async move {
+                loop {
+                    let (slot, duration_to_next_slot) = match (
+                        self.slot_clock.now(),
+                        self.slot_clock.duration_to_next_slot(),
+                    ) {
+                        (Some(slot), Some(duration)) => (slot, duration),
+                        _ => {
+                            sleep(self.slot_clock.slot_duration()).await;
+                            continue;
+                        }
+                    };
+                    let next_slot = slot + 1;
+
+                    sleep(duration_to_next_slot.saturating_sub(lead)).await;
+                    self.prefetch_blocks(next_slot, lead);
+
+                    // Wait for the slot to start, so that its blocks are only requested once.
+                    if let Some(duration) = self.slot_clock.duration_to_slot(next_slot) {
+                        sleep(duration).await;
+                    }
+                    self.start_head_check(next_slot);
+                }
+            },
+            "block_prefetch",
+        );
+    }
+
+    /// Requests the block of each local proposer at `slot`, abandoning any request which has not
+    /// completed within `lead`.
+    fn prefetch_blocks(&self, slot: Slot, lead: Duration) {
+        let proposers = match &self.duties_service {
+            Some(duties_service) => duties_service.block_proposers(slot),
+            None => return,
+        };
+        if !self.validator_store.startup_warmup_complete(slot) {
+            return;
+        }
+
+        for validator_pubkey in proposers {
+            let service = self.clone();
+            self.context.executor.spawn(
+                async move {
+                    let log = service.context.log();
+                    let builder_proposals = service
+                        .validator_store
+                        .get_builder_proposals(&validator_pubkey);
+                    let result = if builder_proposals {
+                        timeout(
+                            lead,
+                            service.prefetch_block::<BlindedPayload<E>>(slot, validator_pubkey),
+                        )
+                        .await
+                    } else {
+                        timeout(
+                            lead,
+                            service.prefetch_block::<FullPayload<E>>(slot, validator_pubkey),
+                        )
+                        .await
+                    };
+
+                    let reason = match result {
+                        Ok(Ok(())) => return,
+                        Ok(Err(e)) => {
+                            debug!(
+                                log,
+                                "Unable to prefetch block";
+                                "error" => ?e,
+                                "slot" => slot.as_u64(),
+                            );
+                            metrics::PREFETCH_FAILED
+                        }
+                        Err(_) => {
+                            debug!(
+                                log,
+                                "Block prefetch timed out";
+                                "lead_ms" => lead.as_millis(),
+                                "slot" => slot.as_u64(),
+                            );
+                            metrics::PREFETCH_LATE
+                        }
+                    };
+                    metrics::inc_counter_vec(&metrics::BLOCK_PREFETCH_MISSES_TOTAL, &[reason]);
+                },
+                "block_prefetch",
+            );
+        }
+    }
+
+    /// Requests the block of `validator_pubkey` at `slot` ahead of the slot, keeping it for
+    /// `Self::take_prefetched_block`.
+    pub(crate) async fn prefetch_block<Payload: PrefetchPayload<E>>(
+        &self,
+        slot: Slot,
+        validator_pubkey: PublicKeyBytes,
+    ) -> Result<(), BlockError> {
+        let graffiti = self.block_graffiti::<Payload>(&validator_pubkey, slot);
+        let block = self
+            .produce_block::<Payload>(slot, validator_pubkey, graffiti)
+            .await?;
+
+        let mut prefetched_blocks = self.prefetched_blocks.lock();
+        prefetched_blocks.prune(slot);
+        Payload::blocks(&mut prefetched_blocks).insert((slot, validator_pubkey), block);
+        Ok(())
+    }
+
+    /// Starts checking the head as soon as `slot` starts if any blocks were prefetched for it, so
+    /// that the check has usually completed by the time the blocks are proposed.
+    fn start_head_check(&self, slot: Slot) {
+        let head_check = {
+            let mut prefetched_blocks = self.prefetched_blocks.lock();
+            if !prefetched_blocks.has_slot(slot) {
+                return;
+            }
+            let head_check = self.head_check();
+            prefetched_blocks.head_check = Some((slot, head_check.clone()));
+            head_check
+        };
+
+        self.context.executor.spawn(
+            async move {
+                // The result is read by `Self::take_prefetched_block`.
+                let _ = head_check.await;
+            },
+            "block_prefetch_head",
+        );
+    }
+
+    /// Returns a future which fetches the root of the head block of the beacon nodes.
+    fn head_check(&self) -> HeadCheck {
+        let proposer_fallback = ProposerFallback {
+            beacon_nodes: self.beacon_nodes.clone(),
+            proposer_nodes: self.proposer_nodes.clone(),
+        };
+        async move {
+            proposer_fallback
+                .first_success_try_proposers_last(
+                    RequireSynced::No,
+                    OfflineOnFailure::No,
+                    |beacon_node| async move {
+                        beacon_node
+                            .get_beacon_blocks_root(BlockId::Head)
+                            .await
+                            .map_err(|e| format!("Unable to fetch head: {:?}", e))?
+                            .map(|response| response.data.root)
+                            .ok_or_else(|| "Head block not found".to_string())
+                    },
+                )
+                .await
+                .map_err(|e| e.to_string())
+        }
+        .boxed()
+        .shared()
+    }
+
+    /// Returns the block of `validator_pubkey` at `slot` which was requested ahead of the slot, if
+    /// it was requested with the same `Payload` and the head has not changed since.
+    ///
+    /// The block is removed, so that it is proposed at most once.
+    async fn take_prefetched_block<Payload: PrefetchPayload<E>>(
+        &self,
+        slot: Slot,
+        validator_pubkey: PublicKeyBytes,
+    ) -> Option<BeaconBlock<E, Payload>> {
+        let (block, head_check) = {
+            let mut prefetched_blocks = self.prefetched_blocks.lock();
+            let block =
+                Payload::blocks(&mut prefetched_blocks).remove(&(slot, validator_pubkey))?;
+            let head_check = match &prefetched_blocks.head_check {
+                Some((check_slot, head_check)) if *check_slot == slot => head_check.clone(),
+                _ => self.head_check(),
+            };
+            (block, head_check)
+        };
+
+        let log = self.context.log();
+        let parent_root = block.parent_root();
+        let head = head_check.await;
+
+        let reason = match head {
+            Ok(head) if head == parent_root => {
+                debug!(
+                    log,
+                    "Proposing prefetched block";
+                    "slot" => slot.as_u64(),
+                );
+                metrics::inc_counter(&metrics::BLOCK_PREFETCH_HITS_TOTAL);
+                return Some(block);
+            }
+            Ok(head) => {
+                debug!(
+                    log,
+                    "Discarding prefetched block";
+                    "msg" => "the head has changed since the block was requested",
+                    "head" => ?head,
+                    "parent_root" => ?parent_root,
+                    "slot" => slot.as_u64(),
+                );
+                metrics::PREFETCH_HEAD_CHANGED
+            }
+            Err(e) => {
+                debug!(
+                    log,
+                    "Discarding prefetched block";
+                    "msg" => "unable to check whether the head has changed",
+                    "error" => %e,
+                    "slot" => slot.as_u64(),
+                );
+                metrics::PREFETCH_HEAD_UNKNOWN
+            }
+        };
+        metrics::inc_counter_vec(&metrics::BLOCK_PREFETCH_MISSES_TOTAL, &[reason]);
+        None
+    }
+
     /// Attempt to produce a block for any block producers in the `ValidatorStore`.
     async fn do_update(&self, notification: BlockServiceNotification) -> Result<(), ()> {
         let log = self.context.log();
@@ -292,8 +736,32 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                 "Skipping block production for expired slot";
                 "current_slot" => slot.as_u64(),
                 "notification_slot" => notification.slot.as_u64(),
+                "skip_reason" => SkipReason::Stale.as_str(),
                 "info" => "Your machine could be overloaded"
             );
+            skip_reason::record_skipped_duties(
+                SkipReason::Stale,
+                metrics::BEACON_BLOCK,
+                notification.block_proposers.len(),
+            );
+            return Ok(());
+        }
+
+        if !self.validator_store.startup_warmup_complete(slot) {
+            if !notification.block_proposers.is_empty() {
+                warn!(
+                    log,
+                    "Not producing block during startup warm-up";
+                    "slot" => slot.as_u64(),
+                    "skip_reason" => SkipReason::StartupWarmup.as_str(),
+                    "proposers" => format!("{:?}", notification.block_proposers),
+                );
+                skip_reason::record_skipped_duties(
+                    SkipReason::StartupWarmup,
+                    metrics::BEACON_BLOCK,
+                    notification.block_proposers.len(),
+                );
+            }
             return Ok(());
         }
 
@@ -330,10 +798,44 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
             )
         }
 
+        let bellatrix_enabled = self
+            .context
+            .eth2_config
+            .spec
+            .bellatrix_fork_epoch
+            .map_or(false, |fork_epoch| {
+                slot.epoch(E::slots_per_epoch()) >= fork_epoch
+            });
+
         for validator_pubkey in proposers {
+            if bellatrix_enabled
+                && self.validator_store.on_missing_fee_recipient()
+                    == OnMissingFeeRecipient::SkipProposal
+                && self
+                    .validator_store
+                    .get_fee_recipient(&validator_pubkey)
+                    .is_none()
+            {
+                crit!(
+                    log,
+                    "Not proposing block without a fee recipient";
+                    "msg" => "set a fee recipient for this validator to resume proposing",
+                    "skip_reason" => SkipReason::NoFeeRecipient.as_str(),
+                    "validator" => ?validator_pubkey,
+                    "slot" => slot.as_u64(),
+                );
+                skip_reason::record_skipped_duties(
+                    SkipReason::NoFeeRecipient,
+                    metrics::BEACON_BLOCK,
+                    1,
+                );
+                continue;
+            }
+
             let builder_proposals = self
                 .validator_store
                 .get_builder_proposals(&validator_pubkey);
+            let builder_unavailable_action = self.builder_unavailable_action;
             let service = self.clone();
             let log = log.clone();
             self.inner.context.executor.spawn(
@@ -344,6 +846,25 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                             .publish_block::<BlindedPayload<E>>(slot, validator_pubkey)
                             .await;
                         match result {
+                            Err(BlockError::Recoverable(e))
+                                if builder_unavailable_action == BuilderUnavailableAction::Skip =>
+                            {
+                                crit!(
+                                    log,
+                                    "Not proposing block after builder proposal failed";
+                                    "msg" => "proposing a local block is disabled by \
+                                        --builder-unavailable-action skip",
+                                    "error" => ?e,
+                                    "skip_reason" => SkipReason::BuilderUnavailable.as_str(),
+                                    "block_slot" => ?slot,
+                                    "validator" => ?validator_pubkey,
+                                );
+                                skip_reason::record_skipped_duties(
+                                    SkipReason::BuilderUnavailable,
+                                    metrics::BEACON_BLOCK,
+                                    1,
+                                );
+                            }
                             Err(BlockError::Recoverable(e)) => {
                                 error!(
                                     log,
@@ -352,6 +873,14 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                                     "block_slot" => ?slot,
                                     "info" => "blinded proposal failed, attempting full block"
                                 );
+                                warn!(
+                                    log,
+                                    "Falling back to a local block";
+                                    "msg" => "the builder is unavailable, the execution payload \
+                                        will be built by the local execution engine",
+                                    "block_slot" => ?slot,
+                                    "validator" => ?validator_pubkey,
+                                );
                                 if let Err(e) = service
                                     .publish_block::<FullPayload<E>>(slot, validator_pubkey)
                                     .await
@@ -403,19 +932,35 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
         Ok(())
     }
 
-    /// Produce a block at the given slot for validator_pubkey
-    async fn publish_block<Payload: AbstractExecPayload<E>>(
-        self,
+    /// Returns the graffiti of the block proposed by `validator_pubkey` at `slot`.
+    fn block_graffiti<Payload: AbstractExecPayload<E>>(
+        &self,
+        validator_pubkey: &PublicKeyBytes,
+        slot: Slot,
+    ) -> Option<Graffiti> {
+        determine_graffiti(
+            validator_pubkey,
+            self.context.log(),
+            slot.epoch(E::slots_per_epoch()),
+            &self.context.eth2_config.spec,
+            self.graffiti_file.clone(),
+            self.validator_store.graffiti(validator_pubkey),
+            self.graffiti,
+            self.graffiti_force,
+        )
+        .map(|graffiti| resolve_block_source(graffiti, Payload::block_type()))
+        .map(|graffiti| resolve_network(graffiti, &self.network_name))
+    }
+
+    /// Requests an unsigned block at `slot` for `validator_pubkey` from the first beacon node
+    /// which produces a valid block.
+    async fn produce_block<Payload: AbstractExecPayload<E>>(
+        &self,
         slot: Slot,
         validator_pubkey: PublicKeyBytes,
-    ) -> Result<(), BlockError> {
+        graffiti: Option<Graffiti>,
+    ) -> Result<BeaconBlock<E, Payload>, BlockError> {
         let log = self.context.log();
-        let _timer =
-            metrics::start_timer_vec(&metrics::BLOCK_SERVICE_TIMES, &[metrics::BEACON_BLOCK]);
-
-        let current_slot = self.slot_clock.now().ok_or_else(|| {
-            BlockError::Recoverable("Unable to determine current slot from clock".to_string())
-        })?;
 
         let randao_reveal = self
             .validator_store
@@ -429,22 +974,39 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
             })?
             .into();
 
-        let graffiti = determine_graffiti(
-            &validator_pubkey,
-            log,
-            self.graffiti_file.clone(),
-            self.validator_store.graffiti(&validator_pubkey),
-            self.graffiti,
-        );
-
         let randao_reveal_ref = &randao_reveal;
-        let self_ref = &self;
         let proposer_index = self.validator_store.validator_index(&validator_pubkey);
         let validator_pubkey_ref = &validator_pubkey;
         let proposer_fallback = ProposerFallback {
             beacon_nodes: self.beacon_nodes.clone(),
             proposer_nodes: self.proposer_nodes.clone(),
         };
+        let slot_clock = &self.slot_clock;
+        let canary = self.validator_store.is_canary(&validator_pubkey);
+        let verify_block_before_signing = self.verify_block_before_signing;
+        // Only locally built payloads are checked, since builders usually set the fee recipient
+        // of the payload to their own address and pay the proposer with a transaction.
+        let expected_fee_recipient =
+            if self.verify_fee_recipient && Payload::block_type() == BlockType::Full {
+                self.validator_store.get_fee_recipient(&validator_pubkey)
+            } else {
+                None
+            };
+        let refuse_proposal_on_optimistic = self.refuse_proposal_on_optimistic;
+        let block_production_limits = &self.block_production_limits;
+        let validator_store = &self.validator_store;
+        let freshly_activated = self
+            .validator_store
+            .is_freshly_activated(&validator_pubkey, slot.epoch(E::slots_per_epoch()));
+        if freshly_activated {
+            info!(
+                log,
+                "Checking proposal of newly activated validator";
+                "msg" => "the head and fork of the beacon node are checked before proposing",
+                "activation_epoch" => ?self.validator_store.activation_epoch(&validator_pubkey),
+                "slot" => slot.as_u64(),
+            );
+        }
 
         info!(
             log,
@@ -456,11 +1018,53 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
         //
         // Try the proposer nodes last, since it's likely that they don't have a
         // great view of attestations on the network.
-        let block = proposer_fallback
+        proposer_fallback
             .first_success_try_proposers_last(
                 RequireSynced::No,
                 OfflineOnFailure::Yes,
                 |beacon_node| async move {
+                    if refuse_proposal_on_optimistic
+                        && is_optimistic(beacon_node).await.map_err(|e| {
+                            BlockError::Recoverable(format!(
+                                "Unable to check whether the beacon node head is optimistic: {:?}",
+                                e
+                            ))
+                        })?
+                    {
+                        crit!(
+                            log,
+                            "Refusing to propose on an optimistic head";
+                            "msg" => "the execution payload of the beacon node head has not been \
+                                verified, trying the next beacon node",
+                            "beacon_node" => %beacon_node,
+                            "slot" => slot.as_u64(),
+                        );
+                        return Err(BlockError::Recoverable(
+                            "Beacon node head is optimistic".to_string(),
+                        ));
+                    }
+
+                    if freshly_activated {
+                        if let Err(reason) = check_fresh_validator_proposal(
+                            beacon_node,
+                            validator_store,
+                            *validator_pubkey_ref,
+                            slot.epoch(E::slots_per_epoch()),
+                        )
+                        .await
+                        {
+                            crit!(
+                                log,
+                                "Refusing proposal of newly activated validator";
+                                "msg" => "trying the next beacon node",
+                                "reason" => &reason,
+                                "beacon_node" => %beacon_node,
+                                "slot" => slot.as_u64(),
+                            );
+                            return Err(BlockError::Recoverable(reason));
+                        }
+                    }
+
                     let block = match Payload::block_type() {
                         BlockType::Full => {
                             let _get_timer = metrics::start_timer_vec(
@@ -468,10 +1072,12 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                                 &[metrics::BEACON_BLOCK_HTTP_GET],
                             );
                             beacon_node
-                                .get_validator_blocks::<E, Payload>(
+                                .get_validator_blocks_with_limits::<E, Payload>(
                                     slot,
                                     randao_reveal_ref,
                                     graffiti.as_ref(),
+                                    SkipRandaoVerification::No,
+                                    block_production_limits,
                                 )
                                 .await
                                 .map_err(|e| {
@@ -509,24 +1115,124 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                         "Received unsigned block";
                         "slot" => slot.as_u64(),
                     );
-                    if proposer_index != Some(block.proposer_index()) {
+                    if canary {
+                        let slot_delay_ms = slot_clock
+                            .millis_from_current_slot_start()
+                            .map(|delay| delay.as_millis());
+                        let response_bytes =
+                            serde_json::to_vec(&block).map_or(0, |bytes| bytes.len());
+                        info!(
+                            log,
+                            "Canary validator diagnostics";
+                            "stage" => "received unsigned block",
+                            "validator_index" => ?proposer_index,
+                            "beacon_node" => %beacon_node,
+                            "slot_delay_ms" => ?slot_delay_ms,
+                            "response_bytes" => response_bytes,
+                            "block_type" => ?Payload::block_type(),
+                            "slot" => slot.as_u64(),
+                        );
+                    }
+                    if verify_block_before_signing {
+                        if let Err(e) = verify_block(&block, slot, proposer_index) {
+                            crit!(
+                                log,
+                                "Refusing to sign invalid block";
+                                "msg" => "the beacon node produced a block which does not match \
+                                    the request, trying the next beacon node",
+                                "error" => ?e,
+                                "beacon_node" => %beacon_node,
+                                "slot" => slot.as_u64(),
+                            );
+                            return Err(BlockError::Recoverable(format!(
+                                "Beacon node produced an invalid block: {:?}",
+                                e
+                            )));
+                        }
+                    } else if proposer_index != Some(block.proposer_index()) {
                         return Err(BlockError::Recoverable(
                             "Proposer index does not match block proposer. Beacon chain re-orged"
                                 .to_string(),
                         ));
                     }
+                    if let Some(fee_recipient) = expected_fee_recipient {
+                        if let Err(e) = verify_fee_recipient(&block, fee_recipient) {
+                            crit!(
+                                log,
+                                "Refusing to sign block with wrong fee recipient";
+                                "msg" => "the beacon node may be misconfigured or malicious, \
+                                    trying the next beacon node",
+                                "error" => ?e,
+                                "beacon_node" => %beacon_node,
+                                "slot" => slot.as_u64(),
+                            );
+                            return Err(BlockError::Recoverable(format!(
+                                "Beacon node produced a block with the wrong fee recipient: {:?}",
+                                e
+                            )));
+                        }
+                    }
 
                     Ok::<_, BlockError>(block)
                 },
             )
-            .await?;
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Produce a block at the given slot for validator_pubkey
+    pub(crate) async fn publish_block<Payload: PrefetchPayload<E>>(
+        self,
+        slot: Slot,
+        validator_pubkey: PublicKeyBytes,
+    ) -> Result<(), BlockError> {
+        let log = self.context.log();
+        let _timer =
+            metrics::start_timer_vec(&metrics::BLOCK_SERVICE_TIMES, &[metrics::BEACON_BLOCK]);
+
+        let current_slot = self.slot_clock.now().ok_or_else(|| {
+            BlockError::Recoverable("Unable to determine current slot from clock".to_string())
+        })?;
+
+        let graffiti = self.block_graffiti::<Payload>(&validator_pubkey, slot);
+
+        let block = match self
+            .take_prefetched_block::<Payload>(slot, validator_pubkey)
+            .await
+        {
+            Some(block) => block,
+            None => {
+                self.produce_block::<Payload>(slot, validator_pubkey, graffiti)
+                    .await?
+            }
+        };
+
+        let self_ref = &self;
+        let proposer_index = self.validator_store.validator_index(&validator_pubkey);
+        let validator_pubkey_ref = &validator_pubkey;
+        let proposer_fallback = ProposerFallback {
+            beacon_nodes: self.beacon_nodes.clone(),
+            proposer_nodes: self.proposer_nodes.clone(),
+        };
+        let slot_clock = &self.slot_clock;
+        let canary = self.validator_store.is_canary(&validator_pubkey);
 
         let signing_timer = metrics::start_timer(&metrics::BLOCK_SIGNING_TIMES);
         let signed_block = self_ref
             .validator_store
             .sign_block::<Payload>(*validator_pubkey_ref, block, current_slot)
             .await
-            .map_err(|e| BlockError::Recoverable(format!("Unable to sign block: {:?}", e)))?;
+            .map_err(|e| {
+                let skip_reason = skip_reason::record_signing_error(&e, metrics::BEACON_BLOCK);
+                warn!(
+                    log,
+                    "Unable to sign block";
+                    "error" => ?e,
+                    "skip_reason" => skip_reason.map(SkipReason::as_str),
+                    "slot" => slot.as_u64(),
+                );
+                BlockError::Recoverable(format!("Unable to sign block: {:?}", e))
+            })?;
         let signing_time_ms =
             Duration::from_secs_f64(signing_timer.map_or(0.0, |t| t.stop_and_record())).as_millis();
 
@@ -579,6 +1285,24 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
                                 })?
                         }
                     }
+                    if canary {
+                        let slot_delay_ms = slot_clock
+                            .millis_from_current_slot_start()
+                            .map(|delay| delay.as_millis());
+                        let request_bytes =
+                            serde_json::to_vec(&signed_block).map_or(0, |bytes| bytes.len());
+                        info!(
+                            log,
+                            "Canary validator diagnostics";
+                            "stage" => "published block",
+                            "validator_index" => ?proposer_index,
+                            "beacon_node" => %beacon_node,
+                            "slot_delay_ms" => ?slot_delay_ms,
+                            "signing_time_ms" => signing_time_ms,
+                            "request_bytes" => request_bytes,
+                            "slot" => slot.as_u64(),
+                        );
+                    }
                     Ok::<_, BlockError>(())
                 },
             )
@@ -597,3 +1321,289 @@ impl<T: SlotClock + 'static, E: EthSpec> BlockService<T, E> {
         Ok(())
     }
 }
+
+/// Checks that `block` was produced for `slot` by the validator with `proposer_index` and that its
+/// parent root is non-zero.
+///
+/// The parent root is not compared with the head of the beacon node, since a beacon node may
+/// deliberately build on an earlier block, e.g. to re-org a late block.
+fn verify_block<E: EthSpec, Payload: AbstractExecPayload<E>>(
+    block: &BeaconBlock<E, Payload>,
+    slot: Slot,
+    proposer_index: Option<u64>,
+) -> Result<(), BlockVerificationError> {
+    if block.slot() != slot {
+        return Err(BlockVerificationError::WrongSlot {
+            requested: slot,
+            block: block.slot(),
+        });
+    }
+    if proposer_index != Some(block.proposer_index()) {
+        return Err(BlockVerificationError::WrongProposer {
+            expected: proposer_index,
+            block: block.proposer_index(),
+        });
+    }
+    if block.parent_root() == Hash256::zero() {
+        return Err(BlockVerificationError::ZeroParentRoot);
+    }
+    Ok(())
+}
+
+/// Checks that the execution payload of `block` pays `fee_recipient`.
+///
+/// Blocks without an execution payload, i.e. from before Bellatrix, are always accepted.
+fn verify_fee_recipient<E: EthSpec, Payload: AbstractExecPayload<E>>(
+    block: &BeaconBlock<E, Payload>,
+    fee_recipient: Address,
+) -> Result<(), BlockVerificationError> {
+    match block.body().execution_payload() {
+        Ok(payload) if payload.fee_recipient() != fee_recipient => {
+            Err(BlockVerificationError::WrongFeeRecipient {
+                expected: fee_recipient,
+                block: payload.fee_recipient(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks made before a proposal in `epoch` by a newly activated validator, in addition to the
+/// usual ones.
+///
+/// The head of `beacon_node` must be synced and verified, the validator must be active in it and
+/// the beacon node must agree with the fork version used for signing. Returns the reason if any of
+/// the checks fail.
+async fn check_fresh_validator_proposal<T: SlotClock + 'static, E: EthSpec>(
+    beacon_node: &BeaconNodeHttpClient,
+    validator_store: &ValidatorStore<T, E>,
+    validator_pubkey: PublicKeyBytes,
+    epoch: Epoch,
+) -> Result<(), String> {
+    let syncing = beacon_node
+        .get_node_syncing()
+        .await
+        .map_err(|e| format!("Unable to read the beacon node sync status: {:?}", e))?
+        .data;
+    if syncing.is_syncing {
+        return Err(format!(
+            "Beacon node is syncing, {} slots behind",
+            syncing.sync_distance
+        ));
+    }
+    if syncing.is_optimistic == Some(true) {
+        return Err("Beacon node head is optimistic".to_string());
+    }
+
+    match validator_store.check_beacon_node_fork_version(epoch) {
+        Some(Ok(())) => (),
+        Some(Err(mismatch)) => {
+            return Err(format!(
+                "Fork version mismatch with the beacon node: {:?}",
+                mismatch
+            ))
+        }
+        None => return Err("Beacon node fork schedule is not yet known".to_string()),
+    }
+
+    let validator = beacon_node
+        .get_beacon_states_validator_id(StateId::Head, &ValidatorId::PublicKey(validator_pubkey))
+        .await
+        .map_err(|e| format!("Unable to read the validator from the beacon node: {:?}", e))?
+        .ok_or_else(|| "Validator is unknown to the beacon node head".to_string())?
+        .data;
+    if !validator.validator.is_active_at(epoch) {
+        return Err(format!(
+            "Validator is not active in epoch {} at the beacon node head, activation epoch {}",
+            epoch, validator.validator.activation_epoch
+        ));
+    }
+
+    Ok(())
+}
+
+/// Replaces each `BLOCK_SOURCE_GRAFFITI_TOKEN` in `graffiti` with the source of the payload of a
+/// block of `block_type`.
+///
+/// The markers are shorter than the token, so the result always fits in the graffiti.
+fn resolve_block_source(graffiti: Graffiti, block_type: BlockType) -> Graffiti {
+    let marker: &[u8] = match block_type {
+        BlockType::Blinded => b"builder",
+        BlockType::Full => b"local",
+    };
+    replace_graffiti_token(graffiti, BLOCK_SOURCE_GRAFFITI_TOKEN.as_bytes(), marker)
+}
+
+/// Replaces each `NETWORK_GRAFFITI_TOKEN` in `graffiti` with `network`.
+///
+/// The network name may be longer than the token, in which case the result is truncated to the
+/// graffiti length without splitting a UTF-8 character.
+fn resolve_network(graffiti: Graffiti, network: &str) -> Graffiti {
+    replace_graffiti_token(
+        graffiti,
+        NETWORK_GRAFFITI_TOKEN.as_bytes(),
+        network.as_bytes(),
+    )
+}
+
+fn replace_graffiti_token(graffiti: Graffiti, token: &[u8], marker: &[u8]) -> Graffiti {
+    let mut bytes = graffiti.0.to_vec();
+    let mut start = 0;
+    while let Some(position) = bytes[start..]
+        .windows(token.len())
+        .position(|window| window == token)
+    {
+        let position = start + position;
+        bytes.splice(position..position + token.len(), marker.iter().copied());
+        start = position + marker.len();
+    }
+
+    let mut resolved = [0; GRAFFITI_BYTES_LEN];
+    let mut len = bytes.len().min(GRAFFITI_BYTES_LEN);
+    if let Ok(text) = std::str::from_utf8(&bytes) {
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+    }
+    resolved[..len].copy_from_slice(&bytes[..len]);
+    resolved.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use types::graffiti::GraffitiString;
+    use types::{ForkName, MainnetEthSpec};
+
+    type E = MainnetEthSpec;
+
+    fn graffiti(graffiti: &str) -> Graffiti {
+        GraffitiString::from_str(graffiti).unwrap().into()
+    }
+
+    #[test]
+    fn block_source_graffiti() {
+        let template = graffiti("Lighthouse {block_source}");
+        assert_eq!(
+            resolve_block_source(template, BlockType::Blinded),
+            graffiti("Lighthouse builder")
+        );
+        assert_eq!(
+            resolve_block_source(template, BlockType::Full),
+            graffiti("Lighthouse local")
+        );
+
+        let template = graffiti("{block_source}/{block_source}");
+        assert_eq!(
+            resolve_block_source(template, BlockType::Blinded),
+            graffiti("builder/builder")
+        );
+
+        let plain = graffiti("no token {block}");
+        assert_eq!(resolve_block_source(plain, BlockType::Full), plain);
+    }
+
+    #[test]
+    fn network_graffiti() {
+        let template = graffiti("Lighthouse on {network}");
+        assert_eq!(
+            resolve_network(template, "mainnet"),
+            graffiti("Lighthouse on mainnet")
+        );
+
+        // The result is truncated to 32 bytes.
+        let template = graffiti("Lighthouse {block_source} {network}");
+        let resolved = resolve_network(
+            resolve_block_source(template, BlockType::Blinded),
+            "holesky-devnet",
+        );
+        assert_eq!(resolved, graffiti("Lighthouse builder holesky-devne"));
+
+        // A multi-byte character is not split.
+        let template = graffiti("{network}");
+        let resolved = resolve_network(template, &format!("a{}", "é".repeat(16)));
+        assert_eq!(resolved, graffiti(&format!("a{}", "é".repeat(15))));
+    }
+
+    fn block(slot: u64, proposer_index: u64, parent_root: Hash256) -> BeaconBlock<E> {
+        let spec = ForkName::Capella.make_genesis_spec(E::default_spec());
+        let mut block = BeaconBlock::empty(&spec);
+        *block.slot_mut() = Slot::new(slot);
+        *block.proposer_index_mut() = proposer_index;
+        *block.parent_root_mut() = parent_root;
+        block
+    }
+
+    #[test]
+    fn verify_valid_block() {
+        let block = block(10, 3, Hash256::repeat_byte(1));
+        assert_eq!(verify_block(&block, Slot::new(10), Some(3)), Ok(()));
+    }
+
+    #[test]
+    fn verify_block_wrong_slot() {
+        let block = block(11, 3, Hash256::repeat_byte(1));
+        assert_eq!(
+            verify_block(&block, Slot::new(10), Some(3)),
+            Err(BlockVerificationError::WrongSlot {
+                requested: Slot::new(10),
+                block: Slot::new(11),
+            })
+        );
+    }
+
+    #[test]
+    fn verify_block_wrong_proposer() {
+        let block = block(10, 4, Hash256::repeat_byte(1));
+        assert_eq!(
+            verify_block(&block, Slot::new(10), Some(3)),
+            Err(BlockVerificationError::WrongProposer {
+                expected: Some(3),
+                block: 4,
+            })
+        );
+        assert_eq!(
+            verify_block(&block, Slot::new(10), None),
+            Err(BlockVerificationError::WrongProposer {
+                expected: None,
+                block: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn verify_block_zero_parent_root() {
+        let block = block(10, 3, Hash256::zero());
+        assert_eq!(
+            verify_block(&block, Slot::new(10), Some(3)),
+            Err(BlockVerificationError::ZeroParentRoot)
+        );
+    }
+
+    #[test]
+    fn verify_block_fee_recipient() {
+        let fee_recipient = Address::repeat_byte(0xaa);
+        let mut block = block(10, 3, Hash256::repeat_byte(1));
+        block
+            .body_mut()
+            .execution_payload_capella_mut()
+            .unwrap()
+            .execution_payload
+            .fee_recipient = fee_recipient;
+        assert_eq!(verify_fee_recipient(&block, fee_recipient), Ok(()));
+
+        let other = Address::repeat_byte(0xbb);
+        assert_eq!(
+            verify_fee_recipient(&block, other),
+            Err(BlockVerificationError::WrongFeeRecipient {
+                expected: other,
+                block: fee_recipient,
+            })
+        );
+
+        // Blocks from before Bellatrix have no fee recipient to check.
+        let base_block = BeaconBlock::<E>::empty(&E::default_spec());
+        assert_eq!(verify_fee_recipient(&base_block, other), Ok(()));
+    }
+}
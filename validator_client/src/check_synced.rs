@@ -80,3 +80,12 @@ pub async fn check_synced<T: SlotClock>(
         Err(CandidateError::NotSynced)
     }
 }
+
+/// Returns `true` if the head of the beacon node is optimistic, i.e. its execution payload has not
+/// been verified by an execution engine.
+///
+/// Beacon nodes which do not report whether their head is optimistic are assumed not to be.
+pub async fn is_optimistic(beacon_node: &BeaconNodeHttpClient) -> Result<bool, eth2::Error> {
+    let resp = beacon_node.get_node_syncing().await?;
+    Ok(resp.data.is_optimistic.unwrap_or(false))
+}
@@ -1,4 +1,13 @@
+use crate::beacon_node_fallback::BeaconNodeSelection;
+use crate::block_service::BuilderUnavailableAction;
+use crate::config::{OnExistingSlashingProtection, OnGraffitiOverflow};
+use crate::duties_service::MetricsValidatorBucketing;
+use crate::fork_schedule::OnForkVersionMismatch;
+use crate::graffiti_file::OnDuplicateGraffiti;
+use crate::initialized_validators::OnKeystoreError;
+use crate::preparation_service::{OnGasLimitOutOfRange, OnMissingFeeRecipient};
 use clap::{App, Arg};
+use strum::VariantNames;
 
 pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
     App::new("validator_client")
@@ -35,6 +44,25 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 )
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("register-via-proposer-nodes")
+                .long("register-via-proposer-nodes")
+                .help("Send validator registrations to the nodes specified in --proposer-nodes \
+                    rather than --beacon-nodes, since registrations only affect block proposals. \
+                    If none of the proposer nodes accept a registration it is sent to the beacon \
+                    nodes instead. Has no effect without --proposer-nodes.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("attestation-fallback-nodes")
+                .long("attestation-fallback-nodes")
+                .value_name("NETWORK_ADDRESSES")
+                .help("Comma-separated addresses to one or more beacon node HTTP APIs. \
+                These nodes are only used to produce and publish attestations, and only when \
+                none of the beacon nodes specified in --beacon-nodes are available. They are \
+                never used for block proposals or duties.")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("disable-run-on-all")
                 .long("disable-run-on-all")
@@ -45,6 +73,92 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                        api calls only go out to the first available and synced beacon node")
                 .takes_value(false)
         )
+        .arg(
+            Arg::with_name("disable-lenient-bn-responses")
+                .long("disable-lenient-bn-responses")
+                .help("By default, optional fields which some beacon node implementations omit \
+                       from their responses, such as the sync committee subnets of a node's \
+                       metadata, are substituted with defaults. This flag treats such responses \
+                       as errors instead.")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("beacon-node-consensus-check")
+                .long("beacon-node-consensus-check")
+                .help("Compare the head block of each synced beacon node once per slot. A node \
+                       whose head disagrees with the majority of nodes for several consecutive \
+                       slots is quarantined and not used until it agrees again. Requires at \
+                       least three beacon nodes to have any effect.")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("allow-mismatched-genesis")
+                .long("allow-mismatched-genesis")
+                .help("By default, a beacon node which reports a different genesis validators \
+                       root to the network config is refused, since it is likely to be on a \
+                       different network. If the network config has no genesis state, the \
+                       beacon nodes must all report the same root at startup. This flag \
+                       disables these checks. Not recommended.")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("beacon-node-selection")
+                .long("beacon-node-selection")
+                .value_name("MODE")
+                .help("The order in which synced beacon nodes are tried for requests which only \
+                       need one beacon node to respond. With `priority`, the nodes are always \
+                       tried in the order given to --beacon-nodes. With `round_robin`, each \
+                       request starts at the next node, spreading the load across all synced \
+                       nodes. With `sticky`, the node which served the previous request is tried \
+                       first until it fails.")
+                .possible_values(BeaconNodeSelection::VARIANTS)
+                .default_value("priority")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("beacon-node-startup-timeout")
+                .long("beacon-node-startup-timeout")
+                .value_name("SECONDS")
+                .help("Exit with an error if none of the --beacon-nodes is reachable within this \
+                       many seconds of startup, so that a process supervisor can react. By \
+                       default the validator client waits for a beacon node indefinitely.")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("detect-config-changes")
+                .long("detect-config-changes")
+                .help("Store a hash of the effective configuration, excluding credentials, in the \
+                       validators directory and log a warning at startup if it differs from the \
+                       hash stored by the previous run.")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("require-homogeneous-beacon-nodes")
+                .long("require-homogeneous-beacon-nodes")
+                .help("By default, a warning is logged at startup if the reachable beacon nodes \
+                       run different clients or client versions. This option changes that \
+                       behaviour such that the validator client refuses to start instead.")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("persist-beacon-node-health")
+                .long("persist-beacon-node-health")
+                .help("Save the health of each beacon node, including any quarantine by \
+                       --beacon-node-consensus-check, to the validators directory once per slot \
+                       and restore it at startup. State saved more than 10 minutes before \
+                       startup is discarded.")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("disable-attestation-publish-retry")
+                .long("disable-attestation-publish-retry")
+                .help("By default, attestations which are rejected by a beacon node are published \
+                       to the next beacon node in `--beacon-nodes`. This option changes that \
+                       behaviour such that a rejection is logged and the attestations are not \
+                       published to any other beacon node. Beacon nodes which are unreachable \
+                       are still skipped.")
+                .takes_value(false)
+        )
         // This argument is deprecated, use `--beacon-nodes` instead.
         .arg(
             Arg::with_name("server")
@@ -80,6 +194,75 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .conflicts_with("datadir")
         )
+        .arg(
+            Arg::with_name("network-dir-name")
+                .long("network-dir-name")
+                .value_name("NAME")
+                .help(
+                    "The name of the per-network directory in ~/.lighthouse which contains the \
+                    default validators and secrets directories, instead of the name of the \
+                    network. Has no effect on directories set with --datadir, --validators-dir \
+                    or --secrets-dir.",
+                )
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("secrets-manifest")
+                .long("secrets-manifest")
+                .value_name("FILE")
+                .help(
+                    "A JSON or YAML file mapping keystore file names or 0x-prefixed voting \
+                    public keys to keystore passwords. It is used for keystores which have no \
+                    password in the validator definitions or the secrets directory. The validator \
+                    client will refuse to start if the file is accessible to anyone other than \
+                    its owner, or if it is missing the password of any such keystore.",
+                )
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("pkcs11-module")
+                .long("pkcs11-module")
+                .value_name("FILE")
+                .help("Path to the PKCS#11 module of a hardware security module which holds the \
+                    keys of the validators with the `pkcs11` type in the validator definitions. \
+                    The HSM must support BLS12-381 signatures via a vendor-defined mechanism.")
+                .requires_all(&["pkcs11-slot", "pkcs11-pin-file", "pkcs11-mechanism"])
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("pkcs11-slot")
+                .long("pkcs11-slot")
+                .value_name("SLOT_ID")
+                .help("The ID of the PKCS#11 slot containing the token with the validator keys.")
+                .requires("pkcs11-module")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("pkcs11-pin-file")
+                .long("pkcs11-pin-file")
+                .value_name("FILE")
+                .help("Path to a file containing the user PIN of the PKCS#11 token.")
+                .requires("pkcs11-module")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("pkcs11-mechanism")
+                .long("pkcs11-mechanism")
+                .value_name("MECHANISM")
+                .help("The vendor-defined PKCS#11 mechanism which produces BLS12-381 signatures, \
+                    as a decimal or 0x-prefixed hexadecimal integer. Refer to the documentation \
+                    of the HSM.")
+                .requires("pkcs11-module")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("log-deprecations")
+                .long("log-deprecations")
+                .help("Log a single report of every deprecated flag in use, along with the flag \
+                       and config field which replace it and the resulting value. Useful when \
+                       migrating away from deprecated flags.")
+                .takes_value(false)
+        )
         .arg(
             Arg::with_name("delete-lockfiles")
             .long("delete-lockfiles")
@@ -98,6 +281,79 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                      misplace your database and then run with this flag you risk being slashed."
                 )
         )
+        .arg(
+            Arg::with_name("on-existing-slashing-protection")
+                .long("on-existing-slashing-protection")
+                .value_name("ACTION")
+                .help("The action to take when --init-slashing-protection is used and a \
+                    validator is already present in the slashing protection database. With \
+                    `keep`, the existing records are kept so that the validator can never sign \
+                    anything below what it has already signed. With `strict`, the validator \
+                    client refuses to start. Defaults to `keep`.")
+                .possible_values(OnExistingSlashingProtection::VARIANTS)
+                .requires("init-slashing-protection")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("slashing-protection-retries")
+                .long("slashing-protection-retries")
+                .value_name("COUNT")
+                .help("The number of times to retry a slashing protection database check which \
+                    failed because the database was in use, e.g. by a concurrent export. If the \
+                    database is still in use after the last retry, the message is not signed.")
+                .default_value("3")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("slashing-protection-retry-delay-ms")
+                .long("slashing-protection-retry-delay-ms")
+                .value_name("MILLIS")
+                .help("The delay before the first retry of a slashing protection database check, \
+                    doubled for each subsequent retry.")
+                .default_value("50")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("slashing-protection-pool-size")
+                .long("slashing-protection-pool-size")
+                .value_name("COUNT")
+                .help("The number of connections to the slashing protection database. Signing \
+                    checks are serialised regardless, so more connections only reduce the time \
+                    spent waiting for a connection while others are in use, e.g. by an export. \
+                    With more than one connection the database is no longer locked against \
+                    other processes, so a warning is logged. Defaults to 1.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-concurrent-signings")
+                .long("max-concurrent-signings")
+                .value_name("COUNT")
+                .help("The maximum number of signatures produced at the same time, e.g. to avoid \
+                    overwhelming a remote signer at an epoch boundary. Waiting signatures are \
+                    produced in order of deadline, and a signature which is still waiting at its \
+                    deadline is produced regardless of the limit. Unlimited by default.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("low-memory-mode")
+                .long("low-memory-mode")
+                .help("Reduce cache, connection pool and batch sizes to minimise memory usage, \
+                    for a small number of validators on modest hardware. Requests and signatures \
+                    are spread over more round trips, so latency is slightly higher. Each of \
+                    --slashing-protection-pool-size, --max-concurrent-signings, \
+                    --max-pending-aggregations, --latency-sample-window and \
+                    --validator-registration-batch-size overrides its value in this mode.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("slow-sign-threshold-ms")
+                .long("slow-sign-threshold-ms")
+                .value_name("MILLIS")
+                .help("Signing operations which take longer than this many milliseconds are \
+                    logged with a warning and counted by the vc_slow_signings_total metric. \
+                    Defaults to 1000.")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("disable-auto-discover")
             .long("disable-auto-discover")
@@ -106,6 +362,106 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 will need to be manually added to the validator_definitions.yml file."
             )
         )
+        .arg(
+            Arg::with_name("rebuild-index-cache")
+                .long("rebuild-index-cache")
+                .help("If present, ignore the cache of validator indices in the validators-dir \
+                    and fetch the index of every validator from the beacon node, replacing the \
+                    cache.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("on-keystore-error")
+                .long("on-keystore-error")
+                .value_name("ACTION")
+                .help("The action to take when a validator keystore cannot be loaded or \
+                    decrypted at startup. With `skip`, the failing keystore is logged and the \
+                    validator client continues with the remaining validators. With `abort`, the \
+                    validator client refuses to start.")
+                .possible_values(OnKeystoreError::VARIANTS)
+                .default_value("abort")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("on-fork-version-mismatch")
+                .long("on-fork-version-mismatch")
+                .value_name("ACTION")
+                .help("The action to take when the fork version the validator client would sign \
+                    with differs from the fork version reported by the beacon node, e.g. because \
+                    only one of them was upgraded for a fork. With `warn`, the mismatch is \
+                    logged and messages are signed anyway. With `refuse`, the mismatch is logged \
+                    and nothing is signed until the versions agree.")
+                .possible_values(OnForkVersionMismatch::VARIANTS)
+                .default_value("warn")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("disable-fork-transition-caution")
+                .long("disable-fork-transition-caution")
+                .help("By default, within --fork-transition-window epochs of a scheduled fork, \
+                    the fork version is compared with the beacon node every slot and blocks are \
+                    not proposed while the versions differ, regardless of \
+                    --on-fork-version-mismatch. This flag disables that behaviour.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("fork-transition-window")
+                .long("fork-transition-window")
+                .value_name("EPOCHS")
+                .help("The number of epochs either side of a scheduled fork in which fork \
+                    transition caution applies.")
+                .default_value("2")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("canary-validator")
+                .long("canary-validator")
+                .value_name("PUBKEY")
+                .help("The public key of a local validator for which detailed diagnostics of each \
+                    attestation, block and sync committee duty are logged at INFO level, \
+                    including timings, the beacon node used and message sizes. This increases \
+                    the log volume for that validator only.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("validators-count-limit")
+                .long("validators-count-limit")
+                .value_name("COUNT")
+                .help("The maximum number of enabled validators this validator client will load. \
+                    If more validators are enabled, the ones with the lowest voting public keys \
+                    are loaded and the rest are logged and ignored. Importing an enabled \
+                    validator through the HTTP API fails once the limit is reached.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("keystore-decrypt-concurrency")
+                .long("keystore-decrypt-concurrency")
+                .value_name("COUNT")
+                .help("The maximum number of keystores to decrypt at once when loading \
+                    validators. Each decryption may use 256 MiB of memory, so lower this value \
+                    on machines with little memory. Defaults to the number of CPUs.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("validator-load-priority")
+                .long("validator-load-priority")
+                .value_name("FILE")
+                .help("A file containing the voting public keys of validators to load first, one \
+                    per line. These validators start performing duties as soon as they are \
+                    loaded, before the remaining validators have been loaded.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("validator-index-range")
+                .long("validator-index-range")
+                .value_name("START..END")
+                .help("Only perform duties for validators with an index of at least START and less \
+                    than END. Validators outside of the range are loaded but remain idle, which \
+                    allows a large set of validators to be split across several validator \
+                    clients which each hold a copy of the whole set. Each validator must be in \
+                    the range of exactly one validator client.")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("allow-unsynced")
                 .long("allow-unsynced")
@@ -125,8 +481,43 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .help("Comma-separated paths to custom TLS certificates to use when connecting \
                         to a beacon node (and/or proposer node). These certificates must be in PEM format and are used \
-                        in addition to the OS trust store. Commas must only be used as a \
-                        delimiter, and must not be part of the certificate path.")
+                        in addition to the OS trust store. A path may also be a directory, in \
+                        which case all of the .pem and .crt files in it are used. Commas must \
+                        only be used as a delimiter, and must not be part of the certificate path.")
+        )
+        .arg(
+            Arg::with_name("beacon-nodes-min-tls-version")
+                .long("beacon-nodes-min-tls-version")
+                .value_name("VERSION")
+                .takes_value(true)
+                .possible_values(&["1.0", "1.1", "1.2", "1.3"])
+                .help("The minimum TLS version to negotiate when connecting to a beacon node (and/or \
+                        proposer node) over HTTPS. Connections to beacon nodes which do not support \
+                        this version fail. The cipher suites are the defaults of the TLS library \
+                        and cannot be configured.")
+        )
+        .arg(
+            Arg::with_name("beacon-nodes-tls-cert-expiry-days")
+                .long("beacon-nodes-tls-cert-expiry-days")
+                .value_name("DAYS")
+                .takes_value(true)
+                .help("Warn when the TLS certificate presented by a beacon node (and/or proposer \
+                        node) connected over HTTPS expires within this many days. The \
+                        certificates are checked every hour and their expiry is also exposed as \
+                        a metric. Set to 0 to disable the check. [default: 14]")
+        )
+        .arg(
+            Arg::with_name("beacon-node-dns-ttl")
+                .long("beacon-node-dns-ttl")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .help("Reuse connections to beacon nodes (and/or proposer nodes) for at most \
+                        this many seconds, so that their hostnames are resolved again once \
+                        their connections are closed. In-flight requests complete on their \
+                        existing connections. Useful when beacon nodes are addressed via DNS that \
+                        may change, e.g. a Kubernetes service. A value of 0 disables connection \
+                        reuse entirely, which resolves the hostname on every request at the cost \
+                        of a new TCP/TLS handshake each time.")
         )
         // This overwrites the graffiti configured in the beacon node.
         .arg(
@@ -136,13 +527,43 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .value_name("GRAFFITI")
                 .takes_value(true)
         )
+        .arg(
+            Arg::with_name("graffiti-on-overflow")
+                .long("graffiti-on-overflow")
+                .value_name("ACTION")
+                .help("The action to take when the --graffiti is longer than 32 bytes. With \
+                    `error`, the validator client refuses to start. With `truncate`, the \
+                    graffiti is cut to the whole characters which fit and a warning is logged.")
+                .possible_values(OnGraffitiOverflow::VARIANTS)
+                .default_value("error")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("graffiti-force")
+                .long("graffiti-force")
+                .help("Use the --graffiti for all validators, in place of any graffiti from the \
+                    --graffiti-file or the validator definitions. The --graffiti-file may only \
+                    be used alongside the --graffiti when this flag is set.")
+                .requires("graffiti")
+                .takes_value(false)
+        )
         .arg(
             Arg::with_name("graffiti-file")
                 .long("graffiti-file")
                 .help("Specify a graffiti file to load validator graffitis from.")
                 .value_name("GRAFFITI-FILE")
                 .takes_value(true)
-                .conflicts_with("graffiti")
+        )
+        .arg(
+            Arg::with_name("graffiti-file-on-duplicate")
+                .long("graffiti-file-on-duplicate")
+                .value_name("ACTION")
+                .help("The action to take when the --graffiti-file lists a validator more than \
+                    once with different graffitis. With `last_wins`, the last graffiti listed is \
+                    used and a warning is logged. With `error`, the file is rejected.")
+                .possible_values(OnDuplicateGraffiti::VARIANTS)
+                .default_value("last_wins")
+                .takes_value(true)
         )
         .arg(
             Arg::with_name("suggested-fee-recipient")
@@ -153,6 +574,21 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .value_name("FEE-RECIPIENT")
                 .takes_value(true)
         )
+        .arg(
+            Arg::with_name("on-missing-fee-recipient")
+                .long("on-missing-fee-recipient")
+                .value_name("ACTION")
+                .help("The action to take when a validator has no fee recipient configured, \
+                       neither in the validator definitions nor via --suggested-fee-recipient. \
+                       With `zero`, blocks are proposed with whatever fee recipient the beacon \
+                       node chooses, which may be the zero address, in which case the transaction \
+                       fees are lost. With `skip_proposal`, the validator will not propose blocks \
+                       and a critical error is logged instead. With `error_startup`, the \
+                       validator client will refuse to start.")
+                .possible_values(OnMissingFeeRecipient::VARIANTS)
+                .default_value("zero")
+                .takes_value(true),
+        )
         /* REST API related arguments */
         .arg(
             Arg::with_name("http")
@@ -246,6 +682,69 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                     of prometheus metrics being collected.")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("metrics-validator-bucketing")
+                .long("metrics-validator-bucketing")
+                .value_name("MODE")
+                .help("How validators are labelled in the duty metrics. With `full`, each \
+                    validator has its own series, subject to --enable-high-validator-count-metrics. \
+                    With `bucketed`, validators are grouped into 16 buckets by the first hex digit \
+                    of their public key, which bounds the number of series regardless of the \
+                    number of validators at the cost of per-validator detail.")
+                .possible_values(MetricsValidatorBucketing::VARIANTS)
+                .default_value("full")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("min-balance-alert-gwei")
+                .long("min-balance-alert-gwei")
+                .value_name("GWEI")
+                .help("If present, the effective balances of all validators are checked once \
+                    per epoch and a warning is logged and the `vc_min_balance_alerts_total` \
+                    metric is incremented when a validator falls below this value, or when its \
+                    status changes whilst it is below this value. Validators which are not yet \
+                    known to the beacon chain are ignored.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("auto-disable-inactive")
+                .long("auto-disable-inactive")
+                .help("If present, the statuses of all validators are checked once per epoch and \
+                    any validator which has exited or been slashed as of the finalized state is \
+                    disabled, stopping all of its duties. Disabled validators are saved to the \
+                    validator definitions file and can be enabled again via the HTTP API.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("index-resolution-interval")
+                .long("index-resolution-interval")
+                .value_name("SECONDS")
+                .help("The minimum number of seconds between lookups of a validator which is not \
+                    yet known to the beacon chain, e.g. because its deposit is pending. The \
+                    validators still awaiting an index are logged after each lookup. \
+                    [default: 60]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("activation-poll-epochs")
+                .long("activation-poll-epochs")
+                .value_name("EPOCHS")
+                .help("The status of validators awaiting activation is polled once per epoch, and \
+                    every slot once they are due to be activated within this many epochs. This \
+                    ensures activations are noticed, and duties begin, as soon as the head state \
+                    reflects them. [default: 1]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("duties-batch-size")
+                .long("duties-batch-size")
+                .value_name("COUNT")
+                .help("The maximum number of validators included in each attester or sync \
+                    committee duties request. If a beacon node refuses a request as too large, \
+                    the batch size is halved for that beacon node until it is accepted. \
+                    [default: 4096]")
+                .takes_value(true),
+        )
         /*
          * Explorer metrics
          */
@@ -270,6 +769,37 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .requires("monitoring-endpoint")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("monitoring-endpoint-exclude")
+                .long("monitoring-endpoint-exclude")
+                .value_name("CATEGORIES")
+                .help("Comma-separated list of metric categories not to send to the \
+                       monitoring-endpoint. `process` is the CPU and memory usage of this \
+                       process, `system` the metrics of the host and `validator` the validator \
+                       counts and beacon node connectivity.")
+                .possible_values(&["process", "system", "validator"])
+                .use_delimiter(true)
+                .multiple(true)
+                .requires("monitoring-endpoint")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("monitoring-endpoint-min-validators")
+                .long("monitoring-endpoint-min-validators")
+                .value_name("COUNT")
+                .help("Only send validator metrics to the monitoring-endpoint while at least this \
+                       many validators are enabled. Default: 0")
+                .requires("monitoring-endpoint")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("monitoring-endpoint-redact-validator-ids")
+                .long("monitoring-endpoint-redact-validator-ids")
+                .help("Omit metrics which could identify your validators, such as the number of \
+                       validators, from the messages sent to the monitoring-endpoint.")
+                .requires("monitoring-endpoint")
+                .takes_value(false),
+        )
         .arg(
             Arg::with_name("enable-doppelganger-protection")
                 .long("enable-doppelganger-protection")
@@ -285,6 +815,75 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                     immediately.")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("continuous-doppelganger")
+                .long("continuous-doppelganger")
+                .help("Continue to check for doppelgangers after doppelganger protection has \
+                    completed. A validator which is live in an epoch in which this validator \
+                    client signed neither an attestation nor a block for it is reported with a \
+                    critical log and the vc_doppelganger_continuous_detections_total metric.")
+                .requires("enable-doppelganger-protection")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("continuous-doppelganger-pause")
+                .long("continuous-doppelganger-pause")
+                .help("Stop signing for a validator which is detected by --continuous-doppelganger, \
+                    until the validator client is restarted.")
+                .requires("continuous-doppelganger")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("doppelganger-disable-detected")
+                .long("doppelganger-disable-detected")
+                .help("Disable each validator for which doppelganger protection detects another \
+                    instance in the validator definitions, so that it is not enabled again when \
+                    the validator client restarts. It can be enabled again via the HTTP API once \
+                    no other instance is running it.")
+                .requires("enable-doppelganger-protection")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("startup-warmup")
+                .long("startup-warmup")
+                .value_name("SECONDS")
+                .help("After startup, fetch duties and connect to the beacon nodes for this many \
+                    seconds before signing anything, then start signing from the next slot. \
+                    Unlike doppelganger protection, this does not monitor the network, it only \
+                    avoids acting on incomplete state right after boot. Slashing protection \
+                    applies as usual.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("attestation-deadline-fraction")
+                .long("attestation-deadline-fraction")
+                .value_name("FRACTION")
+                .help("The fraction of the slot, at least 0 and less than 1, after which attestations are \
+                    produced and published. Aggregates are still published two thirds of the way \
+                    through the slot. Values outside of 0.25 to 0.5 are likely to reduce \
+                    rewards. [default: 0.333]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-attestation-slot-lag")
+                .long("max-attestation-slot-lag")
+                .value_name("SLOTS")
+                .help("Refuse to sign an attestation whose slot is more than this many slots \
+                    before the current slot, which can happen if duties or the beacon node have \
+                    stalled. Such attestations earn little or no reward.")
+                .default_value("2")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-pending-aggregations")
+                .long("max-pending-aggregations")
+                .value_name("COUNT")
+                .help("The maximum number of committees whose aggregate attestations are waiting \
+                    to be produced or published, e.g. because the beacon node is slow. When \
+                    exceeded, the aggregation for the oldest committee is dropped. \
+                    [default: 1024]")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("builder-proposals")
                 .long("builder-proposals")
@@ -293,6 +892,29 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                     headers during proposals and will sign over headers. Useful for outsourcing \
                     execution payload construction during proposals.")
                 .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("builder-proposals-enabled-file")
+                .long("builder-proposals-enabled-file")
+                .value_name("FILE")
+                .help("Path to a file containing the voting public keys of validators which \
+                    should use the builder API for proposals, one per line. All other validators \
+                    propose local blocks. A `builder_proposals` value in the validator \
+                    definitions file takes precedence over this file.")
+                .conflicts_with("builder-proposals")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("builder-unavailable-action")
+                .long("builder-unavailable-action")
+                .value_name("ACTION")
+                .help("The action to take when a builder proposal fails, for example because the \
+                    beacon node is unable to reach any relay. With `local`, a block is proposed \
+                    with a payload from the local execution engine. With `skip`, the validator \
+                    declines to propose and a critical error is logged instead.")
+                .possible_values(BuilderUnavailableAction::VARIANTS)
+                .default_value("local")
+                .takes_value(true),
         ).arg(
             Arg::with_name("strict-fee-recipient")
                 .long("strict-fee-recipient")
@@ -314,6 +936,47 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                     timestamp used in the builder api registration")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("builder-registration-timestamp-override-file")
+                .long("builder-registration-timestamp-override-file")
+                .value_name("FILE")
+                .help("Path to a file containing per-validator unix timestamps that will be used \
+                    to override the timestamp used in the builder api registration. Each line \
+                    should have the form \"0xPUBKEY: TIMESTAMP\". Validators not present in the \
+                    file fall back to --builder-registration-timestamp-override, then to the \
+                    current time. This is intended only for debugging relay compatibility.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("relay-tag-file")
+                .long("relay-tag-file")
+                .value_name("FILE")
+                .help("Path to a file containing per-validator relay tags. Each line should have \
+                    the form \"0xPUBKEY: TAG\", where TAG consists of letters, digits, '-' and \
+                    '_'. Tags are published to the beacon node alongside validator registrations \
+                    and do not modify the registrations themselves. The beacon node, or a proxy \
+                    in front of it, must be configured to act on them.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sync-committee-opt-out-file")
+                .long("sync-committee-opt-out-file")
+                .value_name("FILE")
+                .help("Path to a file containing the voting public keys of validators which \
+                    should not participate in sync committees, one per line. These validators \
+                    neither sign sync committee messages nor aggregate contributions, which \
+                    saves bandwidth but forfeits their sync committee rewards and incurs \
+                    penalties whilst they are members of a sync committee.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sync-committee-broadcast")
+                .long("sync-committee-broadcast")
+                .help("Publish sync committee messages and contributions to all synced beacon \
+                    nodes rather than only the first to accept them. Publishing succeeds if any \
+                    beacon node accepts the messages, which are only signed once.")
+                .takes_value(false),
+        )
         .arg(
             Arg::with_name("gas-limit")
                 .long("gas-limit")
@@ -324,6 +987,37 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                     set here moves too far from the previous block's gas limit. [default: 30,000,000]")
                 .requires("builder-proposals"),
         )
+        .arg(
+            Arg::with_name("gas-limit-min")
+                .long("gas-limit-min")
+                .value_name("INTEGER")
+                .takes_value(true)
+                .help("The lowest gas limit registered for any validator, whether set by \
+                    --gas-limit, the validator definitions or the HTTP API. Lower gas limits are \
+                    handled according to --on-gas-limit-out-of-range. [default: 10,000,000]"),
+        )
+        .arg(
+            Arg::with_name("gas-limit-max")
+                .long("gas-limit-max")
+                .value_name("INTEGER")
+                .takes_value(true)
+                .help("The highest gas limit registered for any validator, whether set by \
+                    --gas-limit, the validator definitions or the HTTP API. Higher gas limits are \
+                    handled according to --on-gas-limit-out-of-range. [default: 100,000,000]"),
+        )
+        .arg(
+            Arg::with_name("on-gas-limit-out-of-range")
+                .long("on-gas-limit-out-of-range")
+                .value_name("ACTION")
+                .help("The action to take when a validator's gas limit is outside of \
+                    --gas-limit-min and --gas-limit-max. With `clamp`, the nearest gas limit \
+                    within the range is registered and a warning is logged. With `reject`, the \
+                    validator is not registered and an error is logged, and the validator client \
+                    refuses to start if --gas-limit is outside of the range.")
+                .possible_values(OnGasLimitOutOfRange::VARIANTS)
+                .default_value("clamp")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("latency-measurement-service")
                 .long("latency-measurement-service")
@@ -333,19 +1027,168 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 .default_value("true")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("latency-sample-interval")
+                .long("latency-sample-interval")
+                .value_name("SECONDS")
+                .help("The minimum number of seconds between two latency measurements. The interval \
+                    is rounded up to a whole number of slots, so that measurements always happen \
+                    late in a slot. [default: 12]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("latency-sample-window")
+                .long("latency-sample-window")
+                .value_name("COUNT")
+                .help("The number of latency measurements kept for each BN, over which the \
+                    average latency is reported in metrics. [default: 32]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("min-free-disk-bytes")
+                .long("min-free-disk-bytes")
+                .value_name("BYTES")
+                .help("The minimum number of bytes which must be available on the filesystem \
+                    holding the slashing protection database. The validator client refuses to \
+                    start with less space available, and logs a warning if the space drops below \
+                    this whilst running. Set to 0 to disable the check. [default: 536870912]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("slashing-protection-prune-interval")
+                .long("slashing-protection-prune-interval")
+                .value_name("EPOCHS")
+                .help("The number of epochs between prunings of old records from the slashing \
+                    protection database. Pruning also runs at start up. By default the database \
+                    is pruned every 512 epochs.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("slashing-protection-retention-epochs")
+                .long("slashing-protection-retention-epochs")
+                .value_name("EPOCHS")
+                .help("The number of epochs of history kept when pruning the slashing protection \
+                    database. Only records older than this are removed. Values below 512 are \
+                    rejected, since more recent records may still prevent a slashable message. \
+                    [default: 512]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("strict-dir-permissions")
+                .long("strict-dir-permissions")
+                .help("Refuse to start if the validators or secrets directory can be accessed by \
+                    users other than its owner. By default a warning is logged instead. The \
+                    check is skipped on platforms without Unix permissions.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("require-synced-clock")
+                .long("require-synced-clock")
+                .help("Refuse to start if the system clock is not synchronized, e.g. by NTP, PTP \
+                    or chrony. By default a warning is logged instead. The check is skipped on \
+                    platforms other than Linux, where the synchronization status is unknown.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("dir-access-timeout")
+                .long("dir-access-timeout")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .help("The time allowed to read the validators and secrets directories before \
+                    they are considered inaccessible, e.g. because they are on an unavailable \
+                    network mount. The validator client refuses to start if either is \
+                    inaccessible, and checks them every minute whilst running. [default: 5]"),
+        )
+        .arg(
+            Arg::with_name("strict-default-dirs")
+                .long("strict-default-dirs")
+                .help("Refuse to start on mainnet if neither --datadir nor --validators-dir is \
+                    set, so that validators are never run from a leftover default directory. By \
+                    default a warning is logged instead.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("print-duties")
+                .long("print-duties")
+                .help("Print the attestation, proposal and sync committee duties of all \
+                    validators for the current and next epoch, as reported by the BN, along \
+                    with the times at which they will be performed.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("exit-after-print-duties")
+                .long("exit-after-print-duties")
+                .help("Exit after printing duties with --print-duties, without performing any \
+                    duties or signing anything.")
+                .requires("print-duties")
+                .takes_value(false),
+        )
         .arg(
             Arg::with_name("validator-registration-batch-size")
                 .long("validator-registration-batch-size")
                 .value_name("INTEGER")
                 .help("Defines the number of validators per \
                     validator/register_validator request sent to the BN. This value \
-                    can be reduced to avoid timeouts from builders.")
+                    can be reduced to avoid timeouts from builders. If the BN rejects a \
+                    request as too large, the batch size is automatically halved until it \
+                    succeeds.")
                 .default_value("500")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("validator-registration-max-body-bytes")
+                .long("validator-registration-max-body-bytes")
+                .value_name("BYTES")
+                .help("The maximum size in bytes of the body of a single \
+                    validator/register_validator request sent to the BN. Batches are made \
+                    smaller than --validator-registration-batch-size where necessary to stay \
+                    within this limit. Defaults to no limit.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("validator-registration-refresh-interval")
+                .long("validator-registration-refresh-interval")
+                .value_name("SECONDS")
+                .help("The interval at which all validator registrations are re-published to \
+                    the builder network, in seconds. Registrations which have changed are always \
+                    published in the next slot. The interval is rounded down to a whole number \
+                    of slots and must be at least 60 seconds. [default: once per epoch]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("reregister-on-bn-restart")
+                .long("reregister-on-bn-restart")
+                .help("Query the identity of each beacon node once per slot and re-publish all \
+                    validator registrations to a beacon node as soon as it appears to have \
+                    restarted, since beacon nodes do not persist registrations. A restart is \
+                    detected by a change of peer ID, a reset of the metadata sequence number or \
+                    the node becoming reachable again.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("block-prefetch-lead-ms")
+                .long("block-prefetch-lead-ms")
+                .value_name("MILLIS")
+                .help("Request the block of each proposal this many milliseconds before the start \
+                    of its slot, to reduce the latency of proposing. The block is only signed \
+                    once the slot starts, and is discarded and requested again if the head of \
+                    the beacon node has changed by then. At most a third of the slot duration. \
+                    By default the block is requested at the start of the slot.")
+                .takes_value(true),
+        )
         /*
          * Experimental/development options.
          */
+        .arg(
+            Arg::with_name("block-max-transactions")
+                .long("block-max-transactions")
+                .value_name("COUNT")
+                .help("Request that locally built blocks contain at most this many transactions, \
+                    for testing. Only beacon nodes which support the limit apply it, others \
+                    ignore it. Blocks from builders are not limited. At most the protocol limit \
+                    of transactions in an execution payload.")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("block-delay-ms")
                 .long("block-delay-ms")
@@ -355,4 +1198,123 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                        used for testing.")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("block-delay-slot-fraction")
+                .long("block-delay-slot-fraction")
+                .value_name("FRACTION")
+                .hidden(true)
+                .help("Time to delay block production from the start of the slot, as a fraction \
+                       of the slot duration. Should only be used for testing.")
+                .conflicts_with("block-delay-ms")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("block-delay-attestation-deadline-offset-ms")
+                .long("block-delay-attestation-deadline-offset-ms")
+                .value_name("MILLIS")
+                .hidden(true)
+                .help("Time to delay block production relative to the attestation deadline. A \
+                       negative value produces the block before the deadline. Should only be used \
+                       for testing.")
+                .conflicts_with_all(&["block-delay-ms", "block-delay-slot-fraction"])
+                .allow_hyphen_values(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("allow-large-block-delay")
+                .long("allow-large-block-delay")
+                .hidden(true)
+                .help("Permit a block delay of more than a third of the slot duration, which \
+                       is likely to cause the block to be orphaned. Should only be used for \
+                       testing.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("disable-block-verification")
+                .long("disable-block-verification")
+                .help("By default, a block produced by a beacon node is only signed if it is for \
+                       the requested slot, is proposed by the validator and has a non-zero parent \
+                       root. Otherwise it is logged and the next beacon node is tried. This \
+                       option disables the checks, except for the proposer index.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("disable-fee-recipient-verification")
+                .long("disable-fee-recipient-verification")
+                .help("By default, a locally built block produced by a beacon node is only \
+                       signed if its execution payload pays the fee recipient configured for the \
+                       validator. Otherwise it is logged and the next beacon node is tried. This \
+                       option disables the check. Builder blocks are never checked.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("allow-proposal-on-optimistic-head")
+                .long("allow-proposal-on-optimistic-head")
+                .help("By default, a block is only requested from a beacon node whose head has \
+                       been verified by its execution engine. If the head is optimistic the \
+                       refusal is logged and the next beacon node is tried. This option allows \
+                       proposing on an optimistic head.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("first-proposal-caution-epochs")
+                .long("first-proposal-caution-epochs")
+                .value_name("EPOCHS")
+                .help("Check the beacon node more thoroughly before the proposals of validators \
+                       activated within this many epochs: the beacon node must be synced with a \
+                       verified head in which the validator is active, and agree with the fork \
+                       version used for signing. If a check fails the refusal is logged and the \
+                       next beacon node is tried. Disabled by default.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("refuse-attestation-on-optimistic-head")
+                .long("refuse-attestation-on-optimistic-head")
+                .help("Only request attestation data from a beacon node whose head has been \
+                       verified by its execution engine, trying the next beacon node otherwise. \
+                       This costs an additional request per committee and slot.")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("genesis-time-override")
+                .long("genesis-time-override")
+                .value_name("UNIX_SECONDS")
+                .hidden(true)
+                .help("Use this genesis time for the slot clock instead of the one provided by \
+                       the beacon node. The validator client does not wait for the beacon node \
+                       to reach genesis if the genesis validators root is also known from the \
+                       network config or --genesis-validators-root-override. Should only be \
+                       used for testing.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("seconds-per-slot-override")
+                .long("seconds-per-slot-override")
+                .value_name("SECONDS")
+                .hidden(true)
+                .help("Use this slot duration instead of the one from the network config. Should \
+                       only be used for testing.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("genesis-validators-root-override")
+                .long("genesis-validators-root-override")
+                .value_name("HASH")
+                .hidden(true)
+                .help("Use this genesis validators root when signing instead of the one provided \
+                       by the beacon node. Should only be used for testing.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("fork-schedule-override")
+                .long("fork-schedule-override")
+                .value_name("FILE")
+                .hidden(true)
+                .help("Path to a JSON or YAML list of forks, each with a `fork` name, `version` \
+                       and optional `epoch`, to use when signing instead of the fork versions and \
+                       epochs from the network config. The fork versions used for signing are \
+                       then not compared against those of the beacon node, so \
+                       --on-fork-version-mismatch has no effect. Should only be used for testing.")
+                .takes_value(true),
+        )
 }
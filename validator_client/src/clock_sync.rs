@@ -0,0 +1,104 @@
+//! Checks that the system clock is synchronized, e.g. by NTP, PTP or chrony.
+//!
+//! Slot timing is derived from the system clock, so an unsynchronized clock may drift far enough
+//! to miss duties. The synchronization status is read from the kernel, which is only possible on
+//! Linux. On other platforms the status is unknown and the check is skipped.
+
+use slog::{debug, error, warn, Logger};
+
+/// The synchronization status of the system clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSyncStatus {
+    Synced,
+    Unsynced,
+    /// The status cannot be read on this platform.
+    Unknown,
+}
+
+/// Reads the synchronization status of the system clock from the kernel.
+#[cfg(target_os = "linux")]
+pub fn clock_sync_status() -> ClockSyncStatus {
+    // A `modes` of zero only reads the state of the kernel clock, without adjusting it.
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+    if state < 0 {
+        ClockSyncStatus::Unknown
+    } else if state == libc::TIME_ERROR || timex.status & libc::STA_UNSYNC != 0 {
+        ClockSyncStatus::Unsynced
+    } else {
+        ClockSyncStatus::Synced
+    }
+}
+
+/// Reads the synchronization status of the system clock from the kernel.
+#[cfg(not(target_os = "linux"))]
+pub fn clock_sync_status() -> ClockSyncStatus {
+    ClockSyncStatus::Unknown
+}
+
+/// Checks that the system clock is synchronized.
+///
+/// Returns an error if the clock is unsynchronized and `require_synced_clock`, otherwise logs a
+/// warning. An unknown status is never an error.
+pub fn check_clock_synced(require_synced_clock: bool, log: &Logger) -> Result<(), String> {
+    check_status(clock_sync_status(), require_synced_clock, log)
+}
+
+fn check_status(
+    status: ClockSyncStatus,
+    require_synced_clock: bool,
+    log: &Logger,
+) -> Result<(), String> {
+    match status {
+        ClockSyncStatus::Synced => {
+            debug!(log, "System clock is synchronized");
+            Ok(())
+        }
+        ClockSyncStatus::Unsynced if require_synced_clock => {
+            error!(
+                log,
+                "System clock is not synchronized";
+                "msg" => "refusing to start because --require-synced-clock is set"
+            );
+            Err(
+                "The system clock is not synchronized, check that NTP, PTP or chrony is \
+                 running and has synchronized the clock"
+                    .to_string(),
+            )
+        }
+        ClockSyncStatus::Unsynced => {
+            warn!(
+                log,
+                "System clock is not synchronized";
+                "msg" => "clock drift may cause missed duties, check that NTP, PTP or chrony is \
+                          running and has synchronized the clock"
+            );
+            Ok(())
+        }
+        ClockSyncStatus::Unknown => {
+            debug!(
+                log,
+                "Unable to determine system clock synchronization";
+                "msg" => "the check is not supported on this platform"
+            );
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logging::test_logger;
+
+    #[test]
+    fn unsynced_clock_is_only_refused_when_required() {
+        let log = test_logger();
+        for require in [false, true] {
+            assert!(check_status(ClockSyncStatus::Synced, require, &log).is_ok());
+            assert!(check_status(ClockSyncStatus::Unknown, require, &log).is_ok());
+        }
+        assert!(check_status(ClockSyncStatus::Unsynced, false, &log).is_ok());
+        assert!(check_status(ClockSyncStatus::Unsynced, true, &log).is_err());
+    }
+}
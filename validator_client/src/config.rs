@@ -1,5 +1,19 @@
-use crate::graffiti_file::GraffitiFile;
-use crate::{http_api, http_metrics};
+use crate::beacon_node_fallback::BeaconNodeSelection;
+use crate::block_service::BuilderUnavailableAction;
+use crate::doppelganger_service::DEFAULT_REMAINING_DETECTION_EPOCHS;
+use crate::duties_service::MetricsValidatorBucketing;
+use crate::fork_schedule::{load_fork_schedule_override, ForkOverride, OnForkVersionMismatch};
+use crate::graffiti_file::{GraffitiFile, OnDuplicateGraffiti};
+use crate::initialized_validators::{
+    read_load_priority_file, Config as InitializedValidatorsConfig,
+};
+use crate::preparation_service::{GasLimitBounds, OnGasLimitOutOfRange, OnMissingFeeRecipient};
+use crate::pubkey_list_file::PubkeyListFile;
+use crate::registration_timestamp_file::RegistrationTimestampFile;
+use crate::relay_tag_file::RelayTagFile;
+use crate::signing_method::Pkcs11Config;
+use crate::validator_store::SLASHING_PROTECTION_HISTORY_EPOCHS;
+use crate::{dir_access, http_api, http_metrics, load_pem_certificate, parse_tls_version};
 use clap::ArgMatches;
 use clap_utils::{flags::DISABLE_MALLOC_TUNING_FLAG, parse_optional, parse_required};
 use directory::{
@@ -9,15 +23,162 @@ use directory::{
 use eth2::types::Graffiti;
 use sensitive_url::SensitiveUrl;
 use serde_derive::{Deserialize, Serialize};
+use slashing_protection::{POOL_SIZE, SLASHING_PROTECTION_FILENAME};
 use slog::{info, warn, Logger};
 use std::fs;
 use std::net::IpAddr;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::time::Duration;
-use types::{Address, GRAFFITI_BYTES_LEN};
+use strum::{Display, EnumString, EnumVariantNames};
+use types::{Address, EthSpec, Hash256, PublicKey, GRAFFITI_BYTES_LEN};
 
 pub const DEFAULT_BEACON_NODE: &str = "http://localhost:5052/";
 
+/// The minimum permitted value for `validator_registration_refresh_interval`.
+///
+/// Avoids spamming the builder network with registrations.
+pub const MIN_VALIDATOR_REGISTRATION_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The default number of retries of a slashing protection database operation.
+pub const DEFAULT_SLASHING_PROTECTION_RETRIES: u32 = 3;
+
+/// The default delay before the first retry of a slashing protection database operation.
+pub const DEFAULT_SLASHING_PROTECTION_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// The default `attestation_deadline_fraction`, as recommended by the specification.
+pub const DEFAULT_ATTESTATION_DEADLINE_FRACTION: f64 = 1.0 / 3.0;
+
+/// The default `max_attestation_slot_lag`.
+pub const DEFAULT_MAX_ATTESTATION_SLOT_LAG: u64 = 2;
+
+/// The default `max_pending_aggregations`, far more than the number of committees in a few slots.
+pub const DEFAULT_MAX_PENDING_AGGREGATIONS: usize = 1_024;
+
+/// The default `min_free_disk_bytes`, 512 MiB. The slashing protection database itself is far
+/// smaller, but other processes may share the filesystem.
+pub const DEFAULT_MIN_FREE_DISK_BYTES: u64 = 512 * 1024 * 1024;
+
+/// The default `beacon_nodes_tls_cert_expiry_window`, 14 days.
+pub const DEFAULT_TLS_CERT_EXPIRY_WINDOW: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// The default `dir_access_timeout`. Reading a local directory takes far less, so this is only
+/// reached if the directory is on an unavailable network mount.
+pub const DEFAULT_DIR_ACCESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The default `fork_transition_window`, in epochs either side of a fork.
+pub const DEFAULT_FORK_TRANSITION_WINDOW: u64 = 2;
+
+/// The default `index_resolution_interval`.
+pub const DEFAULT_INDEX_RESOLUTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The default `duties_batch_size`.
+pub const DEFAULT_DUTIES_BATCH_SIZE: usize = 4096;
+
+/// The default `activation_poll_epochs`.
+pub const DEFAULT_ACTIVATION_POLL_EPOCHS: u64 = 1;
+
+/// The default `latency_sample_interval`, one mainnet slot.
+pub const DEFAULT_LATENCY_SAMPLE_INTERVAL: Duration = Duration::from_secs(12);
+
+/// The default `latency_sample_window`.
+pub const DEFAULT_LATENCY_SAMPLE_WINDOW: usize = 32;
+
+/// The name of the network for which the use of default directories is checked.
+const MAINNET_NETWORK: &str = "mainnet";
+
+/// The default `gas_limit_min`, a third of the default gas limit. The protocol itself only requires
+/// a gas limit of at least 5,000.
+pub const DEFAULT_GAS_LIMIT_MIN: u64 = 10_000_000;
+
+/// The default `gas_limit_max`, far above the default gas limit but below the limits which are
+/// typically the result of a mistyped value.
+pub const DEFAULT_GAS_LIMIT_MAX: u64 = 100_000_000;
+
+/// The default `slow_sign_threshold`.
+pub const DEFAULT_SLOW_SIGN_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Values of `attestation_deadline_fraction` outside of this range are permitted, but risk
+/// attesting before the block has arrived or too late to be included.
+pub const RECOMMENDED_ATTESTATION_DEADLINE_FRACTIONS: std::ops::RangeInclusive<f64> = 0.25..=0.5;
+
+/// The largest permitted `block_delay`, as a fraction of the slot duration, unless
+/// `allow_large_block_delay` is set.
+///
+/// A block published later than this is likely to miss the attestation deadline.
+pub const MAX_BLOCK_DELAY_SLOT_FRACTION: u32 = 3;
+
+/// The largest permitted `block_prefetch_lead`, as a fraction of the slot duration.
+///
+/// A block requested earlier than this is likely to be built on a head which changes before the
+/// slot starts.
+pub const MAX_BLOCK_PREFETCH_SLOT_FRACTION: u32 = 3;
+
+/// The action to take when `--init-slashing-protection` is used and a validator is already
+/// present in the slashing protection database.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum OnExistingSlashingProtection {
+    /// Keep the existing records of the validator, so that it can never sign anything below
+    /// what it has already signed.
+    #[default]
+    Keep,
+    /// Refuse to start.
+    Strict,
+}
+
+/// The `slashing_protection_pool_size` of `low_memory_mode`.
+pub const LOW_MEMORY_SLASHING_PROTECTION_POOL_SIZE: u32 = 1;
+
+/// The `max_concurrent_signings` of `low_memory_mode`.
+pub const LOW_MEMORY_MAX_CONCURRENT_SIGNINGS: usize = 4;
+
+/// The `max_pending_aggregations` of `low_memory_mode`.
+pub const LOW_MEMORY_MAX_PENDING_AGGREGATIONS: usize = 64;
+
+/// The `latency_sample_window` of `low_memory_mode`.
+pub const LOW_MEMORY_LATENCY_SAMPLE_WINDOW: usize = 8;
+
+/// The `validator_registration_batch_size` of `low_memory_mode`.
+pub const LOW_MEMORY_VALIDATOR_REGISTRATION_BATCH_SIZE: usize = 100;
+
+/// The number of idle connections kept open to each beacon node in `low_memory_mode`.
+pub const LOW_MEMORY_MAX_IDLE_CONNECTIONS_PER_BEACON_NODE: usize = 1;
+
+/// The action to take when the `--graffiti` is longer than `GRAFFITI_BYTES_LEN`.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum OnGraffitiOverflow {
+    /// Refuse to start.
+    #[default]
+    Error,
+    /// Log a warning and use as many whole characters as fit.
+    Truncate,
+}
+
 /// Stores the core configuration for this validator instance.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -25,27 +186,82 @@ pub struct Config {
     pub validator_dir: PathBuf,
     /// The directory containing the passwords to unlock validator keystores.
     pub secrets_dir: PathBuf,
+    /// The name of the network, which replaces the `{network}` graffiti token.
+    pub network_name: String,
     /// The http endpoints of the beacon node APIs.
     ///
     /// Should be similar to `["http://localhost:8080"]`
     pub beacon_nodes: Vec<SensitiveUrl>,
     /// An optional beacon node used for block proposals only.
     pub proposer_nodes: Vec<SensitiveUrl>,
+    /// If true, send validator registrations to the `proposer_nodes`, falling back to the
+    /// `beacon_nodes`.
+    pub register_via_proposer_nodes: bool,
+    /// Beacon nodes used for attestations only, and only when none of the `beacon_nodes` are
+    /// available.
+    pub attestation_fallback_nodes: Vec<SensitiveUrl>,
     /// If true, the validator client will still poll for duties and produce blocks even if the
     /// beacon node is not synced at startup.
     pub allow_unsynced_beacon_node: bool,
     /// If true, don't scan the validators dir for new keystores.
     pub disable_auto_discover: bool,
+    /// If true, replace the cache of validator indices instead of loading it.
+    pub rebuild_index_cache: bool,
     /// If true, re-register existing validators in definitions.yml for slashing protection.
     pub init_slashing_protection: bool,
+    /// The action to take when `init_slashing_protection` is set and a validator is already
+    /// registered for slashing protection.
+    pub on_existing_slashing_protection: OnExistingSlashingProtection,
+    /// The number of times to retry a slashing protection database operation which failed because
+    /// the database was in use.
+    pub slashing_protection_retries: u32,
+    /// The delay before the first retry of a slashing protection database operation, doubled for
+    /// each subsequent retry.
+    pub slashing_protection_retry_delay: Duration,
+    /// The number of connections to the slashing protection database.
+    ///
+    /// If `None`, `POOL_SIZE` connections are used, which keeps the database locked against other
+    /// processes.
+    pub slashing_protection_pool_size: Option<u32>,
+    /// The maximum number of concurrent signing operations, or `None` for no limit.
+    pub max_concurrent_signings: Option<usize>,
+    /// If true, reduce cache, connection pool and batch sizes to minimise memory usage, at the
+    /// cost of slightly higher latency. Settings given individually take precedence.
+    pub low_memory_mode: bool,
+    /// Signing operations which take longer than this are logged with a warning and counted.
+    pub slow_sign_threshold: Duration,
+    /// The number of epochs after activation in which a validator's proposals are preceded by
+    /// additional checks of the beacon node head and fork, if enabled.
+    pub first_proposal_caution_epochs: Option<u64>,
     /// If true, use longer timeouts for requests made to the beacon node.
     pub use_long_timeouts: bool,
     /// Graffiti to be inserted everytime we create a block.
     pub graffiti: Option<Graffiti>,
+    /// The action to take when `--graffiti` is too long.
+    pub graffiti_on_overflow: OnGraffitiOverflow,
+    /// If true, `graffiti` takes precedence over the graffiti file and validator definitions.
+    pub graffiti_force: bool,
     /// Graffiti file to load per validator graffitis.
     pub graffiti_file: Option<GraffitiFile>,
     /// Fallback fallback address.
     pub fee_recipient: Option<Address>,
+    /// The action to take when a validator has no fee recipient configured.
+    pub on_missing_fee_recipient: OnMissingFeeRecipient,
+    /// Configuration for the initialization of validators.
+    pub initialized_validators: InitializedValidatorsConfig,
+    /// A file listing the voting public keys of validators to initialize before all others.
+    pub validator_load_priority: Option<PathBuf>,
+    /// Only validators with an index in the half-open range `[start, end)` perform duties.
+    pub validator_index_range: Option<(u64, u64)>,
+    /// The action to take when our fork version differs from the one reported by the beacon node.
+    pub on_fork_version_mismatch: OnForkVersionMismatch,
+    /// If true, proposals are paused when our fork version differs from the one reported by the
+    /// beacon node within `fork_transition_window` epochs of a fork.
+    pub fork_transition_caution: bool,
+    /// The number of epochs either side of a fork in which fork transition caution applies.
+    pub fork_transition_window: u64,
+    /// A validator for which detailed diagnostics of each duty are logged at `INFO` level.
+    pub canary_validator: Option<PublicKey>,
     /// Configuration for the HTTP REST API.
     pub http_api: http_api::Config,
     /// Configuration for the HTTP REST API.
@@ -55,30 +271,203 @@ pub struct Config {
     /// If true, enable functionality that monitors the network for attestations or proposals from
     /// any of the validators managed by this client before starting up.
     pub enable_doppelganger_protection: bool,
+    /// If true, continue checking validators after doppelganger detection has completed, alerting
+    /// if one is live in an epoch in which this validator client signed nothing for it.
+    pub continuous_doppelganger: bool,
+    /// If true, disable signing for a validator detected by continuous doppelganger detection.
+    pub continuous_doppelganger_pause: bool,
+    /// If true, disable each validator with a detected doppelganger in the validator definitions,
+    /// so that it stays disabled after a restart.
+    pub doppelganger_disable_detected: bool,
+    /// Delay signing duties until the first slot which starts after this period has elapsed since
+    /// startup, while duties are fetched and beacon node connectivity is established.
+    pub startup_warmup: Option<Duration>,
+    /// The fraction of the slot, from its start, after which attestations are produced and
+    /// published.
+    pub attestation_deadline_fraction: f64,
+    /// Refuse to sign attestations for slots more than this many slots before the current slot.
+    pub max_attestation_slot_lag: u64,
+    /// The maximum number of committees whose aggregates are waiting to be produced or published.
+    pub max_pending_aggregations: usize,
     /// If true, then we publish validator specific metrics (e.g next attestation duty slot)
     /// for all our managed validators.
     /// Note: We publish validator specific metrics for low validator counts without this flag
     /// (<= 64 validators)
     pub enable_high_validator_count_metrics: bool,
+    /// Publish the duty metrics of each validator, or of buckets of validators to bound the number
+    /// of series with large numbers of validators.
+    pub metrics_validator_bucketing: MetricsValidatorBucketing,
+    /// Raise an alert when the effective balance of a validator falls below this value.
+    pub min_balance_alert_gwei: Option<u64>,
+    /// Disable validators which have exited or been slashed.
+    pub auto_disable_inactive: bool,
+    /// The minimum time between lookups of the index of a validator which is not yet known to the
+    /// beacon chain.
+    pub index_resolution_interval: Duration,
+    /// Poll the status of pending validators every slot, rather than once per epoch, once they are
+    /// due to be activated within this many epochs.
+    pub activation_poll_epochs: u64,
+    /// The maximum number of validator indices in each attester or sync duties request. The batch
+    /// size is reduced automatically for beacon nodes which refuse requests as too large.
+    pub duties_batch_size: usize,
     /// Enable use of the blinded block endpoints during proposals.
     pub builder_proposals: bool,
+    /// Validators which use the builder API for proposals when `builder_proposals` is false.
+    pub builder_proposals_enabled_pubkeys: Option<PubkeyListFile>,
+    /// The action to take when a builder proposal fails.
+    pub builder_unavailable_action: BuilderUnavailableAction,
     /// Overrides the timestamp field in builder api ValidatorRegistrationV1
     pub builder_registration_timestamp_override: Option<u64>,
+    /// Per-validator overrides for the timestamp field in builder api ValidatorRegistrationV1.
+    ///
+    /// Takes precedence over `builder_registration_timestamp_override`.
+    pub builder_registration_timestamp_override_file: Option<RegistrationTimestampFile>,
+    /// Per-validator relay tags, published to the beacon node alongside validator registrations.
+    pub relay_tag_file: Option<RelayTagFile>,
+    /// Validators which do not sign sync committee messages or aggregate sync committee
+    /// contributions.
+    pub sync_committee_opt_out_file: Option<PubkeyListFile>,
+    /// Publish sync committee messages and contributions to all synced beacon nodes.
+    pub sync_committee_broadcast: bool,
     /// Fallback gas limit.
     pub gas_limit: Option<u64>,
+    /// The lowest gas limit registered for a validator.
+    pub gas_limit_min: u64,
+    /// The highest gas limit registered for a validator.
+    pub gas_limit_max: u64,
+    /// The action to take when a validator's gas limit is outside `gas_limit_min..=gas_limit_max`.
+    pub on_gas_limit_out_of_range: OnGasLimitOutOfRange,
     /// A list of custom certificates that the validator client will additionally use when
     /// connecting to a beacon node over SSL/TLS.
     pub beacon_nodes_tls_certs: Option<Vec<PathBuf>>,
+    /// The minimum TLS version, e.g. `1.3`, to negotiate when connecting to a beacon node over
+    /// SSL/TLS.
+    pub beacon_nodes_min_tls_version: Option<String>,
+    /// Maximum time an idle connection to a beacon node is kept in the connection pool before
+    /// it is closed, forcing the hostname to be re-resolved on the next request.
+    ///
+    /// A value of zero disables connection reuse entirely. `None` keeps the HTTP client default.
+    pub beacon_node_dns_ttl: Option<Duration>,
+    /// Warn when the TLS certificate presented by a beacon node expires within this window.
+    ///
+    /// A zero window disables the check.
+    pub beacon_nodes_tls_cert_expiry_window: Duration,
     /// Delay from the start of the slot to wait before publishing a block.
     ///
     /// This is *not* recommended in prod and should only be used for testing.
     pub block_delay: Option<Duration>,
+    /// Delay from the start of the slot to wait before publishing a block, as a fraction of the
+    /// slot duration. Mutually exclusive with `block_delay`.
+    pub block_delay_slot_fraction: Option<f64>,
+    /// Delay before publishing a block relative to the attestation deadline, in milliseconds. A
+    /// negative offset publishes the block before the deadline. Mutually exclusive with
+    /// `block_delay`.
+    pub block_delay_attestation_deadline_offset_ms: Option<i64>,
+    /// If true, permit a `block_delay` longer than `1 / MAX_BLOCK_DELAY_SLOT_FRACTION` of the slot
+    /// duration.
+    pub allow_large_block_delay: bool,
+    /// How long before the start of a proposal slot to speculatively request the block, or `None`
+    /// to request it at the start of the slot.
+    ///
+    /// The block is only signed once the slot starts, and is discarded if the head has changed.
+    pub block_prefetch_lead: Option<Duration>,
+    /// The maximum number of transactions in a locally built block, passed to beacon nodes which
+    /// support it. Only intended for testing.
+    pub block_max_transactions: Option<u64>,
+    /// Check that a block produced by the BN is for the requested slot and validator and has a
+    /// non-zero parent root before signing it.
+    pub verify_block_before_signing: bool,
+    /// Check that a locally built block produced by the BN pays the fee recipient configured for
+    /// the validator before signing it.
+    pub verify_fee_recipient: bool,
+    /// Refuse to propose a block with a beacon node whose head is optimistic.
+    pub refuse_proposal_on_optimistic: bool,
+    /// Refuse to produce attestations with a beacon node whose head is optimistic.
+    pub refuse_attestation_on_optimistic: bool,
+    /// Genesis time to use for the slot clock instead of the one provided by the beacon node.
+    ///
+    /// This is *not* recommended in prod and should only be used for testing.
+    pub genesis_time_override: Option<u64>,
+    /// Slot duration to use instead of the one from the network config.
+    ///
+    /// This is *not* recommended in prod and should only be used for testing.
+    pub seconds_per_slot_override: Option<u64>,
+    /// Genesis validators root to use for signing instead of the one provided by the beacon node.
+    ///
+    /// This is *not* recommended in prod and should only be used for testing.
+    pub genesis_validators_root_override: Option<Hash256>,
+    /// Fork versions and epochs to use for signing instead of the ones from the network config.
+    ///
+    /// This is *not* recommended in prod and should only be used for testing.
+    pub fork_schedule_override: Option<Vec<ForkOverride>>,
     /// Disables publishing http api requests to all beacon nodes for select api calls.
     pub disable_run_on_all: bool,
+    /// Decode beacon node responses which omit optional fields with defaults, rather than failing.
+    pub lenient_bn_responses: bool,
+    /// Compare the heads of the beacon nodes and quarantine any which persistently disagree with
+    /// the majority.
+    pub beacon_node_consensus_check: bool,
+    /// Refuse to use any beacon node which reports a different genesis validators root to the
+    /// beacon node used at startup.
+    pub require_matching_genesis: bool,
+    /// The order in which synced beacon nodes are tried for requests which only need a single
+    /// response.
+    pub beacon_node_selection: BeaconNodeSelection,
+    /// Exit if no beacon node is reachable within this time of startup, rather than retrying
+    /// forever.
+    pub beacon_node_startup_timeout: Option<Duration>,
+    /// Store a hash of the config in `validator_dir` and warn if it differs from the previous run.
+    pub detect_config_changes: bool,
+    /// Refuse to start if the beacon nodes run different clients or client versions.
+    pub require_homogeneous_beacon_nodes: bool,
+    /// Save the health of the beacon nodes in `validator_dir` and restore it at startup.
+    pub persist_beacon_node_health: bool,
+    /// If true, attestations rejected by one beacon node are published to the next one.
+    pub attestation_publish_retry_nodes: bool,
     /// Enables a service which attempts to measure latency between the VC and BNs.
     pub enable_latency_measurement_service: bool,
+    /// The minimum time between two latency measurements, rounded up to a whole number of slots.
+    pub latency_sample_interval: Duration,
+    /// The number of latency measurements per BN over which the average latency is computed.
+    pub latency_sample_window: usize,
+    /// The minimum number of bytes which must be available on the filesystem holding the slashing
+    /// protection database, or 0 to disable the check.
+    pub min_free_disk_bytes: u64,
+    /// The number of epochs between prunings of the slashing protection database, or `None` to
+    /// prune every 512 epochs.
+    pub slashing_protection_prune_interval: Option<u64>,
+    /// The number of epochs of slashing protection history kept when pruning, at least 512.
+    pub slashing_protection_retention_epochs: u64,
+    /// If true, refuse to start if the `validator_dir` or `secrets_dir` can be accessed by users
+    /// other than their owner, rather than logging a warning.
+    pub strict_dir_permissions: bool,
+    /// If true, refuse to start if the system clock is not synchronized, rather than logging a
+    /// warning. Skipped on platforms where the synchronization status is unknown.
+    pub require_synced_clock: bool,
+    /// If true, refuse to start on mainnet if the `validator_dir` is the hardcoded default, rather
+    /// than logging a warning.
+    pub strict_default_dirs: bool,
+    /// The time allowed to read the `validator_dir` or `secrets_dir` before it is considered
+    /// inaccessible, e.g. because it is on an unavailable network mount.
+    pub dir_access_timeout: Duration,
+    /// Print the upcoming duties of all validators at start up.
+    pub print_duties: bool,
+    /// Exit after printing duties, without performing any of them.
+    pub exit_after_print_duties: bool,
     /// Defines the number of validators per `validator/register_validator` request sent to the BN.
     pub validator_registration_batch_size: usize,
+    /// The maximum size of the JSON body of a single `validator/register_validator` request.
+    ///
+    /// Batches are made smaller than `validator_registration_batch_size` where required to stay
+    /// within this limit.
+    pub validator_registration_max_body_bytes: Option<usize>,
+    /// The interval at which all validator registrations are re-published to the BN.
+    ///
+    /// If `None`, registrations are re-published once per epoch.
+    pub validator_registration_refresh_interval: Option<Duration>,
+    /// Re-publish all validator registrations to a beacon node as soon as it is detected to have
+    /// restarted, rather than waiting for the next refresh.
+    pub reregister_on_bn_restart: bool,
 }
 
 impl Default for Config {
@@ -98,28 +487,111 @@ impl Default for Config {
         Self {
             validator_dir,
             secrets_dir,
+            network_name: DEFAULT_HARDCODED_NETWORK.to_string(),
             beacon_nodes,
             proposer_nodes: Vec::new(),
+            register_via_proposer_nodes: false,
+            attestation_fallback_nodes: Vec::new(),
             allow_unsynced_beacon_node: false,
             disable_auto_discover: false,
+            rebuild_index_cache: false,
             init_slashing_protection: false,
+            on_existing_slashing_protection: OnExistingSlashingProtection::default(),
+            slashing_protection_retries: DEFAULT_SLASHING_PROTECTION_RETRIES,
+            slashing_protection_retry_delay: DEFAULT_SLASHING_PROTECTION_RETRY_DELAY,
+            slashing_protection_pool_size: None,
+            max_concurrent_signings: None,
+            low_memory_mode: false,
+            slow_sign_threshold: DEFAULT_SLOW_SIGN_THRESHOLD,
+            first_proposal_caution_epochs: None,
             use_long_timeouts: false,
             graffiti: None,
+            graffiti_on_overflow: OnGraffitiOverflow::default(),
+            graffiti_force: false,
             graffiti_file: None,
             fee_recipient: None,
+            on_missing_fee_recipient: <_>::default(),
+            initialized_validators: <_>::default(),
+            validator_load_priority: None,
+            validator_index_range: None,
+            on_fork_version_mismatch: <_>::default(),
+            fork_transition_caution: true,
+            fork_transition_window: DEFAULT_FORK_TRANSITION_WINDOW,
+            canary_validator: None,
             http_api: <_>::default(),
             http_metrics: <_>::default(),
             monitoring_api: None,
             enable_doppelganger_protection: false,
+            continuous_doppelganger: false,
+            continuous_doppelganger_pause: false,
+            doppelganger_disable_detected: false,
+            startup_warmup: None,
+            attestation_deadline_fraction: DEFAULT_ATTESTATION_DEADLINE_FRACTION,
+            max_attestation_slot_lag: DEFAULT_MAX_ATTESTATION_SLOT_LAG,
+            max_pending_aggregations: DEFAULT_MAX_PENDING_AGGREGATIONS,
             enable_high_validator_count_metrics: false,
+            metrics_validator_bucketing: MetricsValidatorBucketing::default(),
+            min_balance_alert_gwei: None,
+            auto_disable_inactive: false,
+            index_resolution_interval: DEFAULT_INDEX_RESOLUTION_INTERVAL,
+            activation_poll_epochs: DEFAULT_ACTIVATION_POLL_EPOCHS,
+            duties_batch_size: DEFAULT_DUTIES_BATCH_SIZE,
             beacon_nodes_tls_certs: None,
+            beacon_nodes_min_tls_version: None,
+            beacon_node_dns_ttl: None,
+            beacon_nodes_tls_cert_expiry_window: DEFAULT_TLS_CERT_EXPIRY_WINDOW,
             block_delay: None,
+            block_delay_slot_fraction: None,
+            block_delay_attestation_deadline_offset_ms: None,
+            allow_large_block_delay: false,
+            block_prefetch_lead: None,
+            block_max_transactions: None,
+            verify_block_before_signing: true,
+            verify_fee_recipient: true,
+            refuse_proposal_on_optimistic: true,
+            refuse_attestation_on_optimistic: false,
+            genesis_time_override: None,
+            seconds_per_slot_override: None,
+            genesis_validators_root_override: None,
+            fork_schedule_override: None,
             builder_proposals: false,
+            builder_proposals_enabled_pubkeys: None,
+            builder_unavailable_action: <_>::default(),
             builder_registration_timestamp_override: None,
+            builder_registration_timestamp_override_file: None,
+            relay_tag_file: None,
+            sync_committee_opt_out_file: None,
+            sync_committee_broadcast: false,
             gas_limit: None,
+            gas_limit_min: DEFAULT_GAS_LIMIT_MIN,
+            gas_limit_max: DEFAULT_GAS_LIMIT_MAX,
+            on_gas_limit_out_of_range: OnGasLimitOutOfRange::default(),
             disable_run_on_all: false,
+            lenient_bn_responses: true,
+            beacon_node_consensus_check: false,
+            require_matching_genesis: true,
+            beacon_node_selection: <_>::default(),
+            beacon_node_startup_timeout: None,
+            detect_config_changes: false,
+            require_homogeneous_beacon_nodes: false,
+            persist_beacon_node_health: false,
+            attestation_publish_retry_nodes: true,
             enable_latency_measurement_service: true,
+            latency_sample_interval: DEFAULT_LATENCY_SAMPLE_INTERVAL,
+            latency_sample_window: DEFAULT_LATENCY_SAMPLE_WINDOW,
+            min_free_disk_bytes: DEFAULT_MIN_FREE_DISK_BYTES,
+            slashing_protection_prune_interval: None,
+            slashing_protection_retention_epochs: SLASHING_PROTECTION_HISTORY_EPOCHS,
+            strict_dir_permissions: false,
+            require_synced_clock: false,
+            strict_default_dirs: false,
+            dir_access_timeout: DEFAULT_DIR_ACCESS_TIMEOUT,
+            print_duties: false,
+            exit_after_print_duties: false,
             validator_registration_batch_size: 500,
+            validator_registration_max_body_bytes: None,
+            validator_registration_refresh_interval: None,
+            reregister_on_bn_restart: false,
         }
     }
 }
@@ -129,6 +601,7 @@ impl Config {
     /// `cli_args`.
     pub fn from_cli(cli_args: &ArgMatches, log: &Logger) -> Result<Config, String> {
         let mut config = Config::default();
+        let mut deprecated_flags = vec![];
 
         let default_root_dir = dirs::home_dir()
             .map(|home| home.join(DEFAULT_ROOT_DIR))
@@ -147,17 +620,60 @@ impl Config {
             secrets_dir = Some(parse_required(cli_args, "secrets-dir")?);
         }
 
+        config.network_name = get_network_dir(cli_args);
+
+        let network_dir = match cli_args.value_of("network-dir-name") {
+            Some(name) => {
+                validate_network_dir_name(name)?;
+                name.to_string()
+            }
+            None => get_network_dir(cli_args),
+        };
+
         config.validator_dir = validator_dir.unwrap_or_else(|| {
             default_root_dir
-                .join(get_network_dir(cli_args))
+                .join(&network_dir)
                 .join(DEFAULT_VALIDATOR_DIR)
         });
 
-        config.secrets_dir = secrets_dir.unwrap_or_else(|| {
-            default_root_dir
-                .join(get_network_dir(cli_args))
-                .join(DEFAULT_SECRET_DIR)
-        });
+        config.secrets_dir = secrets_dir
+            .unwrap_or_else(|| default_root_dir.join(&network_dir).join(DEFAULT_SECRET_DIR));
+
+        // Running mainnet validators from a leftover default directory, e.g. one previously used
+        // for testing, risks slashing.
+        config.strict_default_dirs = cli_args.is_present("strict-default-dirs");
+        if config.network_name == MAINNET_NETWORK
+            && validator_dir.is_none()
+            && network_dir == config.network_name
+        {
+            if config.strict_default_dirs {
+                return Err(format!(
+                    "Refusing to start on mainnet with the default validators directory {:?} \
+                     because --strict-default-dirs is set. Use --datadir or --validators-dir to \
+                     choose the directory explicitly",
+                    config.validator_dir
+                ));
+            }
+            warn!(
+                log,
+                "Using the default validators directory on mainnet";
+                "msg" => "ensure this directory holds your mainnet validators, or use --datadir \
+                          or --validators-dir to choose it explicitly",
+                "validators_dir" => ?config.validator_dir,
+            );
+        }
+
+        // Check the directories are accessible before using them, since accessing a directory on
+        // an unavailable network mount may hang.
+        if let Some(timeout) = parse_optional::<u64>(cli_args, "dir-access-timeout")? {
+            if timeout == 0 {
+                return Err("dir-access-timeout cannot be 0".to_string());
+            }
+            config.dir_access_timeout = Duration::from_secs(timeout);
+        }
+        for dir in [&config.validator_dir, &config.secrets_dir] {
+            dir_access::check_dir_accessible(dir, config.dir_access_timeout)?;
+        }
 
         if !config.validator_dir.exists() {
             fs::create_dir_all(&config.validator_dir)
@@ -180,6 +696,11 @@ impl Config {
             );
             config.beacon_nodes = vec![SensitiveUrl::parse(&beacon_node)
                 .map_err(|e| format!("Unable to parse beacon node URL: {:?}", e))?];
+            deprecated_flags.push(DeprecatedFlag {
+                flag: "--beacon-node",
+                replacement: Some(("--beacon-nodes", "beacon_nodes")),
+                effective_value: format_urls(&config.beacon_nodes),
+            });
         }
         // To be deprecated.
         else if let Some(server) = parse_optional::<String>(cli_args, "server")? {
@@ -190,6 +711,11 @@ impl Config {
             );
             config.beacon_nodes = vec![SensitiveUrl::parse(&server)
                 .map_err(|e| format!("Unable to parse beacon node URL: {:?}", e))?];
+            deprecated_flags.push(DeprecatedFlag {
+                flag: "--server",
+                replacement: Some(("--beacon-nodes", "beacon_nodes")),
+                effective_value: format_urls(&config.beacon_nodes),
+            });
         }
 
         if let Some(proposer_nodes) = parse_optional::<String>(cli_args, "proposer_nodes")? {
@@ -200,12 +726,36 @@ impl Config {
                 .map_err(|e| format!("Unable to parse proposer node URL: {:?}", e))?;
         }
 
+        config.register_via_proposer_nodes = cli_args.is_present("register-via-proposer-nodes");
+
+        if let Some(attestation_fallback_nodes) =
+            parse_optional::<String>(cli_args, "attestation-fallback-nodes")?
+        {
+            config.attestation_fallback_nodes = attestation_fallback_nodes
+                .split(',')
+                .map(SensitiveUrl::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("Unable to parse attestation fallback node URL: {:?}", e))?;
+
+            if let Some(url) = config
+                .attestation_fallback_nodes
+                .iter()
+                .find(|url| config.beacon_nodes.contains(url))
+            {
+                return Err(format!(
+                    "Attestation fallback node {} is also configured as a beacon node",
+                    url
+                ));
+            }
+        }
+
         if cli_args.is_present("delete-lockfiles") {
             warn!(
                 log,
                 "The --delete-lockfiles flag is deprecated";
                 "msg" => "it is no longer necessary, and no longer has any effect",
             );
+            deprecated_flags.push(DeprecatedFlag::ignored("--delete-lockfiles"));
         }
 
         if cli_args.is_present("allow-unsynced") {
@@ -214,22 +764,186 @@ impl Config {
                 "The --allow-unsynced flag is deprecated";
                 "msg" => "it no longer has any effect",
             );
+            deprecated_flags.push(DeprecatedFlag::ignored("--allow-unsynced"));
+        }
+        if let Some(on_keystore_error) = parse_optional(cli_args, "on-keystore-error")? {
+            config.initialized_validators.on_keystore_error = on_keystore_error;
+        }
+
+        if let Some(max_validators) = parse_optional::<usize>(cli_args, "validators-count-limit")? {
+            if max_validators == 0 {
+                return Err("validators-count-limit cannot be 0".to_string());
+            }
+            config.initialized_validators.max_validators = Some(max_validators);
         }
+
+        config.initialized_validators.secrets_manifest =
+            parse_optional(cli_args, "secrets-manifest")?;
+
+        if let Some(concurrency) =
+            parse_optional::<usize>(cli_args, "keystore-decrypt-concurrency")?
+        {
+            if concurrency == 0 {
+                return Err("keystore-decrypt-concurrency cannot be 0".to_string());
+            }
+            config.initialized_validators.keystore_decrypt_concurrency = Some(concurrency);
+        }
+
+        if let Some(path) = parse_optional::<PathBuf>(cli_args, "validator-load-priority")? {
+            let load_priority = read_load_priority_file(&path)
+                .map_err(|e| format!("Unable to read validator load priority file: {:?}", e))?;
+            info!(
+                log,
+                "Loaded validator load priority file";
+                "path" => ?path,
+                "count" => load_priority.len(),
+            );
+            config.initialized_validators.load_priority = Some(load_priority);
+            config.validator_load_priority = Some(path);
+        }
+
+        if let Some(range) = parse_optional::<String>(cli_args, "validator-index-range")? {
+            let index_range = parse_validator_index_range(&range)?;
+            config.initialized_validators.index_range = Some(index_range);
+            config.validator_index_range = Some(index_range);
+        }
+
+        if let Some(module_path) = parse_optional(cli_args, "pkcs11-module")? {
+            let mechanism = parse_required::<String>(cli_args, "pkcs11-mechanism")?;
+            config.initialized_validators.pkcs11 = Some(Pkcs11Config {
+                module_path,
+                slot: parse_required(cli_args, "pkcs11-slot")?,
+                pin_path: parse_required(cli_args, "pkcs11-pin-file")?,
+                mechanism: parse_pkcs11_mechanism(&mechanism)?,
+            });
+        }
+
+        if let Some(on_fork_version_mismatch) =
+            parse_optional(cli_args, "on-fork-version-mismatch")?
+        {
+            config.on_fork_version_mismatch = on_fork_version_mismatch;
+        }
+
+        config.fork_transition_caution = !cli_args.is_present("disable-fork-transition-caution");
+        if let Some(fork_transition_window) = parse_optional(cli_args, "fork-transition-window")? {
+            config.fork_transition_window = fork_transition_window;
+        }
+
+        config.canary_validator = parse_optional(cli_args, "canary-validator")?;
+
         config.disable_run_on_all = cli_args.is_present("disable-run-on-all");
+        config.lenient_bn_responses = !cli_args.is_present("disable-lenient-bn-responses");
+        config.beacon_node_consensus_check = cli_args.is_present("beacon-node-consensus-check");
+        config.require_matching_genesis = !cli_args.is_present("allow-mismatched-genesis");
+
+        if let Some(beacon_node_selection) = parse_optional(cli_args, "beacon-node-selection")? {
+            config.beacon_node_selection = beacon_node_selection;
+        }
+
+        if let Some(timeout_secs) = parse_optional::<u64>(cli_args, "beacon-node-startup-timeout")?
+        {
+            config.beacon_node_startup_timeout = Some(Duration::from_secs(timeout_secs));
+        }
+        config.detect_config_changes = cli_args.is_present("detect-config-changes");
+        config.require_homogeneous_beacon_nodes =
+            cli_args.is_present("require-homogeneous-beacon-nodes");
+        config.persist_beacon_node_health = cli_args.is_present("persist-beacon-node-health");
+        config.attestation_publish_retry_nodes =
+            !cli_args.is_present("disable-attestation-publish-retry");
         config.disable_auto_discover = cli_args.is_present("disable-auto-discover");
+        config.rebuild_index_cache = cli_args.is_present("rebuild-index-cache");
         config.init_slashing_protection = cli_args.is_present("init-slashing-protection");
+        if let Some(on_existing) = parse_optional(cli_args, "on-existing-slashing-protection")? {
+            config.on_existing_slashing_protection = on_existing;
+        }
+
+        if let Some(retries) = parse_optional(cli_args, "slashing-protection-retries")? {
+            config.slashing_protection_retries = retries;
+        }
+
+        if let Some(delay_ms) =
+            parse_optional::<u64>(cli_args, "slashing-protection-retry-delay-ms")?
+        {
+            config.slashing_protection_retry_delay = Duration::from_millis(delay_ms);
+        }
+
+        if let Some(pool_size) = parse_optional::<u32>(cli_args, "slashing-protection-pool-size")? {
+            if pool_size == 0 {
+                return Err("slashing-protection-pool-size cannot be 0".to_string());
+            }
+            config.slashing_protection_pool_size = Some(pool_size);
+        }
+
+        config.max_concurrent_signings = parse_optional(cli_args, "max-concurrent-signings")?;
+        if config.max_concurrent_signings == Some(0) {
+            return Err("max-concurrent-signings cannot be 0".to_string());
+        }
+
+        if let Some(threshold_ms) = parse_optional::<u64>(cli_args, "slow-sign-threshold-ms")? {
+            if threshold_ms == 0 {
+                return Err("slow-sign-threshold-ms cannot be 0".to_string());
+            }
+            config.slow_sign_threshold = Duration::from_millis(threshold_ms);
+        }
+
+        config.first_proposal_caution_epochs =
+            parse_optional(cli_args, "first-proposal-caution-epochs")?;
+        if config.first_proposal_caution_epochs == Some(0) {
+            return Err("first-proposal-caution-epochs cannot be 0".to_string());
+        }
+
         config.use_long_timeouts = cli_args.is_present("use-long-timeouts");
 
+        config.graffiti_force = cli_args.is_present("graffiti-force");
+        if cli_args.is_present("graffiti-file")
+            && cli_args.is_present("graffiti")
+            && !config.graffiti_force
+        {
+            return Err(
+                "--graffiti-file cannot be used with --graffiti unless --graffiti-force is set"
+                    .to_string(),
+            );
+        }
+
         if let Some(graffiti_file_path) = cli_args.value_of("graffiti-file") {
-            let mut graffiti_file = GraffitiFile::new(graffiti_file_path.into());
+            let on_duplicate: OnDuplicateGraffiti =
+                parse_required(cli_args, "graffiti-file-on-duplicate")?;
+            let mut graffiti_file =
+                GraffitiFile::new(graffiti_file_path.into()).with_on_duplicate(on_duplicate);
             graffiti_file
                 .read_graffiti_file()
                 .map_err(|e| format!("Error reading graffiti file: {:?}", e))?;
+            for pubkey in graffiti_file.duplicates() {
+                warn!(
+                    log,
+                    "Conflicting graffitis in graffiti file";
+                    "msg" => "using the last graffiti listed for the validator",
+                    "pubkey" => ?pubkey,
+                );
+            }
             config.graffiti_file = Some(graffiti_file);
             info!(log, "Successfully loaded graffiti file"; "path" => graffiti_file_path);
         }
 
-        if let Some(input_graffiti) = cli_args.value_of("graffiti") {
+        if let Some(on_overflow) = parse_optional(cli_args, "graffiti-on-overflow")? {
+            config.graffiti_on_overflow = on_overflow;
+        }
+
+        if let Some(mut input_graffiti) = cli_args.value_of("graffiti") {
+            if input_graffiti.len() > GRAFFITI_BYTES_LEN
+                && config.graffiti_on_overflow == OnGraffitiOverflow::Truncate
+            {
+                let truncated = truncate_graffiti(input_graffiti);
+                warn!(
+                    log,
+                    "Truncating graffiti";
+                    "msg" => format!("graffiti is limited to {} bytes", GRAFFITI_BYTES_LEN),
+                    "graffiti" => input_graffiti,
+                    "truncated" => truncated,
+                );
+                input_graffiti = truncated;
+            }
+
             let graffiti_bytes = input_graffiti.as_bytes();
             if graffiti_bytes.len() > GRAFFITI_BYTES_LEN {
                 return Err(format!(
@@ -254,8 +968,34 @@ impl Config {
             config.fee_recipient = Some(input_fee_recipient);
         }
 
+        if let Some(on_missing_fee_recipient) =
+            parse_optional(cli_args, "on-missing-fee-recipient")?
+        {
+            config.on_missing_fee_recipient = on_missing_fee_recipient;
+        }
+
         if let Some(tls_certs) = parse_optional::<String>(cli_args, "beacon-nodes-tls-certs")? {
-            config.beacon_nodes_tls_certs = Some(tls_certs.split(',').map(PathBuf::from).collect());
+            let mut paths = vec![];
+            for path in tls_certs.split(',').map(PathBuf::from) {
+                paths.extend(expand_tls_certs_path(path)?);
+            }
+            config.beacon_nodes_tls_certs = Some(paths);
+        }
+
+        if let Some(min_tls_version) = cli_args.value_of("beacon-nodes-min-tls-version") {
+            parse_tls_version(min_tls_version)?;
+            config.beacon_nodes_min_tls_version = Some(min_tls_version.to_string());
+        }
+
+        if let Some(dns_ttl_secs) = parse_optional::<u64>(cli_args, "beacon-node-dns-ttl")? {
+            config.beacon_node_dns_ttl = Some(Duration::from_secs(dns_ttl_secs));
+        }
+
+        if let Some(expiry_days) =
+            parse_optional::<u64>(cli_args, "beacon-nodes-tls-cert-expiry-days")?
+        {
+            config.beacon_nodes_tls_cert_expiry_window =
+                Duration::from_secs(expiry_days * 24 * 60 * 60);
         }
 
         /*
@@ -306,6 +1046,31 @@ impl Config {
             config.enable_high_validator_count_metrics = true;
         }
 
+        if let Some(bucketing) = parse_optional(cli_args, "metrics-validator-bucketing")? {
+            config.metrics_validator_bucketing = bucketing;
+        }
+
+        config.min_balance_alert_gwei = parse_optional(cli_args, "min-balance-alert-gwei")?;
+        config.auto_disable_inactive = cli_args.is_present("auto-disable-inactive");
+
+        if let Some(interval_secs) = parse_optional::<u64>(cli_args, "index-resolution-interval")? {
+            if interval_secs == 0 {
+                return Err("index-resolution-interval cannot be 0".to_string());
+            }
+            config.index_resolution_interval = Duration::from_secs(interval_secs);
+        }
+
+        if let Some(epochs) = parse_optional(cli_args, "activation-poll-epochs")? {
+            config.activation_poll_epochs = epochs;
+        }
+
+        if let Some(batch_size) = parse_optional(cli_args, "duties-batch-size")? {
+            if batch_size == 0 {
+                return Err("duties-batch-size cannot be 0".to_string());
+            }
+            config.duties_batch_size = batch_size;
+        }
+
         if let Some(address) = cli_args.value_of("metrics-address") {
             config.http_metrics.listen_addr = address
                 .parse::<IpAddr>()
@@ -337,22 +1102,111 @@ impl Config {
         if let Some(monitoring_endpoint) = cli_args.value_of("monitoring-endpoint") {
             let update_period_secs =
                 clap_utils::parse_optional(cli_args, "monitoring-endpoint-period")?;
-            config.monitoring_api = Some(monitoring_api::Config {
+            if let Some(update_period_secs) = update_period_secs {
+                if update_period_secs == 0 {
+                    return Err("monitoring-endpoint-period cannot be 0".to_string());
+                }
+                if update_period_secs < monitoring_api::MIN_RECOMMENDED_UPDATE_DURATION {
+                    warn!(
+                        log,
+                        "Very short monitoring endpoint period";
+                        "msg" => "the remote endpoint may rate limit or reject updates",
+                        "period_secs" => update_period_secs,
+                        "recommended_min_secs" => monitoring_api::MIN_RECOMMENDED_UPDATE_DURATION,
+                    );
+                }
+            }
+
+            let mut monitoring_config = monitoring_api::Config {
                 db_path: None,
                 freezer_db_path: None,
                 update_period_secs,
                 monitoring_endpoint: monitoring_endpoint.to_string(),
-            });
+                ..<_>::default()
+            };
+            if let Some(excluded) = cli_args.values_of("monitoring-endpoint-exclude") {
+                for category in excluded {
+                    match category {
+                        "process" => monitoring_config.process_metrics = false,
+                        "system" => monitoring_config.system_metrics = false,
+                        "validator" => monitoring_config.validator_metrics = false,
+                        other => {
+                            return Err(format!("Unknown monitoring metrics category: {}", other))
+                        }
+                    }
+                }
+            }
+            if let Some(min_validators) =
+                parse_optional(cli_args, "monitoring-endpoint-min-validators")?
+            {
+                monitoring_config.validator_metrics_min_validators = min_validators;
+            }
+            monitoring_config.redact_validator_ids =
+                cli_args.is_present("monitoring-endpoint-redact-validator-ids");
+            config.monitoring_api = Some(monitoring_config);
         }
 
         if cli_args.is_present("enable-doppelganger-protection") {
             config.enable_doppelganger_protection = true;
         }
+        config.continuous_doppelganger = cli_args.is_present("continuous-doppelganger");
+        config.continuous_doppelganger_pause = cli_args.is_present("continuous-doppelganger-pause");
+        config.doppelganger_disable_detected = cli_args.is_present("doppelganger-disable-detected");
+
+        if let Some(warmup_secs) = parse_optional::<u64>(cli_args, "startup-warmup")? {
+            config.startup_warmup = Some(Duration::from_secs(warmup_secs));
+        }
+
+        if let Some(fraction) = parse_optional::<f64>(cli_args, "attestation-deadline-fraction")? {
+            // A fraction of 1 would attest at the start of the next slot, for the wrong slot.
+            if !(0.0..1.0).contains(&fraction) {
+                return Err(format!(
+                    "attestation-deadline-fraction must be at least 0 and less than 1, got {}",
+                    fraction
+                ));
+            }
+            if !RECOMMENDED_ATTESTATION_DEADLINE_FRACTIONS.contains(&fraction) {
+                warn!(
+                    log,
+                    "Attestation deadline is outside of the recommended range";
+                    "msg" => "attestations may be made before the block arrives or be included late",
+                    "attestation_deadline_fraction" => fraction,
+                    "recommended_min" => RECOMMENDED_ATTESTATION_DEADLINE_FRACTIONS.start(),
+                    "recommended_max" => RECOMMENDED_ATTESTATION_DEADLINE_FRACTIONS.end(),
+                );
+            }
+            config.attestation_deadline_fraction = fraction;
+        }
+
+        if let Some(max_lag) = parse_optional(cli_args, "max-attestation-slot-lag")? {
+            config.max_attestation_slot_lag = max_lag;
+        }
+
+        if let Some(max_pending) = parse_optional::<usize>(cli_args, "max-pending-aggregations")? {
+            if max_pending == 0 {
+                return Err("max-pending-aggregations cannot be 0".to_string());
+            }
+            config.max_pending_aggregations = max_pending;
+        }
 
         if cli_args.is_present("builder-proposals") {
             config.builder_proposals = true;
         }
 
+        if let Some(enabled_file_path) = cli_args.value_of("builder-proposals-enabled-file") {
+            let mut enabled_file = PubkeyListFile::new(enabled_file_path.into());
+            enabled_file
+                .read_pubkey_list_file()
+                .map_err(|e| format!("Error reading builder proposals enabled file: {:?}", e))?;
+            config.builder_proposals_enabled_pubkeys = Some(enabled_file);
+        }
+
+        if let Some(builder_unavailable_action) =
+            parse_optional(cli_args, "builder-unavailable-action")?
+        {
+            config.builder_unavailable_action = builder_unavailable_action;
+        }
+
         config.gas_limit = cli_args
             .value_of("gas-limit")
             .map(|gas_limit| {
@@ -362,6 +1216,41 @@ impl Config {
             })
             .transpose()?;
 
+        if let Some(gas_limit_min) = parse_optional(cli_args, "gas-limit-min")? {
+            config.gas_limit_min = gas_limit_min;
+        }
+        if let Some(gas_limit_max) = parse_optional(cli_args, "gas-limit-max")? {
+            config.gas_limit_max = gas_limit_max;
+        }
+        if config.gas_limit_min > config.gas_limit_max {
+            return Err(format!(
+                "gas-limit-min ({}) cannot be greater than gas-limit-max ({})",
+                config.gas_limit_min, config.gas_limit_max
+            ));
+        }
+        if let Some(on_out_of_range) = parse_optional(cli_args, "on-gas-limit-out-of-range")? {
+            config.on_gas_limit_out_of_range = on_out_of_range;
+        }
+        if let Some(gas_limit) = config.gas_limit {
+            let bounds = config.gas_limit_bounds();
+            if !bounds.contains(gas_limit) {
+                if bounds.on_out_of_range == OnGasLimitOutOfRange::Reject {
+                    return Err(format!(
+                        "gas-limit ({}) is outside of gas-limit-min ({}) and gas-limit-max ({})",
+                        gas_limit, bounds.min, bounds.max
+                    ));
+                }
+                warn!(
+                    log,
+                    "Gas limit out of range";
+                    "msg" => "the gas limit will be clamped when registering validators",
+                    "gas_limit" => gas_limit,
+                    "gas_limit_min" => bounds.min,
+                    "gas_limit_max" => bounds.max,
+                );
+            }
+        }
+
         if let Some(registration_timestamp_override) =
             cli_args.value_of("builder-registration-timestamp-override")
         {
@@ -372,23 +1261,140 @@ impl Config {
             );
         }
 
+        if let Some(timestamp_file_path) =
+            cli_args.value_of("builder-registration-timestamp-override-file")
+        {
+            let mut timestamp_file = RegistrationTimestampFile::new(timestamp_file_path.into());
+            timestamp_file.read_timestamp_file().map_err(|e| {
+                format!(
+                    "Error reading builder registration timestamp override file: {:?}",
+                    e
+                )
+            })?;
+            warn!(
+                log,
+                "Overriding builder registration timestamps";
+                "msg" => "this is unusual and should only be used to debug relay compatibility",
+                "path" => timestamp_file_path,
+                "count" => timestamp_file.len(),
+            );
+            config.builder_registration_timestamp_override_file = Some(timestamp_file);
+        }
+
+        if let Some(relay_tag_file_path) = cli_args.value_of("relay-tag-file") {
+            let mut relay_tag_file = RelayTagFile::new(relay_tag_file_path.into());
+            relay_tag_file
+                .read_relay_tag_file()
+                .map_err(|e| format!("Error reading relay tag file: {:?}", e))?;
+            info!(
+                log,
+                "Loaded relay tags";
+                "path" => relay_tag_file_path,
+                "count" => relay_tag_file.len(),
+            );
+            config.relay_tag_file = Some(relay_tag_file);
+        }
+
+        if let Some(opt_out_file_path) = cli_args.value_of("sync-committee-opt-out-file") {
+            let mut opt_out_file = PubkeyListFile::new(opt_out_file_path.into());
+            opt_out_file
+                .read_pubkey_list_file()
+                .map_err(|e| format!("Error reading sync committee opt-out file: {:?}", e))?;
+            config.sync_committee_opt_out_file = Some(opt_out_file);
+        }
+
+        config.sync_committee_broadcast = cli_args.is_present("sync-committee-broadcast");
+
         if cli_args.is_present("strict-fee-recipient") {
             warn!(
                 log,
                 "The flag `--strict-fee-recipient` has been deprecated due to a bug causing \
                 missed proposals. The flag will be ignored."
             );
+            deprecated_flags.push(DeprecatedFlag::ignored("--strict-fee-recipient"));
         }
 
         config.enable_latency_measurement_service =
             parse_optional(cli_args, "latency-measurement-service")?.unwrap_or(true);
 
+        if let Some(interval_secs) = parse_optional::<u64>(cli_args, "latency-sample-interval")? {
+            if interval_secs == 0 {
+                return Err("latency-sample-interval cannot be 0".to_string());
+            }
+            config.latency_sample_interval = Duration::from_secs(interval_secs);
+        }
+
+        if let Some(window) = parse_optional::<usize>(cli_args, "latency-sample-window")? {
+            if window == 0 {
+                return Err("latency-sample-window cannot be 0".to_string());
+            }
+            config.latency_sample_window = window;
+        }
+
+        if let Some(min_free_disk_bytes) = parse_optional(cli_args, "min-free-disk-bytes")? {
+            config.min_free_disk_bytes = min_free_disk_bytes;
+        }
+
+        config.slashing_protection_prune_interval =
+            parse_optional(cli_args, "slashing-protection-prune-interval")?;
+        if config.slashing_protection_prune_interval == Some(0) {
+            return Err("slashing-protection-prune-interval cannot be 0".to_string());
+        }
+
+        if let Some(retention_epochs) =
+            parse_optional::<u64>(cli_args, "slashing-protection-retention-epochs")?
+        {
+            // Records within this many epochs may still conflict with new messages, e.g. after
+            // clock drift, so they are never pruned.
+            if retention_epochs < SLASHING_PROTECTION_HISTORY_EPOCHS {
+                return Err(format!(
+                    "slashing-protection-retention-epochs cannot be less than {}",
+                    SLASHING_PROTECTION_HISTORY_EPOCHS
+                ));
+            }
+            config.slashing_protection_retention_epochs = retention_epochs;
+        }
+
+        config.strict_dir_permissions = cli_args.is_present("strict-dir-permissions");
+        config.require_synced_clock = cli_args.is_present("require-synced-clock");
+
+        config.print_duties = cli_args.is_present("print-duties");
+        config.exit_after_print_duties = cli_args.is_present("exit-after-print-duties");
+
         config.validator_registration_batch_size =
             parse_required(cli_args, "validator-registration-batch-size")?;
         if config.validator_registration_batch_size == 0 {
             return Err("validator-registration-batch-size cannot be 0".to_string());
         }
 
+        config.validator_registration_max_body_bytes =
+            parse_optional(cli_args, "validator-registration-max-body-bytes")?;
+        if config.validator_registration_max_body_bytes == Some(0) {
+            return Err("validator-registration-max-body-bytes cannot be 0".to_string());
+        }
+
+        if let Some(refresh_interval_secs) =
+            parse_optional::<u64>(cli_args, "validator-registration-refresh-interval")?
+        {
+            let refresh_interval = Duration::from_secs(refresh_interval_secs);
+            if refresh_interval < MIN_VALIDATOR_REGISTRATION_REFRESH_INTERVAL {
+                return Err(format!(
+                    "validator-registration-refresh-interval cannot be less than {} seconds",
+                    MIN_VALIDATOR_REGISTRATION_REFRESH_INTERVAL.as_secs()
+                ));
+            }
+            config.validator_registration_refresh_interval = Some(refresh_interval);
+        }
+
+        config.reregister_on_bn_restart = cli_args.is_present("reregister-on-bn-restart");
+
+        if let Some(lead_ms) = parse_optional::<u64>(cli_args, "block-prefetch-lead-ms")? {
+            if lead_ms == 0 {
+                return Err("block-prefetch-lead-ms cannot be 0".to_string());
+            }
+            config.block_prefetch_lead = Some(Duration::from_millis(lead_ms));
+        }
+
         /*
          * Experimental
          */
@@ -396,17 +1402,550 @@ impl Config {
             config.block_delay = Some(Duration::from_millis(delay_ms));
         }
 
+        if let Some(fraction) = parse_optional::<f64>(cli_args, "block-delay-slot-fraction")? {
+            if !(0.0..1.0).contains(&fraction) {
+                return Err(
+                    "block-delay-slot-fraction must be at least 0 and less than 1".to_string(),
+                );
+            }
+            config.block_delay_slot_fraction = Some(fraction);
+        }
+
+        config.block_delay_attestation_deadline_offset_ms =
+            parse_optional(cli_args, "block-delay-attestation-deadline-offset-ms")?;
+
+        config.block_max_transactions = parse_optional(cli_args, "block-max-transactions")?;
+
+        config.genesis_time_override = parse_optional(cli_args, "genesis-time-override")?;
+
+        if let Some(seconds_per_slot) =
+            parse_optional::<u64>(cli_args, "seconds-per-slot-override")?
+        {
+            if seconds_per_slot == 0 {
+                return Err("seconds-per-slot-override cannot be 0".to_string());
+            }
+            config.seconds_per_slot_override = Some(seconds_per_slot);
+        }
+
+        config.allow_large_block_delay = cli_args.is_present("allow-large-block-delay");
+        if config.allow_large_block_delay
+            && config.block_delay.is_none()
+            && config.block_delay_slot_fraction.is_none()
+            && config.block_delay_attestation_deadline_offset_ms.is_none()
+        {
+            return Err("allow-large-block-delay requires a block delay to be set".to_string());
+        }
+        config.verify_block_before_signing = !cli_args.is_present("disable-block-verification");
+        config.verify_fee_recipient = !cli_args.is_present("disable-fee-recipient-verification");
+        config.refuse_proposal_on_optimistic =
+            !cli_args.is_present("allow-proposal-on-optimistic-head");
+        config.refuse_attestation_on_optimistic =
+            cli_args.is_present("refuse-attestation-on-optimistic-head");
+        // The network's slot duration is not known until the validator client starts, at which
+        // point the check is repeated.
+        if let Some(seconds_per_slot) = config.seconds_per_slot_override {
+            config.check_block_delay(seconds_per_slot)?;
+            config.check_block_prefetch_lead(seconds_per_slot)?;
+        }
+
+        if let Some(genesis_validators_root) =
+            parse_optional::<Hash256>(cli_args, "genesis-validators-root-override")?
+        {
+            if genesis_validators_root.is_zero() {
+                return Err("genesis-validators-root-override cannot be zero".to_string());
+            }
+            config.genesis_validators_root_override = Some(genesis_validators_root);
+        }
+
+        if let Some(path) = parse_optional::<PathBuf>(cli_args, "fork-schedule-override")? {
+            config.fork_schedule_override = Some(load_fork_schedule_override(path)?);
+        }
+
+        config.low_memory_mode = cli_args.is_present("low-memory-mode");
+        if config.low_memory_mode {
+            config.apply_low_memory_profile(|flag| cli_args.occurrences_of(flag) > 0);
+        }
+
+        if cli_args.is_present("log-deprecations") {
+            log_deprecated_flags(&deprecated_flags, log);
+        }
+
         Ok(config)
     }
+
+    /// Returns the number of connections to open to the slashing protection database.
+    ///
+    /// A larger pool gives up locking the database against other processes and does not sign any
+    /// faster, so it is only used if configured.
+    pub fn slashing_protection_pool_size(&self) -> u32 {
+        self.slashing_protection_pool_size.unwrap_or(POOL_SIZE)
+    }
+
+    /// Applies the reduced sizes of `low_memory_mode`, except to the settings whose flag is
+    /// `overridden`.
+    fn apply_low_memory_profile(&mut self, overridden: impl Fn(&str) -> bool) {
+        if !overridden("slashing-protection-pool-size") {
+            self.slashing_protection_pool_size = Some(LOW_MEMORY_SLASHING_PROTECTION_POOL_SIZE);
+        }
+        if !overridden("max-concurrent-signings") {
+            self.max_concurrent_signings = Some(LOW_MEMORY_MAX_CONCURRENT_SIGNINGS);
+        }
+        if !overridden("max-pending-aggregations") {
+            self.max_pending_aggregations = LOW_MEMORY_MAX_PENDING_AGGREGATIONS;
+        }
+        if !overridden("latency-sample-window") {
+            self.latency_sample_window = LOW_MEMORY_LATENCY_SAMPLE_WINDOW;
+        }
+        if !overridden("validator-registration-batch-size") {
+            self.validator_registration_batch_size = LOW_MEMORY_VALIDATOR_REGISTRATION_BATCH_SIZE;
+        }
+    }
+
+    /// Returns the bounds applied to the gas limits of validator registrations.
+    pub fn gas_limit_bounds(&self) -> GasLimitBounds {
+        GasLimitBounds {
+            min: self.gas_limit_min,
+            max: self.gas_limit_max,
+            on_out_of_range: self.on_gas_limit_out_of_range,
+        }
+    }
+
+    /// Logs a summary of the safety-relevant settings, so that an unexpected configuration is
+    /// noticed at startup.
+    ///
+    /// Beacon node URLs may contain credentials, so only their number is logged.
+    pub fn log_safety_banner(&self, num_validators: usize, log: &Logger) {
+        let doppelganger_detection_epochs = self
+            .enable_doppelganger_protection
+            .then_some(DEFAULT_REMAINING_DETECTION_EPOCHS);
+        info!(
+            log,
+            "Safety settings";
+            "network" => &self.network_name,
+            "doppelganger_protection" => self.enable_doppelganger_protection,
+            "doppelganger_detection_epochs" => ?doppelganger_detection_epochs,
+            "slashing_protection_db" => ?self.validator_dir.join(SLASHING_PROTECTION_FILENAME),
+            "validators" => num_validators,
+            "beacon_nodes" => self.beacon_nodes.len(),
+            "proposer_nodes" => self.proposer_nodes.len(),
+        );
+    }
+
+    /// Returns the delay from the start of a slot of `seconds_per_slot` to wait before publishing
+    /// a block, however it is configured.
+    ///
+    /// Returns an error if the delay is not shorter than the slot, or if it is longer than
+    /// `1 / MAX_BLOCK_DELAY_SLOT_FRACTION` of the slot unless `allow_large_block_delay` is set.
+    pub fn effective_block_delay(&self, seconds_per_slot: u64) -> Result<Option<Duration>, String> {
+        let slot_duration = Duration::from_secs(seconds_per_slot);
+        let block_delay = if let Some(fraction) = self.block_delay_slot_fraction {
+            slot_duration.mul_f64(fraction)
+        } else if let Some(offset_ms) = self.block_delay_attestation_deadline_offset_ms {
+            let deadline = slot_duration.mul_f64(self.attestation_deadline_fraction);
+            let offset = Duration::from_millis(offset_ms.unsigned_abs());
+            if offset_ms >= 0 {
+                deadline + offset
+            } else {
+                deadline.checked_sub(offset).ok_or_else(|| {
+                    format!(
+                        "block-delay-attestation-deadline-offset-ms of {}ms is before the start \
+                         of the slot, the attestation deadline is {}ms into a {}s slot",
+                        offset_ms,
+                        deadline.as_millis(),
+                        seconds_per_slot
+                    )
+                })?
+            }
+        } else if let Some(block_delay) = self.block_delay {
+            block_delay
+        } else {
+            return Ok(None);
+        };
+
+        if block_delay >= slot_duration {
+            return Err(format!(
+                "Block delay of {}ms is not shorter than the {}s slot",
+                block_delay.as_millis(),
+                seconds_per_slot
+            ));
+        }
+
+        let max_block_delay = slot_duration / MAX_BLOCK_DELAY_SLOT_FRACTION;
+        if block_delay > max_block_delay && !self.allow_large_block_delay {
+            return Err(format!(
+                "Block delay of {}ms exceeds the maximum of {}ms for a {}s slot. Use \
+                 --allow-large-block-delay to permit it",
+                block_delay.as_millis(),
+                max_block_delay.as_millis(),
+                seconds_per_slot
+            ));
+        }
+
+        Ok(Some(block_delay))
+    }
+
+    /// Returns an error if the block delay is invalid for a slot of `seconds_per_slot`, see
+    /// `Self::effective_block_delay`.
+    pub fn check_block_delay(&self, seconds_per_slot: u64) -> Result<(), String> {
+        self.effective_block_delay(seconds_per_slot).map(|_| ())
+    }
+
+    /// Returns an error if the `block_prefetch_lead` is longer than
+    /// `1 / MAX_BLOCK_PREFETCH_SLOT_FRACTION` of a slot of `seconds_per_slot`.
+    pub fn check_block_prefetch_lead(&self, seconds_per_slot: u64) -> Result<(), String> {
+        let max_lead = Duration::from_secs(seconds_per_slot) / MAX_BLOCK_PREFETCH_SLOT_FRACTION;
+        match self.block_prefetch_lead {
+            Some(lead) if lead > max_lead => Err(format!(
+                "Block prefetch lead of {}ms exceeds the maximum of {}ms for a {}s slot",
+                lead.as_millis(),
+                max_lead.as_millis(),
+                seconds_per_slot
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if the `block_max_transactions` exceeds the number of transactions
+    /// permitted in an execution payload of `E`.
+    pub fn check_block_max_transactions<E: EthSpec>(&self) -> Result<(), String> {
+        let protocol_max = E::max_transactions_per_payload() as u64;
+        match self.block_max_transactions {
+            Some(max_transactions) if max_transactions > protocol_max => Err(format!(
+                "Block max transactions of {} exceeds the protocol maximum of {}",
+                max_transactions, protocol_max
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A deprecated flag which was supplied on the command line.
+struct DeprecatedFlag {
+    flag: &'static str,
+    /// The flag which replaces `flag`, and the `Config` field which they both set.
+    ///
+    /// `None` if the flag no longer has any effect.
+    replacement: Option<(&'static str, &'static str)>,
+    /// The value of the `Config` field once all flags have been applied.
+    effective_value: String,
+}
+
+impl DeprecatedFlag {
+    fn ignored(flag: &'static str) -> Self {
+        Self {
+            flag,
+            replacement: None,
+            effective_value: "ignored".to_string(),
+        }
+    }
+}
+
+/// Logs a single report mapping each of the `deprecated_flags` to its replacement, so that
+/// operators can migrate all of them at once.
+fn log_deprecated_flags(deprecated_flags: &[DeprecatedFlag], log: &Logger) {
+    if deprecated_flags.is_empty() {
+        info!(log, "No deprecated flags in use");
+        return;
+    }
+
+    warn!(
+        log,
+        "Deprecated flags in use";
+        "msg" => "replace each of the following flags, which may be removed in a future release",
+        "count" => deprecated_flags.len(),
+    );
+    for deprecated_flag in deprecated_flags {
+        let (replacement, config_field) = deprecated_flag.replacement.unwrap_or(("none", "none"));
+        warn!(
+            log,
+            "Deprecated flag";
+            "flag" => deprecated_flag.flag,
+            "replacement" => replacement,
+            "config_field" => config_field,
+            "effective_value" => &deprecated_flag.effective_value,
+        );
+    }
+}
+
+/// Formats `urls` as a comma-separated list, with any secrets redacted.
+fn format_urls(urls: &[SensitiveUrl]) -> String {
+    urls.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses a PKCS#11 mechanism type from a decimal or 0x-prefixed hexadecimal integer.
+fn parse_pkcs11_mechanism(mechanism: &str) -> Result<u64, String> {
+    let result = match mechanism.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => mechanism.parse(),
+    };
+    result.map_err(|e| format!("Invalid pkcs11-mechanism {}: {:?}", mechanism, e))
+}
+
+/// Returns the longest prefix of `graffiti` which fits in `GRAFFITI_BYTES_LEN` bytes without
+/// splitting a UTF-8 character.
+fn truncate_graffiti(graffiti: &str) -> &str {
+    let mut len = graffiti.len().min(GRAFFITI_BYTES_LEN);
+    while !graffiti.is_char_boundary(len) {
+        len -= 1;
+    }
+    &graffiti[..len]
+}
+
+/// Checks that `name` is a single, normal path component, so that it can't be used to place the
+/// default directories outside of the root directory.
+fn validate_network_dir_name(name: &str) -> Result<(), String> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(component)), None) if component == name => Ok(()),
+        _ => Err(format!(
+            "Invalid network-dir-name {:?}, it must be a single directory name",
+            name
+        )),
+    }
+}
+
+/// Expands a path given to `--beacon-nodes-tls-certs` into certificate files.
+///
+/// A directory is replaced by the `.pem` and `.crt` files it contains, in lexicographic order,
+/// each of which must be a valid PEM certificate. Any other path is returned unchanged and read
+/// when the beacon node clients are built.
+fn expand_tls_certs_path(path: PathBuf) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path]);
+    }
+
+    let mut certs = fs::read_dir(&path)
+        .map_err(|e| format!("Unable to read certificate directory {:?}: {}", path, e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Unable to read certificate directory {:?}: {}", path, e))?;
+    certs.retain(|cert| {
+        cert.is_file()
+            && cert.extension().map_or(false, |ext| {
+                ext.eq_ignore_ascii_case("pem") || ext.eq_ignore_ascii_case("crt")
+            })
+    });
+    certs.sort();
+
+    if certs.is_empty() {
+        return Err(format!(
+            "Certificate directory {:?} contains no .pem or .crt files",
+            path
+        ));
+    }
+    for cert in &certs {
+        load_pem_certificate(cert).map_err(|e| format!("{}: {:?}", e, cert))?;
+    }
+    Ok(certs)
+}
+
+/// Parse a `--validator-index-range` of the form `START..END` into the half-open range
+/// `[START, END)`.
+fn parse_validator_index_range(range: &str) -> Result<(u64, u64), String> {
+    let (start, end) = range
+        .split_once("..")
+        .ok_or_else(|| format!("validator-index-range must be START..END, not {}", range))?;
+    let parse = |index: &str| {
+        index
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid validator-index-range {}: {:?}", range, e))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start >= end {
+        return Err(format!(
+            "validator-index-range {} is empty, END must be greater than START",
+            range
+        ));
+    }
+    Ok((start, end))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use types::MainnetEthSpec;
 
     #[test]
     // Ensures the default config does not panic.
     fn default_config() {
         Config::default();
     }
+
+    #[test]
+    fn default_slashing_protection_pool_size() {
+        let mut config = Config::default();
+        assert_eq!(config.slashing_protection_pool_size(), POOL_SIZE);
+
+        config.slashing_protection_pool_size = Some(8);
+        assert_eq!(config.slashing_protection_pool_size(), 8);
+    }
+
+    #[test]
+    fn block_delay_limit() {
+        let mut config = Config::default();
+        assert!(config.check_block_delay(12).is_ok());
+
+        config.block_delay = Some(Duration::from_secs(4));
+        assert!(config.check_block_delay(12).is_ok());
+
+        config.block_delay = Some(Duration::from_millis(4_001));
+        assert!(config.check_block_delay(12).is_err());
+        assert!(config.check_block_delay(30).is_ok());
+
+        config.allow_large_block_delay = true;
+        assert!(config.check_block_delay(12).is_ok());
+
+        // A delay of a whole slot is never permitted.
+        config.block_delay = Some(Duration::from_secs(12));
+        assert!(config.check_block_delay(12).is_err());
+    }
+
+    #[test]
+    fn low_memory_profile() {
+        let mut config = Config::default();
+        config.apply_low_memory_profile(|_| false);
+        assert_eq!(
+            config.slashing_protection_pool_size(),
+            LOW_MEMORY_SLASHING_PROTECTION_POOL_SIZE
+        );
+        assert_eq!(
+            config.max_concurrent_signings,
+            Some(LOW_MEMORY_MAX_CONCURRENT_SIGNINGS)
+        );
+        assert_eq!(
+            config.max_pending_aggregations,
+            LOW_MEMORY_MAX_PENDING_AGGREGATIONS
+        );
+        assert_eq!(
+            config.latency_sample_window,
+            LOW_MEMORY_LATENCY_SAMPLE_WINDOW
+        );
+        assert_eq!(
+            config.validator_registration_batch_size,
+            LOW_MEMORY_VALIDATOR_REGISTRATION_BATCH_SIZE
+        );
+
+        // Settings given individually are kept.
+        let mut config = Config {
+            max_pending_aggregations: 512,
+            ..Config::default()
+        };
+        config.apply_low_memory_profile(|flag| flag == "max-pending-aggregations");
+        assert_eq!(config.max_pending_aggregations, 512);
+        assert_eq!(
+            config.latency_sample_window,
+            LOW_MEMORY_LATENCY_SAMPLE_WINDOW
+        );
+    }
+
+    #[test]
+    fn block_prefetch_lead_limit() {
+        let mut config = Config::default();
+        assert!(config.check_block_prefetch_lead(12).is_ok());
+
+        config.block_prefetch_lead = Some(Duration::from_secs(4));
+        assert!(config.check_block_prefetch_lead(12).is_ok());
+
+        config.block_prefetch_lead = Some(Duration::from_millis(4_001));
+        assert!(config.check_block_prefetch_lead(12).is_err());
+        assert!(config.check_block_prefetch_lead(30).is_ok());
+    }
+
+    #[test]
+    fn block_max_transactions_limit() {
+        let mut config = Config::default();
+        assert!(config
+            .check_block_max_transactions::<MainnetEthSpec>()
+            .is_ok());
+
+        config.block_max_transactions = Some(0);
+        assert!(config
+            .check_block_max_transactions::<MainnetEthSpec>()
+            .is_ok());
+
+        config.block_max_transactions = Some(1_048_576);
+        assert!(config
+            .check_block_max_transactions::<MainnetEthSpec>()
+            .is_ok());
+
+        config.block_max_transactions = Some(1_048_577);
+        assert!(config
+            .check_block_max_transactions::<MainnetEthSpec>()
+            .is_err());
+    }
+
+    #[test]
+    fn relative_block_delay() {
+        let mut config = Config {
+            block_delay_slot_fraction: Some(0.25),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.effective_block_delay(12),
+            Ok(Some(Duration::from_secs(3)))
+        );
+        config.block_delay_slot_fraction = Some(0.5);
+        assert!(config.effective_block_delay(12).is_err());
+
+        config.block_delay_slot_fraction = None;
+        config.block_delay_attestation_deadline_offset_ms = Some(-1_000);
+        assert_eq!(
+            config.effective_block_delay(12),
+            Ok(Some(Duration::from_secs(3)))
+        );
+        config.block_delay_attestation_deadline_offset_ms = Some(-5_000);
+        assert!(config.effective_block_delay(12).is_err());
+        config.block_delay_attestation_deadline_offset_ms = Some(1_000);
+        assert!(config.effective_block_delay(12).is_err());
+
+        config.allow_large_block_delay = true;
+        assert_eq!(
+            config.effective_block_delay(12),
+            Ok(Some(Duration::from_secs(5)))
+        );
+        config.block_delay_attestation_deadline_offset_ms = Some(8_000);
+        assert!(config.effective_block_delay(12).is_err());
+    }
+
+    #[test]
+    fn validator_index_range() {
+        assert_eq!(parse_validator_index_range("0..100"), Ok((0, 100)));
+        assert_eq!(parse_validator_index_range("5 .. 6"), Ok((5, 6)));
+        assert!(parse_validator_index_range("100..100").is_err());
+        assert!(parse_validator_index_range("100..0").is_err());
+        assert!(parse_validator_index_range("0-100").is_err());
+        assert!(parse_validator_index_range("0..").is_err());
+    }
+
+    #[test]
+    fn pkcs11_mechanism() {
+        assert_eq!(parse_pkcs11_mechanism("2147483649"), Ok(0x8000_0001));
+        assert_eq!(parse_pkcs11_mechanism("0x80000001"), Ok(0x8000_0001));
+        assert!(parse_pkcs11_mechanism("0x").is_err());
+        assert!(parse_pkcs11_mechanism("bls").is_err());
+    }
+
+    #[test]
+    fn graffiti_truncation() {
+        assert_eq!(truncate_graffiti("short"), "short");
+        let ascii = "a".repeat(40);
+        assert_eq!(truncate_graffiti(&ascii), &ascii[..GRAFFITI_BYTES_LEN]);
+        // A 2-byte character straddling the limit is dropped entirely.
+        let multibyte = format!("{}é", "a".repeat(31));
+        assert_eq!(truncate_graffiti(&multibyte), &multibyte[..31]);
+    }
+
+    #[test]
+    fn network_dir_name() {
+        for valid in ["mainnet", "my-testnet", "gnosis_2"] {
+            assert!(validate_network_dir_name(valid).is_ok(), "{}", valid);
+        }
+        for invalid in ["", ".", "..", "a/b", "/mainnet", "mainnet/", "../mainnet"] {
+            assert!(validate_network_dir_name(invalid).is_err(), "{}", invalid);
+        }
+    }
 }
@@ -0,0 +1,196 @@
+//! Checks that the directories holding keystores and their passwords remain accessible.
+//!
+//! Reading a directory on a network mount (e.g. NFS or CIFS) which has become unavailable can
+//! block indefinitely, so each check reads the directory on a separate thread and gives up after
+//! `dir_access_timeout`. The validator client refuses to start if a directory is inaccessible, and
+//! logs an error whilst one is inaccessible once running.
+
+use crate::http_metrics::metrics;
+use environment::RuntimeContext;
+use slog::{error, info};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use tokio::time::sleep;
+use types::EthSpec;
+
+/// How often the directories are checked whilst running.
+pub const DIR_ACCESS_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Lists an entry of `path`, treating a missing directory as accessible since it will be created.
+fn read_dir(path: &Path) -> io::Result<()> {
+    match std::fs::read_dir(path) {
+        Ok(mut entries) => entries.next().transpose().map(|_| ()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// A directory which is checked periodically.
+struct DirProbe {
+    name: &'static str,
+    path: PathBuf,
+    /// Set whilst a check of the directory has not returned, so that a hung mount does not
+    /// accumulate a blocked thread for every check.
+    in_flight: Arc<AtomicBool>,
+}
+
+impl DirProbe {
+    fn new(name: &'static str, path: PathBuf) -> Self {
+        Self {
+            name,
+            path,
+            in_flight: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns an error if the directory cannot be read within `timeout`.
+    fn check(&self, timeout: Duration) -> Result<(), String> {
+        if self.in_flight.swap(true, Ordering::SeqCst) {
+            return Err(format!(
+                "The previous access of {:?} has not returned, it may be on an unavailable \
+                 network mount",
+                self.path
+            ));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let path = self.path.clone();
+        let in_flight = self.in_flight.clone();
+        std::thread::Builder::new()
+            .name("dir_access".into())
+            .spawn(move || {
+                let result = read_dir(&path);
+                in_flight.store(false, Ordering::SeqCst);
+                let _ = sender.send(result);
+            })
+            .map_err(|e| {
+                self.in_flight.store(false, Ordering::SeqCst);
+                format!("Unable to spawn thread to access {:?}: {:?}", self.path, e)
+            })?;
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(format!("Unable to access {:?}: {}", self.path, e)),
+            Err(_) => Err(format!(
+                "Accessing {:?} took longer than {:?}, it may be on an unavailable network mount",
+                self.path, timeout
+            )),
+        }
+    }
+}
+
+/// Returns an error if the directory at `path` cannot be read within `timeout`.
+pub fn check_dir_accessible(path: &Path, timeout: Duration) -> Result<(), String> {
+    DirProbe::new("dir", path.to_path_buf()).check(timeout)
+}
+
+/// Starts a service which periodically checks that `validator_dir` and `secrets_dir` can be read
+/// within `timeout`, logging an error whilst either cannot.
+pub fn start_dir_access_service<E: EthSpec>(
+    context: RuntimeContext<E>,
+    validator_dir: PathBuf,
+    secrets_dir: PathBuf,
+    timeout: Duration,
+) {
+    let log = context.log().clone();
+    let probes = Arc::new(vec![
+        DirProbe::new("validators", validator_dir),
+        DirProbe::new("secrets", secrets_dir),
+    ]);
+
+    let future = async move {
+        let mut inaccessible = vec![false; probes.len()];
+        loop {
+            sleep(DIR_ACCESS_CHECK_INTERVAL).await;
+
+            let thread_probes = probes.clone();
+            let results = match tokio::task::spawn_blocking(move || {
+                thread_probes
+                    .iter()
+                    .map(|probe| probe.check(timeout))
+                    .collect::<Vec<_>>()
+            })
+            .await
+            {
+                Ok(results) => results,
+                Err(e) => {
+                    error!(log, "Unable to check directory access"; "error" => ?e);
+                    continue;
+                }
+            };
+
+            for ((probe, result), was_inaccessible) in
+                probes.iter().zip(results).zip(inaccessible.iter_mut())
+            {
+                metrics::set_gauge_vec(
+                    &metrics::DIR_ACCESSIBLE,
+                    &[probe.name],
+                    result.is_ok() as i64,
+                );
+                match result {
+                    Ok(()) if *was_inaccessible => {
+                        info!(
+                            log,
+                            "Directory accessible again";
+                            "dir" => probe.name,
+                            "path" => ?probe.path,
+                        );
+                        *was_inaccessible = false;
+                    }
+                    Ok(()) => (),
+                    Err(e) => {
+                        error!(
+                            log,
+                            "Directory inaccessible";
+                            "msg" => "keystores and passwords cannot be loaded from this \
+                                      directory, check that its mount is available",
+                            "error" => e,
+                            "dir" => probe.name,
+                        );
+                        *was_inaccessible = true;
+                    }
+                }
+            }
+        }
+    };
+
+    context.executor.spawn(future, "dir_access");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn accessible_dirs() {
+        let dir = TempDir::new().unwrap();
+        let timeout = Duration::from_secs(5);
+
+        assert_eq!(check_dir_accessible(dir.path(), timeout), Ok(()));
+        assert_eq!(
+            check_dir_accessible(&dir.path().join("missing"), timeout),
+            Ok(())
+        );
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, "not a directory").unwrap();
+        assert!(check_dir_accessible(&file, timeout).is_err());
+    }
+
+    #[test]
+    fn hung_check_is_not_repeated() {
+        let dir = TempDir::new().unwrap();
+        let probe = DirProbe::new("validators", dir.path().to_path_buf());
+
+        probe.in_flight.store(true, Ordering::SeqCst);
+        assert!(probe.check(Duration::from_secs(5)).is_err());
+
+        probe.in_flight.store(false, Ordering::SeqCst);
+        assert_eq!(probe.check(Duration::from_secs(5)), Ok(()));
+        assert!(!probe.in_flight.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,91 @@
+//! Checks of the permissions of the directories holding keystores, their passwords and the
+//! slashing protection database.
+//!
+//! Directories which other users can access are reported with a warning, or refused with
+//! `strict_dir_permissions`. The check is skipped on platforms without Unix permissions.
+
+use slog::{warn, Logger};
+use std::path::Path;
+
+/// The permission bits which grant access to users other than the owner.
+#[cfg(unix)]
+const INSECURE_MODE_BITS: u32 = 0o077;
+
+/// Returns the permission bits of `path`, if it grants access to users other than its owner.
+///
+/// Always returns `None` on platforms without Unix permissions, or if `path` does not exist.
+pub fn insecure_mode(path: &Path) -> Result<Option<u32>, String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mode = std::fs::metadata(path)
+            .map_err(|e| format!("Unable to read permissions of {:?}: {:?}", path, e))?
+            .permissions()
+            .mode();
+        Ok((mode & INSECURE_MODE_BITS != 0).then_some(mode & 0o777))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(None)
+    }
+}
+
+/// Checks that none of `dirs` can be accessed by users other than their owner.
+///
+/// Returns an error for the first such directory if `strict`, otherwise logs a warning for each.
+pub fn check_dir_permissions(dirs: &[&Path], strict: bool, log: &Logger) -> Result<(), String> {
+    for dir in dirs {
+        if let Some(mode) = insecure_mode(dir)? {
+            if strict {
+                return Err(format!(
+                    "The directory {:?} is accessible by other users (mode {:o}), refusing to \
+                     start because --strict-dir-permissions is set. Use `chmod 700` to restrict \
+                     it to its owner",
+                    dir, mode
+                ));
+            }
+            warn!(
+                log,
+                "Insecure directory permissions";
+                "msg" => "the directory is accessible by other users, use `chmod 700` to \
+                          restrict it to its owner",
+                "mode" => format!("{:o}", mode),
+                "path" => ?dir,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use logging::test_logger;
+    use std::fs::{set_permissions, Permissions};
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detect_insecure_permissions() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        let log = test_logger();
+
+        set_permissions(path, Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(insecure_mode(path), Ok(None));
+        assert!(check_dir_permissions(&[path], true, &log).is_ok());
+
+        for mode in [0o750, 0o705, 0o755] {
+            set_permissions(path, Permissions::from_mode(mode)).unwrap();
+            assert_eq!(insecure_mode(path), Ok(Some(mode)));
+            assert!(check_dir_permissions(&[path], false, &log).is_ok());
+            assert!(check_dir_permissions(&[path], true, &log).is_err());
+        }
+
+        assert_eq!(insecure_mode(&path.join("missing")), Ok(None));
+    }
+}
@@ -0,0 +1,138 @@
+//! Checks of the disk space available to the slashing protection database.
+//!
+//! Running out of disk space whilst writing to the slashing protection database can corrupt it, so
+//! the validator client refuses to start with less than `min_free_disk_bytes` available, and warns
+//! if the available space drops below it whilst running.
+
+use crate::http_metrics::metrics;
+use environment::RuntimeContext;
+use slog::{warn, Logger};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use sysinfo::{DiskExt, System, SystemExt};
+use tokio::time::sleep;
+use types::EthSpec;
+
+/// How often the available disk space is checked whilst running.
+pub const DISK_SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Returns the number of bytes available on the filesystem holding `path`.
+pub fn available_space(path: &Path) -> Option<u64> {
+    let path = path.canonicalize().ok()?;
+    let mut system = System::new();
+    system.refresh_disks_list();
+    mount_point_space(
+        system
+            .disks()
+            .iter()
+            .map(|disk| (disk.mount_point(), disk.available_space())),
+        &path,
+    )
+}
+
+/// Returns the available space of the innermost mount point containing `path`.
+fn mount_point_space<'a>(
+    disks: impl IntoIterator<Item = (&'a Path, u64)>,
+    path: &Path,
+) -> Option<u64> {
+    disks
+        .into_iter()
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, available)| available)
+}
+
+/// Returns an error if less than `min_free_disk_bytes` are available on the filesystem holding
+/// `path`.
+///
+/// Only a warning is logged if the available space cannot be determined.
+pub fn check_free_disk_space(
+    path: &Path,
+    min_free_disk_bytes: u64,
+    log: &Logger,
+) -> Result<(), String> {
+    match available_space(path) {
+        Some(available) => {
+            metrics::set_gauge(
+                &metrics::SLASHING_PROTECTION_DISK_FREE_BYTES,
+                available as i64,
+            );
+            if available < min_free_disk_bytes {
+                return Err(format!(
+                    "Only {} bytes of disk space are available for the slashing protection \
+                     database in {:?}, less than the minimum of {} bytes set by \
+                     --min-free-disk-bytes",
+                    available, path, min_free_disk_bytes
+                ));
+            }
+        }
+        None => warn!(
+            log,
+            "Unable to determine free disk space";
+            "msg" => "the slashing protection database may be corrupted if the disk fills up",
+            "path" => ?path,
+        ),
+    }
+    Ok(())
+}
+
+/// Starts a service which periodically warns if less than `min_free_disk_bytes` are available
+/// on the filesystem holding `path`.
+pub fn start_disk_space_service<E: EthSpec>(
+    context: RuntimeContext<E>,
+    path: PathBuf,
+    min_free_disk_bytes: u64,
+) {
+    let log = context.log().clone();
+
+    let future = async move {
+        loop {
+            sleep(DISK_SPACE_CHECK_INTERVAL).await;
+
+            if let Some(available) = available_space(&path) {
+                metrics::set_gauge(
+                    &metrics::SLASHING_PROTECTION_DISK_FREE_BYTES,
+                    available as i64,
+                );
+                if available < min_free_disk_bytes {
+                    warn!(
+                        log,
+                        "Low disk space";
+                        "msg" => "the slashing protection database may be corrupted if the disk \
+                                  fills up",
+                        "available_bytes" => available,
+                        "min_free_disk_bytes" => min_free_disk_bytes,
+                        "path" => ?path,
+                    );
+                }
+            }
+        }
+    };
+
+    context.executor.spawn(future, "disk_space");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn innermost_mount_point() {
+        let disks = [
+            (Path::new("/"), 10),
+            (Path::new("/home"), 20),
+            (Path::new("/home/user/data"), 30),
+        ];
+        let space = |path: &str| mount_point_space(disks, Path::new(path));
+
+        assert_eq!(space("/var/lib"), Some(10));
+        assert_eq!(space("/home/user/.lighthouse"), Some(20));
+        assert_eq!(space("/home/user/data/validators"), Some(30));
+        // Components are compared, not string prefixes.
+        assert_eq!(space("/home/user/database"), Some(20));
+        assert_eq!(
+            mount_point_space(disks[1..].to_vec(), Path::new("/var")),
+            None
+        );
+    }
+}
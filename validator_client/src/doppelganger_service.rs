@@ -9,7 +9,9 @@
 //! remaining epochs is zero, the doppelganger will consider that validator to be safe-enough to
 //! start.
 //!
-//! If a doppelganger is detected, the entire validator client will exit.
+//! If a doppelganger is detected, the entire validator client will exit. Optionally, each detected
+//! validator is also disabled in the validator definitions, so that it stays disabled after a
+//! restart until an operator enables it again.
 //!
 //! For validators started during the genesis epoch, there is **no doppelganger protection!**. This
 //! prevents a stale-mate where all validators will cease to function for a few epochs and then all
@@ -28,13 +30,23 @@
 //! detect each other.
 //!
 //! Doppelganger protection is a best-effort, last-line-of-defence mitigation. Do not rely upon it.
+//!
+//! ## Continuous detection
+//!
+//! Optionally, validators continue to be checked after detection has completed. Since this
+//! validator client's own messages make a validator live, a validator is only considered to have a
+//! doppelganger if it was live in an epoch in which the slashing protection database records
+//! neither an attestation nor a block signed for it. Another instance which signs the same
+//! messages as this one, or signs in the same epochs, is not detected.
 
 use crate::beacon_node_fallback::{BeaconNodeFallback, RequireSynced};
+use crate::http_metrics::metrics;
 use crate::validator_store::ValidatorStore;
 use crate::OfflineOnFailure;
 use environment::RuntimeContext;
 use eth2::types::LivenessResponseData;
-use parking_lot::RwLock;
+use lighthouse_metrics::set_gauge;
+use parking_lot::{Mutex, RwLock};
 use slog::{crit, error, info, Logger};
 use slot_clock::SlotClock;
 use std::collections::{HashMap, HashSet};
@@ -252,6 +264,20 @@ async fn beacon_node_liveness<'a, T: 'static + SlotClock, E: EthSpec>(
 
 pub struct DoppelgangerService {
     doppelganger_states: RwLock<HashMap<PublicKeyBytes, DoppelgangerState>>,
+    /// Continue to check validators after doppelganger detection has completed.
+    continuous_detection: bool,
+    /// Disable signing for a validator which is detected by continuous detection.
+    pause_on_detection: bool,
+    /// Validators for which signing was disabled by continuous detection.
+    ///
+    /// These are kept apart from `doppelganger_states` so that they are not checked again by the
+    /// startup detection, which would shut down the validator client on the activity of their
+    /// doppelganger.
+    paused: RwLock<HashSet<PublicKeyBytes>>,
+    /// Disable detected validators in the validator definitions.
+    disable_detected: bool,
+    /// Validators detected since they were last disabled, if `disable_detected` is set.
+    detected: Mutex<Vec<PublicKeyBytes>>,
     log: Logger,
 }
 
@@ -259,10 +285,44 @@ impl DoppelgangerService {
     pub fn new(log: Logger) -> Self {
         Self {
             doppelganger_states: <_>::default(),
+            continuous_detection: false,
+            pause_on_detection: false,
+            paused: <_>::default(),
+            disable_detected: false,
+            detected: <_>::default(),
             log,
         }
     }
 
+    /// Disable each validator which is detected in the validator definitions, so that it is not
+    /// enabled again when the validator client restarts.
+    pub fn disable_detected(mut self) -> Self {
+        self.disable_detected = true;
+        self
+    }
+
+    /// Counts the detection of a doppelganger of `pubkey`, recording it to be disabled if
+    /// `disable_detected` is set.
+    pub(crate) fn record_detection(&self, pubkey: PublicKeyBytes) {
+        metrics::inc_counter(&metrics::DOPPELGANGER_DETECTED_TOTAL);
+        if self.disable_detected {
+            self.detected.lock().push(pubkey);
+        }
+    }
+
+    /// Returns the validators detected since the last call, which are yet to be disabled.
+    fn take_detected(&self) -> Vec<PublicKeyBytes> {
+        std::mem::take(&mut *self.detected.lock())
+    }
+
+    /// Continue to check validators after doppelganger detection has completed, disabling signing
+    /// for any which are detected if `pause_on_detection` is set.
+    pub fn continuous_detection(mut self, pause_on_detection: bool) -> Self {
+        self.continuous_detection = true;
+        self.pause_on_detection = pause_on_detection;
+        self
+    }
+
     /// Starts a reoccurring future which will try to keep the doppelganger service updated each
     /// slot.
     pub fn start_update_service<E: EthSpec, T: 'static + SlotClock>(
@@ -273,7 +333,12 @@ impl DoppelgangerService {
         slot_clock: T,
     ) -> Result<(), String> {
         // Define the `get_index` function as one that uses the validator store.
-        let get_index = move |pubkey| validator_store.validator_index(&pubkey);
+        let store = validator_store.clone();
+        let get_index = move |pubkey| store.validator_index(&pubkey);
+
+        // Define the `signed_in_epoch` function as one that reads the slashing protection database.
+        let store = validator_store.clone();
+        let signed_in_epoch = move |pubkey, epoch| store.signed_in_epoch(&pubkey, epoch).ok();
 
         // Define the `get_liveness` function as one that queries the beacon node API.
         let log = service.log.clone();
@@ -324,12 +389,13 @@ impl DoppelgangerService {
                     }
 
                     if let Some(slot) = slot_clock.now() {
+                        let mut doppelganger_detected = false;
                         if let Err(e) = service
                             .detect_doppelgangers::<E, _, _, _, _>(
                                 slot,
                                 &get_index,
                                 &get_liveness,
-                                &mut shutdown_func,
+                                &mut || doppelganger_detected = true,
                             )
                             .await
                         {
@@ -339,6 +405,23 @@ impl DoppelgangerService {
                                 "error" => ?e
                             );
                         }
+
+                        service
+                            .detect_continuous_doppelgangers::<E, _, _, _, _>(
+                                slot,
+                                &get_index,
+                                &get_liveness,
+                                &signed_in_epoch,
+                            )
+                            .await;
+
+                        service
+                            .disable_detected_and_shutdown(
+                                &validator_store,
+                                doppelganger_detected,
+                                &mut shutdown_func,
+                            )
+                            .await;
                     }
                 }
             },
@@ -347,8 +430,35 @@ impl DoppelgangerService {
         Ok(())
     }
 
+    /// Disables the validators detected since the last call, then calls `shutdown_func` if
+    /// `doppelganger_detected`.
+    ///
+    /// The validators are disabled before shutting down, so that they stay disabled once the
+    /// validator client is restarted.
+    pub(crate) async fn disable_detected_and_shutdown<T: SlotClock + 'static, E: EthSpec, S>(
+        &self,
+        validator_store: &ValidatorStore<T, E>,
+        doppelganger_detected: bool,
+        shutdown_func: &mut S,
+    ) where
+        S: FnMut(),
+    {
+        let detected = self.take_detected();
+        if !detected.is_empty() {
+            disable_validators(validator_store, detected, &self.log).await;
+        }
+
+        if doppelganger_detected {
+            shutdown_func();
+        }
+    }
+
     /// Returns the current status of the `validator` in the doppelganger protection process.
     pub fn validator_status(&self, validator: PublicKeyBytes) -> DoppelgangerStatus {
+        if self.paused.read().contains(&validator) {
+            return DoppelgangerStatus::SigningDisabled(validator);
+        }
+
         self.doppelganger_states
             .read()
             .get(&validator)
@@ -406,10 +516,33 @@ impl DoppelgangerService {
         };
 
         self.doppelganger_states.write().insert(validator, state);
+        self.update_metrics();
 
         Ok(())
     }
 
+    /// Returns the number of validators which have not yet completed doppelganger detection.
+    fn num_waiting(&self) -> usize {
+        self.doppelganger_states
+            .read()
+            .values()
+            .filter(|state| state.requires_further_checks())
+            .count()
+    }
+
+    /// Updates the gauges tracking how many validators are waiting on or have completed
+    /// doppelganger detection.
+    fn update_metrics(&self) {
+        let waiting = self.num_waiting();
+        let total = self.doppelganger_states.read().len();
+
+        set_gauge(&metrics::DOPPELGANGER_WAITING, waiting as i64);
+        set_gauge(
+            &metrics::DOPPELGANGER_ACTIVE,
+            total.saturating_sub(waiting) as i64,
+        );
+    }
+
     /// Contact the beacon node and try to detect if there are any doppelgangers, updating the state
     /// of `self`.
     ///
@@ -456,6 +589,111 @@ impl DoppelgangerService {
         )
     }
 
+    /// If continuous detection is enabled, check the validators which have completed doppelganger
+    /// detection for activity in the previous epoch which was not signed by this validator client.
+    ///
+    /// As for the initial detection, the previous epoch is only checked in the last slot of the
+    /// current epoch.
+    async fn detect_continuous_doppelgangers<E, I, L, F, D>(
+        &self,
+        request_slot: Slot,
+        get_index: &I,
+        get_liveness: &L,
+        signed_in_epoch: &D,
+    ) where
+        E: EthSpec,
+        I: Fn(PublicKeyBytes) -> Option<u64>,
+        L: Fn(Epoch, Vec<u64>) -> F,
+        F: Future<Output = LivenessResponses>,
+        D: Fn(PublicKeyBytes, Epoch) -> Option<bool>,
+    {
+        if !self.continuous_detection {
+            return;
+        }
+
+        let request_epoch = request_slot.epoch(E::slots_per_epoch());
+        let previous_epoch = request_epoch.saturating_sub(1_u64);
+        if previous_epoch == request_epoch
+            || request_slot != request_epoch.end_slot(E::slots_per_epoch())
+        {
+            return;
+        }
+
+        let indices_map = self.compute_continuous_indices_map(previous_epoch, get_index);
+        if indices_map.is_empty() {
+            return;
+        }
+
+        let indices_only = indices_map.keys().copied().collect();
+        let liveness_responses = get_liveness(request_epoch, indices_only).await;
+
+        let doppelgangers = liveness_responses
+            .previous_epoch_responses
+            .iter()
+            .filter(|response| response.is_live && response.epoch == previous_epoch)
+            .filter_map(|response| {
+                let pubkey = *indices_map.get(&response.index)?;
+                // Activity in an epoch in which this validator client signed is attributed to it,
+                // as is activity which cannot be compared to the slashing protection database.
+                (signed_in_epoch(pubkey, previous_epoch) == Some(false))
+                    .then_some((response.index, pubkey))
+            })
+            .collect::<Vec<_>>();
+
+        for (validator_index, pubkey) in &doppelgangers {
+            metrics::inc_counter(&metrics::DOPPELGANGER_CONTINUOUS_DETECTIONS_TOTAL);
+            self.record_detection(*pubkey);
+            crit!(
+                self.log,
+                "Doppelganger detected after startup";
+                "msg" => "the validator was active in an epoch in which this validator client \
+                    signed nothing for it. Another validator client is likely running the same \
+                    key, which risks slashing. Stop all but one instance immediately",
+                "paused" => self.pause_on_detection,
+                "disabled" => self.disable_detected,
+                "epoch" => previous_epoch,
+                "validator_index" => validator_index,
+                "pubkey" => ?pubkey,
+            );
+        }
+
+        if self.pause_on_detection {
+            self.paused
+                .write()
+                .extend(doppelgangers.iter().map(|(_, pubkey)| *pubkey));
+        }
+    }
+
+    /// Get a map of `validator_index` -> `validator_pubkey` for all validators which completed
+    /// doppelganger detection, were checked from `epoch` or earlier and are not paused.
+    fn compute_continuous_indices_map<F>(
+        &self,
+        epoch: Epoch,
+        get_index: &F,
+    ) -> HashMap<u64, PublicKeyBytes>
+    where
+        F: Fn(PublicKeyBytes) -> Option<u64>,
+    {
+        let paused = self.paused.read().clone();
+        let pubkeys = self
+            .doppelganger_states
+            .read()
+            .iter()
+            .filter(|(pubkey, state)| {
+                !state.requires_further_checks()
+                    && state.next_check_epoch <= epoch
+                    && !paused.contains(*pubkey)
+            })
+            .map(|(pubkey, _)| *pubkey)
+            .collect::<Vec<_>>();
+
+        // As for `compute_detection_indices_map`, avoid interleaving locks.
+        pubkeys
+            .into_iter()
+            .filter_map(|pubkey| Some((get_index(pubkey)?, pubkey)))
+            .collect()
+    }
+
     /// Get a map of `validator_index` -> `validator_pubkey` for all validators still requiring
     /// further doppelganger checks.
     ///
@@ -510,6 +748,7 @@ impl DoppelgangerService {
             previous_epoch_responses,
             current_epoch_responses,
         } = liveness_responses;
+        let previously_waiting = self.num_waiting();
 
         // Perform a loop through the current and previous epoch responses and detect any violators.
         //
@@ -566,7 +805,22 @@ impl DoppelgangerService {
                     offences. Ensure that you are not running a duplicate or overlapping \
                     validator client",
                 "doppelganger_indices" => ?violators
-            )
+            );
+
+            for validator_index in &violators {
+                if let Some(pubkey) = indices_map.get(validator_index) {
+                    self.record_detection(*pubkey);
+                    crit!(
+                        self.log,
+                        "Doppelganger detected for validator";
+                        "msg" => "another validator client is running this validator. Stop it \
+                            before restarting this validator client, or disable the validator",
+                        "disabled" => self.disable_detected,
+                        "validator_index" => validator_index,
+                        "pubkey" => ?pubkey,
+                    );
+                }
+            }
         }
 
         // The concept of "epoch satisfaction" is that for some epoch `e` we are *satisfied* that
@@ -659,6 +913,17 @@ impl DoppelgangerService {
             }
         }
 
+        self.update_metrics();
+
+        if !violators_exist && previously_waiting > 0 && self.num_waiting() == 0 {
+            info!(
+                self.log,
+                "Doppelganger detection complete for all validators";
+                "msg" => "all validators are now active",
+                "num_validators" => previously_waiting
+            );
+        }
+
         // Attempt to shutdown the validator client if there are any detected duplicate validators.
         if violators_exist {
             shutdown_func();
@@ -668,6 +933,53 @@ impl DoppelgangerService {
     }
 }
 
+/// Disables each of `pubkeys` in the validator definitions, so that a validator with a doppelganger
+/// stays disabled after a restart. It can be enabled again via the HTTP API or by editing the
+/// validator definitions.
+// FIXME: ignore this clippy lint until the validator store is refactored to use async locks
+#[allow(clippy::await_holding_lock)]
+async fn disable_validators<T: SlotClock + 'static, E: EthSpec>(
+    validator_store: &ValidatorStore<T, E>,
+    pubkeys: Vec<PublicKeyBytes>,
+    log: &Logger,
+) {
+    for pubkey in pubkeys {
+        let voting_public_key = match pubkey.decompress() {
+            Ok(voting_public_key) => voting_public_key,
+            Err(e) => {
+                error!(
+                    log,
+                    "Unable to decompress validator public key";
+                    "error" => ?e,
+                    "pubkey" => ?pubkey,
+                );
+                continue;
+            }
+        };
+
+        let vals_ref = validator_store.initialized_validators();
+        let mut vals = vals_ref.write();
+        match vals
+            .set_validator_definition_fields(&voting_public_key, Some(false), None, None, None)
+            .await
+        {
+            Ok(()) => crit!(
+                log,
+                "Disabled validator with doppelganger";
+                "msg" => "the validator stays disabled until enabled again, only do so once no \
+                    other validator client is running it",
+                "pubkey" => ?pubkey,
+            ),
+            Err(e) => crit!(
+                log,
+                "Unable to disable validator with doppelganger";
+                "error" => ?e,
+                "pubkey" => ?pubkey,
+            ),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1023,6 +1335,33 @@ mod test {
 
             self
         }
+
+        pub fn simulate_continuous_detection<L, F, D>(
+            self,
+            slot: Slot,
+            get_liveness: L,
+            signed_in_epoch: D,
+        ) -> Self
+        where
+            L: Fn(Epoch, Vec<u64>) -> F,
+            F: Future<Output = LivenessResponses>,
+            D: Fn(PublicKeyBytes, Epoch) -> Option<bool>,
+        {
+            let pubkey_to_index = self.pubkey_to_index_map();
+            let get_index = |pubkey| pubkey_to_index.get(&pubkey).copied();
+
+            block_on(
+                self.doppelganger
+                    .detect_continuous_doppelgangers::<E, _, _, _, _>(
+                        slot,
+                        &get_index,
+                        &get_liveness,
+                        &signed_in_epoch,
+                    ),
+            );
+
+            self
+        }
     }
 
     #[test]
@@ -1445,4 +1784,146 @@ mod test {
 
         scenario.assert_all_enabled();
     }
+    #[test]
+    fn continuous_detection() {
+        let epoch = genesis_epoch() + 1;
+
+        for pause_on_detection in [false, true] {
+            let mut scenario = TestBuilder::default()
+                .build()
+                .set_slot(genesis_epoch().start_slot(E::slots_per_epoch()));
+            scenario.doppelganger = DoppelgangerService::new(null_logger().unwrap())
+                .continuous_detection(pause_on_detection);
+            let scenario = scenario
+                .register_all_in_doppelganger_protection_if_enabled()
+                .assert_all_enabled();
+
+            // Validator 0 signed nothing in `epoch`, the signings of validator 1 are unknown and
+            // all others signed in `epoch`.
+            let doppelganger = scenario.validators[0];
+            let unknown = scenario.validators[1];
+            let signed_in_epoch = |pubkey: PublicKeyBytes, signing_epoch: Epoch| {
+                assert_eq!(signing_epoch, epoch);
+                if pubkey == doppelganger {
+                    Some(false)
+                } else if pubkey == unknown {
+                    None
+                } else {
+                    Some(true)
+                }
+            };
+
+            let scenario = scenario
+                // Epochs are only checked in the last slot of the following epoch.
+                .simulate_continuous_detection(
+                    (epoch + 1).start_slot(E::slots_per_epoch()),
+                    |_, _| -> future::Ready<LivenessResponses> {
+                        panic!("liveness should not be queried")
+                    },
+                    signed_in_epoch,
+                )
+                .simulate_continuous_detection(
+                    (epoch + 1).end_slot(E::slots_per_epoch()),
+                    |current_epoch, detection_indices: Vec<_>| {
+                        assert_eq!(current_epoch, epoch + 1);
+                        check_detection_indices(&detection_indices);
+
+                        let mut liveness_responses =
+                            get_false_responses(current_epoch, &detection_indices);
+                        for response in &mut liveness_responses.previous_epoch_responses {
+                            response.is_live = true;
+                        }
+                        future::ready(liveness_responses)
+                    },
+                    signed_in_epoch,
+                );
+
+            let assert_statuses = |scenario: &TestScenario| {
+                for validator in &scenario.validators {
+                    let expected = if *validator == doppelganger && pause_on_detection {
+                        DoppelgangerStatus::SigningDisabled(*validator)
+                    } else {
+                        DoppelgangerStatus::SigningEnabled(*validator)
+                    };
+                    assert_eq!(scenario.doppelganger.validator_status(*validator), expected);
+                }
+            };
+            assert_statuses(&scenario);
+
+            // The doppelganger remains active. A paused validator is not checked again, neither by
+            // the startup detection, which would shut down the validator client, nor by continuous
+            // detection.
+            let all_live = |current_epoch, detection_indices: Vec<_>| {
+                let mut liveness_responses = get_false_responses(current_epoch, &detection_indices);
+                for response in liveness_responses
+                    .previous_epoch_responses
+                    .iter_mut()
+                    .chain(&mut liveness_responses.current_epoch_responses)
+                {
+                    response.is_live = true;
+                }
+                future::ready(liveness_responses)
+            };
+            let scenario = scenario
+                .simulate_detect_doppelgangers(
+                    (epoch + 1).end_slot(E::slots_per_epoch()) + 1,
+                    ShouldShutdown::No,
+                    all_live,
+                )
+                .simulate_continuous_detection(
+                    (epoch + 2).end_slot(E::slots_per_epoch()),
+                    |current_epoch, detection_indices: Vec<_>| {
+                        assert_eq!(
+                            detection_indices.contains(&0),
+                            !pause_on_detection,
+                            "only unpaused validators are checked"
+                        );
+                        all_live(current_epoch, detection_indices)
+                    },
+                    |_, _| Some(true),
+                );
+            assert_statuses(&scenario);
+        }
+    }
+
+    #[test]
+    fn detected_validators_are_recorded_for_disabling() {
+        let starting_epoch = genesis_epoch() + 1;
+        let starting_slot = starting_epoch.start_slot(E::slots_per_epoch());
+        let checking_epoch = starting_epoch + 2;
+        let checking_slot = checking_epoch.start_slot(E::slots_per_epoch());
+
+        for disable_detected in [false, true] {
+            let mut scenario = TestBuilder::default().build().set_slot(starting_slot);
+            if disable_detected {
+                scenario.doppelganger =
+                    DoppelgangerService::new(null_logger().unwrap()).disable_detected();
+            }
+            let detected = scenario.validators[0];
+
+            let scenario = scenario
+                .register_all_in_doppelganger_protection_if_enabled()
+                .simulate_detect_doppelgangers(
+                    checking_slot,
+                    ShouldShutdown::Yes,
+                    |current_epoch, detection_indices: Vec<_>| {
+                        let mut liveness_responses =
+                            get_false_responses(current_epoch, &detection_indices);
+                        for response in &mut liveness_responses.current_epoch_responses {
+                            response.is_live = response.index == 0;
+                        }
+                        future::ready(liveness_responses)
+                    },
+                )
+                .assert_all_disabled();
+
+            let expected = if disable_detected {
+                vec![detected]
+            } else {
+                vec![]
+            };
+            assert_eq!(scenario.doppelganger.take_detected(), expected);
+            assert!(scenario.doppelganger.take_detected().is_empty());
+        }
+    }
 }
@@ -6,29 +6,44 @@
 //! The `DutiesService` is also responsible for sending events to the `BlockService` which trigger
 //! block production.
 
+mod batch;
+mod schedule;
 mod sync;
 
+pub use batch::DutiesBatchSizes;
+
 use crate::beacon_node_fallback::{BeaconNodeFallback, OfflineOnFailure, RequireSynced};
-use crate::http_metrics::metrics::{get_int_gauge, set_int_gauge, ATTESTATION_DUTY};
+use crate::http_metrics::metrics::{
+    get_int_gauge, set_int_gauge, ATTESTATION_DUTY, ATTESTATION_DUTY_BUCKET,
+    VALIDATORS_PER_METRICS_BUCKET,
+};
 use crate::{
     block_service::BlockServiceNotification,
     http_metrics::metrics,
+    skip_reason::{self, SkipReason},
+    validator_index_cache::ValidatorIndexCache,
     validator_store::{DoppelgangerStatus, Error as ValidatorStoreError, ValidatorStore},
 };
 use environment::RuntimeContext;
+use eth2::lighthouse_vc::types::EpochDuties;
 use eth2::types::{
     AttesterData, BeaconCommitteeSubscription, DutiesResponse, ProposerData, StateId, ValidatorId,
+    ValidatorStatus,
 };
 use futures::{stream, StreamExt};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use safe_arith::ArithError;
+pub use schedule::print_duty_schedule;
+use serde_derive::{Deserialize, Serialize};
 use slog::{debug, error, info, warn, Logger};
 use slot_clock::SlotClock;
 use std::cmp::min;
 use std::collections::{hash_map, BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use strum::{Display, EnumString, EnumVariantNames};
 use sync::poll_sync_committee_duties;
+pub use sync::SlotDuties;
 use sync::SyncDutiesMap;
 use tokio::{sync::mpsc::Sender, time::sleep};
 use types::{ChainSpec, Epoch, EthSpec, Hash256, PublicKeyBytes, SelectionProof, Slot};
@@ -57,11 +72,57 @@ const SELECTION_PROOF_SCHEDULE_DENOM: u32 = 2;
 /// flag in the cli to enable collection of per validator metrics.
 const VALIDATOR_METRICS_MIN_COUNT: usize = 64;
 
+/// The number of buckets that validators are grouped into with `MetricsValidatorBucketing::Bucketed`,
+/// one for each value of the first hex digit of a public key.
+const VALIDATOR_METRICS_BUCKETS: usize = 16;
+
+/// The maximum number of validators to request balances for in a single request.
+///
+/// Validator ids are sent in the URL query, so this must remain modest.
+const BALANCE_QUERY_BATCH_SIZE: usize = 256;
+
 /// The number of validators to request duty information for in the initial request.
 /// The initial request is used to determine if further requests are required, so that it
 /// reduces the amount of data that needs to be transferred.
 const INITIAL_DUTIES_QUERY_SIZE: usize = 1;
 
+/// The maximum number of cached validator indices to verify with the beacon node per poll, so
+/// that a large cache does not delay the lookup of new validators.
+const MAX_INDEX_VERIFICATIONS_PER_POLL: usize = 128;
+
+/// The maximum number of validators without an index listed in a single log message.
+const MAX_UNRESOLVED_PUBKEYS_LOGGED: usize = 32;
+
+/// How validators are labelled in the metrics of their duties.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum MetricsValidatorBucketing {
+    /// One series per validator, published for up to `VALIDATOR_METRICS_MIN_COUNT` validators
+    /// unless `enable_high_validator_count_metrics` is set.
+    #[default]
+    Full,
+    /// One series per bucket of validators, grouped by the first hex digit of their public key,
+    /// regardless of the number of validators.
+    Bucketed,
+}
+
+/// Returns the metrics bucket of `pubkey`, the first hex digit of the public key.
+fn validator_metrics_bucket(pubkey: &PublicKeyBytes) -> usize {
+    (pubkey.as_serialized()[0] >> 4) as usize
+}
+
 #[derive(Debug)]
 pub enum Error {
     UnableToReadSlotClock,
@@ -147,7 +208,26 @@ pub struct DutiesService<T, E: EthSpec> {
     pub slot_clock: T,
     /// Provides HTTP access to remote beacon nodes.
     pub beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
+    /// Validator indices persisted across restarts.
+    pub validator_index_cache: Mutex<ValidatorIndexCache>,
     pub enable_high_validator_count_metrics: bool,
+    /// Publish the duty metrics of each validator, or of buckets of validators.
+    pub metrics_validator_bucketing: MetricsValidatorBucketing,
+    /// Raise an alert when a validator's effective balance falls below this value.
+    pub min_balance_alert_gwei: Option<u64>,
+    /// Disable validators which have exited or been slashed as of the finalized state.
+    pub auto_disable_inactive: bool,
+    /// The minimum time between lookups of a validator which is not yet known to the beacon chain.
+    pub index_resolution_interval: Duration,
+    /// The time of the most recent lookup of each validator not yet known to the beacon chain.
+    pub unresolved_indices: Mutex<HashMap<PublicKeyBytes, Instant>>,
+    /// The last known status and activation epoch of each validator which has been queried.
+    pub validator_statuses: RwLock<HashMap<PublicKeyBytes, (ValidatorStatus, Epoch)>>,
+    /// Poll pending validators every slot once they are due to be activated within this many
+    /// epochs.
+    pub activation_poll_epochs: u64,
+    /// The number of validator indices sent in each attester and sync duties request.
+    pub duties_batch_sizes: DutiesBatchSizes,
     pub context: RuntimeContext<E>,
     pub spec: ChainSpec,
 }
@@ -158,6 +238,61 @@ impl<T: SlotClock + 'static, E: EthSpec> DutiesService<T, E> {
         self.validator_store.num_voting_validators()
     }
 
+    /// Returns the range of epochs for which duties are cached in the current epoch, or `None` if
+    /// the slot clock cannot be read.
+    ///
+    /// Duties are retained for `HISTORICAL_DUTIES_EPOCHS` prior to the current epoch and fetched
+    /// up to the next epoch.
+    pub fn cached_duties_epochs(&self) -> Option<(Epoch, Epoch)> {
+        let current_epoch = self.slot_clock.now()?.epoch(E::slots_per_epoch());
+        Some((
+            current_epoch.saturating_sub(HISTORICAL_DUTIES_EPOCHS),
+            current_epoch + 1,
+        ))
+    }
+
+    /// Returns the cached attestation, proposal and sync committee duties of local validators in
+    /// `epoch`, which are empty for any duties which have not been fetched.
+    pub fn cached_duties(&self, epoch: Epoch) -> EpochDuties {
+        let mut attesters: Vec<_> = self
+            .attesters
+            .read()
+            .values()
+            .filter_map(|epoch_duties| {
+                let (_, duty_and_proof) = epoch_duties.get(&epoch)?;
+                Some(duty_and_proof.duty.clone())
+            })
+            .collect();
+        attesters.sort_by_key(|duty| (duty.slot, duty.validator_index));
+
+        let mut proposers = self
+            .proposers
+            .read()
+            .get(&epoch)
+            .map(|(_, proposers)| proposers.clone())
+            .unwrap_or_default();
+        proposers.sort_by_key(|duty| duty.slot);
+
+        let mut sync_committee = epoch
+            .sync_committee_period(&self.spec)
+            .ok()
+            .filter(|_| {
+                self.spec
+                    .altair_fork_epoch
+                    .map_or(false, |altair_epoch| epoch >= altair_epoch)
+            })
+            .map(|period| self.sync_duties.committee_members(period))
+            .unwrap_or_default();
+        sync_committee.sort_by_key(|duty| duty.validator_index);
+
+        EpochDuties {
+            epoch,
+            attesters,
+            proposers,
+            sync_committee,
+        }
+    }
+
     /// Returns the total number of validators that should propose in the given epoch.
     pub fn proposer_count(&self, epoch: Epoch) -> usize {
         // Only collect validators that are considered safe in terms of doppelganger protection.
@@ -250,8 +385,9 @@ impl<T: SlotClock + 'static, E: EthSpec> DutiesService<T, E> {
 
     /// Returns `true` if we should collect per validator metrics and `false` otherwise.
     pub fn per_validator_metrics(&self) -> bool {
-        self.enable_high_validator_count_metrics
-            || self.total_validator_count() <= VALIDATOR_METRICS_MIN_COUNT
+        self.metrics_validator_bucketing == MetricsValidatorBucketing::Full
+            && (self.enable_high_validator_count_metrics
+                || self.total_validator_count() <= VALIDATOR_METRICS_MIN_COUNT)
     }
 }
 
@@ -352,6 +488,91 @@ pub fn start_update_service<T: SlotClock + 'static, E: EthSpec>(
         "duties_service_attesters",
     );
 
+    // Spawn the task which alerts on low validator balances, if enabled.
+    if let Some(min_balance_gwei) = core_duties_service.min_balance_alert_gwei {
+        let duties_service = core_duties_service.clone();
+        core_duties_service.context.executor.spawn(
+            async move {
+                let mut low_balance_statuses = HashMap::new();
+                loop {
+                    poll_validator_balances(
+                        &duties_service,
+                        min_balance_gwei,
+                        &mut low_balance_statuses,
+                    )
+                    .await;
+
+                    // Balances only change at epoch boundaries, so poll once per epoch.
+                    if let Some(duration) = duties_service
+                        .slot_clock
+                        .duration_to_next_epoch(E::slots_per_epoch())
+                    {
+                        sleep(duration + duties_service.slot_clock.slot_duration() / 2).await;
+                    } else {
+                        // Just sleep for one slot if we are unable to read the system clock, this gives
+                        // us an opportunity for the clock to eventually come good.
+                        sleep(duties_service.slot_clock.slot_duration()).await;
+                    }
+                }
+            },
+            "duties_service_balances",
+        );
+    }
+
+    // Spawn the task which tracks the statuses of validators which have not yet been activated.
+    let duties_service = core_duties_service.clone();
+    core_duties_service.context.executor.spawn(
+        async move {
+            let mut last_full_poll = None;
+            loop {
+                if let Some(current_epoch) = duties_service
+                    .slot_clock
+                    .now()
+                    .map(|slot| slot.epoch(E::slots_per_epoch()))
+                {
+                    let full_poll = last_full_poll != Some(current_epoch);
+                    poll_validator_statuses(&duties_service, current_epoch, full_poll).await;
+                    last_full_poll = Some(current_epoch);
+                }
+
+                if let Some(duration) = duties_service.slot_clock.duration_to_next_slot() {
+                    sleep(duration).await;
+                } else {
+                    // Just sleep for one slot if we are unable to read the system clock, this gives
+                    // us an opportunity for the clock to eventually come good.
+                    sleep(duties_service.slot_clock.slot_duration()).await;
+                }
+            }
+        },
+        "duties_service_validator_statuses",
+    );
+
+    // Spawn the task which disables exited and slashed validators, if enabled.
+    if core_duties_service.auto_disable_inactive {
+        let duties_service = core_duties_service.clone();
+        core_duties_service.context.executor.spawn(
+            async move {
+                loop {
+                    poll_inactive_validators(&duties_service).await;
+
+                    // The finalized state only changes at epoch boundaries, so poll once per
+                    // epoch.
+                    if let Some(duration) = duties_service
+                        .slot_clock
+                        .duration_to_next_epoch(E::slots_per_epoch())
+                    {
+                        sleep(duration + duties_service.slot_clock.slot_duration() / 2).await;
+                    } else {
+                        // Just sleep for one slot if we are unable to read the system clock, this gives
+                        // us an opportunity for the clock to eventually come good.
+                        sleep(duties_service.slot_clock.slot_duration()).await;
+                    }
+                }
+            },
+            "duties_service_auto_disable",
+        );
+    }
+
     // Spawn the task which keeps track of local sync committee duties.
     let duties_service = core_duties_service.clone();
     let log = core_duties_service.context.log().clone();
@@ -402,16 +623,67 @@ async fn poll_validator_indices<T: SlotClock + 'static, E: EthSpec>(
         .validator_store
         .voting_pubkeys(DoppelgangerStatus::ignored);
 
+    let mut verifications = 0;
+    let mut out_of_index_range = 0;
+    // Validators without an index after this poll, and the number of them looked up.
+    let mut unresolved = vec![];
+    let mut unresolved_lookups = 0;
     for pubkey in all_pubkeys {
         // This is on its own line to avoid some weirdness with locks and if statements.
-        let is_known = duties_service
+        let known_index = duties_service
             .validator_store
             .initialized_validators()
             .read()
-            .get_index(&pubkey)
-            .is_some();
+            .get_index(&pubkey);
+
+        let (cached_index, cache_unverified) = {
+            let cache = duties_service.validator_index_cache.lock();
+            (cache.get(&pubkey), cache.is_unverified(&pubkey))
+        };
 
-        if !is_known {
+        // Use a cached index straight away, it is verified with the beacon node on a later poll.
+        if let (None, Some(index), true) = (known_index, cached_index, cache_unverified) {
+            debug!(
+                log,
+                "Loaded validator index from cache";
+                "pubkey" => ?pubkey,
+                "validator_index" => index,
+            );
+            let in_range = duties_service
+                .validator_store
+                .initialized_validators()
+                .write()
+                .set_index(&pubkey, index);
+            if !in_range {
+                log_out_of_index_range(log, &pubkey, index);
+                out_of_index_range += 1;
+            }
+            continue;
+        }
+
+        // Validators which are not yet known to the beacon chain are looked up at most once per
+        // `index_resolution_interval`.
+        let recently_looked_up = known_index.is_none()
+            && duties_service
+                .unresolved_indices
+                .lock()
+                .get(&pubkey)
+                .map_or(false, |last_lookup| {
+                    last_lookup.elapsed() < duties_service.index_resolution_interval
+                });
+        if recently_looked_up {
+            unresolved.push(pubkey);
+            continue;
+        }
+
+        let needs_verification = known_index.is_some()
+            && cache_unverified
+            && verifications < MAX_INDEX_VERIFICATIONS_PER_POLL;
+        if needs_verification {
+            verifications += 1;
+        }
+
+        if known_index.is_none() || needs_verification {
             // Query the remote BN to resolve a pubkey to a validator index.
             let download_result = duties_service
                 .beacon_nodes
@@ -444,18 +716,49 @@ async fn poll_validator_indices<T: SlotClock + 'static, E: EthSpec>(
                 });
             match download_result {
                 Ok(Some(response)) => {
-                    info!(
-                        log,
-                        "Validator exists in beacon chain";
-                        "pubkey" => ?pubkey,
-                        "validator_index" => response.data.index,
-                        "fee_recipient" => fee_recipient
-                    );
-                    duties_service
+                    let index = response.data.index;
+                    match known_index {
+                        None => info!(
+                            log,
+                            "Validator exists in beacon chain";
+                            "pubkey" => ?pubkey,
+                            "validator_index" => index,
+                            "fee_recipient" => fee_recipient
+                        ),
+                        Some(cached_index) if cached_index != index => error!(
+                            log,
+                            "Cached validator index is incorrect";
+                            "msg" => "using the index from the beacon node",
+                            "pubkey" => ?pubkey,
+                            "cached_index" => cached_index,
+                            "validator_index" => index,
+                        ),
+                        Some(_) => (),
+                    }
+                    let in_range = duties_service
                         .validator_store
                         .initialized_validators()
                         .write()
-                        .set_index(&pubkey, response.data.index);
+                        .set_index(&pubkey, index);
+                    if !in_range {
+                        log_out_of_index_range(log, &pubkey, index);
+                        out_of_index_range += 1;
+                    }
+                    duties_service
+                        .validator_index_cache
+                        .lock()
+                        .insert(pubkey, index);
+                    duties_service.unresolved_indices.lock().remove(&pubkey);
+                }
+                // The beacon node may be lagging behind the one which provided the cached index,
+                // keep using it and try again on the next poll.
+                Ok(None) if known_index.is_some() => {
+                    warn!(
+                        log,
+                        "Unable to verify cached validator index";
+                        "msg" => "validator unknown to the beacon node",
+                        "pubkey" => ?pubkey,
+                    )
                 }
                 // This is not necessarily an error, it just means the validator is not yet known to
                 // the beacon chain.
@@ -465,10 +768,19 @@ async fn poll_validator_indices<T: SlotClock + 'static, E: EthSpec>(
                         "Validator without index";
                         "pubkey" => ?pubkey,
                         "fee_recipient" => fee_recipient
-                    )
+                    );
+                    duties_service
+                        .unresolved_indices
+                        .lock()
+                        .insert(pubkey, Instant::now());
+                    unresolved.push(pubkey);
+                    unresolved_lookups += 1;
                 }
                 // Don't exit early on an error, keep attempting to resolve other indices.
                 Err(e) => {
+                    if known_index.is_none() {
+                        unresolved.push(pubkey);
+                    }
                     error!(
                         log,
                         "Failed to resolve pubkey to index";
@@ -480,6 +792,429 @@ async fn poll_validator_indices<T: SlotClock + 'static, E: EthSpec>(
             }
         }
     }
+
+    // Forget validators which have been resolved or removed.
+    let unresolved_set: HashSet<_> = unresolved.iter().collect();
+    duties_service
+        .unresolved_indices
+        .lock()
+        .retain(|pubkey, _| unresolved_set.contains(pubkey));
+    metrics::set_gauge(
+        &metrics::VALIDATORS_UNRESOLVED_INDEX_COUNT,
+        unresolved.len() as i64,
+    );
+    if unresolved_lookups > 0 {
+        let pubkeys = unresolved
+            .iter()
+            .take(MAX_UNRESOLVED_PUBKEYS_LOGGED)
+            .map(|pubkey| format!("{:?}", pubkey))
+            .collect::<Vec<_>>()
+            .join(", ");
+        info!(
+            log,
+            "Validators awaiting an index";
+            "msg" => "these validators are not yet known to the beacon chain and will start \
+                performing duties once they are",
+            "count" => unresolved.len(),
+            "pubkeys" => pubkeys,
+        );
+    }
+
+    if let Err(e) = duties_service.validator_index_cache.lock().save() {
+        warn!(
+            log,
+            "Failed to save validator index cache";
+            "error" => ?e,
+        );
+    }
+
+    if out_of_index_range > 0
+        && duties_service
+            .validator_store
+            .initialized_validators()
+            .read()
+            .iter_voting_pubkeys()
+            .next()
+            .is_none()
+    {
+        warn!(
+            log,
+            "No validators within --validator-index-range";
+            "msg" => "none of the validators of this validator client will perform duties",
+            "excluded_validators" => out_of_index_range,
+        );
+    }
+}
+
+fn log_out_of_index_range(log: &Logger, pubkey: &PublicKeyBytes, index: u64) {
+    info!(
+        log,
+        "Validator outside of --validator-index-range";
+        "msg" => "the validator will not perform duties",
+        "pubkey" => ?pubkey,
+        "validator_index" => index,
+    );
+}
+
+/// Query the beacon node for the effective balances of all validators with a known index and
+/// raise an alert for any which are below `min_balance_gwei`.
+///
+/// `low_balance_statuses` holds the status of each validator which was below `min_balance_gwei`
+/// at the previous poll, so that the alert is only raised again when the status of the validator
+/// changes, rather than every epoch for a validator which has exited.
+///
+/// Validators without an index (i.e., not yet known to the beacon chain) are ignored.
+async fn poll_validator_balances<T: SlotClock + 'static, E: EthSpec>(
+    duties_service: &DutiesService<T, E>,
+    min_balance_gwei: u64,
+    low_balance_statuses: &mut HashMap<u64, ValidatorStatus>,
+) {
+    let log = duties_service.context.log();
+
+    let local_indices: Vec<u64> = {
+        let pubkeys: Vec<_> = duties_service
+            .validator_store
+            .voting_pubkeys(DoppelgangerStatus::ignored);
+        let vals_ref = duties_service.validator_store.initialized_validators();
+        let vals = vals_ref.read();
+        pubkeys
+            .iter()
+            .filter_map(|pubkey| vals.get_index(pubkey))
+            .collect()
+    };
+
+    // Forget validators which have been removed, so they are alerted on if they are re-added.
+    let local_index_set: HashSet<u64> = local_indices.iter().copied().collect();
+    low_balance_statuses.retain(|index, _| local_index_set.contains(index));
+
+    let local_indices: Vec<_> = local_indices.into_iter().map(ValidatorId::Index).collect();
+
+    let mut num_below_min = 0;
+    // The count is only accurate if the balances of all validators were downloaded.
+    let mut all_downloaded = true;
+    for batch in local_indices.chunks(BALANCE_QUERY_BATCH_SIZE) {
+        let download_result = duties_service
+            .beacon_nodes
+            .first_success(
+                RequireSynced::No,
+                OfflineOnFailure::Yes,
+                |beacon_node| async move {
+                    beacon_node
+                        .get_beacon_states_validators(StateId::Head, Some(batch), None)
+                        .await
+                },
+            )
+            .await;
+
+        let validators = match download_result {
+            Ok(Some(response)) => response.data,
+            // The BN does not know of the head state, try again next epoch.
+            Ok(None) => {
+                all_downloaded = false;
+                continue;
+            }
+            Err(e) => {
+                error!(
+                    log,
+                    "Failed to download validator balances";
+                    "error" => %e,
+                );
+                all_downloaded = false;
+                continue;
+            }
+        };
+
+        for validator_data in validators {
+            let effective_balance = validator_data.validator.effective_balance;
+            if effective_balance >= min_balance_gwei {
+                low_balance_statuses.remove(&validator_data.index);
+                continue;
+            }
+
+            num_below_min += 1;
+            let previous_status =
+                low_balance_statuses.insert(validator_data.index, validator_data.status);
+            if previous_status != Some(validator_data.status) {
+                metrics::inc_counter(&metrics::MIN_BALANCE_ALERTS_TOTAL);
+                warn!(
+                    log,
+                    "Validator balance below alert threshold";
+                    "msg" => "this may indicate slashing or prolonged downtime",
+                    "pubkey" => ?validator_data.validator.pubkey,
+                    "validator_index" => validator_data.index,
+                    "status" => ?validator_data.status,
+                    "effective_balance_gwei" => effective_balance,
+                    "min_balance_alert_gwei" => min_balance_gwei,
+                );
+            }
+        }
+    }
+
+    if all_downloaded {
+        metrics::set_gauge(&metrics::VALIDATORS_BELOW_MIN_BALANCE_COUNT, num_below_min);
+    }
+}
+
+/// Returns `true` if a validator with `status` has not yet been activated.
+fn is_pending_status(status: ValidatorStatus) -> bool {
+    matches!(
+        status,
+        ValidatorStatus::PendingInitialized
+            | ValidatorStatus::PendingQueued
+            | ValidatorStatus::Pending
+    )
+}
+
+/// Returns `true` if the status of a validator should be queried in `current_epoch`, given its
+/// last known status and activation epoch.
+///
+/// Validators with an unknown status, and pending validators, are queried once per epoch during a
+/// `full_poll`. Pending validators due to be activated within `activation_poll_epochs` are queried
+/// every slot, so that their activation is noticed as soon as the head state reflects it.
+fn needs_status_poll(
+    known: Option<(ValidatorStatus, Epoch)>,
+    current_epoch: Epoch,
+    activation_poll_epochs: u64,
+    full_poll: bool,
+) -> bool {
+    match known {
+        None => full_poll,
+        Some((status, _)) if !is_pending_status(status) => false,
+        Some((_, activation_epoch)) => {
+            full_poll || activation_epoch <= current_epoch.saturating_add(activation_poll_epochs)
+        }
+    }
+}
+
+/// Query the beacon node for the status and activation epoch of local validators which have not
+/// yet been activated, logging each validator as it becomes active.
+///
+/// Pending validators are only queried every slot when close to activation, see
+/// `needs_status_poll`.
+async fn poll_validator_statuses<T: SlotClock + 'static, E: EthSpec>(
+    duties_service: &DutiesService<T, E>,
+    current_epoch: Epoch,
+    full_poll: bool,
+) {
+    let log = duties_service.context.log();
+
+    let local_indices: Vec<_> = {
+        let pubkeys: Vec<_> = duties_service
+            .validator_store
+            .voting_pubkeys(DoppelgangerStatus::ignored);
+        let statuses = duties_service.validator_statuses.read();
+        let vals_ref = duties_service.validator_store.initialized_validators();
+        let vals = vals_ref.read();
+        pubkeys
+            .iter()
+            .filter(|pubkey| {
+                needs_status_poll(
+                    statuses.get(*pubkey).copied(),
+                    current_epoch,
+                    duties_service.activation_poll_epochs,
+                    full_poll,
+                )
+            })
+            .filter_map(|pubkey| vals.get_index(pubkey))
+            .map(ValidatorId::Index)
+            .collect()
+    };
+
+    for batch in local_indices.chunks(BALANCE_QUERY_BATCH_SIZE) {
+        let download_result = duties_service
+            .beacon_nodes
+            .first_success(
+                RequireSynced::No,
+                OfflineOnFailure::Yes,
+                |beacon_node| async move {
+                    beacon_node
+                        .get_beacon_states_validators(StateId::Head, Some(batch), None)
+                        .await
+                },
+            )
+            .await;
+
+        let validators = match download_result {
+            Ok(Some(response)) => response.data,
+            // The BN does not know of the head state, try again later.
+            Ok(None) => continue,
+            Err(e) => {
+                error!(
+                    log,
+                    "Failed to download validator statuses";
+                    "error" => %e,
+                );
+                continue;
+            }
+        };
+
+        let mut statuses = duties_service.validator_statuses.write();
+        for validator_data in validators {
+            let pubkey = validator_data.validator.pubkey;
+            let status = validator_data.status;
+            let activation_epoch = validator_data.validator.activation_epoch;
+
+            if activation_epoch != duties_service.spec.far_future_epoch
+                && duties_service.validator_store.activation_epoch(&pubkey)
+                    != Some(activation_epoch)
+            {
+                debug!(
+                    log,
+                    "Learned validator activation epoch";
+                    "pubkey" => ?pubkey,
+                    "validator_index" => validator_data.index,
+                    "activation_epoch" => activation_epoch,
+                );
+                duties_service
+                    .validator_store
+                    .set_activation_epoch(pubkey, activation_epoch);
+            }
+
+            let previous = statuses.insert(pubkey, (status, activation_epoch));
+            if previous.map_or(false, |(previous, _)| is_pending_status(previous))
+                && !is_pending_status(status)
+            {
+                info!(
+                    log,
+                    "Validator activated";
+                    "pubkey" => ?pubkey,
+                    "validator_index" => validator_data.index,
+                    "activation_epoch" => activation_epoch,
+                    "status" => ?status,
+                );
+            }
+        }
+    }
+
+    let pending_count = duties_service
+        .validator_statuses
+        .read()
+        .values()
+        .filter(|(status, _)| is_pending_status(*status))
+        .count();
+    metrics::set_gauge(
+        &metrics::VALIDATORS_PENDING_ACTIVATION_COUNT,
+        pending_count as i64,
+    );
+}
+
+/// Returns `true` if a validator with `status` will never need to perform duties again.
+///
+/// Slashed validators are included even if they have not yet exited, since they can no longer
+/// propose or earn attestation rewards. Validators which are only exiting are not included, since
+/// they must perform their duties until their exit epoch.
+fn is_terminal_status(status: ValidatorStatus) -> bool {
+    matches!(
+        status,
+        ValidatorStatus::ActiveSlashed
+            | ValidatorStatus::ExitedUnslashed
+            | ValidatorStatus::ExitedSlashed
+            | ValidatorStatus::WithdrawalPossible
+            | ValidatorStatus::WithdrawalDone
+            | ValidatorStatus::Exited
+            | ValidatorStatus::Withdrawal
+    )
+}
+
+/// Query the beacon node for the status of all enabled validators with a known index and disable
+/// any which have exited or been slashed.
+///
+/// Statuses are read from the finalized state, so that a validator is never disabled because of
+/// an exit or slashing which is later reorged out of the chain. Disabled validators are persisted
+/// to the validator definitions file and can be enabled again via the HTTP API.
+// FIXME: ignore this clippy lint until the validator store is refactored to use async locks
+#[allow(clippy::await_holding_lock)]
+async fn poll_inactive_validators<T: SlotClock + 'static, E: EthSpec>(
+    duties_service: &DutiesService<T, E>,
+) {
+    let log = duties_service.context.log();
+
+    let local_indices: Vec<_> = {
+        let pubkeys: Vec<_> = duties_service
+            .validator_store
+            .voting_pubkeys(DoppelgangerStatus::ignored);
+        let vals_ref = duties_service.validator_store.initialized_validators();
+        let vals = vals_ref.read();
+        pubkeys
+            .iter()
+            .filter_map(|pubkey| vals.get_index(pubkey))
+            .map(ValidatorId::Index)
+            .collect()
+    };
+
+    let mut inactive = vec![];
+    for batch in local_indices.chunks(BALANCE_QUERY_BATCH_SIZE) {
+        let download_result = duties_service
+            .beacon_nodes
+            .first_success(
+                RequireSynced::No,
+                OfflineOnFailure::Yes,
+                |beacon_node| async move {
+                    beacon_node
+                        .get_beacon_states_validators(StateId::Finalized, Some(batch), None)
+                        .await
+                },
+            )
+            .await;
+
+        match download_result {
+            Ok(Some(response)) => inactive.extend(
+                response
+                    .data
+                    .into_iter()
+                    .filter(|validator_data| is_terminal_status(validator_data.status)),
+            ),
+            // The BN does not know of the finalized state, try again next epoch.
+            Ok(None) => continue,
+            Err(e) => {
+                error!(
+                    log,
+                    "Failed to download validator statuses";
+                    "error" => %e,
+                );
+                continue;
+            }
+        }
+    }
+
+    for validator_data in inactive {
+        let pubkey = validator_data.validator.pubkey;
+        let voting_public_key = match pubkey.decompress() {
+            Ok(voting_public_key) => voting_public_key,
+            Err(e) => {
+                error!(
+                    log,
+                    "Unable to decompress validator public key";
+                    "error" => ?e,
+                    "pubkey" => ?pubkey,
+                );
+                continue;
+            }
+        };
+
+        let vals_ref = duties_service.validator_store.initialized_validators();
+        let mut vals = vals_ref.write();
+        match vals
+            .set_validator_definition_fields(&voting_public_key, Some(false), None, None, None)
+            .await
+        {
+            Ok(()) => warn!(
+                log,
+                "Disabled inactive validator";
+                "msg" => "the validator has exited or been slashed and will no longer perform duties",
+                "pubkey" => ?pubkey,
+                "validator_index" => validator_data.index,
+                "status" => ?validator_data.status,
+            ),
+            Err(e) => error!(
+                log,
+                "Unable to disable inactive validator";
+                "error" => ?e,
+                "pubkey" => ?pubkey,
+                "validator_index" => validator_data.index,
+            ),
+        }
+    }
 }
 
 /// Query the beacon node for attestation duties for any known validators.
@@ -569,6 +1304,9 @@ async fn poll_beacon_attesters<T: SlotClock + 'static, E: EthSpec>(
     }
 
     update_per_validator_duty_metrics::<T, E>(duties_service, next_epoch, current_slot);
+    if duties_service.metrics_validator_bucketing == MetricsValidatorBucketing::Bucketed {
+        update_bucketed_duty_metrics(duties_service, current_slot);
+    }
 
     drop(next_epoch_timer);
     let subscriptions_timer =
@@ -651,7 +1389,7 @@ async fn poll_beacon_attesters<T: SlotClock + 'static, E: EthSpec>(
 
 /// For the given `local_indices` and `local_pubkeys`, download the duties for the given `epoch` and
 /// store them in `duties_service.attesters`.
-async fn poll_beacon_attesters_for_epoch<T: SlotClock + 'static, E: EthSpec>(
+pub(crate) async fn poll_beacon_attesters_for_epoch<T: SlotClock + 'static, E: EthSpec>(
     duties_service: &Arc<DutiesService<T, E>>,
     epoch: Epoch,
     local_indices: &[u64],
@@ -719,7 +1457,7 @@ async fn poll_beacon_attesters_for_epoch<T: SlotClock + 'static, E: EthSpec>(
         .filter_map(|pubkey| duties_service.validator_store.validator_index(pubkey))
         .collect::<Vec<_>>();
 
-    let new_duties = if !indices_to_request.is_empty() {
+    let mut new_duties = if !indices_to_request.is_empty() {
         post_validator_duties_attester(duties_service, epoch, indices_to_request.as_slice())
             .await?
             .data
@@ -732,6 +1470,17 @@ async fn poll_beacon_attesters_for_epoch<T: SlotClock + 'static, E: EthSpec>(
 
     drop(fetch_timer);
 
+    let num_duplicates = dedup_attester_duties(&mut new_duties);
+    if num_duplicates > 0 {
+        warn!(
+            log,
+            "Beacon node returned duplicate attester duties";
+            "msg" => "the duplicates are ignored, the beacon node may be faulty",
+            "num_duplicates" => num_duplicates,
+            "epoch" => epoch,
+        );
+    }
+
     let _store_timer = metrics::start_timer_vec(
         &metrics::DUTIES_SERVICE_TIMES,
         &[metrics::UPDATE_ATTESTERS_STORE],
@@ -784,6 +1533,18 @@ async fn poll_beacon_attesters_for_epoch<T: SlotClock + 'static, E: EthSpec>(
     Ok(())
 }
 
+/// Removes each duty which repeats an earlier duty of the same validator, slot and committee,
+/// returning the number of duties removed.
+///
+/// A beacon node should never return such duplicates. Keeping them would compute the selection
+/// proof and subscription of the same assignment more than once.
+fn dedup_attester_duties(duties: &mut Vec<AttesterData>) -> usize {
+    let num_duties = duties.len();
+    let mut assignments = HashSet::with_capacity(num_duties);
+    duties.retain(|duty| assignments.insert((duty.pubkey, duty.slot, duty.committee_index)));
+    num_duties - duties.len()
+}
+
 /// Get a filtered list of local validators for which we don't already know their duties for that epoch
 fn get_uninitialized_validators<T: SlotClock + 'static, E: EthSpec>(
     duties_service: &Arc<DutiesService<T, E>>,
@@ -843,6 +1604,47 @@ fn update_per_validator_duty_metrics<T: SlotClock + 'static, E: EthSpec>(
     }
 }
 
+/// Publishes the number of validators and the earliest upcoming attestation duty slot of each
+/// metrics bucket.
+fn update_bucketed_duty_metrics<T: SlotClock + 'static, E: EthSpec>(
+    duties_service: &Arc<DutiesService<T, E>>,
+    current_slot: Slot,
+) {
+    let mut next_duty_slots = [None::<Slot>; VALIDATOR_METRICS_BUCKETS];
+    for (pubkey, attester_duties_by_epoch) in duties_service.attesters.read().iter() {
+        let next_duty_slot = &mut next_duty_slots[validator_metrics_bucket(pubkey)];
+        for (_, duty_and_proof) in attester_duties_by_epoch.values() {
+            let duty_slot = duty_and_proof.duty.slot;
+            if duty_slot >= current_slot && next_duty_slot.map_or(true, |slot| duty_slot < slot) {
+                *next_duty_slot = Some(duty_slot);
+            }
+        }
+    }
+
+    let mut validator_counts = [0; VALIDATOR_METRICS_BUCKETS];
+    duties_service
+        .validator_store
+        .voting_pubkeys::<Vec<_>, _>(DoppelgangerStatus::ignored)
+        .iter()
+        .for_each(|pubkey| validator_counts[validator_metrics_bucket(pubkey)] += 1);
+
+    for (bucket, (validator_count, next_duty_slot)) in validator_counts
+        .into_iter()
+        .zip(next_duty_slots)
+        .enumerate()
+    {
+        let label = format!("{:x}", bucket);
+        set_int_gauge(&VALIDATORS_PER_METRICS_BUCKET, &[&label], validator_count);
+        if let Some(next_duty_slot) = next_duty_slot {
+            set_int_gauge(
+                &ATTESTATION_DUTY_BUCKET,
+                &[&label],
+                next_duty_slot.as_u64() as i64,
+            );
+        }
+    }
+}
+
 async fn post_validator_duties_attester<T: SlotClock + 'static, E: EthSpec>(
     duties_service: &Arc<DutiesService<T, E>>,
     epoch: Epoch,
@@ -858,15 +1660,44 @@ async fn post_validator_duties_attester<T: SlotClock + 'static, E: EthSpec>(
                     &metrics::DUTIES_SERVICE_TIMES,
                     &[metrics::ATTESTER_DUTIES_HTTP_POST],
                 );
-                beacon_node
-                    .post_validator_duties_attester(epoch, validator_indices)
-                    .await
+                let responses = duties_service
+                    .duties_batch_sizes
+                    .request(
+                        beacon_node,
+                        validator_indices,
+                        |batch| beacon_node.post_validator_duties_attester(epoch, batch),
+                        duties_service.context.log(),
+                    )
+                    .await?;
+                Ok(merge_attester_duties_responses(responses))
             },
         )
         .await
         .map_err(|e| Error::FailedToDownloadAttesters(e.to_string()))
 }
 
+/// Merges the responses to batches of an attester duties request.
+///
+/// The dependent root of the first batch is used. If a re-org changed it part way through the
+/// request then the next poll sees a different dependent root and requests the duties again.
+fn merge_attester_duties_responses(
+    responses: Vec<DutiesResponse<Vec<AttesterData>>>,
+) -> DutiesResponse<Vec<AttesterData>> {
+    let mut responses = responses.into_iter();
+    let mut merged = responses.next().unwrap_or(DutiesResponse {
+        dependent_root: Hash256::zero(),
+        execution_optimistic: None,
+        data: vec![],
+    });
+    for response in responses {
+        if response.execution_optimistic == Some(true) {
+            merged.execution_optimistic = Some(true);
+        }
+        merged.data.extend(response.data);
+    }
+    merged
+}
+
 /// Compute the attestation selection proofs for the `duties` and add them to the `attesters` map.
 ///
 /// Duties are computed in batches each slot. If a re-org is detected then the process will
@@ -1169,6 +2000,22 @@ async fn notify_block_production_service<T: SlotClock + 'static, E: EthSpec>(
         .copied()
         .collect::<Vec<_>>();
 
+    let doppelganger_proposers = block_proposers.len() - non_doppelganger_proposers.len();
+    if doppelganger_proposers > 0 {
+        debug!(
+            log,
+            "Not proposing during doppelganger protection";
+            "skip_reason" => SkipReason::Doppelganger.as_str(),
+            "proposers" => doppelganger_proposers,
+            "slot" => current_slot,
+        );
+        skip_reason::record_skipped_duties(
+            SkipReason::Doppelganger,
+            metrics::BEACON_BLOCK,
+            doppelganger_proposers,
+        );
+    }
+
     if !non_doppelganger_proposers.is_empty() {
         if let Err(e) = block_service_tx
             .send(BlockServiceNotification {
@@ -1186,3 +2033,32 @@ async fn notify_block_production_service<T: SlotClock + 'static, E: EthSpec>(
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_validators_are_polled_near_activation() {
+        let current_epoch = Epoch::new(10);
+        let poll = |known, full_poll| needs_status_poll(known, current_epoch, 1, full_poll);
+        let pending =
+            |activation_epoch| Some((ValidatorStatus::PendingQueued, Epoch::new(activation_epoch)));
+
+        // Unknown and distant pending validators are only polled once per epoch.
+        assert!(poll(None, true));
+        assert!(!poll(None, false));
+        assert!(poll(pending(u64::MAX), true));
+        assert!(!poll(pending(u64::MAX), false));
+        assert!(!poll(pending(12), false));
+
+        // Pending validators close to activation are polled every slot.
+        assert!(poll(pending(11), false));
+        assert!(poll(pending(10), false));
+
+        // Active validators are not polled again.
+        let active = Some((ValidatorStatus::ActiveOngoing, Epoch::new(10)));
+        assert!(!poll(active, true));
+        assert!(!poll(active, false));
+    }
+}
@@ -0,0 +1,223 @@
+//! Splits duties requests into batches which the beacon node is willing to accept.
+//!
+//! Some beacon nodes limit the number of validator indices in a single duties request. Requests
+//! are split into batches of at most `duties_batch_size` indices, and whenever a beacon node
+//! refuses a batch as too large the batch size is halved and remembered for that node.
+
+use eth2::{BeaconNodeHttpClient, Error as ApiError, StatusCode};
+use parking_lot::Mutex;
+use slog::{warn, Logger};
+use std::cmp::min;
+use std::collections::HashMap;
+use std::future::Future;
+
+/// The number of validator indices sent in each duties request, per beacon node.
+pub struct DutiesBatchSizes {
+    /// The batch size used for a beacon node which has not refused a batch.
+    default: usize,
+    /// The reduced batch size of each beacon node which has refused a batch, keyed by its full
+    /// URL, since the redacted URLs of nodes which differ only in their path are the same.
+    per_node: Mutex<HashMap<String, usize>>,
+}
+
+impl DutiesBatchSizes {
+    pub fn new(default: usize) -> Self {
+        Self {
+            default: default.max(1),
+            per_node: <_>::default(),
+        }
+    }
+
+    /// Returns the batch size currently used for `beacon_node`.
+    pub fn batch_size(&self, beacon_node: &BeaconNodeHttpClient) -> usize {
+        self.per_node
+            .lock()
+            .get(beacon_node.server().full.as_str())
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Calls `request` with successive batches of `validator_indices`, returning the responses in
+    /// order.
+    ///
+    /// If `beacon_node` refuses a batch as too large, the batch size is halved for all future
+    /// requests to it and the batch is retried. A batch of a single index which is refused fails
+    /// the whole request, as does any other error.
+    pub async fn request<'a, F, R, O>(
+        &self,
+        beacon_node: &BeaconNodeHttpClient,
+        validator_indices: &'a [u64],
+        request: F,
+        log: &Logger,
+    ) -> Result<Vec<O>, ApiError>
+    where
+        F: Fn(&'a [u64]) -> R,
+        R: Future<Output = Result<O, ApiError>>,
+    {
+        let mut batch_size = self.batch_size(beacon_node);
+        let mut responses = vec![];
+        let mut remaining = validator_indices;
+
+        while !remaining.is_empty() {
+            let (batch, rest) = remaining.split_at(min(batch_size, remaining.len()));
+            match request(batch).await {
+                Ok(response) => {
+                    responses.push(response);
+                    remaining = rest;
+                }
+                Err(e) if batch.len() > 1 && is_request_too_large(&e) => {
+                    batch_size = batch.len() / 2;
+                    warn!(
+                        log,
+                        "Reducing duties batch size";
+                        "msg" => "the beacon node refused a request with too many validators",
+                        "batch_size" => batch_size,
+                        "previous_batch_size" => batch.len(),
+                        "node" => %beacon_node,
+                    );
+                    self.per_node
+                        .lock()
+                        .insert(beacon_node.server().full.to_string(), batch_size);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(responses)
+    }
+}
+
+/// Returns `true` if the beacon node refused a request because it contained too many items.
+///
+/// Beacon nodes either respond with `413 Payload Too Large`, or with `400 Bad Request` and a
+/// message stating that there are too many items.
+fn is_request_too_large(error: &ApiError) -> bool {
+    match error.status() {
+        Some(StatusCode::PAYLOAD_TOO_LARGE) => true,
+        Some(StatusCode::BAD_REQUEST) => match error {
+            ApiError::ServerMessage(msg) => {
+                let message = msg.message.to_lowercase();
+                message.contains("too many") || message.contains("too large")
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth2::types::ErrorMessage;
+    use eth2::Timeouts;
+    use logging::test_logger;
+    use sensitive_url::SensitiveUrl;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn beacon_node(url: &str) -> BeaconNodeHttpClient {
+        BeaconNodeHttpClient::new(
+            SensitiveUrl::parse(url).unwrap(),
+            Timeouts::set_all(Duration::from_secs(1)),
+        )
+    }
+
+    /// Simulates a beacon node which responds with a 413 to batches larger than `limit`.
+    async fn limited_request(
+        batch: &[u64],
+        limit: usize,
+        requests: &AtomicUsize,
+    ) -> Result<Vec<u64>, ApiError> {
+        requests.fetch_add(1, Ordering::SeqCst);
+        if batch.len() > limit {
+            Err(ApiError::StatusCode(StatusCode::PAYLOAD_TOO_LARGE))
+        } else {
+            Ok(batch.to_vec())
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_size_is_reduced_on_payload_too_large() {
+        let log = test_logger();
+        let batch_sizes = DutiesBatchSizes::new(8);
+        let indices = (0..10).collect::<Vec<u64>>();
+        let requests = AtomicUsize::new(0);
+        // The redacted URLs of these nodes are the same.
+        let node_a = beacon_node("http://localhost:5052/node_a");
+        let node_b = beacon_node("http://localhost:5052/node_b");
+        assert_eq!(node_a.to_string(), node_b.to_string());
+
+        // Batches of 8 and 4 are refused, then 5 batches of 2 succeed.
+        let responses = batch_sizes
+            .request(
+                &node_a,
+                &indices,
+                |batch| limited_request(batch, 3, &requests),
+                &log,
+            )
+            .await
+            .unwrap();
+        assert_eq!(responses.concat(), indices);
+        assert_eq!(requests.load(Ordering::SeqCst), 7);
+
+        // The reduced batch size is remembered for the node, but not used for other nodes.
+        assert_eq!(batch_sizes.batch_size(&node_a), 2);
+        assert_eq!(batch_sizes.batch_size(&node_b), 8);
+
+        requests.store(0, Ordering::SeqCst);
+        let responses = batch_sizes
+            .request(
+                &node_a,
+                &indices,
+                |batch| limited_request(batch, 3, &requests),
+                &log,
+            )
+            .await
+            .unwrap();
+        assert_eq!(responses.len(), 5);
+        assert_eq!(requests.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn single_index_refusal_fails() {
+        let log = test_logger();
+        let batch_sizes = DutiesBatchSizes::new(4);
+        let requests = AtomicUsize::new(0);
+        let node = beacon_node("http://localhost:5052");
+
+        let result = batch_sizes
+            .request(
+                &node,
+                &[1, 2, 3],
+                |batch| limited_request(batch, 0, &requests),
+                &log,
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(batch_sizes.batch_size(&node), 1);
+    }
+
+    #[test]
+    fn too_large_errors() {
+        let bad_request = |message: &str| {
+            ApiError::ServerMessage(ErrorMessage {
+                code: 400,
+                message: message.to_string(),
+                stacktraces: vec![],
+            })
+        };
+
+        assert!(is_request_too_large(&ApiError::StatusCode(
+            StatusCode::PAYLOAD_TOO_LARGE
+        )));
+        assert!(is_request_too_large(&bad_request(
+            "BAD_REQUEST: too many validator indices"
+        )));
+        assert!(!is_request_too_large(&bad_request(
+            "BAD_REQUEST: invalid validator index"
+        )));
+        assert!(!is_request_too_large(&ApiError::StatusCode(
+            StatusCode::INTERNAL_SERVER_ERROR
+        )));
+    }
+}
@@ -0,0 +1,226 @@
+//! A one-shot mode which prints the upcoming duties of all local validators along with the times
+//! at which this validator client will perform them.
+//!
+//! Duties are fetched from the beacon node without updating any of the maps in the
+//! `DutiesService`, subscribing to subnets or signing anything.
+
+use crate::beacon_node_fallback::{OfflineOnFailure, RequireSynced};
+use crate::duties_service::{
+    poll_validator_indices, post_validator_duties_attester, DutiesService, Error,
+};
+use crate::validator_store::DoppelgangerStatus;
+use chrono::{DateTime, Utc};
+use slot_clock::SlotClock;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use types::{Epoch, EthSpec, PublicKeyBytes, Slot};
+
+/// A duty which the validator client performs at a fixed time within a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DutyKind {
+    /// Block proposal, at the start of the slot.
+    Propose,
+    /// Attestation, one third of the way through the slot.
+    Attest,
+    /// Attestation aggregation, two thirds of the way through the slot.
+    Aggregate,
+}
+
+impl DutyKind {
+    /// Returns the time from the start of the slot at which the duty is performed.
+    fn offset(self, slot_duration: Duration) -> Duration {
+        match self {
+            DutyKind::Propose => Duration::from_secs(0),
+            DutyKind::Attest => slot_duration / 3,
+            DutyKind::Aggregate => slot_duration * 2 / 3,
+        }
+    }
+}
+
+impl fmt::Display for DutyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DutyKind::Propose => write!(f, "propose"),
+            DutyKind::Attest => write!(f, "attest"),
+            DutyKind::Aggregate => write!(f, "aggregate (if selected)"),
+        }
+    }
+}
+
+struct ScheduledDuty {
+    slot: Slot,
+    kind: DutyKind,
+    validator_index: u64,
+    pubkey: PublicKeyBytes,
+}
+
+/// Formats `duration` since the UNIX epoch as a UTC timestamp.
+fn format_time(duration: Duration) -> String {
+    DateTime::<Utc>::from(UNIX_EPOCH + duration)
+        .format("%Y-%m-%d %H:%M:%S%.3f UTC")
+        .to_string()
+}
+
+/// Fetches the duties of all local validators for the current and next epoch and prints them,
+/// along with the time at which each will be performed, to stdout.
+pub async fn print_duty_schedule<T: SlotClock + 'static, E: EthSpec>(
+    duties_service: &Arc<DutiesService<T, E>>,
+) -> Result<(), Error> {
+    poll_validator_indices(duties_service).await;
+
+    let slot_clock = &duties_service.slot_clock;
+    let current_slot = slot_clock.now().ok_or(Error::UnableToReadSlotClock)?;
+    let current_epoch = current_slot.epoch(E::slots_per_epoch());
+    let epochs = [current_epoch, current_epoch + 1];
+
+    let local_pubkeys: HashSet<_> = duties_service
+        .validator_store
+        .voting_pubkeys(DoppelgangerStatus::ignored);
+    let local_indices = {
+        let vals_ref = duties_service.validator_store.initialized_validators();
+        let vals = vals_ref.read();
+        local_pubkeys
+            .iter()
+            .filter_map(|pubkey| vals.get_index(pubkey))
+            .collect::<Vec<_>>()
+    };
+
+    println!(
+        "Duties for epochs {} and {} (current slot {}, {} of {} validators known to the beacon chain)",
+        epochs[0],
+        epochs[1],
+        current_slot,
+        local_indices.len(),
+        local_pubkeys.len()
+    );
+    if local_indices.is_empty() {
+        println!("No local validators have an index, there are no duties to print.");
+        return Ok(());
+    }
+
+    let mut duties = vec![];
+    let mut unavailable = vec![];
+
+    for epoch in epochs {
+        let attesters = post_validator_duties_attester(duties_service, epoch, &local_indices)
+            .await?
+            .data;
+        for duty in attesters {
+            for kind in [DutyKind::Attest, DutyKind::Aggregate] {
+                duties.push(ScheduledDuty {
+                    slot: duty.slot,
+                    kind,
+                    validator_index: duty.validator_index,
+                    pubkey: duty.pubkey,
+                });
+            }
+        }
+
+        match duties_service
+            .beacon_nodes
+            .first_success(
+                RequireSynced::No,
+                OfflineOnFailure::Yes,
+                |beacon_node| async move { beacon_node.get_validator_duties_proposer(epoch).await },
+            )
+            .await
+        {
+            Ok(response) => duties.extend(
+                response
+                    .data
+                    .into_iter()
+                    .filter(|proposer| local_pubkeys.contains(&proposer.pubkey))
+                    .map(|proposer| ScheduledDuty {
+                        slot: proposer.slot,
+                        kind: DutyKind::Propose,
+                        validator_index: proposer.validator_index,
+                        pubkey: proposer.pubkey,
+                    }),
+            ),
+            // Beacon nodes are not required to serve proposer duties for the next epoch.
+            Err(e) => unavailable.push(format!("proposals in epoch {}: {}", epoch, e)),
+        }
+    }
+
+    duties.retain(|duty| duty.slot >= current_slot);
+    duties.sort_by_key(|duty| (duty.slot, duty.kind, duty.validator_index));
+
+    let slot_duration = slot_clock.slot_duration();
+    for duty in &duties {
+        let time = slot_clock
+            .start_of(duty.slot)
+            .map(|start| format_time(start + duty.kind.offset(slot_duration)))
+            .unwrap_or_else(|| "unknown".to_string());
+        println!(
+            "slot {:>10}  {}  {:<24} validator {} ({:?})",
+            duty.slot,
+            time,
+            duty.kind.to_string(),
+            duty.validator_index,
+            duty.pubkey
+        );
+    }
+    if duties.is_empty() {
+        println!("No upcoming attestation or proposal duties.");
+    }
+
+    print_sync_committee_duties(duties_service, current_epoch, &local_indices).await;
+
+    for duty in unavailable {
+        println!("Unable to fetch {}", duty);
+    }
+
+    Ok(())
+}
+
+/// Prints the local members of the current sync committee, which sign a message in every slot.
+async fn print_sync_committee_duties<T: SlotClock + 'static, E: EthSpec>(
+    duties_service: &Arc<DutiesService<T, E>>,
+    current_epoch: Epoch,
+    local_indices: &[u64],
+) {
+    let spec = &duties_service.spec;
+    if spec
+        .altair_fork_epoch
+        .map_or(true, |altair_epoch| current_epoch < altair_epoch)
+    {
+        return;
+    }
+
+    let period_end_epoch = current_epoch
+        .sync_committee_period(spec)
+        .map(|period| spec.epochs_per_sync_committee_period * (period + 1) - 1);
+
+    match duties_service
+        .beacon_nodes
+        .first_success(
+            RequireSynced::No,
+            OfflineOnFailure::Yes,
+            |beacon_node| async move {
+                beacon_node
+                    .post_validator_duties_sync(current_epoch, local_indices)
+                    .await
+            },
+        )
+        .await
+    {
+        Ok(response) => {
+            let offset = duties_service.slot_clock.slot_duration() / 3;
+            for duty in response.data {
+                println!(
+                    "sync committee: validator {} ({:?}) signs in every slot until the end of \
+                     epoch {}, {}s into the slot",
+                    duty.validator_index,
+                    duty.pubkey,
+                    period_end_epoch
+                        .map(|epoch| epoch.to_string())
+                        .unwrap_or_else(|_| "unknown".to_string()),
+                    offset.as_secs_f64()
+                );
+            }
+        }
+        Err(e) => println!("Unable to fetch sync committee duties: {}", e),
+    }
+}
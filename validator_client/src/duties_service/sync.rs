@@ -226,6 +226,21 @@ impl SyncDutiesMap {
         })
     }
 
+    /// Returns the duties of all local members of the sync committee for `committee_period`.
+    pub fn committee_members(&self, committee_period: u64) -> Vec<SyncDuty> {
+        self.committees
+            .read()
+            .get(&committee_period)
+            .map_or_else(Vec::new, |committee_duties| {
+                committee_duties
+                    .validators
+                    .read()
+                    .values()
+                    .filter_map(|opt_duties| Some(opt_duties.as_ref()?.duty.clone()))
+                    .collect()
+            })
+    }
+
     /// Prune duties for past sync committee periods from the map.
     fn prune(&self, current_sync_committee_period: u64) {
         self.committees
@@ -425,15 +440,24 @@ pub async fn poll_sync_committee_duties_for_period<T: SlotClock + 'static, E: Et
             RequireSynced::No,
             OfflineOnFailure::Yes,
             |beacon_node| async move {
-                beacon_node
-                    .post_validator_duties_sync(period_start_epoch, local_indices)
+                duties_service
+                    .duties_batch_sizes
+                    .request(
+                        beacon_node,
+                        local_indices,
+                        |batch| beacon_node.post_validator_duties_sync(period_start_epoch, batch),
+                        duties_service.context.log(),
+                    )
                     .await
             },
         )
         .await;
 
     let duties = match duties_response {
-        Ok(res) => res.data,
+        Ok(responses) => responses
+            .into_iter()
+            .flat_map(|response| response.data)
+            .collect::<Vec<_>>(),
         Err(e) => {
             warn!(
                 log,
@@ -0,0 +1,586 @@
+use crate::beacon_node_fallback::{BeaconNodeFallback, OfflineOnFailure, RequireSynced};
+use crate::validator_store::ValidatorStore;
+use environment::RuntimeContext;
+use serde_derive::{Deserialize, Serialize};
+use slog::{debug, info, warn, Logger};
+use slot_clock::SlotClock;
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use strum::{Display, EnumString, EnumVariantNames};
+use tokio::time::sleep;
+use types::{ChainSpec, Epoch, EthSpec, Fork, ForkName};
+
+/// The action to take when the fork version used for signing does not match the fork version
+/// reported by the beacon node.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum OnForkVersionMismatch {
+    /// Log the mismatch and sign anyway.
+    #[default]
+    Warn,
+    /// Log the mismatch and refuse to sign.
+    Refuse,
+}
+
+/// The fork version used for signing differs from the one the beacon node expects.
+#[derive(Debug, PartialEq)]
+pub struct ForkVersionMismatch {
+    pub epoch: Epoch,
+    pub fork_version: [u8; 4],
+    pub beacon_node_fork_version: [u8; 4],
+}
+
+/// Returns the fork version that `fork_schedule` prescribes for `epoch`, i.e. the current version
+/// of the latest fork activated at or before `epoch`.
+///
+/// Returns `None` if no fork in the schedule has activated by `epoch`.
+pub fn fork_version_at_epoch(fork_schedule: &[Fork], epoch: Epoch) -> Option<[u8; 4]> {
+    fork_schedule
+        .iter()
+        .filter(|fork| fork.epoch <= epoch)
+        .max_by_key(|fork| fork.epoch)
+        .map(|fork| fork.current_version)
+}
+
+/// Checks that the version of `fork` at `epoch` matches the version prescribed by the beacon
+/// node's `fork_schedule`.
+///
+/// An empty or not-yet-active schedule is considered to match, since there is nothing to compare
+/// against.
+pub fn check_fork_version(
+    fork: &Fork,
+    beacon_node_fork_schedule: &[Fork],
+    epoch: Epoch,
+) -> Result<(), ForkVersionMismatch> {
+    let beacon_node_fork_version = match fork_version_at_epoch(beacon_node_fork_schedule, epoch) {
+        Some(fork_version) => fork_version,
+        None => return Ok(()),
+    };
+    let fork_version = fork.get_fork_version(epoch);
+
+    if fork_version == beacon_node_fork_version {
+        Ok(())
+    } else {
+        Err(ForkVersionMismatch {
+            epoch,
+            fork_version,
+            beacon_node_fork_version,
+        })
+    }
+}
+
+/// Returns the fork in `spec` which activates within `window` epochs of `epoch`, along with its
+/// activation epoch.
+///
+/// If several forks are that close, the earliest is returned.
+pub fn fork_in_transition_window(
+    spec: &ChainSpec,
+    epoch: Epoch,
+    window: u64,
+) -> Option<(ForkName, Epoch)> {
+    ForkName::list_all()
+        .into_iter()
+        .filter(|fork| *fork != ForkName::Base)
+        .filter_map(|fork| Some((fork, spec.fork_epoch(fork)?)))
+        .find(|(_, fork_epoch)| {
+            epoch.as_u64().saturating_add(window) >= fork_epoch.as_u64()
+                && fork_epoch.as_u64().saturating_add(window) >= epoch.as_u64()
+        })
+}
+
+/// Replaces the version and activation epoch of a single fork in the spec used for signing.
+///
+/// This is *not* recommended in prod and should only be used for testing or on custom networks.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ForkOverride {
+    pub fork: ForkName,
+    #[serde(with = "serde_utils::bytes_4_hex")]
+    pub version: [u8; 4],
+    /// The epoch at which the fork activates, or `None` if it is not scheduled.
+    ///
+    /// Must be zero (or omitted) for phase0.
+    #[serde(default)]
+    pub epoch: Option<Epoch>,
+}
+
+/// Reads a list of `ForkOverride` from the JSON or YAML file at `path`.
+pub fn load_fork_schedule_override<P: AsRef<Path>>(path: P) -> Result<Vec<ForkOverride>, String> {
+    let file = File::open(path.as_ref()).map_err(|e| {
+        format!(
+            "Unable to open fork schedule override {:?}: {:?}",
+            path.as_ref(),
+            e
+        )
+    })?;
+    serde_yaml::from_reader(file).map_err(|e| {
+        format!(
+            "Unable to parse fork schedule override {:?}: {:?}",
+            path.as_ref(),
+            e
+        )
+    })
+}
+
+/// Applies `overrides` to `spec`, after checking that the resulting fork schedule is consistent.
+///
+/// Forks which are not listed in `overrides` keep the values from `spec`.
+pub fn apply_fork_schedule_override(
+    spec: &mut ChainSpec,
+    overrides: &[ForkOverride],
+) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for fork_override in overrides {
+        if !seen.insert(fork_override.fork) {
+            return Err(format!(
+                "Fork {} is overridden more than once",
+                fork_override.fork
+            ));
+        }
+    }
+
+    let mut new_spec = spec.clone();
+    for fork_override in overrides {
+        let version = fork_override.version;
+        let epoch = fork_override.epoch;
+        match fork_override.fork {
+            ForkName::Base => {
+                if epoch.map_or(false, |epoch| epoch != 0) {
+                    return Err("The phase0 fork must activate at epoch 0".to_string());
+                }
+                new_spec.genesis_fork_version = version;
+            }
+            ForkName::Altair => {
+                new_spec.altair_fork_version = version;
+                new_spec.altair_fork_epoch = epoch;
+            }
+            ForkName::Merge => {
+                new_spec.bellatrix_fork_version = version;
+                new_spec.bellatrix_fork_epoch = epoch;
+            }
+            ForkName::Capella => {
+                new_spec.capella_fork_version = version;
+                new_spec.capella_fork_epoch = epoch;
+            }
+        }
+    }
+
+    // Every scheduled fork must follow a scheduled predecessor, at the same epoch or later, and
+    // have a version distinct from the versions of all other scheduled forks.
+    let mut previous: Option<(ForkName, Epoch)> = None;
+    let mut versions = HashSet::new();
+    for fork in ForkName::list_all() {
+        let epoch = match new_spec.fork_epoch(fork) {
+            Some(epoch) => epoch,
+            None => {
+                if let Some(later_fork) = ForkName::list_all()
+                    .into_iter()
+                    .skip_while(|f| *f != fork)
+                    .find(|f| new_spec.fork_epoch(*f).is_some())
+                {
+                    return Err(format!(
+                        "Fork {} is scheduled but its predecessor {} is not",
+                        later_fork, fork
+                    ));
+                }
+                break;
+            }
+        };
+        if let Some((previous_fork, previous_epoch)) = previous {
+            if epoch < previous_epoch {
+                return Err(format!(
+                    "Fork {} at epoch {} is scheduled before fork {} at epoch {}",
+                    fork, epoch, previous_fork, previous_epoch
+                ));
+            }
+        }
+        let version = new_spec.fork_version_for_name(fork);
+        if !versions.insert(version) {
+            return Err(format!(
+                "Fork {} has the same version as an earlier fork: 0x{}",
+                fork,
+                hex::encode(version)
+            ));
+        }
+        previous = Some((fork, epoch));
+    }
+
+    *spec = new_spec;
+    Ok(())
+}
+
+/// Starts a service that fetches the fork schedule from the beacon nodes once per epoch and
+/// provides it to the `validator_store` for comparison when signing.
+///
+/// Within the fork transition window of the `validator_store`, the fork schedule is fetched once
+/// per slot instead, so that a disagreement with the beacon node is noticed promptly.
+pub fn start_fork_schedule_service<T: SlotClock + 'static, E: EthSpec>(
+    context: RuntimeContext<E>,
+    slot_clock: T,
+    beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
+    validator_store: Arc<ValidatorStore<T, E>>,
+) {
+    let log = context.log().clone();
+
+    let future = async move {
+        let mut previous_epoch: Option<Epoch> = None;
+        let mut previous_transition: Option<(ForkName, Epoch)> = None;
+        let mut previous_mismatch: Option<ForkVersionMismatch> = None;
+
+        loop {
+            match beacon_nodes
+                .first_success(
+                    RequireSynced::No,
+                    OfflineOnFailure::Yes,
+                    |beacon_node| async move { beacon_node.get_config_fork_schedule().await },
+                )
+                .await
+            {
+                Ok(response) => {
+                    debug!(
+                        log,
+                        "Updated beacon node fork schedule";
+                        "forks" => response.data.len(),
+                    );
+                    validator_store.set_beacon_node_fork_schedule(response.data);
+                }
+                Err(e) => warn!(
+                    log,
+                    "Unable to read fork schedule from beacon node";
+                    "error" => %e,
+                ),
+            }
+
+            let epoch = slot_clock
+                .now()
+                .map(|slot| slot.epoch(E::slots_per_epoch()));
+            let transition = epoch.and_then(|epoch| validator_store.fork_transition(epoch));
+
+            if let Some(epoch) = epoch {
+                log_fork_transition(
+                    &log,
+                    &validator_store,
+                    epoch,
+                    previous_epoch,
+                    transition,
+                    previous_transition,
+                    &mut previous_mismatch,
+                );
+                previous_epoch = Some(epoch);
+            }
+            previous_transition = transition;
+
+            // If we can't read the slot clock, just wait one epoch.
+            let sleep_time = if transition.is_some() {
+                slot_clock.duration_to_next_slot()
+            } else {
+                slot_clock.duration_to_next_epoch(E::slots_per_epoch())
+            }
+            .unwrap_or_else(|| slot_clock.slot_duration() * E::slots_per_epoch() as u32);
+            sleep(sleep_time).await;
+        }
+    };
+
+    context.executor.spawn(future, "fork_schedule");
+}
+
+/// Logs entering and leaving a fork transition window, the activation of each fork, and whether
+/// the beacon node agrees with the fork version to be used at the fork.
+fn log_fork_transition<T: SlotClock + 'static, E: EthSpec>(
+    log: &Logger,
+    validator_store: &ValidatorStore<T, E>,
+    epoch: Epoch,
+    previous_epoch: Option<Epoch>,
+    transition: Option<(ForkName, Epoch)>,
+    previous_transition: Option<(ForkName, Epoch)>,
+    previous_mismatch: &mut Option<ForkVersionMismatch>,
+) {
+    if transition != previous_transition {
+        if let Some((fork, fork_epoch)) = previous_transition {
+            info!(
+                log,
+                "Leaving fork transition window";
+                "fork" => %fork,
+                "fork_epoch" => fork_epoch,
+            );
+        }
+        if let Some((fork, fork_epoch)) = transition {
+            info!(
+                log,
+                "Entering fork transition window";
+                "msg" => "checking the beacon node fork version every slot and pausing \
+                          proposals if it differs",
+                "fork" => %fork,
+                "fork_epoch" => fork_epoch,
+                "current_epoch" => epoch,
+            );
+        }
+    }
+
+    if let Some(previous_epoch) = previous_epoch {
+        let (fork, fork_version) = validator_store.signing_fork(epoch);
+        if validator_store.signing_fork(previous_epoch).0 != fork {
+            info!(
+                log,
+                "Fork activated";
+                "fork" => %fork,
+                "fork_version" => format!("0x{}", hex::encode(fork_version)),
+                "epoch" => epoch,
+            );
+        }
+    }
+
+    if let Some((fork, fork_epoch)) = transition {
+        // Check the later of the fork and the current epoch, so that a fork which has already
+        // activated is compared against the current state of the beacon node.
+        let check_epoch = std::cmp::max(epoch, fork_epoch);
+        match validator_store.check_beacon_node_fork_version(check_epoch) {
+            Some(Ok(())) => {
+                if previous_mismatch.take().is_some() {
+                    info!(
+                        log,
+                        "Beacon node fork version agrees";
+                        "msg" => "resuming proposals",
+                        "fork" => %fork,
+                        "epoch" => check_epoch,
+                    );
+                }
+            }
+            Some(Err(mismatch)) => {
+                if previous_mismatch.as_ref() != Some(&mismatch) {
+                    warn!(
+                        log,
+                        "Beacon node fork version differs";
+                        "msg" => "proposals are paused while the versions differ, ensure the \
+                                  validator client and beacon node are both up to date",
+                        "fork" => %fork,
+                        "epoch" => mismatch.epoch,
+                        "fork_version" => ?mismatch.fork_version,
+                        "beacon_node_fork_version" => ?mismatch.beacon_node_fork_version,
+                    );
+                }
+                *previous_mismatch = Some(mismatch);
+            }
+            None => {}
+        }
+    } else {
+        *previous_mismatch = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHASE0: [u8; 4] = [0, 0, 0, 0];
+    const ALTAIR: [u8; 4] = [1, 0, 0, 0];
+    const BELLATRIX: [u8; 4] = [2, 0, 0, 0];
+
+    fn fork(previous_version: [u8; 4], current_version: [u8; 4], epoch: u64) -> Fork {
+        Fork {
+            previous_version,
+            current_version,
+            epoch: Epoch::new(epoch),
+        }
+    }
+
+    fn schedule() -> Vec<Fork> {
+        vec![
+            fork(PHASE0, PHASE0, 0),
+            fork(PHASE0, ALTAIR, 10),
+            fork(ALTAIR, BELLATRIX, 20),
+        ]
+    }
+
+    #[test]
+    fn fork_version_at_epoch_follows_schedule() {
+        let schedule = schedule();
+        for (epoch, expected) in [
+            (0, PHASE0),
+            (9, PHASE0),
+            (10, ALTAIR),
+            (19, ALTAIR),
+            (20, BELLATRIX),
+            (u64::MAX, BELLATRIX),
+        ] {
+            assert_eq!(
+                fork_version_at_epoch(&schedule, Epoch::new(epoch)),
+                Some(expected),
+                "epoch {}",
+                epoch
+            );
+        }
+    }
+
+    #[test]
+    fn fork_version_at_epoch_ignores_order() {
+        let mut schedule = schedule();
+        schedule.reverse();
+        assert_eq!(
+            fork_version_at_epoch(&schedule, Epoch::new(15)),
+            Some(ALTAIR)
+        );
+    }
+
+    #[test]
+    fn fork_version_at_epoch_before_schedule() {
+        assert_eq!(fork_version_at_epoch(&[], Epoch::new(0)), None);
+        assert_eq!(
+            fork_version_at_epoch(&[fork(PHASE0, ALTAIR, 10)], Epoch::new(9)),
+            None
+        );
+    }
+
+    #[test]
+    fn matching_fork_versions() {
+        let schedule = schedule();
+        assert_eq!(
+            check_fork_version(&fork(PHASE0, ALTAIR, 10), &schedule, Epoch::new(12)),
+            Ok(())
+        );
+        // Signing for an epoch prior to our fork uses the previous version.
+        assert_eq!(
+            check_fork_version(&fork(ALTAIR, BELLATRIX, 20), &schedule, Epoch::new(19)),
+            Ok(())
+        );
+        // Nothing to compare against.
+        assert_eq!(
+            check_fork_version(&fork(ALTAIR, BELLATRIX, 20), &[], Epoch::new(25)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn beacon_node_upgraded_but_not_validator_client() {
+        // The VC does not know about the Bellatrix fork.
+        let local_fork = fork(PHASE0, ALTAIR, 10);
+        assert_eq!(
+            check_fork_version(&local_fork, &schedule(), Epoch::new(20)),
+            Err(ForkVersionMismatch {
+                epoch: Epoch::new(20),
+                fork_version: ALTAIR,
+                beacon_node_fork_version: BELLATRIX,
+            })
+        );
+    }
+
+    fn fork_override(fork: ForkName, version: [u8; 4], epoch: Option<u64>) -> ForkOverride {
+        ForkOverride {
+            fork,
+            version,
+            epoch: epoch.map(Epoch::new),
+        }
+    }
+
+    #[test]
+    fn apply_valid_fork_schedule_override() {
+        let mut spec = ChainSpec::minimal();
+        apply_fork_schedule_override(
+            &mut spec,
+            &[
+                fork_override(ForkName::Base, [0, 0, 0, 0x42], None),
+                fork_override(ForkName::Altair, [1, 0, 0, 0x42], Some(1)),
+                fork_override(ForkName::Merge, [2, 0, 0, 0x42], Some(1)),
+                fork_override(ForkName::Capella, [3, 0, 0, 0x42], None),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(spec.genesis_fork_version, [0, 0, 0, 0x42]);
+        assert_eq!(spec.altair_fork_version, [1, 0, 0, 0x42]);
+        assert_eq!(spec.altair_fork_epoch, Some(Epoch::new(1)));
+        assert_eq!(spec.bellatrix_fork_version, [2, 0, 0, 0x42]);
+        assert_eq!(spec.bellatrix_fork_epoch, Some(Epoch::new(1)));
+        assert_eq!(spec.capella_fork_epoch, None);
+    }
+
+    #[test]
+    fn invalid_fork_schedule_override_leaves_spec_unchanged() {
+        let base_spec = || {
+            let mut spec = ChainSpec::minimal();
+            spec.altair_fork_epoch = Some(Epoch::new(10));
+            spec.bellatrix_fork_epoch = Some(Epoch::new(20));
+            spec.capella_fork_epoch = None;
+            spec
+        };
+
+        for overrides in [
+            // Duplicate entry.
+            vec![
+                fork_override(ForkName::Altair, [1, 0, 0, 1], Some(10)),
+                fork_override(ForkName::Altair, [1, 0, 0, 2], Some(10)),
+            ],
+            // Phase0 after genesis.
+            vec![fork_override(ForkName::Base, [0, 0, 0, 1], Some(1))],
+            // Bellatrix before Altair.
+            vec![fork_override(ForkName::Merge, [2, 0, 0, 1], Some(5))],
+            // Bellatrix without Altair.
+            vec![fork_override(ForkName::Altair, [1, 0, 0, 1], None)],
+            // Version reused.
+            vec![fork_override(
+                ForkName::Capella,
+                base_spec().bellatrix_fork_version,
+                Some(30),
+            )],
+        ] {
+            let mut spec = base_spec();
+            assert!(
+                apply_fork_schedule_override(&mut spec, &overrides).is_err(),
+                "{:?}",
+                overrides
+            );
+            assert_eq!(spec, base_spec());
+        }
+    }
+
+    #[test]
+    fn transition_window() {
+        let mut spec = ChainSpec::minimal();
+        spec.altair_fork_epoch = Some(Epoch::new(10));
+        spec.bellatrix_fork_epoch = Some(Epoch::new(20));
+        spec.capella_fork_epoch = None;
+
+        let transition =
+            |epoch, window| fork_in_transition_window(&spec, Epoch::new(epoch), window);
+        assert_eq!(transition(0, 2), None);
+        assert_eq!(transition(7, 2), None);
+        assert_eq!(transition(8, 2), Some((ForkName::Altair, Epoch::new(10))));
+        assert_eq!(transition(12, 2), Some((ForkName::Altair, Epoch::new(10))));
+        assert_eq!(transition(13, 2), None);
+        assert_eq!(transition(19, 0), None);
+        assert_eq!(transition(20, 0), Some((ForkName::Merge, Epoch::new(20))));
+        // The earliest fork is returned when windows overlap.
+        assert_eq!(transition(15, 5), Some((ForkName::Altair, Epoch::new(10))));
+        assert_eq!(
+            transition(u64::MAX, u64::MAX),
+            Some((ForkName::Altair, Epoch::new(10)))
+        );
+    }
+
+    #[test]
+    fn validator_client_upgraded_but_not_beacon_node() {
+        let local_fork = fork(ALTAIR, BELLATRIX, 20);
+        let beacon_node_schedule = vec![fork(PHASE0, PHASE0, 0), fork(PHASE0, ALTAIR, 10)];
+        assert_eq!(
+            check_fork_version(&local_fork, &beacon_node_schedule, Epoch::new(20)),
+            Err(ForkVersionMismatch {
+                epoch: Epoch::new(20),
+                fork_version: BELLATRIX,
+                beacon_node_fork_version: ALTAIR,
+            })
+        );
+    }
+}
@@ -4,9 +4,10 @@ use std::fs::File;
 use std::io::{prelude::*, BufReader};
 use std::path::PathBuf;
 use std::str::FromStr;
+use strum::{Display, EnumString, EnumVariantNames};
 
 use bls::PublicKeyBytes;
-use types::{graffiti::GraffitiString, Graffiti};
+use types::{graffiti::GraffitiString, ChainSpec, Epoch, ForkName, Graffiti};
 
 #[derive(Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -15,20 +16,84 @@ pub enum Error {
     InvalidLine(String),
     InvalidPublicKey(String),
     InvalidGraffiti(String),
+    InvalidSchedule(String),
+    DuplicatePublicKey(PublicKeyBytes),
+}
+
+/// The action to take when the graffiti file lists a public key more than once with different
+/// graffitis.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum OnDuplicateGraffiti {
+    /// Log a warning and use the last graffiti listed for the public key.
+    #[default]
+    LastWins,
+    /// Refuse to load the file.
+    Error,
+}
+
+/// The point from which a scheduled graffiti replaces the default graffiti.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GraffitiActivation {
+    Epoch(Epoch),
+    Fork(ForkName),
+}
+
+impl GraffitiActivation {
+    /// Returns the epoch at which this activation occurs, or `None` for a fork which is not
+    /// scheduled in `spec`.
+    fn epoch(&self, spec: &ChainSpec) -> Option<Epoch> {
+        match self {
+            GraffitiActivation::Epoch(epoch) => Some(*epoch),
+            GraffitiActivation::Fork(fork_name) => spec.fork_epoch(*fork_name),
+        }
+    }
+}
+
+/// A key in the graffiti file.
+#[derive(Debug, PartialEq)]
+enum GraffitiKey {
+    Default,
+    PublicKey(PublicKeyBytes),
+    Scheduled(GraffitiActivation),
 }
 
 /// Struct to load validator graffitis from file.
 /// The graffiti file is expected to have the following structure
 ///
 /// default: Lighthouse
+/// epoch 1000: graffiti_from_epoch_1000
+/// fork capella: graffiti_from_capella
 /// public_key1: graffiti1
 /// public_key2: graffiti2
 /// ...
+///
+/// `epoch` and `fork` entries form a schedule which replaces the default graffiti from the given
+/// epoch (or fork) onwards. They must be listed in increasing epoch order. Forks which are not
+/// scheduled on the current network are ignored.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GraffitiFile {
     graffiti_path: PathBuf,
     graffitis: HashMap<PublicKeyBytes, Graffiti>,
     default: Option<Graffiti>,
+    schedule: Vec<(GraffitiActivation, Graffiti)>,
+    #[serde(default)]
+    on_duplicate: OnDuplicateGraffiti,
+    /// Public keys listed more than once with different graffitis in the most recent read.
+    #[serde(default)]
+    duplicates: Vec<PublicKeyBytes>,
 }
 
 impl GraffitiFile {
@@ -37,52 +102,127 @@ impl GraffitiFile {
             graffiti_path,
             graffitis: HashMap::new(),
             default: None,
+            schedule: vec![],
+            on_duplicate: OnDuplicateGraffiti::default(),
+            duplicates: vec![],
         }
     }
 
-    /// Loads the graffiti file and populates the default graffiti and `graffitis` hashmap.
+    pub fn with_on_duplicate(mut self, on_duplicate: OnDuplicateGraffiti) -> Self {
+        self.on_duplicate = on_duplicate;
+        self
+    }
+
+    /// Returns the public keys which were listed more than once with different graffitis when the
+    /// file was last read.
+    pub fn duplicates(&self) -> &[PublicKeyBytes] {
+        &self.duplicates
+    }
+
+    /// Loads the graffiti file and populates the default graffiti, the schedule and `graffitis`
+    /// hashmap.
     /// Returns the graffiti corresponding to the given public key if present, else returns the
-    /// default graffiti.
+    /// scheduled graffiti for `epoch`, if any, falling back to the default graffiti.
     ///
     /// Returns an error if loading from the graffiti file fails.
     pub fn load_graffiti(
         &mut self,
         public_key: &PublicKeyBytes,
+        epoch: Epoch,
+        spec: &ChainSpec,
     ) -> Result<Option<Graffiti>, Error> {
         self.read_graffiti_file()?;
-        Ok(self.graffitis.get(public_key).copied().or(self.default))
+        if let Some(graffiti) = self.graffitis.get(public_key) {
+            return Ok(Some(*graffiti));
+        }
+        Ok(self.scheduled_graffiti(epoch, spec)?.or(self.default))
     }
 
-    /// Reads from a graffiti file with the specified format and populates the default value
-    /// and the hashmap.
+    /// Checks that the scheduled graffitis are in increasing epoch order on the network described
+    /// by `spec`.
+    pub fn validate_schedule(&self, spec: &ChainSpec) -> Result<(), Error> {
+        self.scheduled_graffiti(Epoch::new(0), spec).map(|_| ())
+    }
+
+    /// Returns the latest scheduled graffiti which is active at `epoch`, if any.
     ///
-    /// Returns an error if the file does not exist, or if the format is invalid.
+    /// Returns an error if the schedule is not in increasing epoch order.
+    pub fn scheduled_graffiti(
+        &self,
+        epoch: Epoch,
+        spec: &ChainSpec,
+    ) -> Result<Option<Graffiti>, Error> {
+        let mut previous_epoch = None;
+        let mut active = None;
+        for (activation, graffiti) in &self.schedule {
+            let activation_epoch = match activation.epoch(spec) {
+                Some(activation_epoch) => activation_epoch,
+                None => continue,
+            };
+            if let Some(previous_epoch) = previous_epoch {
+                if activation_epoch <= previous_epoch {
+                    return Err(Error::InvalidSchedule(format!(
+                        "{:?} at epoch {} is not after epoch {}",
+                        activation, activation_epoch, previous_epoch
+                    )));
+                }
+            }
+            previous_epoch = Some(activation_epoch);
+
+            if activation_epoch <= epoch {
+                active = Some(*graffiti);
+            }
+        }
+        Ok(active)
+    }
+
+    /// Reads from a graffiti file with the specified format and populates the default value,
+    /// the schedule and the hashmap.
+    ///
+    /// Returns an error if the file does not exist, or if the format is invalid. A public key
+    /// which is listed more than once with different graffitis is handled according to
+    /// `on_duplicate`.
     pub fn read_graffiti_file(&mut self) -> Result<(), Error> {
         let file = File::open(self.graffiti_path.as_path()).map_err(Error::InvalidFile)?;
         let reader = BufReader::new(file);
 
         let lines = reader.lines();
 
+        let mut schedule = vec![];
+        let mut seen = HashMap::new();
+        let mut duplicates = vec![];
         for line in lines {
             let line = line.map_err(|e| Error::InvalidLine(e.to_string()))?;
-            let (pk_opt, graffiti) = read_line(&line)?;
-            match pk_opt {
-                Some(pk) => {
+            let (key, graffiti) = read_line(&line)?;
+            match key {
+                GraffitiKey::PublicKey(pk) => {
+                    match seen.insert(pk, graffiti) {
+                        Some(previous) if previous != graffiti => {
+                            if self.on_duplicate == OnDuplicateGraffiti::Error {
+                                return Err(Error::DuplicatePublicKey(pk));
+                            }
+                            if !duplicates.contains(&pk) {
+                                duplicates.push(pk);
+                            }
+                        }
+                        _ => (),
+                    }
                     self.graffitis.insert(pk, graffiti);
                 }
-                None => self.default = Some(graffiti),
+                GraffitiKey::Scheduled(activation) => schedule.push((activation, graffiti)),
+                GraffitiKey::Default => self.default = Some(graffiti),
             }
         }
+        self.schedule = schedule;
+        self.duplicates = duplicates;
         Ok(())
     }
 }
 
 /// Parses a line from the graffiti file.
 ///
-/// `Ok((None, graffiti))` represents the graffiti for the default key.
-/// `Ok((Some(pk), graffiti))` represents graffiti for the public key `pk`.
-/// Returns an error if the line is in the wrong format or does not contain a valid public key or graffiti.
-fn read_line(line: &str) -> Result<(Option<PublicKeyBytes>, Graffiti), Error> {
+/// Returns an error if the line is in the wrong format or does not contain a valid key or graffiti.
+fn read_line(line: &str) -> Result<(GraffitiKey, Graffiti), Error> {
     if let Some(i) = line.find(':') {
         let (key, value) = line.split_at(i);
         // Note: `value.len() >=1` so `value[1..]` is safe
@@ -90,10 +230,21 @@ fn read_line(line: &str) -> Result<(Option<PublicKeyBytes>, Graffiti), Error> {
             .map_err(Error::InvalidGraffiti)?
             .into();
         if key == "default" {
-            Ok((None, graffiti))
+            Ok((GraffitiKey::Default, graffiti))
+        } else if let Some(epoch) = key.strip_prefix("epoch ") {
+            let epoch = epoch
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| Error::InvalidSchedule(format!("{}: {:?}", key, e)))?;
+            let activation = GraffitiActivation::Epoch(Epoch::new(epoch));
+            Ok((GraffitiKey::Scheduled(activation), graffiti))
+        } else if let Some(fork_name) = key.strip_prefix("fork ") {
+            let fork_name = ForkName::from_str(fork_name.trim()).map_err(Error::InvalidSchedule)?;
+            let activation = GraffitiActivation::Fork(fork_name);
+            Ok((GraffitiKey::Scheduled(activation), graffiti))
         } else {
             let pk = PublicKeyBytes::from_str(key).map_err(Error::InvalidPublicKey)?;
-            Ok((Some(pk), graffiti))
+            Ok((GraffitiKey::PublicKey(pk), graffiti))
         }
     } else {
         Err(Error::InvalidLine(format!("Missing delimiter: {}", line)))
@@ -146,6 +297,7 @@ mod tests {
     fn test_load_graffiti() {
         let graffiti_file_path = create_graffiti_file();
         let mut gf = GraffitiFile::new(graffiti_file_path);
+        let spec = ChainSpec::mainnet();
 
         let pk1 = PublicKeyBytes::deserialize(&hex::decode(&PK1[2..]).unwrap()).unwrap();
         let pk2 = PublicKeyBytes::deserialize(&hex::decode(&PK2[2..]).unwrap()).unwrap();
@@ -155,24 +307,188 @@ mod tests {
         gf.read_graffiti_file().unwrap();
 
         assert_eq!(
-            gf.load_graffiti(&pk1).unwrap().unwrap(),
+            gf.load_graffiti(&pk1, Epoch::new(0), &spec)
+                .unwrap()
+                .unwrap(),
             GraffitiString::from_str(CUSTOM_GRAFFITI1).unwrap().into()
         );
         assert_eq!(
-            gf.load_graffiti(&pk2).unwrap().unwrap(),
+            gf.load_graffiti(&pk2, Epoch::new(0), &spec)
+                .unwrap()
+                .unwrap(),
             GraffitiString::from_str(CUSTOM_GRAFFITI2).unwrap().into()
         );
 
         assert_eq!(
-            gf.load_graffiti(&pk3).unwrap().unwrap(),
+            gf.load_graffiti(&pk3, Epoch::new(0), &spec)
+                .unwrap()
+                .unwrap(),
             GraffitiString::from_str(EMPTY_GRAFFITI).unwrap().into()
         );
 
         // Random pk should return the default graffiti
         let random_pk = Keypair::random().pk.compress();
         assert_eq!(
-            gf.load_graffiti(&random_pk).unwrap().unwrap(),
+            gf.load_graffiti(&random_pk, Epoch::new(0), &spec)
+                .unwrap()
+                .unwrap(),
             GraffitiString::from_str(DEFAULT_GRAFFITI).unwrap().into()
         );
     }
+
+    // Create a graffiti file with the given contents and return a path to the file.
+    fn create_graffiti_file_with_contents(contents: &str) -> PathBuf {
+        let temp = TempDir::new().unwrap();
+        let file_name = temp.into_path().join("graffiti.txt");
+
+        let file = File::create(&file_name).unwrap();
+        let mut graffiti_file = LineWriter::new(file);
+        graffiti_file.write_all(contents.as_bytes()).unwrap();
+        graffiti_file.flush().unwrap();
+        file_name
+    }
+
+    fn graffiti(graffiti: &str) -> Graffiti {
+        GraffitiString::from_str(graffiti).unwrap().into()
+    }
+
+    #[test]
+    fn test_graffiti_schedule() {
+        let mut spec = ChainSpec::mainnet();
+        spec.capella_fork_epoch = Some(Epoch::new(200));
+
+        let graffiti_file_path = create_graffiti_file_with_contents(&format!(
+            "default: {}\nepoch 100: epoch-100\nfork capella: capella\nepoch 300: epoch-300\n{}: {}\n",
+            DEFAULT_GRAFFITI, PK1, CUSTOM_GRAFFITI1
+        ));
+        let mut gf = GraffitiFile::new(graffiti_file_path);
+        let pk1 = PublicKeyBytes::from_str(PK1).unwrap();
+        let random_pk = Keypair::random().pk.compress();
+
+        for (epoch, expected) in [
+            (0, DEFAULT_GRAFFITI),
+            (99, DEFAULT_GRAFFITI),
+            (100, "epoch-100"),
+            (199, "epoch-100"),
+            (200, "capella"),
+            (300, "epoch-300"),
+            (u64::MAX, "epoch-300"),
+        ] {
+            assert_eq!(
+                gf.load_graffiti(&random_pk, Epoch::new(epoch), &spec)
+                    .unwrap(),
+                Some(graffiti(expected)),
+                "epoch {}",
+                epoch
+            );
+            // Per-validator graffiti takes precedence over the schedule.
+            assert_eq!(
+                gf.load_graffiti(&pk1, Epoch::new(epoch), &spec).unwrap(),
+                Some(graffiti(CUSTOM_GRAFFITI1))
+            );
+        }
+
+        // Re-reading the file should not duplicate schedule entries.
+        gf.read_graffiti_file().unwrap();
+        assert_eq!(gf.schedule.len(), 3);
+    }
+
+    #[test]
+    fn test_graffiti_schedule_unscheduled_fork() {
+        let mut spec = ChainSpec::mainnet();
+        spec.capella_fork_epoch = None;
+
+        let graffiti_file_path =
+            create_graffiti_file_with_contents("epoch 100: epoch-100\nfork capella: capella\n");
+        let mut gf = GraffitiFile::new(graffiti_file_path);
+        let random_pk = Keypair::random().pk.compress();
+
+        assert_eq!(
+            gf.load_graffiti(&random_pk, Epoch::new(0), &spec).unwrap(),
+            None
+        );
+        assert_eq!(
+            gf.load_graffiti(&random_pk, Epoch::new(u64::MAX), &spec)
+                .unwrap(),
+            Some(graffiti("epoch-100"))
+        );
+    }
+
+    #[test]
+    fn test_graffiti_schedule_not_increasing() {
+        let mut spec = ChainSpec::mainnet();
+        spec.capella_fork_epoch = Some(Epoch::new(200));
+        let random_pk = Keypair::random().pk.compress();
+
+        for contents in [
+            "epoch 100: a\nepoch 100: b\n",
+            "epoch 100: a\nepoch 50: b\n",
+            "epoch 300: a\nfork capella: b\n",
+        ] {
+            let mut gf = GraffitiFile::new(create_graffiti_file_with_contents(contents));
+            assert!(matches!(
+                gf.load_graffiti(&random_pk, Epoch::new(0), &spec),
+                Err(Error::InvalidSchedule(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_duplicate_public_keys() {
+        let contents = format!(
+            "{pk1}: first\n{pk2}: same\n{pk1}: second\n{pk2}: same\n",
+            pk1 = PK1,
+            pk2 = PK2
+        );
+        let spec = ChainSpec::mainnet();
+        let pk1 = PublicKeyBytes::from_str(PK1).unwrap();
+        let pk2 = PublicKeyBytes::from_str(PK2).unwrap();
+
+        // By default the last graffiti wins, and the conflict is reported.
+        let mut gf = GraffitiFile::new(create_graffiti_file_with_contents(&contents));
+        assert_eq!(
+            gf.load_graffiti(&pk1, Epoch::new(0), &spec).unwrap(),
+            Some(graffiti("second"))
+        );
+        assert_eq!(
+            gf.load_graffiti(&pk2, Epoch::new(0), &spec).unwrap(),
+            Some(graffiti("same"))
+        );
+        // Listing the same graffiti twice is not a conflict.
+        assert_eq!(gf.duplicates(), &[pk1]);
+
+        let mut gf = GraffitiFile::new(create_graffiti_file_with_contents(&contents))
+            .with_on_duplicate(OnDuplicateGraffiti::Error);
+        assert!(matches!(
+            gf.read_graffiti_file(),
+            Err(Error::DuplicatePublicKey(pk)) if pk == pk1
+        ));
+
+        let mut gf = GraffitiFile::new(create_graffiti_file_with_contents(&format!(
+            "{}: same\n{}: same\n",
+            PK1, PK1
+        )))
+        .with_on_duplicate(OnDuplicateGraffiti::Error);
+        gf.read_graffiti_file().unwrap();
+        assert!(gf.duplicates().is_empty());
+    }
+
+    #[test]
+    fn test_graffiti_schedule_invalid_lines() {
+        for contents in ["epoch abc: a\n", "epoch -1: a\n", "fork unknown: a\n"] {
+            let mut gf = GraffitiFile::new(create_graffiti_file_with_contents(contents));
+            assert!(matches!(
+                gf.read_graffiti_file(),
+                Err(Error::InvalidSchedule(_))
+            ));
+        }
+
+        let mut gf = GraffitiFile::new(create_graffiti_file_with_contents(
+            "epoch 100: this graffiti is longer than thirty-two bytes\n",
+        ));
+        assert!(matches!(
+            gf.read_graffiti_file(),
+            Err(Error::InvalidGraffiti(_))
+        ));
+    }
 }
@@ -1,10 +1,12 @@
 use eth2::lighthouse_vc::{PK_LEN, SECRET_PREFIX as PK_PREFIX};
 use filesystem::create_with_600_perms;
 use libsecp256k1::{Message, PublicKey, SecretKey};
+use parking_lot::RwLock;
 use rand::thread_rng;
 use ring::digest::{digest, SHA256};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use warp::Filter;
 
 /// The name of the file which stores the secret key.
@@ -23,6 +25,17 @@ pub const SK_LEN: usize = 32;
 /// value in a public forum.
 pub const PK_FILENAME: &str = "api-token.txt";
 
+/// The name of the temporary file used whilst replacing `SK_FILENAME`.
+pub const TEMP_SK_FILENAME: &str = ".secp-sk.tmp";
+
+/// The name of the temporary file used whilst replacing `PK_FILENAME`.
+pub const TEMP_PK_FILENAME: &str = ".api-token.txt.tmp";
+
+struct Keypair {
+    pk: PublicKey,
+    sk: SecretKey,
+}
+
 /// Contains a `secp256k1` keypair that is saved-to/loaded-from disk on instantiation. The keypair
 /// is used for authorization/authentication for requests/responses on the HTTP API.
 ///
@@ -35,9 +48,13 @@ pub const PK_FILENAME: &str = "api-token.txt";
 ///  The aforementioned scheme was first defined here:
 ///
 ///  https://github.com/sigp/lighthouse/issues/1269#issuecomment-649879855
+///
+/// The keypair can be replaced at runtime using `Self::rotate`. Clones of `self` share the same
+/// keypair.
+#[derive(Clone)]
 pub struct ApiSecret {
-    pk: PublicKey,
-    sk: SecretKey,
+    keypair: Arc<RwLock<Keypair>>,
+    sk_path: PathBuf,
     pk_path: PathBuf,
 }
 
@@ -70,16 +87,7 @@ impl ApiSecret {
             })?;
 
             // Create and write the public key to file with appropriate permissions
-            create_with_600_perms(
-                &pk_path,
-                format!(
-                    "{}{}",
-                    PK_PREFIX,
-                    serde_utils::hex::encode(&pk.serialize_compressed()[..])
-                )
-                .as_bytes(),
-            )
-            .map_err(|e| {
+            create_with_600_perms(&pk_path, api_token(&pk).as_bytes()).map_err(|e| {
                 format!(
                     "Unable to create file with permissions for {:?}: {:?}",
                     pk_path, e
@@ -148,17 +156,43 @@ impl ApiSecret {
             ));
         }
 
-        Ok(Self { pk, sk, pk_path })
+        Ok(Self {
+            keypair: Arc::new(RwLock::new(Keypair { pk, sk })),
+            sk_path,
+            pk_path,
+        })
     }
 
-    /// Returns the public key of `self` as a 0x-prefixed hex string.
-    fn pubkey_string(&self) -> String {
-        serde_utils::hex::encode(&self.pk.serialize_compressed()[..])
+    /// Replace the keypair with a new random keypair, returning the new API token.
+    ///
+    /// Each key file is replaced atomically by renaming a temporary file over it. The secret key
+    /// is replaced first, so if the public key cannot be replaced the files no longer form a pair.
+    /// On the next start `Self::create_or_open` then deletes both files and fails, and a new
+    /// keypair is generated on the start after that. The in-memory keypair is only replaced once
+    /// both files have been written.
+    pub fn rotate(&self) -> Result<String, String> {
+        // Hold the lock whilst writing so that concurrent rotations cannot leave the keypair on
+        // disk out of sync with the keypair in memory.
+        let mut keypair = self.keypair.write();
+
+        let sk = SecretKey::random(&mut thread_rng());
+        let pk = PublicKey::from_secret_key(&sk);
+        let token = api_token(&pk);
+
+        replace_with_600_perms(
+            &self.sk_path,
+            TEMP_SK_FILENAME,
+            serde_utils::hex::encode(sk.serialize()).as_bytes(),
+        )?;
+        replace_with_600_perms(&self.pk_path, TEMP_PK_FILENAME, token.as_bytes())?;
+
+        *keypair = Keypair { pk, sk };
+        Ok(token)
     }
 
     /// Returns the API token.
     pub fn api_token(&self) -> String {
-        format!("{}{}", PK_PREFIX, self.pubkey_string())
+        api_token(&self.keypair.read().pk)
     }
 
     /// Returns the path for the API token file
@@ -173,18 +207,21 @@ impl ApiSecret {
     /// technically invalid according to RFC 7617 because the token is not a base64-encoded username
     /// and password. As such, bearer authentication should be preferred.
     fn auth_header_values(&self) -> Vec<String> {
+        let api_token = self.api_token();
         vec![
-            format!("Basic {}", self.api_token()),
-            format!("Bearer {}", self.api_token()),
+            format!("Basic {}", api_token),
+            format!("Bearer {}", api_token),
         ]
     }
 
     /// Returns a `warp` header which filters out request that have a missing or inaccurate
     /// `Authorization` header.
+    ///
+    /// The header is checked against the API token at the time of the request.
     pub fn authorization_header_filter(&self) -> warp::filters::BoxedFilter<()> {
-        let expected = self.auth_header_values();
+        let api_secret = self.clone();
         warp::any()
-            .map(move || expected.clone())
+            .map(move || api_secret.auth_header_values())
             .and(warp::filters::header::header("Authorization"))
             .and_then(move |expected: Vec<String>, header: String| async move {
                 if expected.contains(&header) {
@@ -197,10 +234,12 @@ impl ApiSecret {
             .boxed()
     }
 
-    /// Returns a closure which produces a signature over some bytes using the secret key in
-    /// `self`. The signature is a 32-byte hash formatted as a 0x-prefixed string.
+    /// Returns a closure which produces a signature over some bytes using the current secret key
+    /// in `self`. The signature is a 32-byte hash formatted as a 0x-prefixed string.
+    ///
+    /// The closure is not affected by later calls to `Self::rotate`.
     pub fn signer(&self) -> impl Fn(&[u8]) -> String + Clone {
-        let sk = self.sk;
+        let sk = self.keypair.read().sk;
         move |input: &[u8]| -> String {
             let message =
                 Message::parse_slice(digest(&SHA256, input).as_ref()).expect("sha256 is 32 bytes");
@@ -209,3 +248,26 @@ impl ApiSecret {
         }
     }
 }
+
+/// Returns the API token for `pk`.
+fn api_token(pk: &PublicKey) -> String {
+    format!(
+        "{}{}",
+        PK_PREFIX,
+        serde_utils::hex::encode(&pk.serialize_compressed()[..])
+    )
+}
+
+/// Write `bytes` to a temporary file named `temp_filename` with 600 permissions, then rename it
+/// over `path`.
+fn replace_with_600_perms(path: &Path, temp_filename: &str, bytes: &[u8]) -> Result<(), String> {
+    let temp_path = path.with_file_name(temp_filename);
+    create_with_600_perms(&temp_path, bytes).map_err(|e| {
+        format!(
+            "Unable to create file with permissions for {:?}: {:?}",
+            temp_path, e
+        )
+    })?;
+    fs::rename(&temp_path, path)
+        .map_err(|e| format!("Unable to rename {:?} to {:?}: {}", temp_path, path, e))
+}
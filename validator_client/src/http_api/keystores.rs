@@ -1,18 +1,24 @@
 //! Implementation of the standard keystore management API.
 use crate::{
-    initialized_validators::Error, signing_method::SigningMethod, InitializedValidators,
-    ValidatorStore,
+    http_metrics::metrics, initialized_validators::Error, signing_method::SigningMethod,
+    InitializedValidators, ValidatorStore,
 };
-use account_utils::ZeroizeString;
+use account_utils::{validator_definitions::SigningDefinition, ZeroizeString};
+use bls::PublicKey;
 use eth2::lighthouse_vc::std_types::{
     DeleteKeystoreStatus, DeleteKeystoresRequest, DeleteKeystoresResponse, ImportKeystoreStatus,
-    ImportKeystoresRequest, ImportKeystoresResponse, InterchangeJsonStr, KeystoreJsonStr,
-    ListKeystoresResponse, SingleKeystoreResponse, Status,
+    ImportKeystoresRequest, ImportKeystoresResponse, ImportSlashingProtectionRequest,
+    ImportSlashingProtectionResponse, ImportSlashingProtectionStatus, InterchangeJsonStr,
+    KeystoreJsonStr, ListKeystoresResponse, SingleImportSlashingProtectionResponse,
+    SingleKeystoreResponse, Status,
 };
 use eth2_keystore::Keystore;
+use slashing_protection::{interchange::Interchange, InterchangeError, InterchangeImportOutcome};
 use slog::{info, warn, Logger};
 use slot_clock::SlotClock;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use task_executor::TaskExecutor;
 use tokio::runtime::Handle;
@@ -41,7 +47,9 @@ pub fn list<T: SlotClock + 'static, E: EthSpec>(
                         ref voting_keystore,
                         ..
                     } => (voting_keystore.path(), Some(false)),
-                    SigningMethod::Web3Signer { .. } => (None, Some(true)),
+                    SigningMethod::Web3Signer { .. } | SigningMethod::Pkcs11 { .. } => {
+                        (None, Some(true))
+                    }
                 });
 
             SingleKeystoreResponse {
@@ -77,11 +85,13 @@ pub fn import<T: SlotClock + 'static, E: EthSpec>(
         "count" => request.keystores.len(),
     );
 
-    // Import slashing protection data before keystores, so that new keystores don't start signing
-    // without it. Do not return early on failure, propagate the failure to each key.
-    let slashing_protection_status =
-        if let Some(InterchangeJsonStr(slashing_protection)) = request.slashing_protection {
+    // The slashing protection data of each keystore is imported together with the keystore, see
+    // `import_single_keystore`. The data for any other keys is imported up-front, which also
+    // checks the metadata. Do not return early on failure, propagate the failure to each key.
+    let (slashing_protection, slashing_protection_status) =
+        if let Some(InterchangeJsonStr(mut slashing_protection)) = request.slashing_protection {
             // Warn for missing slashing protection.
+            let mut keystore_pubkeys = HashSet::new();
             for KeystoreJsonStr(ref keystore) in &request.keystores {
                 if let Some(public_key) = keystore.public_key() {
                     let pubkey_bytes = public_key.compress();
@@ -96,13 +106,22 @@ pub fn import<T: SlotClock + 'static, E: EthSpec>(
                             "public_key" => ?public_key,
                         );
                     }
+                    keystore_pubkeys.insert(pubkey_bytes);
                 }
             }
 
-            validator_store.import_slashing_protection(slashing_protection)
+            let (keystore_data, other_data) = std::mem::take(&mut slashing_protection.data)
+                .into_iter()
+                .partition(|data| keystore_pubkeys.contains(&data.pubkey));
+            let status = validator_store.import_slashing_protection(Interchange {
+                metadata: slashing_protection.metadata.clone(),
+                data: other_data,
+            });
+            slashing_protection.data = keystore_data;
+            (Some(slashing_protection), status)
         } else {
             warn!(log, "No slashing protection data provided with keystores");
-            Ok(())
+            (None, Ok(()))
         };
 
     // Import each keystore. Some keystores may fail to be imported, so we record a status for each.
@@ -114,6 +133,18 @@ pub fn import<T: SlotClock + 'static, E: EthSpec>(
         .zip(request.passwords.into_iter())
     {
         let pubkey_str = keystore.pubkey().to_string();
+        let keystore_slashing_protection = slashing_protection.as_ref().map(|interchange| {
+            let pubkey = keystore.public_key().map(|pubkey| pubkey.compress());
+            Interchange {
+                metadata: interchange.metadata.clone(),
+                data: interchange
+                    .data
+                    .iter()
+                    .filter(|data| Some(data.pubkey) == pubkey)
+                    .cloned()
+                    .collect(),
+            }
+        });
 
         let status = if let Err(e) = &slashing_protection_status {
             // Slashing protection import failed, do not attempt to import the key. Record an
@@ -127,6 +158,7 @@ pub fn import<T: SlotClock + 'static, E: EthSpec>(
             match import_single_keystore(
                 keystore,
                 password,
+                keystore_slashing_protection,
                 validator_dir.clone(),
                 &validator_store,
                 handle,
@@ -148,15 +180,35 @@ pub fn import<T: SlotClock + 'static, E: EthSpec>(
                 "validator client shutdown".into(),
             )
         };
+        metrics::inc_counter_vec(
+            &metrics::KEYMANAGER_KEYS_IMPORTED_TOTAL,
+            &[metrics::LOCAL_KEYSTORE, import_status_label(status.status)],
+        );
         statuses.push(status);
     }
 
     Ok(ImportKeystoresResponse { data: statuses })
 }
 
+/// Returns the metrics label for an import `status`, as it appears in the API response.
+fn import_status_label(status: ImportKeystoreStatus) -> &'static str {
+    match status {
+        ImportKeystoreStatus::Imported => "imported",
+        ImportKeystoreStatus::Duplicate => "duplicate",
+        ImportKeystoreStatus::Error => metrics::ERROR,
+    }
+}
+
+/// Imports `keystore` together with its `slashing_protection` data.
+///
+/// The import is atomic for each keystore: its slashing protection data is imported before the
+/// keystore is written, and the keystore is removed again if it can't be added to the validator
+/// definitions. Imported slashing protection data is never removed, since it only prevents the key
+/// from signing messages which conflict with those it has signed before.
 fn import_single_keystore<T: SlotClock + 'static, E: EthSpec>(
     keystore: Keystore,
     password: ZeroizeString,
+    slashing_protection: Option<Interchange>,
     validator_dir_path: PathBuf,
     validator_store: &ValidatorStore<T, E>,
     handle: Handle,
@@ -165,6 +217,7 @@ fn import_single_keystore<T: SlotClock + 'static, E: EthSpec>(
     let pubkey = keystore
         .public_key()
         .ok_or_else(|| format!("invalid pubkey: {}", keystore.pubkey()))?;
+    let mut duplicate = false;
     if let Some(def) = validator_store
         .initialized_validators()
         .read()
@@ -174,19 +227,32 @@ fn import_single_keystore<T: SlotClock + 'static, E: EthSpec>(
     {
         if !def.signing_definition.is_local_keystore() {
             return Err("cannot import duplicate of existing remote signer validator".into());
-        } else if def.enabled {
-            return Ok(ImportKeystoreStatus::Duplicate);
         }
+        duplicate = def.enabled;
     }
 
-    // Check that the password is correct.
-    // In future we should re-structure to avoid the double decryption here. It's not as simple
-    // as removing this check because `add_validator_keystore` will break if provided with an
-    // invalid validator definition (`update_validators` will get stuck trying to decrypt with the
-    // wrong password indefinitely).
-    keystore
-        .decrypt_keypair(password.as_ref())
-        .map_err(|e| format!("incorrect password: {:?}", e))?;
+    if !duplicate {
+        // Check that the password is correct.
+        // In future we should re-structure to avoid the double decryption here. It's not as
+        // simple as removing this check because `add_validator_keystore` will break if provided
+        // with an invalid validator definition (`update_validators` will get stuck trying to
+        // decrypt with the wrong password indefinitely).
+        keystore
+            .decrypt_keypair(password.as_ref())
+            .map_err(|e| format!("incorrect password: {:?}", e))?;
+    }
+
+    // Import slashing protection data before the keystore, so that the key doesn't start signing
+    // without it.
+    if let Some(slashing_protection) = slashing_protection {
+        validator_store
+            .import_slashing_protection(slashing_protection)
+            .map_err(|e| format!("slashing protection import failed: {:?}", e))?;
+    }
+
+    if duplicate {
+        return Ok(ImportKeystoreStatus::Duplicate);
+    }
 
     let validator_dir = ValidatorDirBuilder::new(validator_dir_path)
         .voting_keystore(keystore, password.as_ref())
@@ -196,23 +262,69 @@ fn import_single_keystore<T: SlotClock + 'static, E: EthSpec>(
 
     // Drop validator dir so that `add_validator_keystore` can re-lock the keystore.
     let voting_keystore_path = validator_dir.voting_keystore_path();
+    let validator_dir_path = validator_dir.dir().to_path_buf();
     drop(validator_dir);
 
-    handle
-        .block_on(validator_store.add_validator_keystore(
-            voting_keystore_path,
-            password,
-            true,
-            None,
-            None,
-            None,
-            None,
-        ))
-        .map_err(|e| format!("failed to initialize validator: {:?}", e))?;
+    if let Err(e) = handle.block_on(validator_store.add_validator_keystore(
+        voting_keystore_path,
+        password,
+        true,
+        None,
+        None,
+        None,
+        None,
+    )) {
+        return Err(
+            match roll_back_keystore(&pubkey, &validator_dir_path, validator_store, &handle) {
+                Ok(()) => format!("failed to initialize validator: {:?}", e),
+                Err(rollback_error) => format!(
+                    "failed to initialize validator: {:?}, and to roll back: {}",
+                    e, rollback_error
+                ),
+            },
+        );
+    }
 
     Ok(ImportKeystoreStatus::Imported)
 }
 
+/// Removes the `validator_dir` written for `pubkey` after its import failed, along with its
+/// definition if one was added.
+///
+/// Definitions of `pubkey` with a keystore elsewhere, e.g. one imported concurrently, are kept.
+fn roll_back_keystore<T: SlotClock + 'static, E: EthSpec>(
+    pubkey: &PublicKey,
+    validator_dir: &Path,
+    validator_store: &ValidatorStore<T, E>,
+    handle: &Handle,
+) -> Result<(), String> {
+    let initialized_validators_rwlock = validator_store.initialized_validators();
+    let mut initialized_validators = initialized_validators_rwlock.write();
+    if initialized_validators
+        .validator_definitions()
+        .iter()
+        .any(|def| {
+            def.voting_public_key == *pubkey
+                && matches!(
+                    &def.signing_definition,
+                    SigningDefinition::LocalKeystore { voting_keystore_path, .. }
+                        if voting_keystore_path.starts_with(validator_dir)
+                )
+        })
+    {
+        handle
+            .block_on(initialized_validators.delete_definition_and_keystore(pubkey, true))
+            .map_err(|e| format!("unable to delete definition: {:?}", e))?;
+    }
+    drop(initialized_validators);
+
+    if validator_dir.exists() {
+        fs::remove_dir_all(validator_dir)
+            .map_err(|e| format!("unable to delete {:?}: {:?}", validator_dir, e))?;
+    }
+    Ok(())
+}
+
 pub fn delete<T: SlotClock + 'static, E: EthSpec>(
     request: DeleteKeystoresRequest,
     validator_store: Arc<ValidatorStore<T, E>>,
@@ -272,6 +384,10 @@ pub fn delete<T: SlotClock + 'static, E: EthSpec>(
         {
             status.status = DeleteKeystoreStatus::NotActive;
         }
+        metrics::inc_counter_vec(
+            &metrics::KEYMANAGER_KEYS_DELETED_TOTAL,
+            &[metrics::LOCAL_KEYSTORE, delete_status_label(status.status)],
+        );
     }
 
     Ok(DeleteKeystoresResponse {
@@ -280,6 +396,16 @@ pub fn delete<T: SlotClock + 'static, E: EthSpec>(
     })
 }
 
+/// Returns the metrics label for a deletion `status`, as it appears in the API response.
+fn delete_status_label(status: DeleteKeystoreStatus) -> &'static str {
+    match status {
+        DeleteKeystoreStatus::Deleted => "deleted",
+        DeleteKeystoreStatus::NotActive => "not_active",
+        DeleteKeystoreStatus::NotFound => metrics::NOT_FOUND,
+        DeleteKeystoreStatus::Error => metrics::ERROR,
+    }
+}
+
 fn delete_single_keystore(
     pubkey_bytes: &PublicKeyBytes,
     initialized_validators: &mut InitializedValidators,
@@ -302,3 +428,85 @@ fn delete_single_keystore(
         Err("validator client shutdown".into())
     }
 }
+
+/// Imports EIP-3076 slashing protection data, merging it with the existing records.
+///
+/// Records are merged by keeping the greater of the existing and imported maxima, so an import can
+/// only ever raise the slots and epochs below which signing is refused. The import is atomic: if
+/// any record cannot be merged consistently then nothing is imported, and each record is reported
+/// as an error.
+pub fn import_slashing_protection<T: SlotClock + 'static, E: EthSpec>(
+    request: ImportSlashingProtectionRequest,
+    validator_store: Arc<ValidatorStore<T, E>>,
+    log: Logger,
+) -> Result<ImportSlashingProtectionResponse, Rejection> {
+    let InterchangeJsonStr(interchange) = request.slashing_protection;
+
+    let (outcomes, aborted) = match validator_store.import_slashing_protection(interchange) {
+        Ok(outcomes) => (outcomes, false),
+        Err(InterchangeError::AtomicBatchAborted(outcomes)) => (outcomes, true),
+        Err(
+            e @ (InterchangeError::UnsupportedVersion(_)
+            | InterchangeError::GenesisValidatorsMismatch { .. }
+            | InterchangeError::InvalidPubkey(_)),
+        ) => {
+            return Err(custom_bad_request(format!(
+                "invalid slashing protection data: {:?}",
+                e
+            )))
+        }
+        Err(e) => {
+            return Err(custom_server_error(format!(
+                "error importing slashing protection: {:?}",
+                e
+            )))
+        }
+    };
+
+    let data = outcomes
+        .into_iter()
+        .map(|outcome| match outcome {
+            InterchangeImportOutcome::Success { pubkey, .. } if aborted => {
+                SingleImportSlashingProtectionResponse {
+                    pubkey,
+                    status: Status::error(
+                        ImportSlashingProtectionStatus::Error,
+                        "not imported because the import of another record failed".to_string(),
+                    ),
+                }
+            }
+            InterchangeImportOutcome::Success { pubkey, .. } => {
+                SingleImportSlashingProtectionResponse {
+                    pubkey,
+                    status: Status::ok(ImportSlashingProtectionStatus::Imported),
+                }
+            }
+            InterchangeImportOutcome::Failure { pubkey, error } => {
+                SingleImportSlashingProtectionResponse {
+                    pubkey,
+                    status: Status::error(
+                        ImportSlashingProtectionStatus::Error,
+                        format!("unable to merge with existing records: {:?}", error),
+                    ),
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if aborted {
+        warn!(
+            log,
+            "Slashing protection import aborted";
+            "msg" => "no records were imported",
+            "count" => data.len(),
+        );
+    } else {
+        info!(
+            log,
+            "Imported slashing protection data";
+            "count" => data.len(),
+        );
+    }
+
+    Ok(ImportSlashingProtectionResponse { data })
+}
@@ -5,8 +5,11 @@ mod keystores;
 mod remotekeys;
 mod tests;
 
+use crate::duties_service::DutiesService;
 use crate::http_api::create_signed_voluntary_exit::create_signed_voluntary_exit;
-use crate::{determine_graffiti, GraffitiFile, ValidatorStore};
+use crate::http_metrics::metrics;
+use crate::preparation_service::PreparationService;
+use crate::{determine_graffiti, determine_graffiti_with_source, GraffitiFile, ValidatorStore};
 use account_utils::{
     mnemonic_from_phrase,
     validator_definitions::{SigningDefinition, ValidatorDefinition, Web3SignerDefinition},
@@ -21,6 +24,7 @@ use lighthouse_version::version_with_platform;
 use logging::SSELoggingComponents;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use slashing_protection::NotSafe;
 use slog::{crit, info, warn, Logger};
 use slot_clock::SlotClock;
 use std::collections::HashMap;
@@ -33,7 +37,7 @@ use sysinfo::{System, SystemExt};
 use system_health::observe_system_health_vc;
 use task_executor::TaskExecutor;
 use tokio_stream::{wrappers::BroadcastStream, StreamExt};
-use types::{ChainSpec, ConfigAndPreset, EthSpec};
+use types::{ChainSpec, ConfigAndPreset, Epoch, EthSpec};
 use validator_dir::Builder as ValidatorDirBuilder;
 use warp::{
     http::{
@@ -70,9 +74,12 @@ pub struct Context<T: SlotClock, E: EthSpec> {
     pub task_executor: TaskExecutor,
     pub api_secret: ApiSecret,
     pub validator_store: Option<Arc<ValidatorStore<T, E>>>,
+    pub preparation_service: Option<PreparationService<T, E>>,
+    pub duties_service: Option<Arc<DutiesService<T, E>>>,
     pub validator_dir: Option<PathBuf>,
     pub graffiti_file: Option<GraffitiFile>,
     pub graffiti_flag: Option<Graffiti>,
+    pub graffiti_force: bool,
     pub spec: ChainSpec,
     pub config: Config,
     pub log: Logger,
@@ -159,8 +166,13 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
         }
     };
 
-    let signer = ctx.api_secret.signer();
-    let signer = warp::any().map(move || signer.clone());
+    // The signer is obtained when the request is received, so that responses to requests which
+    // are in-flight whilst the API token is rotated are signed with the previous key.
+    let inner_api_secret = ctx.api_secret.clone();
+    let signer = warp::any().map(move || inner_api_secret.signer());
+
+    let inner_api_secret = ctx.api_secret.clone();
+    let api_secret_filter = warp::any().map(move || inner_api_secret.clone());
 
     let inner_validator_store = ctx.validator_store.clone();
     let validator_store_filter = warp::any()
@@ -173,6 +185,28 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
             })
         });
 
+    let inner_preparation_service = ctx.preparation_service.clone();
+    let preparation_service_filter = warp::any()
+        .map(move || inner_preparation_service.clone())
+        .and_then(|preparation_service: Option<_>| async move {
+            preparation_service.ok_or_else(|| {
+                warp_utils::reject::custom_not_found(
+                    "preparation service is not initialized.".to_string(),
+                )
+            })
+        });
+
+    let inner_duties_service = ctx.duties_service.clone();
+    let duties_service_filter = warp::any()
+        .map(move || inner_duties_service.clone())
+        .and_then(|duties_service: Option<_>| async move {
+            duties_service.ok_or_else(|| {
+                warp_utils::reject::custom_not_found(
+                    "duties service is not initialized.".to_string(),
+                )
+            })
+        });
+
     let inner_task_executor = ctx.task_executor.clone();
     let task_executor_filter = warp::any().map(move || inner_task_executor.clone());
 
@@ -193,6 +227,9 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
     let inner_graffiti_flag = ctx.graffiti_flag;
     let graffiti_flag_filter = warp::any().map(move || inner_graffiti_flag);
 
+    let inner_graffiti_force = ctx.graffiti_force;
+    let graffiti_force_filter = warp::any().map(move || inner_graffiti_force);
+
     let inner_ctx = ctx.clone();
     let log_filter = warp::any().map(move || inner_ctx.log.clone());
 
@@ -334,6 +371,164 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
             },
         );
 
+    // GET lighthouse/validators/{validator_pubkey}/settings
+    let get_lighthouse_validators_pubkey_settings = warp::path("lighthouse")
+        .and(warp::path("validators"))
+        .and(warp::path::param::<PublicKey>())
+        .and(warp::path("settings"))
+        .and(warp::path::end())
+        .and(validator_store_filter.clone())
+        .and(graffiti_file_filter.clone())
+        .and(graffiti_flag_filter.clone())
+        .and(graffiti_force_filter.clone())
+        .and(signer.clone())
+        .and(log_filter.clone())
+        .and(slot_clock_filter.clone())
+        .and(spec_filter.clone())
+        .and_then(
+            |validator_pubkey: PublicKey,
+             validator_store: Arc<ValidatorStore<T, E>>,
+             graffiti_file: Option<GraffitiFile>,
+             graffiti_flag: Option<Graffiti>,
+             graffiti_force: bool,
+             signer,
+             log,
+             slot_clock: T,
+             spec: Arc<ChainSpec>| {
+                blocking_signed_json_task(signer, move || {
+                    if validator_store
+                        .initialized_validators()
+                        .read()
+                        .is_enabled(&validator_pubkey)
+                        .is_none()
+                    {
+                        return Err(warp_utils::reject::custom_not_found(format!(
+                            "no validator found with pubkey {:?}",
+                            validator_pubkey
+                        )));
+                    }
+                    let validator_pubkey = PublicKeyBytes::from(&validator_pubkey);
+
+                    let graffiti = determine_graffiti_with_source(
+                        &validator_pubkey,
+                        &log,
+                        current_epoch::<E>(&slot_clock),
+                        &spec,
+                        graffiti_file,
+                        validator_store.graffiti(&validator_pubkey),
+                        graffiti_flag,
+                        graffiti_force,
+                    );
+
+                    Ok(api_types::GenericResponse::from(
+                        api_types::ValidatorSettings {
+                            voting_pubkey: validator_pubkey,
+                            fee_recipient: validator_store.resolve_fee_recipient(&validator_pubkey),
+                            gas_limit: validator_store.resolve_gas_limit(&validator_pubkey),
+                            graffiti: api_types::ResolvedSetting::new(
+                                graffiti.value.map(|g| g.as_utf8_lossy()),
+                                graffiti.source,
+                            ),
+                            builder_proposals: validator_store
+                                .resolve_builder_proposals(&validator_pubkey),
+                        },
+                    ))
+                })
+            },
+        );
+
+    // GET lighthouse/validators/definitions
+    let get_lighthouse_validators_definitions = warp::path("lighthouse")
+        .and(warp::path("validators"))
+        .and(warp::path("definitions"))
+        .and(warp::path::end())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and_then(|validator_store: Arc<ValidatorStore<T, E>>, signer| {
+            blocking_signed_json_task(signer, move || {
+                let definitions = validator_store
+                    .initialized_validators()
+                    .read()
+                    .validator_definitions()
+                    .iter()
+                    .map(ValidatorDefinition::redacted)
+                    .collect::<Vec<_>>();
+
+                Ok(api_types::GenericResponse::from(definitions))
+            })
+        });
+
+    // GET lighthouse/validators/{validator_pubkey}/slashing-protection/summary
+    let get_lighthouse_validators_pubkey_slashing_protection_summary = warp::path("lighthouse")
+        .and(warp::path("validators"))
+        .and(warp::path::param::<PublicKey>())
+        .and(warp::path("slashing-protection"))
+        .and(warp::path("summary"))
+        .and(warp::path::end())
+        .and(validator_store_filter.clone())
+        .and(signer.clone())
+        .and_then(
+            |validator_pubkey: PublicKey, validator_store: Arc<ValidatorStore<T, E>>, signer| {
+                blocking_signed_json_task(signer, move || {
+                    let validator_pubkey = PublicKeyBytes::from(&validator_pubkey);
+                    let summary = validator_store
+                        .slashing_protection_summary(&validator_pubkey)
+                        .map_err(|e| match e {
+                            NotSafe::UnregisteredValidator(_) => {
+                                warp_utils::reject::custom_not_found(format!(
+                                    "no slashing protection data for {:?}",
+                                    validator_pubkey
+                                ))
+                            }
+                            e => warp_utils::reject::custom_server_error(format!(
+                                "unable to read slashing protection data: {:?}",
+                                e
+                            )),
+                        })?;
+
+                    Ok(api_types::GenericResponse::from(
+                        api_types::SlashingProtectionSummary {
+                            voting_pubkey: validator_pubkey,
+                            max_block_slot: summary.max_block_slot,
+                            max_attestation_source_epoch: summary.max_attestation_source,
+                            max_attestation_target_epoch: summary.max_attestation_target,
+                        },
+                    ))
+                })
+            },
+        );
+
+    // GET lighthouse/duties?epoch
+    let get_lighthouse_duties = warp::path("lighthouse")
+        .and(warp::path("duties"))
+        .and(warp::path::end())
+        .and(warp::query::<api_types::DutiesQuery>())
+        .and(duties_service_filter)
+        .and(signer.clone())
+        .and_then(
+            |query: api_types::DutiesQuery, duties_service: Arc<DutiesService<T, E>>, signer| {
+                blocking_signed_json_task(signer, move || {
+                    let (first_epoch, last_epoch) =
+                        duties_service.cached_duties_epochs().ok_or_else(|| {
+                            warp_utils::reject::custom_server_error(
+                                "unable to read slot clock".to_string(),
+                            )
+                        })?;
+                    if query.epoch < first_epoch || query.epoch > last_epoch {
+                        return Err(warp_utils::reject::custom_not_found(format!(
+                            "duties for epoch {} are not known, duties are only cached for \
+                             epochs {} to {}",
+                            query.epoch, first_epoch, last_epoch
+                        )));
+                    }
+
+                    Ok(api_types::GenericResponse::from(
+                        duties_service.cached_duties(query.epoch),
+                    ))
+                })
+            },
+        );
+
     // GET lighthouse/ui/health
     let get_lighthouse_ui_health = warp::path("lighthouse")
         .and(warp::path("ui"))
@@ -359,15 +554,22 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
         .and(validator_store_filter.clone())
         .and(graffiti_file_filter.clone())
         .and(graffiti_flag_filter)
+        .and(graffiti_force_filter)
         .and(signer.clone())
         .and(log_filter.clone())
+        .and(slot_clock_filter.clone())
+        .and(spec_filter.clone())
         .and_then(
             |validator_store: Arc<ValidatorStore<T, E>>,
              graffiti_file: Option<GraffitiFile>,
              graffiti_flag: Option<Graffiti>,
+             graffiti_force: bool,
              signer,
-             log| {
+             log,
+             slot_clock: T,
+             spec: Arc<ChainSpec>| {
                 blocking_signed_json_task(signer, move || {
+                    let epoch = current_epoch::<E>(&slot_clock);
                     let mut result = HashMap::new();
                     for (key, graffiti_definition) in validator_store
                         .initialized_validators()
@@ -377,9 +579,12 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
                         let graffiti = determine_graffiti(
                             key,
                             &log,
+                            epoch,
+                            &spec,
                             graffiti_file.clone(),
                             graffiti_definition,
                             graffiti_flag,
+                            graffiti_force,
                         );
                         result.insert(key.to_string(), graffiti.map(|g| g.as_utf8_lossy()));
                     }
@@ -406,6 +611,7 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
              signer,
              task_executor: TaskExecutor| {
                 blocking_signed_json_task(signer, move || {
+                    check_slashing_protection_available(&validator_store)?;
                     if let Some(handle) = task_executor.handle() {
                         let (validators, mnemonic) =
                             handle.block_on(create_validators_mnemonic(
@@ -449,6 +655,7 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
              signer,
              task_executor: TaskExecutor| {
                 blocking_signed_json_task(signer, move || {
+                    check_slashing_protection_available(&validator_store)?;
                     if let Some(handle) = task_executor.handle() {
                         let mnemonic =
                             mnemonic_from_phrase(body.mnemonic.as_str()).map_err(|e| {
@@ -493,6 +700,7 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
              signer,
              task_executor: TaskExecutor| {
                 blocking_signed_json_task(signer, move || {
+                    check_slashing_protection_available(&validator_store)?;
                     // Check to ensure the password is correct.
                     let keypair = body
                         .keystore
@@ -573,6 +781,7 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
              signer,
              task_executor: TaskExecutor| {
                 blocking_signed_json_task(signer, move || {
+                    check_slashing_protection_available(&validator_store)?;
                     if let Some(handle) = task_executor.handle() {
                         let web3signers: Vec<ValidatorDefinition> = body
                             .into_iter()
@@ -698,6 +907,74 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
             })
         });
 
+    // POST lighthouse/validators/register
+    let post_validators_register = warp::path("lighthouse")
+        .and(warp::path("validators"))
+        .and(warp::path("register"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(preparation_service_filter)
+        .and(signer.clone())
+        .and(task_executor_filter.clone())
+        .and(log_filter.clone())
+        .and_then(
+            |body: api_types::RegisterValidatorsRequest,
+             preparation_service: PreparationService<T, E>,
+             signer,
+             task_executor: TaskExecutor,
+             log: Logger| {
+                blocking_signed_json_task(signer, move || {
+                    if let Some(handle) = task_executor.handle() {
+                        let results = handle
+                            .block_on(preparation_service.register_validators_now(body.pubkeys))
+                            .map_err(|e| {
+                                warp_utils::reject::custom_server_error(format!(
+                                    "unable to register validators: {}",
+                                    e
+                                ))
+                            })?;
+                        info!(
+                            log,
+                            "Registered validators via the HTTP API";
+                            "count" => results.len(),
+                        );
+                        Ok(api_types::GenericResponse::from(results))
+                    } else {
+                        Err(warp_utils::reject::custom_server_error(
+                            "Lighthouse shutting down".into(),
+                        ))
+                    }
+                })
+            },
+        );
+
+    // POST /lighthouse/auth/rotate-token
+    let post_auth_rotate_token = warp::path("lighthouse")
+        .and(warp::path("auth"))
+        .and(warp::path("rotate-token"))
+        .and(warp::path::end())
+        .and(signer.clone())
+        .and(api_secret_filter)
+        .and(log_filter.clone())
+        .and_then(|signer, api_secret: ApiSecret, log: Logger| {
+            blocking_signed_json_task(signer, move || {
+                let token = api_secret.rotate().map_err(|e| {
+                    warp_utils::reject::custom_server_error(format!(
+                        "unable to rotate API token: {}",
+                        e
+                    ))
+                })?;
+                info!(
+                    log,
+                    "Rotated API token";
+                    "token_path" => ?api_secret.api_token_path(),
+                );
+                Ok(api_types::GenericResponse::from(
+                    api_types::RotateTokenResponse { token },
+                ))
+            })
+        });
+
     // Standard key-manager endpoints.
     let eth_v1 = warp::path("eth").and(warp::path("v1"));
     let std_keystores = eth_v1.and(warp::path("keystores")).and(warp::path::end());
@@ -768,16 +1045,17 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
                             validator_pubkey
                         )));
                     }
-                    validator_store
+                    let result = validator_store
                         .initialized_validators()
                         .write()
-                        .set_validator_fee_recipient(&validator_pubkey, request.ethaddress)
-                        .map_err(|e| {
-                            warp_utils::reject::custom_server_error(format!(
-                                "Error persisting fee recipient: {:?}",
-                                e
-                            ))
-                        })
+                        .set_validator_fee_recipient(&validator_pubkey, request.ethaddress);
+                    record_fee_recipient_change(metrics::SET, &result);
+                    result.map_err(|e| {
+                        warp_utils::reject::custom_server_error(format!(
+                            "Error persisting fee recipient: {:?}",
+                            e
+                        ))
+                    })
                 })
             },
         )
@@ -805,16 +1083,17 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
                             validator_pubkey
                         )));
                     }
-                    validator_store
+                    let result = validator_store
                         .initialized_validators()
                         .write()
-                        .delete_validator_fee_recipient(&validator_pubkey)
-                        .map_err(|e| {
-                            warp_utils::reject::custom_server_error(format!(
-                                "Error persisting fee recipient removal: {:?}",
-                                e
-                            ))
-                        })
+                        .delete_validator_fee_recipient(&validator_pubkey);
+                    record_fee_recipient_change(metrics::DELETE, &result);
+                    result.map_err(|e| {
+                        warp_utils::reject::custom_server_error(format!(
+                            "Error persisting fee recipient removal: {:?}",
+                            e
+                        ))
+                    })
                 })
             },
         )
@@ -950,16 +1229,25 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
              signer,
              task_executor: TaskExecutor| {
                 blocking_signed_json_task(signer, move || {
+                    check_slashing_protection_available(&validator_store)?;
                     if let Some(handle) = task_executor.handle() {
-                        let signed_voluntary_exit =
-                            handle.block_on(create_signed_voluntary_exit(
-                                pubkey,
-                                query.epoch,
-                                validator_store,
-                                slot_clock,
-                                log,
-                            ))?;
-                        Ok(signed_voluntary_exit)
+                        let signed_voluntary_exit = handle.block_on(create_signed_voluntary_exit(
+                            pubkey,
+                            query.epoch,
+                            validator_store,
+                            slot_clock,
+                            log,
+                        ));
+                        let result = if signed_voluntary_exit.is_ok() {
+                            metrics::SUCCESS
+                        } else {
+                            metrics::ERROR
+                        };
+                        metrics::inc_counter_vec(
+                            &metrics::KEYMANAGER_EXITS_SIGNED_TOTAL,
+                            &[result],
+                        );
+                        signed_voluntary_exit
                     } else {
                         Err(warp_utils::reject::custom_server_error(
                             "Lighthouse shutting down".into(),
@@ -988,6 +1276,7 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
         .and_then(
             |request, signer, validator_dir, validator_store, task_executor, log| {
                 blocking_signed_json_task(signer, move || {
+                    check_slashing_protection_available(&validator_store)?;
                     keystores::import(request, validator_dir, validator_store, task_executor, log)
                 })
             },
@@ -1006,6 +1295,22 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
             })
         });
 
+    // POST /eth/v1/validator/slashing-protection
+    let post_std_slashing_protection = eth_v1
+        .and(warp::path("validator"))
+        .and(warp::path("slashing-protection"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(signer.clone())
+        .and(validator_store_filter.clone())
+        .and(log_filter.clone())
+        .and_then(|request, signer, validator_store, log| {
+            blocking_signed_json_task(signer, move || {
+                check_slashing_protection_available(&validator_store)?;
+                keystores::import_slashing_protection(request, validator_store, log)
+            })
+        });
+
     // GET /eth/v1/remotekeys
     let get_std_remotekeys = std_remotekeys
         .and(signer.clone())
@@ -1023,6 +1328,7 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
         .and(log_filter.clone())
         .and_then(|request, signer, validator_store, task_executor, log| {
             blocking_signed_json_task(signer, move || {
+                check_slashing_protection_available(&validator_store)?;
                 remotekeys::import(request, validator_store, task_executor, log)
             })
         });
@@ -1096,9 +1402,13 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
                         .or(get_lighthouse_health)
                         .or(get_lighthouse_spec)
                         .or(get_lighthouse_validators)
+                        .or(get_lighthouse_validators_definitions)
                         .or(get_lighthouse_validators_pubkey)
                         .or(get_lighthouse_ui_health)
                         .or(get_lighthouse_ui_graffiti)
+                        .or(get_lighthouse_validators_pubkey_settings)
+                        .or(get_lighthouse_validators_pubkey_slashing_protection_summary)
+                        .or(get_lighthouse_duties)
                         .or(get_fee_recipient)
                         .or(get_gas_limit)
                         .or(get_std_keystores)
@@ -1109,11 +1419,14 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
                         .or(post_validators_keystore)
                         .or(post_validators_mnemonic)
                         .or(post_validators_web3signer)
+                        .or(post_validators_register)
                         .or(post_validators_voluntary_exits)
                         .or(post_fee_recipient)
                         .or(post_gas_limit)
                         .or(post_std_keystores)
-                        .or(post_std_remotekeys),
+                        .or(post_std_slashing_protection)
+                        .or(post_std_remotekeys)
+                        .or(post_auth_rotate_token),
                 ))
                 .or(warp::patch().and(patch_validators))
                 .or(warp::delete().and(
@@ -1148,6 +1461,45 @@ pub fn serve<T: 'static + SlotClock + Clone, E: EthSpec>(
     Ok((listening_socket, server))
 }
 
+/// Returns a 503 error if the slashing protection database of `validator_store` is unavailable.
+///
+/// Used by endpoints which import keys or sign messages, so that they fail before making any
+/// changes rather than part way through.
+fn check_slashing_protection_available<T: SlotClock + 'static, E: EthSpec>(
+    validator_store: &ValidatorStore<T, E>,
+) -> Result<(), warp::Rejection> {
+    validator_store
+        .check_slashing_protection_available()
+        .map_err(|e| {
+            warp_utils::reject::custom_service_unavailable(format!(
+                "slashing protection database is unavailable: {:?}",
+                e
+            ))
+        })
+}
+
+/// Counts a fee recipient `operation` made via the keymanager API by its result.
+fn record_fee_recipient_change<T, U>(operation: &str, result: &Result<T, U>) {
+    let result = if result.is_ok() {
+        metrics::SUCCESS
+    } else {
+        metrics::ERROR
+    };
+    metrics::inc_counter_vec(
+        &metrics::KEYMANAGER_FEE_RECIPIENT_CHANGES_TOTAL,
+        &[operation, result],
+    );
+}
+
+/// Returns the current epoch according to `slot_clock`, defaulting to the genesis epoch if the
+/// clock cannot be read.
+fn current_epoch<E: EthSpec>(slot_clock: &impl SlotClock) -> Epoch {
+    slot_clock
+        .now_or_genesis()
+        .unwrap_or_else(|| slot_clock.genesis_slot())
+        .epoch(E::slots_per_epoch())
+}
+
 /// Executes `func` in blocking tokio task (i.e., where long-running tasks are permitted).
 /// JSON-encodes the return value of `func`, using the `signer` function to produce a signature of
 /// those bytes.
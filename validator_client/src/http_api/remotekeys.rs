@@ -1,5 +1,7 @@
 //! Implementation of the standard remotekey management API.
-use crate::{initialized_validators::Error, InitializedValidators, ValidatorStore};
+use crate::{
+    http_metrics::metrics, initialized_validators::Error, InitializedValidators, ValidatorStore,
+};
 use account_utils::validator_definitions::{
     SigningDefinition, ValidatorDefinition, Web3SignerDefinition,
 };
@@ -32,7 +34,7 @@ pub fn list<T: SlotClock + 'static, E: EthSpec>(
             let validating_pubkey = def.voting_public_key.compress();
 
             match &def.signing_definition {
-                SigningDefinition::LocalKeystore { .. } => None,
+                SigningDefinition::LocalKeystore { .. } | SigningDefinition::Pkcs11(_) => None,
                 SigningDefinition::Web3Signer(Web3SignerDefinition { url, .. }) => {
                     Some(SingleListRemotekeysResponse {
                         pubkey: validating_pubkey,
@@ -83,6 +85,10 @@ pub fn import<T: SlotClock + 'static, E: EthSpec>(
                 "validator client shutdown".into(),
             )
         };
+        metrics::inc_counter_vec(
+            &metrics::KEYMANAGER_KEYS_IMPORTED_TOTAL,
+            &[metrics::WEB3SIGNER, import_status_label(status.status)],
+        );
         statuses.push(status);
     }
     Ok(ImportRemotekeysResponse { data: statuses })
@@ -141,6 +147,15 @@ fn import_single_remotekey<T: SlotClock + 'static, E: EthSpec>(
     Ok(ImportRemotekeyStatus::Imported)
 }
 
+/// Returns the metrics label for an import `status`, as it appears in the API response.
+fn import_status_label(status: ImportRemotekeyStatus) -> &'static str {
+    match status {
+        ImportRemotekeyStatus::Imported => "imported",
+        ImportRemotekeyStatus::Duplicate => "duplicate",
+        ImportRemotekeyStatus::Error => metrics::ERROR,
+    }
+}
+
 pub fn delete<T: SlotClock + 'static, E: EthSpec>(
     request: DeleteRemotekeysRequest,
     validator_store: Arc<ValidatorStore<T, E>>,
@@ -188,9 +203,25 @@ pub fn delete<T: SlotClock + 'static, E: EthSpec>(
             .map_err(|e| custom_server_error(format!("unable to update key cache: {:?}", e)))?;
     }
 
+    for status in &statuses {
+        metrics::inc_counter_vec(
+            &metrics::KEYMANAGER_KEYS_DELETED_TOTAL,
+            &[metrics::WEB3SIGNER, delete_status_label(status.status)],
+        );
+    }
+
     Ok(DeleteRemotekeysResponse { data: statuses })
 }
 
+/// Returns the metrics label for a deletion `status`, as it appears in the API response.
+fn delete_status_label(status: DeleteRemotekeyStatus) -> &'static str {
+    match status {
+        DeleteRemotekeyStatus::Deleted => "deleted",
+        DeleteRemotekeyStatus::NotFound => metrics::NOT_FOUND,
+        DeleteRemotekeyStatus::Error => metrics::ERROR,
+    }
+}
+
 fn delete_single_remotekey(
     pubkey_bytes: &PublicKeyBytes,
     initialized_validators: &mut InitializedValidators,
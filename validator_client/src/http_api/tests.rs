@@ -2,34 +2,49 @@
 #![cfg(not(debug_assertions))]
 
 mod keystores;
+mod mock_beacon_node;
 
+use crate::attestation_service::AttestationServiceBuilder;
+use crate::block_service::BlockServiceBuilder;
 use crate::doppelganger_service::DoppelgangerService;
+use crate::duties_service::{self, DutiesBatchSizes, DutiesService};
+use crate::validator_index_cache::ValidatorIndexCache;
+use crate::validator_store::DEFAULT_GAS_LIMIT;
 use crate::{
     http_api::{ApiSecret, Config as HttpConfig, Context},
-    initialized_validators::InitializedValidators,
+    initialized_validators::{Config as InitializedValidatorsConfig, InitializedValidators},
     Config, ValidatorDefinitions, ValidatorStore,
 };
 use account_utils::{
-    eth2_wallet::WalletBuilder, mnemonic_from_phrase, random_mnemonic, random_password,
-    random_password_string, ZeroizeString,
+    eth2_wallet::WalletBuilder,
+    mnemonic_from_phrase, random_mnemonic, random_password, random_password_string,
+    validator_definitions::{SigningDefinition, REDACTED},
+    ZeroizeString,
 };
 use deposit_contract::decode_eth1_tx_data;
+use environment::RuntimeContext;
 use eth2::{
     lighthouse_vc::{http_client::ValidatorClientHttpClient, types::*},
-    types::ErrorMessage as ApiErrorMessage,
+    types::{AttesterData, ErrorMessage as ApiErrorMessage},
     Error as ApiError,
 };
 use eth2_keystore::KeystoreBuilder;
 use logging::test_logger;
+use mock_beacon_node::MockBeaconNode;
 use parking_lot::RwLock;
 use sensitive_url::SensitiveUrl;
-use slashing_protection::{SlashingDatabase, SLASHING_PROTECTION_FILENAME};
+use slashing_protection::{
+    interchange::{Interchange, InterchangeMetadata},
+    NotSafe, SlashingDatabase, SLASHING_PROTECTION_FILENAME, SUPPORTED_INTERCHANGE_FORMAT_VERSION,
+};
 use slot_clock::{SlotClock, TestingSlotClock};
+use std::collections::HashSet;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 use std::time::Duration;
 use task_executor::TaskExecutor;
 use tempfile::{tempdir, TempDir};
@@ -46,10 +61,12 @@ struct ApiTester {
     client: ValidatorClientHttpClient,
     initialized_validators: Arc<RwLock<InitializedValidators>>,
     validator_store: Arc<ValidatorStore<TestingSlotClock, E>>,
+    slashing_protection: SlashingDatabase,
     url: SensitiveUrl,
     slot_clock: TestingSlotClock,
+    executor: TaskExecutor,
     _server_shutdown: oneshot::Sender<()>,
-    _validator_dir: TempDir,
+    validator_dir: TempDir,
     _runtime_shutdown: exit_future::Signal,
 }
 
@@ -65,6 +82,13 @@ fn build_runtime() -> Arc<Runtime> {
 
 impl ApiTester {
     pub async fn new(runtime: std::sync::Weak<Runtime>) -> Self {
+        Self::new_with_initialized_validators_config(runtime, Default::default()).await
+    }
+
+    pub async fn new_with_initialized_validators_config(
+        runtime: std::sync::Weak<Runtime>,
+        initialized_validators_config: InitializedValidatorsConfig,
+    ) -> Self {
         let log = test_logger();
 
         let validator_dir = tempdir().unwrap();
@@ -75,6 +99,7 @@ impl ApiTester {
         let initialized_validators = InitializedValidators::from_definitions(
             validator_defs,
             validator_dir.path().into(),
+            initialized_validators_config,
             log.clone(),
         )
         .await
@@ -106,7 +131,7 @@ impl ApiTester {
 
         let validator_store = Arc::new(ValidatorStore::<_, E>::new(
             initialized_validators,
-            slashing_protection,
+            slashing_protection.clone(),
             Hash256::repeat_byte(42),
             spec.clone(),
             Some(Arc::new(DoppelgangerService::new(log.clone()))),
@@ -123,12 +148,15 @@ impl ApiTester {
         let initialized_validators = validator_store.initialized_validators();
 
         let context = Arc::new(Context {
-            task_executor: executor,
+            task_executor: executor.clone(),
             api_secret,
             validator_dir: Some(validator_dir.path().into()),
             validator_store: Some(validator_store.clone()),
+            preparation_service: None,
+            duties_service: None,
             graffiti_file: None,
             graffiti_flag: Some(Graffiti::default()),
+            graffiti_force: false,
             spec: E::default_spec(),
             config: HttpConfig {
                 enabled: true,
@@ -164,14 +192,30 @@ impl ApiTester {
             client,
             initialized_validators,
             validator_store,
+            slashing_protection,
             url,
             slot_clock,
+            executor,
             _server_shutdown: shutdown_tx,
-            _validator_dir: validator_dir,
+            validator_dir,
             _runtime_shutdown: runtime_shutdown,
         }
     }
 
+    /// Returns a context in which to run the validator client services.
+    fn runtime_context(&self) -> RuntimeContext<E> {
+        let mut context = RuntimeContext {
+            executor: self.executor.clone(),
+            eth_spec_instance: E::default(),
+            eth2_config: Default::default(),
+            eth2_network_config: None,
+            sse_logging_components: None,
+        };
+        context.eth2_config.eth_spec_id = E::spec_name();
+        context.eth2_config.spec = E::default_spec();
+        context
+    }
+
     pub fn invalid_token_client(&self) -> ValidatorClientHttpClient {
         let tmp = tempdir().unwrap();
         let api_secret = ApiSecret::create_or_open(tmp.path()).unwrap();
@@ -219,6 +263,132 @@ impl ApiTester {
         self
     }
 
+    pub async fn test_rotate_api_token(mut self) -> Self {
+        let old_client = self.client.clone();
+        let token = self
+            .client
+            .post_lighthouse_auth_rotate_token()
+            .await
+            .unwrap()
+            .data
+            .token;
+
+        // The previous token is no longer accepted.
+        match old_client.get_lighthouse_version().await {
+            Err(ApiError::ServerMessage(ApiErrorMessage { code: 403, .. })) => (),
+            Err(other) => panic!("expected authorized error, got {:?}", other),
+            Ok(_) => panic!("expected authorized error, got Ok"),
+        }
+
+        // The new keypair has been written to disk with the correct permissions.
+        let token_path = PathBuf::from(self.client.get_auth().await.unwrap().token_path);
+        assert_eq!(std::fs::read_to_string(&token_path).unwrap(), token);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&token_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let api_secret = ApiSecret::create_or_open(token_path.parent().unwrap()).unwrap();
+        assert_eq!(api_secret.api_token(), token);
+
+        self.client = ValidatorClientHttpClient::new(self.url.clone(), token).unwrap();
+        self
+    }
+
+    pub async fn test_register_validators_without_preparation_service(self) -> Self {
+        match self
+            .client
+            .post_lighthouse_validators_register(&RegisterValidatorsRequest::default())
+            .await
+        {
+            Err(ApiError::ServerMessage(ApiErrorMessage { code: 404, .. })) => (),
+            Err(other) => panic!("expected not found error, got {:?}", other),
+            Ok(_) => panic!("expected not found error, got Ok"),
+        }
+        self
+    }
+
+    pub async fn test_get_duties_without_duties_service(self) -> Self {
+        match self.client.get_lighthouse_duties(Epoch::new(0)).await {
+            Err(ApiError::ServerMessage(ApiErrorMessage { code: 404, .. })) => (),
+            Err(other) => panic!("expected not found error, got {:?}", other),
+            Ok(_) => panic!("expected not found error, got Ok"),
+        }
+        self
+    }
+
+    /// Holds the only connection to the slashing protection database from another thread, making
+    /// it unavailable to the API until the returned sender is dropped.
+    fn lock_slashing_protection(&self) -> mpsc::Sender<()> {
+        let (locked_tx, locked_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let slashing_protection = self.slashing_protection.clone();
+        std::thread::spawn(move || {
+            slashing_protection
+                .with_transaction(|_| {
+                    locked_tx.send(()).unwrap();
+                    let _ = release_rx.recv();
+                    Ok::<_, NotSafe>(())
+                })
+                .unwrap();
+        });
+        locked_rx.recv().unwrap();
+        release_tx
+    }
+
+    pub async fn test_slashing_protection_unavailable(self) -> Self {
+        let initial_vals = self.vals_total();
+        let release = self.lock_slashing_protection();
+
+        let password = random_password_string();
+        let keypair = Keypair::random();
+        let keystore = KeystoreBuilder::new(&keypair, password.as_ref(), String::new())
+            .unwrap()
+            .build()
+            .unwrap();
+        let request = KeystoreValidatorsPostRequest {
+            enable: true,
+            password,
+            keystore,
+            graffiti: None,
+            suggested_fee_recipient: None,
+            gas_limit: None,
+            builder_proposals: None,
+        };
+        match self
+            .client
+            .post_lighthouse_validators_keystore(&request)
+            .await
+        {
+            Err(ApiError::ServerMessage(ApiErrorMessage { code: 503, .. })) => (),
+            Err(other) => panic!("expected service unavailable error, got {:?}", other),
+            Ok(_) => panic!("expected service unavailable error, got Ok"),
+        }
+        assert_eq!(self.vals_total(), initial_vals);
+
+        let pubkey = keypair.pk.compress();
+        match self
+            .client
+            .post_validator_voluntary_exit(&pubkey, None)
+            .await
+        {
+            Err(ApiError::ServerMessage(ApiErrorMessage { code: 503, .. })) => (),
+            Err(other) => panic!("expected service unavailable error, got {:?}", other),
+            Ok(_) => panic!("expected service unavailable error, got Ok"),
+        }
+
+        // The keystore can be imported once the database is available again.
+        drop(release);
+        self.client
+            .post_lighthouse_validators_keystore(&request)
+            .await
+            .unwrap();
+        assert_eq!(self.vals_total(), initial_vals + 1);
+
+        self
+    }
+
     pub async fn test_get_lighthouse_spec(self) -> Self {
         let result = self
             .client
@@ -504,6 +674,34 @@ impl ApiTester {
         self
     }
 
+    pub async fn test_get_lighthouse_validators_definitions(self) -> Self {
+        let definitions = self
+            .client
+            .get_lighthouse_validators_definitions()
+            .await
+            .unwrap()
+            .data;
+
+        assert_eq!(definitions.len(), self.vals_total());
+
+        for definition in definitions {
+            if let SigningDefinition::LocalKeystore {
+                voting_keystore_password_path,
+                voting_keystore_password,
+                ..
+            } = definition.signing_definition
+            {
+                assert!(voting_keystore_password_path
+                    .map_or(true, |path| path == PathBuf::from(REDACTED)));
+                assert!(
+                    voting_keystore_password.map_or(true, |password| password.as_str() == REDACTED)
+                );
+            }
+        }
+
+        self
+    }
+
     pub async fn test_sign_voluntary_exits(self, index: usize, maybe_epoch: Option<Epoch>) -> Self {
         let validator = &self.client.get_lighthouse_validators().await.unwrap().data[index];
         // manually setting validator index in `ValidatorStore`
@@ -573,6 +771,206 @@ impl ApiTester {
         self
     }
 
+    pub async fn assert_doppelganger_disabled_before_shutdown(self, index: usize) -> Self {
+        let validator = &self.client.get_lighthouse_validators().await.unwrap().data[index];
+        let voting_pubkey = validator.voting_pubkey;
+        let voting_public_key = voting_pubkey.decompress().unwrap();
+
+        let doppelganger = DoppelgangerService::new(test_logger()).disable_detected();
+        doppelganger.record_detection(voting_pubkey);
+
+        let validator_dir = self.validator_dir.path().to_path_buf();
+        let mut did_shutdown = false;
+        doppelganger
+            .disable_detected_and_shutdown(&self.validator_store, true, &mut || {
+                // The validator definitions on disk are updated before shutting down.
+                let definitions = ValidatorDefinitions::open(&validator_dir).unwrap();
+                let definition = definitions
+                    .as_slice()
+                    .iter()
+                    .find(|def| def.voting_public_key == voting_public_key)
+                    .unwrap();
+                assert!(!definition.enabled);
+                did_shutdown = true;
+            })
+            .await;
+        assert!(did_shutdown);
+
+        assert!(!self
+            .initialized_validators
+            .read()
+            .is_enabled(&voting_public_key)
+            .unwrap());
+
+        self
+    }
+
+    pub async fn assert_duplicate_attester_duties_attested_once(self, index: usize) -> Self {
+        let validator = &self.client.get_lighthouse_validators().await.unwrap().data[index];
+        let pubkey = validator.voting_pubkey;
+        let validator_index = index as u64;
+        self.initialized_validators
+            .write()
+            .set_index(&pubkey, validator_index);
+
+        let slot = Slot::new(1);
+        let epoch = slot.epoch(E::slots_per_epoch());
+        self.slot_clock.set_slot(slot.as_u64());
+
+        // The beacon node returns the same duty several times.
+        let duty = AttesterData {
+            pubkey,
+            validator_index,
+            committees_at_slot: 1,
+            committee_index: 0,
+            committee_length: 1,
+            validator_committee_index: 0,
+            slot,
+        };
+        let beacon_node = MockBeaconNode::new(&E::default_spec());
+        *beacon_node.attester_duties.write() = vec![duty.clone(), duty.clone(), duty];
+
+        let context = self.runtime_context();
+        let beacon_nodes = beacon_node
+            .beacon_nodes(&context.eth2_config.spec, test_logger())
+            .await;
+        let config = Config::default();
+        let duties_service = Arc::new(DutiesService {
+            attesters: <_>::default(),
+            proposers: <_>::default(),
+            sync_duties: <_>::default(),
+            slot_clock: self.slot_clock.clone(),
+            beacon_nodes: beacon_nodes.clone(),
+            validator_index_cache: parking_lot::Mutex::new(ValidatorIndexCache::new(
+                self.validator_dir.path(),
+            )),
+            validator_store: self.validator_store.clone(),
+            spec: context.eth2_config.spec.clone(),
+            context: context.clone(),
+            enable_high_validator_count_metrics: false,
+            metrics_validator_bucketing: config.metrics_validator_bucketing,
+            min_balance_alert_gwei: None,
+            auto_disable_inactive: false,
+            index_resolution_interval: config.index_resolution_interval,
+            unresolved_indices: <_>::default(),
+            validator_statuses: <_>::default(),
+            activation_poll_epochs: config.activation_poll_epochs,
+            duties_batch_sizes: DutiesBatchSizes::new(config.duties_batch_size),
+        });
+
+        duties_service::poll_beacon_attesters_for_epoch(
+            &duties_service,
+            epoch,
+            &[validator_index],
+            &HashSet::from([pubkey]),
+        )
+        .await
+        .unwrap();
+
+        // A single duty is stored for the assignment.
+        assert_eq!(duties_service.attesters.read()[&pubkey].len(), 1);
+        let duties = duties_service.attesters(slot);
+        assert_eq!(duties.len(), 1);
+
+        // The validator attests once.
+        let attestation_service = AttestationServiceBuilder::new()
+            .duties_service(duties_service.clone())
+            .validator_store(self.validator_store.clone())
+            .slot_clock(self.slot_clock.clone())
+            .beacon_nodes(beacon_nodes)
+            .runtime_context(context)
+            .build()
+            .unwrap();
+        attestation_service
+            .produce_and_publish_attestations(slot, 0, &duties)
+            .await
+            .unwrap();
+        assert_eq!(beacon_node.published_attestations.read().len(), 1);
+
+        self
+    }
+
+    pub async fn assert_prefetched_blocks_proposed_once(self, index: usize) -> Self {
+        let validator = &self.client.get_lighthouse_validators().await.unwrap().data[index];
+        let pubkey = validator.voting_pubkey;
+        let validator_index = index as u64;
+        self.initialized_validators
+            .write()
+            .set_index(&pubkey, validator_index);
+
+        let beacon_node = MockBeaconNode::new(&E::default_spec());
+        *beacon_node.proposer_index.write() = validator_index;
+
+        let context = self.runtime_context();
+        let beacon_nodes = beacon_node
+            .beacon_nodes(&context.eth2_config.spec, test_logger())
+            .await;
+        let block_service = BlockServiceBuilder::new()
+            .validator_store(self.validator_store.clone())
+            .slot_clock(self.slot_clock.clone())
+            .beacon_nodes(beacon_nodes)
+            .runtime_context(context)
+            .build()
+            .unwrap();
+
+        // The prefetched block is proposed if the head has not changed.
+        self.slot_clock.set_slot(0);
+        block_service
+            .prefetch_block::<FullPayload<E>>(Slot::new(1), pubkey)
+            .await
+            .unwrap();
+        self.slot_clock.set_slot(1);
+        block_service
+            .clone()
+            .publish_block::<FullPayload<E>>(Slot::new(1), pubkey)
+            .await
+            .unwrap();
+        assert_eq!(*beacon_node.produced_blocks.read(), 1);
+        assert_eq!(beacon_node.published_blocks.read().len(), 1);
+
+        // The prefetched block is discarded if the head has changed, and a block is produced on
+        // the new head instead.
+        block_service
+            .prefetch_block::<FullPayload<E>>(Slot::new(2), pubkey)
+            .await
+            .unwrap();
+        let new_head = Hash256::repeat_byte(2);
+        *beacon_node.head.write() = new_head;
+        self.slot_clock.set_slot(2);
+        block_service
+            .clone()
+            .publish_block::<FullPayload<E>>(Slot::new(2), pubkey)
+            .await
+            .unwrap();
+        assert_eq!(*beacon_node.produced_blocks.read(), 3);
+        let published_blocks = beacon_node.published_blocks.read().clone();
+        assert_eq!(published_blocks.len(), 2);
+        assert_eq!(published_blocks[1].slot(), Slot::new(2));
+        assert_eq!(published_blocks[1].parent_root(), new_head);
+
+        // A prefetched block is proposed at most once, and no other block is signed for its slot.
+        self.slot_clock.set_slot(2);
+        block_service
+            .prefetch_block::<FullPayload<E>>(Slot::new(3), pubkey)
+            .await
+            .unwrap();
+        self.slot_clock.set_slot(3);
+        block_service
+            .clone()
+            .publish_block::<FullPayload<E>>(Slot::new(3), pubkey)
+            .await
+            .unwrap();
+        block_service
+            .clone()
+            .publish_block::<FullPayload<E>>(Slot::new(3), pubkey)
+            .await
+            .unwrap_err();
+        assert_eq!(*beacon_node.produced_blocks.read(), 5);
+        assert_eq!(beacon_node.published_blocks.read().len(), 3);
+
+        self
+    }
+
     pub async fn set_gas_limit(self, index: usize, gas_limit: u64) -> Self {
         let validator = &self.client.get_lighthouse_validators().await.unwrap().data[index];
 
@@ -657,6 +1055,121 @@ impl ApiTester {
 
         self
     }
+
+    pub async fn assert_validator_settings(
+        self,
+        index: usize,
+        gas_limit: ResolvedSetting<u64>,
+        graffiti: ResolvedSetting<Option<String>>,
+    ) -> Self {
+        let validator = &self.client.get_lighthouse_validators().await.unwrap().data[index];
+
+        let settings = self
+            .client
+            .get_lighthouse_validators_pubkey_settings(&validator.voting_pubkey)
+            .await
+            .unwrap()
+            .unwrap()
+            .data;
+
+        assert_eq!(settings.voting_pubkey, validator.voting_pubkey);
+        assert_eq!(
+            settings.fee_recipient,
+            ResolvedSetting::new(Some(TEST_DEFAULT_FEE_RECIPIENT), SettingSource::Global)
+        );
+        assert_eq!(settings.gas_limit, gas_limit);
+        assert_eq!(settings.graffiti, graffiti);
+        assert_eq!(
+            settings.builder_proposals,
+            ResolvedSetting::new(false, SettingSource::Global)
+        );
+
+        self
+    }
+
+    pub async fn assert_slashing_protection_summary(
+        self,
+        index: usize,
+        max_block_slot: Option<u64>,
+        max_attestation: Option<(u64, u64)>,
+    ) -> Self {
+        let validator = &self.client.get_lighthouse_validators().await.unwrap().data[index];
+
+        let summary = self
+            .client
+            .get_lighthouse_validators_pubkey_slashing_protection_summary(&validator.voting_pubkey)
+            .await
+            .unwrap()
+            .unwrap()
+            .data;
+
+        assert_eq!(summary.voting_pubkey, validator.voting_pubkey);
+        assert_eq!(summary.max_block_slot, max_block_slot.map(Slot::new));
+        assert_eq!(
+            summary.max_attestation_source_epoch,
+            max_attestation.map(|(source, _)| Epoch::new(source))
+        );
+        assert_eq!(
+            summary.max_attestation_target_epoch,
+            max_attestation.map(|(_, target)| Epoch::new(target))
+        );
+
+        self
+    }
+
+    pub fn sign_slashing_protection_records(
+        self,
+        index: usize,
+        block_slot: u64,
+        attestation: (u64, u64),
+    ) -> Self {
+        let pubkey = self.initialized_validators.read().validator_definitions()[index]
+            .voting_public_key
+            .compress();
+        self.slashing_protection
+            .check_and_insert_block_signing_root(
+                &pubkey,
+                Slot::new(block_slot),
+                Hash256::repeat_byte(1).into(),
+            )
+            .unwrap();
+        self.slashing_protection
+            .check_and_insert_attestation_signing_root(
+                &pubkey,
+                Epoch::new(attestation.0),
+                Epoch::new(attestation.1),
+                Hash256::repeat_byte(2).into(),
+            )
+            .unwrap();
+
+        self
+    }
+
+    pub async fn assert_unknown_validator_slashing_protection_summary(self) -> Self {
+        let summary = self
+            .client
+            .get_lighthouse_validators_pubkey_slashing_protection_summary(
+                &Keypair::random().pk.compress(),
+            )
+            .await
+            .unwrap();
+
+        assert!(summary.is_none());
+
+        self
+    }
+
+    pub async fn assert_unknown_validator_settings(self) -> Self {
+        let settings = self
+            .client
+            .get_lighthouse_validators_pubkey_settings(&Keypair::random().pk.compress())
+            .await
+            .unwrap();
+
+        assert!(settings.is_none());
+
+        self
+    }
 }
 
 struct HdValidatorScenario {
@@ -708,12 +1221,30 @@ fn routes_with_invalid_auth() {
                 |client| async move { client.get_lighthouse_validators().await },
             )
             .await
+            .test_with_invalid_auth(|client| async move {
+                client.get_lighthouse_validators_definitions().await
+            })
+            .await
             .test_with_invalid_auth(|client| async move {
                 client
                     .get_lighthouse_validators_pubkey(&PublicKeyBytes::empty())
                     .await
             })
             .await
+            .test_with_invalid_auth(|client| async move {
+                client
+                    .get_lighthouse_validators_pubkey_settings(&PublicKeyBytes::empty())
+                    .await
+            })
+            .await
+            .test_with_invalid_auth(|client| async move {
+                client
+                    .get_lighthouse_validators_pubkey_slashing_protection_summary(
+                        &PublicKeyBytes::empty(),
+                    )
+                    .await
+            })
+            .await
             .test_with_invalid_auth(|client| async move {
                 client
                     .post_lighthouse_validators(vec![ValidatorRequest {
@@ -770,6 +1301,20 @@ fn routes_with_invalid_auth() {
                     .await
             })
             .await
+            .test_with_invalid_auth(|client| async move {
+                client.post_lighthouse_auth_rotate_token().await
+            })
+            .await
+            .test_with_invalid_auth(|client| async move {
+                client
+                    .post_lighthouse_validators_register(&RegisterValidatorsRequest::default())
+                    .await
+            })
+            .await
+            .test_with_invalid_auth(|client| async move {
+                client.get_lighthouse_duties(Epoch::new(0)).await
+            })
+            .await
             .test_with_invalid_auth(|client| async move { client.get_keystores().await })
             .await
             .test_with_invalid_auth(|client| async move {
@@ -789,6 +1334,20 @@ fn routes_with_invalid_auth() {
                     .await
             })
             .await
+            .test_with_invalid_auth(|client| async move {
+                client
+                    .post_slashing_protection(&ImportSlashingProtectionRequest {
+                        slashing_protection: InterchangeJsonStr(Interchange {
+                            metadata: InterchangeMetadata {
+                                interchange_format_version: SUPPORTED_INTERCHANGE_FORMAT_VERSION,
+                                genesis_validators_root: Hash256::zero(),
+                            },
+                            data: vec![],
+                        }),
+                    })
+                    .await
+            })
+            .await
             .test_with_invalid_auth(|client| async move {
                 let keypair = Keypair::random();
                 client
@@ -817,6 +1376,60 @@ fn simple_getters() {
     });
 }
 
+#[test]
+fn register_validators_without_preparation_service() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .test_register_validators_without_preparation_service()
+            .await;
+    });
+}
+
+#[test]
+fn get_duties_without_duties_service() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .test_get_duties_without_duties_service()
+            .await;
+    });
+}
+
+#[test]
+fn slashing_protection_unavailable() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .test_slashing_protection_unavailable()
+            .await;
+    });
+}
+
+#[test]
+fn rotate_api_token() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .test_rotate_api_token()
+            .await
+            .test_get_lighthouse_version()
+            .await
+            .test_rotate_api_token()
+            .await
+            .test_get_lighthouse_version()
+            .await;
+    });
+}
+
 #[test]
 fn hd_validator_creation() {
     let runtime = build_runtime();
@@ -906,6 +1519,66 @@ fn validator_enabling() {
     });
 }
 
+#[test]
+fn doppelganger_disabled_before_shutdown() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .create_hd_validators(HdValidatorScenario {
+                count: 2,
+                specify_mnemonic: false,
+                key_derivation_path_offset: 0,
+                disabled: vec![],
+            })
+            .await
+            .assert_enabled_validators_count(2)
+            .assert_doppelganger_disabled_before_shutdown(0)
+            .await
+            .assert_enabled_validators_count(1)
+            .assert_validators_count(2);
+    });
+}
+
+#[test]
+fn duplicate_attester_duties_attested_once() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .create_hd_validators(HdValidatorScenario {
+                count: 1,
+                specify_mnemonic: false,
+                key_derivation_path_offset: 0,
+                disabled: vec![],
+            })
+            .await
+            .assert_duplicate_attester_duties_attested_once(0)
+            .await;
+    });
+}
+
+#[test]
+fn prefetched_blocks_proposed_once() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .create_hd_validators(HdValidatorScenario {
+                count: 1,
+                specify_mnemonic: false,
+                key_derivation_path_offset: 0,
+                disabled: vec![],
+            })
+            .await
+            .assert_prefetched_blocks_proposed_once(0)
+            .await;
+    });
+}
+
 #[test]
 fn validator_gas_limit() {
     let runtime = build_runtime();
@@ -1009,6 +1682,82 @@ fn validator_graffiti() {
     });
 }
 
+#[test]
+fn validator_settings() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .create_hd_validators(HdValidatorScenario {
+                count: 2,
+                specify_mnemonic: false,
+                key_derivation_path_offset: 0,
+                disabled: vec![],
+            })
+            .await
+            .assert_unknown_validator_settings()
+            .await
+            .assert_validator_settings(
+                0,
+                ResolvedSetting::new(DEFAULT_GAS_LIMIT, SettingSource::Default),
+                ResolvedSetting::new(
+                    Some(Graffiti::default().as_utf8_lossy()),
+                    SettingSource::Global,
+                ),
+            )
+            .await
+            .set_gas_limit(0, 500)
+            .await
+            .set_graffiti(0, "Mr F was here")
+            .await
+            .assert_validator_settings(
+                0,
+                ResolvedSetting::new(500, SettingSource::Keymanager),
+                ResolvedSetting::new(Some("Mr F was here".into()), SettingSource::Keymanager),
+            )
+            .await
+            // The other validator should be unaffected.
+            .assert_validator_settings(
+                1,
+                ResolvedSetting::new(DEFAULT_GAS_LIMIT, SettingSource::Default),
+                ResolvedSetting::new(
+                    Some(Graffiti::default().as_utf8_lossy()),
+                    SettingSource::Global,
+                ),
+            )
+            .await;
+    });
+}
+
+#[test]
+fn validator_slashing_protection_summary() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .create_hd_validators(HdValidatorScenario {
+                count: 2,
+                specify_mnemonic: false,
+                key_derivation_path_offset: 0,
+                disabled: vec![],
+            })
+            .await
+            .assert_unknown_validator_slashing_protection_summary()
+            .await
+            .assert_slashing_protection_summary(0, None, None)
+            .await
+            .sign_slashing_protection_records(0, 10, (2, 3))
+            .sign_slashing_protection_records(0, 12, (3, 4))
+            .assert_slashing_protection_summary(0, Some(12), Some((3, 4)))
+            .await
+            // The other validator should be unaffected.
+            .assert_slashing_protection_summary(1, None, None)
+            .await;
+    });
+}
+
 #[test]
 fn keystore_validator_creation() {
     let runtime = build_runtime();
@@ -1042,6 +1791,29 @@ fn keystore_validator_creation() {
     });
 }
 
+#[test]
+fn validators_definitions_are_redacted() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        ApiTester::new(weak_runtime)
+            .await
+            .create_keystore_validators(KeystoreValidatorScenario {
+                correct_password: true,
+                enabled: true,
+            })
+            .await
+            .create_web3signer_validators(Web3SignerValidatorScenario {
+                count: 1,
+                enabled: true,
+            })
+            .await
+            .assert_validators_count(2)
+            .test_get_lighthouse_validators_definitions()
+            .await;
+    });
+}
+
 #[test]
 fn web3signer_validator_creation() {
     let runtime = build_runtime();
@@ -10,7 +10,10 @@ use eth2::lighthouse_vc::{
 };
 use itertools::Itertools;
 use rand::{rngs::SmallRng, Rng, SeedableRng};
-use slashing_protection::interchange::{Interchange, InterchangeMetadata};
+use slashing_protection::interchange::{
+    Interchange, InterchangeData, InterchangeMetadata, SignedAttestation as InterchangeAttestation,
+};
+use slashing_protection::SUPPORTED_INTERCHANGE_FORMAT_VERSION;
 use std::{collections::HashMap, path::Path};
 use types::Address;
 
@@ -252,6 +255,97 @@ fn import_new_keystores() {
     })
 }
 
+#[test]
+fn import_keystores_over_validator_count_limit() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        let config = InitializedValidatorsConfig {
+            max_validators: Some(2),
+            ..Default::default()
+        };
+        let tester = ApiTester::new_with_initialized_validators_config(weak_runtime, config).await;
+        let password = random_password_string();
+        let keystores = (0..3)
+            .map(|_| new_keystore(password.clone()))
+            .collect::<Vec<_>>();
+
+        let import_res = tester
+            .client
+            .post_keystores(&ImportKeystoresRequest {
+                keystores: keystores.clone(),
+                passwords: vec![password.clone(); keystores.len()],
+                slashing_protection: None,
+            })
+            .await
+            .unwrap();
+
+        // The keystore over the limit should fail to import rather than be silently ignored.
+        check_keystore_import_response(&import_res, all_imported(2).chain(all_import_error(1)));
+
+        // Check that GET lists only the imported keystores.
+        let get_res = tester.client.get_keystores().await.unwrap();
+        check_keystore_get_response(&get_res, &keystores[..2]);
+    })
+}
+
+#[test]
+fn enable_validator_over_validator_count_limit() {
+    let runtime = build_runtime();
+    let weak_runtime = Arc::downgrade(&runtime);
+    runtime.block_on(async {
+        let config = InitializedValidatorsConfig {
+            max_validators: Some(1),
+            ..Default::default()
+        };
+        let tester = ApiTester::new_with_initialized_validators_config(weak_runtime, config).await;
+        let password = random_password_string();
+        let keystores = (0..2)
+            .map(|_| new_keystore(password.clone()))
+            .collect::<Vec<_>>();
+        let pubkeys = keystores.iter().map(keystore_pubkey).collect::<Vec<_>>();
+
+        let import_keystore = |keystore: &Keystore| ImportKeystoresRequest {
+            keystores: vec![keystore.clone()],
+            passwords: vec![password.clone()],
+            slashing_protection: None,
+        };
+
+        // Import the first keystore and disable it, freeing up the only slot.
+        let import_res = tester
+            .client
+            .post_keystores(&import_keystore(&keystores[0]))
+            .await
+            .unwrap();
+        check_keystore_import_response(&import_res, all_imported(1));
+        tester
+            .client
+            .patch_lighthouse_validators(&pubkeys[0], Some(false), None, None, None)
+            .await
+            .unwrap();
+
+        // Import the second keystore into the free slot.
+        let import_res = tester
+            .client
+            .post_keystores(&import_keystore(&keystores[1]))
+            .await
+            .unwrap();
+        check_keystore_import_response(&import_res, all_imported(1));
+
+        // Re-enabling the first validator would exceed the limit, so it should fail.
+        tester
+            .client
+            .patch_lighthouse_validators(&pubkeys[0], Some(true), None, None, None)
+            .await
+            .unwrap_err();
+
+        let vals = tester.initialized_validators.read();
+        assert!(!vals.is_enabled(&pubkeys[0].decompress().unwrap()).unwrap());
+        assert!(vals.is_enabled(&pubkeys[1].decompress().unwrap()).unwrap());
+        assert_eq!(vals.num_enabled(), 1);
+    })
+}
+
 #[test]
 fn import_only_duplicate_keystores() {
     run_test(|tester| async move {
@@ -591,6 +685,103 @@ fn import_invalid_slashing_protection() {
     })
 }
 
+#[test]
+fn import_some_failed_keystores_with_slashing_protection() {
+    run_test(|tester| async move {
+        let _ = &tester;
+        let num_keystores = 4;
+        let (keystores, correct_passwords): (Vec<_>, Vec<_>) = (0..num_keystores)
+            .map(|_| {
+                let password = random_password_string();
+                (new_keystore(password.clone()), password)
+            })
+            .unzip();
+
+        // Slashing protection data for all keys, with an attestation from epoch 5 to 6.
+        let slashing_protection = Interchange {
+            metadata: InterchangeMetadata {
+                interchange_format_version: SUPPORTED_INTERCHANGE_FORMAT_VERSION,
+                genesis_validators_root: Hash256::repeat_byte(42),
+            },
+            data: keystores
+                .iter()
+                .map(|keystore| InterchangeData {
+                    pubkey: keystore_pubkey(keystore),
+                    signed_blocks: vec![],
+                    signed_attestations: vec![InterchangeAttestation {
+                        source_epoch: Epoch::new(5),
+                        target_epoch: Epoch::new(6),
+                        signing_root: None,
+                    }],
+                })
+                .collect(),
+        };
+
+        // Import with incorrect passwords for the even keys, which fail to import.
+        let passwords = (0..num_keystores)
+            .map(|i| {
+                if i % 2 == 0 {
+                    random_password_string()
+                } else {
+                    correct_passwords[i].clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        let import_res = tester
+            .client
+            .post_keystores(&ImportKeystoresRequest {
+                keystores: keystores.clone(),
+                passwords,
+                slashing_protection: Some(InterchangeJsonStr(slashing_protection)),
+            })
+            .await
+            .unwrap();
+        let expected_statuses = (0..num_keystores).map(|i| {
+            if i % 2 == 0 {
+                ImportKeystoreStatus::Error
+            } else {
+                ImportKeystoreStatus::Imported
+            }
+        });
+        check_keystore_import_response(&import_res, expected_statuses);
+
+        let get_res = tester.client.get_keystores().await.unwrap();
+        check_keystore_get_response(&get_res, keystores.iter().skip(1).step_by(2));
+
+        // Import the failed keys again without slashing protection data.
+        let import_res = tester
+            .client
+            .post_keystores(&ImportKeystoresRequest {
+                keystores: keystores.iter().step_by(2).cloned().collect(),
+                passwords: correct_passwords.iter().step_by(2).cloned().collect(),
+                slashing_protection: None,
+            })
+            .await
+            .unwrap();
+        check_keystore_import_response(&import_res, all_imported(num_keystores / 2));
+
+        // The slashing protection data of the failed keys was not imported, so only they can
+        // sign an attestation from before epoch 5.
+        for (i, keystore) in keystores.iter().enumerate() {
+            let mut attestation = make_attestation(1, 2);
+            let result = tester
+                .validator_store
+                .sign_attestation(
+                    keystore_pubkey(keystore),
+                    0,
+                    &mut attestation,
+                    Epoch::new(2),
+                )
+                .await;
+            if i % 2 == 0 {
+                assert_eq!(result, Ok(()));
+            } else {
+                assert!(result.is_err());
+            }
+        }
+    })
+}
+
 #[test]
 fn check_get_set_fee_recipient() {
     run_test(|tester: ApiTester| async move {
@@ -770,6 +961,150 @@ fn check_get_set_fee_recipient() {
     })
 }
 
+#[test]
+fn concurrent_settings_changes() {
+    run_test(|tester: ApiTester| async move {
+        let password = random_password_string();
+        let keystores = (0..8)
+            .map(|_| new_keystore(password.clone()))
+            .collect::<Vec<_>>();
+        let all_pubkeys = keystores.iter().map(keystore_pubkey).collect::<Vec<_>>();
+
+        let import_res = tester
+            .client
+            .post_keystores(&ImportKeystoresRequest {
+                keystores: keystores.clone(),
+                passwords: vec![password.clone(); keystores.len()],
+                slashing_protection: None,
+            })
+            .await
+            .unwrap();
+        check_keystore_import_response(&import_res, all_imported(keystores.len()));
+
+        let fee_recipient = |i: usize| Address::from_low_u64_be(i as u64 + 1);
+        let gas_limit = |i: usize| 20_000_000 + i as u64;
+
+        // Change the fee recipient and gas limit of every validator at once, each change
+        // rewriting the definitions file.
+        let client = &tester.client;
+        let fee_recipient_changes = all_pubkeys
+            .iter()
+            .enumerate()
+            .map(|(i, pubkey)| async move {
+                client
+                    .post_fee_recipient(
+                        pubkey,
+                        &UpdateFeeRecipientRequest {
+                            ethaddress: fee_recipient(i),
+                        },
+                    )
+                    .await
+                    .expect("should update fee recipient");
+            });
+        let gas_limit_changes = all_pubkeys
+            .iter()
+            .enumerate()
+            .map(|(i, pubkey)| async move {
+                client
+                    .post_gas_limit(
+                        pubkey,
+                        &UpdateGasLimitRequest {
+                            gas_limit: gas_limit(i),
+                        },
+                    )
+                    .await
+                    .expect("should update gas limit");
+            });
+        futures::future::join(
+            futures::future::join_all(fee_recipient_changes),
+            futures::future::join_all(gas_limit_changes),
+        )
+        .await;
+
+        // The definitions file should be valid and hold every change.
+        let definitions = ValidatorDefinitions::open(tester.validator_dir.path()).unwrap();
+        assert_eq!(definitions.as_slice().len(), all_pubkeys.len());
+        for (i, pubkey) in all_pubkeys.iter().enumerate() {
+            let def = definitions
+                .as_slice()
+                .iter()
+                .find(|def| def.voting_public_key.compress() == *pubkey)
+                .expect("definition should exist");
+            assert_eq!(def.suggested_fee_recipient, Some(fee_recipient(i)));
+            assert_eq!(def.gas_limit, Some(gas_limit(i)));
+        }
+    })
+}
+
+#[test]
+fn get_settings_match_resolved_settings() {
+    run_test(|tester: ApiTester| async move {
+        let password = random_password_string();
+        let keystores = (0..4)
+            .map(|_| new_keystore(password.clone()))
+            .collect::<Vec<_>>();
+        let all_pubkeys = keystores.iter().map(keystore_pubkey).collect::<Vec<_>>();
+
+        let import_res = tester
+            .client
+            .post_keystores(&ImportKeystoresRequest {
+                keystores: keystores.clone(),
+                passwords: vec![password.clone(); keystores.len()],
+                slashing_protection: None,
+            })
+            .await
+            .unwrap();
+        check_keystore_import_response(&import_res, all_imported(keystores.len()));
+
+        // Override a different setting for each of the first three validators, leaving the last
+        // with the defaults.
+        tester
+            .client
+            .post_fee_recipient(
+                &all_pubkeys[0],
+                &UpdateFeeRecipientRequest {
+                    ethaddress: Address::repeat_byte(0x42),
+                },
+            )
+            .await
+            .unwrap();
+        tester
+            .client
+            .post_gas_limit(
+                &all_pubkeys[1],
+                &UpdateGasLimitRequest {
+                    gas_limit: 40_000_000,
+                },
+            )
+            .await
+            .unwrap();
+        tester
+            .client
+            .patch_lighthouse_validators(&all_pubkeys[2], None, None, Some(true), None)
+            .await
+            .unwrap();
+
+        let validator_store = &tester.validator_store;
+        for pubkey in &all_pubkeys {
+            let fee_recipient = validator_store.resolve_fee_recipient(pubkey).value;
+            let gas_limit = validator_store.resolve_gas_limit(pubkey).value;
+            let builder_proposals = validator_store.resolve_builder_proposals(pubkey).value;
+
+            assert_eq!(validator_store.get_fee_recipient(pubkey), fee_recipient);
+            assert_eq!(validator_store.get_gas_limit(pubkey), gas_limit);
+            assert_eq!(
+                validator_store.get_builder_proposals(pubkey),
+                builder_proposals
+            );
+
+            let proposal_data = validator_store.proposal_data(pubkey).unwrap();
+            assert_eq!(proposal_data.fee_recipient, fee_recipient);
+            assert_eq!(proposal_data.gas_limit, gas_limit);
+            assert_eq!(proposal_data.builder_proposals, builder_proposals);
+        }
+    })
+}
+
 #[test]
 fn check_get_set_gas_limit() {
     run_test(|tester: ApiTester| async move {
@@ -1398,6 +1733,183 @@ fn delete_then_reimport() {
     })
 }
 
+fn check_slashing_protection_import_response(
+    response: &ImportSlashingProtectionResponse,
+    expected_statuses: impl IntoIterator<Item = ImportSlashingProtectionStatus>,
+) {
+    for (single_response, expected_status) in response.data.iter().zip_eq(expected_statuses) {
+        assert_eq!(
+            expected_status, single_response.status.status,
+            "{:?}",
+            single_response.status.message
+        );
+    }
+}
+
+#[test]
+fn slashing_protection_round_trip() {
+    run_dual_vc_test(|tester1, tester2| async move {
+        let _ = (&tester1, &tester2);
+        let password = random_password_string();
+        let keystores = (0..3)
+            .map(|_| new_keystore(password.clone()))
+            .collect::<Vec<_>>();
+        let import_req = ImportKeystoresRequest {
+            keystores: keystores.clone(),
+            passwords: vec![password.clone(); keystores.len()],
+            slashing_protection: None,
+        };
+
+        // Sign an attestation from epoch 5 to 6 with each key on VC1, then export the slashing
+        // protection data by deleting the keys.
+        let import_res = tester1.client.post_keystores(&import_req).await.unwrap();
+        check_keystore_import_response(&import_res, all_imported(keystores.len()));
+        for keystore in &keystores {
+            tester1
+                .validator_store
+                .sign_attestation(
+                    keystore_pubkey(keystore),
+                    0,
+                    &mut make_attestation(5, 6),
+                    Epoch::new(6),
+                )
+                .await
+                .unwrap();
+        }
+        let delete_res = tester1
+            .client
+            .delete_keystores(&DeleteKeystoresRequest {
+                pubkeys: keystores.iter().map(keystore_pubkey).collect(),
+            })
+            .await
+            .unwrap();
+        check_keystore_delete_response(&delete_res, all_deleted(keystores.len()));
+
+        // Import the slashing protection data into VC2 on its own, then import the keys.
+        let import_res = tester2
+            .client
+            .post_slashing_protection(&ImportSlashingProtectionRequest {
+                slashing_protection: InterchangeJsonStr(delete_res.slashing_protection),
+            })
+            .await
+            .unwrap();
+        check_slashing_protection_import_response(
+            &import_res,
+            all_with_status(keystores.len(), ImportSlashingProtectionStatus::Imported),
+        );
+        let import_res = tester2.client.post_keystores(&import_req).await.unwrap();
+        check_keystore_import_response(&import_res, all_imported(keystores.len()));
+
+        // VC2 refuses to sign the attestations already signed on VC1, but signs newer ones.
+        for keystore in &keystores {
+            let pubkey = keystore_pubkey(keystore);
+            for (source_epoch, target_epoch, should_succeed) in [(5, 6, false), (6, 7, true)] {
+                let result = tester2
+                    .validator_store
+                    .sign_attestation(
+                        pubkey,
+                        0,
+                        &mut make_attestation(source_epoch, target_epoch),
+                        Epoch::new(target_epoch),
+                    )
+                    .await;
+                assert_eq!(result.is_ok(), should_succeed, "{:?}", result);
+            }
+        }
+    })
+}
+
+#[test]
+fn import_slashing_protection_never_lowers_protection() {
+    run_test(|tester| async move {
+        let _ = &tester;
+        let password = random_password_string();
+        let keystore = new_keystore(password.clone());
+        let pubkey = keystore_pubkey(&keystore);
+        let import_res = tester
+            .client
+            .post_keystores(&ImportKeystoresRequest {
+                keystores: vec![keystore],
+                passwords: vec![password],
+                slashing_protection: None,
+            })
+            .await
+            .unwrap();
+        check_keystore_import_response(&import_res, all_imported(1));
+
+        tester
+            .validator_store
+            .sign_attestation(pubkey, 0, &mut make_attestation(10, 11), Epoch::new(11))
+            .await
+            .unwrap();
+
+        // Import older data for the key, which is merged with the existing records.
+        let slashing_protection = Interchange {
+            metadata: InterchangeMetadata {
+                interchange_format_version: SUPPORTED_INTERCHANGE_FORMAT_VERSION,
+                genesis_validators_root: Hash256::repeat_byte(42),
+            },
+            data: vec![InterchangeData {
+                pubkey,
+                signed_blocks: vec![],
+                signed_attestations: vec![InterchangeAttestation {
+                    source_epoch: Epoch::new(1),
+                    target_epoch: Epoch::new(2),
+                    signing_root: None,
+                }],
+            }],
+        };
+        let import_res = tester
+            .client
+            .post_slashing_protection(&ImportSlashingProtectionRequest {
+                slashing_protection: InterchangeJsonStr(slashing_protection),
+            })
+            .await
+            .unwrap();
+        check_slashing_protection_import_response(
+            &import_res,
+            all_with_status(1, ImportSlashingProtectionStatus::Imported),
+        );
+
+        // The existing records still refuse attestations below them.
+        let result = tester
+            .validator_store
+            .sign_attestation(pubkey, 0, &mut make_attestation(5, 6), Epoch::new(11))
+            .await;
+        assert!(result.is_err());
+        let summary = tester
+            .client
+            .get_lighthouse_validators_pubkey_slashing_protection_summary(&pubkey)
+            .await
+            .unwrap()
+            .unwrap()
+            .data;
+        assert_eq!(summary.max_attestation_target_epoch, Some(Epoch::new(11)));
+    })
+}
+
+#[test]
+fn import_invalid_slashing_protection_only() {
+    run_test(|tester| async move {
+        let _ = &tester;
+        // Slashing protection data with a mismatched GVR is rejected as a whole.
+        let slashing_protection = Interchange {
+            metadata: InterchangeMetadata {
+                interchange_format_version: SUPPORTED_INTERCHANGE_FORMAT_VERSION,
+                genesis_validators_root: Hash256::zero(),
+            },
+            data: vec![],
+        };
+        let result = tester
+            .client
+            .post_slashing_protection(&ImportSlashingProtectionRequest {
+                slashing_protection: InterchangeJsonStr(slashing_protection),
+            })
+            .await;
+        assert_eq!(result.unwrap_err().status().map(|s| s.as_u16()), Some(400));
+    })
+}
+
 #[test]
 fn get_empty_remotekeys() {
     run_test(|tester| async move {
@@ -0,0 +1,213 @@
+//! A beacon node which serves fixed responses to the requests of the validator client services.
+
+use super::*;
+use crate::beacon_node_fallback::{BeaconNodeFallback, CandidateBeaconNode};
+use eth2::types::{
+    AttesterData, DutiesResponse, ExecutionOptimisticFinalizedResponse, GenericResponse, RootData,
+    SyncingData, ValidatorAttestationDataQuery, VersionData,
+};
+use eth2::{BeaconNodeHttpClient, Timeouts};
+use slog::Logger;
+use std::net::SocketAddr;
+use types::{
+    Attestation, AttestationData, BeaconBlock, ChainSpec, ConfigAndPreset, ForkVersionedResponse,
+    SignedBeaconBlock,
+};
+use warp::Filter;
+
+pub struct MockBeaconNode {
+    pub url: SensitiveUrl,
+    /// The attester duties returned for every epoch.
+    pub attester_duties: Arc<RwLock<Vec<AttesterData>>>,
+    /// The attestations published to the beacon node.
+    pub published_attestations: Arc<RwLock<Vec<Attestation<E>>>>,
+    /// The head block root, which is the parent root of the blocks produced.
+    pub head: Arc<RwLock<Hash256>>,
+    /// The proposer index of the blocks produced.
+    pub proposer_index: Arc<RwLock<u64>>,
+    /// The number of blocks produced, which is also the state root of the last one so that every
+    /// block is distinct.
+    pub produced_blocks: Arc<RwLock<u64>>,
+    /// The blocks published to the beacon node.
+    pub published_blocks: Arc<RwLock<Vec<SignedBeaconBlock<E>>>>,
+    _server_shutdown: oneshot::Sender<()>,
+}
+
+impl MockBeaconNode {
+    pub fn new(spec: &ChainSpec) -> Self {
+        let attester_duties = Arc::new(RwLock::new(vec![]));
+        let published_attestations = Arc::new(RwLock::new(vec![]));
+        let head = Arc::new(RwLock::new(Hash256::repeat_byte(1)));
+        let proposer_index = Arc::new(RwLock::new(0));
+        let produced_blocks = Arc::new(RwLock::new(0));
+        let published_blocks = Arc::new(RwLock::new(vec![]));
+
+        let eth_v1 = warp::path("eth").and(warp::path("v1"));
+        let eth_v2 = warp::path("eth").and(warp::path("v2"));
+
+        let get_node_version = eth_v1
+            .and(warp::path!("node" / "version"))
+            .and(warp::get())
+            .map(|| {
+                warp::reply::json(&GenericResponse::from(VersionData {
+                    version: "Lighthouse/mock".to_string(),
+                }))
+            });
+
+        let get_node_syncing = eth_v1
+            .and(warp::path!("node" / "syncing"))
+            .and(warp::get())
+            .map(|| {
+                warp::reply::json(&GenericResponse::from(SyncingData {
+                    is_syncing: false,
+                    is_optimistic: Some(false),
+                    el_offline: Some(false),
+                    head_slot: Slot::new(0),
+                    sync_distance: Slot::new(0),
+                }))
+            });
+
+        let config_and_preset = ConfigAndPreset::from_chain_spec::<E>(spec, None);
+        let get_config_spec = eth_v1
+            .and(warp::path!("config" / "spec"))
+            .and(warp::get())
+            .map(move || warp::reply::json(&GenericResponse::from(config_and_preset.clone())));
+
+        let duties = attester_duties.clone();
+        let post_attester_duties = eth_v1
+            .and(warp::path!("validator" / "duties" / "attester" / u64))
+            .and(warp::post())
+            .map(move |_epoch| {
+                warp::reply::json(&DutiesResponse {
+                    dependent_root: Hash256::zero(),
+                    execution_optimistic: Some(false),
+                    data: duties.read().clone(),
+                })
+            });
+
+        let get_attestation_data = eth_v1
+            .and(warp::path!("validator" / "attestation_data"))
+            .and(warp::get())
+            .and(warp::query::<ValidatorAttestationDataQuery>())
+            .map(|query: ValidatorAttestationDataQuery| {
+                warp::reply::json(&GenericResponse::from(AttestationData {
+                    slot: query.slot,
+                    index: query.committee_index,
+                    ..AttestationData::default()
+                }))
+            });
+
+        let attestations = published_attestations.clone();
+        let post_attestations = eth_v1
+            .and(warp::path!("beacon" / "pool" / "attestations"))
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |mut body: Vec<Attestation<E>>| {
+                attestations.write().append(&mut body);
+                warp::reply()
+            });
+
+        let block_spec = spec.clone();
+        let (block_head, block_proposer_index, blocks_produced) = (
+            head.clone(),
+            proposer_index.clone(),
+            produced_blocks.clone(),
+        );
+        let get_validator_blocks = eth_v2
+            .and(warp::path!("validator" / "blocks" / Slot))
+            .and(warp::get())
+            .map(move |slot: Slot| {
+                let mut produced_blocks = blocks_produced.write();
+                *produced_blocks += 1;
+
+                let mut block = BeaconBlock::<E>::empty(&block_spec);
+                *block.slot_mut() = slot;
+                *block.proposer_index_mut() = *block_proposer_index.read();
+                *block.parent_root_mut() = *block_head.read();
+                *block.state_root_mut() = Hash256::from_low_u64_be(*produced_blocks);
+                warp::reply::json(&ForkVersionedResponse {
+                    version: Some(block_spec.fork_name_at_slot::<E>(slot)),
+                    data: block,
+                })
+            });
+
+        let head_root = head.clone();
+        let get_head_root = eth_v1
+            .and(warp::path!("beacon" / "blocks" / "head" / "root"))
+            .and(warp::get())
+            .map(move || {
+                warp::reply::json(&ExecutionOptimisticFinalizedResponse {
+                    execution_optimistic: Some(false),
+                    finalized: Some(false),
+                    data: RootData::from(*head_root.read()),
+                })
+            });
+
+        let blocks = published_blocks.clone();
+        let post_blocks = eth_v1
+            .and(warp::path!("beacon" / "blocks"))
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |block: SignedBeaconBlock<E>| {
+                blocks.write().push(block);
+                warp::reply()
+            });
+
+        let routes = get_node_version
+            .or(get_node_syncing)
+            .or(get_config_spec)
+            .or(post_attester_duties)
+            .or(get_attestation_data)
+            .or(post_attestations)
+            .or(get_validator_blocks)
+            .or(get_head_root)
+            .or(post_blocks);
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let (listening_socket, server) = warp::serve(routes).bind_with_graceful_shutdown(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0),
+            async {
+                // It's not really interesting why this triggered, just that it happened.
+                let _ = shutdown_rx.await;
+            },
+        );
+        tokio::spawn(server);
+
+        let url = SensitiveUrl::parse(&format!(
+            "http://{}:{}",
+            listening_socket.ip(),
+            listening_socket.port()
+        ))
+        .unwrap();
+
+        Self {
+            url,
+            attester_duties,
+            published_attestations,
+            head,
+            proposer_index,
+            produced_blocks,
+            published_blocks,
+            _server_shutdown: shutdown_tx,
+        }
+    }
+
+    /// Returns a fallback containing only `self`, which is ready to be used.
+    pub async fn beacon_nodes(
+        &self,
+        spec: &ChainSpec,
+        log: Logger,
+    ) -> Arc<BeaconNodeFallback<TestingSlotClock, E>> {
+        let beacon_node =
+            BeaconNodeHttpClient::new(self.url.clone(), Timeouts::set_all(Duration::from_secs(12)));
+        let beacon_nodes = BeaconNodeFallback::new(
+            vec![CandidateBeaconNode::new(beacon_node)],
+            false,
+            spec.clone(),
+            log,
+        );
+        beacon_nodes.update_all_candidates().await;
+        assert_eq!(beacon_nodes.num_available().await, 1);
+        Arc::new(beacon_nodes)
+    }
+}
@@ -5,6 +5,8 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use types::EthSpec;
 
 pub const SUCCESS: &str = "success";
+pub const ERROR: &str = "error";
+pub const NOT_FOUND: &str = "not_found";
 pub const SLASHABLE: &str = "slashable";
 pub const SAME_DATA: &str = "same_data";
 pub const UNREGISTERED: &str = "unregistered";
@@ -14,10 +16,13 @@ pub const BEACON_BLOCK_HTTP_GET: &str = "beacon_block_http_get";
 pub const BLINDED_BEACON_BLOCK_HTTP_GET: &str = "blinded_beacon_block_http_get";
 pub const BEACON_BLOCK_HTTP_POST: &str = "beacon_block_http_post";
 pub const BLINDED_BEACON_BLOCK_HTTP_POST: &str = "blinded_beacon_block_http_post";
+pub const BLOCKS: &str = "blocks";
 pub const ATTESTATIONS: &str = "attestations";
 pub const ATTESTATIONS_HTTP_GET: &str = "attestations_http_get";
 pub const ATTESTATIONS_HTTP_POST: &str = "attestations_http_post";
 pub const AGGREGATES: &str = "aggregates";
+pub const SYNC_COMMITTEE_MESSAGES: &str = "sync_committee_messages";
+pub const SYNC_COMMITTEE_CONTRIBUTIONS: &str = "sync_committee_contributions";
 pub const AGGREGATES_HTTP_GET: &str = "aggregates_http_get";
 pub const AGGREGATES_HTTP_POST: &str = "aggregates_http_post";
 pub const CURRENT_EPOCH: &str = "current_epoch";
@@ -36,6 +41,16 @@ pub const ATTESTATION_SELECTION_PROOFS: &str = "attestation_selection_proofs";
 pub const SUBSCRIPTIONS: &str = "subscriptions";
 pub const LOCAL_KEYSTORE: &str = "local_keystore";
 pub const WEB3SIGNER: &str = "web3signer";
+pub const PKCS11: &str = "pkcs11";
+pub const BEACON_NODES: &str = "beacon_nodes";
+pub const PROPOSER_NODES: &str = "proposer_nodes";
+pub const ATTESTATION_FALLBACK_NODES: &str = "attestation_fallback_nodes";
+pub const SET: &str = "set";
+pub const DELETE: &str = "delete";
+pub const PREFETCH_FAILED: &str = "failed";
+pub const PREFETCH_LATE: &str = "late";
+pub const PREFETCH_HEAD_CHANGED: &str = "head_changed";
+pub const PREFETCH_HEAD_UNKNOWN: &str = "head_unknown";
 
 pub use lighthouse_metrics::*;
 
@@ -63,6 +78,11 @@ lazy_static::lazy_static! {
         "Total count of attempted Attestation signings",
         &["status"]
     );
+    pub static ref SLASHING_PROTECTION_RETRIES_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "vc_slashing_protection_retries_total",
+        "Total count of slashing protection database operations retried because the database was \
+         in use",
+    );
     pub static ref SIGNED_AGGREGATES_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
         "vc_signed_aggregates_total",
         "Total count of attempted SignedAggregateAndProof signings",
@@ -112,11 +132,31 @@ lazy_static::lazy_static! {
         "vc_slashing_protection_prune_times_seconds",
         "Time required to prune the slashing protection DB",
     );
+    pub static ref DUTIES_SKIPPED_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_duties_skipped_total",
+        "Total count of duties skipped by the validator client, by reason and type of duty",
+        &["reason", "type"]
+    );
+    pub static ref SLASHING_PROTECTION_PRUNED_RECORDS_TOTAL: Result<IntCounterVec> =
+        try_create_int_counter_vec(
+            "vc_slashing_protection_pruned_records_total",
+            "Total count of records removed from the slashing protection DB by pruning",
+            &["type"]
+        );
     pub static ref BLOCK_SERVICE_TIMES: Result<HistogramVec> = try_create_histogram_vec(
         "vc_beacon_block_service_task_times_seconds",
         "Duration to perform beacon block service tasks",
         &["task"]
     );
+    pub static ref BLOCK_PREFETCH_HITS_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "vc_block_prefetch_hits_total",
+        "Total count of blocks requested before their slot which were proposed",
+    );
+    pub static ref BLOCK_PREFETCH_MISSES_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_block_prefetch_misses_total",
+        "Total count of blocks requested before their slot which were discarded, by reason",
+        &["reason"]
+    );
     pub static ref PROPOSER_COUNT: Result<IntGaugeVec> = try_create_int_gauge_vec(
         "vc_beacon_block_proposer_count",
         "Number of beacon block proposers on this host",
@@ -144,6 +184,30 @@ lazy_static::lazy_static! {
         "The number of beacon node requests for each endpoint",
         &["endpoint"]
     );
+    pub static ref ATTESTATION_PUBLISH_REQUESTS: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_attestation_publish_requests_total",
+        "The number of requests to publish attestations to each beacon node",
+        &["endpoint"]
+    );
+    pub static ref ATTESTATION_PUBLISH_REJECTIONS: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_attestation_publish_rejections_total",
+        "The number of requests to publish attestations rejected by each beacon node",
+        &["endpoint"]
+    );
+    pub static ref SYNC_COMMITTEE_PUBLISH_REQUESTS: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_sync_committee_publish_requests_total",
+        "The number of requests to publish sync committee messages or contributions to each beacon node",
+        &["endpoint"]
+    );
+    pub static ref SYNC_COMMITTEE_PUBLISH_ACCEPTED: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_sync_committee_publish_accepted_total",
+        "The number of requests to publish sync committee messages or contributions accepted by each beacon node",
+        &["endpoint"]
+    );
+    pub static ref AGGREGATIONS_SHED: Result<IntCounter> = try_create_int_counter(
+        "vc_attestation_aggregations_shed_total",
+        "The number of pending aggregations dropped because of max-pending-aggregations",
+    );
 
     /*
     * Beacon node availability metrics
@@ -161,6 +225,69 @@ lazy_static::lazy_static! {
         "Total number of beacon nodes",
     );
 
+    pub static ref BEACON_NODE_DISTINCT_VERSIONS: Result<IntGauge> = try_create_int_gauge(
+        "vc_beacon_node_distinct_versions",
+        "Number of distinct client versions run by the reachable beacon nodes at startup",
+    );
+    pub static ref BEACON_NODE_TLS_CERT_EXPIRY_SECONDS: Result<IntGaugeVec> =
+        try_create_int_gauge_vec(
+            "vc_beacon_node_tls_cert_expiry_seconds",
+            "Seconds until the TLS certificate presented by the beacon node expires",
+            &["endpoint"]
+        );
+    pub static ref BEACON_NODE_TLS_CERT_EXPIRING: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_beacon_node_tls_cert_expiring",
+        "Set to 1 if the TLS certificate presented by the beacon node expires within the warning \
+         window, otherwise set to 0",
+        &["endpoint"]
+    );
+    pub static ref DIR_ACCESSIBLE: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_dir_accessible",
+        "Set to 1 if the directory could be read within the timeout, otherwise set to 0",
+        &["dir"]
+    );
+    pub static ref SLASHING_PROTECTION_DISK_FREE_BYTES: Result<IntGauge> = try_create_int_gauge(
+        "vc_slashing_protection_disk_free_bytes",
+        "Number of bytes available on the filesystem holding the slashing protection database",
+    );
+
+    pub static ref BEACON_NODES_CONFIGURED: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_beacon_nodes_configured",
+        "Number of configured beacon nodes, by kind (beacon_nodes or proposer_nodes)",
+        &["kind"]
+    );
+    pub static ref BEACON_NODES_AVAILABLE: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_beacon_nodes_available",
+        "Number of reachable and compatible beacon nodes, by kind",
+        &["kind"]
+    );
+    pub static ref BEACON_NODES_SYNCED: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_beacon_nodes_synced",
+        "Number of synced beacon nodes, by kind",
+        &["kind"]
+    );
+    pub static ref BEACON_NODE_SYNCED: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_beacon_node_synced",
+        "Set to 1 if the beacon node at the given index is synced, otherwise set to 0",
+        &["kind", "index"]
+    );
+    pub static ref BEACON_NODES_QUARANTINED: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_beacon_nodes_quarantined",
+        "Number of beacon nodes quarantined by the consensus check, by kind",
+        &["kind"]
+    );
+    pub static ref BEACON_NODE_QUARANTINED: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_beacon_node_quarantined",
+        "Set to 1 if the beacon node at the given index is quarantined, otherwise set to 0",
+        &["kind", "index"]
+    );
+    pub static ref BEACON_NODE_ACTIVE: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_beacon_node_active",
+        "Set to 1 if the beacon node at the given index served the most recent request, otherwise \
+         set to 0",
+        &["kind", "index"]
+    );
+
     pub static ref ETH2_FALLBACK_CONFIGURED: Result<IntGauge> = try_create_int_gauge(
         "sync_eth2_fallback_configured",
         "The number of configured eth2 fallbacks",
@@ -178,16 +305,112 @@ lazy_static::lazy_static! {
         "Duration to obtain a signature",
         &["type"]
     );
+    pub static ref SIGNING_CONCURRENCY: Result<IntGauge> = try_create_int_gauge(
+        "vc_signing_concurrency",
+        "Number of signing operations in progress, when limited by max-concurrent-signings",
+    );
+    pub static ref SIGNING_THROTTLE_OVERDUE_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "vc_signing_throttle_overdue_total",
+        "Number of signing operations which exceeded max-concurrent-signings to meet their deadline",
+    );
     pub static ref BLOCK_SIGNING_TIMES: Result<Histogram> = try_create_histogram(
         "vc_block_signing_times_seconds",
         "Duration to obtain a signature for a block",
     );
+    pub static ref DUTY_SIGNING_TIMES: Result<HistogramVec> = try_create_histogram_vec(
+        "vc_duty_signing_times_seconds",
+        "Wall-clock duration of signing operations, by duty",
+        &["type"]
+    );
+    pub static ref SLOW_SIGNINGS_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_slow_signings_total",
+        "Number of signing operations which exceeded slow-sign-threshold-ms, by duty",
+        &["type"]
+    );
+
+    /*
+     * Balance alerts
+     */
+    pub static ref MIN_BALANCE_ALERTS_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "vc_min_balance_alerts_total",
+        "Total count of alerts for validator effective balances below the alert threshold, \
+            raised when a validator falls below it or its status changes whilst below it",
+    );
+    pub static ref VALIDATORS_BELOW_MIN_BALANCE_COUNT: Result<IntGauge> = try_create_int_gauge(
+        "vc_validators_below_min_balance_count",
+        "Number of validators with an effective balance below the alert threshold",
+    );
+    pub static ref VALIDATORS_PENDING_ACTIVATION_COUNT: Result<IntGauge> = try_create_int_gauge(
+        "vc_validators_pending_activation_count",
+        "Number of validators known to the beacon chain which have not yet been activated",
+    );
+    pub static ref VALIDATORS_UNRESOLVED_INDEX_COUNT: Result<IntGauge> = try_create_int_gauge(
+        "vc_validators_unresolved_index_count",
+        "Number of validators which are not yet known to the beacon chain",
+    );
+
+    /*
+     * Doppelganger protection
+     */
+    pub static ref DOPPELGANGER_WAITING: Result<IntGauge> = try_create_int_gauge(
+        "vc_doppelganger_waiting",
+        "Number of validators waiting for doppelganger detection to complete",
+    );
+    pub static ref DOPPELGANGER_ACTIVE: Result<IntGauge> = try_create_int_gauge(
+        "vc_doppelganger_active",
+        "Number of validators that have completed doppelganger detection",
+    );
+    pub static ref DOPPELGANGER_CONTINUOUS_DETECTIONS_TOTAL: Result<IntCounter> =
+        try_create_int_counter(
+            "vc_doppelganger_continuous_detections_total",
+            "Number of validators found live in an epoch in which this validator client signed \
+             nothing for them, after doppelganger detection completed",
+        );
+    pub static ref DOPPELGANGER_DETECTED_TOTAL: Result<IntCounter> = try_create_int_counter(
+        "vc_doppelganger_detected_total",
+        "Number of local validators found live on the network by doppelganger detection, \
+         including continuous detection",
+    );
+
+    /*
+     * Keymanager API
+     */
+    pub static ref KEYMANAGER_KEYS_IMPORTED_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_keymanager_keys_imported_total",
+        "Total count of keys submitted for import via the keymanager API",
+        &["kind", "result"]
+    );
+    pub static ref KEYMANAGER_KEYS_DELETED_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_keymanager_keys_deleted_total",
+        "Total count of keys submitted for deletion via the keymanager API",
+        &["kind", "result"]
+    );
+    pub static ref KEYMANAGER_FEE_RECIPIENT_CHANGES_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_keymanager_fee_recipient_changes_total",
+        "Total count of fee recipients set or deleted via the keymanager API",
+        &["operation", "result"]
+    );
+    pub static ref KEYMANAGER_EXITS_SIGNED_TOTAL: Result<IntCounterVec> = try_create_int_counter_vec(
+        "vc_keymanager_exits_signed_total",
+        "Total count of voluntary exits requested via the keymanager API",
+        &["result"]
+    );
 
     pub static ref ATTESTATION_DUTY: Result<IntGaugeVec> = try_create_int_gauge_vec(
         "vc_attestation_duty_slot",
         "Attestation duty slot for all managed validators",
         &["validator"]
     );
+    pub static ref ATTESTATION_DUTY_BUCKET: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_attestation_duty_slot_bucket",
+        "Earliest upcoming attestation duty slot of the managed validators in each bucket",
+        &["bucket"]
+    );
+    pub static ref VALIDATORS_PER_METRICS_BUCKET: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "vc_validators_per_metrics_bucket",
+        "Number of managed validators in each bucket of the duty metrics",
+        &["bucket"]
+    );
     /*
      * BN latency
      */
@@ -200,6 +423,11 @@ lazy_static::lazy_static! {
         "vc_beacon_node_latency_primary_endpoint",
         "Round-trip latency for the primary BN endpoint",
     );
+    pub static ref VC_BEACON_NODE_LATENCY_WINDOW_AVERAGE: Result<GaugeVec> = try_create_float_gauge_vec(
+        "vc_beacon_node_latency_window_average_seconds",
+        "Average round-trip latency over the most recent measurements of each BN",
+        &["endpoint"]
+    );
 }
 
 pub fn gather_prometheus_metrics<T: EthSpec>(
@@ -6,7 +6,11 @@
 //! The `InitializedValidators` struct in this file serves as the source-of-truth of which
 //! validators are managed by this validator client.
 
-use crate::signing_method::SigningMethod;
+use crate::secrets_manifest::{self, SecretsManifest};
+use crate::signing_method::{
+    pkcs11::{Error as Pkcs11Error, Pkcs11Config, Pkcs11Token},
+    SigningMethod,
+};
 use account_utils::{
     read_password, read_password_from_user,
     validator_definitions::{
@@ -15,18 +19,24 @@ use account_utils::{
     },
     ZeroizeString,
 };
-use eth2_keystore::Keystore;
+use eth2_keystore::{Keystore, PlainText};
+use futures::stream::{self, StreamExt};
 use lighthouse_metrics::set_gauge;
 use lockfile::{Lockfile, LockfileError};
-use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
+use parking_lot::{MappedMutexGuard, Mutex, MutexGuard, RwLock};
 use reqwest::{Certificate, Client, Error as ReqwestError, Identity};
+use serde_derive::{Deserialize, Serialize};
 use slog::{debug, error, info, warn, Logger};
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::{self, Read};
+use std::io::{self, BufRead, BufReader, Read};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
+use strum::{Display, EnumString, EnumVariantNames};
 use types::graffiti::GraffitiString;
 use types::{Address, Graffiti, Keypair, PublicKey, PublicKeyBytes};
 use url::{ParseError, Url};
@@ -44,6 +54,67 @@ const DEFAULT_REMOTE_SIGNER_REQUEST_TIMEOUT: Duration = Duration::from_secs(12);
 // Use TTY instead of stdin to capture passwords from users.
 const USE_STDIN: bool = false;
 
+/// The action to take when a local keystore cannot be initialized at startup.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum OnKeystoreError {
+    /// Log the failing keystore and continue initializing the remaining validators.
+    Skip,
+    /// Refuse to start.
+    #[default]
+    Abort,
+}
+
+/// Configuration for the initialization of validators.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    pub on_keystore_error: OnKeystoreError,
+    /// The maximum number of enabled validators to initialize. Validators beyond the limit are
+    /// left uninitialized, selected in order of their voting public key.
+    pub max_validators: Option<usize>,
+    /// A file mapping keystore file names or voting public keys to keystore passwords, used for
+    /// local keystores without a `voting_keystore_password` or `voting_keystore_password_path`.
+    pub secrets_manifest: Option<PathBuf>,
+    /// The maximum number of keystores to decrypt at once. Defaults to the number of CPUs.
+    ///
+    /// Each decryption holds the memory required by the keystore's KDF (256 MiB for the default
+    /// scrypt parameters), so this also bounds the memory used while decrypting.
+    pub keystore_decrypt_concurrency: Option<usize>,
+    /// The PKCS#11 token holding the keys of `pkcs11` validators, if any.
+    pub pkcs11: Option<Pkcs11Config>,
+    /// If set, only these validators are initialized by `InitializedValidators::from_definitions`.
+    /// The initialization of the remaining enabled validators is deferred until
+    /// `InitializedValidators::initialize_deferred` is called.
+    pub load_priority: Option<HashSet<PublicKeyBytes>>,
+    /// If set, only validators with an index in the half-open range `[start, end)` perform duties.
+    /// Validators with an index outside of the range remain initialized, but are not returned by
+    /// `InitializedValidators::iter_voting_pubkeys`.
+    pub index_range: Option<(u64, u64)>,
+}
+
+impl Config {
+    /// Returns the maximum number of keystores to decrypt at once.
+    pub fn keystore_decrypt_concurrency(&self) -> usize {
+        self.keystore_decrypt_concurrency.unwrap_or_else(|| {
+            thread::available_parallelism()
+                .map(NonZeroUsize::get)
+                .unwrap_or(1)
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     /// Refused to open a validator with an existing lockfile since that validator may be in-use by
@@ -67,6 +138,10 @@ pub enum Error {
     UnableToDecryptKeystore(eth2_keystore::Error),
     /// There was a filesystem error when reading the keystore password from disk.
     UnableToReadVotingKeystorePassword(io::Error),
+    /// The secrets manifest could not be loaded.
+    UnableToLoadSecretsManifest(secrets_manifest::Error),
+    /// The secrets manifest has no password for these keystores, which have no other password.
+    MissingSecretsManifestPasswords(Vec<PathBuf>),
     /// There was an error updating the on-disk validator definitions file.
     UnableToSaveDefinitions(validator_definitions::Error),
     /// It is not legal to try and initialize a disabled validator definition.
@@ -98,6 +173,16 @@ pub enum Error {
     UnableToBuildWeb3SignerClient(ReqwestError),
     /// Unable to apply an action to a validator.
     InvalidActionOnValidator,
+    /// A `pkcs11` validator is defined but no PKCS#11 token is configured.
+    MissingPkcs11Config,
+    /// The PKCS#11 token could not be used.
+    Pkcs11(Pkcs11Error),
+    /// The validator load priority file could not be read.
+    UnableToReadLoadPriorityFile(io::Error),
+    /// The validator load priority file contains an invalid public key.
+    InvalidLoadPriorityFile(String),
+    /// Enabling another validator would exceed `Config::max_validators`.
+    ValidatorCountLimitReached(usize),
 }
 
 impl From<LockfileError> for Error {
@@ -128,8 +213,8 @@ impl InitializedValidator {
                 option_lockfile.as_mut()
             })
             .ok(),
-            // Web3Signer validators do not have any lockfiles.
-            SigningMethod::Web3Signer { .. } => None,
+            // Web3Signer and PKCS#11 validators do not have any lockfiles.
+            SigningMethod::Web3Signer { .. } | SigningMethod::Pkcs11 { .. } => None,
         }
     }
 
@@ -169,8 +254,9 @@ fn get_lockfile_path(file_path: &Path) -> Option<PathBuf> {
 impl InitializedValidator {
     /// Instantiate `self` from a `ValidatorDefinition`.
     ///
-    /// If `stdin.is_some()` any missing passwords will result in a prompt requesting input on
-    /// stdin (prompts published to stderr).
+    /// Any password missing from the definition is looked up in the `secrets_manifest`. If it is
+    /// not present there either, a prompt requesting input on stdin will be raised (prompts
+    /// published to stderr).
     ///
     /// ## Errors
     ///
@@ -180,6 +266,8 @@ impl InitializedValidator {
         key_cache: &mut KeyCache,
         key_stores: &mut HashMap<PathBuf, Keystore>,
         web3_signer_client_map: &mut Option<HashMap<Web3SignerDefinition, Client>>,
+        secrets_manifest: Option<&SecretsManifest>,
+        pkcs11_token: Option<&Arc<Pkcs11Token>>,
     ) -> Result<Self, Error> {
         if !def.enabled {
             return Err(Error::UnableToInitializeDisabledValidator);
@@ -204,22 +292,31 @@ impl InitializedValidator {
                 } else {
                     let keystore = voting_keystore.clone();
                     let keystore_path = voting_keystore_path.clone();
+                    let manifest_password = secrets_manifest
+                        .and_then(|manifest| {
+                            manifest.password(&def.voting_public_key, &voting_keystore_path)
+                        })
+                        .cloned();
                     // Decoding a local keystore can take several seconds, therefore it's best
                     // to keep if off the core executor. This also has the fortunate effect of
                     // interrupting the potentially long-running task during shut down.
                     let (password, keypair) = tokio::task::spawn_blocking(move || {
                         Result::<_, Error>::Ok(
-                            match (voting_keystore_password_path, voting_keystore_password) {
+                            match (
+                                voting_keystore_password_path,
+                                voting_keystore_password,
+                                manifest_password,
+                            ) {
                                 // If the password is supplied, use it and ignore the path
                                 // (if supplied).
-                                (_, Some(password)) => (
+                                (_, Some(password), _) => (
                                     password.as_ref().to_vec().into(),
                                     keystore
                                         .decrypt_keypair(password.as_ref())
                                         .map_err(Error::UnableToDecryptKeystore)?,
                                 ),
                                 // If only the path is supplied, use the path.
-                                (Some(path), None) => {
+                                (Some(path), None, _) => {
                                     let password = read_password(path)
                                         .map_err(Error::UnableToReadVotingKeystorePassword)?;
                                     let keypair = keystore
@@ -227,8 +324,15 @@ impl InitializedValidator {
                                         .map_err(Error::UnableToDecryptKeystore)?;
                                     (password, keypair)
                                 }
+                                // If neither is supplied, use the secrets manifest.
+                                (None, None, Some(password)) => (
+                                    password.as_ref().to_vec().into(),
+                                    keystore
+                                        .decrypt_keypair(password.as_ref())
+                                        .map_err(Error::UnableToDecryptKeystore)?,
+                                ),
                                 // If there is no password available, maybe prompt for a password.
-                                (None, None) => {
+                                (None, None, None) => {
                                     let (password, keypair) = unlock_keystore_via_stdin_password(
                                         &keystore,
                                         &keystore_path,
@@ -309,6 +413,23 @@ impl InitializedValidator {
                     voting_public_key: def.voting_public_key,
                 }
             }
+            SigningDefinition::Pkcs11(pkcs11) => {
+                let token = pkcs11_token.ok_or(Error::MissingPkcs11Config)?.clone();
+                let voting_public_key = def.voting_public_key.clone();
+                // Calls into the PKCS#11 module block until the HSM responds.
+                tokio::task::spawn_blocking(move || {
+                    let key = token.find_key(&pkcs11.key_label)?;
+                    token.check_key(key, &voting_public_key)?;
+                    Result::<_, Pkcs11Error>::Ok(SigningMethod::Pkcs11 {
+                        token,
+                        key,
+                        voting_public_key,
+                    })
+                })
+                .await
+                .map_err(Error::TokioJoin)?
+                .map_err(Error::Pkcs11)?
+            }
         };
 
         Ok(Self {
@@ -327,6 +448,9 @@ impl InitializedValidator {
             SigningMethod::LocalKeystore { voting_keypair, .. } => &voting_keypair.pk,
             SigningMethod::Web3Signer {
                 voting_public_key, ..
+            }
+            | SigningMethod::Pkcs11 {
+                voting_public_key, ..
             } => voting_public_key,
         }
     }
@@ -430,6 +554,38 @@ fn unlock_keystore_via_stdin_password(
     }
 }
 
+/// Checks that `manifest` has a password for every enabled local keystore in `definitions` which
+/// has no password of its own.
+fn check_secrets_manifest(
+    definitions: &ValidatorDefinitions,
+    manifest: &SecretsManifest,
+) -> Result<(), Error> {
+    let missing = definitions
+        .as_slice()
+        .iter()
+        .filter(|def| def.enabled)
+        .filter_map(|def| match &def.signing_definition {
+            SigningDefinition::LocalKeystore {
+                voting_keystore_path,
+                voting_keystore_password_path: None,
+                voting_keystore_password: None,
+            } if manifest
+                .password(&def.voting_public_key, voting_keystore_path)
+                .is_none() =>
+            {
+                Some(voting_keystore_path.clone())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::MissingSecretsManifestPasswords(missing))
+    }
+}
+
 /// A set of `InitializedValidator` objects which is initialized from a list of
 /// `ValidatorDefinition`. The `ValidatorDefinition` file is maintained as `self` is modified.
 ///
@@ -443,28 +599,184 @@ pub struct InitializedValidators {
     validators: HashMap<PublicKeyBytes, InitializedValidator>,
     /// The clients used for communications with a remote signer.
     web3_signer_client_map: Option<HashMap<Web3SignerDefinition, Client>>,
+    /// The session with the PKCS#11 token, opened when the first `pkcs11` validator is enabled.
+    pkcs11_token: Option<Arc<Pkcs11Token>>,
+    /// Keystore passwords loaded from `config.secrets_manifest`, if any.
+    secrets_manifest: Option<SecretsManifest>,
+    /// Enabled validators which are not in `config.load_priority` and are yet to be initialized.
+    deferred: HashSet<PublicKeyBytes>,
+    /// Validators with an index outside of `config.index_range`.
+    out_of_index_range: HashSet<PublicKeyBytes>,
+    /// Configuration for the initialization of validators.
+    config: Config,
     /// For logging via `slog`.
     log: Logger,
 }
 
 impl InitializedValidators {
     /// Instantiates `Self`, initializing all validators in `definitions`.
+    ///
+    /// If `config.load_priority` is set, only the enabled validators it contains are initialized.
     pub async fn from_definitions(
         definitions: ValidatorDefinitions,
         validators_dir: PathBuf,
+        config: Config,
         log: Logger,
     ) -> Result<Self, Error> {
+        let secrets_manifest = config
+            .secrets_manifest
+            .as_ref()
+            .map(SecretsManifest::open)
+            .transpose()
+            .map_err(Error::UnableToLoadSecretsManifest)?;
+        if let Some(manifest) = &secrets_manifest {
+            check_secrets_manifest(&definitions, manifest)?;
+        }
+
+        let deferred = match &config.load_priority {
+            Some(priority) => definitions
+                .as_slice()
+                .iter()
+                .filter(|def| def.enabled)
+                .map(|def| def.voting_public_key.compress())
+                .filter(|pubkey| !priority.contains(pubkey))
+                .collect(),
+            None => HashSet::new(),
+        };
+
         let mut this = Self {
             validators_dir,
             definitions,
             validators: HashMap::default(),
             web3_signer_client_map: None,
+            pkcs11_token: None,
+            secrets_manifest,
+            deferred,
+            out_of_index_range: HashSet::new(),
+            config,
             log,
         };
-        this.update_validators().await?;
+        let num_skipped = this
+            .update_validators_inner(this.config.on_keystore_error)
+            .await?;
+        if num_skipped > 0 {
+            warn!(
+                this.log,
+                "Skipped validators with invalid keystores";
+                "msg" => "these validators will not perform duties",
+                "skipped" => num_skipped,
+                "enabled" => this.num_enabled(),
+            );
+        }
+        if !this.deferred.is_empty() {
+            info!(
+                this.log,
+                "Deferred initialization of validators";
+                "msg" => "these validators will be initialized after the prioritized validators",
+                "prioritized" => this.num_enabled(),
+                "deferred" => this.deferred.len(),
+            );
+        }
         Ok(this)
     }
 
+    /// Initialize the validators deferred by `Config::load_priority`, returning the public keys
+    /// of the validators which were initialized.
+    ///
+    /// The deferred validators are initialized without holding a lock on `initialized_validators`,
+    /// so that the validators which are already initialized can continue to sign whilst the
+    /// deferred keystores are decrypted. The lock is only held to add the newly initialized
+    /// validators.
+    ///
+    /// Deferred validators which are disabled or deleted in the meantime are not added.
+    pub async fn initialize_deferred(
+        initialized_validators: &RwLock<Self>,
+    ) -> Result<Vec<PublicKeyBytes>, Error> {
+        let mut deferred = {
+            let this = initialized_validators.read();
+            if this.deferred.is_empty() {
+                return Ok(vec![]);
+            }
+
+            let definitions = this
+                .definitions
+                .as_slice()
+                .iter()
+                .filter(|def| this.deferred.contains(&def.voting_public_key.compress()))
+                .cloned()
+                .collect::<Vec<_>>();
+            let mut config = this.config.clone();
+            // The limit applies to all validators, not just the deferred validators.
+            config.max_validators = config
+                .max_validators
+                .map(|max_validators| max_validators.saturating_sub(this.num_enabled()));
+
+            Self {
+                validators_dir: this.validators_dir.clone(),
+                definitions: ValidatorDefinitions::from(definitions),
+                validators: HashMap::default(),
+                web3_signer_client_map: this.web3_signer_client_map.clone(),
+                pkcs11_token: this.pkcs11_token.clone(),
+                secrets_manifest: this.secrets_manifest.clone(),
+                deferred: HashSet::new(),
+                config,
+                log: this.log.clone(),
+            }
+        };
+
+        let num_skipped = deferred
+            .update_validators_inner(deferred.config.on_keystore_error)
+            .await?;
+
+        let mut this = initialized_validators.write();
+        let mut initialized = Vec::with_capacity(deferred.validators.len());
+        for (pubkey, validator) in deferred.validators {
+            if this.deferred.remove(&pubkey) {
+                this.validators.insert(pubkey, validator);
+                initialized.push(pubkey);
+            }
+        }
+        // Any validators which failed to initialize are no longer deferred, so that they are
+        // retried by the next call to `update_validators`.
+        this.deferred.clear();
+
+        if let Some(client_map) = deferred.web3_signer_client_map {
+            this.web3_signer_client_map
+                .get_or_insert_with(HashMap::new)
+                .extend(client_map);
+        }
+        if this.pkcs11_token.is_none() {
+            this.pkcs11_token = deferred.pkcs11_token;
+        }
+
+        set_gauge(
+            &crate::http_metrics::metrics::ENABLED_VALIDATORS_COUNT,
+            this.num_enabled() as i64,
+        );
+        set_gauge(
+            &crate::http_metrics::metrics::TOTAL_VALIDATORS_COUNT,
+            this.num_total() as i64,
+        );
+
+        if num_skipped > 0 {
+            warn!(
+                this.log,
+                "Skipped validators with invalid keystores";
+                "msg" => "these validators will not perform duties",
+                "skipped" => num_skipped,
+                "enabled" => this.num_enabled(),
+            );
+        }
+        info!(
+            this.log,
+            "Initialized deferred validators";
+            "initialized" => initialized.len(),
+            "enabled" => this.num_enabled(),
+        );
+
+        Ok(initialized)
+    }
+
     /// The count of enabled validators contained in `self`.
     pub fn num_enabled(&self) -> usize {
         self.validators.len()
@@ -476,8 +788,18 @@ impl InitializedValidators {
     }
 
     /// Iterate through all voting public keys in `self` that should be used when querying for duties.
+    ///
+    /// Validators with an index outside of `Config::index_range` are excluded.
     pub fn iter_voting_pubkeys(&self) -> impl Iterator<Item = &PublicKeyBytes> {
-        self.validators.keys()
+        self.validators
+            .keys()
+            .filter(|pubkey| !self.out_of_index_range.contains(pubkey))
+    }
+
+    /// Iterate through the voting public keys of enabled validators whose initialization has been
+    /// deferred by `Config::load_priority`.
+    pub fn iter_deferred_pubkeys(&self) -> impl Iterator<Item = &PublicKeyBytes> {
+        self.deferred.iter()
     }
 
     /// Returns the voting `Keypair` for a given voting `PublicKey`, if all are true:
@@ -499,27 +821,39 @@ impl InitializedValidators {
         &mut self,
         def: ValidatorDefinition,
     ) -> Result<(), Error> {
-        // Drop any disabled definitions with the same public key.
-        let delete_def = |existing_def: &ValidatorDefinition| {
-            !existing_def.enabled && existing_def.voting_public_key == def.voting_public_key
-        };
-        self.definitions.retain(|def| !delete_def(def));
-
-        // Add the definition.
-        self.add_definition(def).await
+        self.add_definition_inner(def, true).await
     }
 
     /// Add a validator definition to `self`, overwriting the on-disk representation of `self`.
     pub async fn add_definition(&mut self, def: ValidatorDefinition) -> Result<(), Error> {
-        if self
-            .definitions
-            .as_slice()
-            .iter()
-            .any(|existing| existing.voting_public_key == def.voting_public_key)
-        {
+        self.add_definition_inner(def, false).await
+    }
+
+    /// Add a validator definition to `self`, first dropping any disabled definition with the same
+    /// voting public key if `replace_disabled` is set.
+    ///
+    /// The definitions are only modified once the definition is known to be addable.
+    async fn add_definition_inner(
+        &mut self,
+        def: ValidatorDefinition,
+        replace_disabled: bool,
+    ) -> Result<(), Error> {
+        let replaced = |existing_def: &ValidatorDefinition| {
+            replace_disabled
+                && !existing_def.enabled
+                && existing_def.voting_public_key == def.voting_public_key
+        };
+        if self.definitions.as_slice().iter().any(|existing| {
+            existing.voting_public_key == def.voting_public_key && !replaced(existing)
+        }) {
             return Err(Error::DuplicatePublicKey);
         }
+        if def.enabled {
+            self.check_validator_count_limit()?;
+        }
 
+        // Drop any disabled definitions with the same public key.
+        self.definitions.retain(|existing| !replaced(existing));
         self.definitions.push(def);
 
         self.update_validators().await?;
@@ -531,6 +865,19 @@ impl InitializedValidators {
         Ok(())
     }
 
+    /// Returns an error if `self.config.max_validators` validators are already initialized.
+    ///
+    /// Called whenever a definition is about to become enabled, by being added or by being
+    /// enabled, rather than enabling a validator which would not be loaded.
+    fn check_validator_count_limit(&self) -> Result<(), Error> {
+        match self.config.max_validators {
+            Some(max_validators) if self.validators.len() >= max_validators => {
+                Err(Error::ValidatorCountLimitReached(max_validators))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Delete the validator definition and keystore for `pubkey`.
     ///
     /// The delete is carried out in stages so that the filesystem is never left in an inconsistent
@@ -567,6 +914,7 @@ impl InitializedValidators {
 
         // 2. Delete from `self.validators`, which holds the signing method.
         //    Delete the keystore files.
+        self.deferred.remove(&pubkey.compress());
         if let Some(initialized_validator) = self.validators.remove(&pubkey.compress()) {
             if let SigningMethod::LocalKeystore {
                 ref voting_keystore_path,
@@ -698,6 +1046,16 @@ impl InitializedValidators {
         builder_proposals: Option<bool>,
         graffiti: Option<GraffitiString>,
     ) -> Result<(), Error> {
+        let newly_enabled = enabled == Some(true)
+            && self
+                .definitions
+                .as_slice()
+                .iter()
+                .any(|def| def.voting_public_key == *voting_public_key && !def.enabled);
+        if newly_enabled {
+            self.check_validator_count_limit()?;
+        }
+
         if let Some(def) = self
             .definitions
             .as_mut_slice()
@@ -920,8 +1278,8 @@ impl InitializedValidators {
                     };
                     definitions_map.insert(*key_store.uuid(), def);
                 }
-                // Remote signer validators don't interact with the key cache.
-                SigningDefinition::Web3Signer { .. } => (),
+                // Remote signer and PKCS#11 validators don't interact with the key cache.
+                SigningDefinition::Web3Signer { .. } | SigningDefinition::Pkcs11(_) => (),
             }
         }
 
@@ -953,6 +1311,10 @@ impl InitializedValidators {
                         p.as_ref().to_vec().into()
                     } else if let Some(path) = voting_keystore_password_path {
                         read_password(path).map_err(Error::UnableToReadVotingKeystorePassword)?
+                    } else if let Some(p) = self.secrets_manifest.as_ref().and_then(|manifest| {
+                        manifest.password(&def.voting_public_key, voting_keystore_path)
+                    }) {
+                        p.as_ref().to_vec().into()
                     } else {
                         let keystore = open_keystore(voting_keystore_path)?;
                         unlock_keystore_via_stdin_password(&keystore, voting_keystore_path)?
@@ -964,8 +1326,8 @@ impl InitializedValidators {
                     passwords.push(pw);
                     public_keys.push(def.voting_public_key.clone());
                 }
-                // Remote signer validators don't interact with the key cache.
-                SigningDefinition::Web3Signer { .. } => (),
+                // Remote signer and PKCS#11 validators don't interact with the key cache.
+                SigningDefinition::Web3Signer { .. } | SigningDefinition::Pkcs11(_) => (),
             };
         }
 
@@ -978,6 +1340,133 @@ impl InitializedValidators {
         .map_err(Error::TokioJoin)
     }
 
+    /// Concurrently decrypts the local keystores of the enabled definitions in `self.definitions`
+    /// which are neither initialized, in `skip`, nor in the `key_cache`, adding the keypairs to the
+    /// `key_cache`.
+    ///
+    /// At most `self.config.keystore_decrypt_concurrency()` keystores are decrypted at once.
+    /// Keystores which can't be opened or whose password would have to be entered by the user are
+    /// left to `InitializedValidator::from_definition`.
+    ///
+    /// Returns the errors of the keystores which could not be decrypted, by keystore path. All of
+    /// the errors are logged together.
+    async fn decrypt_keystores(
+        &self,
+        skip: &HashSet<PublicKeyBytes>,
+        key_cache: &mut KeyCache,
+        key_stores: &mut HashMap<PathBuf, Keystore>,
+    ) -> HashMap<PathBuf, Error> {
+        let mut pending = vec![];
+        let mut pending_paths = HashSet::new();
+        for def in self.definitions.as_slice().iter().filter(|def| def.enabled) {
+            let pubkey_bytes = def.voting_public_key.compress();
+            if self.validators.contains_key(&pubkey_bytes) || skip.contains(&pubkey_bytes) {
+                continue;
+            }
+
+            let SigningDefinition::LocalKeystore {
+                voting_keystore_path,
+                voting_keystore_password_path,
+                voting_keystore_password,
+            } = &def.signing_definition
+            else {
+                continue;
+            };
+            if !pending_paths.insert(voting_keystore_path.clone()) {
+                continue;
+            }
+
+            use std::collections::hash_map::Entry::*;
+            let keystore = match key_stores.entry(voting_keystore_path.clone()) {
+                Vacant(entry) => match open_keystore(voting_keystore_path) {
+                    Ok(keystore) => entry.insert(keystore),
+                    Err(_) => continue,
+                },
+                Occupied(entry) => entry.into_mut(),
+            };
+            if key_cache.get(keystore.uuid()).is_some() {
+                continue;
+            }
+
+            let password: PlainText = if let Some(password) = voting_keystore_password {
+                password.as_ref().to_vec().into()
+            } else if let Some(path) = voting_keystore_password_path {
+                match read_password(path) {
+                    Ok(password) => password,
+                    Err(_) => continue,
+                }
+            } else if let Some(password) = self.secrets_manifest.as_ref().and_then(|manifest| {
+                manifest.password(&def.voting_public_key, voting_keystore_path)
+            }) {
+                password.as_ref().to_vec().into()
+            } else {
+                continue;
+            };
+
+            pending.push((voting_keystore_path.clone(), keystore.clone(), password));
+        }
+
+        if pending.is_empty() {
+            return HashMap::new();
+        }
+
+        let num_keystores = pending.len();
+        let concurrency = self.config.keystore_decrypt_concurrency();
+        let start = Instant::now();
+        // Each keystore is decrypted on the blocking thread pool, since decryption takes several
+        // seconds. Only `concurrency` tasks are spawned at a time.
+        let results = stream::iter(pending)
+            .map(|(path, keystore, password)| async move {
+                let result = tokio::task::spawn_blocking(move || {
+                    let keypair = keystore.decrypt_keypair(password.as_bytes());
+                    (keystore, password, keypair)
+                })
+                .await;
+                (path, result)
+            })
+            .buffer_unordered(concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut failures = HashMap::new();
+        for (path, result) in results {
+            match result {
+                Ok((keystore, password, Ok(keypair))) => {
+                    key_cache.add(keypair, keystore.uuid(), password)
+                }
+                Ok((_, _, Err(e))) => {
+                    failures.insert(path, Error::UnableToDecryptKeystore(e));
+                }
+                Err(e) => {
+                    failures.insert(path, Error::TokioJoin(e));
+                }
+            }
+        }
+
+        info!(
+            self.log,
+            "Decrypted keystores";
+            "count" => num_keystores - failures.len(),
+            "concurrency" => concurrency,
+            "duration_ms" => start.elapsed().as_millis(),
+        );
+        if !failures.is_empty() {
+            let mut keystores = failures
+                .iter()
+                .map(|(path, e)| format!("{} ({:?})", path.display(), e))
+                .collect::<Vec<_>>();
+            keystores.sort();
+            error!(
+                self.log,
+                "Failed to decrypt keystores";
+                "count" => failures.len(),
+                "keystores" => keystores.join(", "),
+            );
+        }
+
+        failures
+    }
+
     /// Scans `self.definitions` and attempts to initialize and validators which are not already
     /// initialized.
     ///
@@ -990,6 +1479,49 @@ impl InitializedValidators {
     /// I.e., if there are two different definitions with the same public key then the second will
     /// be ignored.
     pub(crate) async fn update_validators(&mut self) -> Result<(), Error> {
+        self.update_validators_inner(OnKeystoreError::Abort)
+            .await
+            .map(|_| ())
+    }
+
+    /// Returns the public keys of enabled, not yet initialized validators which would exceed
+    /// `self.config.max_validators` if they were initialized.
+    ///
+    /// Validators which are already initialized are always kept, the remaining slots are filled
+    /// with validators which have not been deferred by `Config::load_priority` and then in order
+    /// of voting public key so that the selection is reproducible across restarts.
+    fn validators_over_limit(&self) -> HashSet<PublicKeyBytes> {
+        let max_validators = match self.config.max_validators {
+            Some(max_validators) => max_validators,
+            None => return HashSet::new(),
+        };
+
+        let (initialized, mut uninitialized): (Vec<_>, Vec<_>) = self
+            .definitions
+            .as_slice()
+            .iter()
+            .filter(|def| def.enabled)
+            .map(|def| def.voting_public_key.compress())
+            .partition(|pubkey| self.validators.contains_key(pubkey));
+
+        uninitialized.sort_by(|a, b| {
+            (self.deferred.contains(a), a.as_serialized())
+                .cmp(&(self.deferred.contains(b), b.as_serialized()))
+        });
+        uninitialized
+            .into_iter()
+            .skip(max_validators.saturating_sub(initialized.len()))
+            .collect()
+    }
+
+    /// As per `update_validators`, but local keystores which fail to initialize are skipped
+    /// rather than causing an error if `on_keystore_error == OnKeystoreError::Skip`.
+    ///
+    /// Returns the number of skipped validators.
+    async fn update_validators_inner(
+        &mut self,
+        on_keystore_error: OnKeystoreError,
+    ) -> Result<usize, Error> {
         //use key cache if available
         let mut key_stores = HashMap::new();
 
@@ -1019,12 +1551,55 @@ impl InitializedValidators {
             KeyCache::new()
         };
 
+        let over_limit = self.validators_over_limit();
+        let skip_decryption: HashSet<_> = over_limit.union(&self.deferred).copied().collect();
+
+        // The PKCS#11 token is only opened once a `pkcs11` validator needs to be initialized.
+        let has_pkcs11_definitions = self.definitions.as_slice().iter().any(|def| {
+            let pubkey = def.voting_public_key.compress();
+            def.enabled
+                && matches!(def.signing_definition, SigningDefinition::Pkcs11(_))
+                && !self.validators.contains_key(&pubkey)
+                && !self.deferred.contains(&pubkey)
+        });
+        let pkcs11_token = if has_pkcs11_definitions {
+            Some(self.pkcs11_token().await.map_err(|e| {
+                error!(
+                    self.log,
+                    "Unable to open PKCS#11 session";
+                    "error" => format!("{:?}", e),
+                );
+                e
+            })?)
+        } else {
+            None
+        };
+        let mut decryption_failures = if has_local_definitions {
+            self.decrypt_keystores(&skip_decryption, &mut key_cache, &mut key_stores)
+                .await
+        } else {
+            HashMap::new()
+        };
+
         let mut disabled_uuids = HashSet::new();
+        let mut num_skipped = 0;
         for def in self.definitions.as_slice() {
             if def.enabled {
                 let pubkey_bytes = def.voting_public_key.compress();
 
-                if self.validators.contains_key(&pubkey_bytes) {
+                if self.validators.contains_key(&pubkey_bytes)
+                    || self.deferred.contains(&pubkey_bytes)
+                {
+                    continue;
+                }
+
+                if over_limit.contains(&pubkey_bytes) {
+                    warn!(
+                        self.log,
+                        "Not loading validator";
+                        "reason" => "validator count limit reached",
+                        "voting_pubkey" => format!("{:?}", def.voting_public_key),
+                    );
                     continue;
                 }
 
@@ -1037,14 +1612,22 @@ impl InitializedValidators {
                             disabled_uuids.remove(key_store.uuid());
                         }
 
-                        match InitializedValidator::from_definition(
-                            def.clone(),
-                            &mut key_cache,
-                            &mut key_stores,
-                            &mut None,
-                        )
-                        .await
-                        {
+                        let result =
+                            if let Some(e) = decryption_failures.remove(voting_keystore_path) {
+                                Err(e)
+                            } else {
+                                InitializedValidator::from_definition(
+                                    def.clone(),
+                                    &mut key_cache,
+                                    &mut key_stores,
+                                    &mut None,
+                                    self.secrets_manifest.as_ref(),
+                                    None,
+                                )
+                                .await
+                            };
+
+                        match result {
                             Ok(init) => {
                                 let existing_lockfile_path = init
                                     .keystore_lockfile()
@@ -1078,11 +1661,15 @@ impl InitializedValidators {
                                     "Failed to initialize validator";
                                     "error" => format!("{:?}", e),
                                     "signing_method" => "local_keystore",
-                                    "validator" => format!("{:?}", def.voting_public_key)
+                                    "validator" => format!("{:?}", def.voting_public_key),
+                                    "keystore" => voting_keystore_path.display(),
                                 );
 
-                                // Exit on an invalid validator.
-                                return Err(e);
+                                match on_keystore_error {
+                                    OnKeystoreError::Skip => num_skipped += 1,
+                                    // Exit on an invalid validator.
+                                    OnKeystoreError::Abort => return Err(e),
+                                }
                             }
                         }
                     }
@@ -1092,6 +1679,8 @@ impl InitializedValidators {
                             &mut key_cache,
                             &mut key_stores,
                             &mut self.web3_signer_client_map,
+                            self.secrets_manifest.as_ref(),
+                            None,
                         )
                         .await
                         {
@@ -1115,6 +1704,42 @@ impl InitializedValidators {
                                     "validator" => format!("{:?}", def.voting_public_key)
                                 );
 
+                                // Exit on an invalid validator.
+                                return Err(e);
+                            }
+                        }
+                    }
+                    SigningDefinition::Pkcs11(_) => {
+                        match InitializedValidator::from_definition(
+                            def.clone(),
+                            &mut key_cache,
+                            &mut key_stores,
+                            &mut None,
+                            self.secrets_manifest.as_ref(),
+                            pkcs11_token.as_ref(),
+                        )
+                        .await
+                        {
+                            Ok(init) => {
+                                self.validators
+                                    .insert(init.voting_public_key().compress(), init);
+
+                                info!(
+                                    self.log,
+                                    "Enabled validator";
+                                    "signing_method" => "pkcs11",
+                                    "voting_pubkey" => format!("{:?}", def.voting_public_key),
+                                );
+                            }
+                            Err(e) => {
+                                error!(
+                                    self.log,
+                                    "Failed to initialize validator";
+                                    "error" => format!("{:?}", e),
+                                    "signing_method" => "pkcs11",
+                                    "validator" => format!("{:?}", def.voting_public_key)
+                                );
+
                                 // Exit on an invalid validator.
                                 return Err(e);
                             }
@@ -1123,6 +1748,7 @@ impl InitializedValidators {
                 }
             } else {
                 self.validators.remove(&def.voting_public_key.compress());
+                self.deferred.remove(&def.voting_public_key.compress());
                 match &def.signing_definition {
                     SigningDefinition::LocalKeystore {
                         voting_keystore_path,
@@ -1132,8 +1758,8 @@ impl InitializedValidators {
                             disabled_uuids.insert(*key_store.uuid());
                         }
                     }
-                    // Remote signers do not interact with the key cache.
-                    SigningDefinition::Web3Signer { .. } => (),
+                    // Remote signers and PKCS#11 validators do not interact with the key cache.
+                    SigningDefinition::Web3Signer { .. } | SigningDefinition::Pkcs11(_) => (),
                 }
 
                 info!(
@@ -1170,6 +1796,17 @@ impl InitializedValidators {
             debug!(log, "Key cache not modified");
         }
 
+        if let Some(max_validators) = self.config.max_validators {
+            if !over_limit.is_empty() {
+                warn!(
+                    self.log,
+                    "Validator count limit reached";
+                    "limit" => max_validators,
+                    "not_loaded" => over_limit.len(),
+                );
+            }
+        }
+
         // Update the enabled and total validator counts
         set_gauge(
             &crate::http_metrics::metrics::ENABLED_VALIDATORS_COUNT,
@@ -1179,16 +1816,70 @@ impl InitializedValidators {
             &crate::http_metrics::metrics::TOTAL_VALIDATORS_COUNT,
             self.num_total() as i64,
         );
-        Ok(())
+        Ok(num_skipped)
+    }
+
+    /// Returns the session with the PKCS#11 token, opening it if necessary.
+    async fn pkcs11_token(&mut self) -> Result<Arc<Pkcs11Token>, Error> {
+        if let Some(token) = &self.pkcs11_token {
+            return Ok(token.clone());
+        }
+
+        let config = self
+            .config
+            .pkcs11
+            .clone()
+            .ok_or(Error::MissingPkcs11Config)?;
+        let token = tokio::task::spawn_blocking(move || Pkcs11Token::open(&config))
+            .await
+            .map_err(Error::TokioJoin)?
+            .map_err(Error::Pkcs11)?;
+        info!(self.log, "Opened PKCS#11 session");
+
+        let token = Arc::new(token);
+        self.pkcs11_token = Some(token.clone());
+        Ok(token)
     }
 
     pub fn get_index(&self, pubkey: &PublicKeyBytes) -> Option<u64> {
         self.validators.get(pubkey).and_then(|val| val.index)
     }
 
-    pub fn set_index(&mut self, pubkey: &PublicKeyBytes, index: u64) {
+    /// Sets the index of the validator with `pubkey`.
+    ///
+    /// Returns `false` if the index is outside of `Config::index_range`, in which case the
+    /// validator is no longer returned by `iter_voting_pubkeys`.
+    pub fn set_index(&mut self, pubkey: &PublicKeyBytes, index: u64) -> bool {
+        let in_range = self
+            .config
+            .index_range
+            .map_or(true, |(start, end)| (start..end).contains(&index));
         if let Some(val) = self.validators.get_mut(pubkey) {
             val.index = Some(index);
+            if in_range {
+                self.out_of_index_range.remove(pubkey);
+            } else {
+                self.out_of_index_range.insert(*pubkey);
+            }
+        }
+        in_range
+    }
+}
+
+/// Read a validator load priority file, which contains one voting public key per line.
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn read_load_priority_file(path: &Path) -> Result<HashSet<PublicKeyBytes>, Error> {
+    let file = File::open(path).map_err(Error::UnableToReadLoadPriorityFile)?;
+    let mut pubkeys = HashSet::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(Error::UnableToReadLoadPriorityFile)?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
         }
+        let pubkey = PublicKeyBytes::from_str(line).map_err(Error::InvalidLoadPriorityFile)?;
+        pubkeys.insert(pubkey);
     }
+    Ok(pubkeys)
 }
@@ -2,7 +2,9 @@ use crate::{http_metrics::metrics, BeaconNodeFallback};
 use environment::RuntimeContext;
 use slog::debug;
 use slot_clock::SlotClock;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::time::sleep;
 use types::EthSpec;
 
@@ -10,16 +12,64 @@ use types::EthSpec;
 pub const SLOT_DELAY_MULTIPLIER: u32 = 11;
 pub const SLOT_DELAY_DENOMINATOR: u32 = 12;
 
+/// The most recent latency measurements of a single BN.
+struct LatencyWindow {
+    samples: VecDeque<Duration>,
+    size: usize,
+}
+
+impl LatencyWindow {
+    fn new(size: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(size),
+            size,
+        }
+    }
+
+    /// Add a measurement, dropping the oldest one if the window is full.
+    fn push(&mut self, latency: Duration) {
+        while self.samples.len() >= self.size {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    /// Returns the mean of the measurements in the window, if any.
+    fn average(&self) -> Option<Duration> {
+        let count = u32::try_from(self.samples.len()).ok().filter(|&n| n > 0)?;
+        Some(self.samples.iter().sum::<Duration>() / count)
+    }
+}
+
+/// Returns the number of slots between two measurements, such that they are at least
+/// `sample_interval` apart.
+fn slots_per_sample(sample_interval: Duration, slot_duration: Duration) -> u32 {
+    if slot_duration.is_zero() {
+        return 1;
+    }
+    let slot_nanos = slot_duration.as_nanos();
+    let slots = (sample_interval.as_nanos() + slot_nanos - 1) / slot_nanos;
+    u32::try_from(slots).unwrap_or(u32::MAX).max(1)
+}
+
 /// Starts a service that periodically checks the latency between the VC and the
 /// candidate BNs.
+///
+/// Measurements are taken at least `sample_interval` apart and the average over the last
+/// `sample_window` measurements of each BN is published in metrics.
 pub fn start_latency_service<T: SlotClock + 'static, E: EthSpec>(
     context: RuntimeContext<E>,
     slot_clock: T,
     beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
+    sample_interval: Duration,
+    sample_window: usize,
 ) {
     let log = context.log().clone();
+    let slots_per_sample = slots_per_sample(sample_interval, slot_clock.slot_duration());
 
     let future = async move {
+        let mut windows: HashMap<String, LatencyWindow> = HashMap::new();
+
         loop {
             let sleep_time = slot_clock
                 .duration_to_next_slot()
@@ -27,11 +77,13 @@ pub fn start_latency_service<T: SlotClock + 'static, E: EthSpec>(
                     // This is 11/12ths through the next slot. On mainnet this
                     // will happen in the 11th second of each slot, one second
                     // before the next slot.
-                    next_slot + (next_slot / SLOT_DELAY_DENOMINATOR) * SLOT_DELAY_MULTIPLIER
+                    next_slot
+                        + slot_clock.slot_duration() * (slots_per_sample - 1)
+                        + (next_slot / SLOT_DELAY_DENOMINATOR) * SLOT_DELAY_MULTIPLIER
                 })
-                // If we can't read the slot clock, just wait one slot. Running
+                // If we can't read the slot clock, just wait one interval. Running
                 // the measurement at a non-exact time is not a big issue.
-                .unwrap_or_else(|| slot_clock.slot_duration());
+                .unwrap_or_else(|| slot_clock.slot_duration() * slots_per_sample);
 
             // Sleep until it's time to perform the measurement.
             sleep(sleep_time).await;
@@ -55,6 +107,18 @@ pub fn start_latency_service<T: SlotClock + 'static, E: EthSpec>(
                             latency,
                         );
                     }
+
+                    let window = windows
+                        .entry(measurement.beacon_node_id.clone())
+                        .or_insert_with(|| LatencyWindow::new(sample_window));
+                    window.push(latency);
+                    if let Some(average) = window.average() {
+                        metrics::set_float_gauge_vec(
+                            &metrics::VC_BEACON_NODE_LATENCY_WINDOW_AVERAGE,
+                            &[&measurement.beacon_node_id],
+                            average.as_secs_f64(),
+                        );
+                    }
                 }
             }
         }
@@ -62,3 +126,31 @@ pub fn start_latency_service<T: SlotClock + 'static, E: EthSpec>(
 
     context.executor.spawn(future, "latency");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_average() {
+        let mut window = LatencyWindow::new(2);
+        assert_eq!(window.average(), None);
+
+        window.push(Duration::from_millis(10));
+        assert_eq!(window.average(), Some(Duration::from_millis(10)));
+        window.push(Duration::from_millis(20));
+        assert_eq!(window.average(), Some(Duration::from_millis(15)));
+        // The oldest measurement is dropped.
+        window.push(Duration::from_millis(40));
+        assert_eq!(window.average(), Some(Duration::from_millis(30)));
+    }
+
+    #[test]
+    fn sample_interval_rounds_up_to_slots() {
+        let slot = Duration::from_secs(12);
+        assert_eq!(slots_per_sample(Duration::from_secs(1), slot), 1);
+        assert_eq!(slots_per_sample(Duration::from_secs(12), slot), 1);
+        assert_eq!(slots_per_sample(Duration::from_secs(13), slot), 2);
+        assert_eq!(slots_per_sample(Duration::from_secs(60), slot), 5);
+    }
+}
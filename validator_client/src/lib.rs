@@ -1,9 +1,16 @@
 mod attestation_service;
 mod beacon_node_fallback;
+mod beacon_node_health;
+mod beacon_node_version;
 mod block_service;
 mod check_synced;
 mod cli;
+mod clock_sync;
 mod config;
+mod config_hash;
+mod dir_access;
+mod dir_permissions;
+mod disk_space;
 mod duties_service;
 mod graffiti_file;
 mod http_metrics;
@@ -11,26 +18,41 @@ mod key_cache;
 mod latency;
 mod notifier;
 mod preparation_service;
+mod pubkey_list_file;
+mod registration_timestamp_file;
+mod relay_tag_file;
+mod secrets_manifest;
 mod signing_method;
+mod signing_throttle;
+mod skip_reason;
 mod sync_committee_service;
+mod tls_expiry;
+mod validator_index_cache;
 
 mod doppelganger_service;
+pub mod fork_schedule;
 pub mod http_api;
 pub mod initialized_validators;
 pub mod validator_store;
 
+pub use beacon_node_fallback::BeaconNodeSelection;
+pub use block_service::BuilderUnavailableAction;
 pub use cli::cli_app;
-pub use config::Config;
+pub use config::{Config, OnExistingSlashingProtection, OnGraffitiOverflow};
+pub use duties_service::MetricsValidatorBucketing;
 use initialized_validators::InitializedValidators;
 use lighthouse_metrics::set_gauge;
 use monitoring_api::{MonitoringHttpClient, ProcessType};
+pub use preparation_service::{OnGasLimitOutOfRange, OnMissingFeeRecipient};
 use sensitive_url::SensitiveUrl;
+use slashing_protection::{NotSafe, RegistrationOutcome};
 pub use slashing_protection::{SlashingDatabase, SLASHING_PROTECTION_FILENAME};
 
 use crate::beacon_node_fallback::{
     start_fallback_updater_service, BeaconNodeFallback, CandidateBeaconNode, OfflineOnFailure,
     RequireSynced,
 };
+use crate::config::LOW_MEMORY_MAX_IDLE_CONNECTIONS_PER_BEACON_NODE;
 use crate::doppelganger_service::DoppelgangerService;
 use crate::graffiti_file::GraffitiFile;
 use crate::initialized_validators::Error::UnableToOpenVotingKeystore;
@@ -38,30 +60,39 @@ use account_utils::validator_definitions::ValidatorDefinitions;
 use attestation_service::{AttestationService, AttestationServiceBuilder};
 use block_service::{BlockService, BlockServiceBuilder};
 use clap::ArgMatches;
-use duties_service::DutiesService;
+use duties_service::{DutiesBatchSizes, DutiesService};
 use environment::RuntimeContext;
-use eth2::{reqwest::ClientBuilder, types::Graffiti, BeaconNodeHttpClient, StatusCode, Timeouts};
+use eth2::lighthouse_vc::types::{ResolvedSetting, SettingSource};
+use eth2::{
+    reqwest::ClientBuilder,
+    types::{BlockProductionLimits, Graffiti},
+    BeaconNodeHttpClient, StatusCode, Timeouts,
+};
 use http_api::ApiSecret;
+use logging::LOG_CATEGORY_KEY;
 use notifier::spawn_notifier;
-use parking_lot::RwLock;
-use preparation_service::{PreparationService, PreparationServiceBuilder};
-use reqwest::Certificate;
-use slog::{error, info, warn, Logger};
+use parking_lot::{Mutex, RwLock};
+use preparation_service::{OnMissingFeeRecipient, PreparationService, PreparationServiceBuilder};
+use reqwest::{tls, Certificate};
+use slog::{crit, debug, error, info, o, warn, Logger};
 use slot_clock::SlotClock;
 use slot_clock::SystemTimeSlotClock;
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::Read;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use sync_committee_service::SyncCommitteeService;
+use task_executor::ShutdownReason;
 use tokio::{
     sync::mpsc,
     time::{sleep, Duration},
 };
-use types::{EthSpec, Hash256, PublicKeyBytes};
+use types::{ChainSpec, Epoch, EthSpec, ForkName, Hash256, PublicKeyBytes};
+use validator_index_cache::ValidatorIndexCache;
 use validator_store::ValidatorStore;
 
 /// The interval between attempts to contact the beacon node during startup.
@@ -85,6 +116,10 @@ const HTTP_GET_DEPOSIT_SNAPSHOT_QUOTIENT: u32 = 4;
 
 const DOPPELGANGER_SERVICE_NAME: &str = "doppelganger";
 
+/// The log category of signing and publishing attestations, blocks and sync committee messages,
+/// and of slashing protection.
+pub const SIGNING_LOG_CATEGORY: &str = "signing";
+
 #[derive(Clone)]
 pub struct ProductionValidatorClient<T: EthSpec> {
     context: RuntimeContext<T>,
@@ -114,9 +149,80 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
 
     /// Instantiates the validator client, _without_ starting the timers to trigger block
     /// and attestation production.
-    pub async fn new(context: RuntimeContext<T>, config: Config) -> Result<Self, String> {
+    pub async fn new(mut context: RuntimeContext<T>, config: Config) -> Result<Self, String> {
         let log = context.log().clone();
 
+        // Apply the slot duration override before anything reads it from the spec.
+        if let Some(seconds_per_slot) = config.seconds_per_slot_override {
+            warn!(
+                log,
+                "Using overridden slot duration";
+                "msg" => "this should only be used for testing",
+                "seconds_per_slot" => seconds_per_slot,
+                "network_seconds_per_slot" => context.eth2_config.spec.seconds_per_slot,
+            );
+            context.eth2_config.spec.seconds_per_slot = seconds_per_slot;
+        }
+        let block_delay =
+            config.effective_block_delay(context.eth2_config.spec.seconds_per_slot)?;
+        config.check_block_prefetch_lead(context.eth2_config.spec.seconds_per_slot)?;
+        config.check_block_max_transactions::<T>()?;
+        if let Some(max_transactions) = config.block_max_transactions {
+            warn!(
+                log,
+                "Limiting transactions in locally built blocks";
+                "msg" => "this should only be used for testing, and is ignored by beacon nodes \
+                          which do not support it",
+                "max_transactions" => max_transactions,
+            );
+        }
+
+        if config.low_memory_mode {
+            info!(
+                log,
+                "Low memory mode enabled";
+                "max_pending_aggregations" => config.max_pending_aggregations,
+                "validator_registration_batch_size" => config.validator_registration_batch_size,
+            );
+        }
+        if let Some(block_delay) = block_delay {
+            warn!(
+                log,
+                "Delaying block production";
+                "msg" => "this should only be used for testing",
+                "block_delay_ms" => block_delay.as_millis(),
+            );
+        }
+
+        // Signing uses its own copy of the spec so that overriding the fork schedule does not
+        // affect the compatibility checks against the beacon nodes.
+        let mut signing_spec = context.eth2_config.spec.clone();
+        if let Some(fork_schedule) = &config.fork_schedule_override {
+            fork_schedule::apply_fork_schedule_override(&mut signing_spec, fork_schedule)
+                .map_err(|e| format!("Invalid fork schedule override: {}", e))?;
+            for fork in ForkName::list_all() {
+                warn!(
+                    log,
+                    "Using overridden fork schedule for signing";
+                    "msg" => "this should only be used for testing",
+                    "fork" => %fork,
+                    "version" => format!("0x{}", hex::encode(signing_spec.fork_version_for_name(fork))),
+                    "epoch" => ?signing_spec.fork_epoch(fork),
+                    "network_version" => format!(
+                        "0x{}",
+                        hex::encode(context.eth2_config.spec.fork_version_for_name(fork))
+                    ),
+                    "network_epoch" => ?context.eth2_config.spec.fork_epoch(fork),
+                );
+            }
+        }
+
+        if let Some(graffiti_file) = &config.graffiti_file {
+            graffiti_file
+                .validate_schedule(&context.eth2_config.spec)
+                .map_err(|e| format!("Invalid graffiti schedule: {:?}", e))?;
+        }
+
         info!(
             log,
             "Starting validator client";
@@ -124,6 +230,22 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             "validator_dir" => format!("{:?}", config.validator_dir),
         );
 
+        if config.detect_config_changes {
+            let hash = config_hash::config_hash(&config)?;
+            let path = config_hash::config_hash_path(&config.validator_dir);
+            match config_hash::replace_config_hash(&path, &hash)? {
+                Some(previous) if previous != hash => warn!(
+                    log,
+                    "Configuration changed since the previous run";
+                    "msg" => "check that no flags were dropped or added unintentionally",
+                    "hash" => &hash,
+                    "previous_hash" => previous,
+                ),
+                Some(_) => debug!(log, "Configuration unchanged"; "hash" => &hash),
+                None => info!(log, "Stored configuration hash"; "path" => ?path),
+            }
+        }
+
         // Optionally start the metrics server.
         let http_metrics_ctx = if config.http_metrics.enabled {
             let shared = http_metrics::Shared {
@@ -165,6 +287,14 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             );
         };
 
+        clock_sync::check_clock_synced(config.require_synced_clock, &log)?;
+
+        dir_permissions::check_dir_permissions(
+            &[&config.validator_dir, &config.secrets_dir],
+            config.strict_dir_permissions,
+            &log,
+        )?;
+
         let mut validator_defs = ValidatorDefinitions::open_or_create(&config.validator_dir)
             .map_err(|e| format!("Unable to open or create validator definitions: {:?}", e))?;
 
@@ -185,6 +315,7 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
         let validators = InitializedValidators::from_definitions(
             validator_defs,
             config.validator_dir.clone(),
+            config.initialized_validators.clone(),
             log.clone(),
         )
         .await
@@ -199,14 +330,20 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
                 }
             })?;
 
-        let voting_pubkeys: Vec<_> = validators.iter_voting_pubkeys().collect();
+        // Deferred validators are included so that they are checked against slashing protection
+        // before any validators begin performing duties.
+        let voting_pubkeys: Vec<_> = validators
+            .iter_voting_pubkeys()
+            .chain(validators.iter_deferred_pubkeys())
+            .collect();
 
         info!(
             log,
             "Initialized validators";
-            "disabled" => validators.num_total().saturating_sub(validators.num_enabled()),
+            "disabled" => validators.num_total().saturating_sub(voting_pubkeys.len()),
             "enabled" => validators.num_enabled(),
         );
+        config.log_safety_banner(validators.num_enabled(), &log);
 
         if voting_pubkeys.is_empty() {
             warn!(
@@ -216,34 +353,148 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             );
         }
 
+        if let Some(canary_validator) = &config.canary_validator {
+            let canary_validator = canary_validator.compress();
+            if !voting_pubkeys.contains(&&canary_validator) {
+                return Err(format!(
+                    "The canary validator {:?} is not an enabled local validator",
+                    canary_validator
+                ));
+            }
+            info!(
+                log,
+                "Logging duty diagnostics for canary validator";
+                "pubkey" => ?canary_validator,
+            );
+        }
+
+        if let Some(opt_out_file) = &config.sync_committee_opt_out_file {
+            for pubkey in opt_out_file.iter() {
+                if !voting_pubkeys.contains(&pubkey) {
+                    warn!(
+                        log,
+                        "Unknown validator in sync committee opt-out file";
+                        "msg" => "the validator is not an enabled local validator",
+                        "pubkey" => ?pubkey,
+                    );
+                }
+            }
+            warn!(
+                log,
+                "Sync committee participation disabled";
+                "msg" => "these validators will miss sync committee rewards and be penalized \
+                    whilst in a sync committee",
+                "count" => opt_out_file.len(),
+            );
+        }
+
+        if let Some(enabled_pubkeys) = &config.builder_proposals_enabled_pubkeys {
+            let mut num_enabled = 0;
+            for pubkey in enabled_pubkeys.iter() {
+                if !voting_pubkeys.contains(&pubkey) {
+                    warn!(
+                        log,
+                        "Unknown validator in builder proposals enabled file";
+                        "msg" => "the validator is not an enabled local validator",
+                        "pubkey" => ?pubkey,
+                    );
+                } else if validators.builder_proposals(pubkey) != Some(false) {
+                    num_enabled += 1;
+                }
+            }
+            info!(
+                log,
+                "Builder proposals enabled for listed validators";
+                "msg" => "builder_proposals in the validator definitions takes precedence",
+                "listed" => enabled_pubkeys.len(),
+                "enabled" => num_enabled,
+            );
+        }
+
         // Initialize slashing protection.
         //
         // Create the slashing database if there are no validators, even if
         // `init_slashing_protection` is not supplied. There is no risk in creating a slashing
         // database without any validators in it.
         let slashing_db_path = config.validator_dir.join(SLASHING_PROTECTION_FILENAME);
+        if config.min_free_disk_bytes > 0 {
+            disk_space::check_free_disk_space(
+                &config.validator_dir,
+                config.min_free_disk_bytes,
+                &log,
+            )?;
+        }
+        let slashing_protection_pool_size = config.slashing_protection_pool_size();
+        if slashing_protection_pool_size > slashing_protection::POOL_SIZE {
+            warn!(
+                log,
+                "Slashing protection database not locked exclusively";
+                "msg" => "other processes can open the database whilst the validator client \
+                    is running, reduce --slashing-protection-pool-size to 1 to prevent this",
+                "pool_size" => slashing_protection_pool_size,
+            );
+        }
         let slashing_protection = if config.init_slashing_protection || voting_pubkeys.is_empty() {
-            SlashingDatabase::open_or_create(&slashing_db_path).map_err(|e| {
+            SlashingDatabase::open_or_create_with_pool_size(
+                &slashing_db_path,
+                slashing_protection_pool_size,
+            )
+            .map_err(|e| {
                 format!(
                     "Failed to open or create slashing protection database: {:?}",
                     e
                 )
             })
         } else {
-            SlashingDatabase::open(&slashing_db_path).map_err(|e| {
-                format!(
-                    "Failed to open slashing protection database: {:?}.\n\
-                     Ensure that `slashing_protection.sqlite` is in {:?} folder",
-                    e, config.validator_dir
-                )
-            })
+            SlashingDatabase::open_with_pool_size(&slashing_db_path, slashing_protection_pool_size)
+                .map_err(|e| {
+                    format!(
+                        "Failed to open slashing protection database: {:?}.\n\
+                         Ensure that `slashing_protection.sqlite` is in {:?} folder",
+                        e, config.validator_dir
+                    )
+                })
         }?;
 
         // Check validator registration with slashing protection, or auto-register all validators.
         if config.init_slashing_protection {
-            slashing_protection
-                .register_validators(voting_pubkeys.iter().copied())
-                .map_err(|e| format!("Error while registering slashing protection: {:?}", e))?;
+            let allow_existing = match config.on_existing_slashing_protection {
+                OnExistingSlashingProtection::Keep => true,
+                OnExistingSlashingProtection::Strict => false,
+            };
+            let outcomes = slashing_protection
+                .register_validators_with_outcomes(voting_pubkeys.iter().copied(), allow_existing)
+                .map_err(|e| match e {
+                    NotSafe::ExistingValidator(pubkey) => format!(
+                        "Validator {:?} is already present in the slashing protection database. \
+                         Refusing to start with --on-existing-slashing-protection strict, no \
+                         validators were registered",
+                        pubkey
+                    ),
+                    e => format!("Error while registering slashing protection: {:?}", e),
+                })?;
+
+            for (pubkey, outcome) in outcomes {
+                match outcome {
+                    RegistrationOutcome::Registered => info!(
+                        log,
+                        "Registered validator for slashing protection";
+                        "pubkey" => ?pubkey,
+                    ),
+                    RegistrationOutcome::Reenabled => warn!(
+                        log,
+                        "Re-enabled validator with existing slashing protection";
+                        "msg" => "existing records were kept",
+                        "pubkey" => ?pubkey,
+                    ),
+                    RegistrationOutcome::AlreadyRegistered => warn!(
+                        log,
+                        "Validator already registered for slashing protection";
+                        "msg" => "existing records were kept",
+                        "pubkey" => ?pubkey,
+                    ),
+                }
+            }
         } else {
             slashing_protection
                 .check_validator_registrations(voting_pubkeys.iter().copied())
@@ -269,21 +520,78 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             let url = x.1;
             let slot_duration = Duration::from_secs(context.eth2_config.spec.seconds_per_slot);
 
-            let mut beacon_node_http_client_builder = ClientBuilder::new();
+            // Load custom root certificates if specified.
+            let certificates = config
+                .beacon_nodes_tls_certs
+                .iter()
+                .flatten()
+                .map(load_pem_certificate)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let min_tls_version = config
+                .beacon_nodes_min_tls_version
+                .as_deref()
+                .map(parse_tls_version)
+                .transpose()?;
+
+            // Keep the certificate presented by the beacon node so that its expiry can be checked.
+            let check_tls_cert_expiry = url.full.scheme() == "https"
+                && !config.beacon_nodes_tls_cert_expiry_window.is_zero();
+
+            let low_memory_mode = config.low_memory_mode;
+            let dns_ttl = config.beacon_node_dns_ttl;
+            let build_http_client = move || {
+                let mut beacon_node_http_client_builder = ClientBuilder::new();
+
+                for cert in &certificates {
+                    beacon_node_http_client_builder =
+                        beacon_node_http_client_builder.add_root_certificate(cert.clone());
+                }
+
+                if let Some(min_tls_version) = min_tls_version {
+                    // The native TLS backend can't enforce TLS 1.3, rustls only supports 1.2 and
+                    // 1.3.
+                    if min_tls_version == tls::Version::TLS_1_3 {
+                        beacon_node_http_client_builder =
+                            beacon_node_http_client_builder.use_rustls_tls();
+                    }
+                    beacon_node_http_client_builder =
+                        beacon_node_http_client_builder.min_tls_version(min_tls_version);
+                }
 
-            // Add new custom root certificates if specified.
-            if let Some(certificates) = &config.beacon_nodes_tls_certs {
-                for cert in certificates {
+                if low_memory_mode {
                     beacon_node_http_client_builder = beacon_node_http_client_builder
-                        .add_root_certificate(load_pem_certificate(cert)?);
+                        .pool_max_idle_per_host(LOW_MEMORY_MAX_IDLE_CONNECTIONS_PER_BEACON_NODE);
+                }
+
+                // A DNS TTL of zero disables connection reuse entirely.
+                if matches!(dns_ttl, Some(dns_ttl) if dns_ttl.is_zero()) {
+                    beacon_node_http_client_builder =
+                        beacon_node_http_client_builder.pool_max_idle_per_host(0);
+                }
+
+                if check_tls_cert_expiry {
+                    beacon_node_http_client_builder =
+                        beacon_node_http_client_builder.tls_info(true);
                 }
-            }
 
-            let beacon_node_http_client = beacon_node_http_client_builder
-                // Set default timeout to be the full slot duration.
-                .timeout(slot_duration)
-                .build()
-                .map_err(|e| format!("Unable to build HTTP client: {:?}", e))?;
+                beacon_node_http_client_builder
+                    // Set default timeout to be the full slot duration.
+                    .timeout(slot_duration)
+                    .build()
+            };
+
+            let beacon_node_http_client =
+                build_http_client().map_err(|e| format!("Unable to build HTTP client: {:?}", e))?;
+
+            if check_tls_cert_expiry {
+                tls_expiry::start_tls_cert_expiry_service(
+                    context.service_context("tls_cert_expiry".into()),
+                    beacon_node_http_client.clone(),
+                    url.clone(),
+                    config.beacon_nodes_tls_cert_expiry_window,
+                );
+            }
 
             // Use quicker timeouts if a fallback beacon node exists.
             let timeouts = if i < last_beacon_node_index && !config.use_long_timeouts {
@@ -309,11 +617,31 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
                 Timeouts::set_all(slot_duration)
             };
 
-            Ok(BeaconNodeHttpClient::from_components(
+            let mut beacon_node = BeaconNodeHttpClient::from_components(
                 url.clone(),
                 beacon_node_http_client,
                 timeouts,
-            ))
+            );
+            // Replace the client, along with its pooled connections, once it is older than the DNS
+            // TTL so that changes to the beacon node's DNS records are picked up. Connections that
+            // are used every slot are never idle, so an idle timeout alone would not close them.
+            if let Some(dns_ttl) = dns_ttl.filter(|dns_ttl| !dns_ttl.is_zero()) {
+                beacon_node = beacon_node.with_max_client_age(dns_ttl, Arc::new(build_http_client));
+            }
+            if config.lenient_bn_responses {
+                let log = log.clone();
+                let endpoint = url.to_string();
+                Ok(beacon_node.with_lenient_responses(move |field| {
+                    debug!(
+                        log,
+                        "Substituted default for omitted response field";
+                        "field" => field,
+                        "endpoint" => &endpoint,
+                    )
+                }))
+            } else {
+                Ok(beacon_node)
+            }
         };
 
         let beacon_nodes: Vec<BeaconNodeHttpClient> = config
@@ -330,6 +658,13 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             .map(beacon_node_setup)
             .collect::<Result<Vec<BeaconNodeHttpClient>, String>>()?;
 
+        let attestation_fallback_nodes: Vec<BeaconNodeHttpClient> = config
+            .attestation_fallback_nodes
+            .iter()
+            .enumerate()
+            .map(beacon_node_setup)
+            .collect::<Result<Vec<BeaconNodeHttpClient>, String>>()?;
+
         let num_nodes = beacon_nodes.len();
         let candidates = beacon_nodes
             .into_iter()
@@ -342,6 +677,12 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             .map(CandidateBeaconNode::new)
             .collect();
 
+        let attestation_fallback_nodes_num = attestation_fallback_nodes.len();
+        let attestation_fallback_candidates = attestation_fallback_nodes
+            .into_iter()
+            .map(CandidateBeaconNode::new)
+            .collect();
+
         // Set the count for beacon node fallbacks excluding the primary beacon node.
         set_gauge(
             &http_metrics::metrics::ETH2_FALLBACK_CONFIGURED,
@@ -373,12 +714,152 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             log.clone(),
         );
 
+        let mut attestation_fallback_nodes: BeaconNodeFallback<_, T> = BeaconNodeFallback::new(
+            attestation_fallback_candidates,
+            config.disable_run_on_all,
+            context.eth2_config.spec.clone(),
+            log.clone(),
+        );
+
+        if config.beacon_node_consensus_check {
+            info!(
+                log,
+                "Beacon node consensus check enabled";
+                "msg" => "beacon nodes which persistently disagree with the majority head will be quarantined",
+            );
+            beacon_nodes.enable_consensus_check();
+            proposer_nodes.enable_consensus_check();
+            attestation_fallback_nodes.enable_consensus_check();
+        }
+
+        if config.reregister_on_bn_restart {
+            beacon_nodes.enable_restart_detection();
+        }
+
+        if config.beacon_node_selection != BeaconNodeSelection::Priority {
+            info!(
+                log,
+                "Beacon node selection";
+                "mode" => %config.beacon_node_selection,
+            );
+        }
+        beacon_nodes.set_selection(config.beacon_node_selection);
+        proposer_nodes.set_selection(config.beacon_node_selection);
+        attestation_fallback_nodes.set_selection(config.beacon_node_selection);
+
+        if config.persist_beacon_node_health {
+            for (nodes, kind) in [
+                (&mut beacon_nodes, http_metrics::metrics::BEACON_NODES),
+                (&mut proposer_nodes, http_metrics::metrics::PROPOSER_NODES),
+                (
+                    &mut attestation_fallback_nodes,
+                    http_metrics::metrics::ATTESTATION_FALLBACK_NODES,
+                ),
+            ] {
+                if nodes.num_total() == 0 {
+                    continue;
+                }
+                nodes.set_health_path(beacon_node_health::health_path(&config.validator_dir, kind));
+                nodes.restore_health().await;
+            }
+        }
+
         // Perform some potentially long-running initialization tasks.
-        let (genesis_time, genesis_validators_root) = tokio::select! {
-            tuple = init_from_beacon_node(&beacon_nodes, &proposer_nodes, &context) => tuple?,
+        tokio::select! {
+            result = init_from_beacon_node(
+                &beacon_nodes,
+                &proposer_nodes,
+                config.beacon_node_startup_timeout,
+                &context,
+            ) => result?,
             () = context.executor.exit() => return Err("Shutting down".to_string())
         };
 
+        let network_genesis_validators_root = network_genesis_validators_root(&context)?;
+
+        // The genesis of the beacon nodes is not needed if the genesis time is overridden and the
+        // genesis validators root is known without them, in which case the validator client does
+        // not wait for the beacon nodes to reach genesis.
+        let beacon_node_genesis = if config.genesis_time_override.is_some()
+            && config
+                .genesis_validators_root_override
+                .or(network_genesis_validators_root)
+                .is_some()
+        {
+            None
+        } else {
+            tokio::select! {
+                genesis = wait_for_beacon_node_genesis(&beacon_nodes, &context) => Some(genesis),
+                () = context.executor.exit() => return Err("Shutting down".to_string())
+            }
+        };
+        let beacon_node_genesis_time = beacon_node_genesis.map(|(genesis_time, _)| genesis_time);
+        let beacon_node_genesis_validators_root =
+            beacon_node_genesis.map(|(_, genesis_validators_root)| genesis_validators_root);
+
+        if config.require_matching_genesis {
+            let expected_genesis_validators_root = expected_genesis_validators_root(
+                &[&beacon_nodes, &proposer_nodes, &attestation_fallback_nodes],
+                network_genesis_validators_root,
+                &log,
+            )
+            .await?;
+            if let Some(expected_genesis_validators_root) = expected_genesis_validators_root {
+                info!(
+                    log,
+                    "Requiring matching genesis";
+                    "msg" => "beacon nodes which report a different genesis validators root will not be used",
+                    "genesis_validators_root" => ?expected_genesis_validators_root,
+                );
+                for nodes in [
+                    &mut beacon_nodes,
+                    &mut proposer_nodes,
+                    &mut attestation_fallback_nodes,
+                ] {
+                    nodes.set_genesis_validators_root(expected_genesis_validators_root);
+                    // Refuse mismatching nodes before any duties are performed, rather than
+                    // waiting for the fallback updater service.
+                    nodes.update_all_candidates().await;
+                }
+            }
+        }
+
+        check_beacon_node_versions(
+            &[&beacon_nodes, &proposer_nodes, &attestation_fallback_nodes],
+            config.require_homogeneous_beacon_nodes,
+            &log,
+        )
+        .await?;
+
+        let genesis_time = if let Some(genesis_time) = config.genesis_time_override {
+            warn!(
+                log,
+                "Using overridden genesis time";
+                "msg" => "this should only be used for testing",
+                "genesis_time" => genesis_time,
+                "beacon_node_genesis_time" => ?beacon_node_genesis_time,
+            );
+            genesis_time
+        } else {
+            beacon_node_genesis_time.ok_or("Beacon node genesis time is unknown")?
+        };
+
+        let genesis_validators_root =
+            if let Some(genesis_validators_root) = config.genesis_validators_root_override {
+                warn!(
+                    log,
+                    "Using overridden genesis validators root for signing";
+                    "msg" => "this should only be used for testing",
+                    "genesis_validators_root" => ?genesis_validators_root,
+                    "beacon_node_genesis_validators_root" => ?beacon_node_genesis_validators_root,
+                );
+                genesis_validators_root
+            } else {
+                beacon_node_genesis_validators_root
+                    .or(network_genesis_validators_root)
+                    .ok_or("Beacon node genesis validators root is unknown")?
+            };
+
         // Update the metrics server.
         if let Some(ctx) = &http_metrics_ctx {
             ctx.shared.write().genesis_time = Some(genesis_time);
@@ -392,20 +873,46 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
 
         beacon_nodes.set_slot_clock(slot_clock.clone());
         proposer_nodes.set_slot_clock(slot_clock.clone());
+        attestation_fallback_nodes.set_slot_clock(slot_clock.clone());
 
         let beacon_nodes = Arc::new(beacon_nodes);
-        start_fallback_updater_service(context.clone(), beacon_nodes.clone())?;
+        start_fallback_updater_service(
+            context.clone(),
+            beacon_nodes.clone(),
+            http_metrics::metrics::BEACON_NODES,
+        )?;
 
         let proposer_nodes = Arc::new(proposer_nodes);
-        start_fallback_updater_service(context.clone(), proposer_nodes.clone())?;
+        start_fallback_updater_service(
+            context.clone(),
+            proposer_nodes.clone(),
+            http_metrics::metrics::PROPOSER_NODES,
+        )?;
+
+        let attestation_fallback_nodes = Arc::new(attestation_fallback_nodes);
+        start_fallback_updater_service(
+            context.clone(),
+            attestation_fallback_nodes.clone(),
+            http_metrics::metrics::ATTESTATION_FALLBACK_NODES,
+        )?;
 
         let doppelganger_service = if config.enable_doppelganger_protection {
-            Some(Arc::new(DoppelgangerService::new(
+            let doppelganger_service = DoppelgangerService::new(
                 context
                     .service_context(DOPPELGANGER_SERVICE_NAME.into())
                     .log()
                     .clone(),
-            )))
+            );
+            let doppelganger_service = if config.doppelganger_disable_detected {
+                doppelganger_service.disable_detected()
+            } else {
+                doppelganger_service
+            };
+            Some(Arc::new(if config.continuous_doppelganger {
+                doppelganger_service.continuous_detection(config.continuous_doppelganger_pause)
+            } else {
+                doppelganger_service
+            }))
         } else {
             None
         };
@@ -414,17 +921,29 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             validators,
             slashing_protection,
             genesis_validators_root,
-            context.eth2_config.spec.clone(),
+            signing_spec,
             doppelganger_service.clone(),
             slot_clock.clone(),
             &config,
             context.executor.clone(),
-            log.clone(),
+            log.new(o!(LOG_CATEGORY_KEY => SIGNING_LOG_CATEGORY)),
         ));
 
         // Ensure all validators are registered in doppelganger protection.
         validator_store.register_all_in_doppelganger_protection_if_enabled()?;
 
+        if config.on_missing_fee_recipient == OnMissingFeeRecipient::ErrorStartup {
+            let missing = validator_store.validators_without_fee_recipient();
+            if !missing.is_empty() {
+                return Err(format!(
+                    "{} validator(s) have no fee recipient configured, set one in the validator \
+                     definitions or with --suggested-fee-recipient: {:?}",
+                    missing.len(),
+                    missing
+                ));
+            }
+        }
+
         info!(
             log,
             "Loaded validator keypair store";
@@ -439,16 +958,40 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
         }
 
         let duties_context = context.service_context("duties".into());
+        let validator_index_cache = if config.rebuild_index_cache {
+            info!(log, "Rebuilding validator index cache");
+            ValidatorIndexCache::new(&config.validator_dir)
+        } else {
+            ValidatorIndexCache::open_or_create(&config.validator_dir).unwrap_or_else(|e| {
+                warn!(
+                    log,
+                    "Unable to load validator index cache";
+                    "msg" => "indices will be fetched from the beacon node",
+                    "error" => ?e,
+                );
+                ValidatorIndexCache::new(&config.validator_dir)
+            })
+        };
+
         let duties_service = Arc::new(DutiesService {
             attesters: <_>::default(),
             proposers: <_>::default(),
             sync_duties: <_>::default(),
             slot_clock: slot_clock.clone(),
             beacon_nodes: beacon_nodes.clone(),
+            validator_index_cache: Mutex::new(validator_index_cache),
             validator_store: validator_store.clone(),
             spec: context.eth2_config.spec.clone(),
             context: duties_context,
             enable_high_validator_count_metrics: config.enable_high_validator_count_metrics,
+            metrics_validator_bucketing: config.metrics_validator_bucketing,
+            min_balance_alert_gwei: config.min_balance_alert_gwei,
+            auto_disable_inactive: config.auto_disable_inactive,
+            index_resolution_interval: config.index_resolution_interval,
+            unresolved_indices: <_>::default(),
+            validator_statuses: <_>::default(),
+            activation_poll_epochs: config.activation_poll_epochs,
+            duties_batch_sizes: DutiesBatchSizes::new(config.duties_batch_size),
         });
 
         // Update the metrics server.
@@ -461,10 +1004,25 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             .slot_clock(slot_clock.clone())
             .validator_store(validator_store.clone())
             .beacon_nodes(beacon_nodes.clone())
-            .runtime_context(context.service_context("block".into()))
+            .runtime_context(
+                context
+                    .service_context("block".into())
+                    .log_category_context(SIGNING_LOG_CATEGORY),
+            )
             .graffiti(config.graffiti)
+            .graffiti_force(config.graffiti_force)
             .graffiti_file(config.graffiti_file.clone())
-            .block_delay(config.block_delay);
+            .block_delay(block_delay)
+            .builder_unavailable_action(config.builder_unavailable_action)
+            .verify_block_before_signing(config.verify_block_before_signing)
+            .verify_fee_recipient(config.verify_fee_recipient)
+            .refuse_proposal_on_optimistic(config.refuse_proposal_on_optimistic)
+            .network_name(config.network_name.clone())
+            .duties_service(duties_service.clone())
+            .block_prefetch_lead(config.block_prefetch_lead)
+            .block_production_limits(BlockProductionLimits {
+                max_transactions: config.block_max_transactions,
+            });
 
         // If we have proposer nodes, add them to the block service builder.
         if proposer_nodes_num > 0 {
@@ -473,29 +1031,74 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
 
         let block_service = block_service_builder.build()?;
 
-        let attestation_service = AttestationServiceBuilder::new()
+        let mut attestation_service_builder = AttestationServiceBuilder::new()
             .duties_service(duties_service.clone())
             .slot_clock(slot_clock.clone())
             .validator_store(validator_store.clone())
             .beacon_nodes(beacon_nodes.clone())
-            .runtime_context(context.service_context("attestation".into()))
-            .build()?;
+            .attestation_deadline_fraction(config.attestation_deadline_fraction)
+            .max_attestation_slot_lag(config.max_attestation_slot_lag)
+            .max_pending_aggregations(config.max_pending_aggregations)
+            .refuse_attestation_on_optimistic(config.refuse_attestation_on_optimistic)
+            .attestation_publish_retry_nodes(config.attestation_publish_retry_nodes)
+            .runtime_context(
+                context
+                    .service_context("attestation".into())
+                    .log_category_context(SIGNING_LOG_CATEGORY),
+            );
+
+        if attestation_fallback_nodes_num > 0 {
+            attestation_service_builder =
+                attestation_service_builder.attestation_fallback_nodes(attestation_fallback_nodes);
+        }
 
-        let preparation_service = PreparationServiceBuilder::new()
+        let attestation_service = attestation_service_builder.build()?;
+
+        let mut preparation_service_builder = PreparationServiceBuilder::new()
             .slot_clock(slot_clock.clone())
             .validator_store(validator_store.clone())
             .beacon_nodes(beacon_nodes.clone())
             .runtime_context(context.service_context("preparation".into()))
             .builder_registration_timestamp_override(config.builder_registration_timestamp_override)
+            .builder_registration_timestamp_override_file(
+                config.builder_registration_timestamp_override_file.clone(),
+            )
+            .relay_tag_file(config.relay_tag_file.clone())
             .validator_registration_batch_size(config.validator_registration_batch_size)
-            .build()?;
+            .validator_registration_max_body_bytes(config.validator_registration_max_body_bytes)
+            .validator_registration_refresh_interval(config.validator_registration_refresh_interval)
+            .gas_limit_bounds(config.gas_limit_bounds());
+
+        if config.register_via_proposer_nodes {
+            if proposer_nodes_num > 0 {
+                info!(
+                    log,
+                    "Sending validator registrations to proposer nodes";
+                    "proposer_nodes" => proposer_nodes_num,
+                );
+                preparation_service_builder =
+                    preparation_service_builder.registration_proposer_nodes(proposer_nodes.clone());
+            } else {
+                warn!(
+                    log,
+                    "No proposer nodes configured";
+                    "msg" => "validator registrations will be sent to the beacon nodes",
+                );
+            }
+        }
+
+        let preparation_service = preparation_service_builder.build()?;
 
         let sync_committee_service = SyncCommitteeService::new(
             duties_service.clone(),
             validator_store.clone(),
             slot_clock.clone(),
             beacon_nodes.clone(),
-            context.service_context("sync_committee".into()),
+            config.sync_committee_opt_out_file.clone(),
+            config.sync_committee_broadcast,
+            context
+                .service_context("sync_committee".into())
+                .log_category_context(SIGNING_LOG_CATEGORY),
         );
 
         // Wait until genesis has occurred.
@@ -520,6 +1123,31 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
     }
 
     pub fn start_service(&mut self) -> Result<(), String> {
+        if self.config.print_duties {
+            let duties_service = self.duties_service.clone();
+            let exit_after_print_duties = self.config.exit_after_print_duties;
+            let executor = self.context.executor.clone();
+            let log = self.context.log().clone();
+            self.context.executor.spawn(
+                async move {
+                    if let Err(e) = duties_service::print_duty_schedule(&duties_service).await {
+                        error!(log, "Unable to print duties"; "error" => ?e);
+                    }
+                    if exit_after_print_duties {
+                        let _ = executor
+                            .shutdown_sender()
+                            .try_send(ShutdownReason::Success("Printed duties"));
+                    }
+                },
+                "print_duties",
+            );
+
+            // Do not start any services which might sign messages.
+            if exit_after_print_duties {
+                return Ok(());
+            }
+        }
+
         // We use `SLOTS_PER_EPOCH` as the capacity of the block notification channel, because
         // we don't except notifications to be delayed by more than a single slot, let alone a
         // whole epoch!
@@ -549,6 +1177,23 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             .start_update_service(&self.context.eth2_config.spec)
             .map_err(|e| format!("Unable to start preparation service: {}", e))?;
 
+        if self.config.validator_load_priority.is_some() {
+            let validator_store = self.validator_store.clone();
+            let executor = self.context.executor.clone();
+            let log = log.clone();
+            self.context.executor.spawn(
+                async move {
+                    if let Err(e) = validator_store.initialize_deferred_validators().await {
+                        crit!(log, "Unable to initialize deferred validators"; "error" => e);
+                        let _ = executor.shutdown_sender().try_send(ShutdownReason::Failure(
+                            "Unable to initialize deferred validators",
+                        ));
+                    }
+                },
+                "deferred_validators",
+            );
+        }
+
         if let Some(doppelganger_service) = self.doppelganger_service.clone() {
             DoppelgangerService::start_update_service(
                 doppelganger_service,
@@ -572,9 +1217,12 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
                 task_executor: self.context.executor.clone(),
                 api_secret,
                 validator_store: Some(self.validator_store.clone()),
+                preparation_service: Some(self.preparation_service.clone()),
+                duties_service: Some(self.duties_service.clone()),
                 validator_dir: Some(self.config.validator_dir.clone()),
                 graffiti_file: self.config.graffiti_file.clone(),
                 graffiti_flag: self.config.graffiti,
+                graffiti_force: self.config.graffiti_force,
                 spec: self.context.eth2_config.spec.clone(),
                 config: self.config.http_api.clone(),
                 sse_logging_components: self.context.sse_logging_components.clone(),
@@ -599,14 +1247,48 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
             None
         };
 
+        // An overridden fork schedule deliberately differs from that of the beacon nodes, so it
+        // is not compared against theirs.
+        if self.config.fork_schedule_override.is_none() {
+            fork_schedule::start_fork_schedule_service(
+                self.context.clone(),
+                self.duties_service.slot_clock.clone(),
+                self.duties_service.beacon_nodes.clone(),
+                self.validator_store.clone(),
+            );
+        } else {
+            info!(
+                log,
+                "Not checking fork versions against the beacon node";
+                "msg" => "the fork schedule used for signing is overridden",
+            );
+        }
+
         if self.config.enable_latency_measurement_service {
             latency::start_latency_service(
                 self.context.clone(),
                 self.duties_service.slot_clock.clone(),
                 self.duties_service.beacon_nodes.clone(),
+                self.config.latency_sample_interval,
+                self.config.latency_sample_window,
             );
         }
 
+        if self.config.min_free_disk_bytes > 0 {
+            disk_space::start_disk_space_service(
+                self.context.service_context("disk_space".into()),
+                self.config.validator_dir.clone(),
+                self.config.min_free_disk_bytes,
+            );
+        }
+
+        dir_access::start_dir_access_service(
+            self.context.service_context("dir_access".into()),
+            self.config.validator_dir.clone(),
+            self.config.secrets_dir.clone(),
+            self.config.dir_access_timeout,
+        );
+
         Ok(())
     }
 }
@@ -614,8 +1296,10 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
 async fn init_from_beacon_node<E: EthSpec>(
     beacon_nodes: &BeaconNodeFallback<SystemTimeSlotClock, E>,
     proposer_nodes: &BeaconNodeFallback<SystemTimeSlotClock, E>,
+    startup_timeout: Option<Duration>,
     context: &RuntimeContext<E>,
-) -> Result<(u64, Hash256), String> {
+) -> Result<(), String> {
+    let start = Instant::now();
     loop {
         beacon_nodes.update_all_candidates().await;
         proposer_nodes.update_all_candidates().await;
@@ -657,10 +1341,31 @@ async fn init_from_beacon_node<E: EthSpec>(
             );
             break;
         } else {
+            let elapsed = start.elapsed();
+            if let Some(timeout) = startup_timeout {
+                if elapsed >= timeout {
+                    crit!(
+                        context.log(),
+                        "Unable to connect to a beacon node";
+                        "msg" => "exiting since --beacon-node-startup-timeout has elapsed",
+                        "timeout" => format!("{} seconds", timeout.as_secs()),
+                        "total" => num_total,
+                    );
+                    return Err(format!(
+                        "No beacon node became reachable within {} seconds",
+                        timeout.as_secs()
+                    ));
+                }
+            }
             warn!(
                 context.log(),
                 "Unable to connect to a beacon node";
                 "retry in" => format!("{} seconds", RETRY_DELAY.as_secs()),
+                "waited" => format!("{} seconds", elapsed.as_secs()),
+                "timeout" => startup_timeout.map_or_else(
+                    || "none".to_string(),
+                    |timeout| format!("{} seconds", timeout.as_secs()),
+                ),
                 "total" => num_total,
                 "available" => num_available,
             );
@@ -668,6 +1373,85 @@ async fn init_from_beacon_node<E: EthSpec>(
         }
     }
 
+    Ok(())
+}
+
+/// Returns the genesis validators root of the genesis state in the network config, if it is
+/// known.
+fn network_genesis_validators_root<E: EthSpec>(
+    context: &RuntimeContext<E>,
+) -> Result<Option<Hash256>, String> {
+    match &context.eth2_network_config {
+        Some(eth2_network_config) if eth2_network_config.beacon_state_is_known() => {
+            let genesis_state = eth2_network_config.beacon_state::<E>()?;
+            Ok(Some(genesis_state.genesis_validators_root()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Returns the genesis validators root which all beacon nodes must report.
+///
+/// This is the root of the genesis state in the network config if it is known. Otherwise, all
+/// beacon nodes which respond must agree on their root, so that the reference is not decided by
+/// whichever node happens to respond first.
+async fn expected_genesis_validators_root<E: EthSpec>(
+    fallbacks: &[&BeaconNodeFallback<SystemTimeSlotClock, E>],
+    network_genesis_validators_root: Option<Hash256>,
+    log: &Logger,
+) -> Result<Option<Hash256>, String> {
+    if network_genesis_validators_root.is_some() {
+        return Ok(network_genesis_validators_root);
+    }
+
+    let mut roots = vec![];
+    for fallback in fallbacks {
+        roots.extend(
+            fallback
+                .genesis_validators_roots()
+                .await
+                .into_iter()
+                .filter_map(|(endpoint, root)| Some((endpoint, root?))),
+        );
+    }
+
+    let distinct = roots.iter().map(|(_, root)| *root).collect::<HashSet<_>>();
+    match distinct.len() {
+        0 => {
+            warn!(
+                log,
+                "Unable to check the genesis of the beacon nodes";
+                "msg" => "no beacon node reported a genesis validators root",
+            );
+            Ok(None)
+        }
+        1 => Ok(distinct.into_iter().next()),
+        _ => {
+            for (endpoint, root) in &roots {
+                error!(
+                    log,
+                    "Beacon node genesis validators root";
+                    "endpoint" => endpoint,
+                    "genesis_validators_root" => ?root,
+                );
+            }
+            crit!(
+                log,
+                "Beacon nodes report different genesis validators roots";
+                "msg" => "refusing to start since the network config does not include a genesis \
+                          state to decide which beacon nodes are on the right network",
+            );
+            Err("Beacon nodes report different genesis validators roots".to_string())
+        }
+    }
+}
+
+/// Returns the genesis time and genesis validators root of the beacon nodes, waiting until they
+/// have reached genesis.
+async fn wait_for_beacon_node_genesis<E: EthSpec>(
+    beacon_nodes: &BeaconNodeFallback<SystemTimeSlotClock, E>,
+    context: &RuntimeContext<E>,
+) -> (u64, Hash256) {
     let genesis = loop {
         match beacon_nodes
             .first_success(
@@ -704,7 +1488,7 @@ async fn init_from_beacon_node<E: EthSpec>(
         sleep(RETRY_DELAY).await;
     };
 
-    Ok((genesis.genesis_time, genesis.genesis_validators_root))
+    (genesis.genesis_time, genesis.genesis_validators_root)
 }
 
 async fn wait_for_genesis<E: EthSpec>(
@@ -752,6 +1536,67 @@ async fn wait_for_genesis<E: EthSpec>(
     Ok(())
 }
 
+/// Warn if the reachable beacon nodes run different clients or client versions, returning an error
+/// instead if `require_homogeneous` is set.
+async fn check_beacon_node_versions<E: EthSpec>(
+    fallbacks: &[&BeaconNodeFallback<SystemTimeSlotClock, E>],
+    require_homogeneous: bool,
+    log: &Logger,
+) -> Result<(), String> {
+    let mut versions = vec![];
+    for fallback in fallbacks {
+        versions.extend(
+            fallback
+                .node_versions()
+                .await
+                .into_iter()
+                .filter_map(|(endpoint, version)| Some((endpoint, version?))),
+        );
+    }
+
+    let distinct = beacon_node_version::distinct_versions(
+        versions.iter().map(|(_, version)| version.as_str()),
+    );
+    set_gauge(
+        &http_metrics::metrics::BEACON_NODE_DISTINCT_VERSIONS,
+        distinct.len() as i64,
+    );
+    if distinct.len() <= 1 {
+        return Ok(());
+    }
+
+    for (endpoint, version) in &versions {
+        warn!(
+            log,
+            "Beacon node version";
+            "endpoint" => endpoint,
+            "version" => version,
+        );
+    }
+    let distinct = distinct
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if require_homogeneous {
+        crit!(
+            log,
+            "Beacon nodes run different versions";
+            "msg" => "refusing to start due to --require-homogeneous-beacon-nodes",
+            "versions" => &distinct,
+        );
+        Err(format!("Beacon nodes run different versions: {}", distinct))
+    } else {
+        warn!(
+            log,
+            "Beacon nodes run different versions";
+            "msg" => "differences in behaviour between versions may cause missed duties",
+            "versions" => distinct,
+        );
+        Ok(())
+    }
+}
+
 /// Request the version from the node, looping back and trying again on failure. Exit once the node
 /// has been contacted.
 async fn poll_whilst_waiting_for_genesis<E: EthSpec>(
@@ -815,23 +1660,162 @@ pub fn load_pem_certificate<P: AsRef<Path>>(pem_path: P) -> Result<Certificate,
     Certificate::from_pem(&buf).map_err(|e| format!("Unable to parse certificate: {}", e))
 }
 
+/// Parses a TLS version of the form `1.2`.
+pub fn parse_tls_version(version: &str) -> Result<tls::Version, String> {
+    match version {
+        "1.0" => Ok(tls::Version::TLS_1_0),
+        "1.1" => Ok(tls::Version::TLS_1_1),
+        "1.2" => Ok(tls::Version::TLS_1_2),
+        "1.3" => Ok(tls::Version::TLS_1_3),
+        other => Err(format!(
+            "Unsupported TLS version: {}, expected one of 1.0, 1.1, 1.2 or 1.3",
+            other
+        )),
+    }
+}
+
 // Given the various graffiti control methods, determine the graffiti that will be used for
 // the next block produced by the validator with the given public key.
+//
+// The graffiti file takes precedence over the validator definitions, which take precedence over
+// the `--graffiti` flag, unless `graffiti_force` is set, in which case the flag is used for all
+// validators.
 pub fn determine_graffiti(
     validator_pubkey: &PublicKeyBytes,
     log: &Logger,
+    epoch: Epoch,
+    spec: &ChainSpec,
     graffiti_file: Option<GraffitiFile>,
     validator_definition_graffiti: Option<Graffiti>,
     graffiti_flag: Option<Graffiti>,
+    graffiti_force: bool,
 ) -> Option<Graffiti> {
-    graffiti_file
-        .and_then(|mut g| match g.load_graffiti(validator_pubkey) {
-            Ok(g) => g,
-            Err(e) => {
-                warn!(log, "Failed to read graffiti file"; "error" => ?e);
-                None
-            }
-        })
-        .or(validator_definition_graffiti)
-        .or(graffiti_flag)
+    determine_graffiti_with_source(
+        validator_pubkey,
+        log,
+        epoch,
+        spec,
+        graffiti_file,
+        validator_definition_graffiti,
+        graffiti_flag,
+        graffiti_force,
+    )
+    .value
+}
+
+// As `determine_graffiti`, but also reports which graffiti control method the value came from.
+pub fn determine_graffiti_with_source(
+    validator_pubkey: &PublicKeyBytes,
+    log: &Logger,
+    epoch: Epoch,
+    spec: &ChainSpec,
+    graffiti_file: Option<GraffitiFile>,
+    validator_definition_graffiti: Option<Graffiti>,
+    graffiti_flag: Option<Graffiti>,
+    graffiti_force: bool,
+) -> ResolvedSetting<Option<Graffiti>> {
+    if let (true, Some(graffiti)) = (graffiti_force, graffiti_flag) {
+        return ResolvedSetting::new(Some(graffiti), SettingSource::Global);
+    }
+
+    let file_graffiti =
+        graffiti_file.and_then(
+            |mut g| match g.load_graffiti(validator_pubkey, epoch, spec) {
+                Ok(graffiti) => {
+                    if g.duplicates().contains(validator_pubkey) {
+                        warn!(
+                            log,
+                            "Conflicting graffitis in graffiti file";
+                            "msg" => "using the last graffiti listed for the validator",
+                            "pubkey" => ?validator_pubkey,
+                        );
+                    }
+                    graffiti
+                }
+                Err(e) => {
+                    warn!(log, "Failed to read graffiti file"; "error" => ?e);
+                    None
+                }
+            },
+        );
+
+    if let Some(graffiti) = file_graffiti {
+        ResolvedSetting::new(Some(graffiti), SettingSource::File)
+    } else if let Some(graffiti) = validator_definition_graffiti {
+        ResolvedSetting::new(Some(graffiti), SettingSource::Keymanager)
+    } else if let Some(graffiti) = graffiti_flag {
+        ResolvedSetting::new(Some(graffiti), SettingSource::Global)
+    } else {
+        ResolvedSetting::new(None, SettingSource::Default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logging::test_logger;
+    use std::io::Write;
+    use std::str::FromStr;
+    use tempfile::TempDir;
+    use types::GraffitiString;
+
+    fn graffiti(graffiti: &str) -> Graffiti {
+        GraffitiString::from_str(graffiti).unwrap().into()
+    }
+
+    #[test]
+    fn graffiti_precedence() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("graffiti.txt");
+        File::create(&path)
+            .unwrap()
+            .write_all(b"default: file\n")
+            .unwrap();
+        let mut graffiti_file = GraffitiFile::new(path);
+        graffiti_file.read_graffiti_file().unwrap();
+
+        let log = test_logger();
+        let spec = ChainSpec::mainnet();
+        let resolve = |file: bool, definition: bool, flag: bool, force: bool| {
+            let resolved = determine_graffiti_with_source(
+                &PublicKeyBytes::empty(),
+                &log,
+                Epoch::new(0),
+                &spec,
+                file.then(|| graffiti_file.clone()),
+                definition.then(|| graffiti("definition")),
+                flag.then(|| graffiti("flag")),
+                force,
+            );
+            (resolved.value.map(|g| g.as_utf8_lossy()), resolved.source)
+        };
+        let resolved = |value: &str, source| (Some(value.to_string()), source);
+
+        assert_eq!(
+            resolve(true, true, true, false),
+            resolved("file", SettingSource::File)
+        );
+        assert_eq!(
+            resolve(false, true, true, false),
+            resolved("definition", SettingSource::Keymanager)
+        );
+        assert_eq!(
+            resolve(false, false, true, false),
+            resolved("flag", SettingSource::Global)
+        );
+        assert_eq!(
+            resolve(false, false, false, false),
+            (None, SettingSource::Default)
+        );
+
+        // With `graffiti_force` the flag takes precedence over everything else.
+        assert_eq!(
+            resolve(true, true, true, true),
+            resolved("flag", SettingSource::Global)
+        );
+        assert_eq!(
+            resolve(true, true, false, true),
+            resolved("file", SettingSource::File)
+        );
+    }
 }
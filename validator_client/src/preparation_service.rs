@@ -1,16 +1,26 @@
-use crate::beacon_node_fallback::{BeaconNodeFallback, RequireSynced};
+use crate::beacon_node_fallback::{BeaconNodeFallback, Error, Errors, RequireSynced};
+use crate::registration_timestamp_file::RegistrationTimestampFile;
+use crate::relay_tag_file::RelayTagFile;
 use crate::validator_store::{DoppelgangerStatus, ValidatorStore};
 use crate::OfflineOnFailure;
 use bls::PublicKeyBytes;
 use environment::RuntimeContext;
+use eth2::lighthouse::ValidatorRelayTag;
+use eth2::lighthouse_vc::types::{RegisterValidatorResponse, RegisterValidatorStatus};
+use eth2::{BeaconNodeHttpClient, StatusCode};
 use parking_lot::RwLock;
+use serde_derive::{Deserialize, Serialize};
 use slog::{debug, error, info, warn};
 use slot_clock::SlotClock;
 use std::collections::HashMap;
+use std::future::Future;
 use std::hash::Hash;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
+use strum::{Display, EnumString, EnumVariantNames};
+use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration};
 use types::{
     Address, ChainSpec, EthSpec, ProposerPreparationData, SignedValidatorRegistrationData,
@@ -20,17 +30,99 @@ use types::{
 /// Number of epochs before the Bellatrix hard fork to begin posting proposer preparations.
 const PROPOSER_PREPARATION_LOOKAHEAD_EPOCHS: u64 = 2;
 
-/// Number of epochs to wait before re-submitting validator registration.
+/// Number of epochs to wait before re-submitting validator registration, unless a custom
+/// `validator_registration_refresh_interval` is supplied.
 const EPOCHS_PER_VALIDATOR_REGISTRATION_SUBMISSION: u64 = 1;
 
+/// The action to take when a validator has no fee recipient configured, neither in the validator
+/// definitions nor via `--suggested-fee-recipient`.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum OnMissingFeeRecipient {
+    /// Propose blocks anyway, leaving the choice of fee recipient to the beacon node.
+    ///
+    /// If the beacon node has no fee recipient configured either, a zero address may be used and
+    /// the transaction fees of the block are lost.
+    #[default]
+    Zero,
+    /// Log a `crit` and decline to propose the block.
+    SkipProposal,
+    /// Refuse to start the validator client.
+    ErrorStartup,
+}
+
+/// The action to take when the gas limit of a validator registration is outside of the configured
+/// bounds.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumVariantNames,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum OnGasLimitOutOfRange {
+    /// Log a warning and register the nearest gas limit within the bounds.
+    #[default]
+    Clamp,
+    /// Log an error and do not register the validator.
+    Reject,
+}
+
+/// The range of gas limits which are registered for validators, guarding against mistyped values.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GasLimitBounds {
+    pub min: u64,
+    pub max: u64,
+    pub on_out_of_range: OnGasLimitOutOfRange,
+}
+
+impl GasLimitBounds {
+    pub fn contains(&self, gas_limit: u64) -> bool {
+        (self.min..=self.max).contains(&gas_limit)
+    }
+
+    /// Returns the gas limit to register in place of `gas_limit`, or `None` if it is rejected.
+    pub fn apply(&self, gas_limit: u64) -> Option<u64> {
+        match self.on_out_of_range {
+            OnGasLimitOutOfRange::Clamp => Some(gas_limit.clamp(self.min, self.max)),
+            OnGasLimitOutOfRange::Reject => self.contains(gas_limit).then_some(gas_limit),
+        }
+    }
+}
+
 /// Builds an `PreparationService`.
 pub struct PreparationServiceBuilder<T: SlotClock + 'static, E: EthSpec> {
     validator_store: Option<Arc<ValidatorStore<T, E>>>,
     slot_clock: Option<T>,
     beacon_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
+    registration_proposer_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
     context: Option<RuntimeContext<E>>,
     builder_registration_timestamp_override: Option<u64>,
+    builder_registration_timestamp_override_file: Option<RegistrationTimestampFile>,
+    relay_tag_file: Option<RelayTagFile>,
     validator_registration_batch_size: Option<usize>,
+    validator_registration_max_body_bytes: Option<usize>,
+    validator_registration_refresh_interval: Option<Duration>,
+    gas_limit_bounds: Option<GasLimitBounds>,
 }
 
 impl<T: SlotClock + 'static, E: EthSpec> PreparationServiceBuilder<T, E> {
@@ -39,9 +131,15 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationServiceBuilder<T, E> {
             validator_store: None,
             slot_clock: None,
             beacon_nodes: None,
+            registration_proposer_nodes: None,
             context: None,
             builder_registration_timestamp_override: None,
+            builder_registration_timestamp_override_file: None,
+            relay_tag_file: None,
             validator_registration_batch_size: None,
+            validator_registration_max_body_bytes: None,
+            validator_registration_refresh_interval: None,
+            gas_limit_bounds: None,
         }
     }
 
@@ -60,6 +158,15 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationServiceBuilder<T, E> {
         self
     }
 
+    /// Sends validator registrations to `proposer_nodes`, falling back to the beacon nodes.
+    pub fn registration_proposer_nodes(
+        mut self,
+        proposer_nodes: Arc<BeaconNodeFallback<T, E>>,
+    ) -> Self {
+        self.registration_proposer_nodes = Some(proposer_nodes);
+        self
+    }
+
     pub fn runtime_context(mut self, context: RuntimeContext<E>) -> Self {
         self.context = Some(context);
         self
@@ -73,6 +180,20 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationServiceBuilder<T, E> {
         self
     }
 
+    pub fn builder_registration_timestamp_override_file(
+        mut self,
+        builder_registration_timestamp_override_file: Option<RegistrationTimestampFile>,
+    ) -> Self {
+        self.builder_registration_timestamp_override_file =
+            builder_registration_timestamp_override_file;
+        self
+    }
+
+    pub fn relay_tag_file(mut self, relay_tag_file: Option<RelayTagFile>) -> Self {
+        self.relay_tag_file = relay_tag_file;
+        self
+    }
+
     pub fn validator_registration_batch_size(
         mut self,
         validator_registration_batch_size: usize,
@@ -81,6 +202,27 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationServiceBuilder<T, E> {
         self
     }
 
+    pub fn validator_registration_max_body_bytes(
+        mut self,
+        validator_registration_max_body_bytes: Option<usize>,
+    ) -> Self {
+        self.validator_registration_max_body_bytes = validator_registration_max_body_bytes;
+        self
+    }
+
+    pub fn validator_registration_refresh_interval(
+        mut self,
+        validator_registration_refresh_interval: Option<Duration>,
+    ) -> Self {
+        self.validator_registration_refresh_interval = validator_registration_refresh_interval;
+        self
+    }
+
+    pub fn gas_limit_bounds(mut self, gas_limit_bounds: GasLimitBounds) -> Self {
+        self.gas_limit_bounds = Some(gas_limit_bounds);
+        self
+    }
+
     pub fn build(self) -> Result<PreparationService<T, E>, String> {
         Ok(PreparationService {
             inner: Arc::new(Inner {
@@ -93,15 +235,28 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationServiceBuilder<T, E> {
                 beacon_nodes: self
                     .beacon_nodes
                     .ok_or("Cannot build PreparationService without beacon_nodes")?,
+                registration_proposer_nodes: self.registration_proposer_nodes,
                 context: self
                     .context
                     .ok_or("Cannot build PreparationService without runtime_context")?,
                 builder_registration_timestamp_override: self
                     .builder_registration_timestamp_override,
-                validator_registration_batch_size: self.validator_registration_batch_size.ok_or(
-                    "Cannot build PreparationService without validator_registration_batch_size",
-                )?,
+                builder_registration_timestamp_override_file: self
+                    .builder_registration_timestamp_override_file,
+                relay_tag_file: self.relay_tag_file,
+                validator_registration_batch_size: AtomicUsize::new(
+                    self.validator_registration_batch_size.ok_or(
+                        "Cannot build PreparationService without validator_registration_batch_size",
+                    )?,
+                ),
+                validator_registration_max_body_bytes: self.validator_registration_max_body_bytes,
+                validator_registration_refresh_interval: self
+                    .validator_registration_refresh_interval,
+                gas_limit_bounds: self
+                    .gas_limit_bounds
+                    .ok_or("Cannot build PreparationService without gas_limit_bounds")?,
                 validator_registration_cache: RwLock::new(HashMap::new()),
+                validator_registration_lock: Mutex::new(()),
             }),
         })
     }
@@ -112,12 +267,26 @@ pub struct Inner<T, E: EthSpec> {
     validator_store: Arc<ValidatorStore<T, E>>,
     slot_clock: T,
     beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
+    // Validator registrations are sent to these nodes first, if set.
+    registration_proposer_nodes: Option<Arc<BeaconNodeFallback<T, E>>>,
     context: RuntimeContext<E>,
     builder_registration_timestamp_override: Option<u64>,
+    // Per-validator overrides, which take precedence over `builder_registration_timestamp_override`.
+    builder_registration_timestamp_override_file: Option<RegistrationTimestampFile>,
+    // Relay tags published alongside the registrations of the tagged validators.
+    relay_tag_file: Option<RelayTagFile>,
     // Used to track unpublished validator registration changes.
     validator_registration_cache:
         RwLock<HashMap<ValidatorRegistrationKey, SignedValidatorRegistrationData>>,
-    validator_registration_batch_size: usize,
+    // Reduced at runtime if the beacon node rejects a batch as too large.
+    validator_registration_batch_size: AtomicUsize,
+    validator_registration_max_body_bytes: Option<usize>,
+    validator_registration_refresh_interval: Option<Duration>,
+    // Gas limits outside these bounds are clamped or rejected when registering.
+    gas_limit_bounds: GasLimitBounds,
+    // Held while publishing registrations, so that registrations requested via the HTTP API do not
+    // interleave with the scheduled registrations.
+    validator_registration_lock: Mutex<()>,
 }
 
 #[derive(Hash, Eq, PartialEq, Debug, Clone)]
@@ -302,18 +471,52 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationService<T, E> {
 
             // We don't log for missing fee recipients here because this will be logged more
             // frequently in `collect_preparation_data`.
-            proposal_data.fee_recipient.and_then(|fee_recipient| {
-                proposal_data
-                    .builder_proposals
-                    .then_some(ValidatorRegistrationKey {
-                        fee_recipient,
-                        gas_limit: proposal_data.gas_limit,
-                        pubkey,
-                    })
+            let fee_recipient = proposal_data.fee_recipient?;
+            if !proposal_data.builder_proposals {
+                return None;
+            }
+            let gas_limit = self.bounded_gas_limit(pubkey, proposal_data.gas_limit)?;
+
+            Some(ValidatorRegistrationKey {
+                fee_recipient,
+                gas_limit,
+                pubkey,
             })
         })
     }
 
+    /// Returns the gas limit to register for `pubkey` in place of `gas_limit`, logging if it is
+    /// out of bounds, or `None` if it is rejected.
+    fn bounded_gas_limit(&self, pubkey: PublicKeyBytes, gas_limit: u64) -> Option<u64> {
+        let bounds = &self.gas_limit_bounds;
+        if bounds.contains(gas_limit) {
+            return Some(gas_limit);
+        }
+
+        let log = self.context.log();
+        let bounded = bounds.apply(gas_limit);
+        match bounded {
+            Some(bounded) => warn!(
+                log,
+                "Clamping registration gas limit";
+                "msg" => "the gas limit is outside of --gas-limit-min and --gas-limit-max",
+                "gas_limit" => gas_limit,
+                "registered_gas_limit" => bounded,
+                "pubkey" => ?pubkey,
+            ),
+            None => error!(
+                log,
+                "Not registering validator";
+                "msg" => "the gas limit is outside of --gas-limit-min and --gas-limit-max",
+                "gas_limit" => gas_limit,
+                "gas_limit_min" => bounds.min,
+                "gas_limit_max" => bounds.max,
+                "pubkey" => ?pubkey,
+            ),
+        }
+        bounded
+    }
+
     fn collect_proposal_data<G, U>(&self, map_fn: G) -> Vec<U>
     where
         G: Fn(PublicKeyBytes, ProposalData) -> Option<U>,
@@ -369,8 +572,30 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationService<T, E> {
 
     /// Register validators with builders, used in the blinded block proposal flow.
     async fn register_validators(&self) -> Result<(), String> {
+        let _registration_guard = self.validator_registration_lock.lock().await;
         let registration_keys = self.collect_validator_registration_keys();
 
+        // A restarted beacon node has lost the registrations it held in memory.
+        let restarted_nodes = self
+            .registration_proposer_nodes
+            .iter()
+            .flat_map(|proposer_nodes| proposer_nodes.take_restarted_nodes())
+            .chain(self.beacon_nodes.take_restarted_nodes())
+            .collect::<Vec<_>>();
+        for beacon_node in restarted_nodes {
+            if registration_keys.is_empty() {
+                break;
+            }
+            info!(
+                self.context.log(),
+                "Re-registering validators after beacon node restart";
+                "endpoint" => %beacon_node,
+                "count" => registration_keys.len(),
+            );
+            self.publish_validator_registration_data(registration_keys.clone(), Some(&beacon_node))
+                .await?;
+        }
+
         let mut changed_keys = vec![];
 
         // Need to scope this so the read lock is not held across an await point (I don't know why
@@ -385,13 +610,13 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationService<T, E> {
             drop(guard);
         }
 
-        // Check if any have changed or it's been `EPOCHS_PER_VALIDATOR_REGISTRATION_SUBMISSION`.
+        // Check if any have changed or it's been `validator_registration_refresh_slots`.
         if let Some(slot) = self.slot_clock.now() {
-            if slot % (E::slots_per_epoch() * EPOCHS_PER_VALIDATOR_REGISTRATION_SUBMISSION) == 0 {
-                self.publish_validator_registration_data(registration_keys)
+            if slot % self.validator_registration_refresh_slots() == 0 {
+                self.publish_validator_registration_data(registration_keys, None)
                     .await?;
             } else if !changed_keys.is_empty() {
-                self.publish_validator_registration_data(changed_keys)
+                self.publish_validator_registration_data(changed_keys, None)
                     .await?;
             }
         }
@@ -399,14 +624,91 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationService<T, E> {
         Ok(())
     }
 
+    /// Immediately publish the registrations of the validators in `pubkeys`, or of all eligible
+    /// validators if `None`, regardless of when they were last published.
+    ///
+    /// Returns the outcome for each validator. Requested validators which are unknown, inactive,
+    /// missing a fee recipient or not using builder proposals are reported as not registered.
+    pub async fn register_validators_now(
+        &self,
+        pubkeys: Option<Vec<PublicKeyBytes>>,
+    ) -> Result<Vec<RegisterValidatorResponse>, String> {
+        let _registration_guard = self.validator_registration_lock.lock().await;
+        let registration_keys = self.collect_validator_registration_keys();
+
+        let Some(pubkeys) = pubkeys else {
+            return self
+                .publish_validator_registration_data(registration_keys, None)
+                .await;
+        };
+
+        let mut requested_keys = vec![];
+        let mut not_registered = vec![];
+        for pubkey in pubkeys {
+            if requested_keys
+                .iter()
+                .any(|key: &ValidatorRegistrationKey| key.pubkey == pubkey)
+            {
+                continue;
+            }
+            match registration_keys.iter().find(|key| key.pubkey == pubkey) {
+                Some(key) => requested_keys.push(key.clone()),
+                None => not_registered.push(RegisterValidatorResponse {
+                    pubkey,
+                    status: RegisterValidatorStatus::NotRegistered,
+                    message: Some(
+                        "validator is unknown, has no index or fee recipient, or does not use \
+                        builder proposals"
+                            .to_string(),
+                    ),
+                }),
+            }
+        }
+
+        let mut results = self
+            .publish_validator_registration_data(requested_keys, None)
+            .await?;
+        results.extend(not_registered);
+        Ok(results)
+    }
+
+    /// Returns the number of slots between re-publishing all validator registrations.
+    ///
+    /// Defaults to `EPOCHS_PER_VALIDATOR_REGISTRATION_SUBMISSION` epochs, and is never less than
+    /// one slot.
+    fn validator_registration_refresh_slots(&self) -> u64 {
+        self.validator_registration_refresh_interval
+            .map_or(
+                E::slots_per_epoch() * EPOCHS_PER_VALIDATOR_REGISTRATION_SUBMISSION,
+                |interval| {
+                    let slot_duration = self.slot_clock.slot_duration().as_secs();
+                    interval.as_secs().checked_div(slot_duration).unwrap_or(1)
+                },
+            )
+            .max(1)
+    }
+
+    /// Returns the timestamp override for `pubkey`, preferring the per-validator value from the
+    /// timestamp override file over the global override.
+    fn registration_timestamp_override(&self, pubkey: &PublicKeyBytes) -> Option<u64> {
+        self.builder_registration_timestamp_override_file
+            .as_ref()
+            .and_then(|file| file.get(pubkey))
+            .or(self.builder_registration_timestamp_override)
+    }
+
+    /// Sign and publish the registrations of `registration_keys` to `beacon_node`, or to the first
+    /// beacon node to accept them if `None`, returning the outcome for each validator.
     async fn publish_validator_registration_data(
         &self,
         registration_keys: Vec<ValidatorRegistrationKey>,
-    ) -> Result<(), String> {
+        beacon_node: Option<&BeaconNodeHttpClient>,
+    ) -> Result<Vec<RegisterValidatorResponse>, String> {
         let log = self.context.log();
 
         let registration_data_len = registration_keys.len();
         let mut signed = Vec::with_capacity(registration_data_len);
+        let mut results = Vec::with_capacity(registration_data_len);
 
         for key in registration_keys {
             let cached_registration_opt =
@@ -416,7 +718,7 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationService<T, E> {
                 signed_data
             } else {
                 let timestamp =
-                    if let Some(timestamp) = self.builder_registration_timestamp_override {
+                    if let Some(timestamp) = self.registration_timestamp_override(&key.pubkey) {
                         timestamp
                     } else {
                         SystemTime::now()
@@ -444,6 +746,11 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationService<T, E> {
                     Ok(data) => data,
                     Err(e) => {
                         error!(log, "Unable to sign validator registration data"; "error" => ?e, "pubkey" => ?pubkey);
+                        results.push(RegisterValidatorResponse {
+                            pubkey,
+                            status: RegisterValidatorStatus::Error,
+                            message: Some(format!("unable to sign registration: {:?}", e)),
+                        });
                         continue;
                     }
                 };
@@ -457,34 +764,208 @@ impl<T: SlotClock + 'static, E: EthSpec> PreparationService<T, E> {
             signed.push(signed_data);
         }
 
-        if !signed.is_empty() {
-            for batch in signed.chunks(self.validator_registration_batch_size) {
-                match self
-                    .beacon_nodes
-                    .first_success(
-                        RequireSynced::No,
-                        OfflineOnFailure::No,
-                        |beacon_node| async move {
-                            beacon_node.post_validator_register_validator(batch).await
-                        },
-                    )
-                    .await
-                {
-                    Ok(()) => info!(
+        let mut remaining = signed.as_slice();
+        while !remaining.is_empty() {
+            let batch_len = registration_batch_len(
+                remaining,
+                self.validator_registration_batch_size
+                    .load(Ordering::Relaxed),
+                self.validator_registration_max_body_bytes,
+            );
+            let (batch, rest) = remaining.split_at(batch_len);
+
+            match self.post_validator_registrations(batch, beacon_node).await {
+                Ok(()) => {
+                    info!(
                         log,
                         "Published validator registrations to the builder network";
                         "count" => batch.len(),
-                    ),
-                    Err(e) => warn!(
+                    );
+                    self.publish_relay_tags(batch, beacon_node).await;
+                    results.extend(batch.iter().map(|registration| RegisterValidatorResponse {
+                        pubkey: registration.message.pubkey,
+                        status: RegisterValidatorStatus::Registered,
+                        message: None,
+                    }));
+                }
+                // Retry the same registrations in a smaller batch.
+                Err(e) if batch.len() > 1 && is_payload_too_large(&e) => {
+                    let reduced_batch_size = batch.len() / 2;
+                    self.validator_registration_batch_size
+                        .store(reduced_batch_size, Ordering::Relaxed);
+                    warn!(
+                        log,
+                        "Reducing validator registration batch size";
+                        "msg" => "the beacon node rejected the request body as too large",
+                        "previous_batch_size" => batch.len(),
+                        "new_batch_size" => reduced_batch_size,
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    warn!(
                         log,
                         "Unable to publish validator registrations to the builder network";
                         "error" => %e,
-                    ),
+                    );
+                    let message = e.to_string();
+                    results.extend(batch.iter().map(|registration| RegisterValidatorResponse {
+                        pubkey: registration.message.pubkey,
+                        status: RegisterValidatorStatus::Error,
+                        message: Some(message.clone()),
+                    }));
                 }
             }
+            remaining = rest;
+        }
+        Ok(results)
+    }
+
+    /// Posts `batch` to `beacon_node`, or to the first beacon node to accept it if `None`.
+    async fn post_validator_registrations(
+        &self,
+        batch: &[SignedValidatorRegistrationData],
+        beacon_node: Option<&BeaconNodeHttpClient>,
+    ) -> Result<(), Errors<eth2::Error>> {
+        match beacon_node {
+            Some(beacon_node) => beacon_node
+                .post_validator_register_validator(batch)
+                .await
+                .map_err(|e| Errors(vec![(beacon_node.to_string(), Error::RequestFailed(e))])),
+            None => {
+                self.first_registration_success(|beacon_node| async move {
+                    beacon_node.post_validator_register_validator(batch).await
+                })
+                .await
+            }
+        }
+    }
+
+    /// Runs `func` on the first proposer node to succeed if registrations are sent to proposer
+    /// nodes, otherwise or if all of the proposer nodes fail, on the first beacon node to succeed.
+    async fn first_registration_success<'a, F, O, R>(
+        &'a self,
+        func: F,
+    ) -> Result<O, Errors<eth2::Error>>
+    where
+        F: Fn(&'a BeaconNodeHttpClient) -> R + Clone,
+        R: Future<Output = Result<O, eth2::Error>>,
+    {
+        if let Some(proposer_nodes) = &self.registration_proposer_nodes {
+            match proposer_nodes
+                .first_success(RequireSynced::No, OfflineOnFailure::No, func.clone())
+                .await
+            {
+                Ok(result) => return Ok(result),
+                Err(e) => debug!(
+                    self.context.log(),
+                    "Falling back to beacon nodes for registration";
+                    "msg" => "no proposer node accepted the request",
+                    "error" => %e,
+                ),
+            }
+        }
+
+        self.beacon_nodes
+            .first_success(RequireSynced::No, OfflineOnFailure::No, func)
+            .await
+    }
+
+    /// Publishes the relay tags of the validators in `registrations` to `beacon_node`, or to the
+    /// first beacon node to accept them if `None`.
+    ///
+    /// Relay tags are a Lighthouse-specific hint which has no effect on the registrations
+    /// themselves, so failures are logged and otherwise ignored.
+    async fn publish_relay_tags(
+        &self,
+        registrations: &[SignedValidatorRegistrationData],
+        beacon_node: Option<&BeaconNodeHttpClient>,
+    ) {
+        let Some(relay_tag_file) = &self.relay_tag_file else {
+            return;
+        };
+        let relay_tags = registrations
+            .iter()
+            .filter_map(|registration| {
+                let pubkey = registration.message.pubkey;
+                relay_tag_file.get(&pubkey).map(|tag| ValidatorRelayTag {
+                    pubkey,
+                    relay_tag: tag.to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+        if relay_tags.is_empty() {
+            return;
+        }
+
+        let relay_tags = &relay_tags;
+        let result = match beacon_node {
+            Some(beacon_node) => beacon_node
+                .post_lighthouse_validator_relay_tags(relay_tags)
+                .await
+                .map_err(|e| Errors(vec![(beacon_node.to_string(), Error::RequestFailed(e))])),
+            None => {
+                self.first_registration_success(|beacon_node| async move {
+                    beacon_node
+                        .post_lighthouse_validator_relay_tags(relay_tags)
+                        .await
+                })
+                .await
+            }
+        };
+        match result {
+            Ok(()) => debug!(
+                self.context.log(),
+                "Published relay tags";
+                "count" => relay_tags.len(),
+            ),
+            Err(e) => warn!(
+                self.context.log(),
+                "Unable to publish relay tags";
+                "msg" => "the beacon node may not support relay tags, registrations are unaffected",
+                "error" => %e,
+            ),
+        }
+    }
+}
+
+/// Returns the number of leading `registrations` to include in the next batch sent to the beacon
+/// node, such that the batch holds at most `batch_size` registrations and, if provided, its JSON
+/// body is at most `max_body_bytes` long.
+///
+/// At least one registration is always included, even if it alone exceeds `max_body_bytes`.
+fn registration_batch_len(
+    registrations: &[SignedValidatorRegistrationData],
+    batch_size: usize,
+    max_body_bytes: Option<usize>,
+) -> usize {
+    let max_len = registrations.len().min(batch_size).max(1);
+    let max_body_bytes = match max_body_bytes {
+        Some(max_body_bytes) => max_body_bytes,
+        None => return max_len,
+    };
+
+    // The opening and closing brackets of the JSON array.
+    let mut body_bytes = 2;
+    for (i, registration) in registrations.iter().take(max_len).enumerate() {
+        let registration_bytes = serde_json::to_vec(registration).map_or(0, |json| json.len());
+        // Every registration after the first is preceded by a comma.
+        body_bytes += registration_bytes + usize::from(i > 0);
+        if body_bytes > max_body_bytes {
+            return i.max(1);
         }
-        Ok(())
     }
+    max_len
+}
+
+/// Returns `true` if any beacon node responded with `413 Payload Too Large`.
+fn is_payload_too_large(errors: &Errors<eth2::Error>) -> bool {
+    errors.0.iter().any(|(_, error)| {
+        error
+            .request_failure()
+            .and_then(eth2::Error::status)
+            .map_or(false, |status| status == StatusCode::PAYLOAD_TOO_LARGE)
+    })
 }
 
 /// A helper struct, used for passing data from the validator store to services.
@@ -494,3 +975,85 @@ pub struct ProposalData {
     pub(crate) gas_limit: u64,
     pub(crate) builder_proposals: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Signature;
+
+    fn registrations(count: usize) -> Vec<SignedValidatorRegistrationData> {
+        (0..count)
+            .map(|_| SignedValidatorRegistrationData {
+                message: ValidatorRegistrationData {
+                    fee_recipient: Address::zero(),
+                    gas_limit: 30_000_000,
+                    timestamp: 1_700_000_000,
+                    pubkey: PublicKeyBytes::empty(),
+                },
+                signature: Signature::empty(),
+            })
+            .collect()
+    }
+
+    fn body_bytes(registrations: &[SignedValidatorRegistrationData]) -> usize {
+        serde_json::to_vec(registrations).unwrap().len()
+    }
+
+    #[test]
+    fn batch_len_without_max_body_bytes() {
+        let registrations = registrations(10);
+        assert_eq!(registration_batch_len(&registrations, 500, None), 10);
+        assert_eq!(registration_batch_len(&registrations, 4, None), 4);
+        assert_eq!(registration_batch_len(&registrations[..1], 4, None), 1);
+    }
+
+    #[test]
+    fn batch_len_with_max_body_bytes() {
+        let registrations = registrations(10);
+
+        for len in 1..=10 {
+            let max_body_bytes = body_bytes(&registrations[..len]);
+            // The body length is computed exactly.
+            assert_eq!(
+                registration_batch_len(&registrations, 500, Some(max_body_bytes)),
+                len
+            );
+            assert_eq!(
+                registration_batch_len(&registrations, 500, Some(max_body_bytes - 1)),
+                (len - 1).max(1)
+            );
+            // The batch size still applies.
+            assert_eq!(
+                registration_batch_len(&registrations, 2, Some(max_body_bytes)),
+                len.min(2)
+            );
+        }
+    }
+
+    #[test]
+    fn gas_limit_bounds() {
+        let bounds = |on_out_of_range| GasLimitBounds {
+            min: 10_000_000,
+            max: 60_000_000,
+            on_out_of_range,
+        };
+        let clamp = bounds(OnGasLimitOutOfRange::Clamp);
+        let reject = bounds(OnGasLimitOutOfRange::Reject);
+
+        for gas_limit in [10_000_000, 30_000_000, 60_000_000] {
+            assert_eq!(clamp.apply(gas_limit), Some(gas_limit));
+            assert_eq!(reject.apply(gas_limit), Some(gas_limit));
+        }
+
+        assert_eq!(clamp.apply(3_000_000), Some(10_000_000));
+        assert_eq!(clamp.apply(300_000_000), Some(60_000_000));
+        assert_eq!(reject.apply(3_000_000), None);
+        assert_eq!(reject.apply(300_000_000), None);
+    }
+
+    #[test]
+    fn batch_len_oversized_registration() {
+        let registrations = registrations(10);
+        assert_eq!(registration_batch_len(&registrations, 500, Some(1)), 1);
+    }
+}
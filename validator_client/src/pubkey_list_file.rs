@@ -0,0 +1,126 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bls::PublicKeyBytes;
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidFile(std::io::Error),
+    InvalidLine(String),
+    InvalidPublicKey(String),
+}
+
+/// Struct to load a set of validators from file, such as the validators which do not participate
+/// in sync committees. The file is expected to contain one public key per line
+///
+/// public_key1
+/// public_key2
+/// ...
+///
+/// Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PubkeyListFile {
+    path: PathBuf,
+    pubkeys: HashSet<PublicKeyBytes>,
+}
+
+impl PubkeyListFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            pubkeys: HashSet::new(),
+        }
+    }
+
+    /// Returns `true` if the file lists the validator with the given public key.
+    pub fn contains(&self, public_key: &PublicKeyBytes) -> bool {
+        self.pubkeys.contains(public_key)
+    }
+
+    /// Returns an iterator over the public keys listed in the file.
+    pub fn iter(&self) -> impl Iterator<Item = &PublicKeyBytes> {
+        self.pubkeys.iter()
+    }
+
+    /// Returns the number of validators listed in the file.
+    pub fn len(&self) -> usize {
+        self.pubkeys.len()
+    }
+
+    /// Returns `true` if the file lists no validators.
+    pub fn is_empty(&self) -> bool {
+        self.pubkeys.is_empty()
+    }
+
+    /// Reads from a public key file with the specified format and populates the set of public
+    /// keys.
+    ///
+    /// Returns an error if the file does not exist, or if the format is invalid.
+    pub fn read_pubkey_list_file(&mut self) -> Result<(), Error> {
+        let file = File::open(self.path.as_path()).map_err(Error::InvalidFile)?;
+        let reader = BufReader::new(file);
+
+        for line in reader.lines() {
+            let line = line.map_err(|e| Error::InvalidLine(e.to_string()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let pk = PublicKeyBytes::from_str(line).map_err(Error::InvalidPublicKey)?;
+            self.pubkeys.insert(pk);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::Keypair;
+    use std::io::LineWriter;
+    use tempfile::TempDir;
+
+    const PK1: &str = "0x800012708dc03f611751aad7a43a082142832b5c1aceed07ff9b543cf836381861352aa923c70eeb02018b638aa306aa";
+    const PK2: &str = "0x80001866ce324de7d80ec73be15e2d064dcf121adf1b34a0d679f2b9ecbab40ce021e03bb877e1a2fe72eaaf475e6e21";
+
+    // Create a public key file with the given contents and return a path to the file.
+    fn create_pubkey_list_file(contents: &str) -> PathBuf {
+        let temp = TempDir::new().unwrap();
+        let file_name = temp.into_path().join("pubkeys.txt");
+
+        let file = File::create(&file_name).unwrap();
+        let mut pubkey_list_file = LineWriter::new(file);
+        pubkey_list_file.write_all(contents.as_bytes()).unwrap();
+        pubkey_list_file.flush().unwrap();
+        file_name
+    }
+
+    #[test]
+    fn test_load_pubkey_list_file() {
+        let path = create_pubkey_list_file(&format!("# low bandwidth\n{}\n\n  {}  \n", PK1, PK2));
+        let mut lf = PubkeyListFile::new(path);
+        lf.read_pubkey_list_file().unwrap();
+
+        assert_eq!(lf.len(), 2);
+        assert!(lf.contains(&PublicKeyBytes::from_str(PK1).unwrap()));
+        assert!(lf.contains(&PublicKeyBytes::from_str(PK2).unwrap()));
+
+        // Random pk should not be listed.
+        let random_pk = Keypair::random().pk.compress();
+        assert!(!lf.contains(&random_pk));
+    }
+
+    #[test]
+    fn test_invalid_public_key() {
+        let path = create_pubkey_list_file(&format!("{}: opt-out\n", PK1));
+        let mut lf = PubkeyListFile::new(path);
+        assert!(matches!(
+            lf.read_pubkey_list_file(),
+            Err(Error::InvalidPublicKey(_))
+        ));
+    }
+}
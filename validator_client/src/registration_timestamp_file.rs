@@ -0,0 +1,164 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bls::PublicKeyBytes;
+
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    InvalidFile(std::io::Error),
+    InvalidLine(String),
+    InvalidPublicKey(String),
+    InvalidTimestamp(String),
+}
+
+/// Struct to load per-validator builder registration timestamp overrides from file.
+/// The file is expected to have the following structure
+///
+/// public_key1: timestamp1
+/// public_key2: timestamp2
+/// ...
+///
+/// Validators which are not present in the file fall back to the global
+/// `--builder-registration-timestamp-override` value, if any.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationTimestampFile {
+    timestamps_path: PathBuf,
+    timestamps: HashMap<PublicKeyBytes, u64>,
+}
+
+impl RegistrationTimestampFile {
+    pub fn new(timestamps_path: PathBuf) -> Self {
+        Self {
+            timestamps_path,
+            timestamps: HashMap::new(),
+        }
+    }
+
+    /// Returns the timestamp override for the given public key, if present.
+    pub fn get(&self, public_key: &PublicKeyBytes) -> Option<u64> {
+        self.timestamps.get(public_key).copied()
+    }
+
+    /// Returns the number of validators with a timestamp override.
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Returns `true` if there are no timestamp overrides.
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Reads from a timestamp file with the specified format and populates the hashmap.
+    ///
+    /// Empty lines are ignored. Returns an error if the file does not exist, or if the format is
+    /// invalid.
+    pub fn read_timestamp_file(&mut self) -> Result<(), Error> {
+        let file = File::open(self.timestamps_path.as_path()).map_err(Error::InvalidFile)?;
+        let reader = BufReader::new(file);
+
+        for line in reader.lines() {
+            let line = line.map_err(|e| Error::InvalidLine(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (pk, timestamp) = read_line(&line)?;
+            self.timestamps.insert(pk, timestamp);
+        }
+        Ok(())
+    }
+}
+
+/// Parses a line from the timestamp file.
+///
+/// Returns an error if the line is in the wrong format or does not contain a valid public key or
+/// `u64` timestamp.
+fn read_line(line: &str) -> Result<(PublicKeyBytes, u64), Error> {
+    if let Some(i) = line.find(':') {
+        let (key, value) = line.split_at(i);
+        // Note: `value.len() >=1` so `value[1..]` is safe
+        let value = value[1..].trim();
+        let timestamp = value
+            .parse::<u64>()
+            .map_err(|e| Error::InvalidTimestamp(format!("{}: {:?}", value, e)))?;
+        let pk = PublicKeyBytes::from_str(key.trim()).map_err(Error::InvalidPublicKey)?;
+        Ok((pk, timestamp))
+    } else {
+        Err(Error::InvalidLine(format!("Missing delimiter: {}", line)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::Keypair;
+    use std::io::LineWriter;
+    use tempfile::TempDir;
+
+    const PK1: &str = "0x800012708dc03f611751aad7a43a082142832b5c1aceed07ff9b543cf836381861352aa923c70eeb02018b638aa306aa";
+    const PK2: &str = "0x80001866ce324de7d80ec73be15e2d064dcf121adf1b34a0d679f2b9ecbab40ce021e03bb877e1a2fe72eaaf475e6e21";
+
+    // Create a timestamp file with the given contents and return a path to the file.
+    fn create_timestamp_file(contents: &str) -> PathBuf {
+        let temp = TempDir::new().unwrap();
+        let file_name = temp.into_path().join("timestamps.txt");
+
+        let file = File::create(&file_name).unwrap();
+        let mut timestamp_file = LineWriter::new(file);
+        timestamp_file.write_all(contents.as_bytes()).unwrap();
+        timestamp_file.flush().unwrap();
+        file_name
+    }
+
+    #[test]
+    fn test_load_timestamps() {
+        let path = create_timestamp_file(&format!("{}: 100\n\n{}:1700000000\n", PK1, PK2));
+        let mut tf = RegistrationTimestampFile::new(path);
+        tf.read_timestamp_file().unwrap();
+
+        let pk1 = PublicKeyBytes::from_str(PK1).unwrap();
+        let pk2 = PublicKeyBytes::from_str(PK2).unwrap();
+
+        assert_eq!(tf.len(), 2);
+        assert_eq!(tf.get(&pk1), Some(100));
+        assert_eq!(tf.get(&pk2), Some(1_700_000_000));
+
+        // Random pk should not have an override.
+        let random_pk = Keypair::random().pk.compress();
+        assert_eq!(tf.get(&random_pk), None);
+    }
+
+    #[test]
+    fn test_invalid_timestamp() {
+        for value in ["-1", "abc", "", "18446744073709551616"] {
+            let path = create_timestamp_file(&format!("{}: {}\n", PK1, value));
+            let mut tf = RegistrationTimestampFile::new(path);
+            assert!(matches!(
+                tf.read_timestamp_file(),
+                Err(Error::InvalidTimestamp(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let path = create_timestamp_file("default: 100\n");
+        let mut tf = RegistrationTimestampFile::new(path);
+        assert!(matches!(
+            tf.read_timestamp_file(),
+            Err(Error::InvalidPublicKey(_))
+        ));
+
+        let path = create_timestamp_file(&format!("{} 100\n", PK1));
+        let mut tf = RegistrationTimestampFile::new(path);
+        assert!(matches!(
+            tf.read_timestamp_file(),
+            Err(Error::InvalidLine(_))
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bls::PublicKeyBytes;
+
+/// The maximum length of a relay tag, in bytes.
+pub const MAX_RELAY_TAG_LEN: usize = 64;
+
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    InvalidFile(std::io::Error),
+    InvalidLine(String),
+    InvalidPublicKey(String),
+    InvalidRelayTag(String),
+}
+
+/// Struct to load per-validator relay tags from file.
+/// The file is expected to have the following structure
+///
+/// public_key1: tag1
+/// public_key2: tag2
+/// ...
+///
+/// Tags are opaque to the validator client. They are published to the beacon node alongside the
+/// validator registrations so that it, or a proxy in front of it, can route each validator to a
+/// set of relays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayTagFile {
+    tags_path: PathBuf,
+    tags: HashMap<PublicKeyBytes, String>,
+}
+
+impl RelayTagFile {
+    pub fn new(tags_path: PathBuf) -> Self {
+        Self {
+            tags_path,
+            tags: HashMap::new(),
+        }
+    }
+
+    /// Returns the relay tag for the given public key, if present.
+    pub fn get(&self, public_key: &PublicKeyBytes) -> Option<&str> {
+        self.tags.get(public_key).map(String::as_str)
+    }
+
+    /// Returns the number of validators with a relay tag.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Returns `true` if there are no relay tags.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Reads from a relay tag file with the specified format and populates the hashmap.
+    ///
+    /// Empty lines are ignored. Returns an error if the file does not exist, or if the format is
+    /// invalid.
+    pub fn read_relay_tag_file(&mut self) -> Result<(), Error> {
+        let file = File::open(self.tags_path.as_path()).map_err(Error::InvalidFile)?;
+        let reader = BufReader::new(file);
+
+        for line in reader.lines() {
+            let line = line.map_err(|e| Error::InvalidLine(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (pk, tag) = read_line(&line)?;
+            self.tags.insert(pk, tag);
+        }
+        Ok(())
+    }
+}
+
+/// Parses a line from the relay tag file.
+///
+/// Returns an error if the line is in the wrong format or does not contain a valid public key or
+/// relay tag. Tags must be non-empty, at most `MAX_RELAY_TAG_LEN` bytes and consist of ASCII
+/// letters, digits, `-` and `_`.
+fn read_line(line: &str) -> Result<(PublicKeyBytes, String), Error> {
+    if let Some(i) = line.find(':') {
+        let (key, value) = line.split_at(i);
+        // Note: `value.len() >=1` so `value[1..]` is safe
+        let tag = value[1..].trim();
+        if tag.is_empty()
+            || tag.len() > MAX_RELAY_TAG_LEN
+            || !tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(Error::InvalidRelayTag(tag.to_string()));
+        }
+        let pk = PublicKeyBytes::from_str(key.trim()).map_err(Error::InvalidPublicKey)?;
+        Ok((pk, tag.to_string()))
+    } else {
+        Err(Error::InvalidLine(format!("Missing delimiter: {}", line)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::Keypair;
+    use std::io::LineWriter;
+    use tempfile::TempDir;
+
+    const PK1: &str = "0x800012708dc03f611751aad7a43a082142832b5c1aceed07ff9b543cf836381861352aa923c70eeb02018b638aa306aa";
+    const PK2: &str = "0x80001866ce324de7d80ec73be15e2d064dcf121adf1b34a0d679f2b9ecbab40ce021e03bb877e1a2fe72eaaf475e6e21";
+
+    // Create a relay tag file with the given contents and return a path to the file.
+    fn create_relay_tag_file(contents: &str) -> PathBuf {
+        let temp = TempDir::new().unwrap();
+        let file_name = temp.into_path().join("relay_tags.txt");
+
+        let file = File::create(&file_name).unwrap();
+        let mut relay_tag_file = LineWriter::new(file);
+        relay_tag_file.write_all(contents.as_bytes()).unwrap();
+        relay_tag_file.flush().unwrap();
+        file_name
+    }
+
+    #[test]
+    fn test_load_relay_tags() {
+        let path = create_relay_tag_file(&format!("{}: regulated\n\n{}:eu_relays-2\n", PK1, PK2));
+        let mut rf = RelayTagFile::new(path);
+        rf.read_relay_tag_file().unwrap();
+
+        let pk1 = PublicKeyBytes::from_str(PK1).unwrap();
+        let pk2 = PublicKeyBytes::from_str(PK2).unwrap();
+
+        assert_eq!(rf.len(), 2);
+        assert_eq!(rf.get(&pk1), Some("regulated"));
+        assert_eq!(rf.get(&pk2), Some("eu_relays-2"));
+
+        // Random pk should not have a tag.
+        let random_pk = Keypair::random().pk.compress();
+        assert_eq!(rf.get(&random_pk), None);
+    }
+
+    #[test]
+    fn test_invalid_relay_tag() {
+        let too_long = "a".repeat(MAX_RELAY_TAG_LEN + 1);
+        for value in ["", "two words", "slash/tag", too_long.as_str()] {
+            let path = create_relay_tag_file(&format!("{}: {}\n", PK1, value));
+            let mut rf = RelayTagFile::new(path);
+            assert!(matches!(
+                rf.read_relay_tag_file(),
+                Err(Error::InvalidRelayTag(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let path = create_relay_tag_file("default: regulated\n");
+        let mut rf = RelayTagFile::new(path);
+        assert!(matches!(
+            rf.read_relay_tag_file(),
+            Err(Error::InvalidPublicKey(_))
+        ));
+
+        let path = create_relay_tag_file(&format!("{} regulated\n", PK1));
+        let mut rf = RelayTagFile::new(path);
+        assert!(matches!(
+            rf.read_relay_tag_file(),
+            Err(Error::InvalidLine(_))
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+//! Provides a single-file alternative to the one-password-per-keystore convention of the
+//! `secrets_dir`.
+//!
+//! The manifest is a JSON or YAML map from either a keystore file name or a voting public key to
+//! the password of that keystore:
+//!
+//! ```yaml
+//! keystore-m_12381_3600_0_0_0-1670000000.json: "password1"
+//! "0x8000...": "password2"
+//! ```
+//!
+//! Since a YAML parser also accepts JSON, both formats are read with the same parser.
+
+use account_utils::ZeroizeString;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use types::{PublicKey, PublicKeyBytes};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The manifest could not be opened.
+    UnableToOpenManifest(io::Error),
+    /// The manifest could not be parsed as JSON or YAML.
+    UnableToParseManifest(serde_yaml::Error),
+    /// The manifest is readable or writable by users other than its owner.
+    InsecurePermissions { path: PathBuf, mode: u32 },
+    /// A key starting with `0x` could not be parsed as a public key.
+    InvalidPublicKey(String),
+}
+
+/// Passwords for local keystores, loaded from a single manifest file.
+#[derive(Clone, Default)]
+pub struct SecretsManifest {
+    by_pubkey: HashMap<PublicKeyBytes, ZeroizeString>,
+    by_file_name: HashMap<String, ZeroizeString>,
+}
+
+impl SecretsManifest {
+    /// Reads the manifest at `path`.
+    ///
+    /// Returns an error if the file can be accessed by anyone other than its owner, since it
+    /// contains the passwords of all the keystores it lists.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(Error::UnableToOpenManifest)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = file
+                .metadata()
+                .map_err(Error::UnableToOpenManifest)?
+                .permissions()
+                .mode();
+            if mode & 0o077 != 0 {
+                return Err(Error::InsecurePermissions {
+                    path: path.to_path_buf(),
+                    mode: mode & 0o777,
+                });
+            }
+        }
+
+        let entries: HashMap<String, ZeroizeString> =
+            serde_yaml::from_reader(file).map_err(Error::UnableToParseManifest)?;
+        Self::from_entries(entries)
+    }
+
+    fn from_entries(entries: HashMap<String, ZeroizeString>) -> Result<Self, Error> {
+        let mut manifest = Self::default();
+        for (key, password) in entries {
+            if key.starts_with("0x") {
+                let pubkey = PublicKeyBytes::from_str(&key).map_err(Error::InvalidPublicKey)?;
+                manifest.by_pubkey.insert(pubkey, password);
+            } else {
+                manifest.by_file_name.insert(key, password);
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Returns the password for the keystore at `keystore_path` with the given `voting_pubkey`.
+    ///
+    /// An entry for the public key takes precedence over an entry for the file name.
+    pub fn password(
+        &self,
+        voting_pubkey: &PublicKey,
+        keystore_path: &Path,
+    ) -> Option<&ZeroizeString> {
+        self.by_pubkey.get(&voting_pubkey.compress()).or_else(|| {
+            keystore_path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| self.by_file_name.get(file_name))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::Keypair;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_manifest(dir: &TempDir, contents: &str, mode: u32) -> PathBuf {
+        let path = dir.path().join("secrets.yml");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        path
+    }
+
+    #[test]
+    fn load_yaml_and_json() {
+        let pk = Keypair::random().pk;
+        let dir = TempDir::new().unwrap();
+
+        for contents in [
+            format!("keystore-0.json: pw0\n\"{}\": pw1\n", pk.as_hex_string()),
+            format!(
+                "{{\"keystore-0.json\": \"pw0\", \"{}\": \"pw1\"}}",
+                pk.as_hex_string()
+            ),
+        ] {
+            let manifest = SecretsManifest::open(write_manifest(&dir, &contents, 0o600)).unwrap();
+
+            let other_pk = Keypair::random().pk;
+            let password = |pk, path: &str| {
+                manifest
+                    .password(pk, Path::new(path))
+                    .map(|pw| pw.as_str().to_string())
+            };
+
+            assert_eq!(
+                password(&other_pk, "/keys/keystore-0.json"),
+                Some("pw0".into())
+            );
+            // The public key takes precedence over the file name.
+            assert_eq!(password(&pk, "/keys/keystore-0.json"), Some("pw1".into()));
+            assert_eq!(password(&pk, "/keys/keystore-1.json"), Some("pw1".into()));
+            assert_eq!(password(&other_pk, "/keys/keystore-1.json"), None);
+        }
+    }
+
+    #[test]
+    fn invalid_public_key() {
+        let dir = TempDir::new().unwrap();
+        let path = write_manifest(&dir, "0x1234: pw\n", 0o600);
+        assert!(matches!(
+            SecretsManifest::open(path),
+            Err(Error::InvalidPublicKey(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn insecure_permissions() {
+        let dir = TempDir::new().unwrap();
+        for mode in [0o644, 0o640, 0o604, 0o660] {
+            let path = write_manifest(&dir, "keystore-0.json: pw0\n", mode);
+            assert!(matches!(
+                SecretsManifest::open(path),
+                Err(Error::InsecurePermissions { .. })
+            ));
+        }
+    }
+}
@@ -2,11 +2,13 @@
 //!
 //! - Via a local `Keypair`.
 //! - Via a remote signer (Web3Signer)
+//! - Via a hardware security module (PKCS#11)
 
 use crate::http_metrics::metrics;
 use eth2_keystore::Keystore;
 use lockfile::Lockfile;
 use parking_lot::Mutex;
+use pkcs11::{KeyHandle, Pkcs11Token};
 use reqwest::Client;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -15,8 +17,10 @@ use types::*;
 use url::Url;
 use web3signer::{ForkInfo, SigningRequest, SigningResponse};
 
+pub use pkcs11::{Error as Pkcs11Error, Pkcs11Config};
 pub use web3signer::Web3SignerObject;
 
+pub mod pkcs11;
 mod web3signer;
 
 #[derive(Debug, PartialEq)]
@@ -27,6 +31,7 @@ pub enum Error {
     },
     Web3SignerRequestFailed(String),
     Web3SignerJsonParsingFailed(String),
+    Pkcs11SigningFailed(String),
     ShuttingDown,
     TokioJoin(String),
     MergeForkNotSupported,
@@ -71,6 +76,22 @@ impl<'a, T: EthSpec, Payload: AbstractExecPayload<T>> SignableMessage<'a, T, Pay
             SignableMessage::VoluntaryExit(exit) => exit.signing_root(domain),
         }
     }
+
+    /// Returns the type of duty for which the message is signed, for use in metrics and logs.
+    pub fn duty(&self) -> &'static str {
+        match self {
+            SignableMessage::RandaoReveal(_) => "randao_reveal",
+            SignableMessage::BeaconBlock(_) => "block",
+            SignableMessage::AttestationData(_) => "attestation",
+            SignableMessage::SignedAggregateAndProof(_) => "aggregate",
+            SignableMessage::SelectionProof(_) => "selection_proof",
+            SignableMessage::SyncSelectionProof(_) => "sync_selection_proof",
+            SignableMessage::SyncCommitteeSignature { .. } => "sync_committee_message",
+            SignableMessage::SignedContributionAndProof(_) => "sync_committee_contribution",
+            SignableMessage::ValidatorRegistration(_) => "validator_registration",
+            SignableMessage::VoluntaryExit(_) => "voluntary_exit",
+        }
+    }
 }
 
 /// A method used by a validator to sign messages.
@@ -93,6 +114,12 @@ pub enum SigningMethod {
         http_client: Client,
         voting_public_key: PublicKey,
     },
+    /// A validator whose key is held by a hardware security module, accessed via PKCS#11.
+    Pkcs11 {
+        token: Arc<Pkcs11Token>,
+        key: KeyHandle,
+        voting_public_key: PublicKey,
+    },
 }
 
 /// The additional information used to construct a signature. Mostly used for protection from replay
@@ -241,6 +268,19 @@ impl SigningMethod {
 
                 Ok(response.signature)
             }
+            SigningMethod::Pkcs11 { token, key, .. } => {
+                let _timer = metrics::start_timer_vec(&metrics::SIGNING_TIMES, &[metrics::PKCS11]);
+
+                let token = token.clone();
+                let key = *key;
+                // The module blocks until the HSM has produced the signature.
+                executor
+                    .spawn_blocking_handle(move || token.sign(key, signing_root), "pkcs11_signer")
+                    .ok_or(Error::ShuttingDown)?
+                    .await
+                    .map_err(|e| Error::TokioJoin(e.to_string()))?
+                    .map_err(|e| Error::Pkcs11SigningFailed(format!("{:?}", e)))
+            }
         }
     }
 }
@@ -0,0 +1,148 @@
+//! Signing with BLS keys held by a hardware security module (HSM), accessed via PKCS#11.
+//!
+//! PKCS#11 does not define a mechanism for BLS12-381 signatures, so the HSM must provide a
+//! vendor-defined mechanism which signs a 32 byte message as per the Ethereum consensus BLS
+//! signature scheme and returns the 96 byte compressed signature. Only signing roots are sent to
+//! the HSM, so it never needs to understand the messages it signs.
+
+use account_utils::read_password;
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::{vendor_defined::VendorDefinedMechanism, Mechanism, MechanismType};
+use cryptoki::object::{Attribute, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use parking_lot::Mutex;
+use serde_derive::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+use types::{Hash256, PublicKey, Signature};
+
+/// The first vendor-defined mechanism type, `CKM_VENDOR_DEFINED` in the PKCS#11 specification.
+const CKM_VENDOR_DEFINED: u64 = 0x8000_0000;
+
+/// A handle to a private key object in the token.
+pub type KeyHandle = ObjectHandle;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The mechanism is not a vendor-defined mechanism.
+    InvalidMechanism(u64),
+    /// The PIN file could not be read.
+    UnableToReadPin(io::Error),
+    /// The configured slot does not exist or has no token.
+    UnknownSlot(u64),
+    /// No private key with the label exists in the token.
+    UnknownKey(String),
+    /// More than one private key with the label exists in the token.
+    DuplicateKey(String),
+    /// The token produced a signature which is not a valid BLS signature.
+    InvalidSignature(String),
+    /// The signature produced by the key is not valid for the voting public key of the validator.
+    PublicKeyMismatch(String),
+    Pkcs11(cryptoki::error::Error),
+}
+
+impl From<cryptoki::error::Error> for Error {
+    fn from(e: cryptoki::error::Error) -> Self {
+        Error::Pkcs11(e)
+    }
+}
+
+/// Configuration for the PKCS#11 token shared by all PKCS#11 validators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pkcs11Config {
+    /// Path to the PKCS#11 module (a shared library) provided by the HSM vendor.
+    pub module_path: PathBuf,
+    /// The ID of the slot containing the token.
+    pub slot: u64,
+    /// Path to a file containing the user PIN of the token.
+    pub pin_path: PathBuf,
+    /// The vendor-defined mechanism used to produce BLS signatures.
+    pub mechanism: u64,
+}
+
+/// A logged-in session with a PKCS#11 token.
+///
+/// The session is shared by all PKCS#11 validators, so signatures are produced one at a time.
+pub struct Pkcs11Token {
+    // Declared before `_context` so that the session is closed before the library is finalized.
+    session: Mutex<Session>,
+    mechanism: MechanismType,
+    _context: Pkcs11,
+}
+
+impl Pkcs11Token {
+    /// Load the PKCS#11 module in `config` and log in to the token in the configured slot.
+    ///
+    /// This performs blocking calls into the module.
+    pub fn open(config: &Pkcs11Config) -> Result<Self, Error> {
+        let offset = config
+            .mechanism
+            .checked_sub(CKM_VENDOR_DEFINED)
+            .ok_or(Error::InvalidMechanism(config.mechanism))?;
+        let mechanism = MechanismType::new_vendor_defined(offset)?;
+
+        let pin = read_password(&config.pin_path).map_err(Error::UnableToReadPin)?;
+        let pin = String::from_utf8(pin.as_bytes().to_vec()).map_err(|_| {
+            Error::UnableToReadPin(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "PIN is not valid UTF-8",
+            ))
+        })?;
+
+        let context = Pkcs11::new(&config.module_path)?;
+        context.initialize(CInitializeArgs::OsThreads)?;
+        let slot = context
+            .get_slots_with_token()?
+            .into_iter()
+            .find(|slot| slot.id() == config.slot)
+            .ok_or(Error::UnknownSlot(config.slot))?;
+        let session = context.open_ro_session(slot)?;
+        session.login(UserType::User, Some(&AuthPin::new(pin)))?;
+
+        Ok(Self {
+            session: Mutex::new(session),
+            mechanism,
+            _context: context,
+        })
+    }
+
+    /// Returns the handle of the private key with the label `key_label`.
+    pub fn find_key(&self, key_label: &str) -> Result<KeyHandle, Error> {
+        let keys = self.session.lock().find_objects(&[
+            Attribute::Class(ObjectClass::PRIVATE_KEY),
+            Attribute::Label(key_label.as_bytes().to_vec()),
+        ])?;
+        match keys.as_slice() {
+            [key] => Ok(*key),
+            [] => Err(Error::UnknownKey(key_label.to_string())),
+            _ => Err(Error::DuplicateKey(key_label.to_string())),
+        }
+    }
+
+    /// Checks that `key` produces valid signatures for `voting_public_key`.
+    ///
+    /// The zero hash is signed, which is not the signing root of any message since every signing
+    /// root commits to a domain.
+    pub fn check_key(&self, key: KeyHandle, voting_public_key: &PublicKey) -> Result<(), Error> {
+        let signature = self.sign(key, Hash256::zero())?;
+        if signature.verify(voting_public_key, Hash256::zero()) {
+            Ok(())
+        } else {
+            Err(Error::PublicKeyMismatch(format!("{:?}", voting_public_key)))
+        }
+    }
+
+    /// Sign `signing_root` with `key`.
+    ///
+    /// This performs a blocking call into the module.
+    pub fn sign(&self, key: KeyHandle, signing_root: Hash256) -> Result<Signature, Error> {
+        let mechanism =
+            Mechanism::VendorDefined(VendorDefinedMechanism::new::<()>(self.mechanism, None));
+        let bytes = self
+            .session
+            .lock()
+            .sign(&mechanism, key, signing_root.as_bytes())?;
+        Signature::deserialize(&bytes).map_err(|e| Error::InvalidSignature(format!("{:?}", e)))
+    }
+}
@@ -0,0 +1,239 @@
+//! A cap on the number of concurrent signing operations.
+//!
+//! Once the cap is reached, each signing waits for a permit and permits are granted in order of
+//! deadline, so that e.g. selection proofs for future slots do not delay the attestations of the
+//! current slot. A signing which is still waiting when its deadline arrives proceeds regardless of
+//! the cap, so that the throttle never causes a duty to miss its deadline.
+
+use crate::http_metrics::metrics;
+use parking_lot::Mutex;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+pub struct SigningThrottle {
+    max_concurrent: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The number of permits currently held, which may exceed the cap for overdue signings.
+    active: usize,
+    waiting: BinaryHeap<Reverse<Waiter>>,
+    next_id: u64,
+}
+
+/// A signing waiting for a permit, ordered by deadline and then by arrival.
+struct Waiter {
+    deadline: Duration,
+    id: u64,
+    grant: oneshot::Sender<()>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.deadline, self.id).cmp(&(other.deadline, other.id))
+    }
+}
+
+/// Permission to sign, released when dropped.
+pub struct SigningPermit<'a> {
+    throttle: &'a SigningThrottle,
+}
+
+impl<'a> Drop for SigningPermit<'a> {
+    fn drop(&mut self) {
+        self.throttle.release();
+    }
+}
+
+/// Releases a permit that was granted to a waiter which stopped waiting before receiving it.
+struct Waiting<'a> {
+    throttle: &'a SigningThrottle,
+    grant: oneshot::Receiver<()>,
+}
+
+impl<'a> Waiting<'a> {
+    /// Stops waiting, returning `true` if a permit was granted in the meantime.
+    fn stop(&mut self) -> bool {
+        self.grant.close();
+        self.grant.try_recv().is_ok()
+    }
+}
+
+impl<'a> Drop for Waiting<'a> {
+    fn drop(&mut self) {
+        if self.stop() {
+            self.throttle.release();
+        }
+    }
+}
+
+impl SigningThrottle {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            max_concurrent,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Waits for a permit to sign a message which is due at `deadline`.
+    ///
+    /// If no permit has been granted once `time_to_deadline` has elapsed, a permit is issued
+    /// regardless of the cap. A `deadline` of `None` is granted a permit after all others.
+    pub async fn acquire(
+        &self,
+        deadline: Option<Duration>,
+        time_to_deadline: Option<Duration>,
+    ) -> SigningPermit<'_> {
+        let grant = {
+            let mut state = self.state.lock();
+            if state.active < self.max_concurrent {
+                state.active += 1;
+                metrics::set_gauge(&metrics::SIGNING_CONCURRENCY, state.active as i64);
+                return SigningPermit { throttle: self };
+            }
+            let (sender, receiver) = oneshot::channel();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.waiting.push(Reverse(Waiter {
+                deadline: deadline.unwrap_or(Duration::MAX),
+                id,
+                grant: sender,
+            }));
+            receiver
+        };
+
+        let mut waiting = Waiting {
+            throttle: self,
+            grant,
+        };
+        let granted = match time_to_deadline {
+            Some(time_to_deadline) => {
+                matches!(
+                    tokio::time::timeout(time_to_deadline, &mut waiting.grant).await,
+                    Ok(Ok(()))
+                ) || waiting.stop()
+            }
+            None => (&mut waiting.grant).await.is_ok(),
+        };
+
+        if !granted {
+            let mut state = self.state.lock();
+            state.active += 1;
+            metrics::inc_counter(&metrics::SIGNING_THROTTLE_OVERDUE_TOTAL);
+            metrics::set_gauge(&metrics::SIGNING_CONCURRENCY, state.active as i64);
+        }
+        SigningPermit { throttle: self }
+    }
+
+    /// Releases a permit, granting it to the waiter with the earliest deadline.
+    fn release(&self) {
+        let mut state = self.state.lock();
+        state.active = state.active.saturating_sub(1);
+        while state.active < self.max_concurrent {
+            match state.waiting.pop() {
+                // Waiters which have stopped waiting are skipped.
+                Some(Reverse(waiter)) => {
+                    if waiter.grant.send(()).is_ok() {
+                        state.active += 1;
+                    }
+                }
+                None => break,
+            }
+        }
+        metrics::set_gauge(&metrics::SIGNING_CONCURRENCY, state.active as i64);
+    }
+
+    #[cfg(test)]
+    fn active(&self) -> usize {
+        self.state.lock().active
+    }
+
+    #[cfg(test)]
+    fn num_waiting(&self) -> usize {
+        self.state.lock().waiting.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn permits_granted_by_deadline() {
+        let throttle = Arc::new(SigningThrottle::new(1));
+        let permit = throttle.acquire(None, None).await;
+
+        let order = Arc::new(Mutex::new(vec![]));
+        let mut handles = vec![];
+        for deadline in [30, 10, 20] {
+            let throttle = throttle.clone();
+            let order = order.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = throttle
+                    .acquire(Some(Duration::from_secs(deadline)), None)
+                    .await;
+                order.lock().push(deadline);
+            }));
+        }
+        while throttle.num_waiting() < 3 {
+            tokio::task::yield_now().await;
+        }
+
+        drop(permit);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock(), vec![10, 20, 30]);
+        assert_eq!(throttle.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn overdue_signing_ignores_cap() {
+        let throttle = SigningThrottle::new(1);
+        let first = throttle.acquire(None, None).await;
+        let overdue = throttle
+            .acquire(Some(Duration::from_secs(1)), Some(Duration::ZERO))
+            .await;
+        assert_eq!(throttle.active(), 2);
+
+        drop(first);
+        assert_eq!(throttle.active(), 1);
+        drop(overdue);
+        assert_eq!(throttle.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_releases_nothing() {
+        let throttle = SigningThrottle::new(1);
+        let permit = throttle.acquire(None, None).await;
+
+        assert!(throttle.acquire(None, None).now_or_never().is_none());
+        assert_eq!(throttle.num_waiting(), 1);
+
+        drop(permit);
+        assert_eq!(throttle.active(), 0);
+        assert_eq!(throttle.num_waiting(), 0);
+        let _permit = throttle.acquire(None, None).await;
+        assert_eq!(throttle.active(), 1);
+    }
+}
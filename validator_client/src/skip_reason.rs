@@ -0,0 +1,141 @@
+//! The reasons for which the validator client skips a duty.
+//!
+//! The services log skipped duties with a `skip_reason` and count them in the
+//! `vc_duties_skipped_total` metric, so that a missed duty can be traced back to its cause.
+
+use crate::http_metrics::metrics;
+use crate::validator_store::Error as ValidatorStoreError;
+use slashing_protection::NotSafe;
+use strum::{Display, IntoStaticStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum SkipReason {
+    /// No beacon node has a synced head with a verified execution payload.
+    Unsynced,
+    /// The validator is waiting for doppelganger protection to complete.
+    Doppelganger,
+    /// The validator is disabled or unknown, e.g. because it was removed before the duty was due.
+    Disabled,
+    /// Slashing protection refused to sign the message.
+    SlashingProtection,
+    /// The duty is too far in the past to be useful.
+    Stale,
+    /// Signing is paused until the startup warm-up has completed.
+    StartupWarmup,
+    /// The beacon node disagrees with the fork version used for signing.
+    ForkVersionMismatch,
+    /// The validator has no fee recipient and proposals without one are skipped.
+    NoFeeRecipient,
+    /// The builder proposal failed and local block production is disabled.
+    BuilderUnavailable,
+}
+
+impl SkipReason {
+    /// Returns the reason for which a duty was skipped after signing failed with `error`, or
+    /// `None` if the error is unexpected.
+    pub fn from_signing_error(error: &ValidatorStoreError) -> Option<Self> {
+        match error {
+            ValidatorStoreError::DoppelgangerProtected(_)
+            | ValidatorStoreError::UnknownToDoppelgangerService(_) => {
+                Some(SkipReason::Doppelganger)
+            }
+            ValidatorStoreError::UnknownPubkey(_)
+            | ValidatorStoreError::Slashable(NotSafe::DisabledValidator(_)) => {
+                Some(SkipReason::Disabled)
+            }
+            ValidatorStoreError::Slashable(_) | ValidatorStoreError::SameData => {
+                Some(SkipReason::SlashingProtection)
+            }
+            ValidatorStoreError::ForkVersionMismatch(_) => Some(SkipReason::ForkVersionMismatch),
+            ValidatorStoreError::StartupWarmup { .. } => Some(SkipReason::StartupWarmup),
+            ValidatorStoreError::GreaterThanCurrentSlot { .. }
+            | ValidatorStoreError::GreaterThanCurrentEpoch { .. }
+            | ValidatorStoreError::UnableToSignAttestation(_)
+            | ValidatorStoreError::UnableToSign(_) => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.into()
+    }
+}
+
+/// Counts `count` duties of type `duty` which were skipped for `reason`.
+pub fn record_skipped_duties(reason: SkipReason, duty: &str, count: usize) {
+    metrics::inc_counter_vec_by(
+        &metrics::DUTIES_SKIPPED_TOTAL,
+        &[reason.as_str(), duty],
+        count as u64,
+    );
+}
+
+/// Counts a duty of type `duty` which could not be signed due to `error`, returning the reason
+/// for which it was skipped, if any.
+pub fn record_signing_error(error: &ValidatorStoreError, duty: &str) -> Option<SkipReason> {
+    let reason = SkipReason::from_signing_error(error);
+    if let Some(reason) = reason {
+        record_skipped_duties(reason, duty, 1);
+    }
+    reason
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slashing_protection::InvalidBlock;
+    use types::{PublicKeyBytes, Slot};
+
+    #[test]
+    fn signing_error_reasons() {
+        let pubkey = PublicKeyBytes::empty();
+        let reason = |error| SkipReason::from_signing_error(&error);
+
+        assert_eq!(
+            reason(ValidatorStoreError::DoppelgangerProtected(pubkey)),
+            Some(SkipReason::Doppelganger)
+        );
+        assert_eq!(
+            reason(ValidatorStoreError::UnknownPubkey(pubkey)),
+            Some(SkipReason::Disabled)
+        );
+        assert_eq!(
+            reason(ValidatorStoreError::Slashable(NotSafe::DisabledValidator(
+                pubkey
+            ))),
+            Some(SkipReason::Disabled)
+        );
+        assert_eq!(
+            reason(ValidatorStoreError::Slashable(NotSafe::InvalidBlock(
+                InvalidBlock::SlotViolatesLowerBound {
+                    block_slot: Slot::new(1),
+                    bound_slot: Slot::new(2),
+                }
+            ))),
+            Some(SkipReason::SlashingProtection)
+        );
+        assert_eq!(
+            reason(ValidatorStoreError::StartupWarmup {
+                slot: Slot::new(1),
+                first_signing_slot: Slot::new(2),
+            }),
+            Some(SkipReason::StartupWarmup)
+        );
+        assert_eq!(
+            reason(ValidatorStoreError::GreaterThanCurrentSlot {
+                slot: Slot::new(2),
+                current_slot: Slot::new(1),
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn reason_labels() {
+        assert_eq!(
+            SkipReason::SlashingProtection.as_str(),
+            "slashing_protection"
+        );
+        assert_eq!(SkipReason::Stale.to_string(), "stale");
+    }
+}
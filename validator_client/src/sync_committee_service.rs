@@ -1,14 +1,23 @@
-use crate::beacon_node_fallback::{BeaconNodeFallback, RequireSynced};
-use crate::{duties_service::DutiesService, validator_store::ValidatorStore, OfflineOnFailure};
+use crate::beacon_node_fallback::{BeaconNodeFallback, Errors, RequireSynced};
+use crate::http_metrics::metrics;
+use crate::pubkey_list_file::PubkeyListFile;
+use crate::{
+    duties_service::{DutiesService, SlotDuties},
+    skip_reason::{self, SkipReason},
+    validator_store::ValidatorStore,
+    OfflineOnFailure,
+};
 use environment::RuntimeContext;
 use eth2::types::BlockId;
+use eth2::BeaconNodeHttpClient;
 use futures::future::join_all;
 use futures::future::FutureExt;
 use slog::{crit, debug, error, info, trace, warn};
 use slot_clock::SlotClock;
 use std::collections::HashMap;
+use std::future::Future;
 use std::ops::Deref;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::time::{sleep, sleep_until, Duration, Instant};
 use types::{
@@ -44,6 +53,11 @@ pub struct Inner<T: SlotClock + 'static, E: EthSpec> {
     slot_clock: T,
     beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
     context: RuntimeContext<E>,
+    /// Validators which neither sign sync committee messages nor aggregate contributions.
+    opt_out_file: Option<PubkeyListFile>,
+    /// Publish messages and contributions to all synced beacon nodes, rather than the first to
+    /// accept them.
+    broadcast: bool,
     /// Boolean to track whether the service has posted subscriptions to the BN at least once.
     ///
     /// This acts as a latch that fires once upon start-up, and then never again.
@@ -56,6 +70,8 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
         validator_store: Arc<ValidatorStore<T, E>>,
         slot_clock: T,
         beacon_nodes: Arc<BeaconNodeFallback<T, E>>,
+        opt_out_file: Option<PubkeyListFile>,
+        broadcast: bool,
         context: RuntimeContext<E>,
     ) -> Self {
         Self {
@@ -65,11 +81,84 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
                 slot_clock,
                 beacon_nodes,
                 context,
+                opt_out_file,
+                broadcast,
                 first_subscription_done: AtomicBool::new(false),
             }),
         }
     }
 
+    /// Returns `true` if the validator with `pubkey` is listed in the sync committee opt-out file.
+    fn is_opted_out(&self, pubkey: &PublicKeyBytes) -> bool {
+        self.opt_out_file
+            .as_ref()
+            .map_or(false, |opt_out_file| opt_out_file.contains(pubkey))
+    }
+
+    /// Publish already-signed messages using `func`.
+    ///
+    /// Without `broadcast`, `func` is run on the first beacon node to succeed. With `broadcast`,
+    /// `func` is run on all synced beacon nodes and succeeds if any of them accepts the messages.
+    async fn publish<'a, F, R>(&'a self, func: F) -> Result<(), Errors<eth2::Error>>
+    where
+        F: Fn(&'a BeaconNodeHttpClient) -> R,
+        R: Future<Output = Result<(), eth2::Error>>,
+    {
+        let func = &func;
+        let accepted = &AtomicUsize::new(0);
+        let publish = |beacon_node: &'a BeaconNodeHttpClient| async move {
+            metrics::inc_counter_vec(
+                &metrics::SYNC_COMMITTEE_PUBLISH_REQUESTS,
+                &[beacon_node.as_ref()],
+            );
+            func(beacon_node).await?;
+            metrics::inc_counter_vec(
+                &metrics::SYNC_COMMITTEE_PUBLISH_ACCEPTED,
+                &[beacon_node.as_ref()],
+            );
+            accepted.fetch_add(1, Ordering::Relaxed);
+            Ok::<_, eth2::Error>(())
+        };
+
+        if !self.broadcast {
+            return self
+                .beacon_nodes
+                .first_success(RequireSynced::No, OfflineOnFailure::Yes, publish)
+                .await;
+        }
+
+        match self
+            .beacon_nodes
+            .run_on_all(RequireSynced::Yes, OfflineOnFailure::Yes, publish)
+            .await
+        {
+            Err(e) if accepted.load(Ordering::Relaxed) > 0 => {
+                debug!(
+                    self.context.log(),
+                    "Some beacon nodes did not accept sync committee messages";
+                    "accepted" => accepted.load(Ordering::Relaxed),
+                    "error" => %e,
+                );
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Remove the duties of validators which have opted out of sync committees.
+    fn remove_opted_out(&self, mut slot_duties: SlotDuties) -> SlotDuties {
+        slot_duties
+            .duties
+            .retain(|duty| !self.is_opted_out(&duty.pubkey));
+        for aggregators in slot_duties.aggregators.values_mut() {
+            aggregators.retain(|(_, pubkey, _)| !self.is_opted_out(pubkey));
+        }
+        slot_duties
+            .aggregators
+            .retain(|_, aggregators| !aggregators.is_empty());
+        slot_duties
+    }
+
     /// Check if the Altair fork has been activated and therefore sync duties should be performed.
     ///
     /// Slot clock errors are mapped to `false`.
@@ -142,6 +231,11 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
     async fn spawn_contribution_tasks(&self, slot_duration: Duration) -> Result<(), String> {
         let log = self.context.log().clone();
         let slot = self.slot_clock.now().ok_or("Failed to read slot clock")?;
+
+        if !self.validator_store.startup_warmup_complete(slot) {
+            debug!(log, "Not signing sync committee messages during startup warm-up"; "slot" => slot);
+            return Ok(());
+        }
         let duration_to_next_slot = self
             .slot_clock
             .duration_to_next_slot()
@@ -159,7 +253,7 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
             .sync_duties
             .get_duties_for_slot::<E>(slot, &self.duties_service.spec)
         {
-            duties
+            self.remove_opted_out(duties)
         } else {
             debug!(log, "No duties known for slot {}", slot);
             return Ok(());
@@ -203,8 +297,19 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
                     "Refusing to sign sync committee messages for an optimistic head block or \
                     a block head with unknown optimistic status";
                     "errors" => errs.to_string(),
+                    "skip_reason" => SkipReason::Unsynced.as_str(),
                     "slot" => slot,
                 );
+                skip_reason::record_skipped_duties(
+                    SkipReason::Unsynced,
+                    metrics::SYNC_COMMITTEE_MESSAGES,
+                    slot_duties.duties.len(),
+                );
+                skip_reason::record_skipped_duties(
+                    SkipReason::Unsynced,
+                    metrics::SYNC_COMMITTEE_CONTRIBUTIONS,
+                    slot_duties.aggregators.values().map(Vec::len).sum(),
+                );
                 return Ok(());
             }
         };
@@ -265,12 +370,15 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
             {
                 Ok(signature) => Some(signature),
                 Err(e) => {
+                    let skip_reason =
+                        skip_reason::record_signing_error(&e, metrics::SYNC_COMMITTEE_MESSAGES);
                     crit!(
                         log,
                         "Failed to sign sync committee signature";
                         "validator_index" => duty.validator_index,
                         "slot" => slot,
                         "error" => ?e,
+                        "skip_reason" => skip_reason.map(SkipReason::as_str),
                     );
                     None
                 }
@@ -284,25 +392,45 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
             .flatten()
             .collect::<Vec<_>>();
 
-        self.beacon_nodes
-            .first_success(
-                RequireSynced::No,
-                OfflineOnFailure::Yes,
-                |beacon_node| async move {
-                    beacon_node
-                        .post_beacon_pool_sync_committee_signatures(committee_signatures)
-                        .await
-                },
-            )
-            .await
-            .map_err(|e| {
-                error!(
+        let slot_clock = &self.slot_clock;
+        let canary = validator_duties
+            .iter()
+            .find(|duty| self.validator_store.is_canary(&duty.pubkey))
+            .map(|duty| duty.validator_index);
+
+        self.publish(|beacon_node| async move {
+            beacon_node
+                .post_beacon_pool_sync_committee_signatures(committee_signatures)
+                .await?;
+            if let Some(validator_index) = canary {
+                let slot_delay_ms = slot_clock
+                    .millis_from_current_slot_start()
+                    .map(|delay| delay.as_millis());
+                let request_bytes =
+                    serde_json::to_vec(committee_signatures).map_or(0, |bytes| bytes.len());
+                info!(
                     log,
-                    "Unable to publish sync committee messages";
+                    "Canary validator diagnostics";
+                    "stage" => "published sync committee message",
+                    "validator_index" => validator_index,
+                    "beacon_node" => %beacon_node,
+                    "slot_delay_ms" => ?slot_delay_ms,
+                    "request_bytes" => request_bytes,
+                    "count" => committee_signatures.len(),
                     "slot" => slot,
-                    "error" => %e,
                 );
-            })?;
+            }
+            Ok::<_, eth2::Error>(())
+        })
+        .await
+        .map_err(|e| {
+            error!(
+                log,
+                "Unable to publish sync committee messages";
+                "slot" => slot,
+                "error" => %e,
+            );
+        })?;
 
         info!(
             log,
@@ -406,11 +534,16 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
                 {
                     Ok(signed_contribution) => Some(signed_contribution),
                     Err(e) => {
+                        let skip_reason = skip_reason::record_signing_error(
+                            &e,
+                            metrics::SYNC_COMMITTEE_CONTRIBUTIONS,
+                        );
                         crit!(
                             log,
                             "Unable to sign sync committee contribution";
                             "slot" => slot,
                             "error" => ?e,
+                            "skip_reason" => skip_reason.map(SkipReason::as_str),
                         );
                         None
                     }
@@ -426,25 +559,20 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
             .collect::<Vec<_>>();
 
         // Publish to the beacon node.
-        self.beacon_nodes
-            .first_success(
-                RequireSynced::No,
-                OfflineOnFailure::Yes,
-                |beacon_node| async move {
-                    beacon_node
-                        .post_validator_contribution_and_proofs(signed_contributions)
-                        .await
-                },
-            )
-            .await
-            .map_err(|e| {
-                error!(
-                    log,
-                    "Unable to publish signed contributions and proofs";
-                    "slot" => slot,
-                    "error" => %e,
-                );
-            })?;
+        self.publish(|beacon_node| async move {
+            beacon_node
+                .post_validator_contribution_and_proofs(signed_contributions)
+                .await
+        })
+        .await
+        .map_err(|e| {
+            error!(
+                log,
+                "Unable to publish signed contributions and proofs";
+                "slot" => slot,
+                "error" => %e,
+            );
+        })?;
 
         info!(
             log,
@@ -525,7 +653,7 @@ impl<T: SlotClock + 'static, E: EthSpec> SyncCommitteeService<T, E> {
                 .get_duties_for_slot::<E>(duty_slot, spec)
             {
                 Some(duties) => subscriptions.extend(subscriptions_from_sync_duties(
-                    duties.duties,
+                    self.remove_opted_out(duties).duties,
                     sync_committee_period,
                     spec,
                 )),
@@ -0,0 +1,139 @@
+//! Checks of the expiry of the TLS certificates presented by beacon nodes.
+//!
+//! An expired certificate causes every connection to the beacon node to fail at once, so each
+//! beacon node connected over HTTPS is checked periodically and a warning is logged whilst its
+//! certificate expires within `beacon_nodes_tls_cert_expiry_window`.
+
+use crate::http_metrics::metrics;
+use environment::RuntimeContext;
+use reqwest::{tls::TlsInfo, Client};
+use sensitive_url::SensitiveUrl;
+use slog::{debug, warn, Logger};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::sleep;
+use types::EthSpec;
+
+/// How often the certificate presented by each beacon node is checked.
+pub const TLS_CERT_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Returns the expiry of the DER-encoded `certificate`, in seconds since the UNIX epoch.
+pub fn certificate_expiry(certificate: &[u8]) -> Result<i64, String> {
+    let (_, certificate) = x509_parser::parse_x509_certificate(certificate)
+        .map_err(|e| format!("Unable to parse certificate: {:?}", e))?;
+    Ok(certificate.validity().not_after.timestamp())
+}
+
+/// Returns the expiry of the certificate presented by the beacon node at `url`, in seconds since
+/// the UNIX epoch.
+///
+/// The `client` must have been built with `tls_info(true)`.
+async fn peer_certificate_expiry(client: &Client, url: &SensitiveUrl) -> Result<i64, String> {
+    // Any response carries the certificate, so the status of the response is ignored.
+    let response = client
+        .get(url.full.clone())
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e.without_url()))?;
+    let certificate = response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(TlsInfo::peer_certificate)
+        .ok_or("No certificate presented")?;
+    certificate_expiry(certificate)
+}
+
+/// Returns the number of seconds from `now` until `expiry`, which is negative once expired.
+fn seconds_until_expiry(expiry: i64, now: SystemTime) -> i64 {
+    let now = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64);
+    expiry.saturating_sub(now)
+}
+
+/// Updates the metrics for the certificate of `endpoint` and warns if it expires within `window`.
+fn check_expiry(endpoint: &str, seconds_remaining: i64, window: Duration, log: &Logger) {
+    let expiring = seconds_remaining < window.as_secs() as i64;
+    metrics::set_gauge_vec(
+        &metrics::BEACON_NODE_TLS_CERT_EXPIRY_SECONDS,
+        &[endpoint],
+        seconds_remaining,
+    );
+    metrics::set_gauge_vec(
+        &metrics::BEACON_NODE_TLS_CERT_EXPIRING,
+        &[endpoint],
+        expiring as i64,
+    );
+
+    if expiring {
+        warn!(
+            log,
+            "Beacon node TLS certificate expiring";
+            "msg" => "connections to the beacon node will fail once its certificate expires, \
+                      rotate the certificate",
+            "days_remaining" => seconds_remaining / (24 * 60 * 60),
+            "endpoint" => endpoint,
+        );
+    }
+}
+
+/// Starts a service which periodically checks the certificate presented by the beacon node at
+/// `url`, warning whilst it expires within `window`.
+pub fn start_tls_cert_expiry_service<E: EthSpec>(
+    context: RuntimeContext<E>,
+    client: Client,
+    url: SensitiveUrl,
+    window: Duration,
+) {
+    let log = context.log().clone();
+
+    let future = async move {
+        let endpoint = url.to_string();
+        loop {
+            match peer_certificate_expiry(&client, &url).await {
+                Ok(expiry) => check_expiry(
+                    &endpoint,
+                    seconds_until_expiry(expiry, SystemTime::now()),
+                    window,
+                    &log,
+                ),
+                Err(e) => debug!(
+                    log,
+                    "Unable to check beacon node TLS certificate";
+                    "error" => e,
+                    "endpoint" => &endpoint,
+                ),
+            }
+
+            sleep(TLS_CERT_EXPIRY_CHECK_INTERVAL).await;
+        }
+    };
+
+    context.executor.spawn(future, "tls_cert_expiry");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{date_time_ymd, Certificate, CertificateParams};
+
+    #[test]
+    fn parse_certificate_expiry() {
+        let mut params = CertificateParams::new(vec!["localhost".to_string()]);
+        params.not_after = date_time_ymd(2030, 1, 1);
+        let certificate = Certificate::from_params(params)
+            .unwrap()
+            .serialize_der()
+            .unwrap();
+
+        // 2030-01-01T00:00:00Z
+        assert_eq!(certificate_expiry(&certificate), Ok(1_893_456_000));
+        assert!(certificate_expiry(&certificate[1..]).is_err());
+    }
+
+    #[test]
+    fn expiry_countdown() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        assert_eq!(seconds_until_expiry(1_600, now), 600);
+        assert_eq!(seconds_until_expiry(400, now), -600);
+    }
+}
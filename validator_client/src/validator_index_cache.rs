@@ -0,0 +1,165 @@
+//! A persistent cache of validator indices, which avoids resolving the index of every local
+//! validator with the beacon node before the first duties can be fetched.
+//!
+//! Indices loaded from disk are used immediately but are considered unverified until the beacon
+//! node has confirmed them.
+
+use account_utils::write_file_via_temporary;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use types::PublicKeyBytes;
+
+/// The file name for the serialized `ValidatorIndexCache`.
+pub const CACHE_FILENAME: &str = "validator_index_cache.json";
+
+/// The file name for the temporary `ValidatorIndexCache`.
+pub const TEMP_CACHE_FILENAME: &str = ".validator_index_cache.json.tmp";
+
+#[derive(Debug)]
+pub enum Error {
+    /// The cache file could not be opened.
+    UnableToOpenFile(io::Error),
+    /// The cache file could not be parsed as JSON.
+    UnableToParseFile(serde_json::Error),
+    /// The cache could not be serialized as JSON.
+    UnableToEncodeFile(serde_json::Error),
+    /// The cache file or its temporary could not be written to the filesystem.
+    UnableToWriteFile(filesystem::Error),
+}
+
+/// Maps validator public keys to their index in the beacon chain.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ValidatorIndexCache {
+    indices: HashMap<PublicKeyBytes, u64>,
+    #[serde(skip)]
+    unverified: HashSet<PublicKeyBytes>,
+    #[serde(skip)]
+    cache_path: PathBuf,
+    #[serde(skip)]
+    modified: bool,
+}
+
+impl ValidatorIndexCache {
+    /// Returns an empty cache which will replace any existing cache file in `validators_dir` when
+    /// saved.
+    pub fn new<P: AsRef<Path>>(validators_dir: P) -> Self {
+        Self {
+            cache_path: validators_dir.as_ref().join(CACHE_FILENAME),
+            modified: true,
+            ..Self::default()
+        }
+    }
+
+    /// Open the cache file in `validators_dir`, or return an empty cache if it does not exist.
+    ///
+    /// All loaded indices are unverified.
+    pub fn open_or_create<P: AsRef<Path>>(validators_dir: P) -> Result<Self, Error> {
+        let cache_path = validators_dir.as_ref().join(CACHE_FILENAME);
+        if !cache_path.exists() {
+            return Ok(Self::new(validators_dir));
+        }
+
+        let file = File::open(&cache_path).map_err(Error::UnableToOpenFile)?;
+        let mut cache: Self = serde_json::from_reader(file).map_err(Error::UnableToParseFile)?;
+        cache.unverified = cache.indices.keys().copied().collect();
+        cache.cache_path = cache_path;
+        Ok(cache)
+    }
+
+    /// Returns the cached index of `pubkey`, whether or not it has been verified.
+    pub fn get(&self, pubkey: &PublicKeyBytes) -> Option<u64> {
+        self.indices.get(pubkey).copied()
+    }
+
+    /// Returns `true` if the index of `pubkey` was loaded from disk and has not yet been
+    /// confirmed by the beacon node.
+    pub fn is_unverified(&self, pubkey: &PublicKeyBytes) -> bool {
+        self.unverified.contains(pubkey)
+    }
+
+    /// Record `index` as the verified index of `pubkey`.
+    ///
+    /// Returns the previously cached index, if any.
+    pub fn insert(&mut self, pubkey: PublicKeyBytes, index: u64) -> Option<u64> {
+        self.unverified.remove(&pubkey);
+        let previous = self.indices.insert(pubkey, index);
+        if previous != Some(index) {
+            self.modified = true;
+        }
+        previous
+    }
+
+    /// Writes the cache to disk if it has changed since it was opened or last saved.
+    ///
+    /// Returns `false` iff there were no changes to save.
+    pub fn save(&mut self) -> Result<bool, Error> {
+        if !self.modified {
+            return Ok(false);
+        }
+
+        let temp_path = self.cache_path.with_file_name(TEMP_CACHE_FILENAME);
+        let bytes = serde_json::to_vec(self).map_err(Error::UnableToEncodeFile)?;
+        write_file_via_temporary(&self.cache_path, &temp_path, &bytes)
+            .map_err(Error::UnableToWriteFile)?;
+
+        self.modified = false;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::Keypair;
+    use tempfile::TempDir;
+
+    #[test]
+    fn save_and_open() {
+        let dir = TempDir::new().unwrap();
+        let pk1 = Keypair::random().pk.compress();
+        let pk2 = Keypair::random().pk.compress();
+
+        let mut cache = ValidatorIndexCache::open_or_create(dir.path()).unwrap();
+        assert_eq!(cache.get(&pk1), None);
+        assert_eq!(cache.insert(pk1, 1), None);
+        assert_eq!(cache.insert(pk2, 2), None);
+        assert!(cache.save().unwrap());
+        assert!(!cache.save().unwrap());
+
+        let mut cache = ValidatorIndexCache::open_or_create(dir.path()).unwrap();
+        assert_eq!(cache.get(&pk1), Some(1));
+        assert_eq!(cache.get(&pk2), Some(2));
+        assert!(cache.is_unverified(&pk1));
+        assert!(cache.is_unverified(&pk2));
+
+        // Confirming an index does not require the cache to be saved.
+        assert_eq!(cache.insert(pk1, 1), Some(1));
+        assert!(!cache.is_unverified(&pk1));
+        assert!(!cache.save().unwrap());
+
+        // Correcting an index does.
+        assert_eq!(cache.insert(pk2, 3), Some(2));
+        assert!(cache.save().unwrap());
+        let cache = ValidatorIndexCache::open_or_create(dir.path()).unwrap();
+        assert_eq!(cache.get(&pk2), Some(3));
+    }
+
+    #[test]
+    fn rebuild_replaces_existing_file() {
+        let dir = TempDir::new().unwrap();
+        let pk = Keypair::random().pk.compress();
+
+        let mut cache = ValidatorIndexCache::new(dir.path());
+        cache.insert(pk, 1);
+        cache.save().unwrap();
+
+        let mut cache = ValidatorIndexCache::new(dir.path());
+        assert_eq!(cache.get(&pk), None);
+        assert!(cache.save().unwrap());
+        let cache = ValidatorIndexCache::open_or_create(dir.path()).unwrap();
+        assert_eq!(cache.get(&pk), None);
+    }
+}
@@ -1,30 +1,41 @@
 use crate::{
     doppelganger_service::DoppelgangerService,
+    fork_schedule::{
+        check_fork_version, fork_in_transition_window, ForkVersionMismatch, OnForkVersionMismatch,
+    },
     http_metrics::metrics,
     initialized_validators::InitializedValidators,
+    preparation_service::OnMissingFeeRecipient,
+    pubkey_list_file::PubkeyListFile,
     signing_method::{Error as SigningError, SignableMessage, SigningContext, SigningMethod},
+    signing_throttle::{SigningPermit, SigningThrottle},
     Config,
 };
 use account_utils::{validator_definitions::ValidatorDefinition, ZeroizeString};
+use eth2::lighthouse_vc::types::{ResolvedSetting, SettingSource};
 use parking_lot::{Mutex, RwLock};
 use slashing_protection::{
-    interchange::Interchange, InterchangeError, NotSafe, Safe, SlashingDatabase,
+    interchange::Interchange, InterchangeError, InterchangeImportOutcome, NotSafe, Safe,
+    SlashingDatabase, ValidatorSummary,
 };
 use slog::{crit, error, info, warn, Logger};
 use slot_clock::SlotClock;
+use std::collections::HashMap;
 use std::iter::FromIterator;
 use std::marker::PhantomData;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use task_executor::TaskExecutor;
+use tokio::time::sleep;
 use types::{
     attestation::Error as AttestationError, graffiti::GraffitiString, AbstractExecPayload, Address,
     AggregateAndProof, Attestation, BeaconBlock, BlindedPayload, ChainSpec, ContributionAndProof,
-    Domain, Epoch, EthSpec, Fork, Graffiti, Hash256, Keypair, PublicKeyBytes, SelectionProof,
-    Signature, SignedAggregateAndProof, SignedBeaconBlock, SignedContributionAndProof, SignedRoot,
-    SignedValidatorRegistrationData, SignedVoluntaryExit, Slot, SyncAggregatorSelectionData,
-    SyncCommitteeContribution, SyncCommitteeMessage, SyncSelectionProof, SyncSubnetId,
-    ValidatorRegistrationData, VoluntaryExit,
+    Domain, Epoch, EthSpec, Fork, ForkName, Graffiti, Hash256, Keypair, PublicKeyBytes,
+    SelectionProof, Signature, SignedAggregateAndProof, SignedBeaconBlock,
+    SignedContributionAndProof, SignedRoot, SignedValidatorRegistrationData, SignedVoluntaryExit,
+    Slot, SyncAggregatorSelectionData, SyncCommitteeContribution, SyncCommitteeMessage,
+    SyncSelectionProof, SyncSubnetId, ValidatorRegistrationData, VoluntaryExit,
 };
 use validator_dir::ValidatorDir;
 
@@ -38,10 +49,21 @@ pub enum Error {
     UnknownPubkey(PublicKeyBytes),
     Slashable(NotSafe),
     SameData,
-    GreaterThanCurrentSlot { slot: Slot, current_slot: Slot },
-    GreaterThanCurrentEpoch { epoch: Epoch, current_epoch: Epoch },
+    GreaterThanCurrentSlot {
+        slot: Slot,
+        current_slot: Slot,
+    },
+    GreaterThanCurrentEpoch {
+        epoch: Epoch,
+        current_epoch: Epoch,
+    },
     UnableToSignAttestation(AttestationError),
     UnableToSign(SigningError),
+    ForkVersionMismatch(ForkVersionMismatch),
+    StartupWarmup {
+        slot: Slot,
+        first_signing_slot: Slot,
+    },
 }
 
 impl From<SigningError> for Error {
@@ -50,10 +72,11 @@ impl From<SigningError> for Error {
     }
 }
 
-/// Number of epochs of slashing protection history to keep.
+/// Number of epochs of slashing protection history to keep, and the default number of epochs
+/// between prunings.
 ///
 /// This acts as a maximum safe-guard against clock drift.
-const SLASHING_PROTECTION_HISTORY_EPOCHS: u64 = 512;
+pub const SLASHING_PROTECTION_HISTORY_EPOCHS: u64 = 512;
 
 /// Currently used as the default gas limit in execution clients.
 ///
@@ -89,14 +112,45 @@ pub struct ValidatorStore<T, E: EthSpec> {
     validators: Arc<RwLock<InitializedValidators>>,
     slashing_protection: SlashingDatabase,
     slashing_protection_last_prune: Arc<Mutex<Epoch>>,
+    /// The number of epochs between prunings of the slashing protection database.
+    slashing_protection_prune_interval: u64,
+    /// The number of epochs of slashing protection history kept by pruning.
+    slashing_protection_retention_epochs: u64,
     genesis_validators_root: Hash256,
     spec: Arc<ChainSpec>,
     log: Logger,
     doppelganger_service: Option<Arc<DoppelgangerService>>,
     slot_clock: T,
     fee_recipient_process: Option<Address>,
+    on_missing_fee_recipient: OnMissingFeeRecipient,
     gas_limit: Option<u64>,
     builder_proposals: bool,
+    builder_proposals_enabled_pubkeys: Option<PubkeyListFile>,
+    beacon_node_fork_schedule: RwLock<Option<Vec<Fork>>>,
+    on_fork_version_mismatch: OnForkVersionMismatch,
+    /// The last mismatch logged when signing anyway, so that a mismatch is only logged once rather
+    /// than for every signature.
+    last_fork_version_mismatch: Mutex<Option<ForkVersionMismatch>>,
+    /// The number of epochs either side of a fork in which proposals are paused on a fork version
+    /// mismatch, or `None` if fork transition caution is disabled.
+    fork_transition_window: Option<u64>,
+    slashing_protection_retries: u32,
+    slashing_protection_retry_delay: Duration,
+    canary_validator: Option<PublicKeyBytes>,
+    /// The first slot in which duties are signed, if a startup warm-up is configured.
+    first_signing_slot: Option<Slot>,
+    /// Caps the number of concurrent signing operations, if configured.
+    signing_throttle: Option<SigningThrottle>,
+    /// The fraction of the slot at which attestations are produced, which is the latest useful
+    /// time to sign a block.
+    attestation_deadline_fraction: f64,
+    /// Signing operations which take longer than this are logged and counted.
+    slow_sign_threshold: Duration,
+    /// The number of epochs after activation in which proposals are checked more thoroughly, if
+    /// configured.
+    first_proposal_caution_epochs: Option<u64>,
+    /// The activation epoch of each validator, once it is known.
+    activation_epochs: RwLock<HashMap<PublicKeyBytes, Epoch>>,
     task_executor: TaskExecutor,
     _phantom: PhantomData<E>,
 }
@@ -116,30 +170,80 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         task_executor: TaskExecutor,
         log: Logger,
     ) -> Self {
+        // Delay signing until the first slot which starts after the warm-up.
+        let first_signing_slot = config.startup_warmup.and_then(|warmup| {
+            let warmup_end = slot_clock.now_duration()? + warmup;
+            Some(slot_clock.slot_of(warmup_end)? + 1)
+        });
+        if let Some(first_signing_slot) = first_signing_slot {
+            info!(
+                log,
+                "Delaying signing for startup warm-up";
+                "first_signing_slot" => first_signing_slot,
+            );
+        }
+
         Self {
             validators: Arc::new(RwLock::new(validators)),
             slashing_protection,
             slashing_protection_last_prune: Arc::new(Mutex::new(Epoch::new(0))),
+            slashing_protection_prune_interval: config
+                .slashing_protection_prune_interval
+                .unwrap_or(SLASHING_PROTECTION_HISTORY_EPOCHS),
+            slashing_protection_retention_epochs: config.slashing_protection_retention_epochs,
             genesis_validators_root,
             spec: Arc::new(spec),
             log,
             doppelganger_service,
             slot_clock,
             fee_recipient_process: config.fee_recipient,
+            on_missing_fee_recipient: config.on_missing_fee_recipient,
             gas_limit: config.gas_limit,
             builder_proposals: config.builder_proposals,
+            builder_proposals_enabled_pubkeys: config.builder_proposals_enabled_pubkeys.clone(),
+            beacon_node_fork_schedule: RwLock::new(None),
+            on_fork_version_mismatch: config.on_fork_version_mismatch,
+            last_fork_version_mismatch: Mutex::new(None),
+            fork_transition_window: config
+                .fork_transition_caution
+                .then_some(config.fork_transition_window),
+            slashing_protection_retries: config.slashing_protection_retries,
+            slashing_protection_retry_delay: config.slashing_protection_retry_delay,
+            canary_validator: config.canary_validator.as_ref().map(|pk| pk.compress()),
+            first_signing_slot,
+            signing_throttle: config.max_concurrent_signings.map(SigningThrottle::new),
+            attestation_deadline_fraction: config.attestation_deadline_fraction,
+            slow_sign_threshold: config.slow_sign_threshold,
+            first_proposal_caution_epochs: config.first_proposal_caution_epochs,
+            activation_epochs: RwLock::new(HashMap::new()),
             task_executor,
             _phantom: PhantomData,
         }
     }
 
+    /// Returns an error if the slashing protection database cannot currently be used, for example
+    /// because it is locked by another process or its file has become inaccessible.
+    ///
+    /// This may block for up to the connection timeout of the database.
+    pub fn check_slashing_protection_available(&self) -> Result<(), NotSafe> {
+        self.slashing_protection.test_transaction()
+    }
+
     /// Register all local validators in doppelganger protection to try and prevent instances of
     /// duplicate validators operating on the network at the same time.
     ///
     /// This function has no effect if doppelganger protection is disabled.
+    ///
+    /// Deferred validators are registered too, so that they are never initialized without being
+    /// known to doppelganger protection. Their detection cannot progress until they have been
+    /// initialized and their index is known.
     pub fn register_all_in_doppelganger_protection_if_enabled(&self) -> Result<(), String> {
         if let Some(doppelganger_service) = &self.doppelganger_service {
-            for pubkey in self.validators.read().iter_voting_pubkeys() {
+            let validators = self.validators.read();
+            for pubkey in validators
+                .iter_voting_pubkeys()
+                .chain(validators.iter_deferred_pubkeys())
+            {
                 doppelganger_service.register_new_validator::<E, _>(*pubkey, &self.slot_clock)?
             }
         }
@@ -147,6 +251,42 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         Ok(())
     }
 
+    /// Initialize the validators whose initialization was deferred by the validator load priority
+    /// list.
+    ///
+    /// The duties service picks up the new validators at its next poll, and they begin performing
+    /// duties once permitted by doppelganger protection.
+    pub async fn initialize_deferred_validators(&self) -> Result<(), String> {
+        InitializedValidators::initialize_deferred(&self.validators)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    /// Returns `true` if the startup warm-up, if any, has completed by `slot`.
+    ///
+    /// Duties for slots prior to the end of the warm-up must not be signed.
+    pub fn startup_warmup_complete(&self, slot: Slot) -> bool {
+        self.first_signing_slot
+            .map_or(true, |first_signing_slot| slot >= first_signing_slot)
+    }
+
+    fn check_startup_warmup(&self, slot: Slot) -> Result<(), Error> {
+        match self.first_signing_slot {
+            Some(first_signing_slot) if slot < first_signing_slot => Err(Error::StartupWarmup {
+                slot,
+                first_signing_slot,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `true` if `pubkey` is the canary validator, for which diagnostics of each duty
+    /// should be logged.
+    pub fn is_canary(&self, pubkey: &PublicKeyBytes) -> bool {
+        self.canary_validator.as_ref() == Some(pubkey)
+    }
+
     /// Returns `true` if doppelganger protection is enabled, or else `false`.
     pub fn doppelganger_protection_enabled(&self) -> bool {
         self.doppelganger_service.is_some()
@@ -238,7 +378,7 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
                     .get_fee_recipient_defaulting(validator.get_suggested_fee_recipient()),
                 gas_limit: self.get_gas_limit_defaulting(validator.get_gas_limit()),
                 builder_proposals: self
-                    .get_builder_proposals_defaulting(validator.get_builder_proposals()),
+                    .get_builder_proposals_defaulting(pubkey, validator.get_builder_proposals()),
             })
     }
 
@@ -252,6 +392,35 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         self.validators.read().get_index(pubkey)
     }
 
+    /// Returns the number of epochs after activation in which proposals are checked more
+    /// thoroughly, if configured.
+    pub fn first_proposal_caution_epochs(&self) -> Option<u64> {
+        self.first_proposal_caution_epochs
+    }
+
+    pub fn activation_epoch(&self, pubkey: &PublicKeyBytes) -> Option<Epoch> {
+        self.activation_epochs.read().get(pubkey).copied()
+    }
+
+    pub fn set_activation_epoch(&self, pubkey: PublicKeyBytes, activation_epoch: Epoch) {
+        self.activation_epochs
+            .write()
+            .insert(pubkey, activation_epoch);
+    }
+
+    /// Returns `true` if a proposal by `pubkey` in `epoch` should be checked more thoroughly,
+    /// because the validator was activated within `first_proposal_caution_epochs`.
+    ///
+    /// Validators whose activation epoch is not yet known are treated as freshly activated.
+    pub fn is_freshly_activated(&self, pubkey: &PublicKeyBytes, epoch: Epoch) -> bool {
+        match self.first_proposal_caution_epochs {
+            Some(caution_epochs) => self.activation_epoch(pubkey).map_or(true, |activation| {
+                epoch.as_u64() < activation.as_u64().saturating_add(caution_epochs)
+            }),
+            None => false,
+        }
+    }
+
     /// Returns all voting pubkeys for all enabled validators.
     ///
     /// The `filter_func` allows for filtering pubkeys based upon their `DoppelgangerStatus`. There
@@ -368,12 +537,178 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
             .ok_or(Error::UnknownPubkey(validator_pubkey))
     }
 
-    fn signing_context(&self, domain: Domain, signing_epoch: Epoch) -> SigningContext {
-        SigningContext {
+    /// Sets the fork schedule reported by the beacon node, which is compared against our own
+    /// fork when signing.
+    pub fn set_beacon_node_fork_schedule(&self, fork_schedule: Vec<Fork>) {
+        *self.beacon_node_fork_schedule.write() = Some(fork_schedule);
+    }
+
+    /// Compares the fork version used for signing at `epoch` with the beacon node's fork schedule.
+    ///
+    /// Returns `None` if the beacon node's fork schedule is not yet known.
+    pub fn check_beacon_node_fork_version(
+        &self,
+        epoch: Epoch,
+    ) -> Option<Result<(), ForkVersionMismatch>> {
+        let beacon_node_fork_schedule = self.beacon_node_fork_schedule.read();
+        Some(check_fork_version(
+            &self.fork(epoch),
+            beacon_node_fork_schedule.as_ref()?,
+            epoch,
+        ))
+    }
+
+    /// Returns the fork which activates close enough to `epoch` for fork transition caution to
+    /// apply, along with its activation epoch.
+    pub fn fork_transition(&self, epoch: Epoch) -> Option<(ForkName, Epoch)> {
+        fork_in_transition_window(&self.spec, epoch, self.fork_transition_window?)
+    }
+
+    /// Returns the name and version of the fork used for signing at `epoch`.
+    pub fn signing_fork(&self, epoch: Epoch) -> (ForkName, [u8; 4]) {
+        (
+            self.spec.fork_name_at_epoch(epoch),
+            self.fork(epoch).get_fork_version(epoch),
+        )
+    }
+
+    fn signing_context(
+        &self,
+        domain: Domain,
+        signing_epoch: Epoch,
+    ) -> Result<SigningContext, Error> {
+        let fork = self.fork(signing_epoch);
+
+        if let Some(beacon_node_fork_schedule) = self.beacon_node_fork_schedule.read().as_ref() {
+            let check = check_fork_version(&fork, beacon_node_fork_schedule, signing_epoch);
+            if check.is_ok() {
+                *self.last_fork_version_mismatch.lock() = None;
+            }
+            if let Err(mismatch) = check {
+                // Proposals are paused on a mismatch close to a fork, regardless of
+                // `on_fork_version_mismatch`.
+                let pause_proposal = matches!(domain, Domain::BeaconProposer | Domain::Randao)
+                    && self.fork_transition(signing_epoch).is_some();
+                if pause_proposal {
+                    crit!(
+                        self.log,
+                        "Fork version mismatch with beacon node during fork transition";
+                        "msg" => "pausing proposals until the versions agree",
+                        "epoch" => mismatch.epoch,
+                        "fork_version" => ?mismatch.fork_version,
+                        "beacon_node_fork_version" => ?mismatch.beacon_node_fork_version,
+                        "domain" => ?domain,
+                    );
+                    return Err(Error::ForkVersionMismatch(mismatch));
+                }
+
+                match self.on_fork_version_mismatch {
+                    OnForkVersionMismatch::Warn => {
+                        // The mismatch applies to every signature in its epoch, so it is logged
+                        // once per epoch rather than for each signature.
+                        let mut last_mismatch = self.last_fork_version_mismatch.lock();
+                        if last_mismatch.as_ref() != Some(&mismatch) {
+                            warn!(
+                                self.log,
+                                "Fork version mismatch with beacon node";
+                                "msg" => "signing anyway, ensure the validator client and beacon \
+                                          node are both up to date",
+                                "epoch" => mismatch.epoch,
+                                "fork_version" => ?mismatch.fork_version,
+                                "beacon_node_fork_version" => ?mismatch.beacon_node_fork_version,
+                                "domain" => ?domain,
+                            );
+                            *last_mismatch = Some(mismatch);
+                        }
+                    }
+                    OnForkVersionMismatch::Refuse => {
+                        crit!(
+                            self.log,
+                            "Fork version mismatch with beacon node";
+                            "msg" => "refusing to sign, ensure the validator client and beacon \
+                                      node are both up to date",
+                            "epoch" => mismatch.epoch,
+                            "fork_version" => ?mismatch.fork_version,
+                            "beacon_node_fork_version" => ?mismatch.beacon_node_fork_version,
+                            "domain" => ?domain,
+                        );
+                        return Err(Error::ForkVersionMismatch(mismatch));
+                    }
+                }
+            }
+        }
+
+        Ok(SigningContext {
             domain,
             epoch: signing_epoch,
-            fork: self.fork(signing_epoch),
+            fork,
             genesis_validators_root: self.genesis_validators_root,
+        })
+    }
+
+    /// Returns the time, as a duration since the UNIX epoch, after which the signature of
+    /// `message` is no longer useful.
+    ///
+    /// Messages which are not tied to a slot have no deadline.
+    fn signing_deadline<Payload: AbstractExecPayload<E>>(
+        &self,
+        message: &SignableMessage<'_, E, Payload>,
+    ) -> Option<Duration> {
+        let (slot, offset) = latest_useful_time(
+            message,
+            self.slot_clock.now(),
+            self.slot_clock.slot_duration(),
+            self.attestation_deadline_fraction,
+        )?;
+        Some(self.slot_clock.start_of(slot)? + offset)
+    }
+
+    /// Waits for a permit to sign `message`, if the number of concurrent signings is capped.
+    async fn signing_permit<Payload: AbstractExecPayload<E>>(
+        &self,
+        message: &SignableMessage<'_, E, Payload>,
+    ) -> Option<SigningPermit<'_>> {
+        let signing_throttle = self.signing_throttle.as_ref()?;
+        let deadline = self.signing_deadline(message);
+        let time_to_deadline = deadline.map(|deadline| {
+            self.slot_clock
+                .now_duration()
+                .map_or(Duration::ZERO, |now| deadline.saturating_sub(now))
+        });
+        Some(signing_throttle.acquire(deadline, time_to_deadline).await)
+    }
+
+    /// Signs `message` with `signing_method`, subject to the cap on concurrent signings.
+    async fn get_signature<Payload: AbstractExecPayload<E>>(
+        &self,
+        signing_method: &SigningMethod,
+        message: SignableMessage<'_, E, Payload>,
+        signing_context: SigningContext,
+    ) -> Result<Signature, SigningError> {
+        let _permit = self.signing_permit(&message).await;
+        let duty = message.duty();
+        let start = Instant::now();
+        let result = signing_method
+            .get_signature(message, signing_context, &self.spec, &self.task_executor)
+            .await;
+        self.observe_signing_time(duty, start.elapsed());
+        result
+    }
+
+    /// Records the time taken to sign a message for `duty`, warning if it exceeds the
+    /// `slow_sign_threshold`.
+    fn observe_signing_time(&self, duty: &'static str, duration: Duration) {
+        metrics::observe_timer_vec(&metrics::DUTY_SIGNING_TIMES, &[duty], duration);
+        if duration > self.slow_sign_threshold {
+            metrics::inc_counter_vec(&metrics::SLOW_SIGNINGS_TOTAL, &[duty]);
+            warn!(
+                self.log,
+                "Slow signing";
+                "msg" => "the signer may be overloaded or unreachable",
+                "type" => duty,
+                "duration_ms" => duration.as_millis(),
+                "threshold_ms" => self.slow_sign_threshold.as_millis(),
+            );
         }
     }
 
@@ -383,14 +718,13 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         signing_epoch: Epoch,
     ) -> Result<Signature, Error> {
         let signing_method = self.doppelganger_checked_signing_method(validator_pubkey)?;
-        let signing_context = self.signing_context(Domain::Randao, signing_epoch);
+        let signing_context = self.signing_context(Domain::Randao, signing_epoch)?;
 
-        let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+        let signature = self
+            .get_signature::<BlindedPayload<E>>(
+                &signing_method,
                 SignableMessage::RandaoReveal(signing_epoch),
                 signing_context,
-                &self.spec,
-                &self.task_executor,
             )
             .await?;
 
@@ -406,14 +740,40 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
     /// 1. validator_definitions.yml
     /// 2. process level fee recipient
     pub fn get_fee_recipient(&self, validator_pubkey: &PublicKeyBytes) -> Option<Address> {
-        // If there is a `suggested_fee_recipient` in the validator definitions yaml
-        // file, use that value.
-        self.get_fee_recipient_defaulting(self.suggested_fee_recipient(validator_pubkey))
+        self.resolve_fee_recipient(validator_pubkey).value
     }
 
     pub fn get_fee_recipient_defaulting(&self, fee_recipient: Option<Address>) -> Option<Address> {
-        // If there's nothing in the file, try the process-level default value.
-        fee_recipient.or(self.fee_recipient_process)
+        self.resolve_fee_recipient_defaulting(fee_recipient).value
+    }
+
+    fn resolve_fee_recipient_defaulting(
+        &self,
+        fee_recipient: Option<Address>,
+    ) -> ResolvedSetting<Option<Address>> {
+        if let Some(fee_recipient) = fee_recipient {
+            // If there is a `suggested_fee_recipient` in the validator definitions yaml
+            // file, use that value.
+            ResolvedSetting::new(Some(fee_recipient), SettingSource::Keymanager)
+        } else if let Some(fee_recipient) = self.fee_recipient_process {
+            // If there's nothing in the file, try the process-level default value.
+            ResolvedSetting::new(Some(fee_recipient), SettingSource::Global)
+        } else {
+            ResolvedSetting::new(None, SettingSource::Default)
+        }
+    }
+
+    /// Returns the action to take when a validator has no fee recipient.
+    pub fn on_missing_fee_recipient(&self) -> OnMissingFeeRecipient {
+        self.on_missing_fee_recipient
+    }
+
+    /// Returns the public keys of all enabled validators without a fee recipient.
+    pub fn validators_without_fee_recipient(&self) -> Vec<PublicKeyBytes> {
+        self.voting_pubkeys::<Vec<_>, _>(DoppelgangerStatus::ignored)
+            .into_iter()
+            .filter(|pubkey| self.get_fee_recipient(pubkey).is_none())
+            .collect()
     }
 
     /// Returns the suggested_fee_recipient from `validator_definitions.yml` if any.
@@ -431,36 +791,146 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
     /// 2. process level gas limit
     /// 3. `DEFAULT_GAS_LIMIT`
     pub fn get_gas_limit(&self, validator_pubkey: &PublicKeyBytes) -> u64 {
-        self.get_gas_limit_defaulting(self.validators.read().gas_limit(validator_pubkey))
+        self.resolve_gas_limit(validator_pubkey).value
     }
 
     fn get_gas_limit_defaulting(&self, gas_limit: Option<u64>) -> u64 {
-        // If there is a `gas_limit` in the validator definitions yaml
-        // file, use that value.
-        gas_limit
+        self.resolve_gas_limit_defaulting(gas_limit).value
+    }
+
+    fn resolve_gas_limit_defaulting(&self, gas_limit: Option<u64>) -> ResolvedSetting<u64> {
+        if let Some(gas_limit) = gas_limit {
+            // If there is a `gas_limit` in the validator definitions yaml
+            // file, use that value.
+            ResolvedSetting::new(gas_limit, SettingSource::Keymanager)
+        } else if let Some(gas_limit) = self.gas_limit {
             // If there's nothing in the file, try the process-level default value.
-            .or(self.gas_limit)
+            ResolvedSetting::new(gas_limit, SettingSource::Global)
+        } else {
             // If there's no process-level default, use the `DEFAULT_GAS_LIMIT`.
-            .unwrap_or(DEFAULT_GAS_LIMIT)
+            ResolvedSetting::new(DEFAULT_GAS_LIMIT, SettingSource::Default)
+        }
     }
 
     /// Returns a `bool` for the given public key that denotes whther this validator should use the
     /// builder API. The priority order for fetching this value is:
     ///
     /// 1. validator_definitions.yml
-    /// 2. process level flag
+    /// 2. builder proposals enabled file
+    /// 3. process level flag
     pub fn get_builder_proposals(&self, validator_pubkey: &PublicKeyBytes) -> bool {
-        // If there is a `suggested_fee_recipient` in the validator definitions yaml
-        // file, use that value.
-        self.get_builder_proposals_defaulting(
-            self.validators.read().builder_proposals(validator_pubkey),
-        )
+        self.resolve_builder_proposals(validator_pubkey).value
     }
 
-    fn get_builder_proposals_defaulting(&self, builder_proposals: Option<bool>) -> bool {
-        builder_proposals
-            // If there's nothing in the file, try the process-level default value.
-            .unwrap_or(self.builder_proposals)
+    fn get_builder_proposals_defaulting(
+        &self,
+        validator_pubkey: &PublicKeyBytes,
+        builder_proposals: Option<bool>,
+    ) -> bool {
+        self.resolve_builder_proposals_defaulting(validator_pubkey, builder_proposals)
+            .value
+    }
+
+    fn resolve_builder_proposals_defaulting(
+        &self,
+        validator_pubkey: &PublicKeyBytes,
+        builder_proposals: Option<bool>,
+    ) -> ResolvedSetting<bool> {
+        if let Some(builder_proposals) = builder_proposals {
+            // If there is a `builder_proposals` in the validator definitions yaml
+            // file, use that value.
+            ResolvedSetting::new(builder_proposals, SettingSource::Keymanager)
+        } else if let Some(builder_proposals) =
+            self.builder_proposals_enabled_file(validator_pubkey)
+        {
+            // If there's nothing in the definitions file, check the enabled file.
+            ResolvedSetting::new(builder_proposals, SettingSource::File)
+        } else {
+            // If there's nothing in either file, try the process-level default value.
+            ResolvedSetting::new(self.builder_proposals, SettingSource::Global)
+        }
+    }
+
+    /// Returns `Some(true)` if the given public key is listed in the builder proposals enabled
+    /// file.
+    fn builder_proposals_enabled_file(&self, validator_pubkey: &PublicKeyBytes) -> Option<bool> {
+        self.builder_proposals_enabled_pubkeys
+            .as_ref()
+            .filter(|enabled_pubkeys| enabled_pubkeys.contains(validator_pubkey))
+            .map(|_| true)
+    }
+
+    /// Returns the fee recipient for the given public key along with where it was resolved from,
+    /// following the same priority order as `get_fee_recipient`.
+    pub fn resolve_fee_recipient(
+        &self,
+        validator_pubkey: &PublicKeyBytes,
+    ) -> ResolvedSetting<Option<Address>> {
+        self.resolve_fee_recipient_defaulting(self.suggested_fee_recipient(validator_pubkey))
+    }
+
+    /// Returns the gas limit for the given public key along with where it was resolved from,
+    /// following the same priority order as `get_gas_limit`.
+    pub fn resolve_gas_limit(&self, validator_pubkey: &PublicKeyBytes) -> ResolvedSetting<u64> {
+        let gas_limit = self.validators.read().gas_limit(validator_pubkey);
+        self.resolve_gas_limit_defaulting(gas_limit)
+    }
+
+    /// Returns whether the given public key should use the builder API along with where the
+    /// value was resolved from, following the same priority order as `get_builder_proposals`.
+    pub fn resolve_builder_proposals(
+        &self,
+        validator_pubkey: &PublicKeyBytes,
+    ) -> ResolvedSetting<bool> {
+        let builder_proposals = self.validators.read().builder_proposals(validator_pubkey);
+        self.resolve_builder_proposals_defaulting(validator_pubkey, builder_proposals)
+    }
+
+    /// Runs `operation` against the slashing protection database, retrying with exponential
+    /// backoff while it fails because the database is in use.
+    ///
+    /// Each attempt runs on a blocking thread, since it may wait on the database lock. The error
+    /// of the last attempt is returned if the database is still in use after all retries, so that
+    /// nothing is signed.
+    async fn with_slashing_protection_retries<F>(
+        &self,
+        operation: F,
+    ) -> Result<Result<Safe, NotSafe>, Error>
+    where
+        F: Fn(&SlashingDatabase) -> Result<Safe, NotSafe> + Clone + Send + 'static,
+    {
+        let mut delay = self.slashing_protection_retry_delay;
+        let mut retries = 0;
+        loop {
+            let slashing_protection = self.slashing_protection.clone();
+            let operation = operation.clone();
+            let result = self
+                .task_executor
+                .spawn_blocking_handle(
+                    move || operation(&slashing_protection),
+                    "slashing_protection_check",
+                )
+                .ok_or(SigningError::ShuttingDown)?
+                .await
+                .map_err(|e| SigningError::TokioJoin(e.to_string()))?;
+
+            match result {
+                Err(e) if e.is_lock_error() && retries < self.slashing_protection_retries => {
+                    retries += 1;
+                    metrics::inc_counter(&metrics::SLASHING_PROTECTION_RETRIES_TOTAL);
+                    warn!(
+                        self.log,
+                        "Retrying slashing protection check";
+                        "error" => ?e,
+                        "retry" => retries,
+                        "delay_ms" => delay.as_millis(),
+                    );
+                    sleep(delay).await;
+                    delay = delay.saturating_mul(2);
+                }
+                result => return Ok(result),
+            }
+        }
     }
 
     pub async fn sign_block<Payload: AbstractExecPayload<E>>(
@@ -483,16 +953,23 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
             });
         }
 
+        self.check_startup_warmup(block.slot())?;
+
         let signing_epoch = block.epoch();
-        let signing_context = self.signing_context(Domain::BeaconProposer, signing_epoch);
+        let signing_context = self.signing_context(Domain::BeaconProposer, signing_epoch)?;
         let domain_hash = signing_context.domain_hash(&self.spec);
 
         // Check for slashing conditions.
-        let slashing_status = self.slashing_protection.check_and_insert_block_proposal(
-            &validator_pubkey,
-            &block.block_header(),
-            domain_hash,
-        );
+        let block_header = block.block_header();
+        let slashing_status = self
+            .with_slashing_protection_retries(move |slashing_protection| {
+                slashing_protection.check_and_insert_block_proposal(
+                    &validator_pubkey,
+                    &block_header,
+                    domain_hash,
+                )
+            })
+            .await?;
 
         match slashing_status {
             // We can safely sign this block without slashing.
@@ -500,12 +977,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
                 metrics::inc_counter_vec(&metrics::SIGNED_BLOCKS_TOTAL, &[metrics::SUCCESS]);
 
                 let signing_method = self.doppelganger_checked_signing_method(validator_pubkey)?;
-                let signature = signing_method
-                    .get_signature::<E, Payload>(
+                let signature = self
+                    .get_signature::<Payload>(
+                        &signing_method,
                         SignableMessage::BeaconBlock(&block),
                         signing_context,
-                        &self.spec,
-                        &self.task_executor,
                     )
                     .await?;
                 Ok(SignedBeaconBlock::from_block(block, signature))
@@ -555,26 +1031,32 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
             });
         }
 
+        self.check_startup_warmup(attestation.data.slot)?;
+
         // Checking for slashing conditions.
         let signing_epoch = attestation.data.target.epoch;
-        let signing_context = self.signing_context(Domain::BeaconAttester, signing_epoch);
+        let signing_context = self.signing_context(Domain::BeaconAttester, signing_epoch)?;
         let domain_hash = signing_context.domain_hash(&self.spec);
-        let slashing_status = self.slashing_protection.check_and_insert_attestation(
-            &validator_pubkey,
-            &attestation.data,
-            domain_hash,
-        );
+        let attestation_data = attestation.data.clone();
+        let slashing_status = self
+            .with_slashing_protection_retries(move |slashing_protection| {
+                slashing_protection.check_and_insert_attestation(
+                    &validator_pubkey,
+                    &attestation_data,
+                    domain_hash,
+                )
+            })
+            .await?;
 
         match slashing_status {
             // We can safely sign this attestation.
             Ok(Safe::Valid) => {
                 let signing_method = self.doppelganger_checked_signing_method(validator_pubkey)?;
-                let signature = signing_method
-                    .get_signature::<E, BlindedPayload<E>>(
+                let signature = self
+                    .get_signature::<BlindedPayload<E>>(
+                        &signing_method,
                         SignableMessage::AttestationData(&attestation.data),
                         signing_context,
-                        &self.spec,
-                        &self.task_executor,
                     )
                     .await?;
                 attestation
@@ -631,15 +1113,14 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         voluntary_exit: VoluntaryExit,
     ) -> Result<SignedVoluntaryExit, Error> {
         let signing_epoch = voluntary_exit.epoch;
-        let signing_context = self.signing_context(Domain::VoluntaryExit, signing_epoch);
+        let signing_context = self.signing_context(Domain::VoluntaryExit, signing_epoch)?;
         let signing_method = self.doppelganger_bypassed_signing_method(validator_pubkey)?;
 
-        let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+        let signature = self
+            .get_signature::<BlindedPayload<E>>(
+                &signing_method,
                 SignableMessage::VoluntaryExit(&voluntary_exit),
                 signing_context,
-                &self.spec,
-                &self.task_executor,
             )
             .await?;
 
@@ -660,14 +1141,20 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
 
         let signing_method =
             self.doppelganger_bypassed_signing_method(validator_registration_data.pubkey)?;
-        let signature = signing_method
+        let message = SignableMessage::ValidatorRegistration(&validator_registration_data);
+        let _permit = self.signing_permit::<BlindedPayload<E>>(&message).await;
+        let duty = message.duty();
+        let start = Instant::now();
+        let result = signing_method
             .get_signature_from_root::<E, BlindedPayload<E>>(
-                SignableMessage::ValidatorRegistration(&validator_registration_data),
+                message,
                 signing_root,
                 &self.task_executor,
                 None,
             )
-            .await?;
+            .await;
+        self.observe_signing_time(duty, start.elapsed());
+        let signature = result?;
 
         metrics::inc_counter_vec(
             &metrics::SIGNED_VALIDATOR_REGISTRATIONS_TOTAL,
@@ -691,8 +1178,10 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         aggregate: Attestation<E>,
         selection_proof: SelectionProof,
     ) -> Result<SignedAggregateAndProof<E>, Error> {
+        self.check_startup_warmup(aggregate.data.slot)?;
+
         let signing_epoch = aggregate.data.target.epoch;
-        let signing_context = self.signing_context(Domain::AggregateAndProof, signing_epoch);
+        let signing_context = self.signing_context(Domain::AggregateAndProof, signing_epoch)?;
 
         let message = AggregateAndProof {
             aggregator_index,
@@ -701,12 +1190,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         };
 
         let signing_method = self.doppelganger_checked_signing_method(validator_pubkey)?;
-        let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+        let signature = self
+            .get_signature::<BlindedPayload<E>>(
+                &signing_method,
                 SignableMessage::SignedAggregateAndProof(&message),
                 signing_context,
-                &self.spec,
-                &self.task_executor,
             )
             .await?;
 
@@ -723,7 +1211,7 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         slot: Slot,
     ) -> Result<SelectionProof, Error> {
         let signing_epoch = slot.epoch(E::slots_per_epoch());
-        let signing_context = self.signing_context(Domain::SelectionProof, signing_epoch);
+        let signing_context = self.signing_context(Domain::SelectionProof, signing_epoch)?;
 
         // Bypass the `with_validator_signing_method` function.
         //
@@ -734,12 +1222,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         // be published on the network.
         let signing_method = self.doppelganger_bypassed_signing_method(validator_pubkey)?;
 
-        let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+        let signature = self
+            .get_signature::<BlindedPayload<E>>(
+                &signing_method,
                 SignableMessage::SelectionProof(slot),
                 signing_context,
-                &self.spec,
-                &self.task_executor,
             )
             .await
             .map_err(Error::UnableToSign)?;
@@ -758,7 +1245,7 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
     ) -> Result<SyncSelectionProof, Error> {
         let signing_epoch = slot.epoch(E::slots_per_epoch());
         let signing_context =
-            self.signing_context(Domain::SyncCommitteeSelectionProof, signing_epoch);
+            self.signing_context(Domain::SyncCommitteeSelectionProof, signing_epoch)?;
 
         // Bypass `with_validator_signing_method`: sync committee messages are not slashable.
         let signing_method = self.doppelganger_bypassed_signing_method(*validator_pubkey)?;
@@ -773,12 +1260,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
             subcommittee_index: subnet_id.into(),
         };
 
-        let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+        let signature = self
+            .get_signature::<BlindedPayload<E>>(
+                &signing_method,
                 SignableMessage::SyncSelectionProof(&message),
                 signing_context,
-                &self.spec,
-                &self.task_executor,
             )
             .await
             .map_err(Error::UnableToSign)?;
@@ -793,21 +1279,22 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         validator_index: u64,
         validator_pubkey: &PublicKeyBytes,
     ) -> Result<SyncCommitteeMessage, Error> {
+        self.check_startup_warmup(slot)?;
+
         let signing_epoch = slot.epoch(E::slots_per_epoch());
-        let signing_context = self.signing_context(Domain::SyncCommittee, signing_epoch);
+        let signing_context = self.signing_context(Domain::SyncCommittee, signing_epoch)?;
 
         // Bypass `with_validator_signing_method`: sync committee messages are not slashable.
         let signing_method = self.doppelganger_bypassed_signing_method(*validator_pubkey)?;
 
-        let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+        let signature = self
+            .get_signature::<BlindedPayload<E>>(
+                &signing_method,
                 SignableMessage::SyncCommitteeSignature {
                     beacon_block_root,
                     slot,
                 },
                 signing_context,
-                &self.spec,
-                &self.task_executor,
             )
             .await
             .map_err(Error::UnableToSign)?;
@@ -832,8 +1319,10 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
         contribution: SyncCommitteeContribution<E>,
         selection_proof: SyncSelectionProof,
     ) -> Result<SignedContributionAndProof<E>, Error> {
+        self.check_startup_warmup(contribution.slot)?;
+
         let signing_epoch = contribution.slot.epoch(E::slots_per_epoch());
-        let signing_context = self.signing_context(Domain::ContributionAndProof, signing_epoch);
+        let signing_context = self.signing_context(Domain::ContributionAndProof, signing_epoch)?;
 
         // Bypass `with_validator_signing_method`: sync committee messages are not slashable.
         let signing_method = self.doppelganger_bypassed_signing_method(aggregator_pubkey)?;
@@ -844,12 +1333,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
             selection_proof: selection_proof.into(),
         };
 
-        let signature = signing_method
-            .get_signature::<E, BlindedPayload<E>>(
+        let signature = self
+            .get_signature::<BlindedPayload<E>>(
+                &signing_method,
                 SignableMessage::SignedContributionAndProof(&message),
                 signing_context,
-                &self.spec,
-                &self.task_executor,
             )
             .await
             .map_err(Error::UnableToSign)?;
@@ -865,10 +1353,30 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
     pub fn import_slashing_protection(
         &self,
         interchange: Interchange,
-    ) -> Result<(), InterchangeError> {
+    ) -> Result<Vec<InterchangeImportOutcome>, InterchangeError> {
         self.slashing_protection
-            .import_interchange_info(interchange, self.genesis_validators_root)?;
-        Ok(())
+            .import_interchange_info(interchange, self.genesis_validators_root)
+    }
+
+    /// Returns a summary of the slashing protection data of `pubkey`, read in a single
+    /// transaction. Validators which are disabled in the database are included.
+    pub fn slashing_protection_summary(
+        &self,
+        pubkey: &PublicKeyBytes,
+    ) -> Result<ValidatorSummary, NotSafe> {
+        self.slashing_protection.with_transaction(|txn| {
+            self.slashing_protection
+                .validator_summary_ignoring_status(pubkey, txn)
+        })
+    }
+
+    /// Returns `true` if the slashing protection database records an attestation targeting
+    /// `epoch` or a block in one of its slots, i.e. if this validator client signed either.
+    pub fn signed_in_epoch(&self, pubkey: &PublicKeyBytes, epoch: Epoch) -> Result<bool, NotSafe> {
+        self.slashing_protection.with_transaction(|txn| {
+            self.slashing_protection
+                .signed_in_epoch(pubkey, epoch, E::slots_per_epoch(), txn)
+        })
     }
 
     /// Export slashing protection data while also disabling the given keys in the database.
@@ -910,12 +1418,11 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
     /// cheap to call. The `first_run` flag can be used to print a more verbose message when pruning
     /// runs.
     pub fn prune_slashing_protection_db(&self, current_epoch: Epoch, first_run: bool) {
-        // Attempt to prune every SLASHING_PROTECTION_HISTORY_EPOCHs, with a tolerance for
+        // Attempt to prune every `slashing_protection_prune_interval` epochs, with a tolerance for
         // missing the epoch that aligns exactly.
         let mut last_prune = self.slashing_protection_last_prune.lock();
-        if current_epoch / SLASHING_PROTECTION_HISTORY_EPOCHS
-            <= *last_prune / SLASHING_PROTECTION_HISTORY_EPOCHS
-        {
+        let interval = self.slashing_protection_prune_interval;
+        if current_epoch / interval <= *last_prune / interval {
             return;
         }
 
@@ -932,37 +1439,240 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
 
         let _timer = metrics::start_timer(&metrics::SLASHING_PROTECTION_PRUNE_TIMES);
 
-        let new_min_target_epoch = current_epoch.saturating_sub(SLASHING_PROTECTION_HISTORY_EPOCHS);
+        // Never keep less than `SLASHING_PROTECTION_HISTORY_EPOCHS`, which `Config::from_cli` also
+        // enforces.
+        let retention_epochs = std::cmp::max(
+            self.slashing_protection_retention_epochs,
+            SLASHING_PROTECTION_HISTORY_EPOCHS,
+        );
+        let new_min_target_epoch = current_epoch.saturating_sub(retention_epochs);
         let new_min_slot = new_min_target_epoch.start_slot(E::slots_per_epoch());
 
         let all_pubkeys: Vec<_> = self.voting_pubkeys(DoppelgangerStatus::ignored);
 
-        if let Err(e) = self
+        let pruned_attestations = match self
             .slashing_protection
             .prune_all_signed_attestations(all_pubkeys.iter(), new_min_target_epoch)
         {
-            error!(
-                self.log,
-                "Error during pruning of signed attestations";
-                "error" => ?e,
-            );
-            return;
-        }
+            Ok(pruned) => pruned,
+            Err(e) => {
+                error!(
+                    self.log,
+                    "Error during pruning of signed attestations";
+                    "error" => ?e,
+                );
+                return;
+            }
+        };
+        metrics::inc_counter_vec_by(
+            &metrics::SLASHING_PROTECTION_PRUNED_RECORDS_TOTAL,
+            &[metrics::ATTESTATIONS],
+            pruned_attestations as u64,
+        );
 
-        if let Err(e) = self
+        let pruned_blocks = match self
             .slashing_protection
             .prune_all_signed_blocks(all_pubkeys.iter(), new_min_slot)
         {
-            error!(
-                self.log,
-                "Error during pruning of signed blocks";
-                "error" => ?e,
+            Ok(pruned) => pruned,
+            Err(e) => {
+                error!(
+                    self.log,
+                    "Error during pruning of signed blocks";
+                    "error" => ?e,
+                );
+                return;
+            }
+        };
+        metrics::inc_counter_vec_by(
+            &metrics::SLASHING_PROTECTION_PRUNED_RECORDS_TOTAL,
+            &[metrics::BLOCKS],
+            pruned_blocks as u64,
+        );
+
+        *last_prune = current_epoch;
+
+        info!(
+            self.log,
+            "Completed pruning of slashing protection DB";
+            "pruned_attestations" => pruned_attestations,
+            "pruned_blocks" => pruned_blocks,
+            "min_target_epoch" => new_min_target_epoch,
+        );
+    }
+}
+
+/// Returns the slot of `message` and the time after the start of that slot after which its
+/// signature is no longer useful, or `None` if `message` is not tied to a slot.
+///
+/// A randao reveal is only requested immediately before proposing a block in `current_slot`.
+fn latest_useful_time<E: EthSpec, Payload: AbstractExecPayload<E>>(
+    message: &SignableMessage<'_, E, Payload>,
+    current_slot: Option<Slot>,
+    slot_duration: Duration,
+    attestation_deadline_fraction: f64,
+) -> Option<(Slot, Duration)> {
+    // A block only gathers votes if it is seen before attestations are produced.
+    let attestation_deadline = slot_duration.mul_f64(attestation_deadline_fraction);
+    // Attestations and sync committee messages are aggregated two thirds of the way through the
+    // slot, and selection proofs are needed to aggregate.
+    let aggregation_deadline = slot_duration * 2 / 3;
+    // Aggregates are included in the block of the next slot.
+    let next_slot = slot_duration;
+
+    match message {
+        SignableMessage::RandaoReveal(_) => Some((current_slot?, attestation_deadline)),
+        SignableMessage::BeaconBlock(block) => Some((block.slot(), attestation_deadline)),
+        SignableMessage::AttestationData(data) => Some((data.slot, aggregation_deadline)),
+        SignableMessage::SyncCommitteeSignature { slot, .. } => Some((*slot, aggregation_deadline)),
+        SignableMessage::SelectionProof(slot) => Some((*slot, aggregation_deadline)),
+        SignableMessage::SyncSelectionProof(data) => Some((data.slot, aggregation_deadline)),
+        SignableMessage::SignedAggregateAndProof(message) => {
+            Some((message.aggregate.data.slot, next_slot))
+        }
+        SignableMessage::SignedContributionAndProof(message) => {
+            Some((message.contribution.slot, next_slot))
+        }
+        SignableMessage::ValidatorRegistration(_) | SignableMessage::VoluntaryExit(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use types::{
+        test_utils::{SeedableRng, TestRandom, XorShiftRng},
+        FullPayload, MainnetEthSpec,
+    };
+
+    type E = MainnetEthSpec;
+
+    const SLOT_DURATION: Duration = Duration::from_secs(12);
+
+    #[tokio::test]
+    async fn signings_are_throttled_at_their_signing_time() {
+        let spec = E::default_spec();
+        let mut rng = XorShiftRng::from_seed([42; 16]);
+        let slot = Slot::new(10);
+
+        let mut block = BeaconBlock::<E>::empty(&spec);
+        *block.slot_mut() = slot;
+        let attestation_data = AttestationData {
+            slot,
+            ..AttestationData::default()
+        };
+        let mut aggregate = AggregateAndProof::<E>::random_for_test(&mut rng);
+        aggregate.aggregate.data.slot = slot;
+        let mut contribution = ContributionAndProof::<E>::random_for_test(&mut rng);
+        contribution.contribution.slot = slot;
+        let sync_selection_data = SyncAggregatorSelectionData {
+            slot,
+            subcommittee_index: 0,
+        };
+
+        // Each message, and the time after the start of its slot at which it is signed.
+        let first_third = SLOT_DURATION / 3;
+        let second_third = SLOT_DURATION * 2 / 3;
+        let messages: Vec<(SignableMessage<E, FullPayload<E>>, Duration)> = vec![
+            (
+                SignableMessage::RandaoReveal(slot.epoch(E::slots_per_epoch())),
+                Duration::ZERO,
+            ),
+            (SignableMessage::BeaconBlock(&block), Duration::ZERO),
+            (
+                SignableMessage::AttestationData(&attestation_data),
+                first_third,
+            ),
+            (
+                SignableMessage::SyncCommitteeSignature {
+                    beacon_block_root: Hash256::zero(),
+                    slot,
+                },
+                first_third,
+            ),
+            (SignableMessage::SelectionProof(slot), Duration::ZERO),
+            (
+                SignableMessage::SyncSelectionProof(&sync_selection_data),
+                Duration::ZERO,
+            ),
+            (
+                SignableMessage::SignedAggregateAndProof(&aggregate),
+                second_third,
+            ),
+            (
+                SignableMessage::SignedContributionAndProof(&contribution),
+                second_third,
+            ),
+        ];
+
+        let throttle = SigningThrottle::new(1);
+        let _permit = throttle.acquire(None, None).await;
+        for (message, signing_time) in messages {
+            let (deadline_slot, deadline) =
+                latest_useful_time(&message, Some(slot), SLOT_DURATION, 1.0 / 3.0).unwrap();
+            assert_eq!(deadline_slot, slot);
+            let time_to_deadline = deadline.saturating_sub(signing_time);
+            assert!(
+                time_to_deadline > Duration::ZERO,
+                "{} is overdue when signed",
+                message.duty()
             );
-            return;
+
+            // The signing waits for the permit rather than being overdue and ignoring the cap.
+            let start_of_slot = SLOT_DURATION * slot.as_u64() as u32;
+            assert!(throttle
+                .acquire(Some(start_of_slot + deadline), Some(time_to_deadline))
+                .now_or_never()
+                .is_none());
         }
+    }
 
-        *last_prune = current_epoch;
+    #[tokio::test]
+    async fn throttled_attestations_are_released_before_aggregation() {
+        let slot_duration = Duration::from_millis(1_200);
+        let slot = Slot::new(10);
+        let attestation_data = AttestationData {
+            slot,
+            ..AttestationData::default()
+        };
+        let message = SignableMessage::<E>::AttestationData(&attestation_data);
+        let (_, deadline) =
+            latest_useful_time(&message, Some(slot), slot_duration, 1.0 / 3.0).unwrap();
+        let aggregation_time = slot_duration * 2 / 3;
+        assert!(deadline <= aggregation_time);
+
+        // The attestation is signed a third of the way through the slot, whilst the only permit
+        // is held for the rest of the slot.
+        let throttle = SigningThrottle::new(1);
+        let _permit = throttle.acquire(None, None).await;
+        let signing_time = slot_duration / 3;
+        let start = Instant::now();
+        let start_of_slot = slot_duration * slot.as_u64() as u32;
+        let _overdue_permit = throttle
+            .acquire(
+                Some(start_of_slot + deadline),
+                Some(deadline - signing_time),
+            )
+            .await;
+
+        // The attestation bypasses the cap in time to be aggregated, allowing for timer
+        // granularity.
+        assert!(signing_time + start.elapsed() < aggregation_time + slot_duration / 12);
+    }
 
-        info!(self.log, "Completed pruning of slashing protection DB");
+    #[test]
+    fn messages_without_slot_have_no_deadline() {
+        let registration = ValidatorRegistrationData {
+            fee_recipient: Address::zero(),
+            gas_limit: 0,
+            timestamp: 0,
+            pubkey: PublicKeyBytes::empty(),
+        };
+        let message = SignableMessage::<E>::ValidatorRegistration(&registration);
+        assert_eq!(
+            latest_useful_time(&message, Some(Slot::new(1)), SLOT_DURATION, 1.0 / 3.0),
+            None
+        );
     }
 }